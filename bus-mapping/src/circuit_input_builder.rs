@@ -6,6 +6,7 @@ mod block;
 mod call;
 mod execution;
 mod input_state_ref;
+mod receipt;
 #[cfg(test)]
 mod tracer_tests;
 mod transaction;
@@ -20,10 +21,12 @@ pub use access::{Access, AccessSet, AccessValue, CodeSource};
 pub use block::{Block, BlockContext};
 pub use call::{Call, CallContext, CallKind};
 use core::fmt::Debug;
-use eth_types::{self, Address, GethExecStep, GethExecTrace, Word};
+use eth_types::{self, trie, Address, GethExecStep, GethExecTrace, ToWord, Word};
 use ethers_providers::JsonRpcClient;
 pub use execution::{CopyDataType, CopyEvent, CopyStep, ExecState, ExecStep, NumberOrHash};
 pub use input_state_ref::CircuitInputStateRef;
+pub use receipt::{Log, Receipt};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 pub use transaction::{Transaction, TransactionContext};
 
@@ -143,6 +146,7 @@ impl<'a> CircuitInputBuilder {
             self.handle_tx(tx, geth_trace, tx_index + 1 == eth_block.transactions.len())?;
         }
         self.set_value_ops_call_context_rwc_eor();
+        self.block.receipts = self.block.gen_receipts();
         Ok(())
     }
 
@@ -216,6 +220,99 @@ pub fn get_call_memory_offset_length(step: &GethExecStep, nth: usize) -> Result<
 
 type EthBlock = eth_types::Block<eth_types::Transaction>;
 
+/// Data queried from geth by the [`BuilderClient`] to generate the circuit
+/// inputs of a block, which is enough to generate them again without a geth
+/// instance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuilderInputs {
+    /// Chain ID
+    pub chain_id: Word,
+    /// History hashes
+    pub history_hashes: Vec<Word>,
+    /// Block, along with its transactions
+    pub eth_block: EthBlock,
+    /// Execution traces of the transactions of the block
+    pub geth_traces: Vec<eth_types::GethExecTrace>,
+    /// Proofs of the accounts and storage keys accessed in the block, at the
+    /// previous block
+    pub proofs: Vec<eth_types::EIP1186ProofResponse>,
+    /// Proofs of the accounts and storage keys accessed in the block, at the
+    /// block
+    #[serde(default)]
+    pub post_proofs: Vec<eth_types::EIP1186ProofResponse>,
+    /// Codes of the accounts accessed in the block, at the previous block
+    pub codes: HashMap<Address, Vec<u8>>,
+}
+
+impl BuilderInputs {
+    /// Perform the steps 4 and 5 of the [`BuilderClient`] to generate the
+    /// circuit inputs
+    pub fn gen_inputs(&self) -> Result<CircuitInputBuilder, Error> {
+        let (sdb, code_db) = build_state_code_db(self.proofs.clone(), self.codes.clone());
+        gen_inputs_from_state(
+            self.chain_id,
+            self.history_hashes.clone(),
+            sdb,
+            code_db,
+            &self.eth_block,
+            &self.geth_traces,
+            &self.proofs,
+            &self.post_proofs,
+        )
+    }
+}
+
+fn build_state_code_db(
+    proofs: Vec<eth_types::EIP1186ProofResponse>,
+    codes: HashMap<Address, Vec<u8>>,
+) -> (StateDB, CodeDB) {
+    let mut sdb = StateDB::new();
+    for proof in proofs {
+        let mut storage = HashMap::new();
+        for storage_proof in proof.storage_proof {
+            storage.insert(storage_proof.key, storage_proof.value);
+        }
+        sdb.set_account(
+            &proof.address,
+            state_db::Account {
+                nonce: proof.nonce,
+                balance: proof.balance,
+                storage,
+                code_hash: proof.code_hash,
+            },
+        )
+    }
+
+    let mut code_db = CodeDB::new();
+    for (_address, code) in codes {
+        code_db.insert(code.clone());
+    }
+    (sdb, code_db)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn gen_inputs_from_state(
+    chain_id: Word,
+    history_hashes: Vec<Word>,
+    sdb: StateDB,
+    code_db: CodeDB,
+    eth_block: &EthBlock,
+    geth_traces: &[eth_types::GethExecTrace],
+    proofs: &[eth_types::EIP1186ProofResponse],
+    post_proofs: &[eth_types::EIP1186ProofResponse],
+) -> Result<CircuitInputBuilder, Error> {
+    let mut block = Block::new(chain_id, history_hashes, eth_block)?;
+    // Every proof starts at the root of the state trie.
+    if let Some(proof) = proofs.first() {
+        block.prev_state_root = trie::proof_root(&proof.account_proof).to_word();
+    }
+    block.state_proofs = [proofs, post_proofs].concat();
+    let mut builder = CircuitInputBuilder::new(sdb, code_db, block);
+    builder.handle_block(eth_block, geth_traces)?;
+    builder.block.check_receipts(eth_block)?;
+    Ok(builder)
+}
+
 /// Struct that wraps a GethClient and contains methods to perform all the steps
 /// necessary to generate the circuit inputs for a block by querying geth for
 /// the necessary information and using the CircuitInputBuilder.
@@ -270,6 +367,23 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         Ok(AccessSet::from(block_access_trace))
     }
 
+    /// Query geth for the proofs of all accounts and storage keys from
+    /// Accesses, at block `block_num`
+    async fn get_proofs(
+        &self,
+        block_num: u64,
+        access_set: &AccessSet,
+    ) -> Result<Vec<eth_types::EIP1186ProofResponse>, Error> {
+        let mut proofs = Vec::new();
+        for (address, key_set) in &access_set.state {
+            let mut keys: Vec<Word> = key_set.iter().cloned().collect();
+            keys.sort();
+            let proof = self.cli.get_proof(*address, keys, block_num.into()).await?;
+            proofs.push(proof);
+        }
+        Ok(proofs)
+    }
+
     /// Step 3. Query geth for all accounts, storage keys, and codes from
     /// Accesses
     pub async fn get_state(
@@ -283,17 +397,7 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         ),
         Error,
     > {
-        let mut proofs = Vec::new();
-        for (address, key_set) in access_set.state {
-            let mut keys: Vec<Word> = key_set.iter().cloned().collect();
-            keys.sort();
-            let proof = self
-                .cli
-                .get_proof(address, keys, (block_num - 1).into())
-                .await
-                .unwrap();
-            proofs.push(proof);
-        }
+        let proofs = self.get_proofs(block_num - 1, &access_set).await?;
         let mut codes: HashMap<Address, Vec<u8>> = HashMap::new();
         for address in access_set.code {
             let code = self
@@ -306,58 +410,83 @@ impl<P: JsonRpcClient> BuilderClient<P> {
         Ok((proofs, codes))
     }
 
+    /// Step 3b. Query geth for the proofs of all accounts and storage keys from
+    /// Accesses after the block, which hold the nodes that replace the ones
+    /// removed by the deletions of the block
+    pub async fn get_post_state_proofs(
+        &self,
+        block_num: u64,
+        access_set: &AccessSet,
+    ) -> Result<Vec<eth_types::EIP1186ProofResponse>, Error> {
+        self.get_proofs(block_num, access_set).await
+    }
+
     /// Step 4. Build a partial StateDB from step 3
     pub fn build_state_code_db(
         &self,
         proofs: Vec<eth_types::EIP1186ProofResponse>,
         codes: HashMap<Address, Vec<u8>>,
     ) -> (StateDB, CodeDB) {
-        let mut sdb = StateDB::new();
-        for proof in proofs {
-            let mut storage = HashMap::new();
-            for storage_proof in proof.storage_proof {
-                storage.insert(storage_proof.key, storage_proof.value);
-            }
-            sdb.set_account(
-                &proof.address,
-                state_db::Account {
-                    nonce: proof.nonce,
-                    balance: proof.balance,
-                    storage,
-                    code_hash: proof.code_hash,
-                },
-            )
-        }
-
-        let mut code_db = CodeDB::new();
-        for (_address, code) in codes {
-            code_db.insert(code.clone());
-        }
-        (sdb, code_db)
+        build_state_code_db(proofs, codes)
     }
 
     /// Step 5. For each step in TxExecTraces, gen the associated ops and state
-    /// circuit inputs
+    /// circuit inputs.  The proofs of steps 3 and 3b are kept in the block for
+    /// the MPT updates.
     pub fn gen_inputs_from_state(
         &self,
         sdb: StateDB,
         code_db: CodeDB,
         eth_block: &EthBlock,
         geth_traces: &[eth_types::GethExecTrace],
+        proofs: &[eth_types::EIP1186ProofResponse],
+        post_proofs: &[eth_types::EIP1186ProofResponse],
     ) -> Result<CircuitInputBuilder, Error> {
-        let block = Block::new(self.chain_id, self.history_hashes.clone(), eth_block)?;
-        let mut builder = CircuitInputBuilder::new(sdb, code_db, block);
-        builder.handle_block(eth_block, geth_traces)?;
-        Ok(builder)
+        gen_inputs_from_state(
+            self.chain_id,
+            self.history_hashes.clone(),
+            sdb,
+            code_db,
+            eth_block,
+            geth_traces,
+            proofs,
+            post_proofs,
+        )
+    }
+
+    /// Perform the steps 1 to 3b to query geth for the data needed to generate
+    /// the circuit inputs, which can be generated from it later on
+    pub async fn get_inputs(&self, block_num: u64) -> Result<BuilderInputs, Error> {
+        let (eth_block, geth_traces) = self.get_block(block_num).await?;
+        let access_set = self.get_state_accesses(&eth_block, &geth_traces)?;
+        let post_proofs = self.get_post_state_proofs(block_num, &access_set).await?;
+        let (proofs, codes) = self.get_state(block_num, access_set).await?;
+        Ok(BuilderInputs {
+            chain_id: self.chain_id,
+            history_hashes: self.history_hashes.clone(),
+            eth_block,
+            geth_traces,
+            proofs,
+            post_proofs,
+            codes,
+        })
     }
 
     /// Perform all the steps to generate the circuit inputs
     pub async fn gen_inputs(&self, block_num: u64) -> Result<CircuitInputBuilder, Error> {
         let (eth_block, geth_traces) = self.get_block(block_num).await?;
         let access_set = self.get_state_accesses(&eth_block, &geth_traces)?;
+        let post_proofs = self.get_post_state_proofs(block_num, &access_set).await?;
         let (proofs, codes) = self.get_state(block_num, access_set).await?;
-        let (state_db, code_db) = self.build_state_code_db(proofs, codes);
-        let builder = self.gen_inputs_from_state(state_db, code_db, &eth_block, &geth_traces)?;
+        let (state_db, code_db) = self.build_state_code_db(proofs.clone(), codes);
+        let builder = self.gen_inputs_from_state(
+            state_db,
+            code_db,
+            &eth_block,
+            &geth_traces,
+            &proofs,
+            &post_proofs,
+        )?;
         Ok(builder)
     }
 }
@@ -1,11 +1,18 @@
 //! Block-related utility module
 
-use super::{transaction::Transaction, CopyEvent};
+use super::{
+    receipt::{self, Log, Receipt},
+    transaction::Transaction,
+    CopyEvent,
+};
 use crate::{
-    operation::{OperationContainer, RWCounter},
+    operation::{OperationContainer, RWCounter, TxLogField, TxReceiptField, RW},
     Error,
 };
-use eth_types::{Address, Hash, Word};
+use eth_types::{
+    trie::EMPTY_TRIE_ROOT, Address, EIP1186ProofResponse, Hash, ToAddress, ToWord, Word, H256,
+};
+use ethers_core::types::Bloom;
 use std::collections::HashMap;
 
 /// Context of a [`Block`] which can mutate in a [`Transaction`].
@@ -65,6 +72,14 @@ pub struct Block {
     pub txs: Vec<Transaction>,
     /// Copy events in this block.
     pub copy_events: Vec<CopyEvent>,
+    /// Receipts of the transactions, generated from the operations
+    pub receipts: Vec<Receipt>,
+    /// State root before the block
+    pub prev_state_root: Word,
+    /// Results of `eth_getProof` for the accounts and storage slots accessed
+    /// in the block, before and after it, whose nodes are the ones in the
+    /// paths of the updates of the block
+    pub state_proofs: Vec<EIP1186ProofResponse>,
     code: HashMap<Hash, Vec<u8>>,
 }
 
@@ -98,6 +113,9 @@ impl Block {
             container: OperationContainer::new(),
             txs: Vec::new(),
             copy_events: Vec::new(),
+            receipts: Vec::new(),
+            prev_state_root: EMPTY_TRIE_ROOT.to_word(),
+            state_proofs: Vec::new(),
             code: HashMap::new(),
         })
     }
@@ -118,4 +136,77 @@ impl Block {
     pub fn add_copy_event(&mut self, copy: CopyEvent) {
         self.copy_events.push(copy);
     }
+
+    /// Generate the receipts of the transactions from the TxReceipt and TxLog
+    /// operations written by them.
+    pub(crate) fn gen_receipts(&self) -> Vec<Receipt> {
+        let mut receipts: Vec<Receipt> = self
+            .txs
+            .iter()
+            .map(|tx| Receipt {
+                tx_type: tx.tx_type,
+                ..Receipt::default()
+            })
+            .collect();
+        for op in self.container.tx_receipt.iter() {
+            if op.rw() != RW::WRITE {
+                continue;
+            }
+            let op = op.op();
+            let receipt = &mut receipts[op.tx_id - 1];
+            match op.field {
+                TxReceiptField::PostStateOrStatus => receipt.status = op.value != 0,
+                TxReceiptField::CumulativeGasUsed => receipt.cumulative_gas_used = op.value,
+                TxReceiptField::LogLength => receipt.logs = vec![Log::default(); op.value as usize],
+            }
+        }
+        for op in self.container.tx_log.iter() {
+            let op = op.op();
+            let log = &mut receipts[op.tx_id - 1].logs[op.log_id - 1];
+            match op.field {
+                TxLogField::Address => log.address = op.value.to_address(),
+                TxLogField::Topic => {
+                    log.topics
+                        .resize(log.topics.len().max(op.index + 1), Word::zero());
+                    log.topics[op.index] = op.value;
+                }
+                TxLogField::Data => {
+                    log.data.resize(log.data.len().max(op.index + 1), 0);
+                    log.data[op.index] = op.value.as_u64() as u8;
+                }
+            }
+        }
+        receipts
+    }
+
+    /// Return the bloom filter of the logs of the block
+    pub fn logs_bloom(&self) -> Bloom {
+        receipt::logs_bloom(&self.receipts)
+    }
+
+    /// Return the root of the receipts trie of the block
+    pub fn receipts_root(&self) -> H256 {
+        receipt::receipts_root(&self.receipts)
+    }
+
+    /// Check that the receipts root and the logs bloom match the ones in the
+    /// block header.
+    pub fn check_receipts<TX>(&self, eth_block: &eth_types::Block<TX>) -> Result<(), Error> {
+        let receipts_root = self.receipts_root();
+        if receipts_root != eth_block.receipts_root {
+            log::error!(
+                "receipts root {:?} doesn't match the block header {:?}",
+                receipts_root,
+                eth_block.receipts_root
+            );
+            return Err(Error::InvalidBlockReceipts("receipts root mismatch"));
+        }
+        if let Some(block_logs_bloom) = eth_block.logs_bloom {
+            if self.logs_bloom() != block_logs_bloom {
+                log::error!("logs bloom doesn't match the block header");
+                return Err(Error::InvalidBlockReceipts("logs bloom mismatch"));
+            }
+        }
+        Ok(())
+    }
 }
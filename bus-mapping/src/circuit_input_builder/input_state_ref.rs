@@ -17,7 +17,7 @@ use crate::{
 };
 use eth_types::{
     evm_types::{Gas, MemoryAddress, OpcodeId, StackAddress},
-    Address, GethExecStep, ToAddress, ToBigEndian, Word, H256,
+    Address, GethExecStep, ToAddress, ToBigEndian, ToWord, Word, H256,
 };
 use ethers_core::utils::{get_contract_address, get_create2_address};
 
@@ -818,6 +818,75 @@ impl<'a> CircuitInputStateRef<'a> {
         Ok(())
     }
 
+    /// Generate the call context operations that restore the caller's context
+    /// when the current call halts, either successfully or because of an
+    /// exception. This corresponds to
+    /// `Instruction.step_state_transition_to_restored_context` in the python
+    /// spec.
+    pub fn gen_restore_context_ops(
+        &mut self,
+        exec_step: &mut ExecStep,
+        geth_steps: &[GethExecStep],
+    ) -> Result<(), Error> {
+        let geth_step = &geth_steps[0];
+        let call = self.call()?.clone();
+        if call.is_root {
+            return Ok(());
+        }
+
+        let caller = self.caller()?.clone();
+        self.call_context_read(
+            exec_step,
+            call.call_id,
+            CallContextField::CallerId,
+            caller.call_id.into(),
+        );
+
+        let geth_step_next = &geth_steps[1];
+        let caller_ctx = self.caller_ctx()?;
+        // On exceptional halt all the gas given to the callee is consumed, so
+        // the caller continues with what it had left after the call.
+        let caller_gas_left = if exec_step.error.is_some() {
+            geth_step_next.gas.0
+        } else {
+            geth_step_next.gas.0 - geth_step.gas.0
+        };
+        for (field, value) in [
+            (CallContextField::IsRoot, (caller.is_root as u64).into()),
+            (
+                CallContextField::IsCreate,
+                (caller.is_create() as u64).into(),
+            ),
+            (CallContextField::CodeHash, caller.code_hash.to_word()),
+            (CallContextField::ProgramCounter, geth_step_next.pc.0.into()),
+            (
+                CallContextField::StackPointer,
+                geth_step_next.stack.stack_pointer().0.into(),
+            ),
+            (CallContextField::GasLeft, caller_gas_left.into()),
+            (
+                CallContextField::MemorySize,
+                caller_ctx.memory.word_size().into(),
+            ),
+            (
+                CallContextField::ReversibleWriteCounter,
+                caller_ctx.reversible_write_counter.into(),
+            ),
+        ] {
+            self.call_context_read(exec_step, caller.call_id, field, value);
+        }
+
+        for (field, value) in [
+            (CallContextField::LastCalleeId, call.call_id.into()),
+            (CallContextField::LastCalleeReturnDataOffset, 0.into()),
+            (CallContextField::LastCalleeReturnDataLength, 0.into()),
+        ] {
+            self.call_context_write(exec_step, caller.call_id, field, value);
+        }
+
+        Ok(())
+    }
+
     /// Push a copy event to the state.
     pub fn push_copy(&mut self, copy: CopyEvent) {
         self.block.add_copy_event(copy);
@@ -829,7 +898,7 @@ impl<'a> CircuitInputStateRef<'a> {
         next_step: Option<&GethExecStep>,
     ) -> Result<Option<ExecError>, Error> {
         if let Some(error) = &step.error {
-            return Ok(Some(get_step_reported_error(&step.op, error)));
+            return Ok(Some(get_step_reported_error(&step.op, error)?));
         }
 
         if matches!(step.op, OpcodeId::INVALID(_)) {
@@ -861,7 +930,7 @@ impl<'a> CircuitInputStateRef<'a> {
                 return Ok(match step.op {
                     OpcodeId::JUMP | OpcodeId::JUMPI => Some(ExecError::InvalidJump),
                     OpcodeId::RETURNDATACOPY => Some(ExecError::ReturnDataOutOfBounds),
-                    // Break write protection (CALL with value will be handled below)
+                    // Break write protection
                     OpcodeId::SSTORE
                     | OpcodeId::CREATE
                     | OpcodeId::CREATE2
@@ -875,6 +944,9 @@ impl<'a> CircuitInputStateRef<'a> {
                     {
                         Some(ExecError::WriteProtection)
                     }
+                    OpcodeId::CALL if call.is_static && !step.stack.nth_last(2)?.is_zero() => {
+                        Some(ExecError::WriteProtection)
+                    }
                     OpcodeId::REVERT => None,
                     _ => {
                         return Err(Error::UnexpectedExecStepError(
@@ -919,10 +991,13 @@ impl<'a> CircuitInputStateRef<'a> {
         // is unexpected.
         if step.depth == next_depth + 1
             && next_result != Word::zero()
-            && !matches!(step.op, OpcodeId::RETURN | OpcodeId::STOP)
+            && !matches!(
+                step.op,
+                OpcodeId::RETURN | OpcodeId::STOP | OpcodeId::SELFDESTRUCT
+            )
         {
             return Err(Error::UnexpectedExecStepError(
-                "success result without {RETURN, STOP}",
+                "success result without {RETURN, STOP, SELFDESTRUCT}",
                 step.clone(),
             ));
         }
@@ -951,11 +1026,6 @@ impl<'a> CircuitInputStateRef<'a> {
                 _ => Word::zero(),
             };
 
-            // CALL with value
-            if matches!(step.op, OpcodeId::CALL) && !value.is_zero() && self.call()?.is_static {
-                return Ok(Some(ExecError::WriteProtection));
-            }
-
             let sender = self.call()?.address;
             let (found, account) = self.sdb.get_account(&sender);
             if !found {
@@ -965,6 +1035,17 @@ impl<'a> CircuitInputStateRef<'a> {
                 return Ok(Some(ExecError::InsufficientBalance));
             }
 
+            // The execution of a precompiled contract isn't traced, so a call
+            // to it which fails, e.g. because of an invalid input, has no
+            // steps either. The call failing isn't an error of the caller.
+            if matches!(
+                step.op,
+                OpcodeId::CALL | OpcodeId::CALLCODE | OpcodeId::DELEGATECALL | OpcodeId::STATICCALL
+            ) && self.is_precompiled(&step.stack.nth_last(1)?.to_address())
+            {
+                return Ok(None);
+            }
+
             // Address collision
             if matches!(step.op, OpcodeId::CREATE | OpcodeId::CREATE2) {
                 let address = match step.op {
@@ -0,0 +1,263 @@
+//! Transaction receipts, logs bloom and receipts root of a block.
+
+use eth_types::{Address, Word, H256};
+use ethers_core::{types::Bloom, utils::keccak256};
+use rlp::RlpStream;
+
+/// Log emitted by a transaction
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Log {
+    /// Address of the contract that emitted the log
+    pub address: Address,
+    /// Topics of the log
+    pub topics: Vec<Word>,
+    /// Data of the log
+    pub data: Vec<u8>,
+}
+
+impl Log {
+    fn rlp_append(&self, stream: &mut RlpStream) {
+        stream.begin_list(3);
+        stream.append(&self.address);
+        stream.begin_list(self.topics.len());
+        for topic in &self.topics {
+            stream.append(&H256::from_uint(topic));
+        }
+        stream.append(&self.data);
+    }
+}
+
+/// Receipt of a transaction, as in the Yellow Paper (after Byzantium)
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Receipt {
+    /// Type of the transaction (EIP-2718)
+    pub tx_type: u64,
+    /// Whether the transaction succeeded
+    pub status: bool,
+    /// Gas used in the block up to and including the transaction
+    pub cumulative_gas_used: u64,
+    /// Logs emitted by the transaction
+    pub logs: Vec<Log>,
+}
+
+impl Receipt {
+    /// Return the bloom filter of the logs of the receipt
+    pub fn logs_bloom(&self) -> Bloom {
+        let mut bloom = Bloom::zero();
+        for log in &self.logs {
+            bloom_accrue(&mut bloom, log.address.as_bytes());
+            for topic in &log.topics {
+                bloom_accrue(&mut bloom, H256::from_uint(topic).as_bytes());
+            }
+        }
+        bloom
+    }
+
+    /// Return the encoding of the receipt (EIP-2718), which is the value of
+    /// the receipt in the receipts trie: the RLP of the receipt prefixed by
+    /// the transaction type byte for typed transactions.
+    pub fn rlp(&self) -> Vec<u8> {
+        let mut stream = RlpStream::new_list(4);
+        stream.append(&(self.status as u64));
+        stream.append(&self.cumulative_gas_used);
+        stream.append(&self.logs_bloom());
+        stream.begin_list(self.logs.len());
+        for log in &self.logs {
+            log.rlp_append(&mut stream);
+        }
+        let encoding = stream.out().to_vec();
+        match self.tx_type {
+            0 => encoding,
+            tx_type => [vec![tx_type as u8], encoding].concat(),
+        }
+    }
+}
+
+/// Add `input` to the bloom filter: the bits given by the first three pairs
+/// of bytes of its hash, modulo 2048, are set.
+fn bloom_accrue(bloom: &mut Bloom, input: &[u8]) {
+    let hash = keccak256(input);
+    for i in 0..3 {
+        let bit = (((hash[2 * i] as usize) << 8) | hash[2 * i + 1] as usize) & 2047;
+        bloom.0[255 - bit / 8] |= 1 << (bit % 8);
+    }
+}
+
+/// Return the bloom filter of a block, which is the union of the bloom filters
+/// of its receipts.
+pub fn logs_bloom(receipts: &[Receipt]) -> Bloom {
+    let mut bloom = Bloom::zero();
+    for receipt in receipts {
+        bloom.accrue_bloom(&receipt.logs_bloom());
+    }
+    bloom
+}
+
+/// Return the root of the receipts trie of a block, where the receipts are
+/// indexed by the RLP of their position in the block.
+pub fn receipts_root(receipts: &[Receipt]) -> H256 {
+    let entries: Vec<(Vec<u8>, Vec<u8>)> = receipts
+        .iter()
+        .enumerate()
+        .map(|(index, receipt)| {
+            let key = rlp::encode(&(index as u64));
+            let nibbles = key
+                .iter()
+                .flat_map(|byte| [byte >> 4, byte & 0x0f])
+                .collect();
+            (nibbles, receipt.rlp())
+        })
+        .collect();
+    H256(keccak256(trie_node(&entries, 0)))
+}
+
+/// Return the encoding of the Merkle Patricia Trie node that contains the
+/// `entries`, whose keys (as nibbles) share their first `depth` nibbles.
+fn trie_node(entries: &[(Vec<u8>, Vec<u8>)], depth: usize) -> Vec<u8> {
+    let mut stream = RlpStream::new();
+    match entries {
+        [] => {
+            stream.append_empty_data();
+        }
+        [(key, value)] => {
+            stream.begin_list(2);
+            stream.append(&hex_prefix(&key[depth..], true));
+            stream.append(value);
+        }
+        _ => {
+            let (first_key, _) = &entries[0];
+            let prefix_len = (depth..first_key.len())
+                .take_while(|i| {
+                    entries
+                        .iter()
+                        .all(|(key, _)| key.get(*i) == first_key.get(*i))
+                })
+                .count();
+            if prefix_len > 0 {
+                // Extension node
+                stream.begin_list(2);
+                stream.append(&hex_prefix(&first_key[depth..depth + prefix_len], false));
+                append_child(&mut stream, &trie_node(entries, depth + prefix_len));
+            } else {
+                // Branch node
+                stream.begin_list(17);
+                for nibble in 0..16 {
+                    let children: Vec<_> = entries
+                        .iter()
+                        .filter(|(key, _)| key.get(depth) == Some(&nibble))
+                        .cloned()
+                        .collect();
+                    if children.is_empty() {
+                        stream.append_empty_data();
+                    } else {
+                        append_child(&mut stream, &trie_node(&children, depth + 1));
+                    }
+                }
+                match entries.iter().find(|(key, _)| key.len() == depth) {
+                    Some((_, value)) => stream.append(value),
+                    None => stream.append_empty_data(),
+                };
+            }
+        }
+    }
+    stream.out().to_vec()
+}
+
+/// Append a reference to a child node: the node itself if its encoding is
+/// shorter than 32 bytes, or its hash otherwise.
+fn append_child(stream: &mut RlpStream, node: &[u8]) {
+    if node.len() < 32 {
+        stream.append_raw(node, 1);
+    } else {
+        stream.append(&H256(keccak256(node)));
+    }
+}
+
+/// Return the hex-prefix encoding of a path of nibbles
+fn hex_prefix(nibbles: &[u8], is_leaf: bool) -> Vec<u8> {
+    let flag = (is_leaf as u8) << 1;
+    let mut bytes = if nibbles.len() % 2 == 1 {
+        vec![((flag | 1) << 4) | nibbles[0]]
+    } else {
+        vec![flag << 4]
+    };
+    let rest = &nibbles[nibbles.len() % 2..];
+    bytes.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    bytes
+}
+
+#[cfg(test)]
+mod receipt_tests {
+    use super::*;
+    use eth_types::{address, word};
+    use pretty_assertions::assert_eq;
+    use std::str::FromStr;
+
+    #[test]
+    fn receipts_root_empty() {
+        // Root of the empty trie: keccak256(rlp(""))
+        assert_eq!(
+            receipts_root(&[]),
+            H256::from_str("0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn receipt_logs_bloom() {
+        let receipt = Receipt {
+            tx_type: 0,
+            status: true,
+            cumulative_gas_used: 21000,
+            logs: vec![Log {
+                address: address!("0x0000000000000000000000000000000000000001"),
+                topics: vec![word!("0x1")],
+                data: vec![],
+            }],
+        };
+        let bloom = receipt.logs_bloom();
+        // Each input sets at most 3 bits
+        let bits: u32 = bloom.0.iter().map(|byte| byte.count_ones()).sum();
+        assert!(bits > 0 && bits <= 6);
+        // The block bloom contains the bloom of every receipt
+        let empty = Receipt {
+            cumulative_gas_used: 42000,
+            ..Receipt::default()
+        };
+        assert_eq!(logs_bloom(&[receipt, empty]), bloom);
+    }
+
+    #[test]
+    fn receipt_rlp() {
+        let receipt = Receipt {
+            tx_type: 2,
+            status: false,
+            cumulative_gas_used: 0x5208,
+            logs: vec![],
+        };
+        let mut stream = RlpStream::new_list(4);
+        stream
+            .append_empty_data()
+            .append(&0x5208u64)
+            .append(&Bloom::zero())
+            .begin_list(0);
+        assert_eq!(receipt.rlp(), [vec![2], stream.out().to_vec()].concat());
+    }
+
+    #[test]
+    fn hex_prefix_encoding() {
+        assert_eq!(hex_prefix(&[1, 2, 3, 4, 5], false), vec![0x11, 0x23, 0x45]);
+        assert_eq!(
+            hex_prefix(&[0, 1, 2, 3, 4, 5], false),
+            vec![0x00, 0x01, 0x23, 0x45]
+        );
+        assert_eq!(
+            hex_prefix(&[0, 0xf, 1, 0xc, 0xb, 8], true),
+            vec![0x20, 0x0f, 0x1c, 0xb8]
+        );
+        assert_eq!(
+            hex_prefix(&[0xf, 1, 0xc, 0xb, 8], true),
+            vec![0x3f, 0x1c, 0xb8]
+        );
+    }
+}
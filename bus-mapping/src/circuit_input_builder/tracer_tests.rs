@@ -1,9 +1,12 @@
 use super::*;
 use crate::circuit_input_builder::access::gen_state_access_trace;
-use crate::error::{ExecError, OogError};
+use crate::error::{get_step_reported_error, ExecError, OogError};
 use crate::geth_errors::{
-    GETH_ERR_GAS_UINT_OVERFLOW, GETH_ERR_OUT_OF_GAS, GETH_ERR_STACK_OVERFLOW,
-    GETH_ERR_STACK_UNDERFLOW,
+    GETH_ERR_CODE_STORE_OUT_OF_GAS, GETH_ERR_CONTRACT_ADDRESS_COLLISION, GETH_ERR_DEPTH,
+    GETH_ERR_GAS_UINT_OVERFLOW, GETH_ERR_INSUFFICIENT_BALANCE, GETH_ERR_INVALID_CODE,
+    GETH_ERR_INVALID_JUMP, GETH_ERR_INVALID_OPCODE, GETH_ERR_MAX_CODE_SIZE_EXCEEDED,
+    GETH_ERR_OUT_OF_GAS, GETH_ERR_RETURN_DATA_OUT_OF_BOUNDS, GETH_ERR_STACK_OVERFLOW,
+    GETH_ERR_STACK_UNDERFLOW, GETH_ERR_WRITE_PROTECTION,
 };
 use crate::operation::RWCounter;
 use crate::state_db::Account;
@@ -1397,6 +1400,83 @@ fn tracer_err_write_protection() {
     );
 }
 
+#[test]
+fn tracer_err_unknown() {
+    let code = bytecode! {
+        PUSH1(0x0)
+        STOP
+    };
+    let block: GethData = TestContext::<2, 1>::new(
+        None,
+        account_0_code_account_1_no_code(code),
+        tx_from_1_to_0,
+        |block, _tx| block.number(0xcafeu64),
+    )
+    .unwrap()
+    .into();
+
+    // An error reported by geth which can't be mapped into an ExecError is
+    // returned instead of being ignored.
+    let mut step = block.geth_traces[0].struct_logs[0].clone();
+    step.error = Some("unknown error".to_string());
+    let mut builder = CircuitInputBuilderTx::new(&block, &step);
+    let result = crate::evm::opcodes::gen_associated_ops(
+        &step.op,
+        &mut builder.state_ref(),
+        &[step.clone()],
+    );
+    assert!(matches!(
+        result,
+        Err(crate::Error::UnknownGethError(err)) if err == "unknown error"
+    ));
+}
+
+#[test]
+fn tracer_call_precompile_failure() {
+    // bn256Add fails because (1, 0) isn't on the curve.
+    let code = bytecode! {
+        PUSH1(0x1)
+        PUSH1(0x0)
+        MSTORE
+        PUSH1(0x0) // retLength
+        PUSH1(0x0) // retOffset
+        PUSH1(0x80) // argsLength
+        PUSH1(0x0) // argsOffset
+        PUSH1(0x0) // value
+        PUSH1(0x6) // addr
+        PUSH32(0x1_0000) // gas
+        CALL
+        STOP
+    };
+    let index = 10; // CALL
+    let block: GethData = TestContext::<2, 1>::new(
+        None,
+        account_0_code_account_1_no_code(code),
+        tx_from_1_to_0,
+        |block, _tx| block.number(0xcafeu64),
+    )
+    .unwrap()
+    .into();
+
+    let step = &block.geth_traces[0].struct_logs[index];
+    let next_step = block.geth_traces[0].struct_logs.get(index + 1);
+    assert_eq!(step.op, OpcodeId::CALL);
+    assert_eq!(result(next_step), Word::zero());
+
+    // The failure of the precompiled contract isn't an error of the CALL.
+    let mut builder = CircuitInputBuilderTx::new(&block, step);
+    assert_eq!(
+        builder.state_ref().get_step_err(step, next_step).unwrap(),
+        None
+    );
+
+    let mut builder =
+        crate::mock::BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+    builder
+        .handle_block(&block.eth_block, &block.geth_traces)
+        .unwrap();
+}
+
 #[test]
 fn tracer_err_out_of_gas() {
     // Do 3 PUSH1 with gas = 4, which causes out of gas
@@ -1488,6 +1568,65 @@ fn tracer_err_stack_underflow() {
     );
 }
 
+#[test]
+fn tracer_err_reported_by_geth() {
+    for (op, error, exec_error) in [
+        (
+            OpcodeId::JUMP,
+            GETH_ERR_INVALID_JUMP.to_string(),
+            ExecError::InvalidJump,
+        ),
+        (
+            OpcodeId::SSTORE,
+            GETH_ERR_WRITE_PROTECTION.to_string(),
+            ExecError::WriteProtection,
+        ),
+        (
+            OpcodeId::RETURN,
+            GETH_ERR_MAX_CODE_SIZE_EXCEEDED.to_string(),
+            ExecError::MaxCodeSizeExceeded,
+        ),
+        (
+            OpcodeId::RETURNDATACOPY,
+            GETH_ERR_RETURN_DATA_OUT_OF_BOUNDS.to_string(),
+            ExecError::ReturnDataOutOfBounds,
+        ),
+        (
+            OpcodeId::INVALID(0xfe),
+            format!("{}: opcode 0xfe not defined", GETH_ERR_INVALID_OPCODE),
+            ExecError::InvalidOpcode,
+        ),
+        (
+            OpcodeId::CREATE2,
+            GETH_ERR_CONTRACT_ADDRESS_COLLISION.to_string(),
+            ExecError::ContractAddressCollision,
+        ),
+        (OpcodeId::CALL, GETH_ERR_DEPTH.to_string(), ExecError::Depth),
+        (
+            OpcodeId::CALL,
+            GETH_ERR_INSUFFICIENT_BALANCE.to_string(),
+            ExecError::InsufficientBalance,
+        ),
+        (
+            OpcodeId::RETURN,
+            GETH_ERR_CODE_STORE_OUT_OF_GAS.to_string(),
+            ExecError::CodeStoreOutOfGas,
+        ),
+        (
+            OpcodeId::RETURN,
+            GETH_ERR_INVALID_CODE.to_string(),
+            ExecError::InvalidCreationCode,
+        ),
+    ] {
+        assert_eq!(get_step_reported_error(&op, &error).unwrap(), exec_error);
+    }
+
+    assert!(matches!(
+        get_step_reported_error(&OpcodeId::STOP, "unknown error"),
+        Err(Error::UnknownGethError(error)) if error == "unknown error"
+    ));
+}
+
 //
 // Circuit Input Builder tests
 //
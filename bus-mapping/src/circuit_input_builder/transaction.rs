@@ -180,6 +180,8 @@ impl TransactionContext {
 #[derive(Debug, Clone)]
 /// Result of the parsing of an Ethereum Transaction.
 pub struct Transaction {
+    /// Type (EIP-2718)
+    pub tx_type: u64,
     /// Nonce
     pub nonce: u64,
     /// Gas
@@ -256,6 +258,7 @@ impl Transaction {
         };
 
         Ok(Self {
+            tx_type: eth_tx.transaction_type.unwrap_or_default().as_u64(),
             nonce: eth_tx.nonce.as_u64(),
             gas: eth_tx.gas.as_u64(),
             gas_price: eth_tx.gas_price.unwrap_or_default(),
@@ -6,8 +6,11 @@ use ethers_providers::ProviderError;
 use std::error::Error as StdError;
 
 use crate::geth_errors::{
-    GETH_ERR_GAS_UINT_OVERFLOW, GETH_ERR_OUT_OF_GAS, GETH_ERR_STACK_OVERFLOW,
-    GETH_ERR_STACK_UNDERFLOW,
+    GETH_ERR_CODE_STORE_OUT_OF_GAS, GETH_ERR_CONTRACT_ADDRESS_COLLISION, GETH_ERR_DEPTH,
+    GETH_ERR_GAS_UINT_OVERFLOW, GETH_ERR_INSUFFICIENT_BALANCE, GETH_ERR_INVALID_CODE,
+    GETH_ERR_INVALID_JUMP, GETH_ERR_INVALID_OPCODE, GETH_ERR_MAX_CODE_SIZE_EXCEEDED,
+    GETH_ERR_OUT_OF_GAS, GETH_ERR_RETURN_DATA_OUT_OF_BOUNDS, GETH_ERR_STACK_OVERFLOW,
+    GETH_ERR_STACK_UNDERFLOW, GETH_ERR_WRITE_PROTECTION,
 };
 
 /// Error type for any BusMapping related failure.
@@ -38,8 +41,14 @@ pub enum Error {
     EthTypeError(eth_types::Error),
     /// EVM Execution error
     ExecutionError(ExecError),
+    /// Error reported by geth in a [`GethExecStep`] which can't be mapped into
+    /// an [`ExecError`]
+    UnknownGethError(String),
     /// Internal Code error
     InternalError(&'static str),
+    /// The receipts generated from the execution of the block don't match the
+    /// block header.
+    InvalidBlockReceipts(&'static str),
 }
 
 impl From<eth_types::Error> for Error {
@@ -142,7 +151,7 @@ pub enum ExecError {
 }
 
 // TODO: Move to impl block.
-pub(crate) fn get_step_reported_error(op: &OpcodeId, error: &str) -> ExecError {
+pub(crate) fn get_step_reported_error(op: &OpcodeId, error: &str) -> Result<ExecError, Error> {
     if error == GETH_ERR_OUT_OF_GAS || error == GETH_ERR_GAS_UINT_OVERFLOW {
         // NOTE: We report a GasUintOverflow error as an OutOfGas error
         let oog_err = match op {
@@ -174,12 +183,25 @@ pub(crate) fn get_step_reported_error(op: &OpcodeId, error: &str) -> ExecError {
             OpcodeId::SELFDESTRUCT => OogError::SelfDestruct,
             _ => OogError::Constant,
         };
-        ExecError::OutOfGas(oog_err)
+        Ok(ExecError::OutOfGas(oog_err))
     } else if error.starts_with(GETH_ERR_STACK_OVERFLOW) {
-        ExecError::StackOverflow
+        Ok(ExecError::StackOverflow)
     } else if error.starts_with(GETH_ERR_STACK_UNDERFLOW) {
-        ExecError::StackUnderflow
+        Ok(ExecError::StackUnderflow)
+    } else if error.starts_with(GETH_ERR_INVALID_OPCODE) {
+        Ok(ExecError::InvalidOpcode)
     } else {
-        panic!("Unknown GethExecStep.error: {}", error);
+        match error {
+            GETH_ERR_CODE_STORE_OUT_OF_GAS => Ok(ExecError::CodeStoreOutOfGas),
+            GETH_ERR_DEPTH => Ok(ExecError::Depth),
+            GETH_ERR_INSUFFICIENT_BALANCE => Ok(ExecError::InsufficientBalance),
+            GETH_ERR_CONTRACT_ADDRESS_COLLISION => Ok(ExecError::ContractAddressCollision),
+            GETH_ERR_MAX_CODE_SIZE_EXCEEDED => Ok(ExecError::MaxCodeSizeExceeded),
+            GETH_ERR_INVALID_JUMP => Ok(ExecError::InvalidJump),
+            GETH_ERR_WRITE_PROTECTION => Ok(ExecError::WriteProtection),
+            GETH_ERR_RETURN_DATA_OUT_OF_BOUNDS => Ok(ExecError::ReturnDataOutOfBounds),
+            GETH_ERR_INVALID_CODE => Ok(ExecError::InvalidCreationCode),
+            _ => Err(Error::UnknownGethError(error.to_string())),
+        }
     }
 }
@@ -1,6 +1,7 @@
 //! Definition of each opcode of the EVM.
 use crate::{
     circuit_input_builder::{CircuitInputStateRef, ExecStep},
+    error::ExecError,
     evm::OpcodeId,
     operation::{
         AccountField, CallContextField, TxAccessListAccountOp, TxReceiptField, TxRefundOp, RW,
@@ -15,6 +16,7 @@ use eth_types::{
 use keccak256::EMPTY_HASH;
 use log::warn;
 
+mod block_ctx;
 mod call;
 mod calldatacopy;
 mod calldataload;
@@ -26,6 +28,8 @@ mod codecopy;
 mod codesize;
 mod create;
 mod dup;
+mod error_call;
+mod error_write_protection;
 mod extcodecopy;
 mod extcodehash;
 mod gasprice;
@@ -47,6 +51,7 @@ mod swap;
 #[cfg(test)]
 mod memory_expansion_test;
 
+use block_ctx::BlockCtx;
 use call::Call;
 use calldatacopy::Calldatacopy;
 use calldataload::Calldataload;
@@ -57,6 +62,8 @@ use codecopy::Codecopy;
 use codesize::Codesize;
 use create::DummyCreate;
 use dup::Dup;
+use error_call::ErrorCall;
+use error_write_protection::ErrorWriteProtection;
 use extcodecopy::Extcodecopy;
 use extcodehash::Extcodehash;
 use gasprice::GasPrice;
@@ -89,6 +96,18 @@ pub trait Opcode: Debug {
     ) -> Result<Vec<ExecStep>, Error>;
 }
 
+/// Opcode trait for the steps which fail with an [`ExecError`] that is
+/// already known when their operations are generated.
+pub trait ErrorOpcode: Debug {
+    /// Generate the associated operations of a step failing with
+    /// `exec_error`.
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+        exec_error: ExecError,
+    ) -> Result<Vec<ExecStep>, Error>;
+}
+
 #[derive(Debug, Copy, Clone)]
 struct Dummy;
 
@@ -106,6 +125,12 @@ type FnGenAssociatedOps = fn(
     geth_steps: &[GethExecStep],
 ) -> Result<Vec<ExecStep>, Error>;
 
+type FnGenErrorStateAssociatedOps = fn(
+    state: &mut CircuitInputStateRef,
+    geth_steps: &[GethExecStep],
+    exec_error: ExecError,
+) -> Result<Vec<ExecStep>, Error>;
+
 fn fn_gen_associated_ops(opcode_id: &OpcodeId) -> FnGenAssociatedOps {
     if opcode_id.is_push() {
         return StackOnlyOpcode::<0, 1>::gen_associated_ops;
@@ -156,14 +181,14 @@ fn fn_gen_associated_ops(opcode_id: &OpcodeId) -> FnGenAssociatedOps {
         OpcodeId::RETURNDATACOPY => Returndatacopy::gen_associated_ops,
         OpcodeId::EXTCODEHASH => Extcodehash::gen_associated_ops,
         OpcodeId::BLOCKHASH => StackOnlyOpcode::<1, 1>::gen_associated_ops,
-        OpcodeId::COINBASE => StackOnlyOpcode::<0, 1>::gen_associated_ops,
-        OpcodeId::TIMESTAMP => StackOnlyOpcode::<0, 1>::gen_associated_ops,
-        OpcodeId::NUMBER => StackOnlyOpcode::<0, 1>::gen_associated_ops,
-        OpcodeId::DIFFICULTY => StackOnlyOpcode::<0, 1>::gen_associated_ops,
-        OpcodeId::GASLIMIT => StackOnlyOpcode::<0, 1>::gen_associated_ops,
-        OpcodeId::CHAINID => StackOnlyOpcode::<0, 1>::gen_associated_ops,
+        OpcodeId::COINBASE => BlockCtx::gen_associated_ops,
+        OpcodeId::TIMESTAMP => BlockCtx::gen_associated_ops,
+        OpcodeId::NUMBER => BlockCtx::gen_associated_ops,
+        OpcodeId::DIFFICULTY => BlockCtx::gen_associated_ops,
+        OpcodeId::GASLIMIT => BlockCtx::gen_associated_ops,
+        OpcodeId::CHAINID => BlockCtx::gen_associated_ops,
         OpcodeId::SELFBALANCE => Selfbalance::gen_associated_ops,
-        OpcodeId::BASEFEE => StackOnlyOpcode::<0, 1>::gen_associated_ops,
+        OpcodeId::BASEFEE => BlockCtx::gen_associated_ops,
         OpcodeId::POP => StackOnlyOpcode::<1, 0>::gen_associated_ops,
         OpcodeId::MLOAD => Mload::gen_associated_ops,
         OpcodeId::MSTORE => Mstore::<false>::gen_associated_ops,
@@ -213,7 +238,7 @@ fn fn_gen_associated_ops(opcode_id: &OpcodeId) -> FnGenAssociatedOps {
         OpcodeId::LOG2 => Log::gen_associated_ops,
         OpcodeId::LOG3 => Log::gen_associated_ops,
         OpcodeId::LOG4 => Log::gen_associated_ops,
-        OpcodeId::CALL => Call::gen_associated_ops,
+        OpcodeId::CALL | OpcodeId::STATICCALL => Call::gen_associated_ops,
         OpcodeId::RETURN => Return::gen_associated_ops,
         // REVERT is almost the same as RETURN
         OpcodeId::REVERT => Return::gen_associated_ops,
@@ -221,7 +246,7 @@ fn fn_gen_associated_ops(opcode_id: &OpcodeId) -> FnGenAssociatedOps {
             warn!("Using dummy gen_selfdestruct_ops for opcode SELFDESTRUCT");
             DummySelfDestruct::gen_associated_ops
         }
-        OpcodeId::CALLCODE | OpcodeId::DELEGATECALL | OpcodeId::STATICCALL => {
+        OpcodeId::CALLCODE | OpcodeId::DELEGATECALL => {
             warn!("Using dummy gen_call_ops for opcode {:?}", opcode_id);
             DummyCall::gen_associated_ops
         }
@@ -256,17 +281,61 @@ pub fn gen_associated_ops(
         );
     }
 
+    // Errors which depend on the call context are usually not reported by
+    // geth, so they are detected here from the trace and the current call.
+    let geth_step = &geth_steps[0];
+    let exec_error = state.get_step_err(geth_step, geth_steps.get(1))?;
+    if let Some(exec_error) = exec_error {
+        if let Some(fn_gen_error_ops) = fn_gen_error_state_associated_ops(opcode_id, &exec_error) {
+            return fn_gen_error_ops(state, geth_steps, exec_error);
+        }
+    }
+
     let steps = fn_gen_associated_ops(state, geth_steps)?;
 
     Ok(steps)
 }
 
+fn fn_gen_error_state_associated_ops(
+    opcode_id: &OpcodeId,
+    error: &ExecError,
+) -> Option<FnGenErrorStateAssociatedOps> {
+    match (error, opcode_id) {
+        (ExecError::WriteProtection, _) => Some(ErrorWriteProtection::gen_associated_ops),
+        (
+            ExecError::Depth,
+            OpcodeId::CALL | OpcodeId::CALLCODE | OpcodeId::DELEGATECALL | OpcodeId::STATICCALL,
+        ) => Some(ErrorCall::gen_associated_ops),
+        (ExecError::InsufficientBalance, OpcodeId::CALL | OpcodeId::CALLCODE) => {
+            Some(ErrorCall::gen_associated_ops)
+        }
+        _ => None,
+    }
+}
+
 pub fn gen_begin_tx_ops(state: &mut CircuitInputStateRef) -> Result<ExecStep, Error> {
     let mut exec_step = state.new_begin_tx_step();
     let call = state.call()?.clone();
 
+    // The TxId of the first transaction is written here, while the ones of the
+    // following transactions are written by the EndTx of their previous
+    // transaction.
+    if state.tx_ctx.id() == 1 {
+        state.call_context_write(
+            &mut exec_step,
+            call.call_id,
+            CallContextField::TxId,
+            state.tx_ctx.id().into(),
+        );
+    } else {
+        state.call_context_read(
+            &mut exec_step,
+            call.call_id,
+            CallContextField::TxId,
+            state.tx_ctx.id().into(),
+        );
+    }
     for (field, value) in [
-        (CallContextField::TxId, state.tx_ctx.id().into()),
         (
             CallContextField::RwCounterEndOfReversion,
             call.rw_counter_end_of_reversion.into(),
@@ -276,7 +345,7 @@ pub fn gen_begin_tx_ops(state: &mut CircuitInputStateRef) -> Result<ExecStep, Er
             (call.is_persistent as usize).into(),
         ),
     ] {
-        state.call_context_read(&mut exec_step, call.call_id, field, value);
+        state.call_context_write(&mut exec_step, call.call_id, field, value);
     }
 
     // Increase caller's nonce
@@ -383,8 +452,12 @@ pub fn gen_begin_tx_ops(state: &mut CircuitInputStateRef) -> Result<ExecStep, Er
                 (CallContextField::IsRoot, 1.into()),
                 (CallContextField::IsCreate, 0.into()),
                 (CallContextField::CodeHash, code_hash.to_word()),
+                (
+                    CallContextField::IsSuccess,
+                    (call.is_success as usize).into(),
+                ),
             ] {
-                state.call_context_read(&mut exec_step, call.call_id, field, value);
+                state.call_context_write(&mut exec_step, call.call_id, field, value);
             }
 
             Ok(exec_step)
@@ -489,7 +562,7 @@ pub fn gen_end_tx_ops(state: &mut CircuitInputStateRef) -> Result<ExecStep, Erro
     )?;
 
     if !state.tx_ctx.is_last_tx() {
-        state.call_context_read(
+        state.call_context_write(
             &mut exec_step,
             state.block_ctx.rwc.0 + 1,
             CallContextField::TxId,
@@ -0,0 +1,42 @@
+use super::Opcode;
+use crate::circuit_input_builder::{CircuitInputStateRef, ExecStep};
+use crate::operation::CallContextField;
+use crate::Error;
+use eth_types::GethExecStep;
+
+/// Placeholder structure used to implement [`Opcode`] trait over it
+/// corresponding to the block context opcodes: `COINBASE`, `TIMESTAMP`,
+/// `NUMBER`, `DIFFICULTY`, `GASLIMIT`, `CHAINID` and `BASEFEE`.  The TxId is
+/// read so that the block of the transaction can be found in the circuit.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct BlockCtx;
+
+impl Opcode for BlockCtx {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+    ) -> Result<Vec<ExecStep>, Error> {
+        let geth_step = &geth_steps[0];
+        let mut exec_step = state.new_step(geth_step)?;
+        // Get the block context value from next step
+        let value = geth_steps[1].stack.last()?;
+        let tx_id = state.tx_ctx.id();
+
+        // CallContext read of the TxId
+        state.call_context_read(
+            &mut exec_step,
+            state.call()?.call_id,
+            CallContextField::TxId,
+            tx_id.into(),
+        );
+
+        // Stack write of the block context value
+        state.stack_write(
+            &mut exec_step,
+            geth_step.stack.last_filled().map(|a| a - 1),
+            value,
+        )?;
+
+        Ok(vec![exec_step])
+    }
+}
@@ -1,6 +1,7 @@
 use super::Opcode;
 use crate::{
     circuit_input_builder::{CircuitInputStateRef, ExecStep},
+    evm::OpcodeId,
     operation::{AccountField, CallContextField, TxAccessListAccountOp, RW},
     Error,
 };
@@ -16,7 +17,10 @@ use log::warn;
 use std::cmp::max;
 
 /// Placeholder structure used to implement [`Opcode`] trait over it
-/// corresponding to the `OpcodeId::CALL` `OpcodeId`.
+/// corresponding to the `OpcodeId::CALL` and `OpcodeId::STATICCALL`
+/// `OpcodeId`s.
+/// STATICCALL takes no value from the stack, so its memory arguments start one
+/// position earlier.
 #[derive(Debug, Copy, Clone)]
 pub(crate) struct Call;
 
@@ -28,10 +32,16 @@ impl Opcode for Call {
         let geth_step = &geth_steps[0];
         let mut exec_step = state.new_step(geth_step)?;
 
-        let args_offset = geth_step.stack.nth_last(3)?.as_usize();
-        let args_length = geth_step.stack.nth_last(4)?.as_usize();
-        let ret_offset = geth_step.stack.nth_last(5)?.as_usize();
-        let ret_length = geth_step.stack.nth_last(6)?.as_usize();
+        let args_position = match geth_step.op {
+            OpcodeId::STATICCALL => 2,
+            _ => 3,
+        };
+        let stack_inputs = args_position + 4;
+
+        let args_offset = geth_step.stack.nth_last(args_position)?.as_usize();
+        let args_length = geth_step.stack.nth_last(args_position + 1)?.as_usize();
+        let ret_offset = geth_step.stack.nth_last(args_position + 2)?.as_usize();
+        let ret_length = geth_step.stack.nth_last(args_position + 3)?.as_usize();
 
         // we need to keep the memory until parse_call complete
         let call_ctx = state.call_ctx_mut()?;
@@ -77,7 +87,7 @@ impl Opcode for Call {
             state.call_context_read(&mut exec_step, current_call.call_id, field, value);
         }
 
-        for i in 0..7 {
+        for i in 0..stack_inputs {
             state.stack_read(
                 &mut exec_step,
                 geth_step.stack.nth_last_filled(i),
@@ -87,7 +97,7 @@ impl Opcode for Call {
 
         state.stack_write(
             &mut exec_step,
-            geth_step.stack.nth_last_filled(6),
+            geth_step.stack.nth_last_filled(stack_inputs - 1),
             (call.is_success as u64).into(),
         )?;
 
@@ -113,7 +123,7 @@ impl Opcode for Call {
                 (call.is_persistent as u64).into(),
             ),
         ] {
-            state.call_context_read(&mut exec_step, call.call_id, field, value);
+            state.call_context_write(&mut exec_step, call.call_id, field, value);
         }
 
         state.transfer(
@@ -198,7 +208,7 @@ impl Opcode for Call {
                     ),
                     (
                         CallContextField::StackPointer,
-                        (geth_step.stack.stack_pointer().0 + 6).into(),
+                        (geth_step.stack.stack_pointer().0 + stack_inputs - 1).into(),
                     ),
                     (
                         CallContextField::GasLeft,
@@ -248,7 +258,7 @@ impl Opcode for Call {
                     (CallContextField::IsCreate, 0.into()),
                     (CallContextField::CodeHash, call.code_hash.to_word()),
                 ] {
-                    state.call_context_read(&mut exec_step, call.call_id, field, value);
+                    state.call_context_write(&mut exec_step, call.call_id, field, value);
                 }
 
                 Ok(vec![exec_step])
@@ -2,11 +2,16 @@
 
 mod chainid_tests {
     use crate::operation::RW;
-    use crate::{circuit_input_builder::ExecState, mock::BlockData, operation::StackOp};
+    use crate::{
+        circuit_input_builder::ExecState,
+        mock::BlockData,
+        operation::{CallContextField, CallContextOp, StackOp},
+    };
     use eth_types::{
         bytecode,
         evm_types::{OpcodeId, StackAddress},
         geth_types::GethData,
+        Word,
     };
 
     use mock::test_ctx::{helpers::*, TestContext};
@@ -41,10 +46,26 @@ mod chainid_tests {
             .find(|step| step.exec_state == ExecState::Op(OpcodeId::CHAINID))
             .unwrap();
 
+        let call_id = builder.block.txs()[0].calls()[0].call_id;
+        assert_eq!(
+            {
+                let operation =
+                    &builder.block.container.call_context[step.bus_mapping_instance[0].as_usize()];
+                (operation.rw(), operation.op())
+            },
+            (
+                RW::READ,
+                &CallContextOp {
+                    call_id,
+                    field: CallContextField::TxId,
+                    value: Word::one(),
+                }
+            )
+        );
         assert_eq!(
             {
                 let operation =
-                    &builder.block.container.stack[step.bus_mapping_instance[0].as_usize()];
+                    &builder.block.container.stack[step.bus_mapping_instance[1].as_usize()];
                 (operation.rw(), operation.op())
             },
             (
@@ -0,0 +1,355 @@
+use super::ErrorOpcode;
+use crate::{
+    circuit_input_builder::{CircuitInputStateRef, ExecStep},
+    error::ExecError,
+    operation::{AccountField, CallContextField, TxAccessListAccountOp, RW},
+    Error,
+};
+use eth_types::{evm_types::OpcodeId, GethExecStep, ToAddress, ToWord, Word};
+use std::cmp::max;
+
+/// Placeholder structure used to implement [`ErrorOpcode`] trait over it
+/// corresponding to a `OpcodeId::{CALL, CALLCODE, DELEGATECALL, STATICCALL}`
+/// which fails before the callee is executed, because of either
+/// [`ExecError::Depth`] or [`ExecError::InsufficientBalance`].
+/// Unlike other errors these don't halt the current call, the caller just gets
+/// a `0` pushed on the stack and continues its execution.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ErrorCall;
+
+impl ErrorOpcode for ErrorCall {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+        exec_error: ExecError,
+    ) -> Result<Vec<ExecStep>, Error> {
+        let geth_step = &geth_steps[0];
+        let mut exec_step = state.new_step(geth_step)?;
+        exec_step.error = Some(exec_error);
+
+        // DELEGATECALL and STATICCALL don't take a value, so the following
+        // stack items are one position closer to the top.
+        let num_args = match geth_step.op {
+            OpcodeId::CALL | OpcodeId::CALLCODE => 7,
+            _ => 6,
+        };
+        let args_offset = geth_step.stack.nth_last(num_args - 4)?.as_usize();
+        let args_length = geth_step.stack.nth_last(num_args - 3)?.as_usize();
+        let ret_offset = geth_step.stack.nth_last(num_args - 2)?.as_usize();
+        let ret_length = geth_step.stack.nth_last(num_args - 1)?.as_usize();
+
+        // Memory is still expanded even if the callee is never executed.
+        let call_ctx = state.call_ctx_mut()?;
+        let args_minimal = if args_length != 0 {
+            args_offset + args_length
+        } else {
+            0
+        };
+        let ret_minimal = if ret_length != 0 {
+            ret_offset + ret_length
+        } else {
+            0
+        };
+        if args_minimal != 0 || ret_minimal != 0 {
+            let minimal_length = max(args_minimal, ret_minimal);
+            call_ctx.memory.extend_at_least(minimal_length);
+        }
+
+        let tx_id = state.tx_ctx.id();
+        let call = state.parse_call(geth_step)?;
+        let current_call = state.call()?.clone();
+        debug_assert!(!call.is_success);
+
+        // NOTE: For `RwCounterEndOfReversion` we use the `0` value as a placeholder,
+        // and later set the proper value in
+        // `CircuitInputBuilder::set_value_ops_call_context_rwc_eor`
+        for (field, value) in [
+            (CallContextField::TxId, tx_id.into()),
+            (CallContextField::RwCounterEndOfReversion, 0.into()),
+            (
+                CallContextField::IsPersistent,
+                (current_call.is_persistent as u64).into(),
+            ),
+            (
+                CallContextField::CalleeAddress,
+                current_call.address.to_word(),
+            ),
+            (
+                CallContextField::IsStatic,
+                (current_call.is_static as u64).into(),
+            ),
+            (CallContextField::Depth, current_call.depth.into()),
+        ] {
+            state.call_context_read(&mut exec_step, current_call.call_id, field, value);
+        }
+
+        for i in 0..num_args {
+            state.stack_read(
+                &mut exec_step,
+                geth_step.stack.nth_last_filled(i),
+                geth_step.stack.nth_last(i)?,
+            )?;
+        }
+
+        state.stack_write(
+            &mut exec_step,
+            geth_step.stack.nth_last_filled(num_args - 1),
+            Word::zero(),
+        )?;
+
+        // The address whose code would be executed, which is the callee of a
+        // CALL and a STATICCALL, but not of a CALLCODE and a DELEGATECALL that
+        // execute it in the current context.
+        let code_address = geth_step.stack.nth_last(1)?.to_address();
+
+        // The code address is added to the access list before the depth and
+        // balance checks, so it stays warm even if the call fails.
+        let is_warm = state.sdb.check_account_in_access_list(&code_address);
+        state.push_op_reversible(
+            &mut exec_step,
+            RW::WRITE,
+            TxAccessListAccountOp {
+                tx_id,
+                address: code_address,
+                is_warm: true,
+                is_warm_prev: is_warm,
+            },
+        )?;
+
+        let (found, caller_account) = state.sdb.get_account(&current_call.address);
+        if !found {
+            return Err(Error::AccountNotFound(current_call.address));
+        }
+        let caller_balance = caller_account.balance;
+        state.account_read(
+            &mut exec_step,
+            current_call.address,
+            AccountField::Balance,
+            caller_balance,
+            caller_balance,
+        )?;
+
+        // The callee's account is read to know whether it's empty, which is
+        // needed to compute the gas cost of a CALL with value.
+        let (_, callee_account) = state.sdb.get_account(&code_address);
+        let callee_nonce = callee_account.nonce;
+        let callee_balance = callee_account.balance;
+        let callee_code_hash = callee_account.code_hash;
+        for (field, value) in [
+            (AccountField::Nonce, callee_nonce),
+            (AccountField::Balance, callee_balance),
+            (AccountField::CodeHash, callee_code_hash.to_word()),
+        ] {
+            state.account_read(&mut exec_step, code_address, field, value, value)?;
+        }
+
+        for (field, value) in [
+            (CallContextField::LastCalleeId, 0.into()),
+            (CallContextField::LastCalleeReturnDataOffset, 0.into()),
+            (CallContextField::LastCalleeReturnDataLength, 0.into()),
+        ] {
+            state.call_context_write(&mut exec_step, current_call.call_id, field, value);
+        }
+
+        // The failed call is still pushed and popped right away, to keep the
+        // calls in the transaction in sync with the ones found in the trace.
+        state.push_call(call);
+        state.handle_return(geth_step)?;
+
+        Ok(vec![exec_step])
+    }
+}
+
+#[cfg(test)]
+mod error_call_tests {
+    use super::*;
+    use crate::circuit_input_builder::ExecState;
+    use crate::error::ExecError;
+    use crate::mock::BlockData;
+    use crate::operation::StackOp;
+    use eth_types::{
+        address, bytecode,
+        evm_types::{OpcodeId, StackAddress},
+        geth_types::GethData,
+        Address, Bytecode,
+    };
+    use mock::TestContext;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn call_insufficient_balance() {
+        let callee_address = address!("0x000000000000000000000000000000000cafe001");
+        let code_b = bytecode! {
+            PUSH1(0x01)
+            PUSH1(0x00)
+            SSTORE
+            STOP
+        };
+        // The caller doesn't have any balance, so calling with value fails.
+        let code_a = bytecode! {
+            PUSH1(0x00) // retLength
+            PUSH1(0x00) // retOffset
+            PUSH1(0x00) // argsLength
+            PUSH1(0x00) // argsOffset
+            PUSH1(0x01) // value
+            PUSH20(callee_address.to_word()) // addr
+            PUSH32(0x1_0000) // gas
+            CALL
+            STOP
+        };
+
+        // Get the execution steps from the external tracer
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x0000000000000000000000000000000000000010"))
+                    .code(code_a);
+                accs[1].address(callee_address).code(code_b);
+                accs[2]
+                    .address(address!("0x0000000000000000000000000000000000cafe01"))
+                    .balance(Word::from(1u64 << 30));
+            },
+            |mut txs, accs| {
+                txs[0].to(accs[0].address).from(accs[2].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let transaction = &builder.block.txs()[0];
+        let call_id = transaction.calls()[0].call_id;
+        let step = transaction
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(OpcodeId::CALL))
+            .unwrap();
+        assert_eq!(step.error, Some(ExecError::InsufficientBalance));
+        assert_eq!(step.bus_mapping_instance.len(), 22);
+
+        // CALL pushes 0 instead of halting the caller.
+        let container = &builder.block.container;
+        assert_eq!(
+            {
+                let operation = &container.stack[step.bus_mapping_instance[13].as_usize()];
+                (operation.rw(), operation.op())
+            },
+            (
+                RW::WRITE,
+                &StackOp {
+                    call_id,
+                    address: StackAddress::from(1023u32),
+                    value: Word::zero()
+                }
+            )
+        );
+
+        // The callee is never executed, but it's still warm.
+        assert_eq!(transaction.calls().len(), 2);
+        assert!(!transaction.calls()[1].is_success);
+        assert!(!builder.sdb.add_account_to_access_list(callee_address));
+    }
+
+    fn check_depth(code: Bytecode, address: Address, opcode: OpcodeId) {
+        // Get the execution steps from the external tracer
+        let block: GethData = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0].address(address).code(code);
+                accs[1]
+                    .address(address!("0x0000000000000000000000000000000000cafe01"))
+                    .balance(Word::from(1u64 << 40));
+            },
+            |mut txs, accs| {
+                txs[0]
+                    .to(accs[0].address)
+                    .from(accs[1].address)
+                    .gas(Word::from(10u64.pow(11)));
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let transaction = &builder.block.txs()[0];
+        let steps: Vec<_> = transaction
+            .steps()
+            .iter()
+            .filter(|step| step.error.is_some())
+            .collect();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].exec_state, ExecState::Op(opcode));
+        assert_eq!(steps[0].error, Some(ExecError::Depth));
+        assert_eq!(transaction.calls()[steps[0].call_index].depth, 1025);
+
+        // Only the call which exceeds the depth limit fails.
+        assert_eq!(transaction.calls().len(), 1026);
+        assert!(transaction.calls()[..1025]
+            .iter()
+            .all(|call| call.is_success));
+        assert!(!transaction.calls()[1025].is_success);
+    }
+
+    #[test]
+    fn call_depth() {
+        let address = address!("0x0000000000000000000000000000000000000010");
+        // The contract calls itself until the call stack is full.
+        let code = bytecode! {
+            PUSH1(0x00) // retLength
+            PUSH1(0x00) // retOffset
+            PUSH1(0x00) // argsLength
+            PUSH1(0x00) // argsOffset
+            PUSH1(0x00) // value
+            PUSH20(address.to_word()) // addr
+            GAS // gas
+            CALL
+            STOP
+        };
+        check_depth(code, address, OpcodeId::CALL);
+    }
+
+    #[test]
+    fn staticcall_depth() {
+        let address = address!("0x0000000000000000000000000000000000000010");
+        // The contract calls itself until the call stack is full.
+        let code = bytecode! {
+            PUSH1(0x00) // retLength
+            PUSH1(0x00) // retOffset
+            PUSH1(0x00) // argsLength
+            PUSH1(0x00) // argsOffset
+            PUSH20(address.to_word()) // addr
+            GAS // gas
+            STATICCALL
+            STOP
+        };
+        check_depth(code, address, OpcodeId::STATICCALL);
+    }
+
+    #[test]
+    fn delegatecall_depth() {
+        let address = address!("0x0000000000000000000000000000000000000010");
+        // The contract runs its own code until the call stack is full.
+        let code = bytecode! {
+            PUSH1(0x00) // retLength
+            PUSH1(0x00) // retOffset
+            PUSH1(0x00) // argsLength
+            PUSH1(0x00) // argsOffset
+            PUSH20(address.to_word()) // addr
+            GAS // gas
+            DELEGATECALL
+            STOP
+        };
+        check_depth(code, address, OpcodeId::DELEGATECALL);
+    }
+}
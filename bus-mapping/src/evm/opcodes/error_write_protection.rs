@@ -0,0 +1,164 @@
+use super::ErrorOpcode;
+use crate::{
+    circuit_input_builder::{CircuitInputStateRef, ExecStep},
+    error::ExecError,
+    evm::OpcodeId,
+    operation::CallContextField,
+    Error,
+};
+use eth_types::GethExecStep;
+
+/// Placeholder structure used to implement [`ErrorOpcode`] trait over it
+/// corresponding to an opcode which tries to modify the state inside of a
+/// static call (SSTORE, CREATE, CREATE2, SELFDESTRUCT, LOG* or CALL with
+/// value), which halts the current call with
+/// [`ExecError::WriteProtection`].
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ErrorWriteProtection;
+
+impl ErrorOpcode for ErrorWriteProtection {
+    fn gen_associated_ops(
+        state: &mut CircuitInputStateRef,
+        geth_steps: &[GethExecStep],
+        exec_error: ExecError,
+    ) -> Result<Vec<ExecStep>, Error> {
+        let geth_step = &geth_steps[0];
+        let mut exec_step = state.new_step(geth_step)?;
+        debug_assert_eq!(exec_error, ExecError::WriteProtection);
+        exec_step.error = Some(exec_error);
+
+        let call = state.call()?.clone();
+        debug_assert!(call.is_static);
+
+        state.call_context_read(
+            &mut exec_step,
+            call.call_id,
+            CallContextField::IsStatic,
+            1.into(),
+        );
+
+        // CALL only breaks write protection when it has a non-zero value, so
+        // the gas, address and value are read to prove it.
+        if geth_step.op == OpcodeId::CALL {
+            for i in 0..3 {
+                state.stack_read(
+                    &mut exec_step,
+                    geth_step.stack.nth_last_filled(i),
+                    geth_step.stack.nth_last(i)?,
+                )?;
+            }
+        }
+
+        state.call_context_read(
+            &mut exec_step,
+            call.call_id,
+            CallContextField::IsSuccess,
+            0.into(),
+        );
+
+        state.gen_restore_context_ops(&mut exec_step, geth_steps)?;
+        state.handle_return(geth_step)?;
+
+        Ok(vec![exec_step])
+    }
+}
+
+#[cfg(test)]
+mod error_write_protection_tests {
+    use crate::circuit_input_builder::ExecState;
+    use crate::error::ExecError;
+    use crate::evm::OpcodeId;
+    use crate::mock::BlockData;
+    use eth_types::{address, bytecode, geth_types::GethData, Bytecode, ToWord, Word};
+    use mock::TestContext;
+    use pretty_assertions::assert_eq;
+
+    fn test_static_call(code_b: Bytecode, opcode: OpcodeId, bus_mapping_instance_len: usize) {
+        let callee_address = address!("0x000000000000000000000000000000000cafe001");
+        let code_a = bytecode! {
+            PUSH1(0x00) // retLength
+            PUSH1(0x00) // retOffset
+            PUSH1(0x00) // argsLength
+            PUSH1(0x00) // argsOffset
+            PUSH20(callee_address.to_word()) // addr
+            PUSH32(0x1_0000) // gas
+            STATICCALL
+            STOP
+        };
+
+        // Get the execution steps from the external tracer
+        let block: GethData = TestContext::<3, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x0000000000000000000000000000000000000010"))
+                    .code(code_a);
+                accs[1].address(callee_address).code(code_b);
+                accs[2]
+                    .address(address!("0x0000000000000000000000000000000000cafe01"))
+                    .balance(Word::from(1u64 << 30));
+            },
+            |mut txs, accs| {
+                txs[0].to(accs[0].address).from(accs[2].address);
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+
+        let transaction = &builder.block.txs()[0];
+        let step = transaction
+            .steps()
+            .iter()
+            .find(|step| step.exec_state == ExecState::Op(opcode))
+            .unwrap();
+        assert_eq!(step.error, Some(ExecError::WriteProtection));
+        assert_eq!(step.bus_mapping_instance.len(), bus_mapping_instance_len);
+        assert!(!transaction.calls()[1].is_success);
+    }
+
+    #[test]
+    fn sstore_in_static_call() {
+        let code = bytecode! {
+            PUSH1(0x01) // value
+            PUSH1(0x02) // key
+            SSTORE
+            STOP
+        };
+        // IsStatic, IsSuccess and the restoration of the caller's context
+        test_static_call(code, OpcodeId::SSTORE, 14);
+    }
+
+    #[test]
+    fn log_in_static_call() {
+        let code = bytecode! {
+            PUSH1(0x00) // length
+            PUSH1(0x00) // offset
+            LOG0
+            STOP
+        };
+        test_static_call(code, OpcodeId::LOG0, 14);
+    }
+
+    #[test]
+    fn call_with_value_in_static_call() {
+        let code = bytecode! {
+            PUSH1(0x00) // retLength
+            PUSH1(0x00) // retOffset
+            PUSH1(0x00) // argsLength
+            PUSH1(0x00) // argsOffset
+            PUSH1(0x01) // value
+            PUSH20(address!("0x0000000000000000000000000000000000000010").to_word()) // addr
+            PUSH1(0x00) // gas
+            CALL
+            STOP
+        };
+        // The gas, address and value are also read from the stack.
+        test_static_call(code, OpcodeId::CALL, 17);
+    }
+}
@@ -225,7 +225,7 @@ fn gen_copy_event(
 #[cfg(test)]
 mod log_tests {
     use crate::{
-        circuit_input_builder::{CopyDataType, CopyStep, ExecState, NumberOrHash},
+        circuit_input_builder::{CopyDataType, CopyStep, ExecState, Log, NumberOrHash},
         mock::BlockData,
         operation::{
             CallContextField, CallContextOp, MemoryOp, RWCounter, StackOp, TxLogField, TxLogOp, RW,
@@ -529,5 +529,17 @@ mod log_tests {
             );
             rwc_inc -= 1;
         }
+
+        // The receipt is generated from the TxReceipt and TxLog operations
+        let receipt = &builder.block.receipts[0];
+        assert!(receipt.status);
+        assert_eq!(
+            receipt.logs,
+            vec![Log {
+                address: callee_address,
+                topics: topics.iter().rev().cloned().collect(),
+                data: memory_data[mstart..mstart + msize].to_vec(),
+            }]
+        );
     }
 }
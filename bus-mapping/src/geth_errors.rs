@@ -6,3 +6,23 @@ pub const GETH_ERR_STACK_UNDERFLOW: &str = "stack underflow";
 pub const GETH_ERR_OUT_OF_GAS: &str = "out of gas";
 /// Geth error message for gas uint64 overflow
 pub const GETH_ERR_GAS_UINT_OVERFLOW: &str = "gas uint64 overflow";
+/// Geth error message for code store out of gas
+pub const GETH_ERR_CODE_STORE_OUT_OF_GAS: &str = "contract creation code storage out of gas";
+/// Geth error message for max call depth exceeded
+pub const GETH_ERR_DEPTH: &str = "max call depth exceeded";
+/// Geth error message for insufficient balance for transfer
+pub const GETH_ERR_INSUFFICIENT_BALANCE: &str = "insufficient balance for transfer";
+/// Geth error message for contract address collision
+pub const GETH_ERR_CONTRACT_ADDRESS_COLLISION: &str = "contract address collision";
+/// Geth error message for max code size exceeded
+pub const GETH_ERR_MAX_CODE_SIZE_EXCEEDED: &str = "max code size exceeded";
+/// Geth error message for invalid jump destination
+pub const GETH_ERR_INVALID_JUMP: &str = "invalid jump destination";
+/// Geth error message for write protection
+pub const GETH_ERR_WRITE_PROTECTION: &str = "write protection";
+/// Geth error message for return data out of bounds
+pub const GETH_ERR_RETURN_DATA_OUT_OF_BOUNDS: &str = "return data out of bounds";
+/// Geth error message for invalid code (EIP-3541)
+pub const GETH_ERR_INVALID_CODE: &str = "invalid code: must not begin with 0xef";
+/// Geth error message for invalid opcode
+pub const GETH_ERR_INVALID_OPCODE: &str = "invalid opcode";
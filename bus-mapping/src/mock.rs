@@ -4,7 +4,7 @@ use crate::{
     circuit_input_builder::{Block, CircuitInputBuilder},
     state_db::{self, CodeDB, StateDB},
 };
-use eth_types::{geth_types::GethData, Word};
+use eth_types::{geth_types::GethData, trie, EIP1186ProofResponse, ToWord, Word};
 
 /// BlockData is a type that contains all the information from a block required
 /// to build the circuit inputs.
@@ -23,21 +23,27 @@ pub struct BlockData {
     pub eth_block: eth_types::Block<eth_types::Transaction>,
     /// Execution Trace from geth
     pub geth_traces: Vec<eth_types::GethExecTrace>,
+    /// State root before the block
+    pub prev_state_root: Word,
+    /// Proofs of all the accounts and storage slots before the block
+    pub state_proofs: Vec<EIP1186ProofResponse>,
 }
 
 impl BlockData {
     /// Generate a new CircuitInputBuilder initialized with the context of the
     /// BlockData.
     pub fn new_circuit_input_builder(&self) -> CircuitInputBuilder {
-        CircuitInputBuilder::new(
-            self.sdb.clone(),
-            self.code_db.clone(),
-            Block::new(self.chain_id, self.history_hashes.clone(), &self.eth_block).unwrap(),
-        )
+        let mut block =
+            Block::new(self.chain_id, self.history_hashes.clone(), &self.eth_block).unwrap();
+        block.prev_state_root = self.prev_state_root;
+        block.state_proofs = self.state_proofs.clone();
+        CircuitInputBuilder::new(self.sdb.clone(), self.code_db.clone(), block)
     }
 
     /// Create a new block from the given Geth data.
     pub fn new_from_geth_data(geth_data: GethData) -> Self {
+        let (prev_state_root, state_proofs) =
+            trie::state_proofs(&geth_data.accounts).expect("tries of the accounts");
         let mut sdb = StateDB::new();
         let mut code_db = CodeDB::new();
 
@@ -69,6 +75,8 @@ impl BlockData {
             history_hashes: geth_data.history_hashes,
             eth_block: geth_data.eth_block,
             geth_traces: geth_data.geth_traces,
+            prev_state_root: prev_state_root.to_word(),
+            state_proofs,
         }
     }
 }
@@ -0,0 +1,85 @@
+//! Bytecode circuit benchmarks
+
+#[cfg(test)]
+mod tests {
+    use crate::bench_params::DEGREE;
+    use ark_std::{end_timer, start_timer};
+    use bus_mapping::evm::OpcodeId;
+    use halo2_proofs::plonk::{create_proof, keygen_pk, keygen_vk, verify_proof, SingleVerifier};
+    use halo2_proofs::{
+        pairing::bn256::{Bn256, Fr, G1Affine},
+        poly::commitment::{Params, ParamsVerifier},
+        transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+    };
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+    use zkevm_circuits::bytecode_circuit::{unroll, BytecodeCircuit};
+
+    #[cfg_attr(not(feature = "benches"), ignore)]
+    #[test]
+    fn bench_bytecode_circuit_prover() {
+        let size = 1 << DEGREE;
+        let randomness = Fr::from(123456);
+
+        // Fill the circuit with a single bytecode made of PUSH32 instructions,
+        // leaving room for its Length row.
+        let num_bytes = size - BytecodeCircuit::<Fr>::min_num_rows(&[]) - 1;
+        let bytecodes = vec![unroll(
+            vec![OpcodeId::PUSH32.as_u8(); num_bytes],
+            randomness,
+        )];
+        let circuit = BytecodeCircuit::new(bytecodes, size, randomness);
+        let instance = circuit.instance();
+        let instance_slices: Vec<&[Fr]> = instance.iter().map(|v| &v[..]).collect();
+
+        let rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        // Bench setup generation
+        let setup_message = format!("Setup generation with degree = {}", DEGREE);
+        let start1 = start_timer!(|| setup_message);
+        let general_params: Params<G1Affine> =
+            Params::<G1Affine>::unsafe_setup::<Bn256>(DEGREE.try_into().unwrap());
+        let verifier_params: ParamsVerifier<Bn256> =
+            general_params.verifier(instance[0].len()).unwrap();
+        end_timer!(start1);
+
+        // Initialize the proving key
+        let vk = keygen_vk(&general_params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&general_params, vk, &circuit).expect("keygen_pk should not fail");
+        // Create a proof
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+
+        // Bench proof generation time
+        let proof_message = format!("Bytecode Proof generation with {} degree", DEGREE);
+        let start2 = start_timer!(|| proof_message);
+        create_proof(
+            &general_params,
+            &pk,
+            &[circuit],
+            &[&instance_slices[..]],
+            rng,
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        let proof = transcript.finalize();
+        end_timer!(start2);
+
+        // Bench verification time
+        let start3 = start_timer!(|| "Bytecode Proof verification");
+        let mut verifier_transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        let strategy = SingleVerifier::new(&verifier_params);
+
+        verify_proof(
+            &verifier_params,
+            pk.get_vk(),
+            strategy,
+            &[&instance_slices[..]],
+            &mut verifier_transcript,
+        )
+        .expect("failed to verify bench circuit");
+        end_timer!(start3);
+    }
+}
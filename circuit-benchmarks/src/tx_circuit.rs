@@ -44,6 +44,7 @@ mod tests {
         // Transaction generated with `zkevm-circuits/src/tx_circuit.rs:rand_tx` using
         // `rng = ChaCha20Rng::seed_from_u64(42)`
         let txs = vec![Transaction {
+            transaction_type: 0,
             from: address!("0x5f9b7e36af4ff81688f712fb738bbbc1b7348aae"),
             to: Some(address!("0x701653d7ae8ddaa5c8cee1ee056849f271827926")),
             nonce: word!("0x3"),
@@ -57,6 +58,7 @@ mod tests {
             v: 2710,
             r: word!("0xaf180d27f90b2b20808bc7670ce0aca862bc2b5fa39c195ab7b1a96225ee14d7"),
             s: word!("0x61159fa4664b698ea7d518526c96cd94cf4d8adf418000754be106a3a133f866"),
+            block_number: 0,
         }];
 
         let randomness = Fr::random(&mut rng);
@@ -5,6 +5,7 @@ use core::convert::TryFrom;
 use core::ops::{Add, AddAssign, Index, IndexMut, Mul, MulAssign, Range, Sub, SubAssign};
 use core::str::FromStr;
 use itertools::Itertools;
+use serde::ser::SerializeSeq;
 use serde::{Serialize, Serializer};
 use std::fmt;
 
@@ -254,8 +255,12 @@ impl Serialize for Memory {
     where
         S: Serializer,
     {
-        let encoded = hex::encode(&self.0);
-        serializer.serialize_str(encoded.as_str())
+        // chunks of 32 bytes in hex, as returned by geth
+        let mut ser = serializer.serialize_seq(Some(self.0.len() / 32))?;
+        for chunk in self.0.chunks(32) {
+            ser.serialize_element(&hex::encode(chunk))?;
+        }
+        ser.end()
     }
 }
 
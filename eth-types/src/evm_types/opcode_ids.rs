@@ -3,12 +3,12 @@ use crate::{error::Error, evm_types::GasCost};
 use core::fmt::Debug;
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::{de, Deserialize, Serialize};
+use serde::{de, Deserialize, Serialize, Serializer};
 use std::fmt;
 use std::str::FromStr;
 
 /// Opcode enum. One-to-one corresponding to an `u8` value.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Hash)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum OpcodeId {
     /// `STOP`
     STOP,
@@ -987,6 +987,21 @@ impl FromStr for OpcodeId {
     }
 }
 
+impl Serialize for OpcodeId {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            // Serialize an invalid opcode value as reported by geth
+            OpcodeId::INVALID(b) => {
+                serializer.serialize_str(&format!("opcode 0x{:x} not defined", b))
+            }
+            _ => serializer.collect_str(self),
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for OpcodeId {
     fn deserialize<D>(deserializer: D) -> Result<OpcodeId, D::Error>
     where
@@ -90,6 +90,9 @@ impl BlockConstants {
 /// Definition of all of the constants related to an Ethereum transaction.
 #[derive(Debug, Default, Clone, Serialize)]
 pub struct Transaction {
+    /// Transaction type (EIP-2718): 0 for legacy, 1 for EIP-2930 and 2 for
+    /// EIP-1559 transactions
+    pub transaction_type: u64,
     /// Sender address
     pub from: Address,
     /// Recipient address (None for contract creation)
@@ -119,25 +122,30 @@ pub struct Transaction {
     pub r: Word,
     /// "s" value of the transaction signature
     pub s: Word,
+
+    /// Number of the block that includes the transaction
+    pub block_number: u64,
 }
 
 impl Transaction {
     /// Create Self from a web3 transaction
     pub fn from_eth_tx(tx: &crate::Transaction) -> Self {
         Self {
+            transaction_type: tx.transaction_type.unwrap_or_default().as_u64(),
             from: tx.from,
             to: tx.to,
             nonce: tx.nonce,
             gas_limit: tx.gas,
             value: tx.value,
             gas_price: tx.gas_price.unwrap_or_default(),
-            gas_fee_cap: tx.max_priority_fee_per_gas.unwrap_or_default(),
-            gas_tip_cap: tx.max_fee_per_gas.unwrap_or_default(),
+            gas_fee_cap: tx.max_fee_per_gas.unwrap_or_default(),
+            gas_tip_cap: tx.max_priority_fee_per_gas.unwrap_or_default(),
             call_data: tx.input.clone(),
             access_list: tx.access_list.clone(),
             v: tx.v.as_u64(),
             r: tx.r,
             s: tx.s,
+            block_number: tx.block_number.unwrap_or_default().as_u64(),
         }
     }
 }
@@ -20,6 +20,7 @@ pub mod error;
 pub mod bytecode;
 pub mod evm_types;
 pub mod geth_types;
+pub mod trie;
 
 pub use bytecode::Bytecode;
 pub use error::Error;
@@ -194,7 +195,7 @@ impl<F: Field> ToScalar<F> for Address {
 }
 
 /// Struct used to define the storage proof
-#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StorageProof {
     /// Storage key
     pub key: U256,
@@ -205,7 +206,7 @@ pub struct StorageProof {
 }
 
 /// Struct used to define the result of `eth_getProof` call
-#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EIP1186ProofResponse {
     /// Account address
@@ -254,6 +255,7 @@ pub struct GethExecStep {
     pub pc: ProgramCounter,
     pub op: OpcodeId,
     pub gas: Gas,
+    #[serde(rename = "gasCost")]
     pub gas_cost: GasCost,
     pub refund: Gas,
     pub depth: u16,
@@ -471,6 +473,13 @@ mod tests {
         "#;
         let trace: GethExecTrace =
             serde_json::from_str(trace_json).expect("json-deserialize GethExecTrace");
+        // the serialization matches the format returned by geth
+        let trace_json = serde_json::to_string(&trace).expect("json-serialize GethExecTrace");
+        assert_eq!(
+            serde_json::from_str::<GethExecTrace>(&trace_json)
+                .expect("json-deserialize serialized GethExecTrace"),
+            trace
+        );
         assert_eq!(
             trace,
             GethExecTrace {
@@ -0,0 +1,870 @@
+//! Merkle Patricia Tries of the Ethereum state, built from the nodes returned
+//! by `eth_getProof` and updated in memory to compute the state roots after
+//! each change of an account or a storage slot.
+
+use crate::{geth_types, Address, Bytes, EIP1186ProofResponse, StorageProof, H256, U256};
+use ethers_core::utils::keccak256;
+use lazy_static::lazy_static;
+use std::{
+    collections::{HashMap, HashSet},
+    iter,
+};
+
+lazy_static! {
+    /// Root of an empty trie, the hash of the RLP encoding of an empty string
+    pub static ref EMPTY_TRIE_ROOT: H256 = keccak(&rlp_string(&[]));
+    /// Hash of an empty code
+    pub static ref EMPTY_CODE_HASH: H256 = keccak(&[]);
+}
+
+/// Error found while walking or updating a [`PartialTrie`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrieError {
+    /// A node in the path of a key is not in the proofs
+    MissingNode(H256),
+    /// A node is not a canonical RLP encoded trie node
+    InvalidNode(H256),
+    /// The value found in the trie is not the one expected by the update
+    ValueMismatch,
+    /// The root after an update is not the one expected by the update
+    RootMismatch,
+    /// The update needs a feature that is not supported
+    Unsupported(&'static str),
+}
+
+/// Keccak hash of `data`
+pub fn keccak(data: &[u8]) -> H256 {
+    H256(keccak256(data))
+}
+
+/// Nibbles of a hashed key, most significant first
+pub fn key_nibbles(key: H256) -> Vec<u8> {
+    key.as_bytes()
+        .iter()
+        .flat_map(|byte| [byte >> 4, byte & 0xf])
+        .collect()
+}
+
+/// Big-endian bytes of `value` without leading zeros
+pub fn minimal_be_bytes(value: U256) -> Vec<u8> {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes[32 - (value.bits() + 7) / 8..].to_vec()
+}
+
+/// RLP encoding of a byte string
+pub fn rlp_string(bytes: &[u8]) -> Vec<u8> {
+    match bytes.len() {
+        1 if bytes[0] < 0x80 => bytes.to_vec(),
+        len if len < 56 => iter::once(0x80 + len as u8)
+            .chain(bytes.iter().copied())
+            .collect(),
+        len => {
+            let len_bytes = minimal_be_bytes(U256::from(len));
+            iter::once(0xb7 + len_bytes.len() as u8)
+                .chain(len_bytes)
+                .chain(bytes.iter().copied())
+                .collect()
+        }
+    }
+}
+
+/// RLP header of a list with a payload of `len` bytes
+pub fn rlp_list_header(len: usize) -> Vec<u8> {
+    if len < 56 {
+        vec![0xc0 + len as u8]
+    } else {
+        let len_bytes = minimal_be_bytes(U256::from(len));
+        iter::once(0xf7 + len_bytes.len() as u8)
+            .chain(len_bytes)
+            .collect()
+    }
+}
+
+fn be_usize(bytes: &[u8]) -> Option<usize> {
+    bytes.iter().try_fold(0usize, |acc, byte| {
+        acc.checked_mul(256)?.checked_add(*byte as usize)
+    })
+}
+
+/// Decode the RLP item at the start of `data`, returning whether it's a list,
+/// its payload and the bytes that follow it.
+fn rlp_item(data: &[u8]) -> Option<(bool, &[u8], &[u8])> {
+    let (&prefix, rest) = data.split_first()?;
+    let (is_list, offset, len) = match prefix {
+        0x00..=0x7f => return Some((false, &data[..1], rest)),
+        0x80..=0xb7 => (false, 0, (prefix - 0x80) as usize),
+        0xb8..=0xbf => {
+            let n = (prefix - 0xb7) as usize;
+            (false, n, be_usize(rest.get(..n)?)?)
+        }
+        0xc0..=0xf7 => (true, 0, (prefix - 0xc0) as usize),
+        0xf8..=0xff => {
+            let n = (prefix - 0xf7) as usize;
+            (true, n, be_usize(rest.get(..n)?)?)
+        }
+    };
+    let end = offset.checked_add(len)?;
+    Some((is_list, rest.get(offset..end)?, &rest[end..]))
+}
+
+/// An item of an RLP list: whether it's a list, its payload and its encoding
+type RlpListItem<'a> = (bool, &'a [u8], &'a [u8]);
+
+/// Decode the items of the RLP list `data`
+fn rlp_list_items(data: &[u8]) -> Option<Vec<RlpListItem>> {
+    let (is_list, mut payload, rest) = rlp_item(data)?;
+    if !is_list || !rest.is_empty() {
+        return None;
+    }
+    let mut items = vec![];
+    while !payload.is_empty() {
+        let (is_list, item, rest) = rlp_item(payload)?;
+        items.push((is_list, item, &payload[..payload.len() - rest.len()]));
+        payload = rest;
+    }
+    Some(items)
+}
+
+/// Hex-prefix encoding of the key of a leaf or an extension node
+pub fn compact_key(key: &[u8], is_leaf: bool) -> Vec<u8> {
+    let is_odd = key.len() % 2;
+    let flag = 0x20 * is_leaf as u8 + if is_odd == 1 { 0x10 + key[0] } else { 0x00 };
+    iter::once(flag)
+        .chain(key[is_odd..].chunks(2).map(|pair| pair[0] << 4 | pair[1]))
+        .collect()
+}
+
+/// Decode a hex-prefix encoded key, returning its nibbles and whether it's the
+/// key of a leaf.
+fn decode_compact_key(compact: &[u8]) -> Option<(Vec<u8>, bool)> {
+    let (&flag, rest) = compact.split_first()?;
+    let (is_leaf, is_odd) = match flag >> 4 {
+        0 => (false, false),
+        1 => (false, true),
+        2 => (true, false),
+        3 => (true, true),
+        _ => return None,
+    };
+    let mut key = match is_odd {
+        true => vec![flag & 0xf],
+        false if flag & 0xf == 0 => vec![],
+        false => return None,
+    };
+    key.extend(rest.iter().flat_map(|byte| [byte >> 4, byte & 0xf]));
+    Some((key, is_leaf))
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// Reference to a node in its parent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NodeRef {
+    /// No node
+    Empty,
+    /// Node whose encoding has at least 32 bytes, referenced by its hash
+    Hash(H256),
+    /// Node whose encoding has less than 32 bytes, embedded in its parent
+    Embedded(Box<TrieNode>),
+}
+
+impl Default for NodeRef {
+    fn default() -> Self {
+        Self::Empty
+    }
+}
+
+impl NodeRef {
+    fn decode(hash: H256, (is_list, payload, item): RlpListItem) -> Result<Self, TrieError> {
+        match (is_list, payload.len()) {
+            (false, 0) => Ok(Self::Empty),
+            (false, 32) => Ok(Self::Hash(H256::from_slice(payload))),
+            (true, _) if item.len() < 32 => {
+                TrieNode::decode(hash, item).map(|node| Self::Embedded(Box::new(node)))
+            }
+            _ => Err(TrieError::InvalidNode(hash)),
+        }
+    }
+
+    /// RLP item of the reference in the encoding of the parent
+    pub fn item(&self) -> Vec<u8> {
+        match self {
+            Self::Empty => rlp_string(&[]),
+            Self::Hash(hash) => rlp_string(hash.as_bytes()),
+            Self::Embedded(node) => node.encode(),
+        }
+    }
+}
+
+/// Node of a Merkle Patricia Trie whose keys are hashes, so that branch nodes
+/// never hold a value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrieNode {
+    /// Branch node with its 16 children
+    Branch([NodeRef; 16]),
+    /// Extension node with the nibbles it consumes and its child, a branch
+    Extension {
+        /// Nibbles consumed by the node
+        key: Vec<u8>,
+        /// Branch node following the node
+        child: NodeRef,
+    },
+    /// Leaf node with the nibbles of the key not consumed by its ancestors
+    Leaf {
+        /// Nibbles of the key left to the leaf
+        key: Vec<u8>,
+        /// Value stored in the leaf
+        value: Vec<u8>,
+    },
+}
+
+impl TrieNode {
+    /// Decode the node with hash `hash`, which is used to report errors
+    pub fn decode(hash: H256, data: &[u8]) -> Result<Self, TrieError> {
+        let invalid = || TrieError::InvalidNode(hash);
+        let items = rlp_list_items(data).ok_or_else(invalid)?;
+        let node = match items.len() {
+            17 => {
+                let mut children: [NodeRef; 16] = Default::default();
+                for (child, item) in children.iter_mut().zip(&items) {
+                    *child = NodeRef::decode(hash, *item)?;
+                }
+                // A branch with a single child would be an extension or a leaf.
+                if items[16] != (false, &[][..], &[0x80][..])
+                    || children
+                        .iter()
+                        .filter(|child| **child != NodeRef::Empty)
+                        .count()
+                        < 2
+                {
+                    return Err(invalid());
+                }
+                Self::Branch(children)
+            }
+            2 => {
+                let (key_is_list, compact, _) = items[0];
+                if key_is_list {
+                    return Err(invalid());
+                }
+                let (key, is_leaf) = decode_compact_key(compact).ok_or_else(invalid)?;
+                if is_leaf {
+                    let (value_is_list, value, _) = items[1];
+                    if value_is_list {
+                        return Err(invalid());
+                    }
+                    Self::Leaf {
+                        key,
+                        value: value.to_vec(),
+                    }
+                } else {
+                    let child = NodeRef::decode(hash, items[1])?;
+                    if key.is_empty() || child == NodeRef::Empty {
+                        return Err(invalid());
+                    }
+                    Self::Extension { key, child }
+                }
+            }
+            _ => return Err(invalid()),
+        };
+        if node.encode() != data {
+            return Err(invalid());
+        }
+        Ok(node)
+    }
+
+    /// RLP encoded items of the node: 17 for a branch (the last one being the
+    /// empty value) and 2 for an extension or a leaf.
+    pub fn items(&self) -> Vec<Vec<u8>> {
+        match self {
+            Self::Branch(children) => children
+                .iter()
+                .map(NodeRef::item)
+                .chain(iter::once(rlp_string(&[])))
+                .collect(),
+            Self::Extension { key, child } => {
+                vec![rlp_string(&compact_key(key, false)), child.item()]
+            }
+            Self::Leaf { key, value } => {
+                vec![rlp_string(&compact_key(key, true)), rlp_string(value)]
+            }
+        }
+    }
+
+    /// RLP encoding of the node
+    pub fn encode(&self) -> Vec<u8> {
+        let items = self.items().concat();
+        [rlp_list_header(items.len()), items].concat()
+    }
+
+    /// Hash of the node
+    pub fn hash(&self) -> H256 {
+        keccak(&self.encode())
+    }
+}
+
+/// Account stored in the leaves of the state trie
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Account {
+    /// Nonce
+    pub nonce: U256,
+    /// Balance
+    pub balance: U256,
+    /// Root of the storage trie
+    pub storage_root: H256,
+    /// Hash of the code
+    pub code_hash: H256,
+}
+
+impl Default for Account {
+    fn default() -> Self {
+        Self {
+            nonce: U256::zero(),
+            balance: U256::zero(),
+            storage_root: *EMPTY_TRIE_ROOT,
+            code_hash: *EMPTY_CODE_HASH,
+        }
+    }
+}
+
+impl Account {
+    /// Decode the value of a leaf of the state trie
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let items = rlp_list_items(data)?;
+        if items.len() != 4 || items.iter().any(|(is_list, _, _)| *is_list) {
+            return None;
+        }
+        let integer = |item: &[u8]| (item.len() <= 32).then(|| U256::from_big_endian(item));
+        let hash = |item: &[u8]| (item.len() == 32).then(|| H256::from_slice(item));
+        let account = Self {
+            nonce: integer(items[0].1)?,
+            balance: integer(items[1].1)?,
+            storage_root: hash(items[2].1)?,
+            code_hash: hash(items[3].1)?,
+        };
+        (account.encode() == data).then(|| account)
+    }
+
+    /// Whether the account is empty as defined in EIP-161, in which case it's
+    /// not in the state trie.
+    pub fn is_empty(&self) -> bool {
+        self.nonce.is_zero() && self.balance.is_zero() && self.code_hash == *EMPTY_CODE_HASH
+    }
+
+    /// RLP encoded fields of the account
+    pub fn items(&self) -> [Vec<u8>; 4] {
+        [
+            rlp_string(&minimal_be_bytes(self.nonce)),
+            rlp_string(&minimal_be_bytes(self.balance)),
+            rlp_string(self.storage_root.as_bytes()),
+            rlp_string(self.code_hash.as_bytes()),
+        ]
+    }
+
+    /// RLP encoding of the account, the value of its leaf in the state trie
+    pub fn encode(&self) -> Vec<u8> {
+        let items = self.items().concat();
+        [rlp_list_header(items.len()), items].concat()
+    }
+}
+
+/// Value of the leaf of a storage slot in a storage trie, or `None` for a zero
+/// value, which is not in the trie.
+pub fn storage_leaf_value(value: U256) -> Option<Vec<u8>> {
+    (!value.is_zero()).then(|| rlp_string(&minimal_be_bytes(value)))
+}
+
+/// Decode the value stored in a leaf of a storage trie
+pub fn decode_storage_value(data: &[u8]) -> Option<U256> {
+    let (is_list, payload, rest) = rlp_item(data)?;
+    (!is_list
+        && rest.is_empty()
+        && payload.len() <= 32
+        && storage_leaf_value(U256::from_big_endian(payload)).as_deref() == Some(data))
+    .then(|| U256::from_big_endian(payload))
+}
+
+/// Trie nodes indexed by their hash, as found in the proofs of
+/// `eth_getProof`.  Nodes of the state trie and of the storage tries are
+/// stored together.
+#[derive(Clone, Debug, Default)]
+pub struct PartialTrie {
+    nodes: HashMap<H256, Vec<u8>>,
+    /// Hashes of the children of extension nodes, which are branch nodes
+    branches: HashSet<H256>,
+}
+
+impl PartialTrie {
+    /// Add the RLP encoded nodes `nodes`.  Leaves and extension nodes are also
+    /// added without their first nibbles, as the node of the sibling of a
+    /// deleted key is only found merged with the nibbles of its ancestors in
+    /// the proofs of the trie after the deletion.
+    pub fn insert_nodes<'a>(&mut self, nodes: impl IntoIterator<Item = &'a Bytes>) {
+        for node in nodes {
+            let hash = keccak(node);
+            if let Ok(node) = TrieNode::decode(hash, node) {
+                let suffixes = match &node {
+                    TrieNode::Branch(_) => vec![],
+                    TrieNode::Extension { key, child } => {
+                        if let NodeRef::Hash(child) = child {
+                            self.branches.insert(*child);
+                        }
+                        (1..key.len())
+                            .map(|start| TrieNode::Extension {
+                                key: key[start..].to_vec(),
+                                child: child.clone(),
+                            })
+                            .collect()
+                    }
+                    TrieNode::Leaf { key, value } => (1..=key.len())
+                        .map(|start| TrieNode::Leaf {
+                            key: key[start..].to_vec(),
+                            value: value.clone(),
+                        })
+                        .collect(),
+                };
+                for suffix in suffixes {
+                    let data = suffix.encode();
+                    self.nodes.entry(keccak(&data)).or_insert(data);
+                }
+            }
+            self.nodes.insert(hash, node.to_vec());
+        }
+    }
+
+    /// Add the nodes of the account and storage proofs in `proofs`
+    pub fn insert_proofs(&mut self, proofs: &[EIP1186ProofResponse]) {
+        for proof in proofs {
+            self.insert_nodes(&proof.account_proof);
+            for storage_proof in &proof.storage_proof {
+                self.insert_nodes(&storage_proof.proof);
+            }
+        }
+    }
+
+    fn node(&self, hash: H256) -> Result<TrieNode, TrieError> {
+        let data = self.nodes.get(&hash).ok_or(TrieError::MissingNode(hash))?;
+        TrieNode::decode(hash, data)
+    }
+
+    fn resolve(&self, node: &NodeRef) -> Result<Option<TrieNode>, TrieError> {
+        match node {
+            NodeRef::Empty => Ok(None),
+            NodeRef::Hash(hash) => self.node(*hash).map(Some),
+            NodeRef::Embedded(node) => Ok(Some(*node.clone())),
+        }
+    }
+
+    fn root_node(&self, root: H256) -> Result<Option<TrieNode>, TrieError> {
+        match root == *EMPTY_TRIE_ROOT {
+            true => Ok(None),
+            false => self.node(root).map(Some),
+        }
+    }
+
+    /// Store `node`, returning its reference in its parent
+    fn store(&mut self, node: TrieNode) -> NodeRef {
+        let data = node.encode();
+        if data.len() < 32 {
+            NodeRef::Embedded(Box::new(node))
+        } else {
+            let hash = keccak(&data);
+            self.nodes.insert(hash, data);
+            NodeRef::Hash(hash)
+        }
+    }
+
+    /// Nodes in the path of the hashed key `key` in the trie with root `root`,
+    /// from the root to the node where the search of the key ends: its leaf,
+    /// the leaf or the extension node of other keys, or the branch node without
+    /// a child for it.  The path of an empty trie has no node.
+    pub fn path(&self, root: H256, key: H256) -> Result<Vec<TrieNode>, TrieError> {
+        let nibbles = key_nibbles(key);
+        let mut path = vec![];
+        let mut depth = 0;
+        let mut node = self.root_node(root)?;
+        while let Some(current) = node {
+            node = match &current {
+                TrieNode::Branch(children) => {
+                    let nibble = nibbles.get(depth).ok_or(TrieError::InvalidNode(root))?;
+                    depth += 1;
+                    self.resolve(&children[*nibble as usize])?
+                }
+                TrieNode::Extension { key, child } if nibbles[depth..].starts_with(key) => {
+                    depth += key.len();
+                    self.resolve(child)?
+                }
+                _ => None,
+            };
+            path.push(current);
+        }
+        Ok(path)
+    }
+
+    /// Value stored for the hashed key `key` in the trie with root `root`
+    pub fn get(&self, root: H256, key: H256) -> Result<Option<Vec<u8>>, TrieError> {
+        let nibbles = key_nibbles(key);
+        let path = self.path(root, key)?;
+        let depth: usize = path
+            .iter()
+            .map(|node| match node {
+                TrieNode::Branch(_) => 1,
+                TrieNode::Extension { key, .. } => key.len(),
+                TrieNode::Leaf { .. } => 0,
+            })
+            .sum();
+        Ok(match path.last() {
+            Some(TrieNode::Leaf { key, value }) if nibbles.get(depth..) == Some(&key[..]) => {
+                Some(value.clone())
+            }
+            _ => None,
+        })
+    }
+
+    /// RLP encoded nodes in the path of the hashed key `key`, as in the proofs
+    /// of `eth_getProof`: nodes embedded in their parent are left out.
+    pub fn proof(&self, root: H256, key: H256) -> Result<Vec<Bytes>, TrieError> {
+        Ok(self
+            .path(root, key)?
+            .iter()
+            .enumerate()
+            .map(|(depth, node)| (depth, node.encode()))
+            .filter(|(depth, data)| *depth == 0 || data.len() >= 32)
+            .map(|(_, data)| data.into())
+            .collect())
+    }
+
+    /// Set the value of the hashed key `key` in the trie with root `root`,
+    /// inserting, updating or deleting (for `None`) its leaf.  Return the root
+    /// of the new trie, whose nodes are added.
+    pub fn set(
+        &mut self,
+        root: H256,
+        key: H256,
+        value: Option<Vec<u8>>,
+    ) -> Result<H256, TrieError> {
+        let node = self.root_node(root)?;
+        // The root node is referenced by its hash, even when shorter than 32
+        // bytes.
+        Ok(
+            match self.set_node(node, &key_nibbles(key), value.as_deref())? {
+                Some(node) => {
+                    let data = node.encode();
+                    let hash = keccak(&data);
+                    self.nodes.insert(hash, data);
+                    hash
+                }
+                None => *EMPTY_TRIE_ROOT,
+            },
+        )
+    }
+
+    /// Set the value of the key whose nibbles not consumed by the ancestors of
+    /// `node` are `key`, returning the node replacing it.
+    fn set_node(
+        &mut self,
+        node: Option<TrieNode>,
+        key: &[u8],
+        value: Option<&[u8]>,
+    ) -> Result<Option<TrieNode>, TrieError> {
+        let leaf = |key: &[u8], value: &[u8]| TrieNode::Leaf {
+            key: key.to_vec(),
+            value: value.to_vec(),
+        };
+        Ok(match node {
+            None => value.map(|value| leaf(key, value)),
+            Some(TrieNode::Leaf {
+                key: leaf_key,
+                value: leaf_value,
+            }) => match value {
+                _ if leaf_key == key => value.map(|value| leaf(key, value)),
+                // The key isn't in the trie, which doesn't change.
+                None => Some(leaf(&leaf_key, &leaf_value)),
+                Some(value) => {
+                    let common = common_prefix_len(&leaf_key, key);
+                    let mut children: [NodeRef; 16] = Default::default();
+                    children[leaf_key[common] as usize] =
+                        self.store(leaf(&leaf_key[common + 1..], &leaf_value));
+                    children[key[common] as usize] = self.store(leaf(&key[common + 1..], value));
+                    Some(self.prepend(&key[..common], TrieNode::Branch(children)))
+                }
+            },
+            Some(TrieNode::Extension {
+                key: extension_key,
+                child,
+            }) => {
+                if key.starts_with(&extension_key) {
+                    let child = self.resolve(&child)?;
+                    let new_child = self.set_node(child, &key[extension_key.len()..], value)?;
+                    new_child.map(|node| self.prepend(&extension_key, node))
+                } else if let Some(value) = value {
+                    let common = common_prefix_len(&extension_key, key);
+                    let mut children: [NodeRef; 16] = Default::default();
+                    children[extension_key[common] as usize] =
+                        match extension_key.len() > common + 1 {
+                            true => self.store(TrieNode::Extension {
+                                key: extension_key[common + 1..].to_vec(),
+                                child,
+                            }),
+                            false => child,
+                        };
+                    children[key[common] as usize] = self.store(leaf(&key[common + 1..], value));
+                    Some(self.prepend(&key[..common], TrieNode::Branch(children)))
+                } else {
+                    Some(TrieNode::Extension {
+                        key: extension_key,
+                        child,
+                    })
+                }
+            }
+            Some(TrieNode::Branch(mut children)) => {
+                let nibble = *key
+                    .first()
+                    .ok_or(TrieError::Unsupported("key shorter than its path"))?
+                    as usize;
+                let child = self.resolve(&children[nibble])?;
+                children[nibble] = match self.set_node(child, &key[1..], value)? {
+                    Some(node) => self.store(node),
+                    None => NodeRef::Empty,
+                };
+                let mut slots = (0..16).filter(|slot| children[*slot] != NodeRef::Empty);
+                match (slots.next(), slots.next()) {
+                    // A branch left with a single child is merged with it, unless
+                    // it's a branch node, which is only referenced by an
+                    // extension node.
+                    (Some(slot), None) => match &children[slot] {
+                        NodeRef::Hash(hash)
+                            if self.branches.contains(hash) && !self.nodes.contains_key(hash) =>
+                        {
+                            Some(TrieNode::Extension {
+                                key: vec![slot as u8],
+                                child: children[slot].clone(),
+                            })
+                        }
+                        child => {
+                            let child = self
+                                .resolve(child)?
+                                .expect("child of a branch is not empty");
+                            Some(self.prepend(&[slot as u8], child))
+                        }
+                    },
+                    _ => Some(TrieNode::Branch(children)),
+                }
+            }
+        })
+    }
+
+    /// Node consuming the nibbles `prefix` before `node`
+    fn prepend(&mut self, prefix: &[u8], node: TrieNode) -> TrieNode {
+        if prefix.is_empty() {
+            return node;
+        }
+        match node {
+            TrieNode::Branch(_) => TrieNode::Extension {
+                key: prefix.to_vec(),
+                child: self.store(node),
+            },
+            TrieNode::Extension { key, child } => TrieNode::Extension {
+                key: [prefix, &key].concat(),
+                child,
+            },
+            TrieNode::Leaf { key, value } => TrieNode::Leaf {
+                key: [prefix, &key].concat(),
+                value,
+            },
+        }
+    }
+}
+
+/// Root of the state trie holding `accounts`, and the results of
+/// `eth_getProof` for each one of them and all their storage slots.  Empty
+/// accounts are left out of the trie.
+pub fn state_proofs(
+    accounts: &[geth_types::Account],
+) -> Result<(H256, Vec<EIP1186ProofResponse>), TrieError> {
+    let mut trie = PartialTrie::default();
+    let mut root = *EMPTY_TRIE_ROOT;
+    let mut leaves: Vec<(Address, Account)> = vec![];
+    for account in accounts {
+        let mut storage_root = *EMPTY_TRIE_ROOT;
+        for (key, value) in &account.storage {
+            storage_root = trie.set(
+                storage_root,
+                keccak(&key.to_be_bytes()),
+                storage_leaf_value(*value),
+            )?;
+        }
+        let mut leaf = Account {
+            nonce: account.nonce,
+            balance: account.balance,
+            storage_root,
+            code_hash: keccak(&account.code),
+        };
+        if leaf.is_empty() {
+            leaf = Account::default();
+        } else {
+            root = trie.set(
+                root,
+                keccak(account.address.as_bytes()),
+                Some(leaf.encode()),
+            )?;
+        }
+        leaves.push((account.address, leaf));
+    }
+
+    let proofs = accounts
+        .iter()
+        .zip(leaves)
+        .map(|(account, (address, leaf))| {
+            let storage_proof = account
+                .storage
+                .iter()
+                .map(|(key, value)| {
+                    Ok(StorageProof {
+                        key: *key,
+                        value: *value,
+                        proof: trie.proof(leaf.storage_root, keccak(&key.to_be_bytes()))?,
+                    })
+                })
+                .collect::<Result<_, TrieError>>()?;
+            Ok(EIP1186ProofResponse {
+                address,
+                balance: leaf.balance,
+                code_hash: leaf.code_hash,
+                nonce: leaf.nonce,
+                storage_hash: leaf.storage_root,
+                account_proof: trie.proof(root, keccak(address.as_bytes()))?,
+                storage_proof,
+            })
+        })
+        .collect::<Result<_, TrieError>>()?;
+    Ok((root, proofs))
+}
+
+/// Root of the trie whose root node is the first one of `proof`, a path as
+/// returned by `eth_getProof`.
+pub fn proof_root(proof: &[Bytes]) -> H256 {
+    proof
+        .first()
+        .map(|root| keccak(root))
+        .unwrap_or(*EMPTY_TRIE_ROOT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(i: u64) -> H256 {
+        keccak(&U256::from(i).to_be_bytes())
+    }
+
+    fn value(i: u64) -> Option<Vec<u8>> {
+        storage_leaf_value(U256::from(i) * 1000)
+    }
+
+    fn trie(keys: impl IntoIterator<Item = u64>) -> (PartialTrie, H256) {
+        let mut trie = PartialTrie::default();
+        let mut root = *EMPTY_TRIE_ROOT;
+        for i in keys {
+            root = trie.set(root, key(i), value(i)).unwrap();
+        }
+        (trie, root)
+    }
+
+    #[test]
+    fn empty_trie_root() {
+        assert_eq!(
+            format!("{:?}", *EMPTY_TRIE_ROOT),
+            "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+        );
+    }
+
+    #[test]
+    fn root_is_independent_of_insertion_order() {
+        let (_, root) = trie(1..=100);
+        let (_, reversed_root) = trie((1..=100).rev());
+        assert_eq!(root, reversed_root);
+    }
+
+    #[test]
+    fn get_inserted_values() {
+        let (trie, root) = trie(1..=100);
+        for i in 1..=100 {
+            assert_eq!(trie.get(root, key(i)), Ok(value(i)));
+        }
+        assert_eq!(trie.get(root, key(101)), Ok(None));
+    }
+
+    #[test]
+    fn deletion_reverts_insertion() {
+        let (mut trie, root) = trie(1..=100);
+        for i in (51..=100).rev() {
+            let new_root = trie.set(root, key(i), None).unwrap();
+            assert_eq!(trie.get(new_root, key(i)), Ok(None));
+        }
+        let mut new_root = root;
+        for i in 51..=100 {
+            new_root = trie.set(new_root, key(i), None).unwrap();
+        }
+        assert_eq!(new_root, self::trie(1..=50).1);
+        for i in 1..=50 {
+            new_root = trie.set(new_root, key(i), None).unwrap();
+        }
+        assert_eq!(new_root, *EMPTY_TRIE_ROOT);
+    }
+
+    #[test]
+    fn deletion_from_proofs() {
+        let (full_trie, root) = trie(1..=100);
+        let mut after = full_trie.clone();
+        let new_root = after.set(root, key(42), None).unwrap();
+
+        // The proofs of the deleted key before and after the deletion are
+        // enough to delete it.
+        let mut partial_trie = PartialTrie::default();
+        partial_trie.insert_nodes(&full_trie.proof(root, key(42)).unwrap());
+        partial_trie.insert_nodes(&after.proof(new_root, key(42)).unwrap());
+        assert_eq!(partial_trie.set(root, key(42), None), Ok(new_root));
+    }
+
+    #[test]
+    fn embedded_leaves() {
+        // Leaves with short values are embedded in their parent when deep
+        // enough in the trie.
+        let leaf = TrieNode::Leaf {
+            key: vec![1; 4],
+            value: vec![1],
+        };
+        let mut children: [NodeRef; 16] = Default::default();
+        children[0] = NodeRef::Embedded(Box::new(leaf.clone()));
+        children[1] = NodeRef::Embedded(Box::new(leaf));
+        let branch = TrieNode::Branch(children);
+        let data = branch.encode();
+        assert_eq!(TrieNode::decode(keccak(&data), &data), Ok(branch));
+    }
+
+    #[test]
+    fn invalid_nodes() {
+        let leaf = TrieNode::Leaf {
+            key: vec![1; 64],
+            value: vec![1],
+        };
+        // Branch with a single child
+        let mut children: [NodeRef; 16] = Default::default();
+        children[0] = NodeRef::Hash(leaf.hash());
+        let data = TrieNode::Branch(children).encode();
+        assert_eq!(
+            TrieNode::decode(keccak(&data), &data),
+            Err(TrieError::InvalidNode(keccak(&data)))
+        );
+        // Leaf with a non canonical RLP header
+        let mut data = leaf.encode();
+        let payload_len = data[0] - 0xc0;
+        data.splice(0..1, [0xf8, payload_len]);
+        assert_eq!(
+            TrieNode::decode(keccak(&data), &data),
+            Err(TrieError::InvalidNode(keccak(&data)))
+        );
+    }
+}
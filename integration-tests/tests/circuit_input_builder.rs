@@ -21,16 +21,27 @@ async fn test_circuit_input_builder_block(block_num: u64) {
     trace!("AccessSet: {:#?}", access_set);
 
     // 3. Query geth for all accounts, storage keys, and codes from Accesses
+    let post_proofs = cli
+        .get_post_state_proofs(block_num, &access_set)
+        .await
+        .unwrap();
     let (proofs, codes) = cli.get_state(block_num, access_set).await.unwrap();
 
     // 4. Build a partial StateDB from step 3
-    let (state_db, code_db) = cli.build_state_code_db(proofs, codes);
+    let (state_db, code_db) = cli.build_state_code_db(proofs.clone(), codes);
     trace!("StateDB: {:#?}", state_db);
 
     // 5. For each step in TxExecTraces, gen the associated ops and state
     // circuit inputs
     let builder = cli
-        .gen_inputs_from_state(state_db, code_db, &eth_block, &geth_trace)
+        .gen_inputs_from_state(
+            state_db,
+            code_db,
+            &eth_block,
+            &geth_trace,
+            &proofs,
+            &post_proofs,
+        )
         .unwrap();
 
     trace!("CircuitInputBuilder: {:#?}", builder);
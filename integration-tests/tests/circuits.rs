@@ -6,7 +6,7 @@ use halo2_proofs::dev::MockProver;
 use integration_tests::{get_client, log_init, GenDataOutput};
 use lazy_static::lazy_static;
 use log::trace;
-use zkevm_circuits::evm_circuit::witness::RwMap;
+use zkevm_circuits::evm_circuit::witness::{MptUpdates, RwMap};
 use zkevm_circuits::evm_circuit::{
     test::run_test_circuit_complete_fixed_table, witness::block_convert,
 };
@@ -22,7 +22,7 @@ async fn test_evm_circuit_block(block_num: u64) {
     let cli = BuilderClient::new(cli).await.unwrap();
     let builder = cli.gen_inputs(block_num).await.unwrap();
 
-    let block = block_convert(&builder.block, &builder.code_db);
+    let block = block_convert(&builder.block, &builder.code_db).unwrap();
     run_test_circuit_complete_fixed_table(block).expect("evm_circuit verification failed");
 }
 
@@ -52,8 +52,15 @@ async fn test_state_circuit_block(block_num: u64) {
         ..Default::default()
     });
 
+    let updates = MptUpdates::new(
+        &rw_map.table_assignments(),
+        builder.block.prev_state_root,
+        &builder.block.state_proofs,
+    )
+    .unwrap();
+
     let randomness = Fr::rand();
-    let circuit = StateCircuit::<Fr>::new(randomness, rw_map, 1 << 16);
+    let circuit = StateCircuit::<Fr>::new(randomness, rw_map, updates, 1 << 16);
     let power_of_randomness = circuit.instance();
 
     use halo2_proofs::pairing::bn256::Fr as Fp;
@@ -0,0 +1,130 @@
+use env_logger::Env;
+use halo2_proofs::pairing::bn256::Fr;
+use halo2_proofs::plonk::{keygen_vk, Circuit};
+use std::env::var;
+use std::fs::{create_dir_all, File};
+use std::io::Write;
+use std::path::Path;
+
+use prover::circuits::*;
+use prover::compute_proof::gen_block_witness;
+use prover::params::ParamsCache;
+use prover::structs::{CircuitKind, KeyId};
+
+/// Generates the verifying key of `circuit`, with params of its minimal
+/// degree, and writes it to `keys_dir`.
+fn write_vk<C: Circuit<Fr>>(
+    params_cache: &ParamsCache,
+    params_path: &str,
+    keys_dir: &Path,
+    circuit: BlockCircuit<C>,
+) {
+    let (params_path, params) = params_cache
+        .get(params_path, circuit.k)
+        .expect("Failed to load params");
+    let id = KeyId {
+        params_path,
+        k: circuit.k,
+        circuit: circuit.kind,
+        shape: circuit.shape,
+    };
+    println!("Generating verifying key for {:?}", id);
+
+    let vk = keygen_vk(&params, &circuit.circuit).expect("keygen_vk should not fail");
+    let mut buf = Vec::new();
+    vk.write(&mut buf).expect("Failed to write verifying key");
+
+    let path = keys_dir.join(id.vk_file_name());
+    let mut file = File::create(&path).expect("Failed to create file");
+    file.write_all(&buf[..])
+        .expect("Failed to write verifying key to file");
+
+    println!("Written to {}", path.display());
+}
+
+/// This utility generates the verifying keys of the circuits for a block, which
+/// are reused by the prover for blocks of the same shape.
+/// Required environment variables:
+/// - BLOCK_NUM - the block number whose shape the keys are generated for
+/// - RPC_URL - a geth http rpc that supports the debug namespace
+/// - PARAMS_PATH - a path to a file generated with the gen_params tool, or to a
+///   directory of such files
+/// - KEYS_DIR - a path to the directory to write the verifying keys to
+/// Optional environment variables:
+/// - CIRCUITS - a comma separated list of the circuits to generate the keys of,
+///   out of evm, state, tx, bytecode, copy and super. Defaults to all of them
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let block_num: u64 = var("BLOCK_NUM")
+        .expect("BLOCK_NUM env var")
+        .parse()
+        .expect("Cannot parse BLOCK_NUM env var");
+    let rpc_url: String = var("RPC_URL")
+        .expect("RPC_URL env var")
+        .parse()
+        .expect("Cannot parse RPC_URL env var");
+    let params_path: String = var("PARAMS_PATH")
+        .expect("PARAMS_PATH env var")
+        .parse()
+        .expect("Cannot parse PARAMS_PATH env var");
+    let keys_dir: String = var("KEYS_DIR")
+        .expect("KEYS_DIR env var")
+        .parse()
+        .expect("Cannot parse KEYS_DIR env var");
+    let keys_dir = Path::new(&keys_dir);
+    create_dir_all(keys_dir).expect("Failed to create keys directory");
+    let circuits: Vec<CircuitKind> = match var("CIRCUITS") {
+        Ok(circuits) => circuits
+            .split(',')
+            .map(|name| name.trim().parse())
+            .collect::<Result<_, _>>()
+            .expect("Cannot parse CIRCUITS env var"),
+        Err(_) => CircuitKind::ALL.to_vec(),
+    };
+
+    let params_cache = ParamsCache::default();
+
+    let witness = gen_block_witness(&block_num, &rpc_url)
+        .await
+        .expect("gen_block_witness");
+
+    for kind in circuits {
+        match kind {
+            CircuitKind::Evm => {
+                write_vk(&params_cache, &params_path, keys_dir, evm_circuit(&witness))
+            }
+            CircuitKind::State => write_vk(
+                &params_cache,
+                &params_path,
+                keys_dir,
+                state_circuit(&witness),
+            ),
+            CircuitKind::Tx => write_vk(
+                &params_cache,
+                &params_path,
+                keys_dir,
+                tx_circuit(&witness).expect("tx_circuit"),
+            ),
+            CircuitKind::Bytecode => write_vk(
+                &params_cache,
+                &params_path,
+                keys_dir,
+                bytecode_circuit(&witness),
+            ),
+            CircuitKind::Copy => write_vk(
+                &params_cache,
+                &params_path,
+                keys_dir,
+                copy_circuit(&witness),
+            ),
+            CircuitKind::Super => write_vk(
+                &params_cache,
+                &params_path,
+                keys_dir,
+                super_circuit(&witness).expect("super_circuit"),
+            ),
+        }
+    }
+}
@@ -1,30 +1,81 @@
 use halo2_proofs::pairing::bn256::{Bn256, G1Affine};
 use halo2_proofs::poly::commitment::Params;
+use prover::params::{import_ppot, ParamsMetadata, ParamsSource};
 use std::env;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufReader, Write};
+use std::path::Path;
 
 /// This utility supports parameter generation.
 /// Can be invoked with: gen_params <degree> <path to file>
+/// The params are created with `Params::unsafe_setup`, whose secret is
+/// known, and must only be used for testing.
+/// Can be invoked with: gen_params --ppot <path to challenge file> <degree>
+/// <path to file> to import the params from the uncompressed challenge file
+/// of a powers of tau ceremony instead.
+/// The metadata of the params, with their checksum, is written next to them
+/// to <path to file>.json and checked when they are loaded.
+/// Can be invoked with: gen_params --check <path to file> to check the params
+/// against their metadata.
 fn main() {
-    let mut args = env::args();
-    let params_path: String = args.next_back().expect("path to file");
-    let degree: u32 = args
-        .next_back()
-        .expect("degree")
-        .parse::<u32>()
-        .expect("valid number");
-    let mut file = File::create(&params_path).expect("Failed to create file");
-
-    println!("Generating params with degree: {}", degree);
-
-    let general_params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(degree);
-    let mut buf = Vec::new();
-    general_params
-        .write(&mut buf)
-        .expect("Failed to write params");
-    file.write_all(&buf[..])
-        .expect("Failed to write params to file");
-
-    println!("Written to {}", params_path);
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let parse_degree = |degree: &str| degree.parse::<u32>().expect("valid number");
+
+    let (params_path, source) = match args[..] {
+        ["--check", params_path] => {
+            let params_path = Path::new(params_path);
+            let metadata = ParamsMetadata::read(params_path)
+                .expect("Failed to read metadata")
+                .expect("no metadata next to the params");
+            metadata.verify(params_path).expect("invalid params");
+
+            println!("{} matches its metadata: {:?}", params_path.display(), metadata);
+            return;
+        }
+        ["--ppot", ppot_path, degree, params_path] => {
+            let degree = parse_degree(degree);
+            println!("Importing params with degree {} from {}", degree, ppot_path);
+
+            let source = import_ppot(Path::new(ppot_path), degree, Path::new(params_path))
+                .expect("Failed to import params");
+            // checks that the params can be read
+            let params_fs = File::open(params_path).expect("couldn't open params");
+            Params::<G1Affine>::read(&mut BufReader::new(params_fs))
+                .expect("Failed to read imported params");
+            (params_path, source)
+        }
+        [degree, params_path] => {
+            let degree = parse_degree(degree);
+            let mut file = File::create(params_path).expect("Failed to create file");
+
+            println!("Generating params with degree: {}", degree);
+            println!("WARNING: the secret of these params is known, use them for testing only");
+
+            let general_params: Params<G1Affine> =
+                Params::<G1Affine>::unsafe_setup::<Bn256>(degree);
+            let mut buf = Vec::new();
+            general_params
+                .write(&mut buf)
+                .expect("Failed to write params");
+            file.write_all(&buf[..])
+                .expect("Failed to write params to file");
+            (params_path, ParamsSource::UnsafeSetup)
+        }
+        _ => panic!(
+            "usage: gen_params [--ppot <challenge file>] <degree> <path to file> | --check <path to file>"
+        ),
+    };
+
+    let params_path = Path::new(params_path);
+    let metadata = ParamsMetadata::new(params_path, source).expect("Failed to hash params");
+    metadata
+        .write(params_path)
+        .expect("Failed to write metadata");
+
+    println!(
+        "Written to {} with checksum {}",
+        params_path.display(),
+        metadata.sha256
+    );
 }
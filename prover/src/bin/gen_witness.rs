@@ -0,0 +1,40 @@
+use env_logger::Env;
+use std::env::var;
+use std::fs::File;
+use std::io::BufWriter;
+
+use prover::compute_proof::gen_witness_inputs;
+
+/// This utility writes the inputs of the circuits for a block, as queried
+/// from geth, to a json file that prover_cmd can prove without network access
+/// via `prover_cmd --witness <path to file>`.
+/// Required environment variables:
+/// - BLOCK_NUM - the block number to write the inputs of
+/// - RPC_URL - a geth http rpc that supports the debug namespace
+/// - WITNESS_PATH - a path to the file to write the inputs to
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let block_num: u64 = var("BLOCK_NUM")
+        .expect("BLOCK_NUM env var")
+        .parse()
+        .expect("Cannot parse BLOCK_NUM env var");
+    let rpc_url: String = var("RPC_URL")
+        .expect("RPC_URL env var")
+        .parse()
+        .expect("Cannot parse RPC_URL env var");
+    let witness_path: String = var("WITNESS_PATH")
+        .expect("WITNESS_PATH env var")
+        .parse()
+        .expect("Cannot parse WITNESS_PATH env var");
+
+    let inputs = gen_witness_inputs(&block_num, &rpc_url)
+        .await
+        .expect("gen_witness_inputs");
+
+    let file = File::create(&witness_path).expect("Failed to create file");
+    serde_json::to_writer(BufWriter::new(file), &inputs).expect("Failed to write witness");
+
+    println!("Written to {}", witness_path);
+}
@@ -1,42 +1,151 @@
 use env_logger::Env;
-use halo2_proofs::pairing::bn256::G1Affine;
-use halo2_proofs::poly::commitment::Params;
-use std::env::var;
+use std::env::{args, var};
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
 
-use prover::compute_proof::compute_proof;
+use prover::compute_proof::{compute_proof, prove_block, witness_from_inputs};
+use prover::key_cache::{KeyCache, DEFAULT_MAX_KEYS};
+use prover::params::ParamsCache;
+use prover::structs::{CircuitKind, ProofRequestOptions, TaskMessage, TaskState, WitnessInputs};
+
+/// Computes the proofs of the `ProofRequestOptions` read from stdin, one per
+/// line, one after another, until stdin is closed.
+/// Prints the `TaskMessage`s of each task to stdout, one per line.
+/// The params and keys are cached across the tasks.
+async fn run_worker() {
+    let params_cache = ParamsCache::default();
+    let max_keys: usize = var("MAX_KEYS")
+        .map(|max_keys| max_keys.parse().expect("Cannot parse MAX_KEYS env var"))
+        .unwrap_or(DEFAULT_MAX_KEYS);
+    let key_cache = KeyCache::new(var("KEYS_DIR").ok().map(PathBuf::from), max_keys);
+
+    let print = |message: &TaskMessage| {
+        println!(
+            "{}",
+            serde_json::to_string(message).expect("serialize message")
+        )
+    };
+    let progress = |state: TaskState| {
+        print(&TaskMessage::Progress(state));
+        Ok(())
+    };
+    for line in std::io::stdin().lock().lines() {
+        let line = line.expect("Failed to read task");
+        let options: ProofRequestOptions =
+            serde_json::from_str(&line).expect("Failed to read task");
+        let result = compute_proof(
+            &params_cache,
+            &options.param,
+            &key_cache,
+            &progress,
+            &options.block,
+            &options.rpc,
+            &options.circuits,
+            options.seed,
+        )
+        .await
+        .map_err(|err| err.to_string());
+
+        print(&TaskMessage::Result(result));
+    }
+}
 
 /// This command generates and prints the proofs to stdout.
+/// Can be invoked with `prover_cmd --witness <path to file>` to prove the
+/// inputs written by the gen_witness tool instead, without network access.
+/// Invoked with `prover_cmd --worker` by prover_rpcd to compute the proofs of
+/// its tasks in a child process, see `run_worker`. The environment variables
+/// other than KEYS_DIR and MAX_KEYS are ignored then.
 /// Required environment variables:
-/// - BLOCK_NUM - the block number to generate the proof for
-/// - RPC_URL - a geth http rpc that supports the debug namespace
-/// - PARAMS_PATH - a path to a file generated with the gen_params tool
+/// - BLOCK_NUM - the block number to generate the proof for, unless `--witness`
+///   is given
+/// - RPC_URL - a geth http rpc that supports the debug namespace, unless
+///   `--witness` is given
+/// - PARAMS_PATH - a path to a file generated with the gen_params tool, or to a
+///   directory of such files
+/// Optional environment variables:
+/// - KEYS_DIR - a path to a directory of verifying keys generated with the
+///   gen_keys tool
+/// - MAX_KEYS - the number of proving keys kept in memory by a worker. Defaults
+///   to 4
+/// - CIRCUITS - a comma separated list of the circuits to prove, out of evm,
+///   state, tx, bytecode, copy and super. Defaults to evm,state
+/// - PROVER_SEED - a seed for the randomness of the proofs, for reproducible
+///   proofs in tests. The randomness is drawn from the OS if not set
 #[tokio::main]
 async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
-    let block_num: u64 = var("BLOCK_NUM")
-        .expect("BLOCK_NUM env var")
-        .parse()
-        .expect("Cannot parse BLOCK_NUM env var");
-    let rpc_url: String = var("RPC_URL")
-        .expect("RPC_URL env var")
-        .parse()
-        .expect("Cannot parse RPC_URL env var");
+    let mut args = args().skip(1);
+    let witness_path: Option<String> = match args.next().as_deref() {
+        Some("--witness") => Some(args.next().expect("path to witness file")),
+        Some("--worker") => return run_worker().await,
+        Some(arg) => panic!("unknown argument: {}", arg),
+        None => None,
+    };
     let params_path: String = var("PARAMS_PATH")
         .expect("PARAMS_PATH env var")
         .parse()
         .expect("Cannot parse PARAMS_PATH env var");
+    let circuits: Vec<CircuitKind> = match var("CIRCUITS") {
+        Ok(circuits) => circuits
+            .split(',')
+            .map(|name| name.trim().parse())
+            .collect::<Result<_, _>>()
+            .expect("Cannot parse CIRCUITS env var"),
+        Err(_) => ProofRequestOptions::default_circuits(),
+    };
 
-    // load polynomial commitment parameters
-    let params_fs = File::open(&params_path).expect("couldn't open params");
-    let params: Params<G1Affine> =
-        Params::read::<_>(&mut BufReader::new(params_fs)).expect("Failed to read params");
+    let seed: Option<u64> = var("PROVER_SEED")
+        .ok()
+        .map(|seed| seed.parse().expect("Cannot parse PROVER_SEED env var"));
 
-    let result = compute_proof(&params, &block_num, &rpc_url)
-        .await
-        .expect("compute_proof");
+    let params_cache = ParamsCache::default();
+    let key_cache = KeyCache::new(var("KEYS_DIR").ok().map(PathBuf::from), DEFAULT_MAX_KEYS);
+
+    let result = match witness_path {
+        Some(witness_path) => {
+            let witness_fs = File::open(&witness_path).expect("couldn't open witness");
+            let inputs: WitnessInputs = serde_json::from_reader(BufReader::new(witness_fs))
+                .expect("Failed to read witness");
+            let witness = witness_from_inputs(&inputs).expect("witness_from_inputs");
+
+            prove_block(
+                &params_cache,
+                &params_path,
+                &key_cache,
+                &|_| Ok(()),
+                &witness,
+                &circuits,
+                seed,
+            )
+            .expect("prove_block")
+        }
+        None => {
+            let block_num: u64 = var("BLOCK_NUM")
+                .expect("BLOCK_NUM env var")
+                .parse()
+                .expect("Cannot parse BLOCK_NUM env var");
+            let rpc_url: String = var("RPC_URL")
+                .expect("RPC_URL env var")
+                .parse()
+                .expect("Cannot parse RPC_URL env var");
+
+            compute_proof(
+                &params_cache,
+                &params_path,
+                &key_cache,
+                &|_| Ok(()),
+                &block_num,
+                &rpc_url,
+                &circuits,
+                seed,
+            )
+            .await
+            .expect("compute_proof")
+        }
+    };
 
     serde_json::to_writer(std::io::stdout(), &result).expect("serialize and write");
 }
@@ -5,9 +5,15 @@ use hyper::header::HeaderValue;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{Body, Method, Request, Response, Server, StatusCode};
 use std::env::var;
+use std::path::PathBuf;
+use std::time::Duration;
 
+use prover::compute_proof::gen_block_witness;
+use prover::key_cache::{KeyCache, DEFAULT_MAX_KEYS};
 use prover::shared_state::SharedState;
 use prover::structs::*;
+use prover::subprocess::SubprocessConfig;
+use prover::witness::WitnessResponse;
 
 /// sets default headers for CORS requests
 fn set_headers(headers: &mut hyper::HeaderMap, extended: bool) {
@@ -57,15 +63,17 @@ async fn handle_request(
         // returns http 200 if busy else 204.
         // can be used programmatically for e.g. shutting down the instance if no workis being
         // done.
+        // if busy, the body is a `StatusResponse` with the progress of the tasks.
         (&Method::GET, "/status") => {
-            let rw = shared_state.rw.lock().await;
-            let is_busy = rw.pending_tasks > 0 || rw.tasks.iter().any(|e| e.result.is_none());
-            drop(rw);
+            let (is_busy, status) = shared_state.status().await;
 
-            let mut resp = Response::default();
-            *resp.status_mut() = match is_busy {
-                false => StatusCode::NO_CONTENT,
-                true => StatusCode::OK,
+            let mut resp = match is_busy {
+                false => {
+                    let mut resp = Response::default();
+                    *resp.status_mut() = StatusCode::NO_CONTENT;
+                    resp
+                }
+                true => Response::new(Body::from(serde_json::to_vec(&status).unwrap())),
             };
             set_headers(resp.headers_mut(), false);
             Ok(resp)
@@ -149,6 +157,12 @@ async fn handle_method(
             let options = params.get(0).ok_or("expected struct ProofRequestOptions")?;
             let options: ProofRequestOptions =
                 serde_json::from_value(options.to_owned()).map_err(|e| e.to_string())?;
+            if options.seed.is_some() && !shared_state.allow_seed {
+                return Err(
+                    "seed is not allowed, the proofs of a fixed seed aren't zero-knowledge"
+                        .to_string(),
+                );
+            }
 
             shared_state
                 .get_or_enqueue(&options)
@@ -156,33 +170,56 @@ async fn handle_method(
                 .map(|result| serde_json::to_value(result?).map_err(|e| e.to_string()))
                 .unwrap_or_else(|| Ok(serde_json::Value::Null))
         }
-        // TODO/TBD: add method to only return the witnesses for a block.
-        //  block table, tx table, etc...
-        //
-        // TODO: Add the abilitity to abort the current task.
+        // verifies a proof returned by `proof`
+        "verify" => {
+            let options = params
+                .get(0)
+                .ok_or("expected struct VerifyRequestOptions")?;
+            let options: VerifyRequestOptions =
+                serde_json::from_value(options.to_owned()).map_err(|e| e.to_string())?;
+
+            let result = shared_state.verify(&options).await?;
+            serde_json::to_value(result).map_err(|e| e.to_string())
+        }
+        // cancels the task of a `proof` request, returns false if there's
+        // no such task that isn't completed yet
+        "cancel" => {
+            let options = params.get(0).ok_or("expected struct ProofRequestOptions")?;
+            let options: ProofRequestOptions =
+                serde_json::from_value(options.to_owned()).map_err(|e| e.to_string())?;
+
+            Ok(serde_json::Value::Bool(shared_state.cancel(&options).await))
+        }
+        // returns the witness of the circuits for a block along with the
+        // sizes of the circuits, for debugging blocks that fail to prove
+        "witness" => {
+            let options = params
+                .get(0)
+                .ok_or("expected struct WitnessRequestOptions")?;
+            let options: WitnessRequestOptions =
+                serde_json::from_value(options.to_owned()).map_err(|e| e.to_string())?;
+
+            let witness = gen_block_witness(&options.block, &options.rpc)
+                .await
+                .map_err(|e| e.to_string())?;
+            let response = tokio::task::spawn_blocking(move || WitnessResponse::from(&witness))
+                .await
+                .map_err(|e| e.to_string())?;
+            serde_json::to_value(response).map_err(|e| e.to_string())
+        }
 
         // the following methods can be used to programmatically
         // prune the `tasks` from the list.
         "flushAll" => {
-            shared_state.rw.lock().await.tasks.clear();
+            shared_state.flush(|_| true).await;
             Ok(serde_json::Value::Bool(true))
         }
         "flushPending" => {
-            shared_state
-                .rw
-                .lock()
-                .await
-                .tasks
-                .retain(|e| e.result.is_some());
+            shared_state.flush(|e| e.result.is_none()).await;
             Ok(serde_json::Value::Bool(true))
         }
         "flushCompleted" => {
-            shared_state
-                .rw
-                .lock()
-                .await
-                .tasks
-                .retain(|e| e.result.is_none());
+            shared_state.flush(|e| e.result.is_some()).await;
             Ok(serde_json::Value::Bool(true))
         }
         _ => Err("this method is not available".to_string()),
@@ -194,6 +231,24 @@ async fn handle_method(
 /// - BIND - the interface address + port combination to accept connections on
 ///   `[::]:1234`
 /// - PARAMS_PATH - a path to a file generated with the gen_params tool
+/// Optional environment variables:
+/// - KEYS_DIR - a path to a directory of verifying keys generated with the
+///   gen_keys tool
+/// - MAX_KEYS - the number of proving keys, and of verifying keys, kept in
+///   memory. Defaults to 4
+/// - TASKS_PATH - a path to a file the tasks are persisted to, so that they
+///   survive restarts. The proofs of the completed tasks are persisted to the
+///   directory of the same name with the `proofs` extension
+/// - WORKERS - the number of tasks computed at the same time. Defaults to 1
+/// - PROVER_CMD - a path to the prover_cmd binary, which computes the tasks in
+///   up to WORKERS long-lived child processes, each keeping up to MAX_KEYS
+///   proving keys. Defaults to the prover_cmd next to this binary
+/// - TASK_TIMEOUT - the number of seconds after which a task is aborted
+/// - ALLOW_PROVER_SEED - accepts proof requests with a `seed`, for reproducible
+///   proofs in tests. Such proofs aren't zero-knowledge, so the requests are
+///   rejected if not set
+/// - TASK_MEMORY_LIMIT - the number of megabytes of memory a child process,
+///   along with the keys it keeps, may allocate before it's aborted
 #[tokio::main]
 async fn main() {
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
@@ -202,7 +257,37 @@ async fn main() {
         .expect("BIND env var")
         .parse::<std::net::SocketAddr>()
         .expect("valid socket address");
-    let shared_state = SharedState::new();
+    let keys_dir = var("KEYS_DIR").ok().map(PathBuf::from);
+    let max_keys: usize = var("MAX_KEYS")
+        .map(|max_keys| max_keys.parse().expect("Cannot parse MAX_KEYS env var"))
+        .unwrap_or(DEFAULT_MAX_KEYS);
+    let tasks_path = var("TASKS_PATH").ok().map(PathBuf::from);
+    let workers: usize = var("WORKERS")
+        .map(|workers| workers.parse().expect("Cannot parse WORKERS env var"))
+        .unwrap_or(1);
+    let subprocess = SubprocessConfig {
+        prover_cmd: var("PROVER_CMD").map(PathBuf::from).unwrap_or_else(|_| {
+            std::env::current_exe()
+                .expect("path to prover_rpcd")
+                .with_file_name("prover_cmd")
+        }),
+        timeout: var("TASK_TIMEOUT").ok().map(|timeout| {
+            Duration::from_secs(timeout.parse().expect("Cannot parse TASK_TIMEOUT env var"))
+        }),
+        memory_limit: var("TASK_MEMORY_LIMIT").ok().map(|limit| {
+            limit
+                .parse::<u64>()
+                .expect("Cannot parse TASK_MEMORY_LIMIT env var")
+                << 20
+        }),
+    };
+    let mut shared_state = SharedState::new(
+        KeyCache::new(keys_dir, max_keys),
+        tasks_path,
+        workers,
+        Some(subprocess),
+    );
+    shared_state.allow_seed = var("ALLOW_PROVER_SEED").is_ok();
 
     {
         // start the http server
@@ -219,22 +304,29 @@ async fn main() {
             server.await.expect("server should be serving");
         });
 
-        // starts the duty cycle loop
-        let ctx = shared_state.clone();
+        // starts a duty cycle loop per worker
         // use a dedicated runtime for mixed async / heavy (blocking) compute
         let rt = tokio::runtime::Builder::new_multi_thread()
             .enable_all()
             .build()
             .unwrap();
-        let h2 = rt.spawn(async move {
-            let ctx = ctx.clone();
-            loop {
-                ctx.duty_cycle().await;
-                tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
-            }
-        });
+        let workers: Vec<_> = (0..shared_state.workers)
+            .map(|_| {
+                let ctx = shared_state.clone();
+                rt.spawn(async move {
+                    loop {
+                        ctx.duty_cycle().await;
+                        tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
+                    }
+                })
+            })
+            .collect();
 
         // wait for all tasks
-        let _ = tokio::join!(h1, h2);
+        let _ = tokio::join!(h1, async {
+            for worker in workers {
+                let _ = worker.await;
+            }
+        });
     }
 }
@@ -0,0 +1,46 @@
+use env_logger::Env;
+use std::env::var;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+use prover::key_cache::{KeyCache, DEFAULT_MAX_KEYS};
+use prover::params::ParamsCache;
+use prover::structs::CircuitProof;
+use prover::verify_proof::verify_proof;
+
+/// This command verifies a proof and prints the result to stdout, exiting with
+/// a non-zero status if the proof is invalid.
+/// Required environment variables:
+/// - PROOF_PATH - a path to a json file of a proof, like the `evm_proof` or
+///   `state_proof` printed by prover_cmd
+/// - PARAMS_PATH - a path to the file generated with the gen_params tool, or to
+///   the directory of such files, the proof was created with
+/// Optional environment variables:
+/// - KEYS_DIR - a path to a directory of verifying keys generated with the
+///   gen_keys tool
+fn main() {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+
+    let proof_path: String = var("PROOF_PATH")
+        .expect("PROOF_PATH env var")
+        .parse()
+        .expect("Cannot parse PROOF_PATH env var");
+    let params_path: String = var("PARAMS_PATH")
+        .expect("PARAMS_PATH env var")
+        .parse()
+        .expect("Cannot parse PARAMS_PATH env var");
+
+    let proof_fs = File::open(&proof_path).expect("couldn't open proof");
+    let proof: CircuitProof =
+        serde_json::from_reader(BufReader::new(proof_fs)).expect("Failed to read proof");
+
+    let key_cache = KeyCache::new(var("KEYS_DIR").ok().map(PathBuf::from), DEFAULT_MAX_KEYS);
+
+    let result = verify_proof(&ParamsCache::default(), &params_path, &key_cache, &proof);
+
+    serde_json::to_writer(std::io::stdout(), &result).expect("serialize and write");
+    if !result.valid {
+        std::process::exit(1);
+    }
+}
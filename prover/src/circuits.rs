@@ -0,0 +1,674 @@
+use eth_types::geth_types;
+use group::{Curve, Group};
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2_proofs::pairing::bn256::Fr;
+use secp256k1::Secp256k1Affine;
+use std::collections::BTreeMap;
+use strum::IntoEnumIterator;
+use zkevm_circuits::bytecode_circuit::BytecodeCircuit;
+use zkevm_circuits::copy_circuit::{CopyCircuit, StandaloneCopyCircuit};
+use zkevm_circuits::evm_circuit::{
+    table::FixedTableTag,
+    witness::{Block, MptUpdates, RwMap},
+    StandaloneEvmCircuit,
+};
+use zkevm_circuits::keccak_circuit::KeccakCircuit;
+use zkevm_circuits::pi_circuit::PublicData;
+use zkevm_circuits::state_circuit::StateCircuit;
+use zkevm_circuits::super_circuit::{self, SuperCircuit};
+use zkevm_circuits::tx_circuit::{TxCircuit, POW_RAND_SIZE, VERIF_HEIGHT};
+
+use crate::structs::{CircuitKind, PublicInputs};
+
+/// The maximum number of transactions of the Tx Circuit and the Super Circuit
+pub const MAX_TXS: usize = 3;
+/// The maximum number of calldata bytes of the Tx Circuit and the Super
+/// Circuit
+pub const MAX_CALLDATA: usize = 4096;
+/// The degree of the Tx Circuit, which fits the signature verifications of
+/// `MAX_TXS` transactions
+const TX_CIRCUIT_DEGREE: u32 = 19;
+/// The number of rows of the largest fixed range table of the State Circuit
+const STATE_LOOKUPS_ROWS: usize = 1 << 16;
+
+/// The witness of the circuits for a block
+#[derive(Debug, Clone)]
+pub struct BlockWitness {
+    pub block: Block<Fr>,
+    /// the transactions of the block, whose signatures the Tx Circuit verifies
+    pub txs: Vec<geth_types::Transaction>,
+    /// the public data of the block, checked by the PublicInputs Circuit
+    pub public_data: PublicData,
+}
+
+/// A circuit of a block along with what's needed to prove it
+pub struct BlockCircuit<C> {
+    pub kind: CircuitKind,
+    pub circuit: C,
+    /// the capacities of the circuit, see [`KeyId`](crate::structs::KeyId)
+    pub shape: Vec<usize>,
+    /// the minimal degree of the circuit
+    pub k: u32,
+    pub public_inputs: PublicInputs,
+}
+
+fn log2_ceil(n: usize) -> u32 {
+    let n = n.max(1);
+    u32::BITS - (n as u32).leading_zeros() - (n & (n - 1) == 0) as u32
+}
+
+/// Checks that the transactions of `witness` fit in the tx circuit.
+fn check_txs(witness: &BlockWitness) -> Result<(), String> {
+    let calldata_len: usize = witness.txs.iter().map(|tx| tx.call_data.len()).sum();
+    if witness.txs.len() > MAX_TXS || calldata_len > MAX_CALLDATA {
+        return Err(format!(
+            "the tx circuit supports {} txs with {} calldata bytes but the block has {} txs with {} calldata bytes",
+            MAX_TXS,
+            MAX_CALLDATA,
+            witness.txs.len(),
+            calldata_len
+        ));
+    }
+    Ok(())
+}
+
+/// A random auxiliary generator for the ECDSA verification of the Tx Circuit
+fn aux_generator() -> Secp256k1Affine {
+    <Secp256k1Affine as CurveAffine>::CurveExt::random(rand::thread_rng()).to_affine()
+}
+
+/// Returns `block` with the capacities of the EVM circuit rounded up to a
+/// power of two, so that blocks of similar sizes share the same keys.
+fn evm_block(block: &Block<Fr>) -> Block<Fr> {
+    let mut block = block.clone();
+    block.max_txs = block.max_txs.next_power_of_two();
+    block.max_rws = block.max_rws.next_power_of_two();
+    block.max_evm_rows =
+        StandaloneEvmCircuit::<Fr>::get_num_rows_required(&block).next_power_of_two();
+    block
+}
+
+/// Returns the minimal degree of the EVM circuit of `block`, whose
+/// capacities are set.
+fn evm_degree(block: &Block<Fr>) -> u32 {
+    let k = log2_ceil(
+        64 + FixedTableTag::iter()
+            .map(|tag| tag.build::<Fr>().count())
+            .sum::<usize>(),
+    );
+    let k = k.max(log2_ceil(64 + bytecodes_len(block)));
+    let k = k.max(log2_ceil(64 + block.max_evm_rows));
+    k.max(log2_ceil(64 + block.max_rws))
+}
+
+/// Returns the number of rows of the state circuit of `block`, rounded up to
+/// a power of two.
+fn state_num_rows(block: &Block<Fr>) -> usize {
+    StateCircuit::<Fr>::min_num_rows_block(block).next_power_of_two()
+}
+
+/// Returns the minimal degree of the state circuit with `n_rows` rows, which
+/// also fits its fixed range tables.
+fn state_degree(n_rows: usize) -> u32 {
+    log2_ceil(64 + n_rows.max(STATE_LOOKUPS_ROWS))
+}
+
+/// Returns the size of the bytecode circuit of `block`, rounded up to a
+/// power of two.
+fn bytecode_size(block: &Block<Fr>) -> usize {
+    BytecodeCircuit::<Fr>::min_num_rows_block(block).next_power_of_two()
+}
+
+/// Returns `block` with the capacity of the copy circuit rounded up to a
+/// power of two, so that blocks of similar sizes share the same keys.
+fn copy_block(block: &Block<Fr>) -> Block<Fr> {
+    let mut block = block.clone();
+    block.max_copy_rows = (CopyCircuit::get_num_rows_required(&block) - 2).next_power_of_two();
+    block
+}
+
+/// Returns the minimal degree of the copy circuit of `block`, whose capacity
+/// is set, which also fits the rw and bytecode tables it loads.
+fn copy_degree(block: &Block<Fr>) -> u32 {
+    let k = log2_ceil(64 + CopyCircuit::get_num_rows_required(block));
+    let k = k.max(log2_ceil(64 + block.max_rws));
+    k.max(log2_ceil(64 + bytecodes_len(block)))
+}
+
+/// Returns the minimal degree of the keccak circuit hashing the inputs looked
+/// up by the circuits of `witness`.
+fn keccak_degree(witness: &BlockWitness) -> Result<u32, String> {
+    let inputs = super_circuit::keccak_inputs(&witness.block, &witness.txs)
+        .map_err(|err| format!("keccak inputs: {:?}", err))?;
+    Ok(log2_ceil(64 + KeccakCircuit::<Fr>::min_num_rows(&inputs)))
+}
+
+fn bytecodes_len(block: &Block<Fr>) -> usize {
+    block
+        .bytecodes
+        .values()
+        .map(|bytecode| bytecode.bytes.len())
+        .sum()
+}
+
+/// The minimal degree of each circuit of a block, which is the degree of the
+/// params its proof is created with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CircuitDegrees {
+    pub evm: u32,
+    pub state: u32,
+    pub tx: u32,
+    pub bytecode: u32,
+    pub copy: u32,
+    pub keccak: u32,
+}
+
+impl CircuitDegrees {
+    /// Returns the minimal degrees of the circuits of `witness`, sized like
+    /// the circuits returned by the functions of this module.
+    pub fn new(witness: &BlockWitness) -> Result<Self, String> {
+        check_txs(witness)?;
+        let block = &witness.block;
+
+        Ok(Self {
+            evm: evm_degree(&evm_block(block)),
+            state: state_degree(state_num_rows(block)),
+            tx: TX_CIRCUIT_DEGREE,
+            bytecode: log2_ceil(bytecode_size(block)),
+            copy: copy_degree(&copy_block(block)),
+            keccak: keccak_degree(witness)?,
+        })
+    }
+}
+
+/// How much of a capacity of a circuit a block uses
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct Usage {
+    pub used: usize,
+    pub capacity: usize,
+}
+
+/// The size of a circuit of a block, for diagnosing why it can't be proven
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CircuitStats {
+    pub circuit: &'static str,
+    /// the minimal degree of the circuit, unless it can't be sized
+    pub k: Option<u32>,
+    /// the capacities the circuit is sized by
+    pub usage: BTreeMap<&'static str, Usage>,
+    /// why the block doesn't fit in the circuit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CircuitStats {
+    fn new(circuit: &'static str, k: u32, usage: &[(&'static str, usize, usize)]) -> Self {
+        Self {
+            circuit,
+            k: Some(k),
+            usage: usage
+                .iter()
+                .map(|(name, used, capacity)| {
+                    (
+                        *name,
+                        Usage {
+                            used: *used,
+                            capacity: *capacity,
+                        },
+                    )
+                })
+                .collect(),
+            error: None,
+        }
+    }
+}
+
+/// Returns the sizes of the circuits of `witness`, sized like the circuits
+/// returned by the functions of this module.
+/// The super circuit isn't included, as it's sized by its subcircuits.
+pub fn circuit_stats(witness: &BlockWitness) -> Vec<CircuitStats> {
+    let block = &witness.block;
+    let rws: usize = block.rws.0.values().map(|rws| rws.len()).sum();
+    let rows = |k: u32| (1usize << k) - 64;
+
+    let evm = evm_block(block);
+    let evm_k = evm_degree(&evm);
+    let state_rows = state_num_rows(block);
+    let state_k = state_degree(state_rows);
+    let bytecode_rows = bytecode_size(block);
+    let copy = copy_block(block);
+    let copy_k = copy_degree(&copy);
+
+    let calldata_len: usize = witness.txs.iter().map(|tx| tx.call_data.len()).sum();
+    let mut tx = CircuitStats::new(
+        CircuitKind::Tx.name(),
+        TX_CIRCUIT_DEGREE,
+        &[
+            ("txs", witness.txs.len(), MAX_TXS),
+            ("calldata", calldata_len, MAX_CALLDATA),
+        ],
+    );
+    tx.error = check_txs(witness).err();
+
+    let keccak = match super_circuit::keccak_inputs(block, &witness.txs) {
+        Ok(inputs) => {
+            let keccak_rows = KeccakCircuit::<Fr>::min_num_rows(&inputs);
+            let k = log2_ceil(64 + keccak_rows);
+            CircuitStats::new("keccak", k, &[("rows", keccak_rows, rows(k))])
+        }
+        Err(err) => CircuitStats {
+            circuit: "keccak",
+            k: None,
+            usage: BTreeMap::new(),
+            error: Some(format!("keccak inputs: {:?}", err)),
+        },
+    };
+
+    vec![
+        CircuitStats::new(
+            CircuitKind::Evm.name(),
+            evm_k,
+            &[
+                (
+                    "rows",
+                    StandaloneEvmCircuit::<Fr>::get_num_rows_required(block),
+                    evm.max_evm_rows,
+                ),
+                ("rws", rws, evm.max_rws),
+                ("txs", block.txs.len(), evm.max_txs),
+                ("bytecode", bytecodes_len(block), rows(evm_k)),
+            ],
+        ),
+        CircuitStats::new(
+            CircuitKind::State.name(),
+            state_k,
+            &[(
+                "rows",
+                StateCircuit::<Fr>::min_num_rows_block(block),
+                state_rows,
+            )],
+        ),
+        tx,
+        CircuitStats::new(
+            CircuitKind::Bytecode.name(),
+            log2_ceil(bytecode_rows),
+            &[(
+                "rows",
+                BytecodeCircuit::<Fr>::min_num_rows_block(block),
+                bytecode_rows,
+            )],
+        ),
+        CircuitStats::new(
+            CircuitKind::Copy.name(),
+            copy_k,
+            &[
+                (
+                    "rows",
+                    CopyCircuit::get_num_rows_required(block),
+                    CopyCircuit::get_num_rows_required(&copy),
+                ),
+                ("rws", rws, rows(copy_k)),
+                ("bytecode", bytecodes_len(block), rows(copy_k)),
+            ],
+        ),
+        keccak,
+    ]
+}
+
+/// Returns the EVM circuit of `witness`.
+/// The capacities of the circuit are rounded up to a power of two, so that
+/// blocks of similar sizes share the same keys.
+pub fn evm_circuit(witness: &BlockWitness) -> BlockCircuit<StandaloneEvmCircuit<Fr>> {
+    let block = evm_block(&witness.block);
+    let k = evm_degree(&block);
+
+    BlockCircuit {
+        kind: CircuitKind::Evm,
+        shape: vec![block.max_evm_rows, block.max_txs, block.max_rws],
+        k,
+        public_inputs: PublicInputs::new(block.randomness, 31, (1 << k) - 64, &[]),
+        circuit: StandaloneEvmCircuit::<Fr>::new(block, FixedTableTag::iter().collect()),
+    }
+}
+
+/// Returns the state circuit of `witness`, whose number of rows is rounded
+/// up to a power of two.
+pub fn state_circuit(witness: &BlockWitness) -> BlockCircuit<StateCircuit<Fr>> {
+    let block = &witness.block;
+    let n_rows = state_num_rows(block);
+
+    BlockCircuit {
+        kind: CircuitKind::State,
+        circuit: StateCircuit::<Fr>::new(
+            block.randomness,
+            block.rws.clone(),
+            block.mpt_updates.clone(),
+            n_rows,
+        ),
+        shape: vec![n_rows],
+        k: state_degree(n_rows),
+        // the instance of `StateCircuit::instance`
+        public_inputs: PublicInputs::new(block.randomness, 31, n_rows, &[]),
+    }
+}
+
+/// Returns the tx circuit of `witness`.
+pub fn tx_circuit(
+    witness: &BlockWitness,
+) -> Result<BlockCircuit<TxCircuit<Fr, MAX_TXS, MAX_CALLDATA>>, String> {
+    check_txs(witness)?;
+    let block = &witness.block;
+
+    Ok(BlockCircuit {
+        kind: CircuitKind::Tx,
+        circuit: TxCircuit::new(
+            aux_generator(),
+            block.randomness,
+            block.context.chain_id.as_u64(),
+            witness.txs.clone(),
+        ),
+        shape: vec![MAX_TXS, MAX_CALLDATA],
+        k: TX_CIRCUIT_DEGREE,
+        // followed by the empty instance column of the ECDSA chip
+        public_inputs: PublicInputs::new(
+            block.randomness,
+            POW_RAND_SIZE,
+            MAX_TXS * VERIF_HEIGHT,
+            &[vec![]],
+        ),
+    })
+}
+
+/// Returns the bytecode circuit of `witness`, whose size is rounded up to a
+/// power of two.
+pub fn bytecode_circuit(witness: &BlockWitness) -> BlockCircuit<BytecodeCircuit<Fr>> {
+    let size = bytecode_size(&witness.block);
+    let circuit = BytecodeCircuit::<Fr>::new_from_block(&witness.block, size);
+    let num_rows = circuit.instance()[0].len();
+
+    BlockCircuit {
+        kind: CircuitKind::Bytecode,
+        shape: vec![size],
+        k: log2_ceil(size),
+        public_inputs: PublicInputs::new(circuit.randomness, 1, num_rows, &[]),
+        circuit,
+    }
+}
+
+/// Returns the copy circuit of `witness`.
+/// The capacity of the circuit is rounded up to a power of two, so that blocks
+/// of similar sizes share the same keys.
+pub fn copy_circuit(witness: &BlockWitness) -> BlockCircuit<StandaloneCopyCircuit<Fr>> {
+    let block = copy_block(&witness.block);
+
+    BlockCircuit {
+        kind: CircuitKind::Copy,
+        shape: vec![block.max_copy_rows],
+        k: copy_degree(&block),
+        public_inputs: PublicInputs::new(block.randomness, 0, 0, &[]),
+        circuit: StandaloneCopyCircuit::new(block),
+    }
+}
+
+/// Returns `block` with the capacities of the EVM and State circuits of the
+/// super circuit rounded up to a power of two, so that blocks of similar sizes
+/// share the same keys.
+fn super_block(block: &Block<Fr>) -> Block<Fr> {
+    let mut block = block.clone();
+    block.max_rws = block.max_rws.next_power_of_two();
+    block.max_evm_rows = SuperCircuit::<Fr, MAX_TXS, MAX_CALLDATA>::get_num_rows_required(&block)
+        .next_power_of_two();
+    block
+}
+
+/// Returns the super circuit of `witness`.
+/// The capacities of the circuit are rounded up to a power of two, so that
+/// blocks of similar sizes share the same keys.
+pub fn super_circuit(
+    witness: &BlockWitness,
+) -> Result<BlockCircuit<SuperCircuit<Fr, MAX_TXS, MAX_CALLDATA>>, String> {
+    check_txs(witness)?;
+    let block = super_block(&witness.block);
+    let max_evm_rows = block.max_evm_rows;
+    let max_rws = block.max_rws;
+
+    let (k, circuit, instance) = SuperCircuit::<Fr, MAX_TXS, MAX_CALLDATA>::build(
+        block,
+        witness.txs.clone(),
+        aux_generator(),
+        witness.public_data.clone(),
+        FixedTableTag::iter().collect(),
+    )
+    .map_err(|err| format!("super circuit: {:?}", err))?;
+
+    Ok(BlockCircuit {
+        kind: CircuitKind::Super,
+        // the fixed columns depend on the sizes of the circuits
+        shape: vec![
+            max_evm_rows,
+            MAX_TXS,
+            max_rws,
+            circuit.copy_circuit_size(),
+            circuit.bytecode_size,
+            circuit.keccak_circuit_size,
+            circuit.mpt_circuit_size(),
+            circuit.rlp_circuit_size,
+        ],
+        k,
+        public_inputs: PublicInputs::new(
+            witness.block.randomness,
+            POW_RAND_SIZE,
+            instance[0].len(),
+            &instance[POW_RAND_SIZE..],
+        ),
+        circuit,
+    })
+}
+
+/// Returns the EVM circuit of `shape` without witness, which has the same
+/// keys as the circuits of blocks of that shape.
+/// The steps of every block, including the empty one, are padded with
+/// EndBlock steps up to `max_evm_rows`, so the fixed columns only depend on
+/// the shape.
+pub fn evm_keygen_circuit(shape: &[usize]) -> Result<StandaloneEvmCircuit<Fr>, String> {
+    match *shape {
+        [max_evm_rows, max_txs, max_rws] => Ok(StandaloneEvmCircuit::<Fr>::new(
+            Block {
+                max_evm_rows,
+                max_txs,
+                max_rws,
+                ..Default::default()
+            },
+            FixedTableTag::iter().collect(),
+        )),
+        _ => Err(format!("invalid evm circuit shape: {:?}", shape)),
+    }
+}
+
+/// Returns the state circuit of `shape` without witness, which has the same
+/// keys as the circuits of blocks of that shape.
+pub fn state_keygen_circuit(shape: &[usize]) -> Result<StateCircuit<Fr>, String> {
+    match *shape {
+        [n_rows] => Ok(StateCircuit::<Fr>::new(
+            Fr::default(),
+            RwMap::default(),
+            MptUpdates::default(),
+            n_rows,
+        )),
+        _ => Err(format!("invalid state circuit shape: {:?}", shape)),
+    }
+}
+
+/// Returns the tx circuit of `shape` without witness, which has the same keys
+/// as the circuits of blocks of that shape.
+pub fn tx_keygen_circuit(shape: &[usize]) -> Result<TxCircuit<Fr, MAX_TXS, MAX_CALLDATA>, String> {
+    match *shape {
+        [MAX_TXS, MAX_CALLDATA] => Ok(TxCircuit::new(aux_generator(), Fr::default(), 0, vec![])),
+        _ => Err(format!("unsupported tx circuit shape: {:?}", shape)),
+    }
+}
+
+/// Returns the bytecode circuit of `shape` without witness, which has the
+/// same keys as the circuits of blocks of that shape.
+pub fn bytecode_keygen_circuit(shape: &[usize]) -> Result<BytecodeCircuit<Fr>, String> {
+    match *shape {
+        [size] => Ok(BytecodeCircuit::<Fr>::new(vec![], size, Fr::default())),
+        _ => Err(format!("invalid bytecode circuit shape: {:?}", shape)),
+    }
+}
+
+/// Returns the copy circuit of `shape` without witness, which has the same
+/// keys as the circuits of blocks of that shape.
+pub fn copy_keygen_circuit(shape: &[usize]) -> Result<StandaloneCopyCircuit<Fr>, String> {
+    match *shape {
+        [max_copy_rows] => Ok(StandaloneCopyCircuit::<Fr>::new(Block {
+            max_copy_rows,
+            ..Default::default()
+        })),
+        _ => Err(format!("invalid copy circuit shape: {:?}", shape)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_proof::prove_snark;
+    use crate::key_cache::KeyCache;
+    use crate::params::ParamsCache;
+    use bus_mapping::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData, Bytecode};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::pairing::bn256::{Bn256, G1Affine};
+    use halo2_proofs::plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, SingleVerifier, VerifyingKey,
+    };
+    use halo2_proofs::poly::commitment::{Params, ParamsVerifier};
+    use halo2_proofs::transcript::{Blake2bRead, Blake2bWrite, Challenge255};
+    use mock::TestContext;
+    use rand::rngs::OsRng;
+    use std::fs::File;
+    use zkevm_circuits::aggregation_circuit::{AggregationCircuit, ACCUMULATOR_LEN};
+    use zkevm_circuits::evm_circuit::witness::block_convert;
+
+    fn block_witness(bytecode: Bytecode) -> BlockWitness {
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode)
+            .unwrap()
+            .into();
+        let block_data = BlockData::new_from_geth_data(block);
+        let mut builder = block_data.new_circuit_input_builder();
+        builder
+            .handle_block(&block_data.eth_block, &block_data.geth_traces)
+            .unwrap();
+        BlockWitness {
+            block: block_convert(&builder.block, &builder.code_db).unwrap(),
+            txs: vec![],
+            public_data: PublicData::default(),
+        }
+    }
+
+    fn verify(
+        params: &Params<G1Affine>,
+        vk: &VerifyingKey<G1Affine>,
+        public_inputs: &PublicInputs,
+        proof: &[u8],
+    ) {
+        let instance = public_inputs.instance().unwrap();
+        let instance: Vec<&[Fr]> = instance.iter().map(|column| &column[..]).collect();
+        let verifier_params: ParamsVerifier<Bn256> =
+            params.verifier(public_inputs.max_len()).unwrap();
+        let strategy = SingleVerifier::new(&verifier_params);
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+        verify_proof(
+            &verifier_params,
+            vk,
+            strategy,
+            &[&instance],
+            &mut transcript,
+        )
+        .unwrap();
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test [...] serial_ -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn serial_test_evm_keys_of_another_block() {
+        let block_a = evm_circuit(&block_witness(bytecode! {
+            PUSH1(0x01)
+            PUSH1(0x02)
+            ADD
+            PUSH1(0x03)
+            MUL
+            STOP
+        }));
+
+        // Block B has fewer steps, so it fits in the capacities of block A.
+        let mut witness_b = block_witness(bytecode! {
+            PUSH1(0x00)
+            STOP
+        });
+        witness_b.block.max_evm_rows = block_a.shape[0];
+        witness_b.block.max_txs = block_a.shape[1];
+        witness_b.block.max_rws = block_a.shape[2];
+        let block_b = evm_circuit(&witness_b);
+        assert_eq!(block_b.shape, block_a.shape);
+        assert_eq!(block_b.k, block_a.k);
+
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(block_a.k);
+        let vk = keygen_vk(&params, &block_b.circuit).unwrap();
+        let pk = keygen_pk(&params, vk, &block_b.circuit).unwrap();
+
+        let instance = block_a.public_inputs.instance().unwrap();
+        let instance: Vec<&[Fr]> = instance.iter().map(|column| &column[..]).collect();
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof(
+            &params,
+            &pk,
+            &[block_a.circuit],
+            &[&instance],
+            OsRng,
+            &mut transcript,
+        )
+        .unwrap();
+        let proof = transcript.finalize();
+
+        verify(&params, pk.get_vk(), &block_a.public_inputs, &proof);
+        // The verifier generates the keys from the shape alone.
+        let shape_vk = keygen_vk(&params, &evm_keygen_circuit(&block_a.shape).unwrap()).unwrap();
+        verify(&params, &shape_vk, &block_a.public_inputs, &proof);
+    }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test [...] serial_ -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn serial_test_aggregate_copy_circuit() {
+        let circuit = copy_circuit(&block_witness(bytecode! {
+            PUSH1(0x20) // length
+            PUSH1(0x00) // offset
+            PUSH1(0x00) // memOffset
+            CODECOPY
+            STOP
+        }));
+        let params_path = std::env::temp_dir().join(format!("test_params_k{}", circuit.k));
+        Params::<G1Affine>::unsafe_setup::<Bn256>(circuit.k)
+            .write(&mut File::create(&params_path).unwrap())
+            .unwrap();
+
+        let (snark, params) = prove_snark(
+            &ParamsCache::default(),
+            params_path.to_str().unwrap(),
+            &KeyCache::default(),
+            circuit,
+            None,
+        )
+        .unwrap();
+        let aggregation = AggregationCircuit::new(&params, vec![snark]).unwrap();
+        let instance = aggregation.instance();
+        // the copy circuit has no instance
+        assert_eq!(instance[0].len(), ACCUMULATOR_LEN);
+
+        let prover = MockProver::run(23, &aggregation, instance).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -1,5 +1,6 @@
 use bus_mapping::circuit_input_builder::BuilderClient;
 use bus_mapping::rpc::GethClient;
+use eth_types::{geth_types, ToWord};
 use ethers_providers::Http;
 use halo2_proofs::{
     pairing::bn256::{Fr, G1Affine},
@@ -7,88 +8,294 @@ use halo2_proofs::{
     poly::commitment::Params,
     transcript::{Blake2bWrite, Challenge255},
 };
-use rand::SeedableRng;
+use rand::rngs::OsRng;
+use rand::{RngCore, SeedableRng};
 use rand_xorshift::XorShiftRng;
 
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Instant;
 
-use strum::IntoEnumIterator;
-use zkevm_circuits::evm_circuit::{
-    table::FixedTableTag, test::TestCircuit, witness::block_convert,
-};
-use zkevm_circuits::state_circuit::StateCircuit;
+use zkevm_circuits::aggregation_circuit::Snark;
+use zkevm_circuits::evm_circuit::witness::block_convert;
+use zkevm_circuits::pi_circuit::PublicData;
+
+use crate::circuits::*;
+use crate::key_cache::KeyCache;
+use crate::params::ParamsCache;
+use crate::structs::{CircuitKind, CircuitProof, KeyId, Proofs, TaskState, WitnessInputs};
 
-use crate::structs::Proofs;
+/// Reports the stage a proof computation is at. An error aborts the
+/// computation, e.g. if its task got cancelled.
+pub type Progress<'a> = &'a (dyn Fn(TaskState) -> Result<(), String> + Sync);
 
-/// Gathers debug trace(s) from `rpc_url` for block `block_num` with `params`
-/// created via the `gen_params` tool.
+/// Gathers debug trace(s) from `rpc_url` for block `block_num` along with
+/// everything else needed to build the witness of the circuits.
 /// Expects a go-ethereum node with debug & archive capabilities on `rpc_url`.
-pub async fn compute_proof(
-    params: &Params<G1Affine>,
+pub async fn gen_witness_inputs(
     block_num: &u64,
     rpc_url: &str,
-) -> Result<Proofs, Box<dyn std::error::Error>> {
-    // request & build the inputs for the circuits
-    let time_started = Instant::now();
-    let url = Http::from_str(rpc_url)?;
-    let geth_client = GethClient::new(url);
+) -> Result<WitnessInputs, Box<dyn std::error::Error>> {
+    let geth_client = GethClient::new(Http::from_str(rpc_url)?);
+    // the state root before the block is the one of its parent
+    let parent_block = geth_client
+        .get_block_by_number((*block_num - 1).into())
+        .await?;
+
     let builder = BuilderClient::new(geth_client).await?;
-    let builder = builder.gen_inputs(*block_num).await?;
-
-    // TODO: only {evm,state}_proof are implemented right now
-    let evm_proof;
-    let state_proof;
-    let block = block_convert(&builder.block, &builder.code_db);
-    {
-        // generate evm_circuit proof
-        let circuit = TestCircuit::<Fr>::new(block.clone(), FixedTableTag::iter().collect());
-
-        // TODO: can this be pre-generated to a file?
-        // related
-        // https://github.com/zcash/halo2/issues/443
-        // https://github.com/zcash/halo2/issues/449
-        let vk = keygen_vk(params, &circuit)?;
-        let pk = keygen_pk(params, vk, &circuit)?;
-
-        // Create randomness
-        let rng = XorShiftRng::from_seed([
-            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
-            0xbc, 0xe5,
-        ]);
-
-        // create a proof
-        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
-        create_proof(params, &pk, &[circuit], &[], rng, &mut transcript)?;
-        evm_proof = transcript.finalize();
+    Ok(WitnessInputs {
+        builder: builder.get_inputs(*block_num).await?,
+        prev_state_root: parent_block.state_root,
+    })
+}
+
+/// Converts `inputs` into the witness of the circuits, without network
+/// access.
+pub fn witness_from_inputs(
+    inputs: &WitnessInputs,
+) -> Result<BlockWitness, Box<dyn std::error::Error>> {
+    let builder = inputs.builder.gen_inputs()?;
+    let eth_block = &inputs.builder.eth_block;
+
+    let block = block_convert(&builder.block, &builder.code_db)
+        .map_err(|err| format!("MPT updates of the block: {:?}", err))?;
+    let txs: Vec<geth_types::Transaction> = eth_block
+        .transactions
+        .iter()
+        .map(geth_types::Transaction::from_eth_tx)
+        .collect();
+    let public_data = PublicData {
+        block: block.context.clone(),
+        block_hash: eth_block.hash.unwrap_or_default().to_word(),
+        state_root: eth_block.state_root.to_word(),
+        prev_state_root: inputs.prev_state_root.to_word(),
+        receipts_root: eth_block.receipts_root.to_word(),
+        txs: txs.clone(),
+    };
+
+    Ok(BlockWitness {
+        block,
+        txs,
+        public_data,
+    })
+}
+
+/// Gathers debug trace(s) from `rpc_url` for block `block_num` and converts
+/// them into the witness of the circuits.
+/// Expects a go-ethereum node with debug & archive capabilities on `rpc_url`.
+pub async fn gen_block_witness(
+    block_num: &u64,
+    rpc_url: &str,
+) -> Result<BlockWitness, Box<dyn std::error::Error>> {
+    let inputs = gen_witness_inputs(block_num, rpc_url).await?;
+    witness_from_inputs(&inputs)
+}
+
+/// Returns the params of the minimal degree of `circuit`, taken from
+/// `params_cache`, and its proving key, taken from `key_cache`.
+fn proving_key<C: Circuit<Fr>>(
+    params_cache: &ParamsCache,
+    params_path: &str,
+    key_cache: &KeyCache,
+    circuit: &BlockCircuit<C>,
+) -> Result<(KeyId, Arc<Params<G1Affine>>, Arc<ProvingKey<G1Affine>>), Box<dyn std::error::Error>> {
+    let (params_path, params) = params_cache
+        .get(params_path, circuit.k)
+        .map_err(|err| format!("{} circuit: {}", circuit.kind.name(), err))?;
+
+    let key_id = KeyId {
+        params_path,
+        k: circuit.k,
+        circuit: circuit.kind,
+        shape: circuit.shape.clone(),
+    };
+    let pk = key_cache.get_or_gen(&params, &key_id, &circuit.circuit)?;
+
+    Ok((key_id, params, pk))
+}
+
+/// The randomness of a proof, drawn from the OS unless a `seed` is given for
+/// reproducible proofs.
+fn proof_rng(seed: Option<u64>) -> Box<dyn RngCore> {
+    match seed {
+        Some(seed) => {
+            log::warn!("proving with a fixed seed, the proofs aren't zero-knowledge");
+            Box::new(XorShiftRng::seed_from_u64(seed))
+        }
+        None => Box::new(OsRng),
     }
+}
+
+/// Creates a proof of `circuit` with params of its minimal degree, taken
+/// from `params_cache`.
+/// The proving key is taken from `key_cache`.
+/// The randomness of the proof is drawn from the OS, unless a `seed` is
+/// given for reproducible proofs.
+fn prove<C: Circuit<Fr>>(
+    params_cache: &ParamsCache,
+    params_path: &str,
+    key_cache: &KeyCache,
+    progress: Progress,
+    circuit: BlockCircuit<C>,
+    seed: Option<u64>,
+) -> Result<CircuitProof, Box<dyn std::error::Error>> {
+    progress(TaskState::Keygen)?;
+    let (key_id, params, pk) = proving_key(params_cache, params_path, key_cache, &circuit)?;
+
+    let instance = circuit.public_inputs.instance()?;
+    let instance: Vec<&[Fr]> = instance.iter().map(|column| &column[..]).collect();
+
+    progress(TaskState::Proving)?;
+
+    // create a proof
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[circuit.circuit],
+        &[&instance],
+        proof_rng(seed),
+        &mut transcript,
+    )?;
+
+    Ok(CircuitProof {
+        circuit: key_id.circuit,
+        shape: key_id.shape,
+        k: circuit.k,
+        public_inputs: circuit.public_inputs,
+        proof: transcript.finalize().into(),
+    })
+}
+
+/// Creates a proof of `circuit` like [`prove`], but with the Poseidon
+/// transcript, so that it can be verified by the [`AggregationCircuit`].
+/// Returns the proof along with the params it's created with, which the
+/// [`AggregationCircuit`] of the proof is built with.
+///
+/// [`AggregationCircuit`]: zkevm_circuits::aggregation_circuit::AggregationCircuit
+pub fn prove_snark<C: Circuit<Fr>>(
+    params_cache: &ParamsCache,
+    params_path: &str,
+    key_cache: &KeyCache,
+    circuit: BlockCircuit<C>,
+    seed: Option<u64>,
+) -> Result<(Snark, Arc<Params<G1Affine>>), Box<dyn std::error::Error>> {
+    let (_, params, pk) = proving_key(params_cache, params_path, key_cache, &circuit)?;
+    let instance = circuit.public_inputs.instance()?;
+    let snark = Snark::create(&params, &pk, circuit.circuit, instance, proof_rng(seed))?;
+
+    Ok((snark, params))
+}
 
-    {
-        // generate state_circuit proof
-        const N_ROWS: usize = 1 << 16;
-        let circuit = StateCircuit::<Fr>::new(block.randomness, block.rws, N_ROWS);
-
-        // TODO: same quest like in the first scope
-        let vk = keygen_vk(params, &circuit)?;
-        let pk = keygen_pk(params, vk, &circuit)?;
-
-        // Create randomness
-        let rng = XorShiftRng::from_seed([
-            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
-            0xbc, 0xe5,
-        ]);
-
-        // create a proof
-        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
-        create_proof(params, &pk, &[circuit], &[], rng, &mut transcript)?;
-        state_proof = transcript.finalize();
+/// Proves `circuits` of `witness` with params created via the `gen_params`
+/// tool, read from `params_path`, which is either a params file or a
+/// directory of them.
+/// Every circuit is proven with params of its minimal degree, see
+/// [`ParamsCache::get`].
+/// The proving keys are taken from `key_cache`.
+/// The stages of the computation are reported to `progress`.
+/// A `seed` makes the proofs reproducible, for tests only.
+pub fn prove_block(
+    params_cache: &ParamsCache,
+    params_path: &str,
+    key_cache: &KeyCache,
+    progress: Progress,
+    witness: &BlockWitness,
+    circuits: &[CircuitKind],
+    seed: Option<u64>,
+) -> Result<Proofs, Box<dyn std::error::Error>> {
+    let time_started = Instant::now();
+    let mut proofs = Proofs::default();
+    for kind in circuits {
+        log::info!("proving the {} circuit", kind.name());
+        let proof = match kind {
+            CircuitKind::Evm => prove(
+                params_cache,
+                params_path,
+                key_cache,
+                progress,
+                evm_circuit(witness),
+                seed,
+            )?,
+            CircuitKind::State => prove(
+                params_cache,
+                params_path,
+                key_cache,
+                progress,
+                state_circuit(witness),
+                seed,
+            )?,
+            CircuitKind::Tx => prove(
+                params_cache,
+                params_path,
+                key_cache,
+                progress,
+                tx_circuit(witness)?,
+                seed,
+            )?,
+            CircuitKind::Bytecode => prove(
+                params_cache,
+                params_path,
+                key_cache,
+                progress,
+                bytecode_circuit(witness),
+                seed,
+            )?,
+            CircuitKind::Copy => prove(
+                params_cache,
+                params_path,
+                key_cache,
+                progress,
+                copy_circuit(witness),
+                seed,
+            )?,
+            CircuitKind::Super => prove(
+                params_cache,
+                params_path,
+                key_cache,
+                progress,
+                super_circuit(witness)?,
+                seed,
+            )?,
+        };
+        proofs.insert(proof);
     }
+    proofs.duration = Instant::now().duration_since(time_started).as_millis() as u64;
 
-    let ret = Proofs {
-        evm_proof: evm_proof.into(),
-        state_proof: state_proof.into(),
-        duration: Instant::now().duration_since(time_started).as_millis() as u64,
-    };
+    Ok(proofs)
+}
+
+/// Gathers debug trace(s) from `rpc_url` for block `block_num` and proves
+/// `circuits` of it, see [`prove_block`].
+/// Expects a go-ethereum node with debug & archive capabilities on `rpc_url`.
+#[allow(clippy::too_many_arguments)]
+pub async fn compute_proof(
+    params_cache: &ParamsCache,
+    params_path: &str,
+    key_cache: &KeyCache,
+    progress: Progress,
+    block_num: &u64,
+    rpc_url: &str,
+    circuits: &[CircuitKind],
+    seed: Option<u64>,
+) -> Result<Proofs, Box<dyn std::error::Error>> {
+    // request & build the inputs for the circuits
+    let time_started = Instant::now();
+    progress(TaskState::Fetching)?;
+    let inputs = gen_witness_inputs(block_num, rpc_url).await?;
+    progress(TaskState::Witness)?;
+    let witness = witness_from_inputs(&inputs)?;
+
+    let mut proofs = prove_block(
+        params_cache,
+        params_path,
+        key_cache,
+        progress,
+        &witness,
+        circuits,
+        seed,
+    )?;
+    proofs.duration = Instant::now().duration_since(time_started).as_millis() as u64;
 
-    Ok(ret)
+    Ok(proofs)
 }
@@ -0,0 +1,192 @@
+use halo2_proofs::pairing::bn256::{Fr, G1Affine};
+use halo2_proofs::plonk::{keygen_pk, keygen_vk, Circuit, Error, ProvingKey, VerifyingKey};
+use halo2_proofs::poly::commitment::Params;
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::structs::KeyId;
+
+/// The default number of proving keys, and of verifying keys, a [`KeyCache`]
+/// holds.
+pub const DEFAULT_MAX_KEYS: usize = 4;
+
+/// The entries of a cache holding at most `capacity` of them, which evicts
+/// the least recently used entry when it's full.
+struct Lru<V> {
+    capacity: usize,
+    /// ordered from the least to the most recently used
+    entries: Vec<(KeyId, Arc<V>)>,
+}
+
+impl<V> Lru<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::new(),
+        }
+    }
+
+    fn get(&mut self, id: &KeyId) -> Option<Arc<V>> {
+        let index = self.entries.iter().position(|(key, _)| key == id)?;
+        let entry = self.entries.remove(index);
+        let value = entry.1.clone();
+        self.entries.push(entry);
+        Some(value)
+    }
+
+    fn insert(&mut self, id: KeyId, value: Arc<V>) {
+        self.entries.retain(|(key, _)| *key != id);
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            let (evicted, _) = self.entries.remove(0);
+            log::info!("keys: evicted {:?}", evicted);
+        }
+        self.entries.push((id, value));
+    }
+}
+
+/// Caches the proving and verifying keys of the circuits, so that proofs of
+/// circuits with the same shape skip key generation.
+/// At most `max_keys` proving keys, and as many verifying keys, are kept,
+/// evicting the least recently used ones.
+/// Verifying keys serialized with the `gen_keys` tool are loaded from
+/// `keys_dir` to verify proofs, instead of being generated.
+#[derive(Clone)]
+pub struct KeyCache {
+    keys: Arc<Mutex<Lru<ProvingKey<G1Affine>>>>,
+    vks: Arc<Mutex<Lru<VerifyingKey<G1Affine>>>>,
+    keys_dir: Option<PathBuf>,
+}
+
+impl KeyCache {
+    pub fn new(keys_dir: Option<PathBuf>, max_keys: usize) -> Self {
+        Self {
+            keys: Arc::new(Mutex::new(Lru::new(max_keys))),
+            vks: Arc::new(Mutex::new(Lru::new(max_keys))),
+            keys_dir,
+        }
+    }
+
+    /// Returns the proving key of `circuit`, which is generated and cached if
+    /// it isn't cached yet.
+    /// The keys of circuits without a fixed layout are generated every time.
+    pub fn get_or_gen<C: Circuit<Fr>>(
+        &self,
+        params: &Params<G1Affine>,
+        id: &KeyId,
+        circuit: &C,
+    ) -> Result<Arc<ProvingKey<G1Affine>>, Error> {
+        if !id.circuit.has_fixed_layout() {
+            log::info!("keys: generating uncached keys of {:?}", id);
+            let vk = keygen_vk(params, circuit)?;
+            return Ok(Arc::new(keygen_pk(params, vk, circuit)?));
+        }
+
+        if let Some(pk) = self.keys.lock().unwrap().get(id) {
+            log::debug!("keys: cached {:?}", id);
+            return Ok(pk);
+        }
+
+        // the lock isn't held during the potentially long running key generation
+        let vk = keygen_vk(params, circuit)?;
+        // A verifying key of keys_dir which doesn't match the circuit would
+        // reject its proofs, so the generated one replaces it.
+        if let Some(read_vk) = self.read_vk::<C>(params, id) {
+            if !same_vk(&read_vk, &vk) {
+                log::warn!(
+                    "keys: the verifying key of {:?} in keys_dir doesn't match the circuit",
+                    id
+                );
+                self.vks
+                    .lock()
+                    .unwrap()
+                    .insert(id.clone(), Arc::new(vk.clone()));
+            }
+        }
+        let pk = Arc::new(keygen_pk(params, vk, circuit)?);
+        log::info!("keys: initialized {:?}", id);
+
+        self.keys.lock().unwrap().insert(id.clone(), pk.clone());
+        Ok(pk)
+    }
+
+    /// Returns the verifying key of `id` if it's cached, either on its own or
+    /// with the proving key, or if it's in `keys_dir`.
+    pub fn get_vk<C: Circuit<Fr>>(
+        &self,
+        params: &Params<G1Affine>,
+        id: &KeyId,
+    ) -> Option<Arc<VerifyingKey<G1Affine>>> {
+        if let Some(vk) = self.vks.lock().unwrap().get(id) {
+            log::debug!("keys: cached vk {:?}", id);
+            return Some(vk);
+        }
+
+        let pk = self.keys.lock().unwrap().get(id);
+        let vk = match pk {
+            Some(pk) => pk.get_vk().clone(),
+            None => self.read_vk::<C>(params, id)?,
+        };
+        let vk = Arc::new(vk);
+
+        self.vks.lock().unwrap().insert(id.clone(), vk.clone());
+        Some(vk)
+    }
+
+    /// Returns the verifying key of `circuit`, which is generated and cached
+    /// if it isn't available via [`Self::get_vk`].
+    pub fn get_or_gen_vk<C: Circuit<Fr>>(
+        &self,
+        params: &Params<G1Affine>,
+        id: &KeyId,
+        circuit: &C,
+    ) -> Result<Arc<VerifyingKey<G1Affine>>, Error> {
+        if let Some(vk) = self.get_vk::<C>(params, id) {
+            return Ok(vk);
+        }
+
+        let vk = Arc::new(keygen_vk(params, circuit)?);
+        log::info!("keys: initialized vk {:?}", id);
+
+        self.vks.lock().unwrap().insert(id.clone(), vk.clone());
+        Ok(vk)
+    }
+
+    /// Reads the verifying key of `id` from `keys_dir`, if it's there.
+    fn read_vk<C: Circuit<Fr>>(
+        &self,
+        params: &Params<G1Affine>,
+        id: &KeyId,
+    ) -> Option<VerifyingKey<G1Affine>> {
+        let path = self.keys_dir.as_ref()?.join(id.vk_file_name());
+        let file = File::open(&path).ok()?;
+        match VerifyingKey::read::<_, C>(&mut BufReader::new(file), params) {
+            Ok(vk) => {
+                log::info!("keys: loaded {}", path.display());
+                Some(vk)
+            }
+            Err(err) => {
+                log::warn!("keys: failed to read {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+}
+
+/// Returns whether the verifying keys `a` and `b` are the same, i.e. whether
+/// they commit to the same fixed columns and permutation.
+fn same_vk(a: &VerifyingKey<G1Affine>, b: &VerifyingKey<G1Affine>) -> bool {
+    let (mut a_bytes, mut b_bytes) = (Vec::new(), Vec::new());
+    a.write(&mut a_bytes).is_ok() && b.write(&mut b_bytes).is_ok() && a_bytes == b_bytes
+}
+
+impl Default for KeyCache {
+    fn default() -> Self {
+        Self::new(None, DEFAULT_MAX_KEYS)
+    }
+}
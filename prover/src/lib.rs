@@ -1,3 +1,9 @@
+pub mod circuits;
 pub mod compute_proof;
+pub mod key_cache;
+pub mod params;
 pub mod shared_state;
 pub mod structs;
+pub mod subprocess;
+pub mod verify_proof;
+pub mod witness;
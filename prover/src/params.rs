@@ -0,0 +1,412 @@
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Curve, GroupEncoding};
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2_proofs::pairing::bn256::{Fq, Fq2, Fr, G1Affine, G2Affine, G1};
+use halo2_proofs::poly::commitment::Params;
+use sha2::{Digest, Sha256};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// How a params file was created
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum ParamsSource {
+    /// `Params::unsafe_setup`, whose secret is known, for testing only
+    UnsafeSetup,
+    /// imported from the challenge file of a powers of tau ceremony, see
+    /// [`import_ppot`]
+    Ppot {
+        /// the degree of the challenge file
+        degree: u32,
+        /// the blake2b hash at the start of the challenge file, as hex
+        hash: String,
+    },
+}
+
+/// The metadata of a params file, stored next to it with a `.json` extension
+/// appended to its name.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ParamsMetadata {
+    pub k: u32,
+    /// the sha256 checksum of the params file, as hex
+    pub sha256: String,
+    pub source: ParamsSource,
+}
+
+impl ParamsMetadata {
+    /// Returns the metadata of the params file at `params_path`, computing
+    /// its checksum.
+    pub fn new(params_path: &Path, source: ParamsSource) -> std::io::Result<Self> {
+        Ok(Self {
+            k: params_degree(params_path)?,
+            sha256: checksum(params_path)?,
+            source,
+        })
+    }
+
+    /// Returns the path of the metadata of the params file at `params_path`.
+    pub fn path(params_path: &Path) -> PathBuf {
+        let mut path = params_path.as_os_str().to_owned();
+        path.push(".json");
+        PathBuf::from(path)
+    }
+
+    /// Reads the metadata of the params file at `params_path`, if there's
+    /// any.
+    pub fn read(params_path: &Path) -> Result<Option<Self>, String> {
+        let path = Self::path(params_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = File::open(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map(Some)
+            .map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// Writes the metadata next to the params file at `params_path`.
+    pub fn write(&self, params_path: &Path) -> std::io::Result<()> {
+        let file = File::create(Self::path(params_path))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// Checks that the params file at `params_path` matches the metadata.
+    pub fn verify(&self, params_path: &Path) -> Result<(), String> {
+        let file_name = params_path.display();
+        let k = params_degree(params_path).map_err(|err| format!("{}: {}", file_name, err))?;
+        if k != self.k {
+            return Err(format!(
+                "{}: the params have degree {} but their metadata degree {}",
+                file_name, k, self.k
+            ));
+        }
+        let sha256 = checksum(params_path).map_err(|err| format!("{}: {}", file_name, err))?;
+        if sha256 != self.sha256 {
+            return Err(format!(
+                "{}: the checksum {} doesn't match the checksum {} of the metadata",
+                file_name, sha256, self.sha256
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the sha256 checksum of the file at `path`, as hex.
+pub fn checksum(path: &Path) -> std::io::Result<String> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Returns the degree of the params file at `params_path`, which is stored
+/// as a little-endian u32 at its start.
+pub fn params_degree(params_path: &Path) -> std::io::Result<u32> {
+    let mut k = [0u8; 4];
+    File::open(params_path)?.read_exact(&mut k)?;
+    Ok(u32::from_le_bytes(k))
+}
+
+/// Returns the params file in `params_dir` with the smallest degree that is
+/// at least `k`.
+pub fn find_params(params_dir: &Path, k: u32) -> Result<PathBuf, String> {
+    let entries = std::fs::read_dir(params_dir)
+        .map_err(|err| format!("{}: {}", params_dir.display(), err))?;
+
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        // skip the metadata of the params
+        .filter(|path| path.extension().map_or(true, |ext| ext != "json"))
+        .filter_map(|path| params_degree(&path).ok().map(|degree| (degree, path)))
+        .filter(|(degree, _)| *degree >= k)
+        .min()
+        .map(|(_, path)| path)
+        .ok_or_else(|| {
+            format!(
+                "no params of degree {} or more in {}",
+                k,
+                params_dir.display()
+            )
+        })
+}
+
+/// Caches the params read from disk along with their downsized copies, so
+/// that every circuit is proven with params of its own degree.
+#[derive(Clone, Default)]
+pub struct ParamsCache {
+    params: Arc<Mutex<HashMap<(PathBuf, u32), Arc<Params<G1Affine>>>>>,
+}
+
+impl ParamsCache {
+    /// Returns the params of degree `k` along with the path of the file they
+    /// are read from.
+    /// `params_path` is either a file generated with the gen_params tool or a
+    /// directory of such files, out of which the one with the smallest
+    /// degree that fits `k` is picked. Params of a larger degree are
+    /// downsized to `k`.
+    pub fn get(
+        &self,
+        params_path: &str,
+        k: u32,
+    ) -> Result<(String, Arc<Params<G1Affine>>), String> {
+        let path = Path::new(params_path);
+        let file = match path.is_dir() {
+            true => find_params(path, k)?,
+            false => path.to_path_buf(),
+        };
+        let file_name = file.display().to_string();
+
+        if let Some(params) = self.params.lock().unwrap().get(&(file.clone(), k)) {
+            return Ok((file_name, params.clone()));
+        }
+
+        let degree = params_degree(&file).map_err(|err| format!("{}: {}", file_name, err))?;
+        if degree < k {
+            return Err(format!(
+                "the circuit needs degree {} but the params at {} have degree {}",
+                k, file_name, degree
+            ));
+        }
+
+        // the lock isn't held while reading the file
+        let cached = self
+            .params
+            .lock()
+            .unwrap()
+            .get(&(file.clone(), degree))
+            .cloned();
+        let params = match cached {
+            Some(params) => params,
+            None => {
+                match ParamsMetadata::read(&file)? {
+                    Some(metadata) => metadata.verify(&file)?,
+                    None => log::warn!("params: {} has no metadata to verify", file_name),
+                }
+                let params_fs =
+                    File::open(&file).map_err(|err| format!("{}: {}", file_name, err))?;
+                let params = Params::<G1Affine>::read(&mut BufReader::new(params_fs))
+                    .map_err(|err| format!("{}: {}", file_name, err))?;
+                let params = Arc::new(params);
+                log::info!("params: initialized {}", file_name);
+
+                self.params
+                    .lock()
+                    .unwrap()
+                    .insert((file.clone(), degree), params.clone());
+                params
+            }
+        };
+        if degree == k {
+            return Ok((file_name, params));
+        }
+
+        let mut downsized = params.as_ref().clone();
+        downsized.downsize(k);
+        let downsized = Arc::new(downsized);
+        log::info!("params: downsized {} to degree {}", file_name, k);
+
+        self.params
+            .lock()
+            .unwrap()
+            .insert((file, k), downsized.clone());
+        Ok((file_name, downsized))
+    }
+}
+
+/// The size of the hash at the start of a challenge file
+const PPOT_HASH_SIZE: u64 = 64;
+/// The size of an uncompressed G1 point of a challenge file
+const PPOT_G1_SIZE: u64 = 64;
+/// The size of an uncompressed G2 point of a challenge file
+const PPOT_G2_SIZE: u64 = 128;
+
+/// Returns the degree of a challenge file of `len` bytes, which holds the
+/// hash, 2^(degree + 1) - 1 tau powers in G1, 2^degree tau powers in G2,
+/// 2^degree alpha and beta tau powers in G1 each and a beta in G2.
+fn ppot_degree(len: u64) -> Option<u32> {
+    let fixed = PPOT_HASH_SIZE - PPOT_G1_SIZE + PPOT_G2_SIZE;
+    let per_power = 2 * PPOT_G1_SIZE + PPOT_G2_SIZE + 2 * PPOT_G1_SIZE;
+    let powers = len.checked_sub(fixed)?;
+    match powers % per_power == 0 && (powers / per_power).is_power_of_two() {
+        true => Some((powers / per_power).trailing_zeros()),
+        false => None,
+    }
+}
+
+/// Parses a big-endian base field element of a challenge file.
+fn read_fq(bytes: &[u8]) -> Option<Fq> {
+    let mut repr = <Fq as PrimeField>::Repr::default();
+    repr.as_mut().copy_from_slice(bytes);
+    repr.as_mut().reverse();
+    Option::from(Fq::from_repr(repr))
+}
+
+/// Parses an uncompressed G1 point of a challenge file, `x || y`.
+fn read_g1(bytes: &[u8]) -> Option<G1Affine> {
+    // the infinity and compression flags are in the top bits of `x`
+    if bytes[0] & 0xc0 != 0 {
+        return None;
+    }
+    Option::from(G1Affine::from_xy(
+        read_fq(&bytes[..32])?,
+        read_fq(&bytes[32..])?,
+    ))
+}
+
+/// Parses an uncompressed G2 point of a challenge file,
+/// `x.c1 || x.c0 || y.c1 || y.c0`.
+fn read_g2(bytes: &[u8]) -> Option<G2Affine> {
+    if bytes[0] & 0xc0 != 0 {
+        return None;
+    }
+    let x = Fq2 {
+        c0: read_fq(&bytes[32..64])?,
+        c1: read_fq(&bytes[..32])?,
+    };
+    let y = Fq2 {
+        c0: read_fq(&bytes[96..])?,
+        c1: read_fq(&bytes[64..96])?,
+    };
+    Option::from(G2Affine::from_xy(x, y))
+}
+
+/// Computes the FFT of `points` over the domain generated by `omega`, in
+/// place, using up to `threads` threads.
+fn fft(points: &mut [G1], omega: Fr, threads: usize) {
+    let n = points.len();
+    if n == 1 {
+        return;
+    }
+
+    let mut even: Vec<G1> = points.iter().step_by(2).copied().collect();
+    let mut odd: Vec<G1> = points.iter().skip(1).step_by(2).copied().collect();
+    let omega_sq = omega.square();
+    if threads > 1 {
+        std::thread::scope(|scope| {
+            scope.spawn(|| fft(&mut even, omega_sq, threads / 2));
+            fft(&mut odd, omega_sq, threads - threads / 2);
+        });
+    } else {
+        fft(&mut even, omega_sq, 1);
+        fft(&mut odd, omega_sq, 1);
+    }
+
+    let butterflies = |lo: &mut [G1], hi: &mut [G1], even: &[G1], odd: &[G1], start: usize| {
+        let mut w = omega.pow_vartime(&[start as u64]);
+        for (((lo, hi), even), odd) in lo.iter_mut().zip(hi.iter_mut()).zip(even).zip(odd) {
+            let t = *odd * w;
+            *lo = *even + t;
+            *hi = *even - t;
+            w *= omega;
+        }
+    };
+    let (lo, hi) = points.split_at_mut(n / 2);
+    if threads > 1 {
+        let chunk = (n / 2 + threads - 1) / threads;
+        let butterflies = &butterflies;
+        std::thread::scope(|scope| {
+            for (i, ((lo, hi), (even, odd))) in lo
+                .chunks_mut(chunk)
+                .zip(hi.chunks_mut(chunk))
+                .zip(even.chunks(chunk).zip(odd.chunks(chunk)))
+                .enumerate()
+            {
+                scope.spawn(move || butterflies(lo, hi, even, odd, i * chunk));
+            }
+        });
+    } else {
+        butterflies(lo, hi, &even, &odd, 0);
+    }
+}
+
+/// Converts the tau powers of the challenge file of a powers of tau ceremony
+/// at `ppot_path`, as created by the perpetual powers of tau on BN254 and
+/// `reduce_powers` of phase2-bn254, into params of degree `k` written to
+/// `params_path` in the format of `Params::write`.
+/// Unlike `Params::unsafe_setup`, nobody knows the secret of the params as
+/// long as any contributor to the ceremony discarded theirs.
+/// Returns the source of the params, to be stored in their metadata.
+pub fn import_ppot(ppot_path: &Path, k: u32, params_path: &Path) -> Result<ParamsSource, String> {
+    let ppot_name = ppot_path.display();
+    let io_err = |err: std::io::Error| format!("{}: {}", ppot_name, err);
+    let mut ppot = BufReader::new(File::open(ppot_path).map_err(io_err)?);
+
+    let len = ppot.get_ref().metadata().map_err(io_err)?.len();
+    let degree = ppot_degree(len).ok_or_else(|| {
+        format!(
+            "{}: not an uncompressed challenge file, of unexpected size {}",
+            ppot_name, len
+        )
+    })?;
+    if degree < k {
+        return Err(format!(
+            "{}: the ceremony has degree {}, less than {}",
+            ppot_name, degree, k
+        ));
+    }
+
+    let mut hash = vec![0u8; PPOT_HASH_SIZE as usize];
+    ppot.read_exact(&mut hash).map_err(io_err)?;
+    let hash: String = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    // the first 2^k tau powers in G1: g, s * g, s^2 * g, ...
+    let n = 1usize << k;
+    let mut bytes = vec![0u8; PPOT_G1_SIZE as usize];
+    let mut g = Vec::with_capacity(n);
+    for i in 0..n {
+        ppot.read_exact(&mut bytes).map_err(io_err)?;
+        g.push(read_g1(&bytes).ok_or_else(|| format!("{}: invalid G1 power {}", ppot_name, i))?);
+    }
+
+    // the first 2 tau powers in G2: g2, s * g2
+    let g2_offset = PPOT_HASH_SIZE + ((2 << degree) - 1) * PPOT_G1_SIZE;
+    ppot.seek(SeekFrom::Start(g2_offset)).map_err(io_err)?;
+    let mut bytes = vec![0u8; PPOT_G2_SIZE as usize];
+    let mut g2 = Vec::with_capacity(2);
+    for i in 0..2 {
+        ppot.read_exact(&mut bytes).map_err(io_err)?;
+        g2.push(read_g2(&bytes).ok_or_else(|| format!("{}: invalid G2 power {}", ppot_name, i))?);
+    }
+
+    if g[0] != G1Affine::generator() || g2[0] != G2Affine::generator() {
+        return Err(format!(
+            "{}: the first powers aren't the generators",
+            ppot_name
+        ));
+    }
+
+    // the lagrange basis of the domain of size 2^k, the inverse FFT of `g`
+    let omega = Fr::root_of_unity().pow_vartime(&[1u64 << (Fr::S - k)]);
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+    let mut g_lagrange: Vec<G1> = g.iter().map(|point| point.to_curve()).collect();
+    fft(&mut g_lagrange, omega.invert().unwrap(), threads);
+    let n_inv = Fr::from(n as u64).invert().unwrap();
+    let g_lagrange: Vec<G1> = g_lagrange.into_iter().map(|point| point * n_inv).collect();
+    let mut g_lagrange_affine = vec![G1Affine::identity(); n];
+    G1::batch_normalize(&g_lagrange, &mut g_lagrange_affine);
+
+    let params_name = params_path.display();
+    let io_err = |err: std::io::Error| format!("{}: {}", params_name, err);
+    let mut params = BufWriter::new(File::create(params_path).map_err(io_err)?);
+    params.write_all(&k.to_le_bytes()).map_err(io_err)?;
+    for point in g.iter().chain(g_lagrange_affine.iter()) {
+        params
+            .write_all(point.to_bytes().as_ref())
+            .map_err(io_err)?;
+    }
+    // the additional data of the KZG params is s * g2
+    let s_g2 = g2[1].to_bytes();
+    params
+        .write_all(&(s_g2.as_ref().len() as u32).to_le_bytes())
+        .map_err(io_err)?;
+    params.write_all(s_g2.as_ref()).map_err(io_err)?;
+    params.flush().map_err(io_err)?;
+
+    Ok(ParamsSource::Ppot { degree, hash })
+}
@@ -1,43 +1,321 @@
-use halo2_proofs::pairing::bn256::G1Affine;
-use halo2_proofs::poly::commitment::Params;
-
-use std::collections::HashMap;
 use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use tokio::sync::Mutex;
+use sha2::{Digest, Sha256};
+use tokio::sync::{mpsc, Mutex};
 
 use crate::compute_proof::compute_proof;
-use crate::structs::{ProofRequestOptions, Proofs};
+use crate::key_cache::KeyCache;
+use crate::params::ParamsCache;
+use crate::structs::{
+    ProofRequestOptions, Proofs, StatusResponse, TaskState, TaskStatus, VerifyRequestOptions,
+    VerifyResult,
+};
+use crate::subprocess::{compute_proof_subprocess, SubprocessConfig, WorkerPool};
+use crate::verify_proof::verify_proof;
 
 #[derive(Debug, Clone)]
 pub struct ProofRequest {
     pub options: ProofRequestOptions,
     pub result: Option<Result<Proofs, String>>,
+    pub state: TaskState,
+    /// when the task was enqueued, in milliseconds since the unix epoch
+    pub created_at: u64,
+    /// when a worker started the task, in milliseconds since the unix epoch
+    pub started_at: Option<u64>,
+    /// when the task completed, in milliseconds since the unix epoch
+    pub finished_at: Option<u64>,
+    /// set by `SharedState::cancel`, the worker computing the task stops at
+    /// its next stage
+    pub cancelled: Arc<AtomicBool>,
+}
+
+impl ProofRequest {
+    fn new(options: &ProofRequestOptions) -> Self {
+        Self {
+            options: options.clone(),
+            result: None,
+            state: TaskState::Queued,
+            created_at: now(),
+            started_at: None,
+            finished_at: None,
+            cancelled: Default::default(),
+        }
+    }
+
+    /// Enqueues the task again, to be a candidate in `duty_cycle`.
+    fn reset(&mut self) {
+        self.result = None;
+        self.state = TaskState::Queued;
+        self.started_at = None;
+        self.finished_at = None;
+        self.cancelled = Default::default();
+    }
+
+    pub fn status(&self) -> TaskStatus {
+        TaskStatus {
+            options: self.options.clone(),
+            state: self.state,
+            error: match &self.result {
+                Some(Err(err)) => Some(err.clone()),
+                _ => None,
+            },
+            created_at: self.created_at,
+            started_at: self.started_at,
+            finished_at: self.finished_at,
+        }
+    }
+}
+
+/// Returns the current time in milliseconds since the unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Writes the tasks to a file, and the proofs of the completed tasks to
+/// files of their own next to it, so that the tasks survive restarts.
+struct Journal {
+    tasks_path: PathBuf,
+    /// the sequence number of the last snapshot of the tasks
+    snapshots: AtomicU64,
+    /// the sequence number of the last snapshot written to `tasks_path`,
+    /// locked while writing it
+    written: std::sync::Mutex<u64>,
+}
+
+impl Journal {
+    fn new(tasks_path: PathBuf) -> Self {
+        Self {
+            tasks_path,
+            snapshots: AtomicU64::new(0),
+            written: std::sync::Mutex::new(0),
+        }
+    }
+
+    /// Reads the tasks persisted to `tasks_path`, if there are any.
+    /// The tasks that were being computed are enqueued again, as are the
+    /// completed tasks whose proofs can't be read.
+    /// A file that can't be read is moved aside and no tasks are loaded.
+    fn load(&self) -> Vec<ProofRequest> {
+        let statuses: Vec<TaskStatus> = match File::open(&self.tasks_path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                serde_json::from_reader(BufReader::new(file)).map_err(|err| err.to_string())
+            }) {
+            Ok(statuses) => statuses,
+            Err(_) if !self.tasks_path.exists() => return Vec::new(),
+            Err(err) => {
+                let corrupt_path = self.tasks_path.with_extension("corrupt");
+                log::error!(
+                    "tasks: failed to read {}, moving it to {}: {}",
+                    self.tasks_path.display(),
+                    corrupt_path.display(),
+                    err
+                );
+                if let Err(err) = std::fs::rename(&self.tasks_path, &corrupt_path) {
+                    log::error!(
+                        "tasks: failed to move {}: {}",
+                        self.tasks_path.display(),
+                        err
+                    );
+                }
+                return Vec::new();
+            }
+        };
+
+        let tasks: Vec<ProofRequest> = statuses
+            .into_iter()
+            .map(|status| {
+                let mut task = ProofRequest {
+                    options: status.options,
+                    result: None,
+                    state: status.state,
+                    created_at: status.created_at,
+                    started_at: status.started_at,
+                    finished_at: status.finished_at,
+                    cancelled: Default::default(),
+                };
+                match status.state {
+                    TaskState::Queued => {}
+                    TaskState::Completed => match self.read_proofs(&task.options) {
+                        Ok(proofs) => task.result = Some(Ok(proofs)),
+                        Err(err) => {
+                            log::warn!("tasks: enqueuing {:?} again: {}", task.options, err);
+                            task.reset();
+                        }
+                    },
+                    TaskState::Failed | TaskState::Cancelled => {
+                        task.result = Some(Err(status.error.unwrap_or_default()))
+                    }
+                    _ => task.reset(),
+                }
+                task
+            })
+            .collect();
+        log::info!(
+            "tasks: loaded {} from {}",
+            tasks.len(),
+            self.tasks_path.display()
+        );
+
+        tasks
+    }
+
+    /// Returns the sequence number of a new snapshot of the tasks.
+    fn next_snapshot(&self) -> u64 {
+        self.snapshots.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Writes the snapshot `seq` of the tasks, unless a later one was
+    /// written already.
+    fn write(&self, seq: u64, tasks: &[TaskStatus]) {
+        let mut written = self.written.lock().unwrap();
+        if *written > seq {
+            return;
+        }
+        if let Err(err) = write_json(&self.tasks_path, &tasks) {
+            log::error!(
+                "tasks: failed to write {}: {}",
+                self.tasks_path.display(),
+                err
+            );
+            return;
+        }
+        *written = seq;
+    }
+
+    /// Returns the path of the proofs of the task of `options`.
+    fn proofs_path(&self, options: &ProofRequestOptions) -> PathBuf {
+        let options = serde_json::to_vec(options).unwrap_or_default();
+        self.tasks_path
+            .with_extension("proofs")
+            .join(format!("{:x}.json", Sha256::digest(&options)))
+    }
+
+    fn read_proofs(&self, options: &ProofRequestOptions) -> Result<Proofs, String> {
+        let path = self.proofs_path(options);
+        let file = File::open(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    fn write_proofs(&self, options: &ProofRequestOptions, proofs: &Proofs) {
+        let path = self.proofs_path(options);
+        let res = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(|err| err.to_string())
+            .and_then(|_| write_json(&path, proofs));
+        if let Err(err) = res {
+            log::error!("tasks: failed to write {}: {}", path.display(), err);
+        }
+    }
+
+    fn remove_proofs(&self, options: &ProofRequestOptions) {
+        let path = self.proofs_path(options);
+        match std::fs::remove_file(&path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                log::error!("tasks: failed to remove {}: {}", path.display(), err)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Writes `value` as json to `path`, via a temporary file first, so that a
+/// crash can't leave a partially written file behind.
+fn write_json(path: &Path, value: &impl serde::Serialize) -> Result<(), String> {
+    let tmp_path = path.with_extension("tmp");
+    File::create(&tmp_path)
+        .map_err(|err| err.to_string())
+        .and_then(|file| {
+            serde_json::to_writer(BufWriter::new(file), value).map_err(|err| err.to_string())
+        })
+        .and_then(|_| std::fs::rename(&tmp_path, path).map_err(|err| err.to_string()))
 }
 
 pub struct RwState {
     pub tasks: Vec<ProofRequest>,
     pub pending_tasks: u32,
-    pub params_cache: HashMap<String, Arc<Params<G1Affine>>>,
 }
 
 #[derive(Clone)]
 pub struct SharedState {
     pub rw: Arc<Mutex<RwState>>,
+    pub params_cache: ParamsCache,
+    pub key_cache: KeyCache,
+    /// the number of tasks computed at the same time
+    pub workers: usize,
+    /// computes the tasks in child processes if set
+    pub subprocess: Option<SubprocessConfig>,
+    /// whether proof requests may set a `seed`, which makes their proofs
+    /// reproducible but not zero-knowledge. Not set by default
+    pub allow_seed: bool,
+    /// the idle child processes, which keep the keys of the tasks they
+    /// computed
+    worker_pool: WorkerPool,
+    journal: Option<Arc<Journal>>,
 }
 
 impl SharedState {
-    pub fn new() -> SharedState {
+    /// `key_cache` caches the keys of the circuits proven in the process of
+    /// the daemon.
+    /// `tasks_path` is an optional file the tasks are persisted to, and
+    /// loaded from, so that they survive restarts. The proofs of the
+    /// completed tasks are persisted to a directory next to it, with the
+    /// `proofs` extension.
+    /// `workers` is the number of tasks computed at the same time.
+    /// `subprocess` computes the tasks in long-lived child processes, which
+    /// keep the keys they generate for the later tasks, instead of in the
+    /// process of the daemon.
+    pub fn new(
+        key_cache: KeyCache,
+        tasks_path: Option<PathBuf>,
+        workers: usize,
+        subprocess: Option<SubprocessConfig>,
+    ) -> SharedState {
+        let journal = tasks_path.map(|tasks_path| Arc::new(Journal::new(tasks_path)));
+        let tasks = journal
+            .as_ref()
+            .map(|journal| journal.load())
+            .unwrap_or_default();
+
         Self {
             rw: Arc::new(Mutex::new(RwState {
-                tasks: Vec::new(),
+                tasks,
                 pending_tasks: 0,
-                params_cache: HashMap::new(),
             })),
+            params_cache: ParamsCache::default(),
+            key_cache,
+            workers,
+            subprocess,
+            allow_seed: false,
+            worker_pool: WorkerPool::default(),
+            journal,
         }
     }
 
+    /// Writes a snapshot of the tasks of `rw` to `tasks_path`, if set.
+    /// The snapshot is taken while `rw` is locked, but written in the
+    /// background.
+    fn save(&self, rw: &RwState) {
+        let journal = match &self.journal {
+            Some(journal) => journal.clone(),
+            None => return,
+        };
+
+        let seq = journal.next_snapshot();
+        let tasks: Vec<TaskStatus> = rw.tasks.iter().map(ProofRequest::status).collect();
+        tokio::task::spawn_blocking(move || journal.write(seq, &tasks));
+    }
+
     /// Will return the result or error of the task if it's completed.
     /// Otherwise enqueues the task and returns `None`.
     /// `retry_if_error` enqueues the task again if it returned with an error
@@ -52,13 +330,13 @@ impl SharedState {
         let task = rw.tasks.iter_mut().find(|e| e.options == *options);
 
         if task.is_some() {
-            let mut task = task.unwrap();
+            let task = task.unwrap();
 
             if task.result.is_some() {
                 if options.retry && task.result.as_ref().unwrap().is_err() {
                     log::debug!("retrying: {:#?}", task);
                     // will be a candidate in `duty_cycle` again
-                    task.result = None;
+                    task.reset();
                 } else {
                     log::debug!("completed: {:#?}", task);
                     return task.result.clone();
@@ -69,72 +347,198 @@ impl SharedState {
             }
         } else {
             // enqueue the task
-            let task = ProofRequest {
-                options: options.clone(),
-                result: None,
-            };
+            let task = ProofRequest::new(options);
             log::debug!("enqueue: {:#?}", task);
             rw.tasks.push(task);
         }
+        self.save(&rw);
 
         None
     }
 
+    /// Cancels the task of `options` if it isn't completed yet.
+    /// A task that is being computed stops at its next stage.
+    /// Returns whether there was such a task.
+    pub async fn cancel(&self, options: &ProofRequestOptions) -> bool {
+        let mut rw = self.rw.lock().await;
+
+        let task = rw
+            .tasks
+            .iter_mut()
+            .find(|e| e.options == *options && e.result.is_none());
+        let task = match task {
+            Some(task) => task,
+            None => return false,
+        };
+
+        log::info!("cancel: {:#?}", task.options);
+        task.cancelled.store(true, Ordering::Relaxed);
+        if task.state == TaskState::Queued {
+            task.state = TaskState::Cancelled;
+            task.result = Some(Err("cancelled".to_string()));
+            task.finished_at = Some(now());
+        }
+        self.save(&rw);
+
+        true
+    }
+
+    /// Removes the tasks for which `remove` returns true.
+    pub async fn flush(&self, remove: impl Fn(&ProofRequest) -> bool) {
+        let mut rw = self.rw.lock().await;
+        let (removed, tasks): (Vec<_>, Vec<_>) = std::mem::take(&mut rw.tasks)
+            .into_iter()
+            .partition(|task| remove(task));
+        rw.tasks = tasks;
+        self.save(&rw);
+        drop(rw);
+
+        if let Some(journal) = self.journal.clone() {
+            tokio::task::spawn_blocking(move || {
+                for task in removed
+                    .iter()
+                    .filter(|task| task.state == TaskState::Completed)
+                {
+                    journal.remove_proofs(&task.options);
+                }
+            });
+        }
+    }
+
+    /// Returns whether there are tasks pending, along with the status of all
+    /// the tasks.
+    pub async fn status(&self) -> (bool, StatusResponse) {
+        let rw = self.rw.lock().await;
+        let is_busy = rw.pending_tasks > 0 || rw.tasks.iter().any(|e| e.result.is_none());
+
+        (
+            is_busy,
+            StatusResponse {
+                workers: self.workers,
+                tasks: rw.tasks.iter().map(ProofRequest::status).collect(),
+            },
+        )
+    }
+
+    /// Records the stage of the task of `options`, which is being computed.
+    async fn set_state(&self, options: &ProofRequestOptions, state: TaskState) {
+        let mut rw = self.rw.lock().await;
+
+        let task = rw
+            .tasks
+            .iter_mut()
+            .find(|e| e.options == *options && e.state.is_running());
+        if let Some(task) = task {
+            task.state = state;
+            self.save(&rw);
+        }
+    }
+
     /// Checks if there is anything to do like:
     /// - records if a task completed
     /// - starting a new task
+    /// Up to `self.workers` tasks are computed at the same time, so it's
+    /// meant to be called by that many loops.
     /// Blocks until completion but releases the lock of `self.rw` in between.
     pub async fn duty_cycle(&self) {
         let mut rw = self.rw.lock().await;
 
-        if rw.pending_tasks > 0 {
-            // already computing
-            // TODO: can spawn multiple tasks if ever required
+        if rw.pending_tasks as usize >= self.workers {
+            // all the workers are computing
             return;
         }
 
         // find a pending task
-        let pending_task = rw.tasks.iter().find(|&e| e.result.is_none());
+        let pending_task = rw.tasks.iter_mut().find(|e| e.state == TaskState::Queued);
         if pending_task.is_none() {
             // nothing to do
             return;
         }
 
+        // mark the task as started so that other workers skip it
+        let pending_task = pending_task.unwrap();
+        pending_task.state = TaskState::Fetching;
+        pending_task.started_at = Some(now());
+
         // needs to be cloned because of long running tasks and
         // the possibility that the task gets removed in the meantime
-        let mut pending_task = pending_task.unwrap().clone();
+        let mut pending_task = pending_task.clone();
         {
             rw.pending_tasks += 1;
+            self.save(&rw);
             log::info!("compute_proof: {:#?}", pending_task);
             drop(rw);
         }
 
+        // records the stages reported by `compute_proof`
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+        let self_copy = self.clone();
+        let options = pending_task.options.clone();
+        let progress_updates = tokio::spawn(async move {
+            while let Some(state) = progress_rx.recv().await {
+                self_copy.set_state(&options, state).await;
+            }
+        });
+
         // Note: this catches any panics for the task itself but will not help in the
         // situation when the process get itself OOM killed, stack overflows etc.
-        // This could be avoided by spawning a subprocess for the proof computation
-        // instead.
+        // unless the proof computation runs in a subprocess.
 
-        let pending_task_copy = pending_task.clone();
-        let self_copy = self.clone();
+        let cancelled = pending_task.cancelled.clone();
+        let options = pending_task.options.clone();
         let task_result: Result<Result<Proofs, String>, tokio::task::JoinError> =
-            tokio::spawn(async move {
-                // lazily load the file and cache it
-                let param = self_copy.load_param(&pending_task_copy.options.param).await;
-                let res = compute_proof(
-                    param.as_ref(),
-                    &pending_task_copy.options.block,
-                    &pending_task_copy.options.rpc,
-                )
-                .await;
-
-                if let Err(err) = res {
-                    // cast Error to string
-                    return Err(err.to_string());
+            match self.subprocess.clone() {
+                Some(subprocess) => {
+                    let worker_pool = self.worker_pool.clone();
+                    tokio::spawn(async move {
+                        compute_proof_subprocess(
+                            &subprocess,
+                            &worker_pool,
+                            &options,
+                            &cancelled,
+                            |state| {
+                                let _ = progress_tx.send(state);
+                            },
+                        )
+                        .await
+                    })
+                    .await
                 }
-
-                Ok(res.unwrap())
-            })
-            .await;
+                None => {
+                    let self_copy = self.clone();
+                    let runtime = tokio::runtime::Handle::current();
+                    // the proof computation is blocking, apart from fetching the
+                    // traces, so it runs on a thread of its own rather than on
+                    // the threads of the runtime
+                    tokio::task::spawn_blocking(move || {
+                        let progress = move |state: TaskState| {
+                            if cancelled.load(Ordering::Relaxed) {
+                                return Err("cancelled".to_string());
+                            }
+                            // the updates stop once the task is done
+                            let _ = progress_tx.send(state);
+                            Ok(())
+                        };
+
+                        // the params are lazily loaded and cached
+                        runtime
+                            .block_on(compute_proof(
+                                &self_copy.params_cache,
+                                &options.param,
+                                &self_copy.key_cache,
+                                &progress,
+                                &options.block,
+                                &options.rpc,
+                                &options.circuits,
+                                options.seed,
+                            ))
+                            // cast Error to string
+                            .map_err(|err| err.to_string())
+                    })
+                    .await
+                }
+            };
+        let _ = progress_updates.await;
 
         // convert the JoinError to string - if applicable
         let task_result: Result<Proofs, String> = match task_result {
@@ -155,6 +559,21 @@ impl SharedState {
             Ok(val) => val,
         };
 
+        // the proofs are written before the task is recorded as completed, so
+        // that the tasks file never refers to missing proofs
+        let task_result = match (self.journal.clone(), task_result) {
+            (Some(journal), Ok(proofs)) => {
+                let options = pending_task.options.clone();
+                tokio::task::spawn_blocking(move || {
+                    journal.write_proofs(&options, &proofs);
+                    proofs
+                })
+                .await
+                .map_err(|err| err.to_string())
+            }
+            (_, task_result) => task_result,
+        };
+
         {
             // done, update the queue
             log::info!("task_result: {:#?}", task_result);
@@ -162,6 +581,11 @@ impl SharedState {
             let mut rw = self.rw.lock().await;
             rw.pending_tasks -= 1;
 
+            let state = match &task_result {
+                Ok(_) => TaskState::Completed,
+                Err(_) if pending_task.cancelled.load(Ordering::Relaxed) => TaskState::Cancelled,
+                Err(_) => TaskState::Failed,
+            };
             let task = rw
                 .tasks
                 .iter_mut()
@@ -169,41 +593,36 @@ impl SharedState {
             if let Some(task) = task {
                 // found our task, update result
                 task.result = Some(task_result);
+                task.state = state;
+                task.finished_at = Some(now());
             } else {
                 // task was already removed in the meantime, insert it again
                 pending_task.result = Some(task_result);
+                pending_task.state = state;
+                pending_task.finished_at = Some(now());
                 rw.tasks.push(pending_task);
             }
+            self.save(&rw);
         }
     }
 
-    async fn load_param(&self, params_path: &str) -> Arc<Params<G1Affine>> {
-        let mut rw = self.rw.lock().await;
-
-        if !rw.params_cache.contains_key(params_path) {
-            // drop, potentially long running
-            drop(rw);
-
-            // load polynomial commitment parameters
-            let params_fs = File::open(params_path).expect("couldn't open params");
-            let params: Arc<Params<G1Affine>> = Arc::new(
-                Params::read::<_>(&mut std::io::BufReader::new(params_fs))
-                    .expect("Failed to read params"),
-            );
-
-            // acquire lock and update
-            rw = self.rw.lock().await;
-            rw.params_cache.insert(params_path.to_string(), params);
-
-            log::info!("params: initialized {}", params_path);
-        }
-
-        rw.params_cache.get(params_path).unwrap().clone()
+    /// Verifies the proof of `options` with its params file.
+    pub async fn verify(&self, options: &VerifyRequestOptions) -> Result<VerifyResult, String> {
+        let options = options.clone();
+        let params_cache = self.params_cache.clone();
+        let key_cache = self.key_cache.clone();
+
+        // loading the params, key generation and verification are blocking
+        tokio::task::spawn_blocking(move || {
+            verify_proof(&params_cache, &options.param, &key_cache, &options.proof)
+        })
+        .await
+        .map_err(|err| err.to_string())
     }
 }
 
 impl Default for SharedState {
     fn default() -> Self {
-        Self::new()
+        Self::new(KeyCache::default(), None, 1, None)
     }
 }
@@ -1,10 +1,217 @@
-#[derive(Debug, Clone, serde::Serialize)]
+use bus_mapping::circuit_input_builder::BuilderInputs;
+use eth_types::{ToScalar, Word, H256};
+use ff::PrimeField;
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::pairing::bn256::Fr;
+
+/// The circuits that the prover proves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CircuitKind {
+    Evm,
+    State,
+    Tx,
+    Bytecode,
+    Copy,
+    Super,
+}
+
+impl CircuitKind {
+    pub const ALL: [Self; 6] = [
+        Self::Evm,
+        Self::State,
+        Self::Tx,
+        Self::Bytecode,
+        Self::Copy,
+        Self::Super,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Evm => "evm",
+            Self::State => "state",
+            Self::Tx => "tx",
+            Self::Bytecode => "bytecode",
+            Self::Copy => "copy",
+            Self::Super => "super",
+        }
+    }
+
+    /// Whether the fixed columns of the circuit only depend on its shape, so
+    /// that its keys can be shared by the blocks of that shape.
+    /// The fixed columns of the super circuit still depend on the block.
+    pub fn has_fixed_layout(&self) -> bool {
+        !matches!(self, Self::Super)
+    }
+}
+
+impl std::str::FromStr for CircuitKind {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+            .ok_or_else(|| format!("unknown circuit: {}", name))
+    }
+}
+
+/// Identifies the keys of a circuit, which depend on the params and on the
+/// capacities of the circuit.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyId {
+    /// the params file the keys are generated with
+    pub params_path: String,
+    /// the degree the params are downsized to
+    pub k: u32,
+    pub circuit: CircuitKind,
+    /// the capacities of the circuit, which determine its fixed columns
+    pub shape: Vec<usize>,
+}
+
+impl KeyId {
+    /// Name of the file holding the serialized verifying key
+    pub fn vk_file_name(&self) -> String {
+        let params_name = std::path::Path::new(&self.params_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let shape: Vec<String> = self.shape.iter().map(|n| n.to_string()).collect();
+        format!(
+            "{}_{}_k{}_{}.vk",
+            params_name,
+            self.circuit.name(),
+            self.k,
+            shape.join("_")
+        )
+    }
+}
+
+/// The public inputs of a circuit, which are the powers of the randomness in
+/// its first instance columns, followed by any other instance columns.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PublicInputs {
+    /// the randomness used in the random linear combinations
+    pub randomness: Word,
+    /// the number of instance columns with the powers of the randomness
+    pub num_powers: usize,
+    /// the length of the instance columns with the powers of the randomness
+    pub num_rows: usize,
+    /// the instance columns after the powers of the randomness
+    pub columns: Vec<Vec<Word>>,
+}
+
+impl PublicInputs {
+    pub fn new(randomness: Fr, num_powers: usize, num_rows: usize, columns: &[Vec<Fr>]) -> Self {
+        let to_word = |value: &Fr| Word::from_little_endian(&value.to_repr());
+
+        Self {
+            randomness: to_word(&randomness),
+            num_powers,
+            num_rows,
+            columns: columns
+                .iter()
+                .map(|column| column.iter().map(to_word).collect())
+                .collect(),
+        }
+    }
+
+    /// The instance columns, with the powers of the randomness laid out like
+    /// `StateCircuit::instance`
+    pub fn instance(&self) -> Result<Vec<Vec<Fr>>, String> {
+        let to_scalar = |value: &Word| -> Result<Fr, String> {
+            value
+                .to_scalar()
+                .ok_or_else(|| format!("{} is not a field element", value))
+        };
+        let randomness = to_scalar(&self.randomness)?;
+
+        let mut instance: Vec<Vec<Fr>> = (1..=self.num_powers as u64)
+            .map(|exp| vec![randomness.pow(&[exp, 0, 0, 0]); self.num_rows])
+            .collect();
+        for column in self.columns.iter() {
+            instance.push(column.iter().map(to_scalar).collect::<Result<_, _>>()?);
+        }
+
+        Ok(instance)
+    }
+
+    /// The length of the longest instance column
+    pub fn max_len(&self) -> usize {
+        self.columns
+            .iter()
+            .map(|column| column.len())
+            .chain(std::iter::once(self.num_rows))
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// A proof of a circuit along with what's needed to verify it
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CircuitProof {
+    pub circuit: CircuitKind,
+    /// the capacities of the circuit, see [`KeyId`]
+    pub shape: Vec<usize>,
+    /// the minimal degree of the circuit
+    pub k: u32,
+    pub public_inputs: PublicInputs,
+    pub proof: eth_types::Bytes,
+}
+
+/// The proofs of the requested circuits
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
 pub struct Proofs {
-    pub state_proof: eth_types::Bytes,
-    pub evm_proof: eth_types::Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_proof: Option<CircuitProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub evm_proof: Option<CircuitProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_proof: Option<CircuitProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytecode_proof: Option<CircuitProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copy_proof: Option<CircuitProof>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub super_proof: Option<CircuitProof>,
     pub duration: u64,
 }
 
+impl Proofs {
+    /// Sets the proof of the circuit of `proof`
+    pub fn insert(&mut self, proof: CircuitProof) {
+        let entry = match proof.circuit {
+            CircuitKind::Evm => &mut self.evm_proof,
+            CircuitKind::State => &mut self.state_proof,
+            CircuitKind::Tx => &mut self.tx_proof,
+            CircuitKind::Bytecode => &mut self.bytecode_proof,
+            CircuitKind::Copy => &mut self.copy_proof,
+            CircuitKind::Super => &mut self.super_proof,
+        };
+        *entry = Some(proof);
+    }
+}
+
+/// The outcome of verifying a [`CircuitProof`]
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct VerifyResult {
+    pub circuit: CircuitKind,
+    /// whether the proof is valid
+    pub valid: bool,
+    /// why the proof couldn't be verified, if it's invalid
+    pub error: Option<String>,
+    pub duration: u64,
+}
+
+/// The inputs of the circuits of a block as queried from geth, which are
+/// enough to prove the block without network access
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WitnessInputs {
+    pub builder: BuilderInputs,
+    /// the state root of the parent block
+    pub prev_state_root: H256,
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct JsonRpcError {
     pub code: i32,
@@ -32,7 +239,7 @@ pub struct JsonRpcRequest<T: serde::Serialize> {
     pub params: T,
 }
 
-#[derive(Debug, Default, Clone, serde::Deserialize)]
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ProofRequestOptions {
     /// the block number
     pub block: u64,
@@ -40,12 +247,110 @@ pub struct ProofRequestOptions {
     pub rpc: String,
     /// retry proof computation if error
     pub retry: bool,
-    /// parameter file to use
+    /// parameter file, or directory of parameter files, to use
+    pub param: String,
+    /// the circuits to prove
+    #[serde(default = "ProofRequestOptions::default_circuits")]
+    pub circuits: Vec<CircuitKind>,
+    /// a seed for the randomness of the proofs, for reproducible proofs in
+    /// tests. The randomness is drawn from the OS if not set. Only accepted
+    /// by prover_rpcd if ALLOW_PROVER_SEED is set
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl ProofRequestOptions {
+    /// The circuits proven if a request doesn't select any
+    pub fn default_circuits() -> Vec<CircuitKind> {
+        vec![CircuitKind::Evm, CircuitKind::State]
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct WitnessRequestOptions {
+    /// the block number
+    pub block: u64,
+    /// the rpc url
+    pub rpc: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct VerifyRequestOptions {
+    /// the proof to verify, as returned by the `proof` method
+    pub proof: CircuitProof,
+    /// parameter file, or directory of parameter files, the proof was
+    /// created with
     pub param: String,
 }
 
 impl PartialEq for ProofRequestOptions {
     fn eq(&self, other: &Self) -> bool {
-        self.block == other.block && self.rpc == other.rpc && self.param == other.param
+        self.block == other.block
+            && self.rpc == other.rpc
+            && self.param == other.param
+            && self.circuits == other.circuits
+            && self.seed == other.seed
+    }
+}
+
+/// The progress of a proof task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskState {
+    /// waiting for a worker
+    Queued,
+    /// gathering the traces of the block from the rpc
+    Fetching,
+    /// converting the traces into the witness of the circuits
+    Witness,
+    /// generating the keys of a circuit
+    Keygen,
+    /// creating the proof of a circuit
+    Proving,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl TaskState {
+    /// Whether a worker is computing the task
+    pub fn is_running(&self) -> bool {
+        matches!(
+            self,
+            Self::Fetching | Self::Witness | Self::Keygen | Self::Proving
+        )
     }
 }
+
+/// A message printed by `prover_cmd --worker` to stdout, one per line
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskMessage {
+    /// the stage the proof computation is at
+    Progress(TaskState),
+    /// the outcome of the proof computation, printed last
+    Result(Result<Proofs, String>),
+}
+
+/// The status of a proof task, as reported by `/status`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TaskStatus {
+    pub options: ProofRequestOptions,
+    pub state: TaskState,
+    /// the error of the task, if it failed
+    pub error: Option<String>,
+    /// when the task was enqueued, in milliseconds since the unix epoch
+    pub created_at: u64,
+    /// when a worker started the task, in milliseconds since the unix epoch
+    pub started_at: Option<u64>,
+    /// when the task completed, in milliseconds since the unix epoch
+    pub finished_at: Option<u64>,
+}
+
+/// The response of `/status`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StatusResponse {
+    /// the number of tasks that can be computed at the same time
+    pub workers: usize,
+    pub tasks: Vec<TaskStatus>,
+}
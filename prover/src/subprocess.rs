@@ -0,0 +1,174 @@
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::time::Instant;
+
+use crate::structs::{ProofRequestOptions, Proofs, TaskMessage, TaskState};
+
+/// How the proofs of the tasks are computed in child processes, which isolate
+/// the daemon from tasks that run out of memory or crash.
+#[derive(Debug, Clone)]
+pub struct SubprocessConfig {
+    /// the `prover_cmd` binary, which is invoked with `--worker`
+    pub prover_cmd: PathBuf,
+    /// the time after which the child process computing a task is killed
+    pub timeout: Option<Duration>,
+    /// the maximum size of the address space of the child process, in bytes
+    pub memory_limit: Option<u64>,
+}
+
+/// Limits the address space of the calling process to `limit` bytes, so that
+/// allocations beyond it fail.
+fn set_memory_limit(limit: u64) -> std::io::Result<()> {
+    let rlimit = libc::rlimit {
+        rlim_cur: limit as libc::rlim_t,
+        rlim_max: limit as libc::rlim_t,
+    };
+    // SAFETY: `setrlimit` only reads the struct it's given
+    match unsafe { libc::setrlimit(libc::RLIMIT_AS, &rlimit) } {
+        0 => Ok(()),
+        _ => Err(std::io::Error::last_os_error()),
+    }
+}
+
+/// Describes why the child process exited without a result.
+fn exit_error(status: ExitStatus) -> String {
+    match status.signal() {
+        Some(signal) => format!(
+            "prover_cmd was killed by signal {}, e.g. because it ran out of memory",
+            signal
+        ),
+        None => format!("prover_cmd exited with {} without a result", status),
+    }
+}
+
+/// A `prover_cmd --worker` child process, which computes the tasks written
+/// to its stdin one after another, so that the keys it generates for a task
+/// are reused by the later tasks of circuits of the same shape.
+struct Worker {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+impl Worker {
+    fn spawn(config: &SubprocessConfig) -> Result<Self, String> {
+        let mut cmd = Command::new(&config.prover_cmd);
+        cmd.arg("--worker")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(limit) = config.memory_limit {
+            // SAFETY: `set_memory_limit` is async-signal-safe, as required between
+            // fork and exec
+            unsafe {
+                cmd.pre_exec(move || set_memory_limit(limit));
+            }
+        }
+        let mut child = cmd
+            .spawn()
+            .map_err(|err| format!("failed to spawn {}: {}", config.prover_cmd.display(), err))?;
+        log::info!("prover_cmd: spawned worker {:?}", child.id());
+
+        Ok(Self {
+            stdin: child.stdin.take().expect("piped stdin"),
+            stdout: BufReader::new(child.stdout.take().expect("piped stdout")).lines(),
+            child,
+        })
+    }
+}
+
+/// The idle worker child processes, which compute the next tasks.
+/// A worker is taken out of the pool while it computes a task, and only
+/// returned to it if it completed the task, so that there are at most as
+/// many workers as tasks computed at the same time.
+#[derive(Clone, Default)]
+pub struct WorkerPool(Arc<Mutex<Vec<Worker>>>);
+
+impl WorkerPool {
+    /// Returns an idle worker that is still running, if there is one.
+    fn take(&self) -> Option<Worker> {
+        let mut idle = self.0.lock().unwrap();
+        while let Some(mut worker) = idle.pop() {
+            if let Ok(None) = worker.child.try_wait() {
+                return Some(worker);
+            }
+        }
+        None
+    }
+
+    fn put(&self, worker: Worker) {
+        self.0.lock().unwrap().push(worker);
+    }
+}
+
+/// Computes the proofs of `options` in a worker child process of `pool`,
+/// which is spawned via `prover_cmd --worker` if there is no idle one, by
+/// writing the task encoded as a line to its stdin.
+/// The stages the child process reports are passed to `progress`.
+/// The child process is killed once `cancelled` is set or the task times out.
+pub async fn compute_proof_subprocess(
+    config: &SubprocessConfig,
+    pool: &WorkerPool,
+    options: &ProofRequestOptions,
+    cancelled: &AtomicBool,
+    progress: impl Fn(TaskState),
+) -> Result<Proofs, String> {
+    let mut worker = match pool.take() {
+        Some(worker) => worker,
+        None => Worker::spawn(config)?,
+    };
+
+    let mut task = serde_json::to_vec(options).map_err(|err| err.to_string())?;
+    task.push(b'\n');
+    worker
+        .stdin
+        .write_all(&task)
+        .await
+        .map_err(|err| format!("failed to write the task to prover_cmd: {}", err))?;
+
+    let deadline = config.timeout.map(|timeout| Instant::now() + timeout);
+    let mut interval = tokio::time::interval(Duration::from_millis(1000));
+    loop {
+        tokio::select! {
+            line = worker.stdout.next_line() => {
+                let line = match line.map_err(|err| err.to_string())? {
+                    Some(line) => line,
+                    // the child process closed its stdout, it's exiting
+                    None => break,
+                };
+                match serde_json::from_str(&line) {
+                    Ok(TaskMessage::Progress(state)) => progress(state),
+                    Ok(TaskMessage::Result(res)) => {
+                        // the worker is ready for the next task
+                        pool.put(worker);
+                        return res;
+                    }
+                    Err(err) => log::warn!("prover_cmd: unexpected output {}: {}", line, err),
+                }
+            }
+            _ = interval.tick() => {
+                if cancelled.load(Ordering::Relaxed) {
+                    let _ = worker.child.kill().await;
+                    return Err("cancelled".to_string());
+                }
+                if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+                    let _ = worker.child.kill().await;
+                    return Err(format!(
+                        "prover_cmd timed out after {}s",
+                        config.timeout.unwrap_or_default().as_secs()
+                    ));
+                }
+            }
+        }
+    }
+
+    let status = worker.child.wait().await.map_err(|err| err.to_string())?;
+    Err(exit_error(status))
+}
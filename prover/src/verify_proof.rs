@@ -0,0 +1,109 @@
+use halo2_proofs::{
+    pairing::bn256::{Bn256, Fr, G1Affine},
+    plonk::{Circuit, SingleVerifier, VerifyingKey},
+    poly::commitment::{Params, ParamsVerifier},
+    transcript::{Blake2bRead, Challenge255},
+};
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use zkevm_circuits::super_circuit::SuperCircuit;
+
+use crate::circuits::*;
+use crate::key_cache::KeyCache;
+use crate::params::ParamsCache;
+use crate::structs::{CircuitKind, CircuitProof, KeyId, VerifyResult};
+
+/// Returns the verifying key of `id`, taken from `key_cache` or else
+/// generated from the circuit returned by `keygen_circuit`.
+fn get_vk<C: Circuit<Fr>>(
+    params: &Params<G1Affine>,
+    key_cache: &KeyCache,
+    id: &KeyId,
+    keygen_circuit: impl FnOnce(&[usize]) -> Result<C, String>,
+) -> Result<Arc<VerifyingKey<G1Affine>>, Box<dyn std::error::Error>> {
+    if let Some(vk) = key_cache.get_vk::<C>(params, id) {
+        return Ok(vk);
+    }
+    Ok(key_cache.get_or_gen_vk(params, id, &keygen_circuit(&id.shape)?)?)
+}
+
+/// The keys of circuits whose fixed columns depend on the witness can't be
+/// generated from their shape, they have to be generated with the `gen_keys`
+/// tool instead.
+fn no_keygen_circuit<C>(kind: CircuitKind) -> impl FnOnce(&[usize]) -> Result<C, String> {
+    move |_: &[usize]| {
+        Err(format!(
+            "the keys of the {} circuit can't be generated without a block, see gen_keys",
+            kind.name()
+        ))
+    }
+}
+
+fn verify(
+    params_cache: &ParamsCache,
+    params_path: &str,
+    key_cache: &KeyCache,
+    proof: &CircuitProof,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (params_path, params) = params_cache.get(params_path, proof.k)?;
+    let params = params.as_ref();
+
+    let key_id = KeyId {
+        params_path,
+        k: proof.k,
+        circuit: proof.circuit,
+        shape: proof.shape.clone(),
+    };
+    let vk = match proof.circuit {
+        CircuitKind::Evm => get_vk(params, key_cache, &key_id, evm_keygen_circuit)?,
+        CircuitKind::State => get_vk(params, key_cache, &key_id, state_keygen_circuit)?,
+        CircuitKind::Tx => get_vk(params, key_cache, &key_id, tx_keygen_circuit)?,
+        CircuitKind::Bytecode => get_vk(params, key_cache, &key_id, bytecode_keygen_circuit)?,
+        CircuitKind::Copy => get_vk(params, key_cache, &key_id, copy_keygen_circuit)?,
+        CircuitKind::Super => get_vk(
+            params,
+            key_cache,
+            &key_id,
+            no_keygen_circuit::<SuperCircuit<Fr, MAX_TXS, MAX_CALLDATA>>(proof.circuit),
+        )?,
+    };
+
+    let instance = proof.public_inputs.instance()?;
+    let instance: Vec<&[Fr]> = instance.iter().map(|column| &column[..]).collect();
+
+    let verifier_params: ParamsVerifier<Bn256> = params.verifier(proof.public_inputs.max_len())?;
+    let strategy = SingleVerifier::new(&verifier_params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof.proof[..]);
+    halo2_proofs::plonk::verify_proof(
+        &verifier_params,
+        &vk,
+        strategy,
+        &[&instance],
+        &mut transcript,
+    )?;
+
+    Ok(())
+}
+
+/// Verifies `proof` with params created via the `gen_params` tool, read from
+/// `params_path`, which has to be the params file or directory the proof was
+/// created with. The params are downsized to the degree of the proof.
+/// The verifying keys are taken from `key_cache`.
+pub fn verify_proof(
+    params_cache: &ParamsCache,
+    params_path: &str,
+    key_cache: &KeyCache,
+    proof: &CircuitProof,
+) -> VerifyResult {
+    let time_started = Instant::now();
+    let res = verify(params_cache, params_path, key_cache, proof);
+
+    VerifyResult {
+        circuit: proof.circuit,
+        valid: res.is_ok(),
+        error: res.err().map(|err| err.to_string()),
+        duration: Instant::now().duration_since(time_started).as_millis() as u64,
+    }
+}
@@ -0,0 +1,390 @@
+use bus_mapping::circuit_input_builder::{CopyEvent, NumberOrHash};
+use eth_types::{Address, Bytes, Word};
+use zkevm_circuits::evm_circuit::witness::{
+    BlockContext, Bytecode, Call, ExecStep, Rw, Transaction,
+};
+
+use std::collections::BTreeMap;
+
+use crate::circuits::{circuit_stats, BlockWitness, CircuitStats};
+
+/// The witness of the circuits of a block along with the sizes of the
+/// circuits, as returned by the `witness` method of prover_rpcd.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WitnessResponse {
+    pub block: WitnessBlock,
+    pub circuits: Vec<CircuitStats>,
+}
+
+impl From<&BlockWitness> for WitnessResponse {
+    fn from(witness: &BlockWitness) -> Self {
+        Self {
+            block: WitnessBlock::from(witness),
+            circuits: circuit_stats(witness),
+        }
+    }
+}
+
+/// A serializable form of a
+/// [`Block`](zkevm_circuits::evm_circuit::witness::Block)
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WitnessBlock {
+    pub randomness: String,
+    pub context: WitnessContext,
+    pub max_evm_rows: usize,
+    pub max_rws: usize,
+    pub max_txs: usize,
+    pub txs: Vec<WitnessTx>,
+    /// the rws of each `RwTableTag`, in the order they are indexed by the
+    /// steps
+    pub rws: BTreeMap<String, Vec<WitnessRw>>,
+    /// the copy events, ordered by (tx_id, call_id, pc)
+    pub copy_events: Vec<WitnessCopyEvent>,
+    pub bytecodes: Vec<WitnessBytecode>,
+}
+
+impl From<&BlockWitness> for WitnessBlock {
+    fn from(witness: &BlockWitness) -> Self {
+        let block = &witness.block;
+
+        let mut copy_events: Vec<_> = block.copy_events.iter().collect();
+        copy_events.sort_by_key(|(key, _)| **key);
+        let mut bytecodes: Vec<_> = block
+            .bytecodes
+            .values()
+            .map(WitnessBytecode::from)
+            .collect();
+        bytecodes.sort_by_key(|bytecode| bytecode.hash);
+
+        Self {
+            randomness: format!("{:?}", block.randomness),
+            context: WitnessContext::from(&block.context),
+            max_evm_rows: block.max_evm_rows,
+            max_rws: block.max_rws,
+            max_txs: block.max_txs,
+            txs: block.txs.iter().map(WitnessTx::from).collect(),
+            rws: block
+                .rws
+                .0
+                .iter()
+                .map(|(tag, rws)| {
+                    (
+                        format!("{:?}", tag),
+                        rws.iter().map(WitnessRw::from).collect(),
+                    )
+                })
+                .collect(),
+            copy_events: copy_events
+                .into_iter()
+                .map(|(_, event)| WitnessCopyEvent::from(event))
+                .collect(),
+            bytecodes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WitnessContext {
+    pub coinbase: Address,
+    pub gas_limit: u64,
+    pub number: Word,
+    pub timestamp: Word,
+    pub difficulty: Word,
+    pub base_fee: Word,
+    pub history_hashes: Vec<Word>,
+    pub chain_id: Word,
+}
+
+impl From<&BlockContext> for WitnessContext {
+    fn from(context: &BlockContext) -> Self {
+        Self {
+            coinbase: context.coinbase,
+            gas_limit: context.gas_limit,
+            number: context.number,
+            timestamp: context.timestamp,
+            difficulty: context.difficulty,
+            base_fee: context.base_fee,
+            history_hashes: context.history_hashes.clone(),
+            chain_id: context.chain_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WitnessTx {
+    pub id: usize,
+    pub nonce: u64,
+    pub gas: u64,
+    pub gas_price: Word,
+    pub caller_address: Address,
+    pub callee_address: Address,
+    pub is_create: bool,
+    pub value: Word,
+    pub call_data: Bytes,
+    pub call_data_gas_cost: u64,
+    pub block_number: u64,
+    pub calls: Vec<WitnessCall>,
+    pub steps: Vec<WitnessStep>,
+}
+
+impl From<&Transaction> for WitnessTx {
+    fn from(tx: &Transaction) -> Self {
+        Self {
+            id: tx.id,
+            nonce: tx.nonce,
+            gas: tx.gas,
+            gas_price: tx.gas_price,
+            caller_address: tx.caller_address,
+            callee_address: tx.callee_address,
+            is_create: tx.is_create,
+            value: tx.value,
+            call_data: tx.call_data.clone().into(),
+            call_data_gas_cost: tx.call_data_gas_cost,
+            block_number: tx.block_number,
+            calls: tx.calls.iter().map(WitnessCall::from).collect(),
+            steps: tx.steps.iter().map(WitnessStep::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WitnessCall {
+    pub id: usize,
+    pub is_root: bool,
+    pub is_create: bool,
+    pub code_hash: Word,
+    pub rw_counter_end_of_reversion: usize,
+    pub caller_id: usize,
+    pub depth: usize,
+    pub caller_address: Address,
+    pub callee_address: Address,
+    pub call_data_offset: u64,
+    pub call_data_length: u64,
+    pub return_data_offset: u64,
+    pub return_data_length: u64,
+    pub value: Word,
+    pub is_success: bool,
+    pub is_persistent: bool,
+    pub is_static: bool,
+}
+
+impl From<&Call> for WitnessCall {
+    fn from(call: &Call) -> Self {
+        Self {
+            id: call.id,
+            is_root: call.is_root,
+            is_create: call.is_create,
+            code_hash: call.code_hash,
+            rw_counter_end_of_reversion: call.rw_counter_end_of_reversion,
+            caller_id: call.caller_id,
+            depth: call.depth,
+            caller_address: call.caller_address,
+            callee_address: call.callee_address,
+            call_data_offset: call.call_data_offset,
+            call_data_length: call.call_data_length,
+            return_data_offset: call.return_data_offset,
+            return_data_length: call.return_data_length,
+            value: call.value,
+            is_success: call.is_success,
+            is_persistent: call.is_persistent,
+            is_static: call.is_static,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WitnessStep {
+    pub execution_state: String,
+    pub opcode: Option<String>,
+    pub call_index: usize,
+    /// the rws of the step, as indices into the rws of a `RwTableTag`
+    pub rw_indices: Vec<(String, usize)>,
+    pub rw_counter: usize,
+    pub program_counter: u64,
+    pub stack_pointer: usize,
+    pub gas_left: u64,
+    pub gas_cost: u64,
+    pub memory_size: u64,
+    pub reversible_write_counter: usize,
+    pub log_id: usize,
+}
+
+impl From<&ExecStep> for WitnessStep {
+    fn from(step: &ExecStep) -> Self {
+        Self {
+            execution_state: format!("{:?}", step.execution_state),
+            opcode: step.opcode.map(|opcode| format!("{:?}", opcode)),
+            call_index: step.call_index,
+            rw_indices: step
+                .rw_indices
+                .iter()
+                .map(|(tag, index)| (format!("{:?}", tag), *index))
+                .collect(),
+            rw_counter: step.rw_counter,
+            program_counter: step.program_counter,
+            stack_pointer: step.stack_pointer,
+            gas_left: step.gas_left,
+            gas_cost: step.gas_cost,
+            memory_size: step.memory_size,
+            reversible_write_counter: step.reversible_write_counter,
+            log_id: step.log_id,
+        }
+    }
+}
+
+/// A row of the rw table, keyed like in the State Circuit
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WitnessRw {
+    pub rw_counter: usize,
+    pub is_write: bool,
+    pub id: Option<usize>,
+    pub address: Option<Address>,
+    pub field_tag: Option<String>,
+    pub storage_key: Option<Word>,
+    pub value: Word,
+    pub value_prev: Option<Word>,
+    /// the value of a storage slot at the start of the transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub committed_value: Option<Word>,
+}
+
+impl From<&Rw> for WitnessRw {
+    fn from(rw: &Rw) -> Self {
+        let (field_tag, value, value_prev, committed_value) = match *rw {
+            Rw::Start { .. } => (None, Word::zero(), None, None),
+            Rw::TxAccessListAccount {
+                is_warm,
+                is_warm_prev,
+                ..
+            }
+            | Rw::TxAccessListAccountStorage {
+                is_warm,
+                is_warm_prev,
+                ..
+            } => (
+                None,
+                Word::from(is_warm as u64),
+                Some(Word::from(is_warm_prev as u64)),
+                None,
+            ),
+            Rw::TxRefund {
+                value, value_prev, ..
+            } => (None, Word::from(value), Some(Word::from(value_prev)), None),
+            Rw::Account {
+                field_tag,
+                value,
+                value_prev,
+                ..
+            } => (
+                Some(format!("{:?}", field_tag)),
+                value,
+                Some(value_prev),
+                None,
+            ),
+            Rw::AccountStorage {
+                value,
+                value_prev,
+                committed_value,
+                ..
+            } => (None, value, Some(value_prev), Some(committed_value)),
+            Rw::AccountDestructed {
+                is_destructed,
+                is_destructed_prev,
+                ..
+            } => (
+                None,
+                Word::from(is_destructed as u64),
+                Some(Word::from(is_destructed_prev as u64)),
+                None,
+            ),
+            Rw::CallContext {
+                field_tag, value, ..
+            } => (Some(format!("{:?}", field_tag)), value, None, None),
+            Rw::Stack { value, .. } => (None, value, None, None),
+            Rw::Memory { byte, .. } => (None, Word::from(byte), None, None),
+            Rw::TxLog {
+                field_tag, value, ..
+            } => (Some(format!("{:?}", field_tag)), value, None, None),
+            Rw::TxReceipt {
+                field_tag, value, ..
+            } => (
+                Some(format!("{:?}", field_tag)),
+                Word::from(value),
+                None,
+                None,
+            ),
+        };
+
+        Self {
+            rw_counter: rw.rw_counter(),
+            is_write: rw.is_write(),
+            id: rw.id(),
+            address: rw.address(),
+            field_tag,
+            storage_key: rw.storage_key(),
+            value,
+            value_prev,
+            committed_value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WitnessCopyEvent {
+    pub src_type: String,
+    pub src_id: String,
+    pub src_addr: u64,
+    pub src_addr_end: u64,
+    pub dst_type: String,
+    pub dst_id: String,
+    pub dst_addr: u64,
+    pub log_id: Option<u64>,
+    pub length: u64,
+    pub tx_id: usize,
+    pub call_id: usize,
+    pub pc: usize,
+    /// the number of rows of the event in the copy circuit
+    pub num_steps: usize,
+}
+
+fn number_or_hash(id: &NumberOrHash) -> String {
+    match id {
+        NumberOrHash::Number(number) => number.to_string(),
+        NumberOrHash::Hash(hash) => format!("{:?}", hash),
+    }
+}
+
+impl From<&CopyEvent> for WitnessCopyEvent {
+    fn from(event: &CopyEvent) -> Self {
+        Self {
+            src_type: format!("{:?}", event.src_type),
+            src_id: number_or_hash(&event.src_id),
+            src_addr: event.src_addr,
+            src_addr_end: event.src_addr_end,
+            dst_type: format!("{:?}", event.dst_type),
+            dst_id: number_or_hash(&event.dst_id),
+            dst_addr: event.dst_addr,
+            log_id: event.log_id,
+            length: event.length,
+            tx_id: event.tx_id,
+            call_id: event.call_id,
+            pc: event.pc.0,
+            num_steps: event.steps.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WitnessBytecode {
+    pub hash: Word,
+    pub bytes: Bytes,
+}
+
+impl From<&Bytecode> for WitnessBytecode {
+    fn from(bytecode: &Bytecode) -> Self {
+        Self {
+            hash: bytecode.hash,
+            bytes: bytecode.bytes.clone().into(),
+        }
+    }
+}
@@ -0,0 +1,504 @@
+//! The Aggregation circuit verifies the proofs of the zkEVM circuits (EVM,
+//! State, Tx, Bytecode, Copy, Keccak...) in a single circuit, so that a block
+//! is proven by one succinct proof.
+//!
+//! Verifying a KZG proof ends with a pairing check `e(lhs, g2) = e(rhs, s_g2)`
+//! that is too expensive to do in circuit.  Instead, the circuit runs the
+//! verifier of every proof up to the pairing check, which gives a pair of
+//! points `(lhs, rhs)` per proof, called an accumulator, and folds the
+//! accumulators into one by a random linear combination.  The final
+//! accumulator is exposed as instance, and the pairing check is deferred to
+//! the verifier of the aggregation proof, see [`decide`].
+//!
+//! The instance of the circuit is a single column containing:
+//! - The limbs of the coordinates `lhs.x`, `lhs.y`, `rhs.x`, `rhs.y` of the
+//!   final accumulator, [`LIMBS`] limbs of [`BITS`] bits each.
+//! - The instance columns of every aggregated proof, in order.
+//!
+//! The transcript of the aggregated proofs uses the Poseidon hash, which is
+//! cheap to compute in circuit, so they have to be created with
+//! [`Snark::create`] instead of the Blake2b transcript.
+//!
+//! The prover creates such proofs with `prove_snark`, while the proofs it
+//! returns for a block still use the Blake2b transcript and are verified on
+//! their own.
+
+use group::Curve;
+use halo2_proofs::{
+    arithmetic::{CurveAffine, MillerLoopResult, MultiMillerLoop},
+    circuit::{Layouter, SimpleFloorPlanner},
+    pairing::bn256::{Bn256, Fq, Fr, G1Affine, G2Affine, G2Prepared, G1},
+    plonk::{create_proof, Circuit, ConstraintSystem, Error, ProvingKey},
+    poly::commitment::Params,
+};
+use integer::{rns::Rns, NUMBER_OF_LOOKUP_LIMBS};
+use itertools::Itertools;
+use maingate::{
+    MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig, RangeInstructions,
+    RegionCtx,
+};
+use plonk_verifier::{
+    loader::{self, native::NativeLoader},
+    protocol::{
+        halo2::{compile, Config},
+        Protocol,
+    },
+    scheme::kzg::{self, AccumulationScheme, PlonkAccumulationScheme},
+    util::{fe_from_limbs, fe_to_limbs},
+};
+use rand::RngCore;
+use std::rc::Rc;
+
+/// Number of limbs of a coordinate of an accumulator point
+pub const LIMBS: usize = 4;
+/// Number of bits of a limb of a coordinate of an accumulator point
+pub const BITS: usize = 68;
+/// Number of instance cells taken by the accumulator
+pub const ACCUMULATOR_LEN: usize = 4 * LIMBS;
+
+// Poseidon parameters of the transcript of the aggregated proofs
+const T: usize = 5;
+const RATE: usize = 4;
+const R_F: usize = 8;
+const R_P: usize = 60;
+
+type BaseFieldEccChip = ecc::BaseFieldEccChip<G1Affine, LIMBS, BITS>;
+type Halo2Loader<'a, 'b> = loader::halo2::Halo2Loader<'a, 'b, G1Affine, LIMBS, BITS>;
+type PoseidonTranscript<L, S, B> = loader::halo2::PoseidonTranscript<
+    G1Affine,
+    L,
+    S,
+    B,
+    BaseFieldEccChip,
+    LIMBS,
+    BITS,
+    T,
+    RATE,
+    R_F,
+    R_P,
+>;
+type SameCurveAccumulation<L> = kzg::SameCurveAccumulation<G1, L, LIMBS, BITS>;
+
+/// A proof along with the protocol of its circuit and its instance, whose
+/// transcript can be verified by the Aggregation circuit.
+#[derive(Clone)]
+pub struct Snark {
+    protocol: Protocol<G1Affine>,
+    instance: Vec<Vec<Fr>>,
+    proof: Vec<u8>,
+}
+
+impl Snark {
+    /// Create a proof of `circuit` with instance columns `instance`, with the
+    /// proving key `pk` generated from `params`.
+    pub fn create<C: Circuit<Fr>>(
+        params: &Params<G1Affine>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instance: Vec<Vec<Fr>>,
+        rng: impl RngCore,
+    ) -> Result<Self, Error> {
+        let protocol = compile(
+            pk.get_vk(),
+            Config::default()
+                .with_zk(true)
+                .with_query_instance(false)
+                .with_num_instance(instance.iter().map(|column| column.len()).collect()),
+        );
+
+        let columns: Vec<&[Fr]> = instance.iter().map(|column| &column[..]).collect();
+        let mut transcript = PoseidonTranscript::<NativeLoader, _, _>::init(Vec::new());
+        create_proof(params, pk, &[circuit], &[&columns], rng, &mut transcript)?;
+
+        Ok(Self {
+            protocol,
+            instance,
+            proof: transcript.finalize(),
+        })
+    }
+
+    /// Return the instance columns of the proof
+    pub fn instance(&self) -> &[Vec<Fr>] {
+        &self.instance
+    }
+}
+
+/// A [`Snark`] whose instance and proof are unknown at key generation.
+#[derive(Clone)]
+struct SnarkWitness {
+    protocol: Protocol<G1Affine>,
+    instance: Vec<Vec<Option<Fr>>>,
+    proof: Option<Vec<u8>>,
+}
+
+impl From<Snark> for SnarkWitness {
+    fn from(snark: Snark) -> Self {
+        Self {
+            protocol: snark.protocol,
+            instance: snark
+                .instance
+                .into_iter()
+                .map(|column| column.into_iter().map(Some).collect())
+                .collect(),
+            proof: Some(snark.proof),
+        }
+    }
+}
+
+impl SnarkWitness {
+    fn without_witnesses(&self) -> Self {
+        Self {
+            protocol: self.protocol.clone(),
+            instance: self
+                .instance
+                .iter()
+                .map(|column| vec![None; column.len()])
+                .collect(),
+            proof: None,
+        }
+    }
+}
+
+/// Configuration of the Aggregation circuit
+#[derive(Clone, Debug)]
+pub struct AggregationConfig {
+    main_gate_config: MainGateConfig,
+    range_config: RangeConfig,
+}
+
+impl AggregationConfig {
+    fn load_range(&self, layouter: &mut impl Layouter<Fr>) -> Result<(), Error> {
+        let bit_len_lookup = BITS / NUMBER_OF_LOOKUP_LIMBS;
+        let range_chip = RangeChip::<Fr>::new(self.range_config.clone(), bit_len_lookup);
+        range_chip.load_limb_range_table(layouter)?;
+        range_chip.load_overflow_range_tables(layouter)?;
+
+        Ok(())
+    }
+
+    fn ecc_chip_config(&self) -> ecc::EccConfig {
+        ecc::EccConfig::new(self.range_config.clone(), self.main_gate_config.clone())
+    }
+}
+
+/// The Aggregation circuit, which verifies a list of proofs and exposes their
+/// accumulator and their instance.
+#[derive(Clone)]
+pub struct AggregationCircuit {
+    // G1 generator of the params of the aggregated proofs
+    g1: G1Affine,
+    snarks: Vec<SnarkWitness>,
+    instance: Vec<Fr>,
+}
+
+impl AggregationCircuit {
+    /// Build the Aggregation circuit of `snarks`, which have been created with
+    /// `params`.  The accumulator of the proofs is computed natively, which
+    /// fails if any of the proofs is malformed.
+    pub fn new(params: &Params<G1Affine>, snarks: Vec<Snark>) -> Result<Self, Error> {
+        let g1 = params.get_g()[0];
+
+        let mut strategy = SameCurveAccumulation::<NativeLoader>::default();
+        for (idx, snark) in snarks.iter().enumerate() {
+            let mut transcript =
+                PoseidonTranscript::<NativeLoader, _, _>::new(snark.proof.as_slice());
+            PlonkAccumulationScheme::accumulate(
+                &snark.protocol,
+                &NativeLoader,
+                snark.instance.clone(),
+                &mut transcript,
+                &mut strategy,
+            )
+            .map_err(|err| {
+                log::error!("accumulation of proof {} failed: {:?}", idx, err);
+                Error::Synthesis
+            })?;
+        }
+        let (lhs, rhs) = strategy.finalize(G1::from(g1));
+        let (lhs, rhs) = (lhs.to_affine(), rhs.to_affine());
+
+        let mut instance = [lhs.x, lhs.y, rhs.x, rhs.y]
+            .map(fe_to_limbs::<_, _, LIMBS, BITS>)
+            .concat();
+        for snark in snarks.iter() {
+            instance.extend(snark.instance.iter().flatten());
+        }
+
+        Ok(Self {
+            g1,
+            snarks: snarks.into_iter().map_into().collect(),
+            instance,
+        })
+    }
+
+    /// Return the instance column of the circuit, which starts with the
+    /// accumulator of the proofs followed by their instance.
+    pub fn instance(&self) -> Vec<Vec<Fr>> {
+        vec![self.instance.clone()]
+    }
+}
+
+/// Verify `snark` in circuit up to its pairing check, adding its accumulator
+/// to `strategy`.  Return the assigned instance of the proof.
+fn accumulate<'a, 'b>(
+    loader: &Rc<Halo2Loader<'a, 'b>>,
+    strategy: &mut SameCurveAccumulation<Rc<Halo2Loader<'a, 'b>>>,
+    snark: &SnarkWitness,
+) -> Result<Vec<Vec<loader::halo2::Scalar<'a, 'b, G1Affine, LIMBS, BITS>>>, Error> {
+    let instance = snark
+        .instance
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .map(|value| loader.assign_scalar(*value))
+                .collect_vec()
+        })
+        .collect_vec();
+
+    let mut transcript = PoseidonTranscript::<Rc<Halo2Loader>, _, _>::new(
+        loader,
+        snark.proof.as_ref().map(|proof| proof.as_slice()),
+    );
+    PlonkAccumulationScheme::accumulate(
+        &snark.protocol,
+        loader,
+        instance.clone(),
+        &mut transcript,
+        strategy,
+    )
+    .map_err(|err| {
+        log::error!("accumulation failed: {:?}", err);
+        Error::Synthesis
+    })?;
+
+    Ok(instance)
+}
+
+impl Circuit<Fr> for AggregationCircuit {
+    type Config = AggregationConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            g1: self.g1,
+            snarks: self
+                .snarks
+                .iter()
+                .map(SnarkWitness::without_witnesses)
+                .collect(),
+            instance: Vec::new(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let main_gate_config = MainGate::<Fr>::configure(meta);
+        let rns = Rns::<Fq, Fr, LIMBS, BITS>::construct();
+        let range_config =
+            RangeChip::<Fr>::configure(meta, &main_gate_config, rns.overflow_lengths());
+
+        AggregationConfig {
+            main_gate_config,
+            range_config,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        config.load_range(&mut layouter)?;
+
+        let (lhs, rhs, instance) = layouter.assign_region(
+            || "aggregation",
+            |mut region| {
+                let offset = &mut 0;
+                let ctx = RegionCtx::new(&mut region, offset);
+                let loader = Halo2Loader::new(config.ecc_chip_config(), ctx);
+
+                let mut strategy = SameCurveAccumulation::default();
+                let mut instance = Vec::new();
+                for snark in self.snarks.iter() {
+                    let snark_instance = accumulate(&loader, &mut strategy, snark)?;
+                    instance.extend(
+                        snark_instance
+                            .into_iter()
+                            .flatten()
+                            .map(|value| value.assigned()),
+                    );
+                }
+                let (lhs, rhs) = strategy.finalize(G1::from(self.g1));
+
+                Ok((lhs.assigned(), rhs.assigned(), instance))
+            },
+        )?;
+
+        let ecc_chip = BaseFieldEccChip::new(config.ecc_chip_config());
+        ecc_chip.expose_public(layouter.namespace(|| "accumulator lhs"), lhs, 0)?;
+        ecc_chip.expose_public(layouter.namespace(|| "accumulator rhs"), rhs, 2 * LIMBS)?;
+
+        let main_gate = MainGate::<Fr>::new(config.main_gate_config);
+        for (idx, value) in instance.into_iter().enumerate() {
+            main_gate.expose_public(
+                layouter.namespace(|| "aggregated instance"),
+                value,
+                ACCUMULATOR_LEN + idx,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Return whether the accumulator at the start of `instance`, an instance
+/// column of the Aggregation circuit, passes the pairing check, which means
+/// that all the aggregated proofs are valid.  `g2` and `s_g2` are the G2
+/// generator and its multiple by the secret of the params of the aggregated
+/// proofs.
+pub fn decide(g2: G2Affine, s_g2: G2Affine, instance: &[Fr]) -> bool {
+    if instance.len() < ACCUMULATOR_LEN {
+        return false;
+    }
+    let [lhs_x, lhs_y, rhs_x, rhs_y] = [0, 1, 2, 3].map(|idx| {
+        fe_from_limbs::<Fq, Fr, LIMBS, BITS>(
+            instance[idx * LIMBS..(idx + 1) * LIMBS]
+                .try_into()
+                .expect("slice of LIMBS limbs"),
+        )
+    });
+    let (lhs, rhs) = match (
+        Option::<G1Affine>::from(G1Affine::from_xy(lhs_x, lhs_y)),
+        Option::<G1Affine>::from(G1Affine::from_xy(rhs_x, rhs_y)),
+    ) {
+        (Some(lhs), Some(rhs)) => (lhs, rhs),
+        _ => return false,
+    };
+
+    Bn256::multi_miller_loop(&[
+        (&lhs, &G2Prepared::from(g2)),
+        (&rhs, &G2Prepared::from(-s_g2)),
+    ])
+    .final_exponentiation()
+    .is_identity()
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        dev::MockProver,
+        plonk::{keygen_pk, keygen_vk, Advice, Column, Instance, Selector},
+        poly::Rotation,
+    };
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[derive(Clone)]
+    struct SquareConfig {
+        q_enable: Selector,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        instance: Column<Instance>,
+    }
+
+    // Circuit proving the knowledge of a square root of its instance.
+    #[derive(Default)]
+    struct SquareCircuit {
+        root: Option<Fr>,
+    }
+
+    impl Circuit<Fr> for SquareCircuit {
+        type Config = SquareConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let q_enable = meta.selector();
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let instance = meta.instance_column();
+            meta.enable_equality(b);
+            meta.enable_equality(instance);
+            meta.create_gate("b = a * a", |meta| {
+                let q_enable = meta.query_selector(q_enable);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![q_enable * (b - a.clone() * a)]
+            });
+            SquareConfig {
+                q_enable,
+                a,
+                b,
+                instance,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let square = layouter.assign_region(
+                || "square",
+                |mut region| {
+                    config.q_enable.enable(&mut region, 0)?;
+                    region.assign_advice(
+                        || "a",
+                        config.a,
+                        0,
+                        || self.root.ok_or(Error::Synthesis),
+                    )?;
+                    region.assign_advice(
+                        || "b",
+                        config.b,
+                        0,
+                        || self.root.map(|root| root * root).ok_or(Error::Synthesis),
+                    )
+                },
+            )?;
+            layouter.constrain_instance(square.cell(), config.instance, 0)
+        }
+    }
+
+    fn snarks(params: &Params<G1Affine>, roots: &[u64]) -> Vec<Snark> {
+        let mut rng = XorShiftRng::seed_from_u64(2);
+        let vk = keygen_vk(params, &SquareCircuit::default()).unwrap();
+        let pk = keygen_pk(params, vk, &SquareCircuit::default()).unwrap();
+        roots
+            .iter()
+            .map(|root| {
+                let root = Fr::from(*root);
+                let circuit = SquareCircuit { root: Some(root) };
+                Snark::create(params, &pk, circuit, vec![vec![root * root]], &mut rng).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aggregation_circuit_valid() {
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(8);
+        let circuit = AggregationCircuit::new(&params, snarks(&params, &[3, 5])).unwrap();
+        let instance = circuit.instance();
+        assert_eq!(instance[0].len(), ACCUMULATOR_LEN + 2);
+
+        let prover = MockProver::run(21, &circuit, instance).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn aggregation_circuit_wrong_instance() {
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(8);
+        let circuit = AggregationCircuit::new(&params, snarks(&params, &[3])).unwrap();
+        let mut instance = circuit.instance();
+        // claim that the aggregated proof is of another square
+        instance[0][ACCUMULATOR_LEN] = Fr::from(10);
+
+        let prover = MockProver::run(21, &circuit, instance).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -2,3 +2,5 @@
 
 pub(crate) mod bytecode_unroller;
 pub(crate) mod param;
+
+pub use bytecode_unroller::{unroll, BytecodeCircuit, UnrolledBytecode};
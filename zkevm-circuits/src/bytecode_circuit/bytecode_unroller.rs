@@ -1,19 +1,26 @@
 use crate::{
-    evm_circuit::util::{
-        and, constraint_builder::BaseConstraintBuilder, not, or, select, RandomLinearCombination,
+    evm_circuit::{
+        util::{
+            and, constraint_builder::BaseConstraintBuilder, not, or, select,
+            RandomLinearCombination,
+        },
+        witness::Block,
     },
     table::{BytecodeFieldTag, BytecodeTable, DynamicTableColumns, KeccakTable},
-    util::Expr,
+    util::{power_of_randomness_from_instance, Expr},
 };
 use bus_mapping::evm::OpcodeId;
 use eth_types::{Field, ToLittleEndian, Word};
 use gadgets::is_zero::{IsZeroChip, IsZeroConfig, IsZeroInstruction};
 use halo2_proofs::{
-    circuit::{Layouter, Region},
-    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector, VirtualCells},
+    circuit::{Layouter, Region, SimpleFloorPlanner},
+    plonk::{
+        Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Selector, VirtualCells,
+    },
     poly::Rotation,
 };
 use keccak256::plain::Keccak;
+use log::error;
 use std::vec;
 
 use super::param::PUSH_TABLE_WIDTH;
@@ -396,6 +403,16 @@ impl<F: Field> Config<F> {
         // Subtract the unusable rows from the size
         let last_row_offset = size - self.minimum_rows + 1;
 
+        let num_rows = witness.iter().map(|b| b.rows.len()).sum::<usize>();
+        if num_rows > last_row_offset + 1 {
+            error!(
+                "bytecode circuit needs {} rows but only {} are available",
+                num_rows,
+                last_row_offset + 1
+            );
+            return Err(Error::Synthesis);
+        }
+
         layouter.assign_region(
             || "assign bytecode",
             |mut region| {
@@ -422,30 +439,28 @@ impl<F: Field> Config<F> {
                         }
 
                         // Set the data for this row
-                        if offset <= last_row_offset {
-                            self.set_row(
-                                &mut region,
-                                &push_rindex_is_zero_chip,
-                                &length_is_zero_chip,
-                                offset,
-                                true,
-                                offset == last_row_offset,
-                                row.code_hash,
-                                row.tag,
-                                row.index,
-                                row.is_code,
-                                row.value,
-                                push_rindex,
-                                hash_input_rlc,
-                                code_length,
-                                F::from(byte_push_size as u64),
-                                idx == bytecode.bytes.len(),
-                                false,
-                                F::from(push_rindex_prev),
-                            )?;
-                            push_rindex_prev = push_rindex;
-                            offset += 1;
-                        }
+                        self.set_row(
+                            &mut region,
+                            &push_rindex_is_zero_chip,
+                            &length_is_zero_chip,
+                            offset,
+                            true,
+                            offset == last_row_offset,
+                            row.code_hash,
+                            row.tag,
+                            row.index,
+                            row.is_code,
+                            row.value,
+                            push_rindex,
+                            hash_input_rlc,
+                            code_length,
+                            F::from(byte_push_size as u64),
+                            idx == bytecode.bytes.len(),
+                            false,
+                            F::from(push_rindex_prev),
+                        )?;
+                        push_rindex_prev = push_rindex;
+                        offset += 1;
                     }
                 }
 
@@ -583,6 +598,7 @@ impl<F: Field> Config<F> {
     }
 }
 
+/// Unroll `bytes` into the rows of the BytecodeTable
 pub fn unroll<F: Field>(bytes: Vec<u8>, randomness: F) -> UnrolledBytecode<F> {
     let code_hash = keccak(&bytes[..], randomness);
     let mut rows = vec![BytecodeRow::<F> {
@@ -648,73 +664,116 @@ fn into_words(message: &[u8]) -> Vec<u64> {
     words
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::util::power_of_randomness_from_instance;
-    use eth_types::{Bytecode, Word};
-    use halo2_proofs::{
-        circuit::{Layouter, SimpleFloorPlanner},
-        dev::MockProver,
-        pairing::bn256::Fr,
-        plonk::{Circuit, ConstraintSystem, Error},
-    };
-
-    #[derive(Default)]
-    struct MyCircuit<F: Field> {
-        bytecodes: Vec<UnrolledBytecode<F>>,
-        size: usize,
-        randomness: F,
+/// BytecodeCircuit: proves the unrolling of the bytecodes assigned to the
+/// BytecodeTable
+#[derive(Clone, Default, Debug)]
+pub struct BytecodeCircuit<F: Field> {
+    /// Unrolled bytecodes
+    pub bytecodes: Vec<UnrolledBytecode<F>>,
+    /// Number of rows of the circuit, including the unusable rows
+    pub size: usize,
+    /// Randomness for RLC encoding
+    pub randomness: F,
+}
+
+impl<F: Field> BytecodeCircuit<F> {
+    /// Make a new BytecodeCircuit proving `bytecodes` in `size` rows
+    pub fn new(bytecodes: Vec<UnrolledBytecode<F>>, size: usize, randomness: F) -> Self {
+        Self {
+            bytecodes,
+            size,
+            randomness,
+        }
     }
 
-    fn get_randomness<F: Field>() -> F {
-        F::from(123456)
+    /// Make a new BytecodeCircuit proving the bytecodes of `block` in `size`
+    /// rows
+    pub fn new_from_block(block: &Block<F>, size: usize) -> Self {
+        let bytecodes = block
+            .bytecodes
+            .values()
+            .map(|b| unroll(b.bytes.clone(), block.randomness))
+            .collect();
+        Self::new(bytecodes, size, block.randomness)
     }
 
-    impl<F: Field> Circuit<F> for MyCircuit<F> {
-        type Config = Config<F>;
-        type FloorPlanner = SimpleFloorPlanner;
+    /// Return the minimum size of a circuit proving `bytecodes`
+    pub fn min_num_rows(bytecodes: &[UnrolledBytecode<F>]) -> usize {
+        bytecodes.iter().map(|b| b.rows.len()).sum::<usize>() + Self::unusable_rows()
+    }
 
-        fn without_witnesses(&self) -> Self {
-            Self::default()
-        }
+    /// Return the minimum size of a circuit proving the bytecodes of `block`
+    pub fn min_num_rows_block(block: &Block<F>) -> usize {
+        // Every bytecode is unrolled into a Length row followed by a row per
+        // byte
+        block
+            .bytecodes
+            .values()
+            .map(|b| b.bytes.len() + 1)
+            .sum::<usize>()
+            + Self::unusable_rows()
+    }
 
-        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-            let bytecode_table = BytecodeTable::construct(meta);
+    /// Return the values of the instance column, which holds the randomness
+    pub fn instance(&self) -> Vec<Vec<F>> {
+        vec![vec![self.randomness; self.size - Self::unusable_rows()]]
+    }
 
-            let randomness = power_of_randomness_from_instance::<_, 1>(meta);
-            let keccak_table = KeccakTable::construct(meta);
+    /// Number of rows at the end of the circuit reserved for the blinding
+    /// factors
+    fn unusable_rows() -> usize {
+        let mut cs = ConstraintSystem::default();
+        Self::configure(&mut cs);
+        cs.minimum_rows() - 2
+    }
+}
 
-            Config::configure(meta, randomness[0].clone(), bytecode_table, keccak_table)
-        }
+impl<F: Field> Circuit<F> for BytecodeCircuit<F> {
+    type Config = Config<F>;
+    type FloorPlanner = SimpleFloorPlanner;
 
-        fn synthesize(
-            &self,
-            config: Self::Config,
-            mut layouter: impl Layouter<F>,
-        ) -> Result<(), Error> {
-            config.load(&mut layouter)?;
-            config.keccak_table.load(
-                &mut layouter,
-                self.bytecodes.iter().map(|b| b.bytes.as_slice()),
-                self.randomness,
-            )?;
-            config.assign(&mut layouter, self.size, &self.bytecodes, self.randomness)?;
-            Ok(())
-        }
+    fn without_witnesses(&self) -> Self {
+        Self::default()
     }
 
-    fn verify<F: Field>(k: u32, bytecodes: Vec<UnrolledBytecode<F>>, randomness: F, success: bool) {
-        let circuit = MyCircuit::<F> {
-            bytecodes,
-            size: 2usize.pow(k),
-            randomness,
-        };
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let bytecode_table = BytecodeTable::construct(meta);
+
+        let randomness = power_of_randomness_from_instance::<_, 1>(meta);
+        let keccak_table = KeccakTable::construct(meta);
+
+        Config::configure(meta, randomness[0].clone(), bytecode_table, keccak_table)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.load(&mut layouter)?;
+        config.keccak_table.load(
+            &mut layouter,
+            self.bytecodes.iter().map(|b| b.bytes.as_slice()),
+            self.randomness,
+        )?;
+        config.assign(&mut layouter, self.size, &self.bytecodes, self.randomness)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eth_types::{Bytecode, Word};
+    use halo2_proofs::{dev::MockProver, pairing::bn256::Fr};
 
-        let num_rows = 1 << k;
-        const NUM_BLINDING_ROWS: usize = 7 - 1;
-        let instance = vec![vec![randomness; num_rows - NUM_BLINDING_ROWS]];
-        let prover = MockProver::<F>::run(k, &circuit, instance).unwrap();
+    fn get_randomness<F: Field>() -> F {
+        F::from(123456)
+    }
+
+    fn verify<F: Field>(k: u32, bytecodes: Vec<UnrolledBytecode<F>>, randomness: F, success: bool) {
+        let circuit = BytecodeCircuit::new(bytecodes, 2usize.pow(k), randomness);
+        let prover = MockProver::<F>::run(k, &circuit, circuit.instance()).unwrap();
         let result = prover.verify();
         if let Err(failures) = &result {
             for failure in failures.iter() {
@@ -819,25 +878,23 @@ mod tests {
     fn bytecode_full() {
         let k = 9;
         let randomness = get_randomness();
-        verify::<Fr>(
-            k,
-            vec![unroll(vec![7u8; 2usize.pow(k) - 7], randomness)],
-            randomness,
-            true,
-        );
+        let bytecodes = vec![unroll(vec![7u8; 2usize.pow(k) - 7], randomness)];
+        assert_eq!(BytecodeCircuit::min_num_rows(&bytecodes), 2usize.pow(k));
+        verify::<Fr>(k, bytecodes, randomness, true);
     }
 
-    /// Tests a circuit with incomplete bytecode
+    /// Tests a circuit with incomplete bytecode, which is rejected
     #[test]
     fn bytecode_incomplete() {
         let k = 9;
         let randomness = get_randomness();
-        verify::<Fr>(
-            k,
-            vec![unroll(vec![7u8; 2usize.pow(k) + 1], randomness)],
-            randomness,
-            false,
-        );
+        let bytecodes = vec![unroll(vec![7u8; 2usize.pow(k) - 6], randomness)];
+        assert_eq!(BytecodeCircuit::min_num_rows(&bytecodes), 2usize.pow(k) + 1);
+        let circuit = BytecodeCircuit::<Fr>::new(bytecodes, 2usize.pow(k), randomness);
+        assert!(matches!(
+            MockProver::<Fr>::run(k, &circuit, circuit.instance()),
+            Err(Error::Synthesis)
+        ));
     }
 
     /// Tests multiple bytecodes in a single circuit
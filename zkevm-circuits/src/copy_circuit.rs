@@ -2,7 +2,10 @@
 //! copied bytes while execution opcodes such as CALLDATACOPY, CODECOPY, LOGS,
 //! etc.
 
-use bus_mapping::circuit_input_builder::{CopyDataType, CopyEvent, CopyStep, NumberOrHash};
+use bus_mapping::{
+    circuit_input_builder::{CopyDataType, CopyEvent, CopyStep, NumberOrHash},
+    operation::RW,
+};
 use eth_types::{Field, ToAddress, ToScalar, U256};
 use gadgets::{
     binary_number::BinaryNumberChip,
@@ -10,8 +13,8 @@ use gadgets::{
     util::{and, not, or, Expr},
 };
 use halo2_proofs::{
-    circuit::{Layouter, Region},
-    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Selector},
+    circuit::{Layouter, Region, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Selector},
     poly::Rotation,
 };
 
@@ -21,7 +24,8 @@ use crate::{
         witness::Block,
     },
     table::{
-        BytecodeFieldTag, CopyTable, LookupTable, RwTableTag, TxContextFieldTag, TxLogFieldTag,
+        BytecodeFieldTag, BytecodeTable, CopyTable, LookupTable, RwTable, RwTableTag,
+        TxContextFieldTag, TxLogFieldTag, TxTable,
     },
 };
 
@@ -321,6 +325,19 @@ impl<F: Field> CopyCircuit<F> {
         }
     }
 
+    /// Return the number of rows required to assign the copy events of
+    /// `block`, padded up to `block.max_copy_rows`, including the two padding
+    /// rows at the end.
+    pub fn get_num_rows_required(block: &Block<F>) -> usize {
+        block
+            .copy_events
+            .values()
+            .map(|copy_event| copy_event.steps.len())
+            .sum::<usize>()
+            .max(block.max_copy_rows)
+            + 2
+    }
+
     /// Assign a witness block to the Copy Circuit.
     pub fn assign_block(
         &self,
@@ -349,9 +366,24 @@ impl<F: Field> CopyCircuit<F> {
                         offset += 1;
                     }
                 }
+                // pad the copy events with steps that copy nothing up to
+                // `block.max_copy_rows`, so that the fixed columns only depend
+                // on the capacity of the circuit
+                while offset + 2 <= block.max_copy_rows {
+                    for rw in [RW::READ, RW::WRITE] {
+                        self.assign_padding_row(
+                            &mut region,
+                            offset,
+                            Some(rw),
+                            &tag_chip,
+                            &lt_chip,
+                        )?;
+                        offset += 1;
+                    }
+                }
                 // pad two rows in the end to satisfy Halo2 cell assignment check
                 for _ in 0..2 {
-                    self.assign_padding_row(&mut region, offset, &tag_chip)?;
+                    self.assign_padding_row(&mut region, offset, None, &tag_chip, &lt_chip)?;
                     offset += 1;
                 }
                 Ok(())
@@ -494,14 +526,32 @@ impl<F: Field> CopyCircuit<F> {
         Ok(())
     }
 
+    /// Assign a padding row, which is disabled when `rw` is `None`, or else
+    /// the read or write row of an enabled step that copies nothing: its read
+    /// row is padding and its write row is the last of the step, so that no
+    /// constraint links it to the neighbouring steps.
     fn assign_padding_row(
         &self,
         region: &mut Region<F>,
         offset: usize,
+        rw: Option<RW>,
         tag_chip: &BinaryNumberChip<F, CopyDataType, 3>,
+        lt_chip: &LtChip<F, 8>,
     ) -> Result<(), Error> {
+        let is_read = rw.map_or(false, |rw| rw.is_read());
+        let is_write = rw.map_or(false, |rw| !rw.is_read());
         // q_enable
-        region.assign_fixed(|| "q_enable", self.q_enable, offset, || Ok(F::zero()))?;
+        region.assign_fixed(
+            || "q_enable",
+            self.q_enable,
+            offset,
+            || Ok(F::from(rw.is_some())),
+        )?;
+        // enable q_step on the Read step
+        if is_read {
+            self.q_step.enable(region, offset)?;
+            lt_chip.assign(region, offset, F::zero(), F::zero())?;
+        }
         // is_first
         region.assign_advice(
             || format!("assign is_first {}", offset),
@@ -514,7 +564,7 @@ impl<F: Field> CopyCircuit<F> {
             || format!("assign is_last {}", offset),
             self.is_last,
             offset,
-            || Ok(F::zero()),
+            || Ok(F::from(is_write)),
         )?;
         // id
         region.assign_advice(
@@ -542,7 +592,7 @@ impl<F: Field> CopyCircuit<F> {
             || format!("assign bytes_left {}", offset),
             self.copy_table.bytes_left,
             offset,
-            || Ok(F::zero()),
+            || Ok(F::from(is_read)),
         )?;
         // value
         region.assign_advice(
@@ -563,7 +613,7 @@ impl<F: Field> CopyCircuit<F> {
             || format!("assign is_pad {}", offset),
             self.is_pad,
             offset,
-            || Ok(F::zero()),
+            || Ok(F::from(is_read)),
         )?;
         // rw_counter
         region.assign_advice(
@@ -580,105 +630,109 @@ impl<F: Field> CopyCircuit<F> {
             || Ok(F::zero()),
         )?;
         // tag
-        tag_chip.assign(region, offset, &CopyDataType::default())?;
+        tag_chip.assign(region, offset, &CopyDataType::Padding)?;
         Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use bus_mapping::{
-        circuit_input_builder::{CircuitInputBuilder, CopyDataType},
-        mock::BlockData,
-        operation::RWCounter,
-    };
-    use eth_types::{bytecode, geth_types::GethData, Field, Word};
-    use halo2_proofs::{
-        circuit::{Layouter, SimpleFloorPlanner},
-        dev::{MockProver, VerifyFailure},
-        plonk::{Circuit, ConstraintSystem},
-    };
-    use mock::TestContext;
-    use rand::{prelude::SliceRandom, Rng};
+/// Config of [`StandaloneCopyCircuit`]
+#[derive(Clone)]
+pub struct StandaloneCopyCircuitConfig<F> {
+    tx_table: TxTable,
+    rw_table: RwTable,
+    bytecode_table: BytecodeTable,
+    copy_circuit: CopyCircuit<F>,
+}
 
-    use crate::{
-        evm_circuit::witness::{block_convert, Block},
-        table::{BytecodeTable, RwTable, TxTable},
-    };
+/// The Copy Circuit of a block along with the Tx Table, Rw Table and Bytecode
+/// Table it looks up, to prove the Copy Circuit on its own rather than as part
+/// of the Super Circuit.
+#[derive(Default)]
+pub struct StandaloneCopyCircuit<F> {
+    block: Block<F>,
+}
 
-    #[derive(Clone)]
-    struct MyConfig<F> {
-        tx_table: TxTable,
-        rw_table: RwTable,
-        bytecode_table: BytecodeTable,
-        copy_table: CopyCircuit<F>,
+impl<F> StandaloneCopyCircuit<F> {
+    /// Return a new Copy Circuit of `block`
+    pub fn new(block: Block<F>) -> Self {
+        Self { block }
     }
+}
 
-    #[derive(Default)]
-    struct MyCircuit<F> {
-        block: Block<F>,
-    }
+impl<F: Field> Circuit<F> for StandaloneCopyCircuit<F> {
+    type Config = StandaloneCopyCircuitConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
 
-    impl<F> MyCircuit<F> {
-        pub fn new(block: Block<F>) -> Self {
-            Self { block }
-        }
+    fn without_witnesses(&self) -> Self {
+        Self::default()
     }
 
-    impl<F: Field> Circuit<F> for MyCircuit<F> {
-        type Config = MyConfig<F>;
-        type FloorPlanner = SimpleFloorPlanner;
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let tx_table = TxTable::construct(meta);
+        let rw_table = RwTable::construct(meta);
+        let bytecode_table = BytecodeTable::construct(meta);
+        let q_enable = meta.fixed_column();
+        let copy_table = CopyTable::construct(meta, q_enable);
+        let copy_circuit = CopyCircuit::configure(
+            meta,
+            &tx_table,
+            &rw_table,
+            &bytecode_table,
+            copy_table,
+            q_enable,
+        );
 
-        fn without_witnesses(&self) -> Self {
-            Self::default()
+        StandaloneCopyCircuitConfig {
+            tx_table,
+            rw_table,
+            bytecode_table,
+            copy_circuit,
         }
+    }
 
-        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-            let tx_table = TxTable::construct(meta);
-            let rw_table = RwTable::construct(meta);
-            let bytecode_table = BytecodeTable::construct(meta);
-            let q_enable = meta.fixed_column();
-            let copy_table = CopyTable::construct(meta, q_enable);
-            let copy_table = CopyCircuit::configure(
-                meta,
-                &tx_table,
-                &rw_table,
-                &bytecode_table,
-                copy_table,
-                q_enable,
-            );
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.tx_table.load(
+            &mut layouter,
+            &self.block.txs,
+            self.block.max_txs,
+            self.block.randomness,
+        )?;
+        config.rw_table.load(
+            &mut layouter,
+            &self.block.rws,
+            self.block.max_rws,
+            self.block.randomness,
+        )?;
+        config.bytecode_table.load(
+            &mut layouter,
+            self.block.bytecodes.values(),
+            self.block.randomness,
+        )?;
+        config.copy_circuit.assign_block(&mut layouter, &self.block)
+    }
+}
 
-            MyConfig {
-                tx_table,
-                rw_table,
-                bytecode_table,
-                copy_table,
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bus_mapping::{
+        circuit_input_builder::{CircuitInputBuilder, CopyDataType},
+        mock::BlockData,
+        operation::RWCounter,
+    };
+    use eth_types::{bytecode, geth_types::GethData, Field, Word};
+    use halo2_proofs::dev::{MockProver, VerifyFailure};
+    use mock::TestContext;
+    use rand::{prelude::SliceRandom, Rng};
 
-        fn synthesize(
-            &self,
-            config: Self::Config,
-            mut layouter: impl Layouter<F>,
-        ) -> Result<(), halo2_proofs::plonk::Error> {
-            config
-                .tx_table
-                .load(&mut layouter, &self.block.txs, self.block.randomness)?;
-            config
-                .rw_table
-                .load(&mut layouter, &self.block.rws, self.block.randomness)?;
-            config.bytecode_table.load(
-                &mut layouter,
-                self.block.bytecodes.values(),
-                self.block.randomness,
-            )?;
-            config.copy_table.assign_block(&mut layouter, &self.block)
-        }
-    }
+    use crate::evm_circuit::witness::{block_convert, Block};
 
     fn run_circuit<F: Field>(k: u32, block: Block<F>) -> Result<(), Vec<VerifyFailure>> {
-        let circuit = MyCircuit::<F>::new(block);
+        let circuit = StandaloneCopyCircuit::<F>::new(block);
         let prover = MockProver::<F>::run(k, &circuit, vec![]).unwrap();
         prover.verify()
     }
@@ -720,14 +774,22 @@ mod tests {
     #[test]
     fn copy_circuit_valid_calldatacopy() {
         let builder = gen_calldatacopy_data();
-        let block = block_convert(&builder.block, &builder.code_db);
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
         assert!(run_circuit(10, block).is_ok());
     }
 
     #[test]
     fn copy_circuit_valid_codecopy() {
         let builder = gen_codecopy_data();
-        let block = block_convert(&builder.block, &builder.code_db);
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
+        assert!(run_circuit(10, block).is_ok());
+    }
+
+    #[test]
+    fn copy_circuit_valid_padded() {
+        let builder = gen_codecopy_data();
+        let mut block = block_convert(&builder.block, &builder.code_db).unwrap();
+        block.max_copy_rows = 256;
         assert!(run_circuit(10, block).is_ok());
     }
 
@@ -759,7 +821,7 @@ mod tests {
             true => perturb_tag(&mut builder.block, CopyDataType::Memory),
             false => perturb_tag(&mut builder.block, CopyDataType::TxCalldata),
         }
-        let block = block_convert(&builder.block, &builder.code_db);
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
         assert!(run_circuit(10, block).is_err());
     }
 
@@ -770,7 +832,7 @@ mod tests {
             true => perturb_tag(&mut builder.block, CopyDataType::Memory),
             false => perturb_tag(&mut builder.block, CopyDataType::Bytecode),
         }
-        let block = block_convert(&builder.block, &builder.code_db);
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
         assert!(run_circuit(10, block).is_err());
     }
 }
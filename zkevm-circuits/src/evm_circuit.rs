@@ -1,7 +1,10 @@
 //! The EVM circuit implementation.
 
 #![allow(missing_docs)]
-use halo2_proofs::{circuit::Layouter, plonk::*};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::*,
+};
 
 mod execution;
 pub mod param;
@@ -11,10 +14,14 @@ pub(crate) mod util;
 pub mod table;
 pub mod witness;
 
-use crate::table::LookupTable;
+use crate::{
+    table::{BlockTable, BytecodeTable, CopyTable, LookupTable, RwTable, TxTable},
+    util::power_of_randomness_from_instance,
+};
 use eth_types::Field;
 use execution::ExecutionConfig;
 use itertools::Itertools;
+use step::ExecutionState;
 use table::FixedTableTag;
 use witness::Block;
 
@@ -100,7 +107,8 @@ impl<F: Field> EvmCircuit<F> {
         )
     }
 
-    /// Assign block
+    /// Assign block, padding the steps with EndBlock steps up to
+    /// `block.max_evm_rows`
     pub fn assign_block(
         &self,
         layouter: &mut impl Layouter<F>,
@@ -139,23 +147,142 @@ impl<F: Field> EvmCircuit<F> {
                 num_rows += self.execution.get_step_height(step.execution_state);
             }
         }
-        num_rows
+        // An EndBlock step is added to an empty block
+        if block.txs.is_empty() {
+            num_rows += self.execution.get_step_height(ExecutionState::EndBlock);
+        }
+        num_rows.max(block.max_evm_rows)
+    }
+}
+
+/// Config of [`StandaloneEvmCircuit`]
+#[derive(Clone)]
+pub struct StandaloneEvmCircuitConfig<F> {
+    tx_table: TxTable,
+    rw_table: RwTable,
+    bytecode_table: BytecodeTable,
+    block_table: BlockTable,
+    copy_table: CopyTable,
+    pub evm_circuit: EvmCircuit<F>,
+}
+
+/// The EVM Circuit of a block along with the tables it looks up, which are
+/// loaded with the witness of the block, to prove the EVM Circuit on its own
+/// rather than as part of the Super Circuit.
+#[derive(Default)]
+pub struct StandaloneEvmCircuit<F> {
+    block: Block<F>,
+    fixed_table_tags: Vec<FixedTableTag>,
+}
+
+impl<F> StandaloneEvmCircuit<F> {
+    /// Return a new EVM Circuit of `block`, whose fixed table is loaded with
+    /// `fixed_table_tags`
+    pub fn new(block: Block<F>, fixed_table_tags: Vec<FixedTableTag>) -> Self {
+        Self {
+            block,
+            fixed_table_tags,
+        }
+    }
+}
+
+impl<F: Field> Circuit<F> for StandaloneEvmCircuit<F> {
+    type Config = StandaloneEvmCircuitConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let tx_table = TxTable::construct(meta);
+        let rw_table = RwTable::construct(meta);
+        let bytecode_table = BytecodeTable::construct(meta);
+        let block_table = BlockTable::construct(meta);
+        let q_copy_table = meta.fixed_column();
+        let copy_table = CopyTable::construct(meta, q_copy_table);
+
+        let power_of_randomness = power_of_randomness_from_instance(meta);
+        let evm_circuit = EvmCircuit::configure(
+            meta,
+            power_of_randomness,
+            &tx_table,
+            &rw_table,
+            &bytecode_table,
+            &block_table,
+            &copy_table,
+        );
+
+        Self::Config {
+            tx_table,
+            rw_table,
+            bytecode_table,
+            block_table,
+            copy_table,
+            evm_circuit,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config
+            .evm_circuit
+            .load_fixed_table(&mut layouter, self.fixed_table_tags.clone())?;
+        config.evm_circuit.load_byte_table(&mut layouter)?;
+        config.tx_table.load(
+            &mut layouter,
+            &self.block.txs,
+            self.block.max_txs,
+            self.block.randomness,
+        )?;
+        config.rw_table.load(
+            &mut layouter,
+            &self.block.rws,
+            self.block.max_rws,
+            self.block.randomness,
+        )?;
+        config.bytecode_table.load(
+            &mut layouter,
+            self.block.bytecodes.values(),
+            self.block.randomness,
+        )?;
+        config.block_table.load(
+            &mut layouter,
+            std::iter::once(&self.block.context),
+            self.block.randomness,
+        )?;
+        config
+            .copy_table
+            .load(&mut layouter, &self.block, self.block.randomness)?;
+        config.evm_circuit.assign_block(&mut layouter, &self.block)
+    }
+}
+
+impl<F: Field> StandaloneEvmCircuit<F> {
+    pub fn get_num_rows_required(block: &Block<F>) -> usize {
+        let mut cs = ConstraintSystem::default();
+        let config = StandaloneEvmCircuit::configure(&mut cs);
+        config.evm_circuit.get_num_rows_required(block)
+    }
+
+    pub fn get_active_rows(block: &Block<F>) -> (Vec<usize>, Vec<usize>) {
+        let mut cs = ConstraintSystem::default();
+        let config = StandaloneEvmCircuit::configure(&mut cs);
+        config.evm_circuit.get_active_rows(block)
     }
 }
 
 #[cfg(any(feature = "test", test))]
 pub mod test {
-    use crate::{
-        evm_circuit::{table::FixedTableTag, witness::Block, EvmCircuit},
-        table::{BlockTable, BytecodeTable, CopyTable, RwTable, TxTable},
-        util::power_of_randomness_from_instance,
+    pub use super::{
+        StandaloneEvmCircuit as TestCircuit, StandaloneEvmCircuitConfig as TestCircuitConfig,
     };
+    use crate::evm_circuit::{table::FixedTableTag, witness::Block};
     use eth_types::{Field, Word};
-    use halo2_proofs::{
-        circuit::{Layouter, SimpleFloorPlanner},
-        dev::{MockProver, VerifyFailure},
-        plonk::{Circuit, ConstraintSystem, Error},
-    };
+    use halo2_proofs::dev::{MockProver, VerifyFailure};
     use rand::{
         distributions::uniform::{SampleRange, SampleUniform},
         random, thread_rng, Rng,
@@ -182,114 +309,6 @@ pub mod test {
         Word::from_big_endian(&rand_bytes_array::<32>())
     }
 
-    #[derive(Clone)]
-    pub struct TestCircuitConfig<F> {
-        tx_table: TxTable,
-        rw_table: RwTable,
-        bytecode_table: BytecodeTable,
-        block_table: BlockTable,
-        copy_table: CopyTable,
-        pub evm_circuit: EvmCircuit<F>,
-    }
-
-    #[derive(Default)]
-    pub struct TestCircuit<F> {
-        block: Block<F>,
-        fixed_table_tags: Vec<FixedTableTag>,
-    }
-
-    impl<F> TestCircuit<F> {
-        pub fn new(block: Block<F>, fixed_table_tags: Vec<FixedTableTag>) -> Self {
-            Self {
-                block,
-                fixed_table_tags,
-            }
-        }
-    }
-
-    impl<F: Field> Circuit<F> for TestCircuit<F> {
-        type Config = TestCircuitConfig<F>;
-        type FloorPlanner = SimpleFloorPlanner;
-
-        fn without_witnesses(&self) -> Self {
-            Self::default()
-        }
-
-        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-            let tx_table = TxTable::construct(meta);
-            let rw_table = RwTable::construct(meta);
-            let bytecode_table = BytecodeTable::construct(meta);
-            let block_table = BlockTable::construct(meta);
-            let q_copy_table = meta.fixed_column();
-            let copy_table = CopyTable::construct(meta, q_copy_table);
-
-            let power_of_randomness = power_of_randomness_from_instance(meta);
-            let evm_circuit = EvmCircuit::configure(
-                meta,
-                power_of_randomness,
-                &tx_table,
-                &rw_table,
-                &bytecode_table,
-                &block_table,
-                &copy_table,
-            );
-
-            Self::Config {
-                tx_table,
-                rw_table,
-                bytecode_table,
-                block_table,
-                copy_table,
-                evm_circuit,
-            }
-        }
-
-        fn synthesize(
-            &self,
-            config: Self::Config,
-            mut layouter: impl Layouter<F>,
-        ) -> Result<(), Error> {
-            config
-                .evm_circuit
-                .load_fixed_table(&mut layouter, self.fixed_table_tags.clone())?;
-            config.evm_circuit.load_byte_table(&mut layouter)?;
-            config
-                .tx_table
-                .load(&mut layouter, &self.block.txs, self.block.randomness)?;
-            config
-                .rw_table
-                .load(&mut layouter, &self.block.rws, self.block.randomness)?;
-            config.bytecode_table.load(
-                &mut layouter,
-                self.block.bytecodes.values(),
-                self.block.randomness,
-            )?;
-            config
-                .block_table
-                .load(&mut layouter, &self.block.context, self.block.randomness)?;
-            config
-                .copy_table
-                .load(&mut layouter, &self.block, self.block.randomness)?;
-            config
-                .evm_circuit
-                .assign_block_exact(&mut layouter, &self.block)
-        }
-    }
-
-    impl<F: Field> TestCircuit<F> {
-        pub fn get_num_rows_required(block: &Block<F>) -> usize {
-            let mut cs = ConstraintSystem::default();
-            let config = TestCircuit::configure(&mut cs);
-            config.evm_circuit.get_num_rows_required(block)
-        }
-
-        pub fn get_active_rows(block: &Block<F>) -> (Vec<usize>, Vec<usize>) {
-            let mut cs = ConstraintSystem::default();
-            let config = TestCircuit::configure(&mut cs);
-            config.evm_circuit.get_active_rows(block)
-        }
-    }
-
     pub fn run_test_circuit<F: Field>(
         block: Block<F>,
         fixed_table_tags: Vec<FixedTableTag>,
@@ -43,7 +43,9 @@ mod dummy;
 mod dup;
 mod end_block;
 mod end_tx;
+mod error_call;
 mod error_oog_static_memory;
+mod error_write_protection;
 mod extcodehash;
 mod gas;
 mod gasprice;
@@ -92,7 +94,9 @@ use dummy::DummyGadget;
 use dup::DupGadget;
 use end_block::EndBlockGadget;
 use end_tx::EndTxGadget;
+use error_call::{ErrorDepthGadget, ErrorInsufficientBalanceGadget};
 use error_oog_static_memory::ErrorOOGStaticMemoryGadget;
+use error_write_protection::ErrorWriteProtectionGadget;
 use extcodehash::ExtcodehashGadget;
 use gas::GasGadget;
 use gasprice::GasPriceGadget;
@@ -207,7 +211,6 @@ pub(crate) struct ExecutionConfig<F> {
     callcode_gadget: DummyGadget<F, 7, 1, { ExecutionState::CALLCODE }>,
     delegatecall_gadget: DummyGadget<F, 6, 1, { ExecutionState::DELEGATECALL }>,
     create2_gadget: DummyGadget<F, 4, 1, { ExecutionState::CREATE2 }>,
-    staticcall_gadget: DummyGadget<F, 6, 1, { ExecutionState::STATICCALL }>,
     selfdestruct_gadget: DummyGadget<F, 1, 0, { ExecutionState::SELFDESTRUCT }>,
     signed_comparator_gadget: SignedComparatorGadget<F>,
     signextend_gadget: SignextendGadget<F>,
@@ -220,6 +223,9 @@ pub(crate) struct ExecutionConfig<F> {
     block_ctx_u256_gadget: BlockCtxU256Gadget<F>,
     // error gadgets
     error_oog_static_memory_gadget: ErrorOOGStaticMemoryGadget<F>,
+    error_write_protection_gadget: ErrorWriteProtectionGadget<F>,
+    error_depth_gadget: ErrorDepthGadget<F>,
+    error_insufficient_balance_gadget: ErrorInsufficientBalanceGadget<F>,
 }
 
 impl<F: Field> ExecutionConfig<F> {
@@ -242,6 +248,9 @@ impl<F: Field> ExecutionConfig<F> {
         let q_step_first = meta.complex_selector();
         let q_step_last = meta.complex_selector();
         let advices = [(); STEP_WIDTH].map(|_| meta.advice_column());
+        // Constants bound to the copy cells of the steps
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
 
         let step_curr = Step::new(meta, advices, 0);
         let mut height_map = HashMap::new();
@@ -270,16 +279,16 @@ impl<F: Field> ExecutionConfig<F> {
                 )
             });
 
-            let _first_step_check = {
-                let begin_tx_selector =
-                    step_curr.execution_state_selector([ExecutionState::BeginTx]);
+            let first_step_check = {
+                let begin_tx_end_block_selector = step_curr
+                    .execution_state_selector([ExecutionState::BeginTx, ExecutionState::EndBlock]);
                 iter::once((
-                    "First step should be BeginTx",
-                    q_step_first * (1.expr() - begin_tx_selector),
+                    "First step should be BeginTx or EndBlock",
+                    q_step_first * (1.expr() - begin_tx_end_block_selector),
                 ))
             };
 
-            let _last_step_check = {
+            let last_step_check = {
                 let end_block_selector =
                     step_curr.execution_state_selector([ExecutionState::EndBlock]);
                 iter::once((
@@ -291,9 +300,8 @@ impl<F: Field> ExecutionConfig<F> {
             iter::once(sum_to_one)
                 .chain(bool_checks)
                 .map(move |(name, poly)| (name, q_usable.clone() * q_step.clone() * poly))
-            // TODO: Enable these after incomplete trace is no longer necessary.
-            // .chain(first_step_check)
-            // .chain(last_step_check)
+                .chain(first_step_check)
+                .chain(last_step_check)
         });
 
         meta.create_gate("q_step", |meta| {
@@ -417,7 +425,6 @@ impl<F: Field> ExecutionConfig<F> {
             callcode_gadget: configure_gadget!(),
             delegatecall_gadget: configure_gadget!(),
             create2_gadget: configure_gadget!(),
-            staticcall_gadget: configure_gadget!(),
             selfdestruct_gadget: configure_gadget!(),
             shr_gadget: configure_gadget!(),
             signed_comparator_gadget: configure_gadget!(),
@@ -431,6 +438,9 @@ impl<F: Field> ExecutionConfig<F> {
             block_ctx_u256_gadget: configure_gadget!(),
             // error gadgets
             error_oog_static_memory_gadget: configure_gadget!(),
+            error_write_protection_gadget: configure_gadget!(),
+            error_depth_gadget: configure_gadget!(),
+            error_insufficient_balance_gadget: configure_gadget!(),
             // step and presets
             step: step_curr,
             height_map,
@@ -450,6 +460,14 @@ impl<F: Field> ExecutionConfig<F> {
             &cell_manager,
         );
 
+        // The steps are padded with EndBlock steps up to the capacity of the
+        // circuit, which can only be filled exactly if they take a single row.
+        assert_eq!(
+            config.get_step_height(ExecutionState::EndBlock),
+            1,
+            "EndBlock step should fit in a single row"
+        );
+
         config
     }
 
@@ -487,7 +505,7 @@ impl<F: Field> ExecutionConfig<F> {
                 G::EXECUTION_STATE,
             );
             G::configure(&mut cb);
-            let (_, _, _, height) = cb.build();
+            let (_, _, _, _, height) = cb.build();
             height
         };
 
@@ -515,7 +533,8 @@ impl<F: Field> ExecutionConfig<F> {
             (height - 1).expr(),
         );
 
-        let (constraints, constraints_first_step, stored_expressions, _) = cb.build();
+        let (constraints, constraints_first_step, constraints_not_step_last, stored_expressions, _) =
+            cb.build();
         debug_assert!(
             !height_map.contains_key(&G::EXECUTION_STATE),
             "execution state already configured"
@@ -532,9 +551,14 @@ impl<F: Field> ExecutionConfig<F> {
             &|meta| meta.query_advice(q_step, Rotation::cur());
         let q_steps_first: &dyn Fn(&mut VirtualCells<F>) -> Expression<F> =
             &|meta| meta.query_selector(q_step_first);
+        let q_steps_not_last: &dyn Fn(&mut VirtualCells<F>) -> Expression<F> = &|meta| {
+            meta.query_advice(q_step, Rotation::cur())
+                * (1.expr() - meta.query_selector(q_step_last))
+        };
         for (selector, constraints) in [
             (q_steps, constraints),
             (q_steps_first, constraints_first_step),
+            (q_steps_not_last, constraints_not_step_last),
         ] {
             if !constraints.is_empty() {
                 meta.create_gate(G::NAME, |meta| {
@@ -649,11 +673,16 @@ impl<F: Field> ExecutionConfig<F> {
     /// Assign block
     /// When exact is enabled, assign exact steps in block without padding for
     /// unit test purpose
+    ///
+    /// Otherwise the steps are padded with EndBlock steps so that they fill
+    /// exactly `block.max_evm_rows` rows, keeping the last one for the unused
+    /// `next` row, which makes the fixed columns only depend on the capacity
+    /// rather than on the steps of the block.
     pub fn assign_block(
         &self,
         layouter: &mut impl Layouter<F>,
         block: &Block<F>,
-        _exact: bool,
+        exact: bool,
     ) -> Result<(), Error> {
         let power_of_randomness = (1..32)
             .map(|exp| block.randomness.pow(&[exp, 0, 0, 0]))
@@ -661,24 +690,61 @@ impl<F: Field> ExecutionConfig<F> {
             .try_into()
             .unwrap();
 
+        // An empty block only has the EndBlock step, which isn't part of any
+        // transaction.
+        let dummy_tx = Transaction {
+            calls: vec![Call::default()],
+            ..Default::default()
+        };
+        let end_block_step = ExecStep {
+            rw_counter: 1,
+            execution_state: ExecutionState::EndBlock,
+            ..Default::default()
+        };
+
+        // Collect all steps
+        let mut steps: Vec<_> = block
+            .txs
+            .iter()
+            .flat_map(|tx| tx.steps.iter().map(move |step| (tx, step)))
+            .collect();
+        if steps.is_empty() {
+            steps.push((&dummy_tx, &end_block_step));
+        }
+
+        // Pad leftover region to the desired capacity with copies of the last
+        // EndBlock step, keeping a row for the unused `next` row.
+        let mut num_step_rows = steps
+            .iter()
+            .map(|(_, step)| self.get_step_height(step.execution_state))
+            .sum::<usize>();
+        if !exact {
+            let num_padding_steps = block.max_evm_rows.saturating_sub(num_step_rows + 1);
+            let last_step = *steps.last().unwrap();
+            steps.extend(iter::repeat(last_step).take(num_padding_steps));
+            num_step_rows += num_padding_steps;
+        }
+
         layouter.assign_region(
             || "Execution step",
             |mut region| {
-                let mut offset = 0;
-
-                self.q_step_first.enable(&mut region, offset)?;
-
-                // Collect all steps
-                let mut steps = block
-                    .txs
-                    .iter()
-                    .flat_map(|tx| tx.steps.iter().map(move |step| (tx, step)))
-                    .peekable();
+                // The selectors are enabled on the whole capacity, so that they
+                // don't depend on the steps of the block.
+                self.q_step_first.enable(&mut region, 0)?;
+                for offset in 0..num_step_rows {
+                    self.q_usable.enable(&mut region, offset)?;
+                }
+                // The last step is an EndBlock step, which takes a single row.
+                self.q_step_last.enable(&mut region, num_step_rows - 1)?;
 
-                let mut last_height = 0;
+                let mut offset = 0;
+                let mut steps = steps.iter().copied().peekable();
                 while let Some((transaction, step)) = steps.next() {
                     let call = &transaction.calls[step.call_index];
                     let height = self.get_step_height(step.execution_state);
+                    let next = steps.peek().map(|&(transaction, step)| {
+                        (transaction, &transaction.calls[step.call_index], step)
+                    });
 
                     // Assign the step witness
                     self.assign_exec_step(
@@ -689,16 +755,14 @@ impl<F: Field> ExecutionConfig<F> {
                         call,
                         step,
                         height,
-                        steps.peek().map(|&(transaction, step)| {
-                            (transaction, &transaction.calls[step.call_index], step)
-                        }),
+                        next,
+                        next.is_none(),
                         power_of_randomness,
                     )?;
 
                     // q_step logic
                     for idx in 0..height {
                         let offset = offset + idx;
-                        self.q_usable.enable(&mut region, offset)?;
                         region.assign_advice(
                             || "step selector",
                             self.q_step,
@@ -725,7 +789,6 @@ impl<F: Field> ExecutionConfig<F> {
                     }
 
                     offset += height;
-                    last_height = height;
                 }
                 // These are still referenced (but not used) in next rows
                 region.assign_advice(
@@ -741,11 +804,6 @@ impl<F: Field> ExecutionConfig<F> {
                     || Ok(F::zero()),
                 )?;
 
-                // If not exact:
-                // TODO: Pad leftover region to the desired capacity
-                // TODO: Enable q_step_last
-                self.q_step_last.enable(&mut region, offset - last_height)?;
-
                 Ok(())
             },
         )
@@ -762,6 +820,7 @@ impl<F: Field> ExecutionConfig<F> {
         step: &ExecStep,
         height: usize,
         next: Option<(&Transaction, &Call, &ExecStep)>,
+        is_last: bool,
         power_of_randomness: [F; 31],
     ) -> Result<(), Error> {
         // Make the region large enough for the current step and the next step.
@@ -788,12 +847,14 @@ impl<F: Field> ExecutionConfig<F> {
                 transaction_next,
                 call_next,
                 step_next,
+                false,
             )?;
         }
 
-        self.assign_exec_step_int(region, offset, block, transaction, call, step)
+        self.assign_exec_step_int(region, offset, block, transaction, call, step, is_last)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn assign_exec_step_int(
         &self,
         region: &mut CachedRegion<'_, '_, F>,
@@ -802,6 +863,7 @@ impl<F: Field> ExecutionConfig<F> {
         transaction: &Transaction,
         call: &Call,
         step: &ExecStep,
+        is_last: bool,
     ) -> Result<(), Error> {
         log::trace!("assign_exec_step offset:{} step:{:?}", offset, step);
         self.step
@@ -817,7 +879,15 @@ impl<F: Field> ExecutionConfig<F> {
             // internal states
             ExecutionState::BeginTx => assign_exec_step!(self.begin_tx_gadget),
             ExecutionState::EndTx => assign_exec_step!(self.end_tx_gadget),
-            ExecutionState::EndBlock => assign_exec_step!(self.end_block_gadget),
+            ExecutionState::EndBlock => {
+                assign_exec_step!(self.end_block_gadget);
+                // The capacities are only bound to constants once, so that the
+                // constants don't depend on the number of EndBlock steps.
+                if is_last {
+                    self.end_block_gadget
+                        .constrain_capacities(region, offset, block)?;
+                }
+            }
             // opcode
             ExecutionState::ADD_SUB => assign_exec_step!(self.add_sub_gadget),
             ExecutionState::ADDMOD => assign_exec_step!(self.addmod_gadget),
@@ -874,7 +944,6 @@ impl<F: Field> ExecutionConfig<F> {
             ExecutionState::CALLCODE => assign_exec_step!(self.callcode_gadget),
             ExecutionState::DELEGATECALL => assign_exec_step!(self.delegatecall_gadget),
             ExecutionState::CREATE2 => assign_exec_step!(self.create2_gadget),
-            ExecutionState::STATICCALL => assign_exec_step!(self.staticcall_gadget),
             ExecutionState::SELFDESTRUCT => assign_exec_step!(self.selfdestruct_gadget),
             // end of dummy gadgets
             ExecutionState::SHR => assign_exec_step!(self.shr_gadget),
@@ -887,6 +956,13 @@ impl<F: Field> ExecutionConfig<F> {
             ExecutionState::ErrorOutOfGasStaticMemoryExpansion => {
                 assign_exec_step!(self.error_oog_static_memory_gadget)
             }
+            ExecutionState::ErrorWriteProtection => {
+                assign_exec_step!(self.error_write_protection_gadget)
+            }
+            ExecutionState::ErrorDepth => assign_exec_step!(self.error_depth_gadget),
+            ExecutionState::ErrorInsufficientBalance => {
+                assign_exec_step!(self.error_insufficient_balance_gadget)
+            }
             _ => unimplemented!("unimplemented ExecutionState: {:?}", step.execution_state),
         }
 
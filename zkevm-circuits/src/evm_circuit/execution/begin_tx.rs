@@ -9,7 +9,7 @@ use crate::{
                 ConstraintBuilder, ReversionInfo, StepStateTransition,
                 Transition::{Delta, To},
             },
-            math_gadget::{MulWordByU64Gadget, RangeCheckGadget},
+            math_gadget::{IsEqualGadget, MulWordByU64Gadget, RangeCheckGadget},
             select, CachedRegion, Cell, RandomLinearCombination, Word,
         },
         witness::{Block, Call, ExecStep, Transaction},
@@ -22,6 +22,7 @@ use halo2_proofs::plonk::Error;
 
 #[derive(Clone, Debug)]
 pub(crate) struct BeginTxGadget<F> {
+    is_first_tx: IsEqualGadget<F>,
     tx_id: Cell<F>,
     tx_nonce: Cell<F>,
     tx_gas: Cell<F>,
@@ -48,8 +49,17 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
         // Use rw_counter of the step which triggers next call as its call_id.
         let call_id = cb.curr.state.rw_counter.clone();
 
-        let tx_id = cb.call_context(Some(call_id.expr()), CallContextFieldTag::TxId);
-        let mut reversion_info = cb.reversion_info(None);
+        // Only the first transaction writes its TxId here, the following ones
+        // read the TxId written by EndTx of their previous transaction.
+        let is_first_tx = IsEqualGadget::construct(cb, call_id.expr(), 1.expr());
+        let tx_id = cb.query_cell();
+        cb.call_context_lookup(
+            is_first_tx.expr(),
+            Some(call_id.expr()),
+            CallContextFieldTag::TxId,
+            tx_id.expr(),
+        );
+        let mut reversion_info = cb.reversion_info_write(None);
 
         let [tx_nonce, tx_gas, tx_caller_address, tx_callee_address, tx_is_create, tx_call_data_length, tx_call_data_gas_cost] =
             [
@@ -155,32 +165,41 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
             (CallContextFieldTag::IsRoot, 1.expr()),
             (CallContextFieldTag::IsCreate, 0.expr()),
             (CallContextFieldTag::CodeHash, code_hash.expr()),
+            // A root call is persistent if and only if it succeeds.
+            (
+                CallContextFieldTag::IsSuccess,
+                reversion_info.is_persistent(),
+            ),
         ] {
-            cb.call_context_lookup(false.expr(), Some(call_id.expr()), field_tag, value);
+            cb.call_context_lookup(true.expr(), Some(call_id.expr()), field_tag, value);
         }
 
         cb.require_step_state_transition(StepStateTransition {
-            // 19 read/write including:
-            //   - Read CallContext TxId
-            //   - Read CallContext RwCounterEndOfReversion
-            //   - Read CallContext IsPersistent
+            // 23 read/write including:
+            //   - Write/Read CallContext TxId
+            //   - Write CallContext RwCounterEndOfReversion
+            //   - Write CallContext IsPersistent
             //   - Write Account Nonce
             //   - Write TxAccessListAccount
             //   - Write TxAccessListAccount
             //   - Write Account Balance
             //   - Write Account Balance
             //   - Read Account CodeHash
-            //   - Read CallContext Depth
-            //   - Read CallContext CallerAddress
-            //   - Read CallContext CalleeAddress
-            //   - Read CallContext CallDataOffset
-            //   - Read CallContext CallDataLength
-            //   - Read CallContext Value
-            //   - Read CallContext IsStatic
-            //   - Read CallContext LastCalleeId
-            //   - Read CallContext LastCalleeReturnDataOffset
-            //   - Read CallContext LastCalleeReturnDataLength
-            rw_counter: Delta(22.expr()),
+            //   - Write CallContext Depth
+            //   - Write CallContext CallerAddress
+            //   - Write CallContext CalleeAddress
+            //   - Write CallContext CallDataOffset
+            //   - Write CallContext CallDataLength
+            //   - Write CallContext Value
+            //   - Write CallContext IsStatic
+            //   - Write CallContext LastCalleeId
+            //   - Write CallContext LastCalleeReturnDataOffset
+            //   - Write CallContext LastCalleeReturnDataLength
+            //   - Write CallContext IsRoot
+            //   - Write CallContext IsCreate
+            //   - Write CallContext CodeHash
+            //   - Write CallContext IsSuccess
+            rw_counter: Delta(23.expr()),
             call_id: To(call_id.expr()),
             is_root: To(true.expr()),
             is_create: To(false.expr()),
@@ -192,6 +211,7 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
         });
 
         Self {
+            is_first_tx,
             tx_id,
             tx_nonce,
             tx_gas,
@@ -224,6 +244,8 @@ impl<F: Field> ExecutionGadget<F> for BeginTxGadget<F> {
             [step.rw_indices[6], step.rw_indices[7], step.rw_indices[8]]
                 .map(|idx| block.rws[idx].account_value_pair());
 
+        self.is_first_tx
+            .assign(region, offset, F::from(step.rw_counter as u64), F::one())?;
         self.tx_id
             .assign(region, offset, Some(F::from(tx.id as u64)))?;
         self.tx_nonce
@@ -335,7 +357,7 @@ mod test {
         builder
             .handle_block(&block.eth_block, &block.geth_traces)
             .unwrap();
-        let block = block_convert(&builder.block, &builder.code_db);
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
         assert_eq!(run_test_circuit_incomplete_fixed_table(block), Ok(()));
     }
 
@@ -400,7 +422,7 @@ mod test {
         builder
             .handle_block(&block.eth_block, &block.geth_traces)
             .unwrap();
-        let block = block_convert(&builder.block, &builder.code_db);
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
 
         assert_eq!(run_test_circuit_incomplete_fixed_table(block), Ok(()));
     }
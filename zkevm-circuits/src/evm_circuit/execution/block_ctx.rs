@@ -6,11 +6,11 @@ use crate::{
         util::{
             common_gadget::SameContextGadget,
             constraint_builder::{ConstraintBuilder, StepStateTransition, Transition::Delta},
-            from_bytes, CachedRegion, RandomLinearCombination,
+            from_bytes, CachedRegion, Cell, RandomLinearCombination,
         },
         witness::{Block, Call, ExecStep, Transaction},
     },
-    table::BlockContextFieldTag,
+    table::{BlockContextFieldTag, CallContextFieldTag, TxContextFieldTag},
     util::Expr,
 };
 use bus_mapping::evm::OpcodeId;
@@ -22,6 +22,8 @@ use std::convert::{TryFrom, TryInto};
 #[derive(Clone, Debug)]
 pub(crate) struct BlockCtxGadget<F, const N_BYTES: usize> {
     same_context: SameContextGadget<F>,
+    tx_id: Cell<F>,
+    block_number: Cell<F>,
     value: RandomLinearCombination<F, N_BYTES>,
 }
 
@@ -29,6 +31,11 @@ impl<F: Field, const N_BYTES: usize> BlockCtxGadget<F, N_BYTES> {
     fn construct(cb: &mut ConstraintBuilder<F>) -> Self {
         let value = cb.query_rlc();
 
+        // Lookup in call_ctx the TxId, and in the tx table the number of the
+        // block that includes the tx
+        let tx_id = cb.call_context(None, CallContextFieldTag::TxId);
+        let block_number = cb.tx_context(tx_id.expr(), TxContextFieldTag::BlockNumber, None);
+
         // Push the const generic parameter N_BYTES value to the stack
         cb.stack_push(value.expr());
 
@@ -44,11 +51,11 @@ impl<F: Field, const N_BYTES: usize> BlockCtxGadget<F, N_BYTES> {
         } else {
             from_bytes::expr(&value.cells)
         };
-        cb.block_lookup(blockctx_tag, None, value_expr);
+        cb.block_lookup(blockctx_tag, Some(block_number.expr()), value_expr);
 
         // State transition
         let step_state_transition = StepStateTransition {
-            rw_counter: Delta(1.expr()),
+            rw_counter: Delta(2.expr()),
             program_counter: Delta(1.expr()),
             stack_pointer: Delta((-1).expr()),
             gas_left: Delta(-OpcodeId::TIMESTAMP.constant_gas_cost().expr()),
@@ -58,9 +65,27 @@ impl<F: Field, const N_BYTES: usize> BlockCtxGadget<F, N_BYTES> {
 
         Self {
             same_context,
+            tx_id,
+            block_number,
             value,
         }
     }
+
+    fn assign_exec_step(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        tx: &Transaction,
+        step: &ExecStep,
+    ) -> Result<(), Error> {
+        self.same_context.assign_exec_step(region, offset, step)?;
+        self.tx_id
+            .assign(region, offset, Some(F::from(tx.id as u64)))?;
+        self.block_number
+            .assign(region, offset, Some(F::from(tx.block_number)))?;
+
+        Ok(())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -84,15 +109,13 @@ impl<F: Field> ExecutionGadget<F> for BlockCtxU64Gadget<F> {
         region: &mut CachedRegion<'_, '_, F>,
         offset: usize,
         block: &Block<F>,
-        _: &Transaction,
+        tx: &Transaction,
         _: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
-        self.value_u64
-            .same_context
-            .assign_exec_step(region, offset, step)?;
+        self.value_u64.assign_exec_step(region, offset, tx, step)?;
 
-        let value = block.rws[step.rw_indices[0]].stack_value();
+        let value = block.rws[step.rw_indices[1]].stack_value();
 
         self.value_u64.value.assign(
             region,
@@ -125,15 +148,13 @@ impl<F: Field> ExecutionGadget<F> for BlockCtxU160Gadget<F> {
         region: &mut CachedRegion<'_, '_, F>,
         offset: usize,
         block: &Block<F>,
-        _: &Transaction,
+        tx: &Transaction,
         _: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
-        self.value_u160
-            .same_context
-            .assign_exec_step(region, offset, step)?;
+        self.value_u160.assign_exec_step(region, offset, tx, step)?;
 
-        let value = block.rws[step.rw_indices[0]].stack_value();
+        let value = block.rws[step.rw_indices[1]].stack_value();
 
         self.value_u160.value.assign(
             region,
@@ -170,15 +191,13 @@ impl<F: Field> ExecutionGadget<F> for BlockCtxU256Gadget<F> {
         region: &mut CachedRegion<'_, '_, F>,
         offset: usize,
         block: &Block<F>,
-        _: &Transaction,
+        tx: &Transaction,
         _: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
-        self.value_u256
-            .same_context
-            .assign_exec_step(region, offset, step)?;
+        self.value_u256.assign_exec_step(region, offset, tx, step)?;
 
-        let value = block.rws[step.rw_indices[0]].stack_value();
+        let value = block.rws[step.rw_indices[1]].stack_value();
 
         self.value_u256
             .value
@@ -190,8 +209,12 @@ impl<F: Field> ExecutionGadget<F> for BlockCtxU256Gadget<F> {
 
 #[cfg(test)]
 mod test {
-    use crate::test_util::run_test_circuits;
-    use eth_types::bytecode;
+    use crate::{
+        evm_circuit::{test::run_test_circuit_incomplete_fixed_table, witness::block_convert},
+        test_util::run_test_circuits,
+    };
+    use bus_mapping::mock::BlockData;
+    use eth_types::{bytecode, geth_types::GethData};
     use mock::TestContext;
 
     fn test_ok(bytecode: bytecode::Bytecode) {
@@ -235,4 +258,25 @@ mod test {
         };
         test_ok(bytecode);
     }
+
+    #[test]
+    fn blockcxt_gadget_wrong_block_number() {
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode! {
+            COINBASE
+            POP
+            NUMBER
+            STOP
+        })
+        .unwrap()
+        .into();
+        let mut builder = BlockData::new_from_geth_data(block.clone()).new_circuit_input_builder();
+        builder
+            .handle_block(&block.eth_block, &block.geth_traces)
+            .unwrap();
+        let mut block = block_convert(&builder.block, &builder.code_db).unwrap();
+        // The tx points to a block that isn't in the Block Table
+        block.txs[0].block_number += 1;
+
+        assert!(run_test_circuit_incomplete_fixed_table(block).is_err());
+    }
 }
@@ -33,6 +33,7 @@ use keccak256::EMPTY_HASH_LE;
 #[derive(Clone, Debug)]
 pub(crate) struct CallGadget<F> {
     opcode: Cell<F>,
+    is_staticcall: IsZeroGadget<F>,
     tx_id: Cell<F>,
     reversion_info: ReversionInfo<F>,
     current_address: Cell<F>,
@@ -70,10 +71,16 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
 
         // We do the responsible opcode check explicitly here because we're not using
         // the `SameContextGadget` for `CALL`.
+        let is_staticcall =
+            IsZeroGadget::construct(cb, opcode.expr() - OpcodeId::STATICCALL.expr());
         cb.require_equal(
-            "Opcode should be CALL",
+            "Opcode should be CALL or STATICCALL",
             opcode.expr(),
-            OpcodeId::CALL.expr(),
+            select::expr(
+                is_staticcall.expr(),
+                OpcodeId::STATICCALL.expr(),
+                OpcodeId::CALL.expr(),
+            ),
         );
 
         let gas_word = cb.query_word();
@@ -97,17 +104,33 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
         ]
         .map(|field_tag| cb.call_context(None, field_tag));
 
-        cb.range_lookup(depth.expr(), 1024);
+        // Depth of the root call is 1, and CALL fails with ErrorDepth at 1025.
+        cb.range_lookup(depth.expr() - 1.expr(), 1024);
 
-        // Lookup values from stack
+        // Lookup values from stack. STATICCALL doesn't take a value, so the
+        // following stack items are one position closer to the top.
         cb.stack_pop(gas_word.expr());
         cb.stack_pop(callee_address_word.expr());
-        cb.stack_pop(value.expr());
-        cb.stack_pop(cd_offset.expr());
-        cb.stack_pop(cd_length.expr());
-        cb.stack_pop(rd_offset.expr());
-        cb.stack_pop(rd_length.expr());
-        cb.stack_push(is_success.expr());
+        cb.condition(1.expr() - is_staticcall.expr(), |cb| {
+            cb.stack_pop(value.expr());
+        });
+        for (idx, item) in [
+            cd_offset.expr(),
+            cd_length.expr(),
+            rd_offset.expr(),
+            rd_length.expr(),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            cb.stack_lookup(false.expr(), (idx + 3).expr() - is_staticcall.expr(), item);
+        }
+        cb.stack_lookup(
+            true.expr(),
+            6.expr() - is_staticcall.expr(),
+            is_success.expr(),
+        );
+        let stack_pointer_delta = 6.expr() - is_staticcall.expr();
 
         // Recomposition of random linear combination to integer
         let callee_address =
@@ -134,7 +157,7 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
         );
 
         // Propagate rw_counter_end_of_reversion and is_persistent
-        let mut callee_reversion_info = cb.reversion_info(Some(callee_call_id.expr()));
+        let mut callee_reversion_info = cb.reversion_info_write(Some(callee_call_id.expr()));
         cb.require_equal(
             "callee_is_persistent == is_persistent ⋅ is_success",
             callee_reversion_info.is_persistent(),
@@ -151,6 +174,9 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
         // Verify transfer
         let value_is_zero = IsZeroGadget::construct(cb, sum::expr(&value.cells));
         let has_value = 1.expr() - value_is_zero.expr();
+        cb.condition(is_staticcall.expr(), |cb| {
+            cb.require_zero("STATICCALL has no value", has_value.clone());
+        });
         cb.condition(has_value.clone(), |cb| {
             cb.require_zero(
                 "CALL with value must not be in static call stack",
@@ -221,9 +247,9 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
             }
 
             cb.require_step_state_transition(StepStateTransition {
-                rw_counter: Delta(24.expr()),
+                rw_counter: Delta(24.expr() - is_staticcall.expr()),
                 program_counter: Delta(1.expr()),
-                stack_pointer: Delta(6.expr()),
+                stack_pointer: Delta(stack_pointer_delta.clone()),
                 gas_left: Delta(
                     has_value.clone() * GAS_STIPEND_CALL_WITH_VALUE.expr() - gas_cost.clone(),
                 ),
@@ -242,7 +268,7 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
                 ),
                 (
                     CallContextFieldTag::StackPointer,
-                    cb.curr.state.stack_pointer.expr() + 6.expr(),
+                    cb.curr.state.stack_pointer.expr() + stack_pointer_delta,
                 ),
                 (
                     CallContextFieldTag::GasLeft,
@@ -273,7 +299,11 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
                 (CallContextFieldTag::ReturnDataLength, rd_address.length()),
                 (CallContextFieldTag::Value, value.expr()),
                 (CallContextFieldTag::IsSuccess, is_success.expr()),
-                (CallContextFieldTag::IsStatic, is_static.expr()),
+                (
+                    CallContextFieldTag::IsStatic,
+                    is_static.expr() + is_staticcall.expr()
+                        - is_static.expr() * is_staticcall.expr(),
+                ),
                 (CallContextFieldTag::LastCalleeId, 0.expr()),
                 (CallContextFieldTag::LastCalleeReturnDataOffset, 0.expr()),
                 (CallContextFieldTag::LastCalleeReturnDataLength, 0.expr()),
@@ -281,14 +311,14 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
                 (CallContextFieldTag::IsCreate, 0.expr()),
                 (CallContextFieldTag::CodeHash, callee_code_hash.expr()),
             ] {
-                cb.call_context_lookup(false.expr(), Some(callee_call_id.expr()), field_tag, value);
+                cb.call_context_lookup(true.expr(), Some(callee_call_id.expr()), field_tag, value);
             }
 
             // Give gas stipend if value is not zero
             let callee_gas_left = callee_gas_left + has_value * GAS_STIPEND_CALL_WITH_VALUE.expr();
 
             cb.require_step_state_transition(StepStateTransition {
-                rw_counter: Delta(44.expr()),
+                rw_counter: Delta(44.expr() - is_staticcall.expr()),
                 call_id: To(callee_call_id.expr()),
                 is_root: To(false.expr()),
                 is_create: To(false.expr()),
@@ -301,6 +331,7 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
 
         Self {
             opcode,
+            is_staticcall,
             tx_id,
             reversion_info,
             current_address,
@@ -337,41 +368,55 @@ impl<F: Field> ExecutionGadget<F> for CallGadget<F> {
         call: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
+        let opcode = step.opcode.unwrap();
+        let is_call = opcode == OpcodeId::CALL;
+        // The rws after the value are shifted by one for STATICCALL, which
+        // doesn't pop a value.
+        let rw_index = |idx: usize| {
+            if is_call || idx < 8 {
+                step.rw_indices[idx]
+            } else {
+                step.rw_indices[idx - 1]
+            }
+        };
+
         let [tx_id, current_address, is_static, depth, callee_rw_counter_end_of_reversion, callee_is_persistent] =
             [
-                step.rw_indices[0],
-                step.rw_indices[3],
-                step.rw_indices[4],
-                step.rw_indices[5],
-                step.rw_indices[15],
-                step.rw_indices[16],
+                rw_index(0),
+                rw_index(3),
+                rw_index(4),
+                rw_index(5),
+                rw_index(15),
+                rw_index(16),
             ]
             .map(|idx| block.rws[idx].call_context_value());
-        let [gas, callee_address, value, cd_offset, cd_length, rd_offset, rd_length, is_success] =
-            [
-                step.rw_indices[6],
-                step.rw_indices[7],
-                step.rw_indices[8],
-                step.rw_indices[9],
-                step.rw_indices[10],
-                step.rw_indices[11],
-                step.rw_indices[12],
-                step.rw_indices[13],
-            ]
-            .map(|idx| block.rws[idx].stack_value());
-        let (is_warm, is_warm_prev) = block.rws[step.rw_indices[14]].tx_access_list_value_pair();
+        let [gas, callee_address, cd_offset, cd_length, rd_offset, rd_length, is_success] = [
+            rw_index(6),
+            rw_index(7),
+            rw_index(9),
+            rw_index(10),
+            rw_index(11),
+            rw_index(12),
+            rw_index(13),
+        ]
+        .map(|idx| block.rws[idx].stack_value());
+        let value = if is_call {
+            block.rws[rw_index(8)].stack_value()
+        } else {
+            eth_types::Word::zero()
+        };
+        let (is_warm, is_warm_prev) = block.rws[rw_index(14)].tx_access_list_value_pair();
         let [caller_balance_pair, callee_balance_pair, (callee_nonce, _), (callee_code_hash, _)] =
-            [
-                step.rw_indices[17],
-                step.rw_indices[18],
-                step.rw_indices[19],
-                step.rw_indices[20],
-            ]
-            .map(|idx| block.rws[idx].account_value_pair());
+            [rw_index(17), rw_index(18), rw_index(19), rw_index(20)]
+                .map(|idx| block.rws[idx].account_value_pair());
 
-        let opcode = step.opcode.unwrap();
         self.opcode
             .assign(region, offset, Some(F::from(opcode.as_u64())))?;
+        self.is_staticcall.assign(
+            region,
+            offset,
+            F::from(opcode.as_u64()) - F::from(OpcodeId::STATICCALL.as_u64()),
+        )?;
 
         self.tx_id
             .assign(region, offset, Some(F::from(tx_id.low_u64())))?;
@@ -545,6 +590,36 @@ mod test {
         }
     }
 
+    fn static_caller(stack: Stack) -> Account {
+        // Call twice for testing both cold and warm access
+        let bytecode = bytecode! {
+            PUSH32(Word::from(stack.rd_length))
+            PUSH32(Word::from(stack.rd_offset))
+            PUSH32(Word::from(stack.cd_length))
+            PUSH32(Word::from(stack.cd_offset))
+            PUSH32(Address::repeat_byte(0xff).to_word())
+            PUSH32(Word::from(stack.gas))
+            STATICCALL
+            PUSH32(Word::from(stack.rd_length))
+            PUSH32(Word::from(stack.rd_offset))
+            PUSH32(Word::from(stack.cd_length))
+            PUSH32(Word::from(stack.cd_offset))
+            PUSH32(Address::repeat_byte(0xff).to_word())
+            PUSH32(Word::from(stack.gas))
+            STATICCALL
+            PUSH1(0)
+            PUSH1(0)
+            RETURN
+        };
+
+        Account {
+            address: Address::repeat_byte(0xfe),
+            balance: Word::from(10).pow(20.into()),
+            code: bytecode.to_vec().into(),
+            ..Default::default()
+        }
+    }
+
     fn callee(code: Bytecode) -> Account {
         let code = code.to_vec();
         let is_empty = code.is_empty();
@@ -590,7 +665,7 @@ mod test {
         builder
             .handle_block(&block_data.eth_block, &block_data.geth_traces)
             .unwrap();
-        let block = block_convert(&builder.block, &builder.code_db);
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
         assert_eq!(
             if use_complete_fixed_table {
                 run_test_circuit_complete_fixed_table(block)
@@ -649,6 +724,35 @@ mod test {
         }
     }
 
+    #[test]
+    fn call_gadget_staticcall() {
+        let stacks = vec![
+            // With nothing
+            Stack::default(),
+            // With gas
+            Stack {
+                gas: 100000,
+                ..Default::default()
+            },
+            // With memory expansion
+            Stack {
+                cd_offset: 64,
+                cd_length: 320,
+                rd_offset: 0,
+                rd_length: 32,
+                ..Default::default()
+            },
+        ];
+        let callees = vec![
+            callee(bytecode! {}),
+            callee(bytecode! { STOP }),
+            callee(bytecode! { PUSH1(0) PUSH1(0) REVERT }),
+        ];
+        for (stack, callee) in stacks.into_iter().cartesian_product(callees.into_iter()) {
+            test_ok(static_caller(stack), callee, false);
+        }
+    }
+
     #[test]
     fn call_gadget_nested() {
         let callers = vec![
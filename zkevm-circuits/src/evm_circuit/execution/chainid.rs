@@ -9,7 +9,7 @@ use crate::{
         },
         witness::{Block, Call, ExecStep, Transaction},
     },
-    table::BlockContextFieldTag,
+    table::{BlockContextFieldTag, CallContextFieldTag, TxContextFieldTag},
     util::Expr,
 };
 use bus_mapping::evm::OpcodeId;
@@ -19,6 +19,8 @@ use halo2_proofs::plonk::Error;
 #[derive(Clone, Debug)]
 pub(crate) struct ChainIdGadget<F> {
     same_context: SameContextGadget<F>,
+    tx_id: Cell<F>,
+    block_number: Cell<F>,
     chain_id: Cell<F>,
 }
 
@@ -30,16 +32,25 @@ impl<F: Field> ExecutionGadget<F> for ChainIdGadget<F> {
     fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
         let chain_id = cb.query_cell();
 
+        // Lookup in call_ctx the TxId, and in the tx table the number of the
+        // block that includes the tx
+        let tx_id = cb.call_context(None, CallContextFieldTag::TxId);
+        let block_number = cb.tx_context(tx_id.expr(), TxContextFieldTag::BlockNumber, None);
+
         // Push the value to the stack
         cb.stack_push(chain_id.expr());
 
         // Lookup block table with chain_id
-        cb.block_lookup(BlockContextFieldTag::ChainId.expr(), None, chain_id.expr());
+        cb.block_lookup(
+            BlockContextFieldTag::ChainId.expr(),
+            Some(block_number.expr()),
+            chain_id.expr(),
+        );
 
         // State transition
         let opcode = cb.query_cell();
         let step_state_transition = StepStateTransition {
-            rw_counter: Delta(1.expr()),
+            rw_counter: Delta(2.expr()),
             program_counter: Delta(1.expr()),
             stack_pointer: Delta((-1).expr()),
             gas_left: Delta(-OpcodeId::CHAINID.constant_gas_cost().expr()),
@@ -49,6 +60,8 @@ impl<F: Field> ExecutionGadget<F> for ChainIdGadget<F> {
 
         Self {
             same_context,
+            tx_id,
+            block_number,
             chain_id,
         }
     }
@@ -58,12 +71,16 @@ impl<F: Field> ExecutionGadget<F> for ChainIdGadget<F> {
         region: &mut CachedRegion<'_, '_, F>,
         offset: usize,
         block: &Block<F>,
-        _: &Transaction,
+        tx: &Transaction,
         _: &Call,
         step: &ExecStep,
     ) -> Result<(), Error> {
         self.same_context.assign_exec_step(region, offset, step)?;
-        let chain_id = block.rws[step.rw_indices[0]].stack_value();
+        self.tx_id
+            .assign(region, offset, Some(F::from(tx.id as u64)))?;
+        self.block_number
+            .assign(region, offset, Some(F::from(tx.block_number)))?;
+        let chain_id = block.rws[step.rw_indices[1]].stack_value();
 
         self.chain_id.assign(
             region,
@@ -1,17 +1,28 @@
-use crate::evm_circuit::{
-    execution::ExecutionGadget,
-    step::ExecutionState,
-    util::{constraint_builder::ConstraintBuilder, CachedRegion},
-    witness::{Block, Call, ExecStep, Transaction},
+use crate::{
+    evm_circuit::{
+        execution::ExecutionGadget,
+        step::ExecutionState,
+        util::{
+            constraint_builder::{ConstraintBuilder, StepStateTransition, Transition::Same},
+            math_gadget::IsEqualGadget,
+            not, CachedRegion, Cell,
+        },
+        witness::{Block, Call, ExecStep, Transaction},
+    },
+    table::TxContextFieldTag,
+    util::Expr,
 };
 use eth_types::Field;
 use halo2_proofs::plonk::Error;
 
-use std::marker::PhantomData;
-
 #[derive(Clone, Debug)]
 pub(crate) struct EndBlockGadget<F> {
-    _marker: PhantomData<F>,
+    // Queried first, so that it's at the same position in every EndBlock step,
+    // which is expected by EndTx and by the previous EndBlock step.
+    total_txs: Cell<F>,
+    max_txs: Cell<F>,
+    max_rws: Cell<F>,
+    total_txs_is_max_txs: IsEqualGadget<F>,
 }
 
 impl<F: Field> ExecutionGadget<F> for EndBlockGadget<F> {
@@ -19,27 +30,162 @@ impl<F: Field> ExecutionGadget<F> for EndBlockGadget<F> {
 
     const EXECUTION_STATE: ExecutionState = ExecutionState::EndBlock;
 
-    fn configure(_: &mut ConstraintBuilder<F>) -> Self {
-        // TODO: For last step, constrain:
-        //         - tx_id is equal to total_tx
-        //         - rw_counter is equal to public input one
+    fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
+        let total_txs = cb.query_cell();
+        let max_txs = cb.query_copy_cell();
+        let max_rws = cb.query_copy_cell();
+        let total_txs_is_max_txs = IsEqualGadget::construct(cb, total_txs.expr(), max_txs.expr());
+
+        // In an empty block, the EndBlock step is the first one
+        cb.add_constraint_first_step(
+            "rw_counter is initialized to be 1",
+            1.expr() - cb.curr.state.rw_counter.expr(),
+        );
+        cb.add_constraint_first_step("total_txs is 0 in an empty block", total_txs.expr());
+
+        // Every rw lookup of the block uses a unique rw_counter, so there are at
+        // least total_rws meaningful rows in the RwTable.  The RwTable has
+        // max_rws rows, padded at the front with Rw::Start rows, so finding
+        // the Start row with rw_counter max_rws - total_rws proves that there
+        // are at most total_rws meaningful rows.
+        let total_rws = cb.curr.state.rw_counter.expr() - 1.expr();
+        cb.rw_table_start_lookup(max_rws.expr() - total_rws);
+
+        // Similarly, every tx from 1 to total_txs is looked up by its BeginTx
+        // step.  Unless the TxTable is full, the tx that follows them must be a
+        // padding tx, which has a zero caller address.
+        cb.condition(not::expr(total_txs_is_max_txs.expr()), |cb| {
+            cb.tx_context_lookup(
+                total_txs.expr() + 1.expr(),
+                TxContextFieldTag::CallerAddress,
+                None,
+                0.expr(),
+            );
+        });
 
-        // TODO: For the rest steps, propagate the rw_counter and call_id to next step.
+        // The padding EndBlock steps propagate the rw_counter, call_id,
+        // total_txs and capacities to the last step, where the capacities are
+        // bound to constants.
+        cb.not_step_last(|cb| {
+            cb.require_step_state_transition(StepStateTransition {
+                rw_counter: Same,
+                call_id: Same,
+                ..StepStateTransition::any()
+            });
+            cb.constrain_next_step(ExecutionState::EndBlock, None, |cb| {
+                let total_txs_next = cb.query_cell();
+                let max_txs_next = cb.query_copy_cell();
+                let max_rws_next = cb.query_copy_cell();
+                cb.require_equal(
+                    "total_txs is the same in the next EndBlock step",
+                    total_txs_next.expr(),
+                    total_txs.expr(),
+                );
+                cb.require_equal(
+                    "max_txs is the same in the next EndBlock step",
+                    max_txs_next.expr(),
+                    max_txs.expr(),
+                );
+                cb.require_equal(
+                    "max_rws is the same in the next EndBlock step",
+                    max_rws_next.expr(),
+                    max_rws.expr(),
+                );
+            });
+        });
 
         Self {
-            _marker: PhantomData,
+            total_txs,
+            max_txs,
+            max_rws,
+            total_txs_is_max_txs,
         }
     }
 
     fn assign_exec_step(
         &self,
-        _region: &mut CachedRegion<'_, '_, F>,
-        _offset: usize,
-        _: &Block<F>,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
         _: &Transaction,
         _: &Call,
         _step: &ExecStep,
     ) -> Result<(), Error> {
+        let total_txs = F::from(block.txs.len() as u64);
+        let max_txs = F::from(block.max_txs as u64);
+        let max_rws = F::from(block.max_rws as u64);
+
+        self.total_txs.assign(region, offset, Some(total_txs))?;
+        self.max_txs.assign(region, offset, Some(max_txs))?;
+        self.max_rws.assign(region, offset, Some(max_rws))?;
+        self.total_txs_is_max_txs
+            .assign(region, offset, total_txs, max_txs)?;
+
         Ok(())
     }
 }
+
+impl<F: Field> EndBlockGadget<F> {
+    /// Bind the capacities of the last EndBlock step at `offset` to the
+    /// constants of `block`, which the other steps copy.
+    pub(crate) fn constrain_capacities(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
+    ) -> Result<(), Error> {
+        let max_txs = F::from(block.max_txs as u64);
+        let max_rws = F::from(block.max_rws as u64);
+
+        let max_txs_cell = self.max_txs.assign(region, offset, Some(max_txs))?;
+        region.constrain_constant(max_txs_cell, max_txs)?;
+        let max_rws_cell = self.max_rws.assign(region, offset, Some(max_rws))?;
+        region.constrain_constant(max_rws_cell, max_rws)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evm_circuit::{
+        test::run_test_circuit_incomplete_fixed_table, witness::block_convert,
+    };
+    use eth_types::{bytecode, geth_types::GethData};
+    use mock::TestContext;
+
+    fn test_circuit(max_evm_rows: usize, extra_txs: usize) {
+        let bytecode = bytecode! {
+            PUSH1(0x00)
+            STOP
+        };
+        let block: GethData = TestContext::<2, 1>::simple_ctx_with_bytecode(bytecode)
+            .unwrap()
+            .into();
+        let block_data = bus_mapping::mock::BlockData::new_from_geth_data(block);
+        let mut builder = block_data.new_circuit_input_builder();
+        builder
+            .handle_block(&block_data.eth_block, &block_data.geth_traces)
+            .unwrap();
+        let mut block = block_convert(&builder.block, &builder.code_db).unwrap();
+        block.max_evm_rows = max_evm_rows;
+        block.max_txs += extra_txs;
+
+        assert_eq!(run_test_circuit_incomplete_fixed_table(block), Ok(()));
+    }
+
+    #[test]
+    fn end_block_simple() {
+        test_circuit(0, 0);
+    }
+
+    #[test]
+    fn end_block_padded() {
+        test_circuit(200, 0);
+    }
+
+    #[test]
+    fn end_block_padded_tx_table() {
+        test_circuit(200, 2);
+    }
+}
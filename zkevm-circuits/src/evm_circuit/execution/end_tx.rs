@@ -32,6 +32,7 @@ pub(crate) struct EndTxGadget<F> {
     effective_refund: MinMaxGadget<F, N_BYTES_GAS>,
     mul_gas_price_by_refund: MulWordByU64Gadget<F>,
     tx_caller_address: Cell<F>,
+    tx_block_number: Cell<F>,
     gas_fee_refund: UpdateBalanceGadget<F, 2, true>,
     sub_gas_price_by_base_fee: AddWordsGadget<F, 2, true>,
     mul_effective_tip_by_gas_used: MulWordByU64Gadget<F>,
@@ -51,9 +52,12 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
         let tx_id = cb.call_context(None, CallContextFieldTag::TxId);
         let is_persistent = cb.call_context(None, CallContextFieldTag::IsPersistent);
 
-        let [tx_gas, tx_caller_address] =
-            [TxContextFieldTag::Gas, TxContextFieldTag::CallerAddress]
-                .map(|field_tag| cb.tx_context(tx_id.expr(), field_tag, None));
+        let [tx_gas, tx_caller_address, tx_block_number] = [
+            TxContextFieldTag::Gas,
+            TxContextFieldTag::CallerAddress,
+            TxContextFieldTag::BlockNumber,
+        ]
+        .map(|field_tag| cb.tx_context(tx_id.expr(), field_tag, None));
         let tx_gas_price = cb.tx_context_as_word(tx_id.expr(), TxContextFieldTag::GasPrice, None);
 
         // Calculate effective gas to refund
@@ -87,7 +91,7 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             (BlockContextFieldTag::Coinbase, coinbase.expr()),
             (BlockContextFieldTag::BaseFee, base_fee.expr()),
         ] {
-            cb.block_lookup(tag.expr(), None, value);
+            cb.block_lookup(tag.expr(), Some(tx_block_number.expr()), value);
         }
         let effective_tip = cb.query_word();
         let sub_gas_price_by_base_fee =
@@ -144,8 +148,10 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
         cb.condition(
             cb.next.execution_state_selector([ExecutionState::BeginTx]),
             |cb| {
+                // Write the TxId of the next transaction, which is the first
+                // access to the call context of its root call.
                 cb.call_context_lookup(
-                    false.expr(),
+                    true.expr(),
                     Some(cb.next.state.rw_counter.expr()),
                     CallContextFieldTag::TxId,
                     tx_id.expr() + 1.expr(),
@@ -167,6 +173,19 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
                 });
             },
         );
+        cb.constrain_next_step(
+            ExecutionState::EndBlock,
+            Some(cb.next.execution_state_selector([ExecutionState::EndBlock])),
+            |cb| {
+                // The last tx of the block is the total_txs of EndBlock
+                let total_txs = cb.query_cell();
+                cb.require_equal(
+                    "total_txs is the tx_id of the last tx",
+                    total_txs.expr(),
+                    tx_id.expr(),
+                );
+            },
+        );
 
         Self {
             tx_id,
@@ -176,6 +195,7 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
             effective_refund,
             mul_gas_price_by_refund,
             tx_caller_address,
+            tx_block_number,
             gas_fee_refund,
             sub_gas_price_by_base_fee,
             mul_effective_tip_by_gas_used,
@@ -223,6 +243,8 @@ impl<F: Field> ExecutionGadget<F> for EndTxGadget<F> {
         )?;
         self.tx_caller_address
             .assign(region, offset, tx.caller_address.to_scalar())?;
+        self.tx_block_number
+            .assign(region, offset, Some(F::from(tx.block_number)))?;
         self.gas_fee_refund.assign(
             region,
             offset,
@@ -294,7 +316,7 @@ mod test {
         builder
             .handle_block(&block_data.eth_block, &block_data.geth_traces)
             .unwrap();
-        let block = block_convert(&builder.block, &builder.code_db);
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
 
         assert_eq!(run_test_circuit_incomplete_fixed_table(block), Ok(()));
     }
@@ -0,0 +1,594 @@
+use crate::{
+    evm_circuit::{
+        execution::ExecutionGadget,
+        param::{N_BYTES_ACCOUNT_ADDRESS, N_BYTES_MEMORY_WORD_SIZE},
+        step::ExecutionState,
+        util::{
+            constraint_builder::{
+                ConstraintBuilder, ReversionInfo, StepStateTransition,
+                Transition::{Delta, To},
+            },
+            from_bytes,
+            math_gadget::{BatchedIsZeroGadget, IsEqualGadget, IsZeroGadget, LtWordGadget},
+            memory_gadget::{MemoryAddressGadget, MemoryExpansionGadget},
+            select, sum, CachedRegion, Cell, Word,
+        },
+        witness::{Block, Call, ExecStep, Transaction},
+    },
+    table::{AccountFieldTag, CallContextFieldTag},
+    util::Expr,
+};
+use bus_mapping::evm::OpcodeId;
+use eth_types::{
+    evm_types::{GasCost, GAS_STIPEND_CALL_WITH_VALUE},
+    Field, ToLittleEndian, ToScalar,
+};
+use halo2_proofs::plonk::Error;
+use keccak256::EMPTY_HASH_LE;
+
+/// Gadget for a CALL, CALLCODE, DELEGATECALL or STATICCALL which fails before
+/// the callee is executed. The caller still pays for the account access, value
+/// transfer and memory expansion, but gets back the gas which would have been
+/// given to the callee, a `0` is pushed on the stack, and the execution
+/// continues in the same context.
+#[derive(Clone, Debug)]
+pub(crate) struct ErrorCallGadget<F> {
+    opcode: Cell<F>,
+    is_call: IsZeroGadget<F>,
+    is_callcode: IsZeroGadget<F>,
+    is_delegatecall: IsZeroGadget<F>,
+    is_staticcall: IsZeroGadget<F>,
+    tx_id: Cell<F>,
+    reversion_info: ReversionInfo<F>,
+    current_address: Cell<F>,
+    is_static: Cell<F>,
+    depth: Cell<F>,
+    gas: Word<F>,
+    callee_address: Word<F>,
+    value: Word<F>,
+    is_warm_prev: Cell<F>,
+    value_is_zero: IsZeroGadget<F>,
+    cd_address: MemoryAddressGadget<F>,
+    rd_address: MemoryAddressGadget<F>,
+    memory_expansion: MemoryExpansionGadget<F, 2, N_BYTES_MEMORY_WORD_SIZE>,
+    caller_balance: Word<F>,
+    callee_nonce: Cell<F>,
+    callee_balance: Cell<F>,
+    callee_code_hash: Cell<F>,
+    is_empty_nonce_and_balance: BatchedIsZeroGadget<F, 2>,
+    is_empty_code_hash: IsEqualGadget<F>,
+}
+
+impl<F: Field> ErrorCallGadget<F> {
+    fn construct(cb: &mut ConstraintBuilder<F>) -> Self {
+        let opcode = cb.query_cell();
+        cb.opcode_lookup(opcode.expr(), 1.expr());
+
+        // We do the responsible opcode check explicitly here because we're not using
+        // the `SameContextGadget` for `CALL`.
+        let [is_call, is_callcode, is_delegatecall, is_staticcall] = [
+            OpcodeId::CALL,
+            OpcodeId::CALLCODE,
+            OpcodeId::DELEGATECALL,
+            OpcodeId::STATICCALL,
+        ]
+        .map(|op| IsZeroGadget::construct(cb, opcode.expr() - op.expr()));
+        cb.require_equal(
+            "Opcode should be CALL, CALLCODE, DELEGATECALL or STATICCALL",
+            is_call.expr() + is_callcode.expr() + is_delegatecall.expr() + is_staticcall.expr(),
+            1.expr(),
+        );
+        // DELEGATECALL and STATICCALL don't take a value, so the following
+        // stack items are one position closer to the top.
+        let no_value_arg = is_delegatecall.expr() + is_staticcall.expr();
+
+        let gas_word = cb.query_word();
+        let callee_address_word = cb.query_word();
+        let value = cb.query_word();
+        let cd_offset = cb.query_cell();
+        let cd_length = cb.query_rlc();
+        let rd_offset = cb.query_cell();
+        let rd_length = cb.query_rlc();
+
+        let tx_id = cb.call_context(None, CallContextFieldTag::TxId);
+        let mut reversion_info = cb.reversion_info(None);
+        let [current_address, is_static, depth] = [
+            CallContextFieldTag::CalleeAddress,
+            CallContextFieldTag::IsStatic,
+            CallContextFieldTag::Depth,
+        ]
+        .map(|field_tag| cb.call_context(None, field_tag));
+
+        // Lookup values from stack
+        cb.stack_pop(gas_word.expr());
+        cb.stack_pop(callee_address_word.expr());
+        cb.condition(1.expr() - no_value_arg.clone(), |cb| {
+            cb.stack_pop(value.expr());
+        });
+        for (idx, item) in [
+            cd_offset.expr(),
+            cd_length.expr(),
+            rd_offset.expr(),
+            rd_length.expr(),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            cb.stack_lookup(false.expr(), (idx + 3).expr() - no_value_arg.clone(), item);
+        }
+        // The call fails, so 0 is pushed
+        cb.stack_lookup(true.expr(), 6.expr() - no_value_arg.clone(), 0.expr());
+
+        // Recomposition of random linear combination to integer
+        let callee_address =
+            from_bytes::expr(&callee_address_word.cells[..N_BYTES_ACCOUNT_ADDRESS]);
+        let cd_address = MemoryAddressGadget::construct(cb, cd_offset, cd_length);
+        let rd_address = MemoryAddressGadget::construct(cb, rd_offset, rd_length);
+        let memory_expansion = MemoryExpansionGadget::construct(
+            cb,
+            cb.curr.state.memory_word_size.expr(),
+            [cd_address.address(), rd_address.address()],
+        );
+
+        // Callee is added to access list even if the call fails
+        let is_warm_prev = cb.query_bool();
+        cb.account_access_list_write(
+            tx_id.expr(),
+            callee_address.clone(),
+            1.expr(),
+            is_warm_prev.expr(),
+            Some(&mut reversion_info),
+        );
+
+        // Write protection is checked before depth and balance, so a CALL with
+        // value in a static call would have failed with ErrorWriteProtection.
+        let value_is_zero = IsZeroGadget::construct(cb, sum::expr(&value.cells));
+        let has_value = 1.expr() - value_is_zero.expr();
+        cb.condition(no_value_arg.clone(), |cb| {
+            cb.require_zero(
+                "DELEGATECALL and STATICCALL have no value",
+                has_value.clone(),
+            );
+        });
+        cb.condition(is_call.expr() * has_value.clone(), |cb| {
+            cb.require_zero(
+                "CALL with value must not be in static call stack",
+                is_static.expr(),
+            );
+        });
+
+        let caller_balance = cb.query_word();
+        cb.account_read(
+            current_address.expr(),
+            AccountFieldTag::Balance,
+            caller_balance.expr(),
+        );
+
+        // Verify gas cost
+        let [callee_nonce, callee_balance, callee_code_hash] = [
+            AccountFieldTag::Nonce,
+            AccountFieldTag::Balance,
+            AccountFieldTag::CodeHash,
+        ]
+        .map(|field_tag| {
+            let value = cb.query_cell();
+            cb.account_read(callee_address.clone(), field_tag, value.expr());
+            value
+        });
+        let is_empty_nonce_and_balance =
+            BatchedIsZeroGadget::construct(cb, [callee_nonce.expr(), callee_balance.expr()]);
+        let is_empty_code_hash = IsEqualGadget::construct(
+            cb,
+            callee_code_hash.expr(),
+            Word::random_linear_combine_expr(
+                (*EMPTY_HASH_LE).map(|byte| byte.expr()),
+                cb.power_of_randomness(),
+            ),
+        );
+        let is_empty_account = is_empty_nonce_and_balance.expr() * is_empty_code_hash.expr();
+        // Sum up gas cost
+        let gas_cost = select::expr(
+            is_warm_prev.expr(),
+            GasCost::WARM_ACCESS.expr(),
+            GasCost::COLD_ACCOUNT_ACCESS.expr(),
+        ) + has_value.clone()
+            * (GasCost::CALL_WITH_VALUE.expr()
+                + is_call.expr() * is_empty_account * GasCost::NEW_ACCOUNT.expr())
+            + memory_expansion.gas_cost();
+
+        // Save caller's call state
+        for field_tag in [
+            CallContextFieldTag::LastCalleeId,
+            CallContextFieldTag::LastCalleeReturnDataOffset,
+            CallContextFieldTag::LastCalleeReturnDataLength,
+        ] {
+            cb.call_context_lookup(true.expr(), None, field_tag, 0.expr());
+        }
+
+        // The gas given to the callee is returned, including the stipend.
+        cb.require_step_state_transition(StepStateTransition {
+            rw_counter: Delta(22.expr() - no_value_arg.clone()),
+            program_counter: Delta(1.expr()),
+            stack_pointer: Delta(6.expr() - no_value_arg),
+            gas_left: Delta(has_value * GAS_STIPEND_CALL_WITH_VALUE.expr() - gas_cost),
+            memory_word_size: To(memory_expansion.next_memory_word_size()),
+            reversible_write_counter: Delta(1.expr()),
+            ..StepStateTransition::default()
+        });
+
+        Self {
+            opcode,
+            is_call,
+            is_callcode,
+            is_delegatecall,
+            is_staticcall,
+            tx_id,
+            reversion_info,
+            current_address,
+            is_static,
+            depth,
+            gas: gas_word,
+            callee_address: callee_address_word,
+            value,
+            is_warm_prev,
+            value_is_zero,
+            cd_address,
+            rd_address,
+            memory_expansion,
+            caller_balance,
+            callee_nonce,
+            callee_balance,
+            callee_code_hash,
+            is_empty_nonce_and_balance,
+            is_empty_code_hash,
+        }
+    }
+
+    fn assign(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
+        call: &Call,
+        step: &ExecStep,
+    ) -> Result<(), Error> {
+        let rw = |idx| rw_index(step, idx);
+        let [tx_id, current_address, is_static, depth] =
+            [rw(0), rw(3), rw(4), rw(5)].map(|idx| block.rws[idx].call_context_value());
+        let [gas, callee_address, cd_offset, cd_length, rd_offset, rd_length] =
+            [rw(6), rw(7), rw(9), rw(10), rw(11), rw(12)].map(|idx| block.rws[idx].stack_value());
+        let value = call_value(block, step);
+        let (_, is_warm_prev) = block.rws[rw(14)].tx_access_list_value_pair();
+        let [(caller_balance, _), (callee_nonce, _), (callee_balance, _), (callee_code_hash, _)] =
+            [rw(15), rw(16), rw(17), rw(18)].map(|idx| block.rws[idx].account_value_pair());
+
+        let opcode = step.opcode.unwrap();
+        self.opcode
+            .assign(region, offset, Some(F::from(opcode.as_u64())))?;
+        for (is_opcode, op) in [
+            (&self.is_call, OpcodeId::CALL),
+            (&self.is_callcode, OpcodeId::CALLCODE),
+            (&self.is_delegatecall, OpcodeId::DELEGATECALL),
+            (&self.is_staticcall, OpcodeId::STATICCALL),
+        ] {
+            is_opcode.assign(
+                region,
+                offset,
+                F::from(opcode.as_u64()) - F::from(op.as_u64()),
+            )?;
+        }
+
+        self.tx_id
+            .assign(region, offset, Some(F::from(tx_id.low_u64())))?;
+        self.reversion_info.assign(
+            region,
+            offset,
+            call.rw_counter_end_of_reversion,
+            call.is_persistent,
+        )?;
+        self.current_address
+            .assign(region, offset, current_address.to_scalar())?;
+        self.is_static
+            .assign(region, offset, Some(F::from(is_static.low_u64())))?;
+        self.depth
+            .assign(region, offset, Some(F::from(depth.low_u64())))?;
+
+        self.gas.assign(region, offset, Some(gas.to_le_bytes()))?;
+        self.callee_address
+            .assign(region, offset, Some(callee_address.to_le_bytes()))?;
+        self.value
+            .assign(region, offset, Some(value.to_le_bytes()))?;
+        self.is_warm_prev
+            .assign(region, offset, Some(F::from(is_warm_prev as u64)))?;
+        self.value_is_zero
+            .assign(region, offset, sum::value(&value.to_le_bytes()))?;
+        let cd_address =
+            self.cd_address
+                .assign(region, offset, cd_offset, cd_length, block.randomness)?;
+        let rd_address =
+            self.rd_address
+                .assign(region, offset, rd_offset, rd_length, block.randomness)?;
+        self.memory_expansion.assign(
+            region,
+            offset,
+            step.memory_word_size(),
+            [cd_address, rd_address],
+        )?;
+        self.caller_balance
+            .assign(region, offset, Some(caller_balance.to_le_bytes()))?;
+        self.callee_nonce
+            .assign(region, offset, callee_nonce.to_scalar())?;
+        let callee_balance =
+            Word::random_linear_combine(callee_balance.to_le_bytes(), block.randomness);
+        self.callee_balance
+            .assign(region, offset, Some(callee_balance))?;
+        self.callee_code_hash.assign(
+            region,
+            offset,
+            Some(Word::random_linear_combine(
+                callee_code_hash.to_le_bytes(),
+                block.randomness,
+            )),
+        )?;
+        self.is_empty_nonce_and_balance.assign(
+            region,
+            offset,
+            [F::from(callee_nonce.low_u64()), callee_balance],
+        )?;
+        self.is_empty_code_hash.assign(
+            region,
+            offset,
+            Word::random_linear_combine(callee_code_hash.to_le_bytes(), block.randomness),
+            Word::random_linear_combine(*EMPTY_HASH_LE, block.randomness),
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Return the index in `step.rw_indices` of the rw at position `idx` of a
+/// CALL. The rws after the value are shifted by one for DELEGATECALL and
+/// STATICCALL, which don't pop a value.
+fn rw_index(step: &ExecStep, idx: usize) -> usize {
+    match step.opcode {
+        Some(OpcodeId::DELEGATECALL | OpcodeId::STATICCALL) if idx >= 8 => step.rw_indices[idx - 1],
+        _ => step.rw_indices[idx],
+    }
+}
+
+/// Return the value transferred by the call of `step`, which is zero for
+/// DELEGATECALL and STATICCALL.
+fn call_value<F: Field>(block: &Block<F>, step: &ExecStep) -> eth_types::Word {
+    match step.opcode {
+        Some(OpcodeId::DELEGATECALL | OpcodeId::STATICCALL) => eth_types::Word::zero(),
+        _ => block.rws[step.rw_indices[8]].stack_value(),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ErrorDepthGadget<F> {
+    error_call: ErrorCallGadget<F>,
+}
+
+impl<F: Field> ExecutionGadget<F> for ErrorDepthGadget<F> {
+    const NAME: &'static str = "ErrorDepth";
+
+    const EXECUTION_STATE: ExecutionState = ExecutionState::ErrorDepth;
+
+    fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
+        let error_call = ErrorCallGadget::construct(cb);
+
+        // Depth of the root call is 1, so the call stack is full when the
+        // current depth is 1025.
+        cb.require_equal("depth == 1025", error_call.depth.expr(), 1025.expr());
+
+        Self { error_call }
+    }
+
+    fn assign_exec_step(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
+        _: &Transaction,
+        call: &Call,
+        step: &ExecStep,
+    ) -> Result<(), Error> {
+        self.error_call.assign(region, offset, block, call, step)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ErrorInsufficientBalanceGadget<F> {
+    error_call: ErrorCallGadget<F>,
+    insufficient_balance: LtWordGadget<F>,
+}
+
+impl<F: Field> ExecutionGadget<F> for ErrorInsufficientBalanceGadget<F> {
+    const NAME: &'static str = "ErrorInsufficientBalance";
+
+    const EXECUTION_STATE: ExecutionState = ExecutionState::ErrorInsufficientBalance;
+
+    fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
+        let error_call = ErrorCallGadget::construct(cb);
+
+        // Depth is checked before balance, so it must be valid here.
+        cb.range_lookup(error_call.depth.expr() - 1.expr(), 1024);
+
+        let insufficient_balance =
+            LtWordGadget::construct(cb, &error_call.caller_balance, &error_call.value);
+        cb.require_equal(
+            "caller_balance < value",
+            insufficient_balance.expr(),
+            1.expr(),
+        );
+
+        Self {
+            error_call,
+            insufficient_balance,
+        }
+    }
+
+    fn assign_exec_step(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
+        _: &Transaction,
+        call: &Call,
+        step: &ExecStep,
+    ) -> Result<(), Error> {
+        self.error_call.assign(region, offset, block, call, step)?;
+
+        let (caller_balance, _) = block.rws[rw_index(step, 15)].account_value_pair();
+        let value = call_value(block, step);
+        self.insufficient_balance
+            .assign(region, offset, caller_balance, value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evm_circuit::{
+        step::ExecutionState, test::run_test_circuit_incomplete_fixed_table, witness::block_convert,
+    };
+    use bus_mapping::evm::OpcodeId;
+    use eth_types::{address, bytecode, Address, Bytecode, ToWord, Word};
+    use mock::TestContext;
+
+    fn test_ok(opcode: OpcodeId, caller_balance: Word, value: Word, callee_code: Bytecode) {
+        let callee_address = Address::repeat_byte(0xff);
+        let mut caller_code = bytecode! {
+            PUSH32(Word::from(32)) // retLength
+            PUSH32(Word::from(0)) // retOffset
+            PUSH32(Word::from(64)) // argsLength
+            PUSH32(Word::from(0)) // argsOffset
+            PUSH32(value)
+            PUSH32(callee_address.to_word())
+            PUSH32(Word::from(10000)) // gas
+        };
+        caller_code.write_op(opcode);
+        caller_code.write_op(OpcodeId::STOP);
+
+        let block = TestContext::<3, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x000000000000000000000000000000000000cafe"))
+                    .balance(Word::from(10u64.pow(19)));
+                accs[1]
+                    .address(Address::repeat_byte(0xfe))
+                    .code(caller_code)
+                    .balance(caller_balance);
+                accs[2].address(callee_address).code(callee_code);
+            },
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[0].address)
+                    .to(accs[1].address)
+                    .gas(100000.into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let block_data = bus_mapping::mock::BlockData::new_from_geth_data(block);
+        let mut builder = block_data.new_circuit_input_builder();
+        builder
+            .handle_block(&block_data.eth_block, &block_data.geth_traces)
+            .unwrap();
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
+        assert_eq!(run_test_circuit_incomplete_fixed_table(block), Ok(()));
+    }
+
+    #[test]
+    fn error_insufficient_balance() {
+        for opcode in [OpcodeId::CALL, OpcodeId::CALLCODE] {
+            // Callee with code
+            test_ok(opcode, Word::zero(), Word::one(), bytecode! { STOP });
+            test_ok(
+                opcode,
+                Word::from(10).pow(18.into()),
+                Word::from(10).pow(18.into()) + 1,
+                bytecode! { STOP },
+            );
+            // Empty callee
+            test_ok(opcode, Word::from(100), Word::from(101), bytecode! {});
+        }
+    }
+
+    fn test_depth(opcode: OpcodeId) {
+        let address = Address::repeat_byte(0xfe);
+        // The contract calls itself until the call stack is full, so the call
+        // at depth 1025 fails with ErrorDepth.
+        let mut code = bytecode! {
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0) // argsLength
+            PUSH1(0) // argsOffset
+        };
+        if matches!(opcode, OpcodeId::CALL | OpcodeId::CALLCODE) {
+            code.push(1, Word::zero()); // value
+        }
+        code.push(32, address.to_word());
+        code.write_op(OpcodeId::GAS);
+        code.write_op(opcode);
+        code.write_op(OpcodeId::STOP);
+
+        let block = TestContext::<2, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x000000000000000000000000000000000000cafe"))
+                    .balance(Word::from(10u64.pow(19)));
+                accs[1].address(address).code(code);
+            },
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[0].address)
+                    .to(accs[1].address)
+                    .gas(Word::from(10u64.pow(11)));
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let block_data = bus_mapping::mock::BlockData::new_from_geth_data(block);
+        let mut builder = block_data.new_circuit_input_builder();
+        builder
+            .handle_block(&block_data.eth_block, &block_data.geth_traces)
+            .unwrap();
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
+        assert!(block.txs[0]
+            .steps
+            .iter()
+            .any(|step| step.execution_state == ExecutionState::ErrorDepth
+                && step.opcode == Some(opcode)));
+        assert_eq!(run_test_circuit_incomplete_fixed_table(block), Ok(()));
+    }
+
+    #[test]
+    fn error_depth() {
+        test_depth(OpcodeId::CALL);
+    }
+
+    // Each of these builds 1024 nested calls.  Run in serial with:
+    // `cargo test [...] serial_ -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn serial_error_depth_callcode() {
+        test_depth(OpcodeId::CALLCODE);
+    }
+
+    #[ignore]
+    #[test]
+    fn serial_error_depth_delegatecall() {
+        test_depth(OpcodeId::DELEGATECALL);
+    }
+
+    #[ignore]
+    #[test]
+    fn serial_error_depth_staticcall() {
+        test_depth(OpcodeId::STATICCALL);
+    }
+}
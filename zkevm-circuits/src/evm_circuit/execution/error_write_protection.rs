@@ -0,0 +1,224 @@
+use crate::{
+    evm_circuit::{
+        execution::ExecutionGadget,
+        step::ExecutionState,
+        table::{FixedTableTag, Lookup},
+        util::{
+            common_gadget::RestoreContextGadget, constraint_builder::ConstraintBuilder,
+            math_gadget::IsZeroGadget, sum, CachedRegion, Cell, Word,
+        },
+        witness::{Block, Call, ExecStep, Transaction},
+    },
+    table::CallContextFieldTag,
+    util::Expr,
+};
+use bus_mapping::evm::OpcodeId;
+use eth_types::{Field, ToLittleEndian};
+use halo2_proofs::plonk::Error;
+
+/// Gadget for an opcode which tries to modify the state in a static call,
+/// which halts the current call in exception.
+#[derive(Clone, Debug)]
+pub(crate) struct ErrorWriteProtectionGadget<F> {
+    opcode: Cell<F>,
+    is_call: IsZeroGadget<F>,
+    gas: Word<F>,
+    callee_address: Word<F>,
+    value: Word<F>,
+    value_is_zero: IsZeroGadget<F>,
+    restore_context: RestoreContextGadget<F>,
+}
+
+impl<F: Field> ExecutionGadget<F> for ErrorWriteProtectionGadget<F> {
+    const NAME: &'static str = "ErrorWriteProtection";
+
+    const EXECUTION_STATE: ExecutionState = ExecutionState::ErrorWriteProtection;
+
+    fn configure(cb: &mut ConstraintBuilder<F>) -> Self {
+        let opcode = cb.query_cell();
+        cb.opcode_lookup(opcode.expr(), 1.expr());
+        cb.add_lookup(
+            "Responsible opcode lookup",
+            Lookup::Fixed {
+                tag: FixedTableTag::ResponsibleOpcode.expr(),
+                values: [
+                    cb.execution_state().as_u64().expr(),
+                    opcode.expr(),
+                    0.expr(),
+                ],
+            },
+        );
+
+        let is_call = IsZeroGadget::construct(cb, opcode.expr() - OpcodeId::CALL.expr());
+
+        // Current call must be static
+        cb.call_context_lookup(false.expr(), None, CallContextFieldTag::IsStatic, 1.expr());
+
+        // CALL only breaks write protection when it has value
+        let gas = cb.query_word();
+        let callee_address = cb.query_word();
+        let value = cb.query_word();
+        let value_is_zero = IsZeroGadget::construct(cb, sum::expr(&value.cells));
+        cb.condition(is_call.expr(), |cb| {
+            cb.stack_pop(gas.expr());
+            cb.stack_pop(callee_address.expr());
+            cb.stack_pop(value.expr());
+            cb.require_zero("CALL with value", value_is_zero.expr());
+        });
+
+        // Call ends with exception must not be successful
+        cb.call_context_lookup(false.expr(), None, CallContextFieldTag::IsSuccess, 0.expr());
+
+        // Root call can't be static, so the caller's context is always restored.
+        // Reversions of the reversible writes happen after the restoration.
+        let restore_context = RestoreContextGadget::construct(
+            cb,
+            2.expr() + 3.expr() * is_call.expr() + cb.curr.state.reversible_write_counter.expr(),
+            0.expr(),
+            0.expr(),
+        );
+
+        Self {
+            opcode,
+            is_call,
+            gas,
+            callee_address,
+            value,
+            value_is_zero,
+            restore_context,
+        }
+    }
+
+    fn assign_exec_step(
+        &self,
+        region: &mut CachedRegion<'_, '_, F>,
+        offset: usize,
+        block: &Block<F>,
+        _: &Transaction,
+        call: &Call,
+        step: &ExecStep,
+    ) -> Result<(), Error> {
+        let opcode = step.opcode.unwrap();
+        self.opcode
+            .assign(region, offset, Some(F::from(opcode.as_u64())))?;
+
+        let is_call = opcode == OpcodeId::CALL;
+        self.is_call.assign(
+            region,
+            offset,
+            F::from(opcode.as_u64()) - F::from(OpcodeId::CALL.as_u64()),
+        )?;
+
+        if is_call {
+            let [gas, callee_address, value] =
+                [step.rw_indices[1], step.rw_indices[2], step.rw_indices[3]]
+                    .map(|idx| block.rws[idx].stack_value());
+            self.gas.assign(region, offset, Some(gas.to_le_bytes()))?;
+            self.callee_address
+                .assign(region, offset, Some(callee_address.to_le_bytes()))?;
+            self.value
+                .assign(region, offset, Some(value.to_le_bytes()))?;
+            self.value_is_zero
+                .assign(region, offset, sum::value(&value.to_le_bytes()))?;
+        } else {
+            self.value_is_zero.assign(region, offset, F::zero())?;
+        }
+
+        let rw_offset = if is_call { 5 } else { 2 };
+        self.restore_context
+            .assign(region, offset, block, call, step, rw_offset)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::evm_circuit::{
+        test::run_test_circuit_incomplete_fixed_table, witness::block_convert,
+    };
+    use eth_types::{address, bytecode, Address, Bytecode, ToWord, Word};
+    use mock::TestContext;
+
+    fn test_ok(callee_code: Bytecode) {
+        let callee_address = Address::repeat_byte(0xff);
+        let caller_code = bytecode! {
+            PUSH32(Word::from(32)) // retLength
+            PUSH32(Word::from(0)) // retOffset
+            PUSH32(Word::from(64)) // argsLength
+            PUSH32(Word::from(0)) // argsOffset
+            PUSH32(callee_address.to_word())
+            PUSH32(Word::from(100000)) // gas
+            STATICCALL
+            STOP
+        };
+
+        let block = TestContext::<3, 1>::new(
+            None,
+            |accs| {
+                accs[0]
+                    .address(address!("0x000000000000000000000000000000000000cafe"))
+                    .balance(Word::from(10u64.pow(19)));
+                accs[1]
+                    .address(Address::repeat_byte(0xfe))
+                    .code(caller_code)
+                    .balance(Word::from(10u64.pow(19)));
+                accs[2]
+                    .address(callee_address)
+                    .code(callee_code)
+                    .balance(Word::from(10u64.pow(19)));
+            },
+            |mut txs, accs| {
+                txs[0]
+                    .from(accs[0].address)
+                    .to(accs[1].address)
+                    .gas(1000000.into());
+            },
+            |block, _tx| block.number(0xcafeu64),
+        )
+        .unwrap()
+        .into();
+        let block_data = bus_mapping::mock::BlockData::new_from_geth_data(block);
+        let mut builder = block_data.new_circuit_input_builder();
+        builder
+            .handle_block(&block_data.eth_block, &block_data.geth_traces)
+            .unwrap();
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
+        assert_eq!(run_test_circuit_incomplete_fixed_table(block), Ok(()));
+    }
+
+    #[test]
+    fn error_write_protection_sstore() {
+        test_ok(bytecode! {
+            PUSH1(1) // value
+            PUSH1(0) // key
+            SSTORE
+            STOP
+        });
+    }
+
+    #[test]
+    fn error_write_protection_log() {
+        test_ok(bytecode! {
+            PUSH1(0) // length
+            PUSH1(0) // offset
+            LOG0
+            STOP
+        });
+    }
+
+    #[test]
+    fn error_write_protection_call_with_value() {
+        test_ok(bytecode! {
+            PUSH1(0) // retLength
+            PUSH1(0) // retOffset
+            PUSH1(0) // argsLength
+            PUSH1(0) // argsOffset
+            PUSH1(1) // value
+            PUSH32(Address::repeat_byte(0xfe).to_word())
+            PUSH1(0) // gas
+            CALL
+            STOP
+        });
+    }
+}
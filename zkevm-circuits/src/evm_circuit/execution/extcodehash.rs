@@ -252,7 +252,7 @@ mod test {
             .expect("could not handle block tx");
 
         test_circuits_using_witness_block(
-            block_convert(&builder.block, &builder.code_db),
+            block_convert(&builder.block, &builder.code_db).unwrap(),
             BytecodeTestConfig::default(),
         )
         .unwrap();
@@ -120,7 +120,7 @@ impl<F: Field> ExecutionGadget<F> for StopGadget<F> {
             .assign(region, offset, Some(F::from(opcode.as_u64())))?;
 
         self.restore_context
-            .assign(region, offset, block, call, step)?;
+            .assign(region, offset, block, call, step, 1)?;
 
         Ok(())
     }
@@ -203,7 +203,7 @@ mod test {
         builder
             .handle_block(&block_data.eth_block, &block_data.geth_traces)
             .unwrap();
-        let block = block_convert(&builder.block, &builder.code_db);
+        let block = block_convert(&builder.block, &builder.code_db).unwrap();
         assert_eq!(run_test_circuit_incomplete_fixed_table(block), Ok(()));
     }
 
@@ -17,6 +17,10 @@ pub(crate) const LOOKUP_CONFIG: &[(Table, usize)] = &[
     (Table::Copy, 1),
 ];
 
+/// Columns of the step with copy constraints enabled, used for the cells
+/// bound to constants.
+pub(crate) const N_COPY_COLUMNS: usize = 2;
+
 /// Maximum number of bytes that an integer can fit in field without wrapping
 /// around.
 pub(crate) const MAX_N_BYTES_INTEGER: usize = 31;
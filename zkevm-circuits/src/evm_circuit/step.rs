@@ -84,7 +84,6 @@ pub enum ExecutionState {
     RETURN,
     DELEGATECALL,
     CREATE2,
-    STATICCALL,
     REVERT,
     SELFDESTRUCT,
     // Error cases
@@ -145,8 +144,6 @@ impl ExecutionState {
                 | Self::ErrorStackOverflow
                 | Self::ErrorStackUnderflow
                 | Self::ErrorWriteProtection
-                | Self::ErrorDepth
-                | Self::ErrorInsufficientBalance
                 | Self::ErrorContractAddressCollision
                 | Self::ErrorInvalidCreationCode
                 | Self::ErrorMaxCodeSizeExceeded
@@ -309,14 +306,32 @@ impl ExecutionState {
                 OpcodeId::LOG4,
             ],
             Self::CREATE => vec![OpcodeId::CREATE],
-            Self::CALL => vec![OpcodeId::CALL],
+            Self::CALL => vec![OpcodeId::CALL, OpcodeId::STATICCALL],
             Self::CALLCODE => vec![OpcodeId::CALLCODE],
             Self::RETURN => vec![OpcodeId::RETURN],
             Self::DELEGATECALL => vec![OpcodeId::DELEGATECALL],
             Self::CREATE2 => vec![OpcodeId::CREATE2],
-            Self::STATICCALL => vec![OpcodeId::STATICCALL],
             Self::REVERT => vec![OpcodeId::REVERT],
             Self::SELFDESTRUCT => vec![OpcodeId::SELFDESTRUCT],
+            Self::ErrorWriteProtection => vec![
+                OpcodeId::SSTORE,
+                OpcodeId::CREATE,
+                OpcodeId::CREATE2,
+                OpcodeId::CALL,
+                OpcodeId::SELFDESTRUCT,
+                OpcodeId::LOG0,
+                OpcodeId::LOG1,
+                OpcodeId::LOG2,
+                OpcodeId::LOG3,
+                OpcodeId::LOG4,
+            ],
+            Self::ErrorDepth => vec![
+                OpcodeId::CALL,
+                OpcodeId::CALLCODE,
+                OpcodeId::DELEGATECALL,
+                OpcodeId::STATICCALL,
+            ],
+            Self::ErrorInsufficientBalance => vec![OpcodeId::CALL, OpcodeId::CALLCODE],
             _ => vec![],
         }
     }
@@ -1,6 +1,6 @@
 use crate::{
     evm_circuit::{
-        param::{LOOKUP_CONFIG, N_BYTES_MEMORY_ADDRESS},
+        param::{LOOKUP_CONFIG, N_BYTES_MEMORY_ADDRESS, N_COPY_COLUMNS},
         table::Table,
     },
     util::Expr,
@@ -125,6 +125,15 @@ impl<'r, 'b, F: FieldExt> CachedRegion<'r, 'b, F> {
         res
     }
 
+    /// Constrain the assigned cell to be equal to a constant.
+    pub fn constrain_constant(
+        &mut self,
+        cell: AssignedCell<F, F>,
+        constant: F,
+    ) -> Result<(), Error> {
+        self.region.constrain_constant(cell.cell(), constant)
+    }
+
     pub fn get_fixed(&self, _row_index: usize, _column_index: usize, _rotation: Rotation) -> F {
         unimplemented!("fixed column");
     }
@@ -172,6 +181,7 @@ impl<F: FieldExt> StoredExpression<F> {
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub(crate) enum CellType {
     Storage,
+    StoragePermutation,
     Lookup(Table),
 }
 
@@ -232,6 +242,13 @@ impl<F: FieldExt> CellManager<F> {
             }
         }
 
+        // Mark columns used for copy constraints
+        for _ in 0..N_COPY_COLUMNS {
+            meta.enable_equality(advices[column_idx]);
+            columns[column_idx].cell_type = CellType::StoragePermutation;
+            column_idx += 1;
+        }
+
         Self {
             width,
             height,
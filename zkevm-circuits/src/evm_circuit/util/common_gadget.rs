@@ -186,23 +186,15 @@ impl<F: Field> RestoreContextGadget<F> {
         block: &Block<F>,
         call: &Call,
         step: &ExecStep,
+        rw_offset: usize,
     ) -> Result<(), Error> {
         let [caller_id, caller_is_root, caller_is_create, caller_code_hash, caller_program_counter, caller_stack_pointer, caller_gas_left, caller_memory_word_size, caller_reversible_write_counter] =
             if call.is_root {
                 [U256::zero(); 9]
             } else {
-                [
-                    step.rw_indices[1],
-                    step.rw_indices[2],
-                    step.rw_indices[3],
-                    step.rw_indices[4],
-                    step.rw_indices[5],
-                    step.rw_indices[6],
-                    step.rw_indices[7],
-                    step.rw_indices[8],
-                    step.rw_indices[9],
-                ]
-                .map(|idx| block.rws[idx].call_context_value())
+                [0, 1, 2, 3, 4, 5, 6, 7, 8]
+                    .map(|i| step.rw_indices[i + rw_offset])
+                    .map(|idx| block.rws[idx].call_context_value())
             };
 
         for (cell, value) in [
@@ -90,10 +90,11 @@ impl<F: Field> StepStateTransition<F> {
 /// write.
 #[derive(Clone, Debug)]
 pub(crate) struct ReversionInfo<F> {
-    /// Field [`CallContextFieldTag::RwCounterEndOfReversion`] read from call
-    /// context.
+    /// Field [`CallContextFieldTag::RwCounterEndOfReversion`] read from or
+    /// written to call context.
     rw_counter_end_of_reversion: Cell<F>,
-    /// Field [`CallContextFieldTag::IsPersistent`] read from call context.
+    /// Field [`CallContextFieldTag::IsPersistent`] read from or written to call
+    /// context.
     is_persistent: Cell<F>,
     /// Current cumulative reversible_write_counter.
     reversible_write_counter: Expression<F>,
@@ -240,11 +241,13 @@ pub(crate) struct ConstraintBuilder<'a, F> {
     execution_state: ExecutionState,
     constraints: Vec<(&'static str, Expression<F>)>,
     constraints_first_step: Vec<(&'static str, Expression<F>)>,
+    constraints_not_step_last: Vec<(&'static str, Expression<F>)>,
     rw_counter_offset: Expression<F>,
     program_counter_offset: usize,
     stack_pointer_offset: i32,
     log_id_offset: usize,
     in_next_step: bool,
+    in_not_step_last: bool,
     condition: Option<Expression<F>>,
     stored_expressions: Vec<StoredExpression<F>>,
 }
@@ -264,11 +267,13 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
             execution_state,
             constraints: Vec::new(),
             constraints_first_step: Vec::new(),
+            constraints_not_step_last: Vec::new(),
             rw_counter_offset: 0.expr(),
             program_counter_offset: 0,
             stack_pointer_offset: 0,
             log_id_offset: 0,
             in_next_step: false,
+            in_not_step_last: false,
             condition: None,
             stored_expressions: Vec::new(),
         }
@@ -278,21 +283,23 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
     pub(crate) fn build(
         self,
     ) -> (
+        Vec<(&'static str, Expression<F>)>,
         Vec<(&'static str, Expression<F>)>,
         Vec<(&'static str, Expression<F>)>,
         Vec<StoredExpression<F>>,
         usize,
     ) {
         let execution_state_selector = self.curr.execution_state_selector([self.execution_state]);
-        (
-            self.constraints
+        let select = |constraints: Vec<(&'static str, Expression<F>)>| -> Vec<_> {
+            constraints
                 .into_iter()
                 .map(|(name, constraint)| (name, execution_state_selector.clone() * constraint))
-                .collect(),
-            self.constraints_first_step
-                .into_iter()
-                .map(|(name, constraint)| (name, execution_state_selector.clone() * constraint))
-                .collect(),
+                .collect()
+        };
+        (
+            select(self.constraints),
+            select(self.constraints_first_step),
+            select(self.constraints_not_step_last),
             self.stored_expressions,
             self.curr.cell_manager.get_height(),
         )
@@ -360,6 +367,10 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
         self.query_cell_with_type(CellType::Storage)
     }
 
+    pub(crate) fn query_copy_cell(&mut self) -> Cell<F> {
+        self.query_cell_with_type(CellType::StoragePermutation)
+    }
+
     pub(crate) fn query_cell_with_type(&mut self, cell_type: CellType) -> Cell<F> {
         self.query_cells(cell_type, 1).first().unwrap().clone()
     }
@@ -629,6 +640,18 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
         );
     }
 
+    /// Add a Lookup::Rw of a `Rw::Start` padding row, without increasing the
+    /// rw_counter_offset.
+    pub(crate) fn rw_table_start_lookup(&mut self, counter: Expression<F>) {
+        self.rw_lookup_with_counter(
+            "Start",
+            counter,
+            false.expr(),
+            RwTableTag::Start,
+            [(); 8].map(|_| 0.expr()),
+        );
+    }
+
     /// Add a Lookup::Rw and increase the rw_counter_offset, useful in normal
     /// cases.
     fn rw_lookup(
@@ -930,11 +953,32 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
     }
 
     pub(crate) fn reversion_info(&mut self, call_id: Option<Expression<F>>) -> ReversionInfo<F> {
+        self.reversion_info_lookup(false.expr(), call_id)
+    }
+
+    /// Same as [`Self::reversion_info`], but writes the fields instead, which
+    /// is used by the steps that set up the context of a new call.
+    pub(crate) fn reversion_info_write(
+        &mut self,
+        call_id: Option<Expression<F>>,
+    ) -> ReversionInfo<F> {
+        self.reversion_info_lookup(true.expr(), call_id)
+    }
+
+    fn reversion_info_lookup(
+        &mut self,
+        is_write: Expression<F>,
+        call_id: Option<Expression<F>>,
+    ) -> ReversionInfo<F> {
         let [rw_counter_end_of_reversion, is_persistent] = [
             CallContextFieldTag::RwCounterEndOfReversion,
             CallContextFieldTag::IsPersistent,
         ]
-        .map(|field_tag| self.call_context(call_id.clone(), field_tag));
+        .map(|field_tag| {
+            let cell = self.query_cell();
+            self.call_context_lookup(is_write.clone(), call_id.clone(), field_tag, cell.expr());
+            cell
+        });
         ReversionInfo {
             rw_counter_end_of_reversion,
             is_persistent,
@@ -1150,6 +1194,17 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
         ret
     }
 
+    /// The constraints added in `constraint` are only enabled when the step
+    /// is not the last one, so that they can constrain the next step.  Lookups
+    /// are not supported, as they are enabled on every step.
+    pub(crate) fn not_step_last<R>(&mut self, constraint: impl FnOnce(&mut Self) -> R) -> R {
+        assert!(!self.in_not_step_last, "Already not in the last step");
+        self.in_not_step_last = true;
+        let ret = constraint(self);
+        self.in_not_step_last = false;
+        ret
+    }
+
     pub(crate) fn add_constraints(&mut self, constraints: Vec<(&'static str, Expression<F>)>) {
         for (name, constraint) in constraints {
             self.add_constraint(name, constraint);
@@ -1162,10 +1217,16 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
             None => constraint,
         };
 
-        let constraint = self.split_expression(name, constraint, MAX_DEGREE - IMPLICIT_DEGREE);
+        // The steps other than the last one are selected with an extra degree
+        let max_degree = MAX_DEGREE - IMPLICIT_DEGREE - self.in_not_step_last as usize;
+        let constraint = self.split_expression(name, constraint, max_degree);
 
         self.validate_degree(constraint.degree(), name);
-        self.constraints.push((name, constraint));
+        if self.in_not_step_last {
+            self.constraints_not_step_last.push((name, constraint));
+        } else {
+            self.constraints.push((name, constraint));
+        }
     }
 
     pub(crate) fn add_constraint_first_step(
@@ -1182,6 +1243,10 @@ impl<'a, F: Field> ConstraintBuilder<'a, F> {
     }
 
     pub(crate) fn add_lookup(&mut self, name: &'static str, lookup: Lookup<F>) {
+        debug_assert!(
+            !self.in_not_step_last,
+            "Lookups can't be restricted to the steps other than the last one"
+        );
         let lookup = match &self.condition {
             Some(condition) => lookup.conditional(condition.clone()),
             None => lookup,
@@ -17,9 +17,14 @@ use bus_mapping::{
     operation::{self, AccountField, CallContextField, TxLogField, TxReceiptField},
 };
 
+use eth_types::trie::{
+    decode_storage_value, keccak, storage_leaf_value, Account, PartialTrie, TrieError,
+};
 use eth_types::{evm_types::OpcodeId, ToWord};
-use eth_types::{Address, Field, ToLittleEndian, ToScalar, Word};
-use eth_types::{ToAddress, U256};
+use eth_types::{
+    Address, EIP1186ProofResponse, Field, ToBigEndian, ToLittleEndian, ToScalar, Word,
+};
+use eth_types::{ToAddress, H256, U256};
 use halo2_proofs::arithmetic::{BaseExt, FieldExt};
 use halo2_proofs::pairing::bn256::Fr;
 use itertools::Itertools;
@@ -41,6 +46,22 @@ pub struct Block<F> {
     /// Copy events for the EVM circuit's Copy Table, a mapping from (tx_id ||
     /// call_id || pc) to the corresponding copy event.
     pub copy_events: HashMap<(usize, usize, usize), CopyEvent>,
+    /// Number of rows of the EVM Circuit, up to which the execution steps are
+    /// padded with EndBlock steps
+    pub max_evm_rows: usize,
+    /// Number of rows of the RwTable, which is padded at the front with
+    /// `Rw::Start` rows
+    pub max_rws: usize,
+    /// Number of transactions of the TxTable, which is padded with
+    /// transactions with a zero caller address
+    pub max_txs: usize,
+    /// Number of rows of the Copy Circuit, up to which the copy steps are
+    /// padded with `CopyDataType::Padding` steps
+    pub max_copy_rows: usize,
+    /// Updates of the MPT done by the rws, with the state roots after each one
+    pub mpt_updates: MptUpdates,
+    /// Proofs with the nodes of the tries in the paths of the MPT updates
+    pub state_proofs: Vec<EIP1186ProofResponse>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -65,26 +86,23 @@ pub struct BlockContext {
 
 impl BlockContext {
     pub fn table_assignments<F: Field>(&self, randomness: F) -> Vec<[F; 3]> {
+        let number = self.number.to_scalar().unwrap();
         [
             vec![
                 [
                     F::from(BlockContextFieldTag::Coinbase as u64),
-                    F::zero(),
+                    number,
                     self.coinbase.to_scalar().unwrap(),
                 ],
                 [
                     F::from(BlockContextFieldTag::Timestamp as u64),
-                    F::zero(),
+                    number,
                     self.timestamp.to_scalar().unwrap(),
                 ],
-                [
-                    F::from(BlockContextFieldTag::Number as u64),
-                    F::zero(),
-                    self.number.to_scalar().unwrap(),
-                ],
+                [F::from(BlockContextFieldTag::Number as u64), number, number],
                 [
                     F::from(BlockContextFieldTag::Difficulty as u64),
-                    F::zero(),
+                    number,
                     RandomLinearCombination::random_linear_combine(
                         self.difficulty.to_le_bytes(),
                         randomness,
@@ -92,12 +110,12 @@ impl BlockContext {
                 ],
                 [
                     F::from(BlockContextFieldTag::GasLimit as u64),
-                    F::zero(),
+                    number,
                     F::from(self.gas_limit),
                 ],
                 [
                     F::from(BlockContextFieldTag::BaseFee as u64),
-                    F::zero(),
+                    number,
                     RandomLinearCombination::random_linear_combine(
                         self.base_fee.to_le_bytes(),
                         randomness,
@@ -105,7 +123,7 @@ impl BlockContext {
                 ],
                 [
                     F::from(BlockContextFieldTag::ChainId as u64),
-                    F::zero(),
+                    number,
                     RandomLinearCombination::random_linear_combine(
                         self.chain_id.to_le_bytes(),
                         randomness,
@@ -155,6 +173,8 @@ pub struct Transaction {
     pub call_data_length: usize,
     /// The gas cost for transaction call data
     pub call_data_gas_cost: u64,
+    /// The number of the block that includes the transaction
+    pub block_number: u64,
     /// The calls made in the transaction
     pub calls: Vec<Call>,
     /// The steps executioned in the transaction
@@ -225,6 +245,12 @@ impl Transaction {
                     F::zero(),
                     F::from(self.call_data_gas_cost),
                 ],
+                [
+                    F::from(self.id as u64),
+                    F::from(TxContextFieldTag::BlockNumber as u64),
+                    F::zero(),
+                    F::from(self.block_number),
+                ],
             ],
             self.call_data
                 .iter()
@@ -379,6 +405,283 @@ impl std::ops::Index<(RwTableTag, usize)> for RwMap {
     }
 }
 
+impl RwMap {
+    /// Rows of all the tags, sorted in the order used by the State Circuit,
+    /// i.e. by (tag, id, address, field_tag, storage_key, rw_counter).
+    pub fn table_assignments(&self) -> Vec<Rw> {
+        let mut rows: Vec<_> = self.0.values().flatten().cloned().collect();
+        rows.sort_by_key(|row| {
+            (
+                row.tag() as u64,
+                row.id().unwrap_or_default(),
+                row.address().unwrap_or_default(),
+                row.field_tag().unwrap_or_default(),
+                row.storage_key().unwrap_or_default(),
+                row.rw_counter(),
+            )
+        });
+        rows
+    }
+}
+
+/// Update of an account field or a storage slot in the MPT, covering a group of
+/// `Rw::Account` or `Rw::AccountStorage` rows that share the same key in the
+/// State Circuit.
+#[derive(Debug, Clone, Copy)]
+pub struct MptUpdate {
+    /// First row of the access group
+    first: Rw,
+    /// Last row of the access group
+    last: Rw,
+    /// State root before the update
+    old_root: Word,
+    /// State root after the update
+    new_root: Word,
+}
+
+impl MptUpdate {
+    /// State root before the update
+    pub fn old_root(&self) -> Word {
+        self.old_root
+    }
+
+    /// State root after the update
+    pub fn new_root(&self) -> Word {
+        self.new_root
+    }
+
+    /// Address of the updated account
+    pub fn address(&self) -> Address {
+        self.first.address().unwrap_or_default()
+    }
+
+    /// Key of the updated storage slot, or `None` for an account field update
+    pub fn storage_key(&self) -> Option<Word> {
+        self.first.storage_key()
+    }
+
+    /// Updated account field, or `None` for a storage update
+    pub fn field_tag(&self) -> Option<AccountFieldTag> {
+        match self.first {
+            Rw::Account { field_tag, .. } => Some(field_tag),
+            _ => None,
+        }
+    }
+
+    /// Values of the account field or storage slot before and after the update
+    pub fn value_pair(&self) -> (Word, Word) {
+        match (self.first, self.last) {
+            (Rw::Account { value_prev, .. }, Rw::Account { value, .. })
+            | (Rw::AccountStorage { value_prev, .. }, Rw::AccountStorage { value, .. }) => {
+                (value_prev, value)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Apply the update to the state trie with root `root`, returning the root
+    /// after it.  Accounts are only in the trie when they are not empty, and
+    /// storage slots when their value is not zero.
+    pub fn apply(&self, trie: &mut PartialTrie, root: H256) -> Result<H256, TrieError> {
+        let (old_value, new_value) = self.value_pair();
+        let account_key = keccak(self.address().as_bytes());
+        let old_account = match trie.get(root, account_key)? {
+            Some(leaf) => Account::decode(&leaf).ok_or(TrieError::InvalidNode(root))?,
+            None => Account::default(),
+        };
+        let mut account = old_account.clone();
+        match (self.storage_key(), self.field_tag()) {
+            (Some(storage_key), _) => {
+                let key = keccak(&storage_key.to_be_bytes());
+                let storage_root = account.storage_root;
+                let value = match trie.get(storage_root, key)? {
+                    Some(leaf) => {
+                        decode_storage_value(&leaf).ok_or(TrieError::InvalidNode(storage_root))?
+                    }
+                    None => U256::zero(),
+                };
+                if value != old_value {
+                    return Err(TrieError::ValueMismatch);
+                }
+                // Only an account with code can write its storage, which is then
+                // not empty.
+                if old_account.is_empty() && new_value != old_value {
+                    return Err(TrieError::Unsupported("storage update of an empty account"));
+                }
+                account.storage_root =
+                    trie.set(storage_root, key, storage_leaf_value(new_value))?;
+            }
+            (None, Some(field_tag)) => {
+                let value = match field_tag {
+                    AccountFieldTag::Nonce => account.nonce,
+                    AccountFieldTag::Balance => account.balance,
+                    AccountFieldTag::CodeHash => account.code_hash.to_word(),
+                };
+                if value != old_value {
+                    return Err(TrieError::ValueMismatch);
+                }
+                match field_tag {
+                    AccountFieldTag::Nonce => account.nonce = new_value,
+                    AccountFieldTag::Balance => account.balance = new_value,
+                    AccountFieldTag::CodeHash => {
+                        account.code_hash = H256::from(new_value.to_be_bytes())
+                    }
+                }
+            }
+            (None, None) => unreachable!("update of an account field or a storage slot"),
+        }
+        if account == old_account {
+            return Ok(root);
+        }
+        trie.set(
+            root,
+            account_key,
+            (!account.is_empty()).then(|| account.encode()),
+        )
+    }
+
+    /// Row of the MptTable for this update, in the column order (address,
+    /// storage_key, field_tag, old_root, new_root, old_value, new_value).
+    pub fn table_assignment<F: Field>(&self, randomness: F) -> [F; 7] {
+        [
+            self.first
+                .address()
+                .unwrap_or_default()
+                .to_scalar()
+                .unwrap(),
+            RandomLinearCombination::random_linear_combine(
+                self.first.storage_key().unwrap_or_default().to_le_bytes(),
+                randomness,
+            ),
+            F::from(self.first.field_tag().unwrap_or_default() as u64),
+            RandomLinearCombination::random_linear_combine(self.old_root.to_le_bytes(), randomness),
+            RandomLinearCombination::random_linear_combine(self.new_root.to_le_bytes(), randomness),
+            self.first
+                .value_prev_assignment(randomness)
+                .unwrap_or_default(),
+            self.last.value_assignment(randomness),
+        ]
+    }
+}
+
+/// Updates to the MPT done in a block, in the order in which the State Circuit
+/// sees them.
+#[derive(Debug, Default, Clone)]
+pub struct MptUpdates {
+    /// State root before the block
+    old_root: Word,
+    /// Updates, each one starting from the `new_root` of the previous one
+    updates: Vec<MptUpdate>,
+}
+
+/// First and last rows of the access groups of `Rw::Account` and
+/// `Rw::AccountStorage` rows in `rows`
+fn account_access_groups(rows: &[Rw]) -> Vec<(Rw, Rw)> {
+    let mut groups: Vec<(Rw, Rw)> = vec![];
+    for row in rows
+        .iter()
+        .filter(|row| matches!(row.tag(), RwTableTag::Account | RwTableTag::AccountStorage))
+    {
+        match groups.last_mut() {
+            Some((first, last)) if first.is_same_access_group(row) => *last = *row,
+            _ => groups.push((*row, *row)),
+        }
+    }
+    groups
+}
+
+impl MptUpdates {
+    /// Build the MPT updates from rows sorted in the order of the State Circuit
+    /// (see [`RwMap::table_assignments`]), applying them in order to the state
+    /// trie with root `old_root`, whose nodes in the paths of the updated keys
+    /// are in `proofs`.
+    pub fn new(
+        rows: &[Rw],
+        old_root: Word,
+        proofs: &[EIP1186ProofResponse],
+    ) -> Result<Self, TrieError> {
+        let mut trie = PartialTrie::default();
+        trie.insert_proofs(proofs);
+        let mut root = H256::from(old_root.to_be_bytes());
+        let updates = account_access_groups(rows)
+            .into_iter()
+            .map(|(first, last)| {
+                let mut update = MptUpdate {
+                    first,
+                    last,
+                    old_root: root.to_word(),
+                    new_root: Word::zero(),
+                };
+                root = update.apply(&mut trie, root)?;
+                update.new_root = root.to_word();
+                Ok(update)
+            })
+            .collect::<Result<_, TrieError>>()?;
+        Ok(Self { old_root, updates })
+    }
+
+    /// Build the MPT updates from rows sorted in the order of the State Circuit
+    /// (see [`RwMap::table_assignments`]), with mock state roots (a counter
+    /// starting at 0), for tests of rows not coming from a block.
+    #[cfg(any(feature = "test", test))]
+    pub fn mock_from(rows: &[Rw]) -> Self {
+        let updates = account_access_groups(rows)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (first, last))| MptUpdate {
+                first,
+                last,
+                old_root: Word::from(i),
+                new_root: Word::from(i + 1),
+            })
+            .collect();
+        Self {
+            old_root: Word::zero(),
+            updates,
+        }
+    }
+
+    /// State root before the block
+    pub fn old_root(&self) -> Word {
+        self.old_root
+    }
+
+    /// State root after the block, which is the one before it when there are
+    /// no updates
+    pub fn new_root(&self) -> Word {
+        self.updates
+            .last()
+            .map_or(self.old_root, |update| update.new_root)
+    }
+
+    /// Replace the state roots with the ones obtained by applying the updates
+    /// to the trie with root `old_root`.  `new_roots` must contain the root
+    /// after each update.
+    pub fn set_roots(&mut self, old_root: Word, new_roots: &[Word]) {
+        assert_eq!(self.updates.len(), new_roots.len());
+        self.old_root = old_root;
+        let old_roots = iter::once(old_root).chain(new_roots.iter().copied());
+        for ((update, old_root), new_root) in self.updates.iter_mut().zip(old_roots).zip(new_roots)
+        {
+            update.old_root = old_root;
+            update.new_root = *new_root;
+        }
+    }
+
+    /// Iterator over the updates
+    pub fn iter(&self) -> impl Iterator<Item = &MptUpdate> {
+        self.updates.iter()
+    }
+
+    /// Rows of the MptTable, one for each update
+    pub fn table_assignments<F: Field>(&self, randomness: F) -> Vec<[F; 7]> {
+        self.updates
+            .iter()
+            .map(|update| update.table_assignment(randomness))
+            .collect()
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub enum Rw {
     Start {
@@ -767,6 +1070,17 @@ impl Rw {
         }
     }
 
+    /// Returns true if both rows have the same (tag, id, address, field_tag,
+    /// storage_key), i.e. they belong to the same access group in the State
+    /// Circuit.
+    pub fn is_same_access_group(&self, other: &Self) -> bool {
+        self.tag() == other.tag()
+            && self.id() == other.id()
+            && self.address() == other.address()
+            && self.field_tag() == other.field_tag()
+            && self.storage_key() == other.storage_key()
+    }
+
     pub fn value_assignment<F: Field>(&self, randomness: F) -> F {
         match self {
             Self::Start { .. } => F::zero(),
@@ -1214,7 +1528,7 @@ impl From<&circuit_input_builder::ExecStep> for ExecutionState {
                     OpcodeId::CALLDATACOPY => ExecutionState::CALLDATACOPY,
                     OpcodeId::CHAINID => ExecutionState::CHAINID,
                     OpcodeId::ISZERO => ExecutionState::ISZERO,
-                    OpcodeId::CALL => ExecutionState::CALL,
+                    OpcodeId::CALL | OpcodeId::STATICCALL => ExecutionState::CALL,
                     OpcodeId::ORIGIN => ExecutionState::ORIGIN,
                     OpcodeId::CODECOPY => ExecutionState::CODECOPY,
                     OpcodeId::CALLDATALOAD => ExecutionState::CALLDATALOAD,
@@ -1236,7 +1550,6 @@ impl From<&circuit_input_builder::ExecStep> for ExecutionState {
                     OpcodeId::CALLCODE => dummy!(ExecutionState::CALLCODE),
                     OpcodeId::DELEGATECALL => dummy!(ExecutionState::DELEGATECALL),
                     OpcodeId::CREATE2 => dummy!(ExecutionState::CREATE2),
-                    OpcodeId::STATICCALL => dummy!(ExecutionState::STATICCALL),
                     OpcodeId::SELFDESTRUCT => dummy!(ExecutionState::SELFDESTRUCT),
                     _ => unimplemented!("unimplemented opcode {:?}", op),
                 }
@@ -1294,7 +1607,12 @@ fn step_convert(step: &circuit_input_builder::ExecStep) -> ExecStep {
     }
 }
 
-fn tx_convert(tx: &circuit_input_builder::Transaction, id: usize, is_last_tx: bool) -> Transaction {
+fn tx_convert(
+    tx: &circuit_input_builder::Transaction,
+    id: usize,
+    block_number: u64,
+    is_last_tx: bool,
+) -> Transaction {
     Transaction {
         id,
         nonce: tx.nonce,
@@ -1310,6 +1628,7 @@ fn tx_convert(tx: &circuit_input_builder::Transaction, id: usize, is_last_tx: bo
             .input
             .iter()
             .fold(0, |acc, byte| acc + if *byte == 0 { 4 } else { 16 }),
+        block_number,
         calls: tx
             .calls()
             .iter()
@@ -1355,19 +1674,38 @@ fn tx_convert(tx: &circuit_input_builder::Transaction, id: usize, is_last_tx: bo
     }
 }
 
+/// Convert the circuit inputs of `block` into the witness of the circuits.
+/// Fail if the updates of the rws can't be applied to the tries in the state
+/// proofs of the block.
 pub fn block_convert(
     block: &circuit_input_builder::Block,
     code_db: &bus_mapping::state_db::CodeDB,
-) -> Block<Fr> {
-    Block {
+) -> Result<Block<Fr>, TrieError> {
+    let rws = RwMap::from(&block.container);
+    // One more row than the number of rws, so that the RwTable has at least one
+    // Rw::Start padding row.
+    let max_rws = rws.0.values().map(|rws| rws.len()).sum::<usize>() + 1;
+    let mpt_updates = MptUpdates::new(
+        &rws.table_assignments(),
+        block.prev_state_root,
+        &block.state_proofs,
+    )?;
+    Ok(Block {
         randomness: Fr::rand(),
         context: block.into(),
-        rws: RwMap::from(&block.container),
+        rws,
         txs: block
             .txs()
             .iter()
             .enumerate()
-            .map(|(idx, tx)| tx_convert(tx, idx + 1, idx + 1 == block.txs().len()))
+            .map(|(idx, tx)| {
+                tx_convert(
+                    tx,
+                    idx + 1,
+                    block.number.as_u64(),
+                    idx + 1 == block.txs().len(),
+                )
+            })
             .collect(),
         bytecodes: block
             .txs()
@@ -1394,5 +1732,11 @@ pub fn block_convert(
                 )
             })
             .collect(),
-    }
+        max_evm_rows: 0,
+        max_rws,
+        max_txs: block.txs().len(),
+        max_copy_rows: 0,
+        mpt_updates,
+        state_proofs: block.state_proofs.clone(),
+    })
 }
@@ -0,0 +1,1066 @@
+//! The Keccak circuit implementation.
+//!
+//! The circuit proves the rows of the [`KeccakTable`] for a list of variable
+//! length inputs.  The Keccak-f state is laid out in bits: each of the 25
+//! lanes is a column and the 64 bits of a lane span 64 consecutive rows, so
+//! that a row contains the bit `z` of every lane.  We call these 64 rows a
+//! segment.  Every block of [`RATE`] bytes absorbed by the sponge takes
+//! `NUM_ROUNDS + 1` segments:
+//! - The first segment contains the state returned by the previous block and
+//!   absorbs the padded input block into it, or into the zero state when the
+//!   block is the first one of its input.  The first [`RATE`] rows of the block
+//!   also lay out the padded input bytes, from which the input RLC and length
+//!   are accumulated.
+//! - Each of the following segments applies a round of the permutation to its
+//!   state, giving the state of the next segment.  The segment following the
+//!   last round is the first segment of the next block.
+//!
+//! The last row of a block is a row of the [`KeccakTable`], enabled when the
+//! block is the final one of its input.  Any number of blocks can be chained
+//! for a single input.
+
+use crate::{
+    evm_circuit::util::{constraint_builder::BaseConstraintBuilder, not, select},
+    table::KeccakTable,
+    util::{power_of_randomness_from_instance, Expr},
+};
+use eth_types::Field;
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, VirtualCells},
+    poly::Rotation,
+};
+use itertools::Itertools;
+use keccak256::{
+    common::{
+        State, LANE_SIZE, NEXT_INPUTS_LANES, PERMUTATION, ROTATION_CONSTANTS, ROUND_CONSTANTS,
+    },
+    plain::KeccakF,
+};
+use log::error;
+use std::{collections::BTreeMap, marker::PhantomData};
+
+const NUM_ROUNDS: usize = PERMUTATION;
+const NUM_LANES: usize = 25;
+const NUM_BITS_PER_LANE: usize = LANE_SIZE as usize;
+const NUM_BYTES_PER_LANE: usize = NUM_BITS_PER_LANE / 8;
+const NUM_OUTPUT_LANES: usize = 4;
+/// Number of bytes absorbed per block
+pub const RATE: usize = NEXT_INPUTS_LANES * NUM_BYTES_PER_LANE;
+/// Number of rows used by each absorbed block
+pub const ROWS_PER_BLOCK: usize = (NUM_ROUNDS + 1) * NUM_BITS_PER_LANE;
+const MAX_DEGREE: usize = 9;
+
+/// Witness of a block absorbed by the sponge
+#[derive(Clone, Debug)]
+pub(crate) struct KeccakBlock {
+    /// Whether the block is the first one of its input
+    pub(crate) is_first: bool,
+    /// Whether the block is the last one of its input
+    pub(crate) is_final: bool,
+    /// Padded input bytes of the block
+    pub(crate) bytes: Vec<u8>,
+    /// Number of input bytes absorbed by the previous blocks of the input
+    pub(crate) num_bytes_before: usize,
+    /// Length of the input
+    pub(crate) input_len: usize,
+    /// The states in the segments of the block, followed by the state
+    /// returned by the block.  The first one is the state returned by the
+    /// previous block.
+    pub(crate) states: Vec<State>,
+}
+
+/// Returns the witness of the blocks absorbing `inputs`, followed by blocks
+/// for empty inputs up to `num_blocks`.
+pub(crate) fn keccak_blocks<'a>(
+    inputs: impl IntoIterator<Item = &'a [u8]>,
+    num_blocks: usize,
+) -> Result<Vec<KeccakBlock>, Error> {
+    let inputs = inputs.into_iter().collect::<Vec<_>>();
+    let num_blocks_required = inputs
+        .iter()
+        .map(|input| input.len() / RATE + 1)
+        .sum::<usize>();
+    if num_blocks_required > num_blocks {
+        error!(
+            "keccak circuit needs {} blocks but only {} are available",
+            num_blocks_required, num_blocks
+        );
+        return Err(Error::Synthesis);
+    }
+    let padding_inputs = num_blocks - num_blocks_required;
+
+    let mut blocks = Vec::with_capacity(num_blocks);
+    let mut state: State = Default::default();
+    for input in inputs
+        .into_iter()
+        .chain(std::iter::repeat(&[][..]).take(padding_inputs))
+    {
+        let mut padded = input.to_vec();
+        padded.push(0x01);
+        padded.resize((padded.len() + RATE - 1) / RATE * RATE, 0);
+        *padded.last_mut().unwrap() |= 0x80;
+
+        let num_input_blocks = padded.len() / RATE;
+        for (idx, bytes) in padded.chunks(RATE).enumerate() {
+            let mut states = vec![state];
+            if idx == 0 {
+                state = Default::default();
+            }
+            for (i, lane) in bytes.chunks(NUM_BYTES_PER_LANE).enumerate() {
+                state[i % 5][i / 5] ^= u64::from_le_bytes(lane.try_into().unwrap());
+            }
+            states.push(state);
+            for round_constant in ROUND_CONSTANTS.iter() {
+                let rotated = KeccakF::pi(KeccakF::rho(KeccakF::theta(state)));
+                state = KeccakF::iota(KeccakF::xi(rotated), *round_constant);
+                states.push(state);
+            }
+            blocks.push(KeccakBlock {
+                is_first: idx == 0,
+                is_final: idx == num_input_blocks - 1,
+                bytes: bytes.to_vec(),
+                num_bytes_before: idx * RATE,
+                input_len: input.len(),
+                states,
+            });
+        }
+    }
+    Ok(blocks)
+}
+
+/// The bit `z` of the lane `x + 5 * y` of `state`
+fn state_bit(state: &State, lane: usize, z: usize) -> bool {
+    (state[lane % 5][lane / 5] >> z) & 1 == 1
+}
+
+/// Returns the bit `z - shift` (modulo the lane size) of the lane in `column`,
+/// for a row holding the bit `z` of the lane.
+fn query_rotated<F: Field>(
+    meta: &mut VirtualCells<F>,
+    wrap: &BTreeMap<usize, Column<Fixed>>,
+    column: Column<Advice>,
+    shift: usize,
+) -> Expression<F> {
+    if shift == 0 {
+        return meta.query_advice(column, Rotation::cur());
+    }
+    select::expr(
+        meta.query_fixed(wrap[&shift], Rotation::cur()),
+        meta.query_advice(column, Rotation((NUM_BITS_PER_LANE - shift) as i32)),
+        meta.query_advice(column, Rotation(-(shift as i32))),
+    )
+}
+
+fn xor_expr<F: Field>(a: Expression<F>, b: Expression<F>) -> Expression<F> {
+    a.clone() + b.clone() - 2.expr() * a * b
+}
+
+/// Returns the expression that is 1 when `sum`, an integer in `0..=max`, is
+/// odd and 0 when it is even.
+fn parity_expr<F: Field>(sum: Expression<F>, max: u64) -> Expression<F> {
+    (1..=max).step_by(2).fold(0.expr(), |acc, k| {
+        let (numerator, denominator) = (0..=max).filter(|j| *j != k).fold(
+            (1.expr(), F::one()),
+            |(numerator, denominator), j| {
+                (
+                    numerator * (sum.clone() - Expression::Constant(F::from(j))),
+                    denominator * (F::from(k) - F::from(j)),
+                )
+            },
+        );
+        acc + numerator * Expression::Constant(denominator.invert().unwrap())
+    })
+}
+
+/// Config for KeccakCircuit
+#[derive(Clone, Debug)]
+pub struct KeccakConfig<F> {
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    q_block_first: Column<Fixed>,
+    q_absorb: Column<Fixed>,
+    q_round: Column<Fixed>,
+    q_squeeze: Column<Fixed>,
+    q_byte: Column<Fixed>,
+    q_byte_last: Column<Fixed>,
+    q_table: Column<Fixed>,
+    round_constant: Column<Fixed>,
+    bit_weight: Column<Fixed>,
+    q_byte_first_bit: Column<Fixed>,
+    // For each rotation `shift` used by the permutation, 1 in the rows of the
+    // bits `z < shift`, where the rotated bit wraps around the lane.
+    wrap: BTreeMap<usize, Column<Fixed>>,
+    state: [Column<Advice>; NUM_LANES],
+    c: [Column<Advice>; 5],
+    theta: [Column<Advice>; NUM_LANES],
+    absorb: [Column<Advice>; NEXT_INPUTS_LANES],
+    byte_bits: [Column<Advice>; 8],
+    is_pad: Column<Advice>,
+    data_rlc: Column<Advice>,
+    length: Column<Advice>,
+    is_first_block: Column<Advice>,
+    is_final: Column<Advice>,
+    output_rlc: [Column<Advice>; NUM_OUTPUT_LANES],
+    keccak_table: KeccakTable,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> KeccakConfig<F> {
+    /// Configure the Keccak circuit, constraining the rows of `keccak_table`.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        power_of_randomness: [Expression<F>; 31],
+        keccak_table: KeccakTable,
+    ) -> Self {
+        let q_enable = meta.fixed_column();
+        let q_first = meta.fixed_column();
+        let q_block_first = meta.fixed_column();
+        let q_absorb = meta.fixed_column();
+        let q_round = meta.fixed_column();
+        let q_squeeze = meta.fixed_column();
+        let q_byte = meta.fixed_column();
+        let q_byte_last = meta.fixed_column();
+        let q_table = meta.fixed_column();
+        let round_constant = meta.fixed_column();
+        let bit_weight = meta.fixed_column();
+        let q_byte_first_bit = meta.fixed_column();
+        let wrap: BTreeMap<usize, Column<Fixed>> = ROTATION_CONSTANTS
+            .iter()
+            .flatten()
+            .filter(|shift| **shift != 0)
+            .map(|shift| (*shift as usize, meta.fixed_column()))
+            .collect();
+
+        let state = [(); NUM_LANES].map(|_| meta.advice_column());
+        let c = [(); 5].map(|_| meta.advice_column());
+        let theta = [(); NUM_LANES].map(|_| meta.advice_column());
+        let absorb = [(); NEXT_INPUTS_LANES].map(|_| meta.advice_column());
+        let byte_bits = [(); 8].map(|_| meta.advice_column());
+        let is_pad = meta.advice_column();
+        let data_rlc = meta.advice_column();
+        let length = meta.advice_column();
+        let is_first_block = meta.advice_column();
+        let is_final = meta.advice_column();
+        let output_rlc = [(); NUM_OUTPUT_LANES].map(|_| meta.advice_column());
+
+        // The input bits absorbed in the state are copied from the input bytes.
+        for column in absorb.iter().chain(byte_bits.iter()) {
+            meta.enable_equality(*column);
+        }
+
+        let randomness = power_of_randomness[0].clone();
+
+        meta.create_gate("block flags", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let q_first = meta.query_fixed(q_first, Rotation::cur());
+            let q_block_first = meta.query_fixed(q_block_first, Rotation::cur());
+            let is_first_block_cur = meta.query_advice(is_first_block, Rotation::cur());
+            let is_first_block_prev = meta.query_advice(is_first_block, Rotation::prev());
+            let is_final_cur = meta.query_advice(is_final, Rotation::cur());
+            let is_final_prev = meta.query_advice(is_final, Rotation::prev());
+
+            cb.condition(q_block_first.clone(), |cb| {
+                cb.require_equal(
+                    "a block is the first of its input when it follows a final block",
+                    is_first_block_cur.clone(),
+                    select::expr(q_first, 1.expr(), is_final_prev.clone()),
+                );
+            });
+            cb.condition(not::expr(q_block_first), |cb| {
+                cb.require_equal(
+                    "is_first_block is the same in all the rows of a block",
+                    is_first_block_cur,
+                    is_first_block_prev,
+                );
+                cb.require_equal(
+                    "is_final is the same in all the rows of a block",
+                    is_final_cur,
+                    is_final_prev,
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("absorb", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let is_first_block = meta.query_advice(is_first_block, Rotation::cur());
+            for (lane, column) in state.iter().enumerate() {
+                // The first block of an input starts from the zero state.
+                let state_cur =
+                    meta.query_advice(*column, Rotation::cur()) * not::expr(is_first_block.clone());
+                let state_next = meta.query_advice(*column, Rotation(NUM_BITS_PER_LANE as i32));
+                let absorbed = match absorb.get(lane) {
+                    Some(input) => xor_expr(state_cur, meta.query_advice(*input, Rotation::cur())),
+                    None => state_cur,
+                };
+                cb.require_equal("absorb the input block", state_next, absorbed);
+            }
+
+            cb.gate(meta.query_fixed(q_absorb, Rotation::cur()))
+        });
+
+        meta.create_gate("round", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let state_cur = state.map(|column| meta.query_advice(column, Rotation::cur()));
+
+            // theta
+            for (x, column) in c.iter().enumerate() {
+                let column_sum = (0..5).fold(0.expr(), |acc, y| acc + state_cur[x + 5 * y].clone());
+                cb.require_equal(
+                    "c is the parity of a column of the state",
+                    meta.query_advice(*column, Rotation::cur()),
+                    parity_expr(column_sum, 5),
+                );
+            }
+            for (x, y) in (0..5).cartesian_product(0..5) {
+                let c_left = meta.query_advice(c[(x + 4) % 5], Rotation::cur());
+                let c_right = query_rotated(meta, &wrap, c[(x + 1) % 5], 1);
+                cb.require_equal(
+                    "theta",
+                    meta.query_advice(theta[x + 5 * y], Rotation::cur()),
+                    xor_expr(xor_expr(state_cur[x + 5 * y].clone(), c_left), c_right),
+                );
+            }
+
+            // rho and pi
+            let mut b = vec![vec![0.expr(); 5]; 5];
+            for (x, y) in (0..5).cartesian_product(0..5) {
+                b[y][(2 * x + 3 * y) % 5] = query_rotated(
+                    meta,
+                    &wrap,
+                    theta[x + 5 * y],
+                    ROTATION_CONSTANTS[x][y] as usize,
+                );
+            }
+
+            // chi and iota
+            let round_constant = meta.query_fixed(round_constant, Rotation::cur());
+            for (x, y) in (0..5).cartesian_product(0..5) {
+                let mut state_next = xor_expr(
+                    b[x][y].clone(),
+                    not::expr(b[(x + 1) % 5][y].clone()) * b[(x + 2) % 5][y].clone(),
+                );
+                if x == 0 && y == 0 {
+                    state_next = xor_expr(state_next, round_constant.clone());
+                }
+                cb.require_equal(
+                    "chi and iota",
+                    meta.query_advice(state[x + 5 * y], Rotation(NUM_BITS_PER_LANE as i32)),
+                    state_next,
+                );
+            }
+
+            cb.gate(meta.query_fixed(q_round, Rotation::cur()))
+        });
+
+        meta.create_gate("squeeze", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            // The bits of a lane are little endian, so the RLC of its bytes is
+            // accumulated with the bits weighted by their position in the byte.
+            let is_lane_first_bit = meta.query_fixed(wrap[&1], Rotation::cur());
+            let multiplier = select::expr(
+                meta.query_fixed(q_byte_first_bit, Rotation::cur()),
+                randomness.clone(),
+                1.expr(),
+            );
+            let bit_weight = meta.query_fixed(bit_weight, Rotation::cur());
+            for (lane, column) in output_rlc.iter().enumerate() {
+                let output_bit = meta.query_advice(state[lane], Rotation(NUM_BITS_PER_LANE as i32));
+                cb.require_equal(
+                    "accumulate the RLC of the output bytes of a lane",
+                    meta.query_advice(*column, Rotation::cur()),
+                    select::expr(
+                        is_lane_first_bit.clone(),
+                        0.expr(),
+                        meta.query_advice(*column, Rotation::prev()) * multiplier.clone(),
+                    ) + output_bit * bit_weight.clone(),
+                );
+            }
+
+            cb.gate(meta.query_fixed(q_squeeze, Rotation::cur()))
+        });
+
+        meta.create_gate("input bytes", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let q_block_first = meta.query_fixed(q_block_first, Rotation::cur());
+            let is_first_block = meta.query_advice(is_first_block, Rotation::cur());
+            // The previous byte of the first byte of a block is the last byte of
+            // the previous block, unless the block is the first of its input.
+            let mut query_prev = |column| {
+                select::expr(
+                    q_block_first.clone(),
+                    not::expr(is_first_block.clone())
+                        * meta
+                            .query_advice(column, Rotation(-((ROWS_PER_BLOCK - RATE + 1) as i32))),
+                    meta.query_advice(column, Rotation::prev()),
+                )
+            };
+            let is_pad_prev = query_prev(is_pad);
+            let data_rlc_prev = query_prev(data_rlc);
+            let length_prev = query_prev(length);
+
+            let bits = byte_bits.map(|column| meta.query_advice(column, Rotation::cur()));
+            for bit in bits.iter() {
+                cb.require_boolean("byte bit is boolean", bit.clone());
+            }
+            let byte = bits
+                .iter()
+                .rev()
+                .fold(0.expr(), |acc, bit| acc * 2.expr() + bit.clone());
+
+            let is_pad = meta.query_advice(is_pad, Rotation::cur());
+            cb.require_boolean("is_pad is boolean", is_pad.clone());
+            let is_pad_start = is_pad.clone() - is_pad_prev;
+            cb.require_boolean(
+                "padding continues until the end of the input",
+                is_pad_start.clone(),
+            );
+            cb.condition(is_pad.clone(), |cb| {
+                cb.require_equal(
+                    "padding is 0x01, followed by zeros, then 0x80 in the last byte",
+                    byte.clone(),
+                    is_pad_start + meta.query_fixed(q_byte_last, Rotation::cur()) * 0x80.expr(),
+                );
+            });
+
+            cb.require_equal(
+                "length counts the input bytes",
+                meta.query_advice(length, Rotation::cur()),
+                length_prev + not::expr(is_pad.clone()),
+            );
+            cb.require_equal(
+                "data_rlc accumulates the input bytes",
+                meta.query_advice(data_rlc, Rotation::cur()),
+                select::expr(
+                    is_pad.clone(),
+                    data_rlc_prev.clone(),
+                    data_rlc_prev * randomness.clone() + byte,
+                ),
+            );
+
+            cb.condition(meta.query_fixed(q_byte_last, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "a block is final when it ends with padding",
+                    meta.query_advice(is_final, Rotation::cur()),
+                    is_pad,
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_byte, Rotation::cur()))
+        });
+
+        meta.create_gate("keccak table", |meta| {
+            let mut cb = BaseConstraintBuilder::new(MAX_DEGREE);
+
+            let q_table = meta.query_fixed(q_table, Rotation::cur());
+            let is_enabled = meta.query_advice(keccak_table.is_enabled, Rotation::cur());
+            cb.condition(not::expr(q_table.clone()), |cb| {
+                cb.require_zero(
+                    "the table row of a block is its last row",
+                    is_enabled.clone(),
+                );
+            });
+
+            let is_final = meta.query_advice(is_final, Rotation::cur());
+            let last_byte = Rotation(-((ROWS_PER_BLOCK - RATE) as i32));
+            let output_rlc = output_rlc.iter().fold(0.expr(), |acc, column| {
+                acc * power_of_randomness[NUM_BYTES_PER_LANE - 1].clone()
+                    + meta.query_advice(*column, Rotation::cur())
+            });
+            cb.condition(q_table, |cb| {
+                cb.require_equal(
+                    "the table row of a block is enabled when it is final",
+                    is_enabled,
+                    is_final.clone(),
+                );
+                cb.require_zero(
+                    "input_rlc is the RLC of the input bytes",
+                    is_final.clone()
+                        * (meta.query_advice(keccak_table.input_rlc, Rotation::cur())
+                            - meta.query_advice(data_rlc, last_byte)),
+                );
+                cb.require_zero(
+                    "input_len is the number of input bytes",
+                    is_final.clone()
+                        * (meta.query_advice(keccak_table.input_len, Rotation::cur())
+                            - meta.query_advice(length, last_byte)),
+                );
+                cb.require_zero(
+                    "output_rlc is the RLC of the output bytes",
+                    is_final
+                        * (meta.query_advice(keccak_table.output_rlc, Rotation::cur())
+                            - output_rlc),
+                );
+            });
+
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        Self {
+            q_enable,
+            q_first,
+            q_block_first,
+            q_absorb,
+            q_round,
+            q_squeeze,
+            q_byte,
+            q_byte_last,
+            q_table,
+            round_constant,
+            bit_weight,
+            q_byte_first_bit,
+            wrap,
+            state,
+            c,
+            theta,
+            absorb,
+            byte_bits,
+            is_pad,
+            data_rlc,
+            length,
+            is_first_block,
+            is_final,
+            output_rlc,
+            keccak_table,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Assign the Keccak circuit hashing `inputs`, using at most `size` rows.
+    /// The remaining blocks hash empty inputs.
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        size: usize,
+        inputs: &[Vec<u8>],
+        randomness: F,
+    ) -> Result<(), Error> {
+        let num_blocks = size.saturating_sub(NUM_BITS_PER_LANE) / ROWS_PER_BLOCK;
+        let blocks = keccak_blocks(inputs.iter().map(|input| input.as_slice()), num_blocks)?;
+        self.assign_blocks(layouter, &blocks, randomness)
+    }
+
+    pub(crate) fn assign_blocks(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        blocks: &[KeccakBlock],
+        randomness: F,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "keccak circuit",
+            |mut region| {
+                let mut data_rlc = F::zero();
+                let mut length = 0;
+                for (idx, block) in blocks.iter().enumerate() {
+                    if block.is_first {
+                        data_rlc = F::zero();
+                        length = 0;
+                    }
+                    (data_rlc, length) = self.assign_block(
+                        &mut region,
+                        idx * ROWS_PER_BLOCK,
+                        block,
+                        data_rlc,
+                        length,
+                        randomness,
+                    )?;
+                }
+                // The state returned by the last block, constrained by its last
+                // round.
+                if let Some(block) = blocks.last() {
+                    self.assign_state(
+                        &mut region,
+                        blocks.len() * ROWS_PER_BLOCK,
+                        block.states.last().unwrap(),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn assign_fixed(
+        &self,
+        region: &mut Region<'_, F>,
+        column: Column<Fixed>,
+        offset: usize,
+        value: F,
+    ) -> Result<(), Error> {
+        region.assign_fixed(|| format!("fixed {}", offset), column, offset, || Ok(value))?;
+        Ok(())
+    }
+
+    fn assign_advice(
+        &self,
+        region: &mut Region<'_, F>,
+        column: Column<Advice>,
+        offset: usize,
+        value: F,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        region.assign_advice(
+            || format!("advice {}", offset),
+            column,
+            offset,
+            || Ok(value),
+        )
+    }
+
+    fn assign_state(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        state: &State,
+    ) -> Result<(), Error> {
+        for z in 0..NUM_BITS_PER_LANE {
+            for (lane, column) in self.state.iter().enumerate() {
+                let bit = state_bit(state, lane, z);
+                self.assign_advice(region, *column, offset + z, F::from(bit as u64))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Assign a block at `offset`, returning the input RLC and length
+    /// accumulated up to the end of the block.
+    fn assign_block(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        block: &KeccakBlock,
+        mut data_rlc: F,
+        mut length: u64,
+        randomness: F,
+    ) -> Result<(F, u64), Error> {
+        // Fixed columns
+        for row in 0..ROWS_PER_BLOCK {
+            let segment = row / NUM_BITS_PER_LANE;
+            let z = row % NUM_BITS_PER_LANE;
+            let mut enabled = vec![self.q_enable];
+            if offset + row == 0 {
+                enabled.push(self.q_first);
+            }
+            if row == 0 {
+                enabled.push(self.q_block_first);
+            }
+            if segment == 0 {
+                enabled.push(self.q_absorb);
+            } else {
+                enabled.push(self.q_round);
+                if (ROUND_CONSTANTS[segment - 1] >> z) & 1 == 1 {
+                    enabled.push(self.round_constant);
+                }
+            }
+            if segment == NUM_ROUNDS {
+                enabled.push(self.q_squeeze);
+            }
+            if row < RATE {
+                enabled.push(self.q_byte);
+            }
+            if row == RATE - 1 {
+                enabled.push(self.q_byte_last);
+            }
+            if row == ROWS_PER_BLOCK - 1 {
+                enabled.push(self.q_table);
+            }
+            if z % 8 == 0 {
+                enabled.push(self.q_byte_first_bit);
+            }
+            enabled.extend(
+                self.wrap
+                    .iter()
+                    .filter(|(shift, _)| z < **shift)
+                    .map(|(_, column)| *column),
+            );
+            for column in enabled {
+                self.assign_fixed(region, column, offset + row, F::one())?;
+            }
+            self.assign_fixed(
+                region,
+                self.bit_weight,
+                offset + row,
+                F::from(1u64 << (z % 8)),
+            )?;
+        }
+
+        // Block flags
+        for row in 0..ROWS_PER_BLOCK {
+            for (column, value) in [
+                (self.is_first_block, block.is_first),
+                (self.is_final, block.is_final),
+            ] {
+                self.assign_advice(region, column, offset + row, F::from(value as u64))?;
+            }
+        }
+
+        // Input bytes
+        let mut byte_bit_cells = Vec::with_capacity(RATE);
+        for (row, byte) in block.bytes.iter().enumerate() {
+            let is_pad = block.num_bytes_before + row >= block.input_len;
+            if !is_pad {
+                data_rlc = data_rlc * randomness + F::from(*byte as u64);
+                length += 1;
+            }
+            let bit_cells = self
+                .byte_bits
+                .iter()
+                .enumerate()
+                .map(|(idx, column)| {
+                    let bit = (byte >> idx) & 1;
+                    self.assign_advice(region, *column, offset + row, F::from(bit as u64))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            byte_bit_cells.push(bit_cells);
+            for (column, value) in [
+                (self.is_pad, F::from(is_pad as u64)),
+                (self.data_rlc, data_rlc),
+                (self.length, F::from(length)),
+            ] {
+                self.assign_advice(region, column, offset + row, value)?;
+            }
+        }
+
+        // Absorb
+        self.assign_state(region, offset, &block.states[0])?;
+        for z in 0..NUM_BITS_PER_LANE {
+            for (lane, column) in self.absorb.iter().enumerate() {
+                let bit_cell = &byte_bit_cells[lane * NUM_BYTES_PER_LANE + z / 8][z % 8];
+                let cell = self.assign_advice(
+                    region,
+                    *column,
+                    offset + z,
+                    bit_cell.value().cloned().unwrap_or_default(),
+                )?;
+                region.constrain_equal(cell.cell(), bit_cell.cell())?;
+            }
+        }
+
+        // Rounds
+        for round in 0..NUM_ROUNDS {
+            let segment_offset = offset + (round + 1) * NUM_BITS_PER_LANE;
+            let state = &block.states[round + 1];
+            let theta = KeccakF::theta(*state);
+            self.assign_state(region, segment_offset, state)?;
+            for z in 0..NUM_BITS_PER_LANE {
+                for (x, column) in self.c.iter().enumerate() {
+                    let parity = (0..5).fold(false, |acc, y| acc ^ state_bit(state, x + 5 * y, z));
+                    self.assign_advice(
+                        region,
+                        *column,
+                        segment_offset + z,
+                        F::from(parity as u64),
+                    )?;
+                }
+                for (lane, column) in self.theta.iter().enumerate() {
+                    let bit = state_bit(&theta, lane, z);
+                    self.assign_advice(region, *column, segment_offset + z, F::from(bit as u64))?;
+                }
+            }
+        }
+
+        // Squeeze
+        let squeeze_offset = offset + NUM_ROUNDS * NUM_BITS_PER_LANE;
+        let output = block.states.last().unwrap();
+        let mut output_rlc = [F::zero(); NUM_OUTPUT_LANES];
+        for z in 0..NUM_BITS_PER_LANE {
+            for (lane, column) in self.output_rlc.iter().enumerate() {
+                if z % 8 == 0 {
+                    output_rlc[lane] *= randomness;
+                }
+                if state_bit(output, lane, z) {
+                    output_rlc[lane] += F::from(1u64 << (z % 8));
+                }
+                self.assign_advice(region, *column, squeeze_offset + z, output_rlc[lane])?;
+            }
+        }
+
+        // Keccak table
+        let table_offset = offset + ROWS_PER_BLOCK - 1;
+        let row = if block.is_final {
+            let randomness_lane = randomness.pow(&[NUM_BYTES_PER_LANE as u64, 0, 0, 0]);
+            [
+                F::one(),
+                data_rlc,
+                F::from(length),
+                output_rlc
+                    .iter()
+                    .fold(F::zero(), |acc, lane| acc * randomness_lane + lane),
+            ]
+        } else {
+            [F::zero(); 4]
+        };
+        for (column, value) in [
+            self.keccak_table.is_enabled,
+            self.keccak_table.input_rlc,
+            self.keccak_table.input_len,
+            self.keccak_table.output_rlc,
+        ]
+        .into_iter()
+        .zip(row)
+        {
+            self.assign_advice(region, column, table_offset, value)?;
+        }
+
+        Ok((data_rlc, length))
+    }
+}
+
+/// Keccak circuit proving the hashes of a list of inputs
+#[derive(Default)]
+pub struct KeccakCircuit<F: Field> {
+    inputs: Vec<Vec<u8>>,
+    size: usize,
+    randomness: F,
+}
+
+impl<F: Field> KeccakCircuit<F> {
+    /// Make a new Keccak circuit hashing `inputs` in `size` rows
+    pub fn new(inputs: Vec<Vec<u8>>, size: usize, randomness: F) -> Self {
+        Self {
+            inputs,
+            size,
+            randomness,
+        }
+    }
+
+    /// Returns the number of rows required to hash `inputs`
+    pub fn min_num_rows(inputs: &[Vec<u8>]) -> usize {
+        let num_blocks = inputs
+            .iter()
+            .map(|input| input.len() / RATE + 1)
+            .sum::<usize>();
+        num_blocks * ROWS_PER_BLOCK + NUM_BITS_PER_LANE
+    }
+
+    /// powers of randomness for instance columns
+    pub fn instance(&self) -> Vec<Vec<F>> {
+        (1..32)
+            .map(|exp| vec![self.randomness.pow(&[exp, 0, 0, 0]); self.size])
+            .collect()
+    }
+}
+
+impl<F: Field> Circuit<F> for KeccakCircuit<F> {
+    type Config = KeccakConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let keccak_table = KeccakTable::construct(meta);
+        let power_of_randomness = power_of_randomness_from_instance(meta);
+        KeccakConfig::configure(meta, power_of_randomness, keccak_table)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.assign(&mut layouter, self.size, &self.inputs, self.randomness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        pairing::bn256::Fr,
+    };
+    use keccak256::circuit::MAX_INPUT_BYTES;
+
+    const RANDOMNESS: u64 = 0x100;
+
+    fn log2_ceil(n: usize) -> u32 {
+        u32::BITS - (n as u32).leading_zeros() - (n & (n - 1) == 0) as u32
+    }
+
+    #[derive(Clone)]
+    struct MyConfig {
+        keccak: KeccakConfig<Fr>,
+        q_lookup: Column<Fixed>,
+        lookup: [Column<Advice>; 4],
+    }
+
+    // Keccak circuit assigning the witness of the blocks as given, which looks
+    // up the expected rows of the KeccakTable.
+    #[derive(Default)]
+    struct MyCircuit {
+        blocks: Vec<KeccakBlock>,
+        lookups: Vec<[Fr; 4]>,
+    }
+
+    impl Circuit<Fr> for MyCircuit {
+        type Config = MyConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let keccak = KeccakCircuit::<Fr>::configure(meta);
+            let q_lookup = meta.fixed_column();
+            let lookup = [(); 4].map(|_| meta.advice_column());
+            meta.lookup_any("keccak table lookup", |meta| {
+                let q_lookup = meta.query_fixed(q_lookup, Rotation::cur());
+                let table = &keccak.keccak_table;
+                lookup
+                    .iter()
+                    .zip([
+                        table.is_enabled,
+                        table.input_rlc,
+                        table.input_len,
+                        table.output_rlc,
+                    ])
+                    .map(|(input, table)| {
+                        (
+                            q_lookup.clone() * meta.query_advice(*input, Rotation::cur()),
+                            meta.query_advice(table, Rotation::cur()),
+                        )
+                    })
+                    .collect()
+            });
+            MyConfig {
+                keccak,
+                q_lookup,
+                lookup,
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config
+                .keccak
+                .assign_blocks(&mut layouter, &self.blocks, Fr::from(RANDOMNESS))?;
+            layouter.assign_region(
+                || "keccak table lookups",
+                |mut region| {
+                    for (offset, row) in self.lookups.iter().enumerate() {
+                        region.assign_fixed(
+                            || "q_lookup",
+                            config.q_lookup,
+                            offset,
+                            || Ok(Fr::one()),
+                        )?;
+                        for (column, value) in config.lookup.iter().zip(row) {
+                            region.assign_advice(|| "lookup", *column, offset, || Ok(*value))?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn verify(blocks: Vec<KeccakBlock>, inputs: &[Vec<u8>]) -> Result<(), Vec<VerifyFailure>> {
+        let randomness = Fr::from(RANDOMNESS);
+        let size = blocks.len() * ROWS_PER_BLOCK + NUM_BITS_PER_LANE;
+        let instance = KeccakCircuit::new(vec![], size, randomness).instance();
+        let lookups = inputs
+            .iter()
+            .flat_map(|input| KeccakTable::assignments(input, randomness))
+            .collect();
+        let circuit = MyCircuit { blocks, lookups };
+        let prover = MockProver::run(log2_ceil(size + 64), &circuit, instance).unwrap();
+        prover.verify()
+    }
+
+    fn test_inputs() -> Vec<Vec<u8>> {
+        vec![
+            vec![],
+            vec![0x01; RATE - 1],
+            (0..RATE).map(|byte| byte as u8).collect(),
+            vec![0xff; RATE + 1],
+        ]
+    }
+
+    fn blocks(inputs: &[Vec<u8>]) -> Vec<KeccakBlock> {
+        let num_blocks = inputs.iter().map(|input| input.len() / RATE + 1).sum();
+        keccak_blocks(inputs.iter().map(|input| input.as_slice()), num_blocks).unwrap()
+    }
+
+    #[test]
+    fn keccak_circuit_valid() {
+        let inputs = test_inputs();
+        assert_eq!(verify(blocks(&inputs), &inputs), Ok(()));
+    }
+
+    #[test]
+    fn keccak_circuit_input_longer_than_max_perm_rounds() {
+        let inputs = vec![vec![0xab; MAX_INPUT_BYTES + 1]];
+        assert_eq!(verify(blocks(&inputs), &inputs), Ok(()));
+    }
+
+    #[test]
+    fn keccak_circuit_padding_blocks() {
+        let inputs = test_inputs();
+        let size = KeccakCircuit::<Fr>::min_num_rows(&inputs) + 2 * ROWS_PER_BLOCK;
+        let circuit = KeccakCircuit::new(inputs, size, Fr::from(RANDOMNESS));
+        let prover = MockProver::run(log2_ceil(size + 64), &circuit, circuit.instance()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn keccak_circuit_not_enough_rows() {
+        let inputs = test_inputs();
+        let size = KeccakCircuit::<Fr>::min_num_rows(&inputs) - 1;
+        let circuit = KeccakCircuit::new(inputs, size, Fr::from(RANDOMNESS));
+        assert!(MockProver::run(log2_ceil(size + 64), &circuit, circuit.instance()).is_err());
+    }
+
+    #[test]
+    fn keccak_circuit_invalid_state() {
+        let inputs = test_inputs();
+        let mut blocks = blocks(&inputs);
+        blocks[2].states[NUM_ROUNDS / 2][1][3] ^= 1 << 17;
+        assert!(verify(blocks, &[]).is_err());
+    }
+
+    #[test]
+    fn keccak_circuit_invalid_output() {
+        let inputs = test_inputs();
+        let mut blocks = blocks(&inputs);
+        // The output of a block is the first state of the next block.
+        blocks[0].states[NUM_ROUNDS + 1][0][0] ^= 1;
+        blocks[1].states[0][0][0] ^= 1;
+        assert!(verify(blocks, &[]).is_err());
+    }
+
+    #[test]
+    fn keccak_circuit_invalid_input_byte() {
+        let inputs = test_inputs();
+        let mut blocks = blocks(&inputs);
+        blocks[2].bytes[7] ^= 0x10;
+        assert!(verify(blocks, &[]).is_err());
+    }
+
+    #[test]
+    fn keccak_circuit_invalid_padding() {
+        // Claim that the 0x01 padding byte of the input is an input byte.
+        let inputs = vec![vec![0x02; 7]];
+        let mut blocks = blocks(&inputs);
+        blocks[0].input_len += 1;
+        assert!(verify(blocks, &[]).is_err());
+    }
+
+    #[test]
+    fn keccak_circuit_invalid_final_block() {
+        let inputs = vec![vec![0x02; RATE + 7]];
+        let mut blocks = blocks(&inputs);
+        blocks[0].is_final = true;
+        assert!(verify(blocks, &[]).is_err());
+    }
+}
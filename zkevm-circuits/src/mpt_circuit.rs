@@ -0,0 +1,4065 @@
+//! The MPT circuit proves the updates of account fields and storage slots
+//! against the Merkle Patricia Tries of the state, exposing them in the
+//! [`MptTable`] used by the State Circuit.
+//!
+//! Each update is laid out as the RLP encodings, before and after the update,
+//! of the nodes in the path of its key: the nodes of the account in the state
+//! trie followed, for storage updates, by the nodes of the slot in the storage
+//! trie of the account.  Each row appends one RLP item (or list header) to the
+//! encodings of the current node, whose hashes are looked up in the
+//! [`KeccakTable`] on its last row, unless the node is embedded in its parent.
+//! The nibbles followed along the branch and extension nodes, together with
+//! the key left in the leaf, are bound to the hash of the address or storage
+//! key.
+//!
+//! The leaf of a key missing from a trie (an empty account or a slot of value
+//! 0) is laid out with the default values, but it's not in the trie: the path
+//! of the key ends at an empty child of a branch, or at a leaf or an extension
+//! node of other keys, the divergent node.  Inserting the key replaces the
+//! divergent node with an extension node for the nibbles both keys have in
+//! common, if any, and a branch node with the leaf of the key and the node the
+//! divergent node drifts to, with the nibbles left to it.  Deleting the key
+//! does the opposite.  When the key is missing from both tries, these nodes
+//! are laid out as well to prove that its path diverges.
+//!
+//! Branch and extension nodes embedded in their parent, branch nodes of less
+//! than 56 bytes, leaves with a key of less than 2 nibbles and nonces above
+//! `u64::MAX` are not supported.
+
+use crate::{
+    evm_circuit::{
+        param::{N_BYTES_ACCOUNT_ADDRESS, N_BYTES_WORD},
+        util::{constraint_builder::BaseConstraintBuilder, from_bytes, not, rlc, select, sum},
+        witness::{MptUpdate, MptUpdates},
+    },
+    table::{AccountFieldTag, DynamicTableColumns, KeccakTable, MptTable},
+    util::{power_of_randomness_from_instance, Expr},
+};
+use eth_types::{
+    trie::{
+        compact_key, decode_storage_value, keccak, key_nibbles, minimal_be_bytes, rlp_list_header,
+        rlp_string, Account, NodeRef, PartialTrie, TrieError, TrieNode, EMPTY_CODE_HASH,
+        EMPTY_TRIE_ROOT,
+    },
+    EIP1186ProofResponse, Field, ToBigEndian, ToLittleEndian, ToWord, Word, H256, U256,
+};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, VirtualCells},
+    poly::Rotation,
+};
+use log::error;
+use std::{iter, marker::PhantomData};
+use strum::EnumCount;
+
+/// Number of powers of the randomness used by the MPT circuit, for the RLC
+/// of the bytes of a word
+pub const POW_RAND_SIZE: usize = N_BYTES_WORD - 1;
+
+/// Length of the longest RLP item laid out in a row: the value of a leaf of
+/// the state trie, the RLP string of an account with a nonce of 8 bytes.
+const MAX_CHUNK_LEN: usize = 112;
+
+/// Kind of a row of the MPT circuit
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum_macros::EnumCount)]
+enum RowKind {
+    /// Padding after the last update
+    Padding,
+    /// List header of a branch node
+    BranchHeader,
+    /// Child of a branch node
+    BranchChild,
+    /// Empty value of a branch node, last row of the node
+    BranchValue,
+    /// List header of an extension node
+    ExtensionHeader,
+    /// Key of an extension node
+    ExtensionKey,
+    /// Child of an extension node, last row of the node
+    ExtensionChild,
+    /// List header of a leaf node
+    LeafHeader,
+    /// Key of a leaf node
+    LeafKey,
+    /// Value of a leaf of another key, last row of the node
+    LeafValue,
+    /// String and list headers of the account in a leaf of the state trie
+    AccountHeader,
+    /// Nonce of an account
+    Nonce,
+    /// Balance of an account
+    Balance,
+    /// Storage root of an account
+    StorageRoot,
+    /// Code hash of an account, last row of the leaf node
+    CodeHash,
+    /// Value of a leaf of a storage trie, last row of the leaf node
+    StorageValue,
+}
+
+impl Default for RowKind {
+    fn default() -> Self {
+        Self::Padding
+    }
+}
+
+/// Key, depth and second item of a divergent node, carried along the split
+/// nodes to the node it drifts to.
+#[derive(Clone, Copy, Debug, Default)]
+struct Drift<F> {
+    /// RLC of the nibbles of the key at the end of the node
+    key: F,
+    key_len: usize,
+    is_odd: bool,
+    is_leaf: bool,
+    /// RLC of the value of a leaf, or of the hash of the child of an extension
+    item: F,
+    /// Length of the value of a leaf
+    item_len: usize,
+}
+
+/// Witness of a row of the MPT circuit.  Pairs of values are the ones of the
+/// node before and after the update.
+#[derive(Clone, Debug, Default)]
+struct MptRow<F> {
+    kind: RowKind,
+    is_update_start: bool,
+    in_storage: bool,
+    /// Whether the node is in the trie
+    is_active: [bool; 2],
+    /// RLC of the reference to the node in its parent: its hash, or its
+    /// encoding when embedded
+    hash: [F; 2],
+    is_embedded: [bool; 2],
+    /// RLC of the reference to the next node in the path
+    child: [F; 2],
+    child_embedded: [bool; 2],
+    /// Whether the node is a leaf or an extension node of another key where
+    /// the path of the key ends
+    is_divergent: bool,
+    /// Whether the node is the extension or branch node replacing the
+    /// divergent node in the trie with the key
+    is_split: bool,
+    /// Whether the node is the one the divergent node drifts to
+    is_drifted: bool,
+    /// RLC of the hashed key of the path
+    key_rlc: F,
+    /// RLC of the nibbles of the key consumed by the ancestors of the node
+    key_acc: F,
+    /// Number of bytes of the key in the leaf, were it the node
+    key_len: usize,
+    /// Whether the ancestors of the node consumed an odd number of nibbles
+    is_odd: bool,
+    /// Power of the randomness of the next nibble of the key
+    key_pow: F,
+    /// Next nibble of the key in a branch, or nibble in the flag of the key of
+    /// an extension or leaf node
+    nibble: u8,
+    /// `key_acc`, `key_len` and `is_odd` after the key of an extension or leaf
+    /// node
+    key_end: F,
+    key_len_end: usize,
+    is_odd_end: bool,
+    key_pow_end: F,
+    /// RLC of the reference to the child of a split branch outside of the path,
+    /// and its slot
+    sibling: F,
+    sibling_embedded: bool,
+    drift_nibble: u8,
+    drift: Drift<F>,
+    slot: usize,
+    is_empty: [bool; 2],
+    is_path: bool,
+    path_count: usize,
+    is_updated: bool,
+    value: [F; 2],
+    /// Byte length of an RLP encoded integer, or number of children of a branch
+    n: [usize; 2],
+    /// `is_long` for list headers, `is_short` for RLP encoded integers and
+    /// `is_embedded` for children of branches
+    flag: [bool; 2],
+    /// RLP item appended to the encoding of the node
+    chunk: [Vec<u8>; 2],
+    bytes: [u8; N_BYTES_WORD],
+    /// Whether each little-endian byte of the integer after the update is in
+    /// its minimal encoding, or each byte is in the key of the node
+    is_significant: [bool; N_BYTES_WORD],
+    inverse: F,
+    table: [F; 7],
+}
+
+/// Config for MptCircuit
+#[derive(Clone, Debug)]
+pub struct MptCircuitConfig<F> {
+    minimum_rows: usize,
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    kinds: [Column<Advice>; RowKind::COUNT],
+    is_update_start: Column<Advice>,
+    in_storage: Column<Advice>,
+    is_active: [Column<Advice>; 2],
+    hash: [Column<Advice>; 2],
+    is_embedded: [Column<Advice>; 2],
+    child: [Column<Advice>; 2],
+    child_embedded: [Column<Advice>; 2],
+    is_divergent: Column<Advice>,
+    is_split: Column<Advice>,
+    is_drifted: Column<Advice>,
+    key_rlc: Column<Advice>,
+    key_acc: Column<Advice>,
+    key_len: Column<Advice>,
+    is_odd: Column<Advice>,
+    key_pow: Column<Advice>,
+    nibble: Column<Advice>,
+    key_end: Column<Advice>,
+    key_len_end: Column<Advice>,
+    is_odd_end: Column<Advice>,
+    key_pow_end: Column<Advice>,
+    sibling: Column<Advice>,
+    sibling_embedded: Column<Advice>,
+    drift_nibble: Column<Advice>,
+    drift_key: Column<Advice>,
+    drift_key_len: Column<Advice>,
+    drift_is_odd: Column<Advice>,
+    drift_is_leaf: Column<Advice>,
+    drift_item: Column<Advice>,
+    drift_item_len: Column<Advice>,
+    slot: Column<Advice>,
+    is_empty: [Column<Advice>; 2],
+    is_path: Column<Advice>,
+    path_count: Column<Advice>,
+    is_updated: Column<Advice>,
+    value: [Column<Advice>; 2],
+    n: [Column<Advice>; 2],
+    flag: [Column<Advice>; 2],
+    chunk: [Column<Advice>; 2],
+    chunk_len: [Column<Advice>; 2],
+    pow: [Column<Advice>; 2],
+    acc: [Column<Advice>; 2],
+    len: [Column<Advice>; 2],
+    bytes: [Column<Advice>; N_BYTES_WORD],
+    /// High nibble of each byte
+    nibbles: [Column<Advice>; N_BYTES_WORD],
+    is_significant: [Column<Advice>; N_BYTES_WORD],
+    inverse: Column<Advice>,
+    u8_table: Column<Fixed>,
+    hi_nibble_table: Column<Fixed>,
+    q_pow: Column<Fixed>,
+    pow_index: Column<Fixed>,
+    pow_value: Column<Advice>,
+    /// MptTable
+    pub mpt_table: MptTable,
+    /// KeccakTable
+    pub keccak_table: KeccakTable,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field> MptCircuitConfig<F> {
+    /// Return a new MptCircuitConfig
+    pub fn new(
+        meta: &mut ConstraintSystem<F>,
+        power_of_randomness: [Expression<F>; POW_RAND_SIZE],
+        mpt_table: MptTable,
+        keccak_table: KeccakTable,
+    ) -> Self {
+        let q_enable = meta.fixed_column();
+        let q_first = meta.fixed_column();
+        let kinds = [(); RowKind::COUNT].map(|_| meta.advice_column());
+        let is_update_start = meta.advice_column();
+        let in_storage = meta.advice_column();
+        let is_active = [(); 2].map(|_| meta.advice_column());
+        let hash = [(); 2].map(|_| meta.advice_column());
+        let is_embedded = [(); 2].map(|_| meta.advice_column());
+        let child = [(); 2].map(|_| meta.advice_column());
+        let child_embedded = [(); 2].map(|_| meta.advice_column());
+        let is_divergent = meta.advice_column();
+        let is_split = meta.advice_column();
+        let is_drifted = meta.advice_column();
+        let key_rlc = meta.advice_column();
+        let key_acc = meta.advice_column();
+        let key_len = meta.advice_column();
+        let is_odd = meta.advice_column();
+        let key_pow = meta.advice_column();
+        let nibble = meta.advice_column();
+        let key_end = meta.advice_column();
+        let key_len_end = meta.advice_column();
+        let is_odd_end = meta.advice_column();
+        let key_pow_end = meta.advice_column();
+        let sibling = meta.advice_column();
+        let sibling_embedded = meta.advice_column();
+        let drift_nibble = meta.advice_column();
+        let drift_key = meta.advice_column();
+        let drift_key_len = meta.advice_column();
+        let drift_is_odd = meta.advice_column();
+        let drift_is_leaf = meta.advice_column();
+        let drift_item = meta.advice_column();
+        let drift_item_len = meta.advice_column();
+        let slot = meta.advice_column();
+        let is_empty = [(); 2].map(|_| meta.advice_column());
+        let is_path = meta.advice_column();
+        let path_count = meta.advice_column();
+        let is_updated = meta.advice_column();
+        let value = [(); 2].map(|_| meta.advice_column());
+        let n = [(); 2].map(|_| meta.advice_column());
+        let flag = [(); 2].map(|_| meta.advice_column());
+        let chunk = [(); 2].map(|_| meta.advice_column());
+        let chunk_len = [(); 2].map(|_| meta.advice_column());
+        let pow = [(); 2].map(|_| meta.advice_column());
+        let acc = [(); 2].map(|_| meta.advice_column());
+        let len = [(); 2].map(|_| meta.advice_column());
+        let bytes = [(); N_BYTES_WORD].map(|_| meta.advice_column());
+        let nibbles = [(); N_BYTES_WORD].map(|_| meta.advice_column());
+        let is_significant = [(); N_BYTES_WORD].map(|_| meta.advice_column());
+        let inverse = meta.advice_column();
+        let u8_table = meta.fixed_column();
+        let hi_nibble_table = meta.fixed_column();
+        let q_pow = meta.fixed_column();
+        let pow_index = meta.fixed_column();
+        let pow_value = meta.advice_column();
+        // The roots before and after the updates are copied to the state roots of
+        // the public inputs.
+        meta.enable_equality(mpt_table.old_root);
+        meta.enable_equality(mpt_table.new_root);
+
+        let r = power_of_randomness[0].clone();
+        let r2 = power_of_randomness[1].clone();
+        let r3 = power_of_randomness[2].clone();
+        let empty_root_rlc = constant_hash_rlc(*EMPTY_TRIE_ROOT, &power_of_randomness);
+        let empty_code_hash_rlc = constant_hash_rlc(*EMPTY_CODE_HASH, &power_of_randomness);
+
+        let kind = move |meta: &mut VirtualCells<F>, kind: RowKind, rotation: Rotation| {
+            meta.query_advice(kinds[kind as usize], rotation)
+        };
+        let is_node_start = move |meta: &mut VirtualCells<F>, rotation: Rotation| {
+            kind(meta, RowKind::BranchHeader, rotation)
+                + kind(meta, RowKind::ExtensionHeader, rotation)
+                + kind(meta, RowKind::LeafHeader, rotation)
+        };
+        // Last rows of the nodes followed by another node in the same trie: all but
+        // the leaf of the key.
+        let is_inner_node_end = move |meta: &mut VirtualCells<F>| {
+            kind(meta, RowKind::BranchValue, Rotation::cur())
+                + kind(meta, RowKind::ExtensionChild, Rotation::cur())
+                + kind(meta, RowKind::LeafValue, Rotation::cur())
+        };
+        let is_node_end = move |meta: &mut VirtualCells<F>| {
+            is_inner_node_end(meta)
+                + kind(meta, RowKind::CodeHash, Rotation::cur())
+                + kind(meta, RowKind::StorageValue, Rotation::cur())
+        };
+        let is_leaf_end = move |meta: &mut VirtualCells<F>| {
+            kind(meta, RowKind::LeafValue, Rotation::cur())
+                + kind(meta, RowKind::CodeHash, Rotation::cur())
+                + kind(meta, RowKind::StorageValue, Rotation::cur())
+        };
+        // Whether the node at `rotation` is divergent, split or drifted
+        let role = move |meta: &mut VirtualCells<F>, rotation: Rotation| {
+            meta.query_advice(is_divergent, rotation)
+                + meta.query_advice(is_split, rotation)
+                + meta.query_advice(is_drifted, rotation)
+        };
+        // Whether the node starting at `rotation` is the leaf of the key
+        let is_key_leaf = move |meta: &mut VirtualCells<F>, rotation: Rotation| {
+            kind(meta, RowKind::LeafHeader, rotation) * not::expr(role(meta, rotation))
+        };
+        let q_enable_not_padding = move |meta: &mut VirtualCells<F>| {
+            meta.query_fixed(q_enable, Rotation::cur())
+                * not::expr(kind(meta, RowKind::Padding, Rotation::cur()))
+        };
+        // Little-endian bytes of the integer after the update in a row of
+        // `row_kind`, and whether each of them is in its minimal encoding.
+        let integer_bytes = move |meta: &mut VirtualCells<F>, row_kind: RowKind| {
+            let range = match row_kind {
+                RowKind::Nonce => 8..16,
+                _ => 0..N_BYTES_WORD,
+            };
+            let value_bytes = bytes[range.clone()]
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::cur()))
+                .collect::<Vec<_>>();
+            let is_significant = is_significant[..range.len()]
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::cur()))
+                .collect::<Vec<_>>();
+            (value_bytes, is_significant)
+        };
+        // Bytes of the key of an extension or leaf node after its flag, from the
+        // last one, with their high nibbles and whether each of them is in the key.
+        let key_bytes = move |meta: &mut VirtualCells<F>| {
+            let [key_bytes, key_nibbles, is_key_byte] =
+                [bytes, nibbles, is_significant].map(|columns| {
+                    columns
+                        .iter()
+                        .map(|column| meta.query_advice(*column, Rotation::cur()))
+                        .collect::<Vec<_>>()
+                });
+            (key_bytes, key_nibbles, is_key_byte)
+        };
+        // Constrain the node starting at `rotation` to be referenced by `refs`: an
+        // active node is the one referenced, or the sibling of the split branch
+        // when it's drifted, a node missing from the trie passes the reference on
+        // to the next node, and the missing leaf of the key is referenced by
+        // `empty_ref`.
+        let chain = move |meta: &mut VirtualCells<F>,
+                          cb: &mut BaseConstraintBuilder<F>,
+                          enable: Expression<F>,
+                          rotation: Rotation,
+                          refs: [Expression<F>; 2],
+                          refs_embedded: [Expression<F>; 2],
+                          empty_ref: Expression<F>| {
+            let is_drifted_node = meta.query_advice(is_drifted, rotation);
+            let is_key = is_key_leaf(meta, rotation);
+            let sibling_cur = meta.query_advice(sibling, Rotation::cur());
+            let sibling_embedded_cur = meta.query_advice(sibling_embedded, Rotation::cur());
+            for i in 0..2 {
+                let is_active_node = meta.query_advice(is_active[i], rotation);
+                let hash_node = meta.query_advice(hash[i], rotation);
+                let is_embedded_node = meta.query_advice(is_embedded[i], rotation);
+                let child_node = meta.query_advice(child[i], rotation);
+                let child_embedded_node = meta.query_advice(child_embedded[i], rotation);
+
+                let is_referenced = enable.clone() * is_active_node.clone();
+                cb.require_zero(
+                    "active node is the one referenced",
+                    is_referenced.clone()
+                        * (hash_node
+                            - select::expr(
+                                is_drifted_node.clone(),
+                                sibling_cur.clone(),
+                                refs[i].clone(),
+                            )),
+                );
+                cb.require_zero(
+                    "active node is the one referenced",
+                    is_referenced
+                        * (is_embedded_node
+                            - select::expr(
+                                is_drifted_node.clone(),
+                                sibling_embedded_cur.clone(),
+                                refs_embedded[i].clone(),
+                            )),
+                );
+                let passes_ref = not::expr(is_active_node.clone()) * not::expr(is_key.clone())
+                    + is_active_node.clone() * is_drifted_node.clone();
+                cb.require_zero(
+                    "node missing from the trie passes the reference on",
+                    enable.clone() * passes_ref.clone() * (child_node - refs[i].clone()),
+                );
+                cb.require_zero(
+                    "node missing from the trie passes the reference on",
+                    enable.clone() * passes_ref * (child_embedded_node - refs_embedded[i].clone()),
+                );
+                let is_missing_key = enable.clone() * not::expr(is_active_node) * is_key.clone();
+                cb.require_zero(
+                    "leaf of the key missing from the trie is not referenced",
+                    is_missing_key.clone() * (refs[i].clone() - empty_ref.clone()),
+                );
+                cb.require_zero(
+                    "leaf of the key missing from the trie is not referenced",
+                    is_missing_key * refs_embedded[i].clone(),
+                );
+            }
+        };
+
+        meta.create_gate("row", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+
+            for column in kinds
+                .iter()
+                .chain(&[
+                    is_update_start,
+                    in_storage,
+                    is_divergent,
+                    is_split,
+                    is_drifted,
+                    is_odd,
+                    is_odd_end,
+                    sibling_embedded,
+                    drift_is_odd,
+                    drift_is_leaf,
+                    is_path,
+                    is_updated,
+                ])
+                .chain(&is_active)
+                .chain(&is_embedded)
+                .chain(&child_embedded)
+                .chain(&is_empty)
+                .chain(&flag)
+            {
+                cb.require_boolean(
+                    "flags are boolean",
+                    meta.query_advice(*column, Rotation::cur()),
+                );
+            }
+            cb.require_boolean(
+                "node is divergent, split or drifted at most",
+                role(meta, Rotation::cur()),
+            );
+            cb.require_equal(
+                "row has exactly one kind",
+                sum::expr(kinds.map(|column| meta.query_advice(column, Rotation::cur()))),
+                1.expr(),
+            );
+
+            let is_padding = kind(meta, RowKind::Padding, Rotation::cur());
+            let table = mpt_table
+                .columns()
+                .into_iter()
+                .map(|column| meta.query_advice(column, Rotation::cur()))
+                .collect::<Vec<_>>();
+            cb.condition(is_padding.clone(), |cb| {
+                for column in table {
+                    cb.require_zero("MptTable row is all zero in padding", column);
+                }
+            });
+
+            // The encodings of the nodes are accumulated as `RLC(reversed(encoding))`, the
+            // `input_rlc` of the KeccakTable.
+            let node_continues = not::expr(is_node_start(meta, Rotation::cur()));
+            let accumulation = (0..2)
+                .map(|i| {
+                    (
+                        meta.query_advice(acc[i], Rotation::cur()),
+                        meta.query_advice(acc[i], Rotation::prev()),
+                        meta.query_advice(len[i], Rotation::cur()),
+                        meta.query_advice(len[i], Rotation::prev()),
+                        meta.query_advice(chunk[i], Rotation::cur()),
+                        meta.query_advice(chunk_len[i], Rotation::cur()),
+                        meta.query_advice(pow[i], Rotation::cur()),
+                    )
+                })
+                .collect::<Vec<_>>();
+            cb.condition(not::expr(is_padding), |cb| {
+                for (acc, acc_prev, len, len_prev, chunk, chunk_len, pow) in accumulation {
+                    cb.require_equal(
+                        "acc := acc_prev * r^chunk_len + chunk, or chunk at the start of a node",
+                        acc,
+                        node_continues.clone() * acc_prev * pow + chunk,
+                    );
+                    cb.require_equal(
+                        "len := len_prev + chunk_len, or chunk_len at the start of a node",
+                        len,
+                        node_continues.clone() * len_prev + chunk_len,
+                    );
+                }
+            });
+
+            cb.require_zero(
+                "update starts at the start of a node",
+                meta.query_advice(is_update_start, Rotation::cur())
+                    * not::expr(is_node_start(meta, Rotation::cur())),
+            );
+
+            // The divergent, split and drifted nodes are laid out the same way in both
+            // tries, even when missing from one of them.
+            let sides = [value, n, flag, is_empty, chunk, chunk_len].map(|columns| {
+                (
+                    meta.query_advice(columns[0], Rotation::cur()),
+                    meta.query_advice(columns[1], Rotation::cur()),
+                )
+            });
+            cb.condition(role(meta, Rotation::cur()), |cb| {
+                for (old, new) in sides {
+                    cb.require_equal("node off the path of the key doesn't change", old, new);
+                }
+            });
+
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("first row", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            cb.require_equal(
+                "first row is the start of an update or padding",
+                meta.query_advice(is_update_start, Rotation::cur())
+                    + kind(meta, RowKind::Padding, Rotation::cur()),
+                1.expr(),
+            );
+            cb.gate(meta.query_fixed(q_first, Rotation::cur()))
+        });
+
+        meta.create_gate("last row", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            cb.require_equal(
+                "last row is padding",
+                kind(meta, RowKind::Padding, Rotation::cur()),
+                1.expr(),
+            );
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * not::expr(meta.query_fixed(q_enable, Rotation::next())),
+            )
+        });
+
+        meta.create_gate("constants", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+
+            let node_constants = [
+                is_active[0],
+                is_active[1],
+                hash[0],
+                hash[1],
+                is_embedded[0],
+                is_embedded[1],
+                child[0],
+                child[1],
+                child_embedded[0],
+                child_embedded[1],
+                is_divergent,
+                is_split,
+                is_drifted,
+                key_rlc,
+                key_acc,
+                key_len,
+                is_odd,
+                key_pow,
+                nibble,
+                key_end,
+                key_len_end,
+                is_odd_end,
+                sibling,
+                sibling_embedded,
+                drift_nibble,
+                drift_key,
+                drift_key_len,
+                drift_is_odd,
+                drift_is_leaf,
+                drift_item,
+                drift_item_len,
+                in_storage,
+            ]
+            .map(|column| {
+                (
+                    meta.query_advice(column, Rotation::cur()),
+                    meta.query_advice(column, Rotation::prev()),
+                )
+            });
+            cb.condition(not::expr(is_node_start(meta, Rotation::cur())), |cb| {
+                for (value, value_prev) in node_constants {
+                    cb.require_equal("node constants don't change in the node", value, value_prev);
+                }
+            });
+
+            let update_constants = mpt_table.columns().into_iter().map(|column| {
+                (
+                    meta.query_advice(column, Rotation::cur()),
+                    meta.query_advice(column, Rotation::prev()),
+                )
+            });
+            let update_constants = update_constants.collect::<Vec<_>>();
+            cb.condition(
+                not::expr(meta.query_advice(is_update_start, Rotation::cur())),
+                |cb| {
+                    for (value, value_prev) in update_constants {
+                        cb.require_equal(
+                            "MptTable row doesn't change in the update",
+                            value,
+                            value_prev,
+                        );
+                    }
+                },
+            );
+
+            cb.gate(
+                q_enable_not_padding(meta) * not::expr(meta.query_fixed(q_first, Rotation::cur())),
+            )
+        });
+
+        meta.create_gate("node", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let is_branch = kind(meta, RowKind::BranchHeader, Rotation::cur());
+            let is_leaf = kind(meta, RowKind::LeafHeader, Rotation::cur());
+            let is_divergent_cur = meta.query_advice(is_divergent, Rotation::cur());
+            let is_split_cur = meta.query_advice(is_split, Rotation::cur());
+            let is_drifted_cur = meta.query_advice(is_drifted, Rotation::cur());
+            let is_active_cur = is_active.map(|column| meta.query_advice(column, Rotation::cur()));
+
+            cb.require_zero(
+                "divergent and drifted nodes are extension nodes or leaves",
+                is_branch * (is_divergent_cur.clone() + is_drifted_cur),
+            );
+            cb.require_zero(
+                "split nodes are extension or branch nodes",
+                is_leaf.clone() * is_split_cur,
+            );
+            cb.require_zero(
+                "divergent node is in a trie at least",
+                is_divergent_cur
+                    * not::expr(is_active_cur[0].clone())
+                    * not::expr(is_active_cur[1].clone()),
+            );
+            let is_in_path = not::expr(role(meta, Rotation::cur())) * not::expr(is_leaf.clone());
+            for (i, is_active_cur) in is_active_cur.into_iter().enumerate() {
+                cb.require_zero(
+                    "branch and extension nodes in the path of the key are in both tries",
+                    is_in_path.clone() * not::expr(is_active_cur),
+                );
+                cb.require_zero(
+                    "branch and extension nodes are referenced by their hash",
+                    not::expr(is_leaf.clone()) * meta.query_advice(is_embedded[i], Rotation::cur()),
+                );
+            }
+
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur()) * is_node_start(meta, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("update start", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            cb.require_zero(
+                "update starts in the state trie",
+                meta.query_advice(in_storage, Rotation::cur()),
+            );
+            cb.require_zero(
+                "no nibble of the key is consumed at the root",
+                meta.query_advice(key_acc, Rotation::cur()),
+            );
+            cb.require_equal(
+                "the key at the root has 32 bytes",
+                meta.query_advice(key_len, Rotation::cur()),
+                32.expr(),
+            );
+            cb.require_zero(
+                "no nibble of the key is consumed at the root",
+                meta.query_advice(is_odd, Rotation::cur()),
+            );
+            cb.require_zero(
+                "trie doesn't start with a split or drifted node",
+                meta.query_advice(is_split, Rotation::cur())
+                    + meta.query_advice(is_drifted, Rotation::cur()),
+            );
+            let roots = [mpt_table.old_root, mpt_table.new_root]
+                .map(|column| meta.query_advice(column, Rotation::cur()));
+            chain(
+                meta,
+                &mut cb,
+                1.expr(),
+                Rotation::cur(),
+                roots,
+                [0.expr(), 0.expr()],
+                empty_root_rlc.clone(),
+            );
+            let old_root = meta.query_advice(mpt_table.old_root, Rotation::cur());
+            let new_root_prev = meta.query_advice(mpt_table.new_root, Rotation::prev());
+            cb.condition(
+                not::expr(meta.query_fixed(q_first, Rotation::cur())),
+                |cb| {
+                    cb.require_equal(
+                        "old_root is the new_root of the previous update",
+                        old_root,
+                        new_root_prev,
+                    );
+                },
+            );
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * meta.query_advice(is_update_start, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("transitions", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+
+            let next = |meta: &mut VirtualCells<F>, row_kind: RowKind| {
+                kind(meta, row_kind, Rotation::next())
+            };
+            let is_update_start_next = meta.query_advice(is_update_start, Rotation::next());
+            let is_padding_next = next(meta, RowKind::Padding);
+            let is_node_start_next = is_node_start(meta, Rotation::next());
+
+            for (from, to) in [
+                (RowKind::Padding, RowKind::Padding),
+                (RowKind::BranchHeader, RowKind::BranchChild),
+                (RowKind::ExtensionHeader, RowKind::ExtensionKey),
+                (RowKind::ExtensionKey, RowKind::ExtensionChild),
+                (RowKind::LeafHeader, RowKind::LeafKey),
+                (RowKind::AccountHeader, RowKind::Nonce),
+                (RowKind::Nonce, RowKind::Balance),
+                (RowKind::Balance, RowKind::StorageRoot),
+                (RowKind::StorageRoot, RowKind::CodeHash),
+            ] {
+                let to = next(meta, to);
+                cb.condition(kind(meta, from, Rotation::cur()), |cb| {
+                    cb.require_equal("row kind transition", to, 1.expr());
+                });
+            }
+
+            let slot_cur = meta.query_advice(slot, Rotation::cur());
+            let slot_next = meta.query_advice(slot, Rotation::next());
+            cb.condition(kind(meta, RowKind::BranchHeader, Rotation::cur()), |cb| {
+                cb.require_zero("first child of a branch is in slot 0", slot_next.clone());
+            });
+            let is_child_next = next(meta, RowKind::BranchChild);
+            let is_value_next = next(meta, RowKind::BranchValue);
+            cb.condition(kind(meta, RowKind::BranchChild, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "branch child is followed by a child or the value",
+                    is_child_next.clone() + is_value_next.clone(),
+                    1.expr(),
+                );
+                cb.require_zero(
+                    "slot increases by 1 in the children of a branch",
+                    is_child_next * (slot_next - slot_cur.clone() - 1.expr()),
+                );
+                cb.require_zero(
+                    "branch has 16 children",
+                    is_value_next * (slot_cur - 15.expr()),
+                );
+            });
+
+            // Nodes in the path of the key are consecutive.
+            let is_inner_node_end_cur = is_inner_node_end(meta);
+            let path_constants = [key_rlc, in_storage].map(|column| {
+                meta.query_advice(column, Rotation::next())
+                    - meta.query_advice(column, Rotation::cur())
+            });
+            cb.condition(is_inner_node_end_cur.clone(), |cb| {
+                cb.require_equal(
+                    "node is followed by the next one in the path",
+                    is_node_start_next.clone(),
+                    1.expr(),
+                );
+                cb.require_zero(
+                    "node is followed by the next one in the path",
+                    is_update_start_next.clone(),
+                );
+                for constraint in path_constants {
+                    cb.require_zero("next node is in the same path", constraint);
+                }
+            });
+
+            let [is_divergent_cur, is_split_cur, is_drifted_cur] =
+                [is_divergent, is_split, is_drifted]
+                    .map(|column| meta.query_advice(column, Rotation::cur()));
+            let [is_divergent_next, is_split_next, is_drifted_next] =
+                [is_divergent, is_split, is_drifted]
+                    .map(|column| meta.query_advice(column, Rotation::next()));
+            let role_cur = role(meta, Rotation::cur());
+            let role_next = role(meta, Rotation::next());
+            let is_branch_end = kind(meta, RowKind::BranchValue, Rotation::cur());
+            let is_extension_end = kind(meta, RowKind::ExtensionChild, Rotation::cur());
+            let is_branch_next = next(meta, RowKind::BranchHeader);
+            let is_key_next = is_key_leaf(meta, Rotation::next());
+            for (name, constraint) in [
+                (
+                    "divergent node is followed by a split node",
+                    is_inner_node_end_cur.clone()
+                        * is_divergent_cur.clone()
+                        * not::expr(is_split_next.clone()),
+                ),
+                (
+                    "split extension node is followed by the split branch",
+                    is_split_cur.clone()
+                        * is_extension_end.clone()
+                        * not::expr(is_split_next.clone() * is_branch_next.clone()),
+                ),
+                (
+                    "split branch is followed by the drifted node or the leaf of the key",
+                    is_split_cur.clone()
+                        * is_branch_end.clone()
+                        * (1.expr() - is_drifted_next.clone() - is_key_next.clone()),
+                ),
+                (
+                    "drifted node is followed by the leaf of the key",
+                    is_inner_node_end_cur.clone() * is_drifted_cur.clone() * not::expr(is_key_next),
+                ),
+                (
+                    "extension node in the path is followed by a branch in the path",
+                    not::expr(role_cur.clone())
+                        * is_extension_end.clone()
+                        * not::expr(is_branch_next * not::expr(role_next)),
+                ),
+                (
+                    "branch in the path is not followed by a split or drifted node",
+                    not::expr(role_cur)
+                        * is_branch_end.clone()
+                        * (is_split_next.clone() + is_drifted_next.clone()),
+                ),
+            ] {
+                cb.require_zero(name, constraint);
+            }
+            for column in is_active {
+                let is_active_cur = meta.query_advice(column, Rotation::cur());
+                let is_active_next = meta.query_advice(column, Rotation::next());
+                cb.require_zero(
+                    "split nodes are in the tries without the divergent node",
+                    is_inner_node_end_cur.clone()
+                        * is_divergent_cur.clone()
+                        * (is_active_next.clone() + is_active_cur.clone() - 1.expr()),
+                );
+                cb.require_zero(
+                    "nodes after a split node are in the same tries",
+                    is_inner_node_end_cur.clone()
+                        * (is_split_cur.clone() + is_drifted_cur.clone())
+                        * (is_active_next - is_active_cur),
+                );
+            }
+            for column in [
+                drift_key,
+                drift_key_len,
+                drift_is_odd,
+                drift_is_leaf,
+                drift_item,
+                drift_item_len,
+            ] {
+                cb.require_zero(
+                    "divergent node is carried on to the node it drifts to",
+                    is_inner_node_end_cur.clone()
+                        * (is_split_next.clone() + is_drifted_next.clone())
+                        * (meta.query_advice(column, Rotation::next())
+                            - meta.query_advice(column, Rotation::cur())),
+                );
+            }
+
+            // State of the key after the branch at `rotation`, following the child in
+            // the slot `nibble`.
+            let after_branch =
+                |meta: &mut VirtualCells<F>, rotation: Rotation, nibble: Expression<F>| {
+                    let is_odd_branch = meta.query_advice(is_odd, rotation);
+                    [
+                        meta.query_advice(key_acc, rotation)
+                            + nibble
+                                * meta.query_advice(key_pow, rotation)
+                                * select::expr(is_odd_branch.clone(), 1.expr(), 16.expr()),
+                        meta.query_advice(key_len, rotation) - not::expr(is_odd_branch.clone()),
+                        not::expr(is_odd_branch),
+                    ]
+                };
+            let key_state =
+                |meta: &mut VirtualCells<F>, columns: [Column<Advice>; 3], rotation: Rotation| {
+                    columns.map(|column| meta.query_advice(column, rotation))
+                };
+            let branch_nibble = select::expr(
+                is_drifted_next,
+                meta.query_advice(drift_nibble, Rotation::cur()),
+                meta.query_advice(nibble, Rotation::cur()),
+            );
+            // The drifted node follows the split branch 3 rows above its last row.
+            let split_nibble = meta.query_advice(nibble, Rotation(-3));
+            let key_next = key_state(meta, [key_acc, key_len, is_odd], Rotation::next());
+            for (condition, expected) in [
+                (
+                    is_branch_end,
+                    after_branch(meta, Rotation::cur(), branch_nibble),
+                ),
+                (
+                    is_extension_end * not::expr(is_divergent_cur.clone() + is_drifted_cur.clone()),
+                    key_state(meta, [key_end, key_len_end, is_odd_end], Rotation::cur()),
+                ),
+                (
+                    is_inner_node_end_cur.clone() * is_divergent_cur,
+                    key_state(meta, [key_acc, key_len, is_odd], Rotation::cur()),
+                ),
+                (
+                    is_inner_node_end_cur.clone() * is_drifted_cur,
+                    after_branch(meta, Rotation(-3), split_nibble),
+                ),
+            ] {
+                for (value, expected) in key_next.iter().zip(expected) {
+                    cb.require_zero(
+                        "next node follows the key",
+                        condition.clone() * (value.clone() - expected),
+                    );
+                }
+            }
+
+            let refs = child.map(|column| meta.query_advice(column, Rotation::cur()));
+            let refs_embedded =
+                child_embedded.map(|column| meta.query_advice(column, Rotation::cur()));
+            chain(
+                meta,
+                &mut cb,
+                is_inner_node_end_cur,
+                Rotation::next(),
+                refs.clone(),
+                refs_embedded,
+                0.expr(),
+            );
+
+            let in_storage_cur = meta.query_advice(in_storage, Rotation::cur());
+            let is_leaf_value_next = next(meta, RowKind::LeafValue);
+            let is_storage_value_next = next(meta, RowKind::StorageValue);
+            let is_account_header_next = next(meta, RowKind::AccountHeader);
+            let is_key_cur = not::expr(role(meta, Rotation::cur()));
+            cb.condition(kind(meta, RowKind::LeafKey, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "leaf of another key has its value",
+                    is_leaf_value_next,
+                    not::expr(is_key_cur.clone()),
+                );
+                cb.require_equal(
+                    "leaf of the key in a storage trie has a storage value",
+                    is_storage_value_next,
+                    is_key_cur.clone() * in_storage_cur.clone(),
+                );
+                cb.require_equal(
+                    "leaf of the key in the state trie has an account",
+                    is_account_header_next,
+                    is_key_cur * not::expr(in_storage_cur),
+                );
+            });
+
+            // The storage trie of a storage update starts after the leaf of the account.
+            let is_storage_update = meta.query_advice(is_updated, Rotation::prev());
+            let is_storage_start =
+                kind(meta, RowKind::CodeHash, Rotation::cur()) * is_storage_update.clone();
+            let storage_trie_start = [
+                meta.query_advice(in_storage, Rotation::next()) - 1.expr(),
+                meta.query_advice(key_acc, Rotation::next()),
+                meta.query_advice(key_len, Rotation::next()) - 32.expr(),
+                meta.query_advice(is_odd, Rotation::next()),
+                is_split_next + is_drifted_next,
+            ];
+            cb.condition(is_storage_start.clone(), |cb| {
+                cb.require_equal(
+                    "storage update continues in the storage trie",
+                    is_node_start_next,
+                    1.expr(),
+                );
+                cb.require_zero(
+                    "storage update continues in the storage trie",
+                    is_update_start_next.clone(),
+                );
+                for constraint in storage_trie_start {
+                    cb.require_zero("storage trie starts at the storage root", constraint);
+                }
+            });
+            chain(
+                meta,
+                &mut cb,
+                is_storage_start,
+                Rotation::next(),
+                refs,
+                [0.expr(), 0.expr()],
+                empty_root_rlc.clone(),
+            );
+            cb.condition(
+                kind(meta, RowKind::CodeHash, Rotation::cur()) * not::expr(is_storage_update),
+                |cb| {
+                    cb.require_equal(
+                        "account update is followed by an update or padding",
+                        is_update_start_next.clone() + is_padding_next.clone(),
+                        1.expr(),
+                    );
+                },
+            );
+            cb.condition(kind(meta, RowKind::StorageValue, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "storage update is followed by an update or padding",
+                    is_update_start_next + is_padding_next,
+                    1.expr(),
+                );
+            });
+
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * meta.query_fixed(q_enable, Rotation::next()),
+            )
+        });
+
+        meta.create_gate("branch header", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            for i in 0..2 {
+                let payload_len = meta.query_advice(value[i], Rotation::cur());
+                let lo = meta.query_advice(bytes[2 * i], Rotation::cur());
+                let hi = meta.query_advice(bytes[2 * i + 1], Rotation::cur());
+                let is_long = meta.query_advice(flag[i], Rotation::cur());
+
+                cb.require_equal(
+                    "payload_len = lo + 256 * hi",
+                    payload_len,
+                    lo.clone() + hi.clone() * 256.expr(),
+                );
+                cb.require_zero(
+                    "payload length of a short header is a byte",
+                    not::expr(is_long.clone()) * hi.clone(),
+                );
+                // A branch node has at most 16 * 33 + 1 bytes of payload.
+                cb.require_zero(
+                    "payload length of a long header is in [256, 768)",
+                    is_long.clone() * (hi.clone() - 1.expr()) * (hi.clone() - 2.expr()),
+                );
+                cb.require_equal(
+                    "chunk_len = 2 + is_long",
+                    meta.query_advice(chunk_len[i], Rotation::cur()),
+                    2.expr() + is_long.clone(),
+                );
+                cb.require_equal(
+                    "chunk = is_long ? [0xf9, hi, lo] : [0xf8, lo]",
+                    meta.query_advice(chunk[i], Rotation::cur()),
+                    select::expr(
+                        is_long,
+                        0xf9.expr() * r2.clone() + hi * r.clone(),
+                        0xf8.expr() * r.clone(),
+                    ) + lo,
+                );
+                cb.require_zero(
+                    "no child is counted yet",
+                    meta.query_advice(n[i], Rotation::cur()),
+                );
+            }
+            cb.require_zero(
+                "path_count starts at 0",
+                meta.query_advice(path_count, Rotation::cur()),
+            );
+
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * kind(meta, RowKind::BranchHeader, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("branch child", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let is_path_cur = meta.query_advice(is_path, Rotation::cur());
+            let is_split_cur = meta.query_advice(is_split, Rotation::cur());
+            let is_empty_cur = is_empty.map(|column| meta.query_advice(column, Rotation::cur()));
+            let flag_cur = flag.map(|column| meta.query_advice(column, Rotation::cur()));
+            let chunk_cur = chunk.map(|column| meta.query_advice(column, Rotation::cur()));
+            let values = value.map(|column| meta.query_advice(column, Rotation::cur()));
+            // Reference to the child: its hash, or its encoding when embedded
+            let refs = [0, 1].map(|i| {
+                select::expr(flag_cur[i].clone(), chunk_cur[i].clone(), values[i].clone())
+            });
+
+            cb.require_equal(
+                "path_count := path_count_prev + is_path",
+                meta.query_advice(path_count, Rotation::cur()),
+                meta.query_advice(path_count, Rotation::prev()) + is_path_cur.clone(),
+            );
+            let slot_cur = meta.query_advice(slot, Rotation::cur());
+            let nibble_cur = meta.query_advice(nibble, Rotation::cur());
+            cb.condition(is_path_cur.clone(), |cb| {
+                cb.require_equal(
+                    "child in the path is in the slot of the nibble",
+                    slot_cur.clone(),
+                    nibble_cur,
+                );
+            });
+
+            for i in 0..2 {
+                let chunk_len_cur = meta.query_advice(chunk_len[i], Rotation::cur());
+                let pow_cur = meta.query_advice(pow[i], Rotation::cur());
+                let is_not_empty = not::expr(is_empty_cur[i].clone());
+                cb.require_equal(
+                    "n := n_prev + is_not_empty",
+                    meta.query_advice(n[i], Rotation::cur()),
+                    meta.query_advice(n[i], Rotation::prev()) + is_not_empty.clone(),
+                );
+                for (name, constraint) in [
+                    ("empty child is [0x80]", chunk_cur[i].clone() - 0x80.expr()),
+                    ("empty child is [0x80]", chunk_len_cur.clone() - 1.expr()),
+                    ("empty child has no value", values[i].clone()),
+                    ("empty child is not embedded", flag_cur[i].clone()),
+                ] {
+                    cb.require_zero(name, is_empty_cur[i].clone() * constraint);
+                }
+                // An embedded child is a list of less than 32 bytes, whose header is a
+                // single byte.
+                cb.require_zero(
+                    "child is [0xa0] ++ hash or an embedded node",
+                    is_not_empty.clone()
+                        * (r.clone() * chunk_cur[i].clone()
+                            - select::expr(
+                                flag_cur[i].clone(),
+                                0xc0.expr() + chunk_len_cur.clone() - 1.expr(),
+                                0xa0.expr(),
+                            ) * pow_cur
+                            - r.clone() * values[i].clone()),
+                );
+                cb.require_zero(
+                    "child is [0xa0] ++ hash",
+                    is_not_empty * not::expr(flag_cur[i].clone()) * (chunk_len_cur - 33.expr()),
+                );
+
+                let is_next_node =
+                    is_path_cur.clone() * meta.query_advice(is_active[i], Rotation::cur());
+                cb.require_zero(
+                    "child in the path is the next node",
+                    is_next_node.clone()
+                        * (meta.query_advice(child[i], Rotation::cur()) - refs[i].clone()),
+                );
+                cb.require_zero(
+                    "child in the path is the next node",
+                    is_next_node
+                        * (meta.query_advice(child_embedded[i], Rotation::cur())
+                            - flag_cur[i].clone()),
+                );
+            }
+            for columns in [is_empty, flag, chunk, chunk_len] {
+                cb.require_zero(
+                    "children outside of the path don't change",
+                    not::expr(is_path_cur.clone())
+                        * (meta.query_advice(columns[0], Rotation::cur())
+                            - meta.query_advice(columns[1], Rotation::cur())),
+                );
+            }
+
+            cb.require_zero(
+                "child in the path of a split branch is not empty",
+                is_split_cur.clone() * is_path_cur.clone() * is_empty_cur[0].clone(),
+            );
+            let is_sibling =
+                is_split_cur * not::expr(is_path_cur) * not::expr(is_empty_cur[0].clone());
+            for (name, constraint) in [
+                (
+                    "other child of a split branch is its sibling",
+                    meta.query_advice(sibling, Rotation::cur()) - refs[0].clone(),
+                ),
+                (
+                    "other child of a split branch is its sibling",
+                    meta.query_advice(sibling_embedded, Rotation::cur()) - flag_cur[0].clone(),
+                ),
+                (
+                    "other child of a split branch is in the slot of the drifted node",
+                    meta.query_advice(drift_nibble, Rotation::cur()) - slot_cur,
+                ),
+            ] {
+                cb.require_zero(name, is_sibling.clone() * constraint);
+            }
+
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * kind(meta, RowKind::BranchChild, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("branch value", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            for i in 0..2 {
+                cb.require_equal(
+                    "branch value is empty",
+                    meta.query_advice(chunk[i], Rotation::cur()),
+                    0x80.expr(),
+                );
+                cb.require_equal(
+                    "branch value is empty",
+                    meta.query_advice(chunk_len[i], Rotation::cur()),
+                    1.expr(),
+                );
+                // The header of the branch is 17 rows above.
+                cb.require_equal(
+                    "len = header_len + payload_len",
+                    meta.query_advice(len[i], Rotation::cur()),
+                    meta.query_advice(len[i], Rotation(-17))
+                        + meta.query_advice(value[i], Rotation(-17)),
+                );
+                cb.require_equal(
+                    "n doesn't change",
+                    meta.query_advice(n[i], Rotation::cur()),
+                    meta.query_advice(n[i], Rotation::prev()),
+                );
+            }
+            let path_count_cur = meta.query_advice(path_count, Rotation::cur());
+            cb.require_equal(
+                "path_count doesn't change",
+                path_count_cur.clone(),
+                meta.query_advice(path_count, Rotation::prev()),
+            );
+            cb.require_equal("exactly one child is in the path", path_count_cur, 1.expr());
+
+            let is_split_cur = meta.query_advice(is_split, Rotation::cur());
+            cb.require_zero(
+                "split branch has the leaf of the key and the drifted node",
+                is_split_cur.clone() * (meta.query_advice(n[0], Rotation::cur()) - 2.expr()),
+            );
+            // Without a drifted node, the divergent node is an extension node whose child
+            // is the sibling in the split branch.
+            let is_not_drifted =
+                is_split_cur * not::expr(meta.query_advice(is_drifted, Rotation::next()));
+            let is_odd_cur = meta.query_advice(is_odd, Rotation::cur());
+            for (column, expected) in [
+                (
+                    drift_key,
+                    meta.query_advice(key_acc, Rotation::cur())
+                        + meta.query_advice(drift_nibble, Rotation::cur())
+                            * meta.query_advice(key_pow, Rotation::cur())
+                            * select::expr(is_odd_cur.clone(), 1.expr(), 16.expr()),
+                ),
+                (
+                    drift_key_len,
+                    meta.query_advice(key_len, Rotation::cur()) - not::expr(is_odd_cur.clone()),
+                ),
+                (drift_is_odd, not::expr(is_odd_cur)),
+                (drift_is_leaf, 0.expr()),
+                (drift_item, meta.query_advice(sibling, Rotation::cur())),
+                (sibling_embedded, 0.expr()),
+            ] {
+                cb.require_zero(
+                    "divergent extension node leads to the sibling in the split branch",
+                    is_not_drifted.clone()
+                        * (meta.query_advice(column, Rotation::cur()) - expected),
+                );
+            }
+
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * kind(meta, RowKind::BranchValue, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("leaf and extension header", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            for i in 0..2 {
+                let payload_len = meta.query_advice(value[i], Rotation::cur());
+                let is_long = meta.query_advice(flag[i], Rotation::cur());
+                let chunk_cur = meta.query_advice(chunk[i], Rotation::cur());
+                let chunk_len_cur = meta.query_advice(chunk_len[i], Rotation::cur());
+                cb.require_equal(
+                    "payload length of a leaf or extension node is a byte",
+                    payload_len.clone(),
+                    meta.query_advice(bytes[i], Rotation::cur()),
+                );
+                cb.require_equal(
+                    "chunk_len = 1 + is_long",
+                    chunk_len_cur,
+                    1.expr() + is_long.clone(),
+                );
+                cb.require_equal(
+                    "chunk = is_long ? [0xf8, payload_len] : [0xc0 + payload_len]",
+                    chunk_cur,
+                    select::expr(
+                        is_long,
+                        0xf8.expr() * r.clone() + payload_len.clone(),
+                        0xc0.expr() + payload_len,
+                    ),
+                );
+            }
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * (kind(meta, RowKind::LeafHeader, Rotation::cur())
+                        + kind(meta, RowKind::ExtensionHeader, Rotation::cur())),
+            )
+        });
+
+        meta.create_gate("extension key", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let chunk_cur = chunk.map(|column| meta.query_advice(column, Rotation::cur()));
+            let chunk_len_cur = chunk_len.map(|column| meta.query_advice(column, Rotation::cur()));
+            let pow_cur = meta.query_advice(pow[0], Rotation::cur());
+            let is_odd_cur = meta.query_advice(is_odd, Rotation::cur());
+            let is_odd_end_cur = meta.query_advice(is_odd_end, Rotation::cur());
+            let nibble_cur = meta.query_advice(nibble, Rotation::cur());
+            let (key_bytes, key_nibbles, is_key_byte) = key_bytes(meta);
+
+            cb.require_equal(
+                "extension key doesn't change",
+                chunk_cur[0].clone(),
+                chunk_cur[1].clone(),
+            );
+            cb.require_equal(
+                "extension key doesn't change",
+                chunk_len_cur[0].clone(),
+                chunk_len_cur[1].clone(),
+            );
+            significant_prefix(&mut cb, &key_bytes, &is_key_byte);
+            let byte_len = sum::expr(&is_key_byte);
+            // The key has an odd number of nibbles, the first one being in its flag,
+            // when it changes the parity of the number of consumed nibbles.
+            let has_odd_len = is_odd_cur.clone() + is_odd_end_cur.clone()
+                - 2.expr() * is_odd_cur.clone() * is_odd_end_cur.clone();
+            cb.require_equal(
+                "key_len_end = key_len - byte_len - (has_odd_len && !is_odd)",
+                meta.query_advice(key_len_end, Rotation::cur()),
+                meta.query_advice(key_len, Rotation::cur())
+                    - byte_len.clone()
+                    - has_odd_len.clone() * not::expr(is_odd_cur.clone()),
+            );
+            cb.require_zero(
+                "extension key has a nibble at least",
+                not::expr(is_key_byte[0].clone()) * not::expr(has_odd_len.clone()),
+            );
+            cb.require_zero(
+                "flag of an even key has no nibble",
+                not::expr(has_odd_len.clone()) * nibble_cur.clone(),
+            );
+            // The chunk is [0x81 + byte_len, flag] ++ key_bytes, where the flag is 0x00
+            // for an even key, or 0x10 + nibble for an odd one, which is a single byte
+            // for a key of 1 nibble.
+            let bytes_rlc = rlc::expr(&key_bytes, &power_of_randomness);
+            let flag_byte = has_odd_len.clone() * (0x10.expr() + nibble_cur.clone());
+            cb.require_zero(
+                "chunk = [0x81 + byte_len, flag] ++ key_bytes",
+                is_key_byte[0].clone() * (chunk_len_cur[0].clone() - byte_len.clone() - 2.expr()),
+            );
+            cb.require_zero(
+                "chunk = [0x81 + byte_len, flag] ++ key_bytes",
+                is_key_byte[0].clone()
+                    * (r2.clone() * chunk_cur[0].clone()
+                        - (0x81.expr() + byte_len) * pow_cur.clone() * r.clone()
+                        - flag_byte.clone() * pow_cur
+                        - r2.clone() * bytes_rlc.clone()),
+            );
+            cb.require_zero(
+                "key of a single nibble is its flag",
+                not::expr(is_key_byte[0].clone()) * (chunk_len_cur[0].clone() - 1.expr()),
+            );
+            cb.require_zero(
+                "key of a single nibble is its flag",
+                not::expr(is_key_byte[0].clone()) * (chunk_cur[0].clone() - flag_byte),
+            );
+            // The bytes of the key are aligned with the ones of the hashed key when the
+            // key ends at an even depth.  Otherwise, the low nibble of each byte of the
+            // key is the high nibble of the next byte of the hashed key.
+            let nibbles_rlc = rlc::expr(&key_nibbles, &power_of_randomness);
+            cb.require_equal(
+                "key_end = key_acc + RLC of the nibbles of the key",
+                meta.query_advice(key_end, Rotation::cur()),
+                meta.query_advice(key_acc, Rotation::cur())
+                    + has_odd_len
+                        * nibble_cur
+                        * meta.query_advice(key_pow, Rotation::cur())
+                        * select::expr(is_odd_cur, 1.expr(), 16.expr())
+                    + meta.query_advice(key_pow_end, Rotation::cur())
+                        * select::expr(
+                            is_odd_end_cur,
+                            (r.clone() - 256.expr()) * nibbles_rlc + 16.expr() * bytes_rlc.clone(),
+                            bytes_rlc,
+                        ),
+            );
+
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * kind(meta, RowKind::ExtensionKey, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("extension child", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let is_divergent_cur = meta.query_advice(is_divergent, Rotation::cur());
+            let is_drifts =
+                is_divergent_cur.clone() + meta.query_advice(is_drifted, Rotation::cur());
+            for i in 0..2 {
+                let value_cur = meta.query_advice(value[i], Rotation::cur());
+                let child_cur = meta.query_advice(child[i], Rotation::cur());
+                let child_embedded_cur = meta.query_advice(child_embedded[i], Rotation::cur());
+                let is_active_cur = meta.query_advice(is_active[i], Rotation::cur());
+                cb.require_equal(
+                    "child is [0xa0] ++ hash",
+                    meta.query_advice(chunk_len[i], Rotation::cur()),
+                    33.expr(),
+                );
+                cb.require_equal(
+                    "child is [0xa0] ++ hash",
+                    r.clone() * meta.query_advice(chunk[i], Rotation::cur()),
+                    0xa0.expr() * meta.query_advice(pow[i], Rotation::cur())
+                        + r.clone() * value_cur.clone(),
+                );
+                // The header of the node is 2 rows above.
+                cb.require_equal(
+                    "len = header_len + payload_len",
+                    meta.query_advice(len[i], Rotation::cur()),
+                    meta.query_advice(len[i], Rotation(-2))
+                        + meta.query_advice(value[i], Rotation(-2)),
+                );
+                let is_next_node = is_active_cur.clone() * not::expr(is_drifts.clone());
+                cb.require_zero(
+                    "child of an extension node in the path is the next node",
+                    is_next_node.clone() * (child_cur.clone() - value_cur),
+                );
+                cb.require_zero(
+                    "child of an extension node in the path is the next node",
+                    is_next_node * child_embedded_cur.clone(),
+                );
+                let is_path_end = is_divergent_cur.clone() * is_active_cur;
+                cb.require_zero(
+                    "divergent node ends the path",
+                    is_path_end.clone() * child_cur,
+                );
+                cb.require_zero(
+                    "divergent node ends the path",
+                    is_path_end * child_embedded_cur,
+                );
+            }
+            for (column, expected) in [
+                (drift_key, meta.query_advice(key_end, Rotation::cur())),
+                (
+                    drift_key_len,
+                    meta.query_advice(key_len_end, Rotation::cur()),
+                ),
+                (drift_is_odd, meta.query_advice(is_odd_end, Rotation::cur())),
+                (drift_is_leaf, 0.expr()),
+                (drift_item, meta.query_advice(value[0], Rotation::cur())),
+            ] {
+                cb.require_zero(
+                    "divergent node drifts with its key and child",
+                    is_drifts.clone() * (meta.query_advice(column, Rotation::cur()) - expected),
+                );
+            }
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * kind(meta, RowKind::ExtensionChild, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("leaf key", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let chunk_cur = chunk.map(|column| meta.query_advice(column, Rotation::cur()));
+            let chunk_len_cur = chunk_len.map(|column| meta.query_advice(column, Rotation::cur()));
+            let pow_cur = meta.query_advice(pow[0], Rotation::cur());
+            let key_len_cur = meta.query_advice(key_len, Rotation::cur());
+            let is_odd_cur = meta.query_advice(is_odd, Rotation::cur());
+            let nibble_cur = meta.query_advice(nibble, Rotation::cur());
+            let key_end_cur = meta.query_advice(key_end, Rotation::cur());
+            let (key_bytes, _, is_key_byte) = key_bytes(meta);
+
+            cb.require_equal(
+                "leaf key doesn't change",
+                chunk_cur[0].clone(),
+                chunk_cur[1].clone(),
+            );
+            cb.require_equal(
+                "leaf key doesn't change",
+                chunk_len_cur[0].clone(),
+                chunk_len_cur[1].clone(),
+            );
+            significant_prefix(&mut cb, &key_bytes, &is_key_byte);
+            cb.require_equal(
+                "leaf key has key_len bytes after its flag",
+                sum::expr(&is_key_byte),
+                key_len_cur.clone(),
+            );
+            cb.require_equal(
+                "leaf key has a header, a flag and key_len bytes",
+                chunk_len_cur[0].clone(),
+                key_len_cur.clone() + 2.expr(),
+            );
+            cb.require_zero(
+                "flag of an even key has no nibble",
+                not::expr(is_odd_cur.clone()) * nibble_cur.clone(),
+            );
+            // The chunk is [0x81 + key_len, flag] ++ key_bytes, where the flag is 0x20 for
+            // an even number of consumed nibbles, or 0x30 + nibble for an odd one.
+            let bytes_rlc = rlc::expr(&key_bytes, &power_of_randomness);
+            cb.require_equal(
+                "chunk = [0x81 + key_len, flag] ++ key_bytes",
+                r2.clone() * chunk_cur[0].clone(),
+                (0x81.expr() + key_len_cur) * pow_cur.clone() * r.clone()
+                    + (0x20.expr() + is_odd_cur.clone() * (0x10.expr() + nibble_cur.clone()))
+                        * pow_cur
+                    + r2.clone() * bytes_rlc.clone(),
+            );
+            cb.require_equal(
+                "key_end = key_acc + RLC of the nibbles of the key",
+                key_end_cur.clone(),
+                meta.query_advice(key_acc, Rotation::cur())
+                    + is_odd_cur * nibble_cur * meta.query_advice(key_pow, Rotation::cur())
+                    + bytes_rlc,
+            );
+            cb.require_zero(
+                "leaf consumes the whole key",
+                meta.query_advice(key_len_end, Rotation::cur()),
+            );
+            cb.require_zero(
+                "leaf consumes the whole key",
+                meta.query_advice(is_odd_end, Rotation::cur()),
+            );
+            cb.require_zero(
+                "leaf of the key has the key",
+                not::expr(role(meta, Rotation::cur()))
+                    * (key_end_cur - meta.query_advice(key_rlc, Rotation::cur())),
+            );
+
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * kind(meta, RowKind::LeafKey, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("leaf value", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let is_divergent_cur = meta.query_advice(is_divergent, Rotation::cur());
+            cb.require_equal(
+                "values of leaves are laid out for the divergent and drifted ones",
+                is_divergent_cur.clone() + meta.query_advice(is_drifted, Rotation::cur()),
+                1.expr(),
+            );
+            for i in 0..2 {
+                // The header of the leaf is 2 rows above.
+                cb.require_equal(
+                    "len = header_len + payload_len",
+                    meta.query_advice(len[i], Rotation::cur()),
+                    meta.query_advice(len[i], Rotation(-2))
+                        + meta.query_advice(value[i], Rotation(-2)),
+                );
+                let is_path_end =
+                    is_divergent_cur.clone() * meta.query_advice(is_active[i], Rotation::cur());
+                cb.require_zero(
+                    "divergent node ends the path",
+                    is_path_end.clone() * meta.query_advice(child[i], Rotation::cur()),
+                );
+                cb.require_zero(
+                    "divergent node ends the path",
+                    is_path_end * meta.query_advice(child_embedded[i], Rotation::cur()),
+                );
+            }
+            for (column, expected) in [
+                (drift_key, meta.query_advice(key_end, Rotation::cur())),
+                (
+                    drift_key_len,
+                    meta.query_advice(key_len_end, Rotation::cur()),
+                ),
+                (drift_is_odd, meta.query_advice(is_odd_end, Rotation::cur())),
+                (drift_is_leaf, 1.expr()),
+                (drift_item, meta.query_advice(chunk[0], Rotation::cur())),
+                (
+                    drift_item_len,
+                    meta.query_advice(chunk_len[0], Rotation::cur()),
+                ),
+            ] {
+                cb.require_equal(
+                    "divergent node drifts with its key and value",
+                    meta.query_advice(column, Rotation::cur()),
+                    expected,
+                );
+            }
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * kind(meta, RowKind::LeafValue, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("node end", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            for i in 0..2 {
+                cb.require_zero(
+                    "embedded node is referenced by its encoding",
+                    meta.query_advice(is_active[i], Rotation::cur())
+                        * meta.query_advice(is_embedded[i], Rotation::cur())
+                        * (meta.query_advice(hash[i], Rotation::cur())
+                            - meta.query_advice(acc[i], Rotation::cur())),
+                );
+            }
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()) * is_node_end(meta))
+        });
+
+        meta.create_gate("account header", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            for i in 0..2 {
+                let payload_len = meta.query_advice(value[i], Rotation::cur());
+                cb.require_equal(
+                    "account headers have 4 bytes",
+                    meta.query_advice(chunk_len[i], Rotation::cur()),
+                    4.expr(),
+                );
+                cb.require_equal(
+                    "chunk = [0xb8, payload_len + 2, 0xf8, payload_len]",
+                    meta.query_advice(chunk[i], Rotation::cur()),
+                    0xb8.expr() * r3.clone()
+                        + (payload_len.clone() + 2.expr()) * r2.clone()
+                        + 0xf8.expr() * r.clone()
+                        + payload_len,
+                );
+            }
+            let address_bytes = bytes[..N_BYTES_ACCOUNT_ADDRESS]
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::cur()))
+                .collect::<Vec<_>>();
+            cb.require_equal(
+                "address = from_bytes(address_bytes)",
+                meta.query_advice(mpt_table.address, Rotation::cur()),
+                from_bytes::expr(&address_bytes),
+            );
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * kind(meta, RowKind::AccountHeader, Rotation::cur()),
+            )
+        });
+
+        // Field of an account: its RLP item and its value before and after the update.
+        let account_field = |meta: &mut ConstraintSystem<F>,
+                             name: &'static str,
+                             row_kind: RowKind,
+                             field_tag: Expression<F>| {
+            meta.create_gate(name, |meta| {
+                let mut cb = BaseConstraintBuilder::default();
+                let values = value.map(|column| meta.query_advice(column, Rotation::cur()));
+                let n_cur = n.map(|column| meta.query_advice(column, Rotation::cur()));
+                let flag_cur = flag.map(|column| meta.query_advice(column, Rotation::cur()));
+                let is_active_cur =
+                    is_active.map(|column| meta.query_advice(column, Rotation::cur()));
+                let default_value = match row_kind {
+                    RowKind::StorageRoot => empty_root_rlc.clone(),
+                    RowKind::CodeHash => empty_code_hash_rlc.clone(),
+                    _ => 0.expr(),
+                };
+
+                for i in 0..2 {
+                    let chunk_cur = meta.query_advice(chunk[i], Rotation::cur());
+                    let chunk_len_cur = meta.query_advice(chunk_len[i], Rotation::cur());
+                    let pow_cur = meta.query_advice(pow[i], Rotation::cur());
+                    match row_kind {
+                        RowKind::Nonce | RowKind::Balance => {
+                            let value_rlc = if row_kind == RowKind::Nonce {
+                                let nonce_bytes = bytes[8 * i..8 * (i + 1)]
+                                    .iter()
+                                    .map(|column| meta.query_advice(*column, Rotation::cur()))
+                                    .collect::<Vec<_>>();
+                                cb.require_equal(
+                                    "nonce = from_bytes(nonce_bytes)",
+                                    values[i].clone(),
+                                    from_bytes::expr(&nonce_bytes),
+                                );
+                                rlc::expr(&nonce_bytes, &power_of_randomness)
+                            } else {
+                                values[i].clone()
+                            };
+                            rlp_integer(
+                                &mut cb,
+                                r.clone(),
+                                chunk_cur,
+                                chunk_len_cur,
+                                pow_cur,
+                                n_cur[i].clone(),
+                                flag_cur[i].clone(),
+                                value_rlc,
+                            );
+                        }
+                        _ => {
+                            cb.require_equal("hash is [0xa0] ++ hash", chunk_len_cur, 33.expr());
+                            cb.require_equal(
+                                "hash is [0xa0] ++ hash",
+                                r.clone() * chunk_cur,
+                                0xa0.expr() * pow_cur + r.clone() * values[i].clone(),
+                            );
+                        }
+                    }
+                    cb.require_zero(
+                        "account missing from the trie has the default values",
+                        not::expr(is_active_cur[i].clone())
+                            * (values[i].clone() - default_value.clone()),
+                    );
+                }
+
+                if matches!(row_kind, RowKind::Nonce | RowKind::Balance) {
+                    let (value_bytes, is_significant) = integer_bytes(meta, row_kind);
+                    if row_kind == RowKind::Balance {
+                        cb.require_equal(
+                            "balance = rlc(balance_bytes)",
+                            values[1].clone(),
+                            rlc::expr(&value_bytes, &power_of_randomness),
+                        );
+                    }
+                    canonical_integer(
+                        &mut cb,
+                        &value_bytes,
+                        &is_significant,
+                        n_cur[1].clone(),
+                        flag_cur[1].clone(),
+                    );
+                }
+
+                let is_updated_cur = meta.query_advice(is_updated, Rotation::cur());
+                let field_tag_cur = meta.query_advice(mpt_table.field_tag, Rotation::cur());
+                let (old_value, new_value) = if row_kind == RowKind::StorageRoot {
+                    (
+                        meta.query_advice(child[0], Rotation::cur()),
+                        meta.query_advice(child[1], Rotation::cur()),
+                    )
+                } else {
+                    (
+                        meta.query_advice(mpt_table.old_value, Rotation::cur()),
+                        meta.query_advice(mpt_table.new_value, Rotation::cur()),
+                    )
+                };
+                cb.condition(is_updated_cur.clone(), |cb| {
+                    cb.require_equal("field of the update", field_tag_cur, field_tag);
+                    cb.require_equal("value before the update", values[0].clone(), old_value);
+                    cb.require_equal("value after the update", values[1].clone(), new_value);
+                });
+                cb.condition(not::expr(is_updated_cur), |cb| {
+                    cb.require_equal(
+                        "fields not in the update don't change",
+                        values[0].clone(),
+                        values[1].clone(),
+                    );
+                    if matches!(row_kind, RowKind::Nonce | RowKind::Balance) {
+                        cb.require_equal(
+                            "fields not in the update don't change",
+                            n_cur[0].clone(),
+                            n_cur[1].clone(),
+                        );
+                        cb.require_equal(
+                            "fields not in the update don't change",
+                            flag_cur[0].clone(),
+                            flag_cur[1].clone(),
+                        );
+                    }
+                });
+
+                if row_kind == RowKind::CodeHash {
+                    cb.require_equal(
+                        "exactly one field of the account is updated",
+                        sum::expr(
+                            (-3..=0)
+                                .map(|rotation| meta.query_advice(is_updated, Rotation(rotation))),
+                        ),
+                        1.expr(),
+                    );
+                    let storage_key = meta.query_advice(mpt_table.storage_key, Rotation::cur());
+                    cb.condition(
+                        not::expr(meta.query_advice(is_updated, Rotation::prev())),
+                        |cb| {
+                            cb.require_zero("storage_key is 0 for account updates", storage_key);
+                        },
+                    );
+                    // An empty account is deleted: the nonce and balance 3 and 2 rows above
+                    // have no byte, and the code hash is the one of the empty code.
+                    let emptiness = meta.query_advice(n[1], Rotation(-3))
+                        + meta.query_advice(n[1], Rotation(-2))
+                        + values[1].clone()
+                        - empty_code_hash_rlc.clone();
+                    cb.require_zero(
+                        "account in the trie after the update is not empty",
+                        is_active_cur[1].clone()
+                            * (1.expr() - meta.query_advice(inverse, Rotation::cur()) * emptiness),
+                    );
+                    for i in 0..2 {
+                        // The leaf header is 6 rows above and the account header 4 rows above.
+                        let len_cur = meta.query_advice(len[i], Rotation::cur());
+                        cb.require_equal(
+                            "len = leaf_header_len + leaf_payload_len",
+                            len_cur.clone(),
+                            meta.query_advice(len[i], Rotation(-6))
+                                + meta.query_advice(value[i], Rotation(-6)),
+                        );
+                        cb.require_equal(
+                            "len = len_at_account_header + account_payload_len",
+                            len_cur,
+                            meta.query_advice(len[i], Rotation(-4))
+                                + meta.query_advice(value[i], Rotation(-4)),
+                        );
+                    }
+                }
+
+                cb.gate(
+                    meta.query_fixed(q_enable, Rotation::cur())
+                        * kind(meta, row_kind, Rotation::cur()),
+                )
+            });
+        };
+        account_field(meta, "nonce", RowKind::Nonce, AccountFieldTag::Nonce.expr());
+        account_field(
+            meta,
+            "balance",
+            RowKind::Balance,
+            AccountFieldTag::Balance.expr(),
+        );
+        account_field(meta, "storage root", RowKind::StorageRoot, 0.expr());
+        account_field(
+            meta,
+            "code hash",
+            RowKind::CodeHash,
+            AccountFieldTag::CodeHash.expr(),
+        );
+
+        meta.create_gate("storage value", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let table_values = [
+                meta.query_advice(mpt_table.old_value, Rotation::cur()),
+                meta.query_advice(mpt_table.new_value, Rotation::cur()),
+            ];
+            for (i, table_value) in table_values.into_iter().enumerate() {
+                let value_cur = meta.query_advice(value[i], Rotation::cur());
+                let chunk_cur = meta.query_advice(chunk[i], Rotation::cur());
+                let chunk_len_cur = meta.query_advice(chunk_len[i], Rotation::cur());
+                let pow_cur = meta.query_advice(pow[i], Rotation::cur());
+                let n_cur = meta.query_advice(n[i], Rotation::cur());
+                let is_short = meta.query_advice(flag[i], Rotation::cur());
+                let is_active_cur = meta.query_advice(is_active[i], Rotation::cur());
+                cb.require_equal(
+                    "storage value of the update",
+                    value_cur.clone(),
+                    table_value,
+                );
+                cb.require_zero(
+                    "slot missing from the trie is 0",
+                    not::expr(is_active_cur.clone()) * value_cur.clone(),
+                );
+                if i == 1 {
+                    let (value_bytes, is_significant) = integer_bytes(meta, RowKind::StorageValue);
+                    cb.require_equal(
+                        "value = rlc(value_bytes)",
+                        value_cur.clone(),
+                        rlc::expr(&value_bytes, &power_of_randomness),
+                    );
+                    cb.require_zero(
+                        "slot in the trie after the update is not 0",
+                        is_active_cur * not::expr(is_significant[0].clone()),
+                    );
+                    canonical_integer(
+                        &mut cb,
+                        &value_bytes,
+                        &is_significant,
+                        n_cur.clone(),
+                        is_short.clone(),
+                    );
+                }
+                // The value is stored as the RLP string of its RLP encoding, which is
+                // [0x81 + n, 0x80 + n] ++ value_bytes unless it's a single byte below 0x80.
+                cb.condition(is_short.clone(), |cb| {
+                    cb.require_equal(
+                        "short value is a single byte",
+                        chunk_cur.clone(),
+                        value_cur.clone(),
+                    );
+                    cb.require_equal(
+                        "short value is a single byte",
+                        chunk_len_cur.clone(),
+                        1.expr(),
+                    );
+                });
+                cb.condition(not::expr(is_short), |cb| {
+                    cb.require_equal(
+                        "chunk = [0x81 + n, 0x80 + n] ++ value_bytes",
+                        chunk_len_cur,
+                        n_cur.clone() + 2.expr(),
+                    );
+                    cb.require_equal(
+                        "chunk = [0x81 + n, 0x80 + n] ++ value_bytes",
+                        r2.clone() * chunk_cur,
+                        (0x81.expr() + n_cur.clone()) * pow_cur.clone() * r.clone()
+                            + (0x80.expr() + n_cur) * pow_cur
+                            + r2.clone() * value_cur,
+                    );
+                });
+                // The leaf header is 2 rows above.
+                cb.require_equal(
+                    "len = leaf_header_len + leaf_payload_len",
+                    meta.query_advice(len[i], Rotation::cur()),
+                    meta.query_advice(len[i], Rotation(-2))
+                        + meta.query_advice(value[i], Rotation(-2)),
+                );
+            }
+            cb.gate(
+                meta.query_fixed(q_enable, Rotation::cur())
+                    * kind(meta, RowKind::StorageValue, Rotation::cur()),
+            )
+        });
+
+        meta.create_gate("power of randomness table", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let q_pow_prev = meta.query_fixed(q_pow, Rotation::prev());
+            let pow_value_cur = meta.query_advice(pow_value, Rotation::cur());
+            let pow_value_prev = meta.query_advice(pow_value, Rotation::prev());
+            cb.require_equal(
+                "pow_value := is_first ? 1 : pow_value_prev * r",
+                pow_value_cur,
+                select::expr(q_pow_prev, pow_value_prev * r.clone(), 1.expr()),
+            );
+            cb.gate(meta.query_fixed(q_pow, Rotation::cur()))
+        });
+
+        for (byte, hi_nibble) in bytes.into_iter().zip(nibbles) {
+            meta.lookup_any("mpt byte in u8 range with its high nibble", |meta| {
+                let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+                vec![
+                    (
+                        q_enable.clone() * meta.query_advice(byte, Rotation::cur()),
+                        meta.query_fixed(u8_table, Rotation::cur()),
+                    ),
+                    (
+                        q_enable * meta.query_advice(hi_nibble, Rotation::cur()),
+                        meta.query_fixed(hi_nibble_table, Rotation::cur()),
+                    ),
+                ]
+            });
+        }
+        meta.lookup_any("mpt nibble in [0, 16)", |meta| {
+            let q_enable = meta.query_fixed(q_enable, Rotation::cur());
+            let nibble_cur = meta.query_advice(nibble, Rotation::cur());
+            vec![
+                (
+                    q_enable.clone() * 16.expr() * nibble_cur.clone(),
+                    meta.query_fixed(u8_table, Rotation::cur()),
+                ),
+                (
+                    q_enable * nibble_cur,
+                    meta.query_fixed(hi_nibble_table, Rotation::cur()),
+                ),
+            ]
+        });
+
+        let power_of_randomness_lookup =
+            |meta: &mut VirtualCells<F>,
+             enable: Expression<F>,
+             exponent: Expression<F>,
+             value: Expression<F>| {
+                vec![
+                    (enable.clone(), meta.query_fixed(q_pow, Rotation::cur())),
+                    (
+                        enable.clone() * exponent,
+                        meta.query_fixed(pow_index, Rotation::cur()),
+                    ),
+                    (
+                        enable * value,
+                        meta.query_advice(pow_value, Rotation::cur()),
+                    ),
+                ]
+            };
+        for i in 0..2 {
+            meta.lookup_any("mpt pow = r^chunk_len", |meta| {
+                let enable = q_enable_not_padding(meta);
+                let exponent = meta.query_advice(chunk_len[i], Rotation::cur());
+                let value = meta.query_advice(pow[i], Rotation::cur());
+                power_of_randomness_lookup(meta, enable, exponent, value)
+            });
+        }
+        meta.lookup_any("mpt key_pow = r^(key_len - 1 + is_odd)", |meta| {
+            let enable = q_enable_not_padding(meta);
+            let exponent = meta.query_advice(key_len, Rotation::cur()) - 1.expr()
+                + meta.query_advice(is_odd, Rotation::cur());
+            let value = meta.query_advice(key_pow, Rotation::cur());
+            power_of_randomness_lookup(meta, enable, exponent, value)
+        });
+        meta.lookup_any("mpt key_pow_end = r^key_len_end", |meta| {
+            let enable = meta.query_fixed(q_enable, Rotation::cur())
+                * kind(meta, RowKind::ExtensionKey, Rotation::cur());
+            let exponent = meta.query_advice(key_len_end, Rotation::cur());
+            let value = meta.query_advice(key_pow_end, Rotation::cur());
+            power_of_randomness_lookup(meta, enable, exponent, value)
+        });
+
+        let u8_lookup =
+            |meta: &mut ConstraintSystem<F>,
+             name: &'static str,
+             expression: &dyn Fn(&mut VirtualCells<F>) -> Expression<F>| {
+                meta.lookup_any(name, |meta| {
+                    vec![(
+                        meta.query_fixed(q_enable, Rotation::cur()) * expression(meta),
+                        meta.query_fixed(u8_table, Rotation::cur()),
+                    )]
+                });
+            };
+        for i in 0..2 {
+            u8_lookup(meta, "mpt embedded child has less than 32 bytes", &|meta| {
+                kind(meta, RowKind::BranchChild, Rotation::cur())
+                    * meta.query_advice(flag[i], Rotation::cur())
+                    * (31.expr() - meta.query_advice(chunk_len[i], Rotation::cur()))
+            });
+            u8_lookup(meta, "mpt branch header is canonical", &|meta| {
+                kind(meta, RowKind::BranchHeader, Rotation::cur())
+                    * not::expr(meta.query_advice(flag[i], Rotation::cur()))
+                    * (meta.query_advice(bytes[2 * i], Rotation::cur()) - 56.expr())
+            });
+            u8_lookup(
+                meta,
+                "mpt branch in the path has two children at least",
+                &|meta| {
+                    kind(meta, RowKind::BranchValue, Rotation::cur())
+                        * not::expr(meta.query_advice(is_split, Rotation::cur()))
+                        * (meta.query_advice(n[i], Rotation::cur()) - 2.expr())
+                },
+            );
+            u8_lookup(
+                meta,
+                "mpt leaf and extension headers are canonical",
+                &|meta| {
+                    let payload_len = meta.query_advice(value[i], Rotation::cur());
+                    (kind(meta, RowKind::LeafHeader, Rotation::cur())
+                        + kind(meta, RowKind::ExtensionHeader, Rotation::cur()))
+                        * select::expr(
+                            meta.query_advice(flag[i], Rotation::cur()),
+                            payload_len.clone() - 56.expr(),
+                            55.expr() - payload_len,
+                        )
+                },
+            );
+            u8_lookup(meta, "mpt embedded node has less than 32 bytes", &|meta| {
+                is_node_end(meta)
+                    * meta.query_advice(is_active[i], Rotation::cur())
+                    * meta.query_advice(is_embedded[i], Rotation::cur())
+                    * (31.expr() - meta.query_advice(len[i], Rotation::cur()))
+            });
+            u8_lookup(meta, "mpt hashed leaf has 32 bytes at least", &|meta| {
+                is_leaf_end(meta)
+                    * meta.query_advice(is_active[i], Rotation::cur())
+                    * not::expr(meta.query_advice(is_embedded[i], Rotation::cur()))
+                    * (meta.query_advice(len[i], Rotation::cur()) - 32.expr())
+            });
+        }
+
+        for row_kind in [RowKind::Nonce, RowKind::Balance, RowKind::StorageValue] {
+            let enable = move |meta: &mut VirtualCells<F>| {
+                meta.query_fixed(q_enable, Rotation::cur()) * kind(meta, row_kind, Rotation::cur())
+            };
+            meta.lookup_any("mpt most significant byte of an integer is not 0", |meta| {
+                let (value_bytes, is_significant) = integer_bytes(meta, row_kind);
+                let most_significant_byte =
+                    sum::expr(value_bytes.iter().enumerate().map(|(k, byte)| {
+                        let is_last = is_significant[k].clone()
+                            - is_significant
+                                .get(k + 1)
+                                .cloned()
+                                .unwrap_or_else(|| 0.expr());
+                        is_last * byte.clone()
+                    }));
+                // The integer is not 0 iff its first byte is significant.
+                vec![(
+                    enable(meta) * (most_significant_byte - is_significant[0].clone()),
+                    meta.query_fixed(u8_table, Rotation::cur()),
+                )]
+            });
+            meta.lookup_any("mpt short integer is below 0x80", |meta| {
+                let (value_bytes, _) = integer_bytes(meta, row_kind);
+                vec![(
+                    enable(meta)
+                        * meta.query_advice(flag[1], Rotation::cur())
+                        * (0x7f.expr() - value_bytes[0].clone()),
+                    meta.query_fixed(u8_table, Rotation::cur()),
+                )]
+            });
+            meta.lookup_any("mpt long integer of 1 byte is at least 0x80", |meta| {
+                let (value_bytes, is_significant) = integer_bytes(meta, row_kind);
+                // A short integer has exactly 1 significant byte.
+                let is_long_of_1_byte = is_significant[0].clone()
+                    - is_significant[1].clone()
+                    - meta.query_advice(flag[1], Rotation::cur());
+                vec![(
+                    enable(meta) * is_long_of_1_byte * (value_bytes[0].clone() - 0x80.expr()),
+                    meta.query_fixed(u8_table, Rotation::cur()),
+                )]
+            });
+        }
+
+        let keccak_lookup = |meta: &mut VirtualCells<F>,
+                             enable: Expression<F>,
+                             input_rlc: Expression<F>,
+                             input_len: Expression<F>,
+                             output_rlc: Expression<F>| {
+            vec![
+                (
+                    enable.clone(),
+                    meta.query_advice(keccak_table.is_enabled, Rotation::cur()),
+                ),
+                (
+                    enable.clone() * input_rlc,
+                    meta.query_advice(keccak_table.input_rlc, Rotation::cur()),
+                ),
+                (
+                    enable.clone() * input_len,
+                    meta.query_advice(keccak_table.input_len, Rotation::cur()),
+                ),
+                (
+                    enable * output_rlc,
+                    meta.query_advice(keccak_table.output_rlc, Rotation::cur()),
+                ),
+            ]
+        };
+        for i in 0..2 {
+            meta.lookup_any("mpt node hash", |meta| {
+                let enable = meta.query_fixed(q_enable, Rotation::cur())
+                    * is_node_end(meta)
+                    * meta.query_advice(is_active[i], Rotation::cur())
+                    * not::expr(meta.query_advice(is_embedded[i], Rotation::cur()));
+                let input_rlc = meta.query_advice(acc[i], Rotation::cur());
+                let input_len = meta.query_advice(len[i], Rotation::cur());
+                let output_rlc = meta.query_advice(hash[i], Rotation::cur());
+                keccak_lookup(meta, enable, input_rlc, input_len, output_rlc)
+            });
+        }
+        meta.lookup_any("mpt account key is the hash of the address", |meta| {
+            let enable = meta.query_fixed(q_enable, Rotation::cur())
+                * kind(meta, RowKind::AccountHeader, Rotation::cur());
+            let address_bytes = bytes[..N_BYTES_ACCOUNT_ADDRESS]
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::cur()))
+                .collect::<Vec<_>>();
+            let input_rlc = rlc::expr(&address_bytes, &power_of_randomness);
+            let output_rlc = meta.query_advice(key_rlc, Rotation::cur());
+            keccak_lookup(
+                meta,
+                enable,
+                input_rlc,
+                N_BYTES_ACCOUNT_ADDRESS.expr(),
+                output_rlc,
+            )
+        });
+        meta.lookup_any("mpt storage key is the hash of the storage key", |meta| {
+            let enable = meta.query_fixed(q_enable, Rotation::cur())
+                * kind(meta, RowKind::CodeHash, Rotation::cur())
+                * meta.query_advice(is_updated, Rotation::prev());
+            let input_rlc = meta.query_advice(mpt_table.storage_key, Rotation::cur());
+            let output_rlc = meta.query_advice(key_rlc, Rotation::next());
+            keccak_lookup(meta, enable, input_rlc, 32.expr(), output_rlc)
+        });
+
+        Self {
+            minimum_rows: meta.minimum_rows(),
+            q_enable,
+            q_first,
+            kinds,
+            is_update_start,
+            in_storage,
+            is_active,
+            hash,
+            is_embedded,
+            child,
+            child_embedded,
+            is_divergent,
+            is_split,
+            is_drifted,
+            key_rlc,
+            key_acc,
+            key_len,
+            is_odd,
+            key_pow,
+            nibble,
+            key_end,
+            key_len_end,
+            is_odd_end,
+            key_pow_end,
+            sibling,
+            sibling_embedded,
+            drift_nibble,
+            drift_key,
+            drift_key_len,
+            drift_is_odd,
+            drift_is_leaf,
+            drift_item,
+            drift_item_len,
+            slot,
+            is_empty,
+            is_path,
+            path_count,
+            is_updated,
+            value,
+            n,
+            flag,
+            chunk,
+            chunk_len,
+            pow,
+            acc,
+            len,
+            bytes,
+            nibbles,
+            is_significant,
+            inverse,
+            u8_table,
+            hi_nibble_table,
+            q_pow,
+            pow_index,
+            pow_value,
+            mpt_table,
+            keccak_table,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Load the fixed u8 table and the table of powers of the randomness.
+    pub(crate) fn load(&self, layouter: &mut impl Layouter<F>, randomness: F) -> Result<(), Error> {
+        layouter.assign_region(
+            || "mpt u8 table",
+            |mut region| {
+                for byte in 0..256 {
+                    for (name, column, value) in [
+                        ("u8", self.u8_table, byte),
+                        ("high nibble", self.hi_nibble_table, byte >> 4),
+                    ] {
+                        region.assign_fixed(
+                            || format!("assign {} in {} table", byte, name),
+                            column,
+                            byte,
+                            || Ok(F::from(value as u64)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        layouter.assign_region(
+            || "mpt power of randomness table",
+            |mut region| {
+                // The first row is all zero, for the lookups of padding rows.
+                let mut pow_value = F::one();
+                for offset in 0..=MAX_CHUNK_LEN + 1 {
+                    let (q_pow, pow_index, value) = match offset {
+                        0 => (F::zero(), F::zero(), F::zero()),
+                        _ => (F::one(), F::from((offset - 1) as u64), pow_value),
+                    };
+                    for (name, column, value) in [
+                        ("q_pow", self.q_pow, q_pow),
+                        ("pow_index", self.pow_index, pow_index),
+                    ] {
+                        region.assign_fixed(
+                            || format!("assign {} {}", name, offset),
+                            column,
+                            offset,
+                            || Ok(value),
+                        )?;
+                    }
+                    region.assign_advice(
+                        || format!("assign pow_value {}", offset),
+                        self.pow_value,
+                        offset,
+                        || Ok(value),
+                    )?;
+                    if offset > 0 {
+                        pow_value *= randomness;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Assign the rows of the updates, followed by padding up to `size`.
+    /// Return the cells of the old_root of the first update and the new_root
+    /// of the last one, if there are updates.
+    fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        size: usize,
+        rows: &[MptRow<F>],
+        randomness: F,
+    ) -> Result<Option<[AssignedCell<F, F>; 2]>, Error> {
+        // Subtract the unusable rows from the size
+        let last_row_offset = size - self.minimum_rows + 1;
+        if rows.len() >= last_row_offset {
+            error!(
+                "mpt circuit needs {} rows but only {} are available",
+                rows.len() + 1,
+                last_row_offset
+            );
+            return Err(Error::Synthesis);
+        }
+
+        layouter.assign_region(
+            || "assign mpt",
+            |mut region| {
+                let padding = MptRow::default();
+                let mut acc = [F::zero(); 2];
+                let mut len = [0; 2];
+                let mut old_root = None;
+                let mut new_root = None;
+                for offset in 0..=last_row_offset {
+                    let row = rows.get(offset).unwrap_or(&padding);
+                    let mut chunk = [F::zero(); 2];
+                    let mut pow = [F::zero(); 2];
+                    if row.kind != RowKind::Padding {
+                        if matches!(
+                            row.kind,
+                            RowKind::BranchHeader | RowKind::ExtensionHeader | RowKind::LeafHeader
+                        ) {
+                            acc = [F::zero(); 2];
+                            len = [0; 2];
+                        }
+                        for i in 0..2 {
+                            chunk[i] = rlc::value(row.chunk[i].iter().rev(), randomness);
+                            pow[i] = randomness.pow(&[row.chunk[i].len() as u64, 0, 0, 0]);
+                            acc[i] = acc[i] * pow[i] + chunk[i];
+                            len[i] += row.chunk[i].len();
+                        }
+                    }
+                    let [old_root_cell, new_root_cell] = self.set_row(
+                        &mut region,
+                        offset,
+                        offset < last_row_offset,
+                        row,
+                        [chunk, pow, acc, len.map(|len| F::from(len as u64))],
+                    )?;
+                    if offset == 0 {
+                        old_root = Some(old_root_cell);
+                    }
+                    if offset + 1 == rows.len() {
+                        new_root = Some(new_root_cell);
+                    }
+                }
+                Ok(old_root
+                    .zip(new_root)
+                    .map(|(old_root, new_root)| [old_root, new_root]))
+            },
+        )
+    }
+
+    fn set_row(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        enable: bool,
+        row: &MptRow<F>,
+        [chunk, pow, acc, len]: [[F; 2]; 4],
+    ) -> Result<[AssignedCell<F, F>; 2], Error> {
+        for (name, column, value) in [
+            ("q_enable", self.q_enable, enable),
+            ("q_first", self.q_first, offset == 0),
+        ] {
+            region.assign_fixed(
+                || format!("assign {} {}", name, offset),
+                column,
+                offset,
+                || Ok(F::from(value as u64)),
+            )?;
+        }
+
+        let mut advices = vec![
+            (
+                "is_update_start",
+                self.is_update_start,
+                F::from(row.is_update_start as u64),
+            ),
+            (
+                "in_storage",
+                self.in_storage,
+                F::from(row.in_storage as u64),
+            ),
+            (
+                "is_divergent",
+                self.is_divergent,
+                F::from(row.is_divergent as u64),
+            ),
+            ("is_split", self.is_split, F::from(row.is_split as u64)),
+            (
+                "is_drifted",
+                self.is_drifted,
+                F::from(row.is_drifted as u64),
+            ),
+            ("key_rlc", self.key_rlc, row.key_rlc),
+            ("key_acc", self.key_acc, row.key_acc),
+            ("key_len", self.key_len, F::from(row.key_len as u64)),
+            ("is_odd", self.is_odd, F::from(row.is_odd as u64)),
+            ("key_pow", self.key_pow, row.key_pow),
+            ("nibble", self.nibble, F::from(row.nibble as u64)),
+            ("key_end", self.key_end, row.key_end),
+            (
+                "key_len_end",
+                self.key_len_end,
+                F::from(row.key_len_end as u64),
+            ),
+            (
+                "is_odd_end",
+                self.is_odd_end,
+                F::from(row.is_odd_end as u64),
+            ),
+            ("key_pow_end", self.key_pow_end, row.key_pow_end),
+            ("sibling", self.sibling, row.sibling),
+            (
+                "sibling_embedded",
+                self.sibling_embedded,
+                F::from(row.sibling_embedded as u64),
+            ),
+            (
+                "drift_nibble",
+                self.drift_nibble,
+                F::from(row.drift_nibble as u64),
+            ),
+            ("drift_key", self.drift_key, row.drift.key),
+            (
+                "drift_key_len",
+                self.drift_key_len,
+                F::from(row.drift.key_len as u64),
+            ),
+            (
+                "drift_is_odd",
+                self.drift_is_odd,
+                F::from(row.drift.is_odd as u64),
+            ),
+            (
+                "drift_is_leaf",
+                self.drift_is_leaf,
+                F::from(row.drift.is_leaf as u64),
+            ),
+            ("drift_item", self.drift_item, row.drift.item),
+            (
+                "drift_item_len",
+                self.drift_item_len,
+                F::from(row.drift.item_len as u64),
+            ),
+            ("slot", self.slot, F::from(row.slot as u64)),
+            ("is_path", self.is_path, F::from(row.is_path as u64)),
+            (
+                "path_count",
+                self.path_count,
+                F::from(row.path_count as u64),
+            ),
+            (
+                "is_updated",
+                self.is_updated,
+                F::from(row.is_updated as u64),
+            ),
+            ("inverse", self.inverse, row.inverse),
+        ];
+        for (column, kind) in self.kinds.iter().zip(0..RowKind::COUNT) {
+            advices.push(("kind", *column, F::from((row.kind as usize == kind) as u64)));
+        }
+        for i in 0..2 {
+            advices.extend([
+                (
+                    "is_active",
+                    self.is_active[i],
+                    F::from(row.is_active[i] as u64),
+                ),
+                ("hash", self.hash[i], row.hash[i]),
+                (
+                    "is_embedded",
+                    self.is_embedded[i],
+                    F::from(row.is_embedded[i] as u64),
+                ),
+                ("child", self.child[i], row.child[i]),
+                (
+                    "child_embedded",
+                    self.child_embedded[i],
+                    F::from(row.child_embedded[i] as u64),
+                ),
+                (
+                    "is_empty",
+                    self.is_empty[i],
+                    F::from(row.is_empty[i] as u64),
+                ),
+                ("value", self.value[i], row.value[i]),
+                ("n", self.n[i], F::from(row.n[i] as u64)),
+                ("flag", self.flag[i], F::from(row.flag[i] as u64)),
+                ("chunk", self.chunk[i], chunk[i]),
+                (
+                    "chunk_len",
+                    self.chunk_len[i],
+                    F::from(row.chunk[i].len() as u64),
+                ),
+                ("pow", self.pow[i], pow[i]),
+                ("acc", self.acc[i], acc[i]),
+                ("len", self.len[i], len[i]),
+            ]);
+        }
+        for ((byte_column, nibble_column), byte) in
+            self.bytes.iter().zip(&self.nibbles).zip(row.bytes)
+        {
+            advices.push(("bytes", *byte_column, F::from(byte as u64)));
+            advices.push(("nibbles", *nibble_column, F::from((byte >> 4) as u64)));
+        }
+        for (column, is_significant) in self.is_significant.iter().zip(row.is_significant) {
+            advices.push(("is_significant", *column, F::from(is_significant as u64)));
+        }
+        for (column, value) in self.mpt_table.columns().into_iter().zip(row.table) {
+            advices.push(("mpt table", column, value));
+        }
+
+        let mut roots = vec![];
+        for (name, column, value) in advices {
+            let cell = region.assign_advice(
+                || format!("assign {} {}", name, offset),
+                column,
+                offset,
+                || Ok(value),
+            )?;
+            if column == self.mpt_table.old_root || column == self.mpt_table.new_root {
+                roots.push(cell);
+            }
+        }
+        Ok([roots[0].clone(), roots[1].clone()])
+    }
+}
+
+/// Constrain `chunk` to be the RLP encoding of an integer of `n` bytes
+/// with RLC `value_rlc`, which is the integer itself when `is_short`.  The
+/// encoding is canonical when the integer is also constrained by
+/// [`canonical_integer`].
+#[allow(clippy::too_many_arguments)]
+fn rlp_integer<F: Field>(
+    cb: &mut BaseConstraintBuilder<F>,
+    r: Expression<F>,
+    chunk: Expression<F>,
+    chunk_len: Expression<F>,
+    pow: Expression<F>,
+    n: Expression<F>,
+    is_short: Expression<F>,
+    value_rlc: Expression<F>,
+) {
+    cb.condition(is_short.clone(), |cb| {
+        cb.require_equal(
+            "short integer is a single byte",
+            chunk.clone(),
+            value_rlc.clone(),
+        );
+        cb.require_equal(
+            "short integer is a single byte",
+            chunk_len.clone(),
+            1.expr(),
+        );
+    });
+    cb.condition(not::expr(is_short), |cb| {
+        cb.require_equal(
+            "chunk = [0x80 + n] ++ value_bytes",
+            chunk_len,
+            n.clone() + 1.expr(),
+        );
+        cb.require_equal(
+            "chunk = [0x80 + n] ++ value_bytes",
+            r.clone() * chunk,
+            (0x80.expr() + n) * pow + r * value_rlc,
+        );
+    });
+}
+
+/// Constrain `n` and `is_short` to be the ones of the canonical RLP encoding
+/// of the integer with little-endian bytes `value_bytes`: `is_significant`
+/// is 1 for the bytes up to its most significant nonzero one, and 0 above.
+/// The lookups "mpt most significant byte of an integer is not 0", "mpt
+/// short integer is below 0x80" and "mpt long integer of 1 byte is at least
+/// 0x80" complete the constraints.
+fn canonical_integer<F: Field>(
+    cb: &mut BaseConstraintBuilder<F>,
+    value_bytes: &[Expression<F>],
+    is_significant: &[Expression<F>],
+    n: Expression<F>,
+    is_short: Expression<F>,
+) {
+    significant_prefix(cb, value_bytes, is_significant);
+    let byte_len = sum::expr(is_significant);
+    cb.condition(is_short.clone(), |cb| {
+        cb.require_equal("short integer has 1 byte", byte_len.clone(), 1.expr());
+    });
+    cb.condition(not::expr(is_short), |cb| {
+        cb.require_equal("n is the minimal number of bytes", n, byte_len);
+    });
+}
+
+/// Constrain `is_significant` to be 1 for a prefix of `bytes` and 0 for the
+/// bytes after it, which are 0.
+fn significant_prefix<F: Field>(
+    cb: &mut BaseConstraintBuilder<F>,
+    bytes: &[Expression<F>],
+    is_significant: &[Expression<F>],
+) {
+    for (k, (byte, is_significant_cur)) in bytes.iter().zip(is_significant).enumerate() {
+        cb.require_boolean("is_significant is boolean", is_significant_cur.clone());
+        cb.require_zero(
+            "bytes after the significant ones are 0",
+            byte.clone() * not::expr(is_significant_cur.clone()),
+        );
+        if let Some(is_significant_next) = is_significant.get(k + 1) {
+            cb.require_zero(
+                "significant bytes are a prefix of the bytes",
+                is_significant_next.clone() * not::expr(is_significant_cur.clone()),
+            );
+        }
+    }
+}
+
+/// RLC of the constant hash `hash`, as computed by [`hash_rlc`]
+fn constant_hash_rlc<F: Field>(hash: H256, power_of_randomness: &[Expression<F>]) -> Expression<F> {
+    let bytes = hash
+        .as_bytes()
+        .iter()
+        .rev()
+        .map(|byte| Expression::Constant(F::from(*byte as u64)))
+        .collect::<Vec<_>>();
+    rlc::expr(&bytes, power_of_randomness)
+}
+
+fn hash_rlc<F: Field>(hash: H256, randomness: F) -> F {
+    rlc::value(hash.as_bytes().iter().rev(), randomness)
+}
+
+fn word_rlc<F: Field>(value: Word, randomness: F) -> F {
+    rlc::value(&value.to_le_bytes(), randomness)
+}
+
+/// Whether each little-endian byte of `value` is in its minimal encoding
+fn significant_bytes(value: U256) -> [bool; N_BYTES_WORD] {
+    let mut is_significant = [false; N_BYTES_WORD];
+    is_significant[..minimal_be_bytes(value).len()].fill(true);
+    is_significant
+}
+
+/// Whether the RLP encoding of an integer is the integer itself
+fn is_short(value: U256) -> bool {
+    !value.is_zero() && value < U256::from(0x80)
+}
+
+/// RLC of the reference to a node in its parent, its hash or its encoding
+/// when embedded, and whether it's embedded
+fn ref_rlc<F: Field>(node_ref: &NodeRef, randomness: F) -> (F, bool) {
+    match node_ref {
+        NodeRef::Empty => (F::zero(), false),
+        NodeRef::Hash(hash) => (hash_rlc(*hash, randomness), false),
+        NodeRef::Embedded(node) => (rlc::value(node.encode().iter().rev(), randomness), true),
+    }
+}
+
+/// Reference to `node` in its parent
+fn node_ref(node: &TrieNode) -> NodeRef {
+    match node.encode().len() {
+        len if len < 32 => NodeRef::Embedded(Box::new(node.clone())),
+        _ => NodeRef::Hash(node.hash()),
+    }
+}
+
+/// RLC of the reference to `node`, which is its hash at the root of a trie
+fn node_rlc<F: Field>(node: &TrieNode, is_root: bool, randomness: F) -> (F, bool) {
+    match is_root {
+        true => (hash_rlc(node.hash(), randomness), false),
+        false => ref_rlc(&node_ref(node), randomness),
+    }
+}
+
+/// Value of a child of a branch: its hash, or its encoding without its list
+/// header when embedded
+fn child_value<F: Field>(child: &NodeRef, randomness: F) -> F {
+    match child {
+        NodeRef::Empty => F::zero(),
+        NodeRef::Hash(hash) => hash_rlc(*hash, randomness),
+        NodeRef::Embedded(node) => rlc::value(node.encode()[1..].iter().rev(), randomness),
+    }
+}
+
+/// RLC of the first nibbles of a hashed key, weighted as in the RLC of the
+/// hashed key
+fn nibbles_rlc<F: Field>(nibbles: &[u8], randomness: F) -> F {
+    nibbles
+        .iter()
+        .enumerate()
+        .fold(F::zero(), |acc, (position, nibble)| {
+            let weight = randomness.pow(&[(31 - position / 2) as u64, 0, 0, 0]);
+            let shift = if position % 2 == 0 { 16 } else { 1 };
+            acc + F::from(*nibble as u64 * shift) * weight
+        })
+}
+
+/// Number of bytes of the key left to a leaf after `depth` nibbles
+fn key_len_at(depth: usize) -> usize {
+    32 - (depth + 1) / 2
+}
+
+/// Bytes of the hex-prefix encoded key of a leaf or an extension node after
+/// its flag, from the last one, and whether each of them is in the key
+fn compact_key_bytes(key: &[u8], is_leaf: bool) -> ([u8; N_BYTES_WORD], [bool; N_BYTES_WORD]) {
+    let mut bytes = [0; N_BYTES_WORD];
+    let mut is_key_byte = [false; N_BYTES_WORD];
+    for (k, byte) in compact_key(key, is_leaf)[1..].iter().rev().enumerate() {
+        bytes[k] = *byte;
+        is_key_byte[k] = true;
+    }
+    (bytes, is_key_byte)
+}
+
+fn leaf_value(node: &TrieNode) -> &[u8] {
+    match node {
+        TrieNode::Leaf { value, .. } => value,
+        _ => unreachable!("path ends with the leaf of the key"),
+    }
+}
+
+/// Role of a node in the path of a key, see the module documentation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum NodeRole {
+    /// Branch or extension node in the path of the key in both tries
+    Common,
+    /// Leaf or extension node of other keys where the path of the key ends
+    Divergent,
+    /// Node replacing the divergent node in the tries with the key
+    Split,
+    /// Divergent node after the split, as a child of the split branch
+    Drifted,
+    /// Leaf of the key
+    Key,
+}
+
+/// Node laid out in the path of a key, before and after an update
+#[derive(Clone, Debug)]
+struct PathNode {
+    role: NodeRole,
+    nodes: [TrieNode; 2],
+    /// Whether the node is in each trie
+    is_active: [bool; 2],
+    /// Nibbles of the key consumed by the ancestors of the node
+    prefix: Vec<u8>,
+}
+
+/// Node where the path of a key ends in a trie, after the nodes in the path of
+/// the key in both tries
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PathEnd {
+    /// Empty trie or empty child of a branch
+    Empty,
+    /// Leaf of the key
+    Key,
+    /// Leaf or extension node of other keys
+    Divergent,
+    /// Node in the path of the key, which only is in this trie
+    Split,
+}
+
+fn path_end(rest: &[TrieNode], key_rest: &[u8]) -> PathEnd {
+    match rest.first() {
+        None => PathEnd::Empty,
+        Some(TrieNode::Leaf { key, .. }) if key == key_rest => PathEnd::Key,
+        Some(TrieNode::Leaf { .. }) => PathEnd::Divergent,
+        Some(TrieNode::Extension { key, .. }) if !key_rest.starts_with(key) => PathEnd::Divergent,
+        Some(_) => PathEnd::Split,
+    }
+}
+
+/// Nodes laid out for the hashed key `key` in the tries with roots `roots`
+/// before and after an update, ending with the leaf of the key, whose value
+/// is `default_value` in the tries without the key.
+fn key_path(
+    trie: &PartialTrie,
+    roots: [H256; 2],
+    key: H256,
+    default_value: &[u8],
+) -> Result<Vec<PathNode>, TrieError> {
+    let nibbles = key_nibbles(key);
+    let paths = [trie.path(roots[0], key)?, trie.path(roots[1], key)?];
+    let mut path = vec![];
+    let mut depth = 0;
+    while let (Some(old_node), Some(new_node)) =
+        (paths[0].get(path.len()), paths[1].get(path.len()))
+    {
+        let consumed = match (old_node, new_node) {
+            (TrieNode::Branch(_), TrieNode::Branch(_)) => 1,
+            (
+                TrieNode::Extension { key: old_key, .. },
+                TrieNode::Extension { key: new_key, .. },
+            ) if old_key == new_key && nibbles[depth..].starts_with(old_key) => old_key.len(),
+            _ => break,
+        };
+        path.push(PathNode {
+            role: NodeRole::Common,
+            nodes: [old_node.clone(), new_node.clone()],
+            is_active: [true; 2],
+            prefix: nibbles[..depth].to_vec(),
+        });
+        depth += consumed;
+    }
+
+    let (prefix, key_rest) = nibbles.split_at(depth);
+    let rests = [&paths[0][path.len()..], &paths[1][path.len()..]];
+    let ends = rests.map(|rest| path_end(rest, key_rest));
+    match ends {
+        [PathEnd::Empty | PathEnd::Key, PathEnd::Empty | PathEnd::Key] => {
+            let is_active = ends.map(|end| end == PathEnd::Key);
+            path.push(PathNode {
+                role: NodeRole::Key,
+                nodes: [0, 1].map(|i| match is_active[i] {
+                    true => rests[i][0].clone(),
+                    false => TrieNode::Leaf {
+                        key: key_rest.to_vec(),
+                        value: default_value.to_vec(),
+                    },
+                }),
+                is_active,
+                prefix: prefix.to_vec(),
+            });
+        }
+        // The key is missing from both tries.
+        [PathEnd::Divergent, PathEnd::Divergent] if rests[0][0] == rests[1][0] => {
+            path.extend(split_path(
+                &rests[0][0],
+                [true; 2],
+                key_rest,
+                prefix,
+                [default_value; 2],
+            ));
+        }
+        // The key is inserted or deleted, splitting or merging the divergent node.
+        [PathEnd::Divergent, PathEnd::Split] | [PathEnd::Split, PathEnd::Divergent] => {
+            let split = (ends[1] == PathEnd::Split) as usize;
+            let invalid = || TrieError::InvalidNode(roots[split]);
+            let mut values = [default_value; 2];
+            values[split] = match rests[split].last() {
+                Some(TrieNode::Leaf { value, .. }) => value.as_slice(),
+                _ => return Err(invalid()),
+            };
+            let mut is_divergent = [true; 2];
+            is_divergent[split] = false;
+            let nodes = split_path(&rests[1 - split][0], is_divergent, key_rest, prefix, values);
+            // The split nodes and the leaf of the key are the ones of the trie with the
+            // key.
+            if !nodes
+                .iter()
+                .filter(|node| matches!(node.role, NodeRole::Split | NodeRole::Key))
+                .map(|node| &node.nodes[split])
+                .eq(rests[split])
+            {
+                return Err(invalid());
+            }
+            path.extend(nodes);
+        }
+        _ => {
+            return Err(TrieError::Unsupported(
+                "change of the path of a key other than its insertion or deletion",
+            ))
+        }
+    }
+
+    for node in &path {
+        for trie_node in &node.nodes {
+            match trie_node {
+                TrieNode::Branch(_) if trie_node.items().concat().len() < 56 => {
+                    return Err(TrieError::Unsupported("branch node of less than 56 bytes"))
+                }
+                TrieNode::Extension {
+                    child: NodeRef::Embedded(_),
+                    ..
+                } => {
+                    return Err(TrieError::Unsupported(
+                        "extension node with an embedded child",
+                    ))
+                }
+                // The compact key of a leaf is laid out as an RLP string of at least 2
+                // bytes.
+                TrieNode::Leaf { key, .. } if key.len() < 2 => {
+                    return Err(TrieError::Unsupported(
+                        "leaf with a key of less than 2 nibbles",
+                    ))
+                }
+                TrieNode::Leaf { value, .. }
+                    if node.role != NodeRole::Key && rlp_string(value).len() > MAX_CHUNK_LEN =>
+                {
+                    return Err(TrieError::Unsupported("leaf value longer than a row"))
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Nodes laid out for the key from the node `divergent` where its path ends
+/// in the tries where `is_divergent`: the divergent node, the split extension
+/// (if the keys have nibbles in common) and branch nodes replacing it in the
+/// other tries, the node it drifts to in the split branch (unless it's the
+/// child of the divergent extension node) and the leaf of the key, with the
+/// values `values`.
+fn split_path(
+    divergent: &TrieNode,
+    is_divergent: [bool; 2],
+    key_rest: &[u8],
+    prefix: &[u8],
+    values: [&[u8]; 2],
+) -> Vec<PathNode> {
+    let divergent_key = match divergent {
+        TrieNode::Extension { key, .. } | TrieNode::Leaf { key, .. } => key,
+        TrieNode::Branch(_) => unreachable!("divergent node is an extension node or a leaf"),
+    };
+    let common = divergent_key
+        .iter()
+        .zip(key_rest)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let drift_key = divergent_key[common + 1..].to_vec();
+    let drifted = match divergent {
+        TrieNode::Extension { .. } if drift_key.is_empty() => None,
+        TrieNode::Extension { child, .. } => Some(TrieNode::Extension {
+            key: drift_key,
+            child: child.clone(),
+        }),
+        TrieNode::Leaf { value, .. } => Some(TrieNode::Leaf {
+            key: drift_key,
+            value: value.clone(),
+        }),
+        TrieNode::Branch(_) => unreachable!("divergent node is an extension node or a leaf"),
+    };
+    let sibling = match (&drifted, divergent) {
+        (Some(drifted), _) => node_ref(drifted),
+        (None, TrieNode::Extension { child, .. }) => child.clone(),
+        _ => unreachable!("divergent leaf drifts to a leaf"),
+    };
+
+    let leaves = values.map(|value| TrieNode::Leaf {
+        key: key_rest[common + 1..].to_vec(),
+        value: value.to_vec(),
+    });
+    // The split nodes have the leaf of the key in the tries they are in.
+    let active = is_divergent
+        .iter()
+        .position(|is_divergent| !is_divergent)
+        .unwrap_or(0);
+    let mut children: [NodeRef; 16] = Default::default();
+    children[key_rest[common] as usize] = node_ref(&leaves[active]);
+    children[divergent_key[common] as usize] = sibling;
+    let branch = TrieNode::Branch(children);
+
+    let is_split = is_divergent.map(|is_divergent| !is_divergent);
+    let path_node = |role, node: TrieNode, is_active, prefix: Vec<u8>| PathNode {
+        role,
+        nodes: [node.clone(), node],
+        is_active,
+        prefix,
+    };
+    let mut path = vec![path_node(
+        NodeRole::Divergent,
+        divergent.clone(),
+        is_divergent,
+        prefix.to_vec(),
+    )];
+    if common > 0 {
+        let extension = TrieNode::Extension {
+            key: key_rest[..common].to_vec(),
+            child: node_ref(&branch),
+        };
+        path.push(path_node(
+            NodeRole::Split,
+            extension,
+            is_split,
+            prefix.to_vec(),
+        ));
+    }
+    let branch_prefix = [prefix, &key_rest[..common]].concat();
+    path.push(path_node(
+        NodeRole::Split,
+        branch,
+        is_split,
+        branch_prefix.clone(),
+    ));
+    if let Some(drifted) = drifted {
+        let mut drifted_prefix = branch_prefix.clone();
+        drifted_prefix.push(divergent_key[common]);
+        path.push(path_node(
+            NodeRole::Drifted,
+            drifted,
+            is_split,
+            drifted_prefix,
+        ));
+    }
+    let mut key_prefix = branch_prefix;
+    key_prefix.push(key_rest[common]);
+    path.push(PathNode {
+        role: NodeRole::Key,
+        nodes: leaves,
+        is_active: is_split,
+        prefix: key_prefix,
+    });
+    path
+}
+
+/// Nodes in the path of a storage slot in the storage trie of an account
+/// before and after an update.
+#[derive(Clone, Debug)]
+struct StorageWitness {
+    key: H256,
+    path: Vec<PathNode>,
+    values: [U256; 2],
+}
+
+/// Nodes in the path of the account of an [`MptUpdate`] in the state trie
+/// before and after it, followed by the ones of the storage slot for storage
+/// updates.
+#[derive(Clone, Debug)]
+struct UpdateWitness {
+    account_key: H256,
+    account_path: Vec<PathNode>,
+    accounts: [Account; 2],
+    storage: Option<StorageWitness>,
+}
+
+impl UpdateWitness {
+    /// Apply `update` to the state trie, whose nodes are in `trie`, checking
+    /// that it leads to the new root of the update.
+    fn new(trie: &mut PartialTrie, update: &MptUpdate) -> Result<Self, TrieError> {
+        let old_root = H256::from(update.old_root().to_be_bytes());
+        let new_root = update.apply(trie, old_root)?;
+        if new_root.to_word() != update.new_root() {
+            return Err(TrieError::RootMismatch);
+        }
+
+        let trie = &*trie;
+        let roots = [old_root, new_root];
+        let account_key = keccak(update.address().as_bytes());
+        let account_path = key_path(trie, roots, account_key, &Account::default().encode())?;
+        let leaf = account_path
+            .last()
+            .expect("path ends with the leaf of the key");
+        let mut accounts = [Account::default(), Account::default()];
+        for i in 0..2 {
+            if leaf.is_active[i] {
+                accounts[i] = Account::decode(leaf_value(&leaf.nodes[i]))
+                    .ok_or(TrieError::InvalidNode(roots[i]))?;
+            }
+            // The nonces are laid out in 8 bytes each.
+            if accounts[i].nonce > U256::from(u64::MAX) {
+                return Err(TrieError::Unsupported("nonce above u64::MAX"));
+            }
+        }
+
+        let storage = match update.storage_key() {
+            Some(storage_key) => {
+                let key = keccak(&storage_key.to_be_bytes());
+                let roots = accounts.clone().map(|account| account.storage_root);
+                let path = key_path(trie, roots, key, &rlp_string(&[]))?;
+                let leaf = path.last().expect("path ends with the leaf of the key");
+                let mut values = [U256::zero(); 2];
+                for i in 0..2 {
+                    if leaf.is_active[i] {
+                        values[i] = decode_storage_value(leaf_value(&leaf.nodes[i]))
+                            .ok_or(TrieError::InvalidNode(roots[i]))?;
+                    }
+                }
+                Some(StorageWitness { key, path, values })
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            account_key,
+            account_path,
+            accounts,
+            storage,
+        })
+    }
+}
+
+/// Push the rows of the nodes in `path`, the path of the hashed key `key` in
+/// the tries with roots `roots`, returning the row with the constants of the
+/// leaf of the key, whose children are `key_child`, for the rows of its value.
+fn push_path_rows<F: Field>(
+    rows: &mut Vec<MptRow<F>>,
+    base: &MptRow<F>,
+    path: &[PathNode],
+    key: H256,
+    roots: [H256; 2],
+    key_child: [F; 2],
+    randomness: F,
+) -> MptRow<F> {
+    let nibbles = key_nibbles(key);
+    let drift = path
+        .iter()
+        .find(|node| node.role == NodeRole::Divergent)
+        .map(|node| {
+            let (key, is_leaf, item, item_len) = match &node.nodes[0] {
+                TrieNode::Leaf { key, .. } => {
+                    let item = node.nodes[0].items().swap_remove(1);
+                    (
+                        key,
+                        true,
+                        rlc::value(item.iter().rev(), randomness),
+                        item.len(),
+                    )
+                }
+                TrieNode::Extension { key, child } => {
+                    (key, false, child_value(child, randomness), 0)
+                }
+                TrieNode::Branch(_) => {
+                    unreachable!("divergent node is an extension node or a leaf")
+                }
+            };
+            let end = [&node.prefix[..], &key[..]].concat();
+            Drift {
+                key: nibbles_rlc(&end, randomness),
+                key_len: key_len_at(end.len()),
+                is_odd: end.len() % 2 == 1,
+                is_leaf,
+                item,
+                item_len,
+            }
+        })
+        .unwrap_or_default();
+
+    // References to the next node in each trie, starting at the root
+    let mut refs = roots.map(|root| (hash_rlc(root, randomness), false));
+    let mut has_root = [false; 2];
+    for node in path {
+        let depth = node.prefix.len();
+        let key_len = key_len_at(depth);
+        let is_odd = depth % 2 == 1;
+        let key_pow = randomness.pow(&[(key_len + is_odd as usize - 1) as u64, 0, 0, 0]);
+        let role = node.role;
+
+        let hash = [0, 1].map(|i| match node.is_active[i] {
+            true => node_rlc(&node.nodes[i], !has_root[i], randomness),
+            false => (F::zero(), false),
+        });
+        let child = [0, 1].map(|i| match (role, node.is_active[i]) {
+            (NodeRole::Key, _) => (key_child[i], false),
+            (NodeRole::Drifted, _) | (_, false) => refs[i],
+            _ => match &node.nodes[i] {
+                TrieNode::Branch(children) => {
+                    ref_rlc(&children[nibbles[depth] as usize], randomness)
+                }
+                TrieNode::Extension { child, .. } if role != NodeRole::Divergent => {
+                    ref_rlc(child, randomness)
+                }
+                _ => (F::zero(), false),
+            },
+        });
+        for i in 0..2 {
+            has_root[i] |= node.is_active[i];
+        }
+        refs = child;
+
+        let mut row = MptRow {
+            is_active: node.is_active,
+            hash: hash.map(|(hash, _)| hash),
+            is_embedded: hash.map(|(_, is_embedded)| is_embedded),
+            child: child.map(|(child, _)| child),
+            child_embedded: child.map(|(_, is_embedded)| is_embedded),
+            is_divergent: role == NodeRole::Divergent,
+            is_split: role == NodeRole::Split,
+            is_drifted: role == NodeRole::Drifted,
+            key_acc: nibbles_rlc(&node.prefix, randomness),
+            key_len,
+            is_odd,
+            key_pow,
+            ..base.clone()
+        };
+        if matches!(
+            role,
+            NodeRole::Divergent | NodeRole::Split | NodeRole::Drifted
+        ) {
+            row.drift = drift;
+        }
+        match &node.nodes[0] {
+            TrieNode::Branch(children) => {
+                row.nibble = nibbles[depth];
+                if role == NodeRole::Split {
+                    let slot = (0..16)
+                        .find(|slot| {
+                            *slot != row.nibble as usize && children[*slot] != NodeRef::Empty
+                        })
+                        .expect("split branch has the node the divergent node drifts to");
+                    let (sibling, sibling_embedded) = ref_rlc(&children[slot], randomness);
+                    row.sibling = sibling;
+                    row.sibling_embedded = sibling_embedded;
+                    row.drift_nibble = slot as u8;
+                }
+            }
+            TrieNode::Extension { key, .. } | TrieNode::Leaf { key, .. } => {
+                row.nibble = if key.len() % 2 == 1 { key[0] } else { 0 };
+                let end = [&node.prefix[..], &key[..]].concat();
+                row.key_end = nibbles_rlc(&end, randomness);
+                row.key_len_end = key_len_at(end.len());
+                row.is_odd_end = end.len() % 2 == 1;
+                row.key_pow_end = randomness.pow(&[row.key_len_end as u64, 0, 0, 0]);
+            }
+        }
+
+        let items = node.nodes.clone().map(|node| node.items());
+        let item = |k: usize| [items[0][k].clone(), items[1][k].clone()];
+        let payload_len = items.clone().map(|items| items.concat().len());
+        let headers = payload_len.map(rlp_list_header);
+        let header_row = |kind: RowKind| {
+            let mut bytes = [0; N_BYTES_WORD];
+            bytes[0] = payload_len[0] as u8;
+            bytes[1] = payload_len[1] as u8;
+            MptRow {
+                kind,
+                value: payload_len.map(|len| F::from(len as u64)),
+                flag: payload_len.map(|len| len >= 56),
+                chunk: headers.clone(),
+                bytes,
+                ..row.clone()
+            }
+        };
+
+        match &node.nodes {
+            [TrieNode::Branch(old_children), TrieNode::Branch(new_children)] => {
+                let mut bytes = [0; N_BYTES_WORD];
+                for i in 0..2 {
+                    bytes[2 * i] = payload_len[i] as u8;
+                    bytes[2 * i + 1] = (payload_len[i] >> 8) as u8;
+                }
+                rows.push(MptRow {
+                    kind: RowKind::BranchHeader,
+                    value: payload_len.map(|len| F::from(len as u64)),
+                    flag: payload_len.map(|len| len >= 256),
+                    chunk: headers.clone(),
+                    bytes,
+                    ..row.clone()
+                });
+                let children = [old_children, new_children];
+                let mut n = [0; 2];
+                let mut path_count = 0;
+                for slot in 0..16 {
+                    let is_path = slot == row.nibble as usize;
+                    path_count += is_path as usize;
+                    let is_empty = children.map(|children| children[slot] == NodeRef::Empty);
+                    for i in 0..2 {
+                        n[i] += !is_empty[i] as usize;
+                    }
+                    rows.push(MptRow {
+                        kind: RowKind::BranchChild,
+                        slot,
+                        is_empty,
+                        is_path,
+                        path_count,
+                        n,
+                        value: children.map(|children| child_value(&children[slot], randomness)),
+                        flag: children
+                            .map(|children| matches!(children[slot], NodeRef::Embedded(_))),
+                        chunk: item(slot),
+                        ..row.clone()
+                    });
+                }
+                rows.push(MptRow {
+                    kind: RowKind::BranchValue,
+                    path_count,
+                    n,
+                    chunk: item(16),
+                    ..row
+                });
+            }
+            [TrieNode::Extension { key, .. }, TrieNode::Extension { .. }] => {
+                let (bytes, is_significant) = compact_key_bytes(key, false);
+                rows.push(header_row(RowKind::ExtensionHeader));
+                rows.push(MptRow {
+                    kind: RowKind::ExtensionKey,
+                    chunk: item(0),
+                    bytes,
+                    is_significant,
+                    ..row.clone()
+                });
+                let values = node.nodes.clone().map(|node| match node {
+                    TrieNode::Extension { child, .. } => child_value(&child, randomness),
+                    _ => unreachable!("nodes laid out together have the same kind"),
+                });
+                rows.push(MptRow {
+                    kind: RowKind::ExtensionChild,
+                    value: values,
+                    chunk: item(1),
+                    ..row
+                });
+            }
+            [TrieNode::Leaf { key, .. }, TrieNode::Leaf { .. }] => {
+                let (bytes, is_significant) = compact_key_bytes(key, true);
+                rows.push(header_row(RowKind::LeafHeader));
+                rows.push(MptRow {
+                    kind: RowKind::LeafKey,
+                    chunk: item(0),
+                    bytes,
+                    is_significant,
+                    ..row.clone()
+                });
+                if role == NodeRole::Key {
+                    return row;
+                }
+                rows.push(MptRow {
+                    kind: RowKind::LeafValue,
+                    chunk: item(1),
+                    ..row
+                });
+            }
+            _ => unreachable!("nodes laid out together have the same kind"),
+        }
+    }
+    unreachable!("path ends with the leaf of the key")
+}
+
+/// MptCircuit proving the updates of a block, from the state root before the
+/// block to the one after it.
+#[derive(Clone, Default, Debug)]
+pub struct MptCircuit<F: Field> {
+    /// Updates of the block
+    pub updates: MptUpdates,
+    /// Randomness for RLC encoding
+    pub randomness: F,
+    /// Number of rows of the circuit
+    pub size: usize,
+    witness: Vec<UpdateWitness>,
+}
+
+impl<F: Field> MptCircuit<F> {
+    /// Return a new MptCircuit proving `updates`, built by [`MptUpdates::new`]
+    /// from the tries whose nodes are in `proofs`, the result of
+    /// `eth_getProof` for every account and storage slot in the updates
+    /// before and after the block.
+    pub fn new(
+        proofs: &[EIP1186ProofResponse],
+        updates: MptUpdates,
+        randomness: F,
+        size: usize,
+    ) -> Result<Self, TrieError> {
+        let mut trie = PartialTrie::default();
+        trie.insert_proofs(proofs);
+        let witness = updates
+            .iter()
+            .map(|update| UpdateWitness::new(&mut trie, update))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            updates,
+            randomness,
+            size,
+            witness,
+        })
+    }
+
+    /// Inputs of the keccak hashes looked up by the MptCircuit
+    pub fn keccak_inputs(&self) -> Vec<Vec<u8>> {
+        let mut inputs = Vec::new();
+        for (update, witness) in self.updates.iter().zip(&self.witness) {
+            let storage_path = witness.storage.iter().map(|storage| &storage.path);
+            for path in iter::once(&witness.account_path).chain(storage_path) {
+                // Nodes embedded in their parent are not hashed, unlike the roots.
+                let mut has_root = [false; 2];
+                for node in path {
+                    for i in 0..2 {
+                        if node.is_active[i] {
+                            let data = node.nodes[i].encode();
+                            if !has_root[i] || data.len() >= 32 {
+                                inputs.push(data);
+                            }
+                            has_root[i] = true;
+                        }
+                    }
+                }
+            }
+            inputs.push(update.address().as_bytes().to_vec());
+            if let Some(storage_key) = update.storage_key() {
+                inputs.push(storage_key.to_be_bytes().to_vec());
+            }
+        }
+        inputs
+    }
+
+    /// Number of rows of the updates, before the padding
+    pub fn min_num_rows(&self) -> usize {
+        self.rows().len()
+    }
+
+    /// Assign the circuit with `config`, except for the [`KeccakTable`],
+    /// returning the cells of the old_root of the first update and the
+    /// new_root of the last one, if there are updates.
+    pub(crate) fn assign(
+        &self,
+        config: &MptCircuitConfig<F>,
+        layouter: &mut impl Layouter<F>,
+    ) -> Result<Option<[AssignedCell<F, F>; 2]>, Error> {
+        config.load(layouter, self.randomness)?;
+        config.assign(layouter, self.size, &self.rows(), self.randomness)
+    }
+
+    fn rows(&self) -> Vec<MptRow<F>> {
+        let randomness = self.randomness;
+        let mut rows = Vec::new();
+        for (update, witness) in self.updates.iter().zip(&self.witness) {
+            let start = rows.len();
+            let base = MptRow {
+                key_rlc: hash_rlc(witness.account_key, randomness),
+                table: update.table_assignment(randomness),
+                ..Default::default()
+            };
+            let accounts = &witness.accounts;
+            let storage_roots = accounts.clone().map(|account| account.storage_root);
+            let leaf = push_path_rows(
+                &mut rows,
+                &base,
+                &witness.account_path,
+                witness.account_key,
+                [update.old_root(), update.new_root()].map(|root| H256::from(root.to_be_bytes())),
+                storage_roots.map(|root| hash_rlc(root, randomness)),
+                randomness,
+            );
+            let items = [accounts[0].items(), accounts[1].items()];
+            let payload_len = items.clone().map(|items| items.concat().len());
+            let mut address_bytes = [0; N_BYTES_WORD];
+            address_bytes[..N_BYTES_ACCOUNT_ADDRESS].copy_from_slice(update.address().as_bytes());
+            address_bytes[..N_BYTES_ACCOUNT_ADDRESS].reverse();
+            rows.push(MptRow {
+                kind: RowKind::AccountHeader,
+                value: payload_len.map(|len| F::from(len as u64)),
+                chunk: payload_len.map(|len| vec![0xb8, len as u8 + 2, 0xf8, len as u8]),
+                bytes: address_bytes,
+                ..leaf.clone()
+            });
+            let is_updated = |kind: RowKind| match update.field_tag() {
+                Some(AccountFieldTag::Nonce) => kind == RowKind::Nonce,
+                Some(AccountFieldTag::Balance) => kind == RowKind::Balance,
+                Some(AccountFieldTag::CodeHash) => kind == RowKind::CodeHash,
+                None => kind == RowKind::StorageRoot,
+            };
+            let integer_row = |kind: RowKind, values: [U256; 2], item: usize| MptRow {
+                kind,
+                is_updated: is_updated(kind),
+                n: values.map(|value| minimal_be_bytes(value).len()),
+                flag: values.map(is_short),
+                is_significant: significant_bytes(values[1]),
+                chunk: [items[0][item].clone(), items[1][item].clone()],
+                ..leaf.clone()
+            };
+            let hash_row = |kind: RowKind, values: [H256; 2], item: usize| MptRow {
+                kind,
+                is_updated: is_updated(kind),
+                value: values.map(|value| hash_rlc(value, randomness)),
+                chunk: [items[0][item].clone(), items[1][item].clone()],
+                ..leaf.clone()
+            };
+
+            let nonces = accounts.clone().map(|account| account.nonce);
+            let mut nonce_bytes = [0; N_BYTES_WORD];
+            for (i, nonce) in nonces.iter().enumerate() {
+                nonce_bytes[8 * i..8 * (i + 1)].copy_from_slice(&nonce.to_le_bytes()[..8]);
+            }
+            rows.push(MptRow {
+                value: nonces.map(|nonce| F::from(nonce.as_u64())),
+                bytes: nonce_bytes,
+                ..integer_row(RowKind::Nonce, nonces, 0)
+            });
+            let balances = accounts.clone().map(|account| account.balance);
+            rows.push(MptRow {
+                value: balances.map(|balance| word_rlc(balance, randomness)),
+                bytes: balances[1].to_le_bytes(),
+                ..integer_row(RowKind::Balance, balances, 1)
+            });
+            rows.push(hash_row(
+                RowKind::StorageRoot,
+                accounts.clone().map(|account| account.storage_root),
+                2,
+            ));
+            // The inverse proves that the account after the update is not empty when
+            // it's in the trie.
+            let code_hashes = accounts.clone().map(|account| account.code_hash);
+            let emptiness = F::from(minimal_be_bytes(nonces[1]).len() as u64)
+                + F::from(minimal_be_bytes(balances[1]).len() as u64)
+                + hash_rlc(code_hashes[1], randomness)
+                - hash_rlc(*EMPTY_CODE_HASH, randomness);
+            rows.push(MptRow {
+                inverse: Option::from(emptiness.invert()).unwrap_or_else(F::zero),
+                ..hash_row(RowKind::CodeHash, code_hashes, 3)
+            });
+
+            if let Some(storage) = &witness.storage {
+                let base = MptRow {
+                    in_storage: true,
+                    key_rlc: hash_rlc(storage.key, randomness),
+                    ..base
+                };
+                let leaf = push_path_rows(
+                    &mut rows,
+                    &base,
+                    &storage.path,
+                    storage.key,
+                    storage_roots,
+                    [F::zero(); 2],
+                    randomness,
+                );
+                let key_leaf = storage
+                    .path
+                    .last()
+                    .expect("path ends with the leaf of the key");
+                let chunk = key_leaf
+                    .nodes
+                    .clone()
+                    .map(|node| node.items().swap_remove(1));
+                rows.push(MptRow {
+                    kind: RowKind::StorageValue,
+                    value: storage.values.map(|value| word_rlc(value, randomness)),
+                    n: storage.values.map(|value| minimal_be_bytes(value).len()),
+                    flag: storage.values.map(is_short),
+                    bytes: storage.values[1].to_le_bytes(),
+                    is_significant: significant_bytes(storage.values[1]),
+                    chunk,
+                    ..leaf
+                });
+            }
+
+            rows[start].is_update_start = true;
+        }
+        rows
+    }
+}
+
+impl<F: Field> Circuit<F> for MptCircuit<F> {
+    type Config = MptCircuitConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let power_of_randomness = power_of_randomness_from_instance(meta);
+        let mpt_table = MptTable::construct(meta);
+        let keccak_table = KeccakTable::construct(meta);
+        MptCircuitConfig::new(meta, power_of_randomness, mpt_table, keccak_table)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        self.assign(&config, &mut layouter)?;
+        config.keccak_table.load(
+            &mut layouter,
+            self.keccak_inputs().iter().map(|input| input.as_slice()),
+            self.randomness,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm_circuit::witness::Rw;
+    use eth_types::{geth_types, trie::state_proofs, Address, Bytes};
+    use halo2_proofs::{
+        arithmetic::Field as Halo2Field,
+        dev::{MockProver, VerifyFailure},
+        pairing::bn256::Fr,
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use std::collections::HashMap;
+
+    /// First `n` items whose hashed keys have distinct first nibbles, so that
+    /// their paths diverge at the root.
+    fn distinct_first_nibbles<T>(
+        items: impl Iterator<Item = T>,
+        hashed_key: impl Fn(&T) -> H256,
+        n: usize,
+    ) -> Vec<T> {
+        let mut nibbles = vec![];
+        items
+            .filter(|item| {
+                let nibble = hashed_key(item).as_bytes()[0] >> 4;
+                let is_new = !nibbles.contains(&nibble);
+                nibbles.push(nibble);
+                is_new
+            })
+            .take(n)
+            .collect()
+    }
+
+    /// First item whose hashed key has nibbles satisfying `is_wanted`
+    fn find_key<T>(
+        mut items: impl Iterator<Item = T>,
+        hashed_key: impl Fn(&T) -> H256,
+        is_wanted: impl Fn(&[u8]) -> bool,
+    ) -> T {
+        items
+            .find(|item| is_wanted(&key_nibbles(hashed_key(item))))
+            .unwrap()
+    }
+
+    /// Whether the keys with nibbles `a` and `b` have exactly `n` first nibbles
+    /// in common
+    fn shares_nibbles(a: &[u8], b: &[u8], n: usize) -> bool {
+        a[..n] == b[..n] && a[n] != b[n]
+    }
+
+    fn address_key(address: &Address) -> H256 {
+        keccak(address.as_bytes())
+    }
+
+    fn storage_key_hash(key: &U256) -> H256 {
+        keccak(&key.to_be_bytes())
+    }
+
+    fn addresses() -> Vec<Address> {
+        distinct_first_nibbles((1..).map(Address::from_low_u64_be), address_key, 3)
+    }
+
+    /// Address whose hashed key has exactly 1 first nibble in common with the
+    /// one of `address`
+    fn address_near(address: Address) -> Address {
+        let nibbles = key_nibbles(address_key(&address));
+        find_key((100..).map(Address::from_low_u64_be), address_key, |key| {
+            shares_nibbles(key, &nibbles, 1)
+        })
+    }
+
+    /// Account sharing the slot of alice in the root of the state trie
+    fn dave() -> Address {
+        address_near(addresses()[0])
+    }
+
+    /// Account missing from the state trie, whose path ends at the leaf of bob
+    fn erin() -> Address {
+        address_near(addresses()[1])
+    }
+
+    /// Account missing from the state trie, whose slot in the root is empty
+    fn frank() -> Address {
+        let first_nibbles = addresses()
+            .iter()
+            .map(|address| key_nibbles(address_key(address))[0])
+            .collect::<Vec<_>>();
+        find_key((100..).map(Address::from_low_u64_be), address_key, |key| {
+            !first_nibbles.contains(&key[0])
+        })
+    }
+
+    fn storage_keys() -> Vec<U256> {
+        distinct_first_nibbles((1..).map(U256::from), storage_key_hash, 4)
+    }
+
+    /// Slot of alice missing from her storage trie, whose path ends at the leaf
+    /// of `storage_keys()[0]`
+    fn slot_near() -> U256 {
+        let nibbles = key_nibbles(storage_key_hash(&storage_keys()[0]));
+        find_key((100..).map(U256::from), storage_key_hash, |key| {
+            shares_nibbles(key, &nibbles, 1)
+        })
+    }
+
+    /// Slot of alice missing from her storage trie, whose slot in the root is
+    /// empty
+    fn missing_slot() -> U256 {
+        let first_nibbles = storage_keys()
+            .iter()
+            .map(|key| key_nibbles(storage_key_hash(key))[0])
+            .collect::<Vec<_>>();
+        find_key((100..).map(U256::from), storage_key_hash, |key| {
+            !first_nibbles.contains(&key[0])
+        })
+    }
+
+    /// Slots of bob: the first two ones share exactly 2 nibbles, so that the
+    /// root of his storage trie is an extension node, and the last two ones are
+    /// missing from his storage trie, sharing 1 and 0 nibbles with them.
+    fn extension_keys() -> [U256; 4] {
+        let e1 = U256::from(1000);
+        let nibbles = key_nibbles(storage_key_hash(&e1));
+        let near = |n| {
+            find_key((1001..).map(U256::from), storage_key_hash, |key| {
+                shares_nibbles(key, &nibbles, n)
+            })
+        };
+        [e1, near(2), near(1), near(0)]
+    }
+
+    fn account(address: Address) -> Account {
+        Account {
+            nonce: U256::from(address.to_low_u64_be()),
+            balance: U256::from(address.to_low_u64_be()) * U256::exp10(18),
+            ..Default::default()
+        }
+    }
+
+    /// Accounts in `addresses()`, the first one having a slot with value
+    /// `1000 * key` for each key in `storage_keys()` and the second one the
+    /// first two slots of `extension_keys()`, followed by dave, with no nonce,
+    /// and the empty accounts of erin and frank.  Missing slots are listed with
+    /// the value 0 for their proofs.
+    fn accounts() -> Vec<geth_types::Account> {
+        let [e1, e2, e3, e4] = extension_keys();
+        let mut accounts = addresses()
+            .into_iter()
+            .enumerate()
+            .map(|(i, address)| geth_types::Account {
+                address,
+                nonce: account(address).nonce,
+                balance: account(address).balance,
+                code: Bytes::default(),
+                storage: match i {
+                    0 => storage_keys()
+                        .into_iter()
+                        .map(|key| (key, key * 1000))
+                        .chain([(slot_near(), U256::zero()), (missing_slot(), U256::zero())])
+                        .collect(),
+                    1 => HashMap::from([
+                        (e1, U256::one()),
+                        (e2, U256::from(2)),
+                        (e3, U256::zero()),
+                        (e4, U256::zero()),
+                    ]),
+                    _ => HashMap::new(),
+                },
+            })
+            .collect::<Vec<_>>();
+        for (address, balance) in [
+            (dave(), U256::exp10(18)),
+            (erin(), U256::zero()),
+            (frank(), U256::zero()),
+        ] {
+            accounts.push(geth_types::Account {
+                address,
+                balance,
+                ..Default::default()
+            });
+        }
+        accounts
+    }
+
+    fn account_rw(
+        address: Address,
+        field_tag: AccountFieldTag,
+        value_prev: U256,
+        value: U256,
+    ) -> Rw {
+        Rw::Account {
+            rw_counter: 1,
+            is_write: true,
+            account_address: address,
+            field_tag,
+            value,
+            value_prev,
+        }
+    }
+
+    fn storage_rw(address: Address, storage_key: U256, value_prev: U256, value: U256) -> Rw {
+        Rw::AccountStorage {
+            rw_counter: 1,
+            is_write: true,
+            account_address: address,
+            storage_key,
+            value,
+            value_prev,
+            tx_id: 1,
+            committed_value: value_prev,
+        }
+    }
+
+    fn test_rws() -> Vec<Rw> {
+        let addresses = addresses();
+        let (alice, bob) = (addresses[0], addresses[1]);
+        let alice_account = account(alice);
+        let bob_account = account(bob);
+        let storage_key = storage_keys()[0];
+        vec![
+            account_rw(
+                alice,
+                AccountFieldTag::Nonce,
+                alice_account.nonce,
+                alice_account.nonce + 1,
+            ),
+            account_rw(
+                alice,
+                AccountFieldTag::Balance,
+                alice_account.balance,
+                alice_account.balance - 1000,
+            ),
+            account_rw(
+                bob,
+                AccountFieldTag::Balance,
+                bob_account.balance,
+                bob_account.balance + 1000,
+            ),
+            account_rw(
+                bob,
+                AccountFieldTag::CodeHash,
+                bob_account.code_hash.to_word(),
+                U256::from(0xc0de),
+            ),
+            storage_rw(alice, storage_key, storage_key * 1000, U256::from(0x42)),
+        ]
+    }
+
+    fn run(circuit: &MptCircuit<Fr>) -> Result<(), Vec<VerifyFailure>> {
+        // Upper bound of the unusable rows, whose number depends on the rotations
+        // of the most queried advice column.  Padding rows don't need the
+        // randomness.
+        const NUM_BLINDING_ROWS: usize = 16;
+        let instance = (1..=POW_RAND_SIZE)
+            .map(|exp| {
+                vec![
+                    circuit.randomness.pow(&[exp as u64, 0, 0, 0]);
+                    circuit.size - NUM_BLINDING_ROWS
+                ]
+            })
+            .collect();
+        let k = circuit.size.trailing_zeros();
+        let prover = MockProver::<Fr>::run(k, circuit, instance).unwrap();
+        prover.verify()
+    }
+
+    fn test_updates(rws: &[Rw]) -> (Vec<EIP1186ProofResponse>, MptUpdates) {
+        let (root, proofs) = state_proofs(&accounts()).unwrap();
+        let updates = MptUpdates::new(rws, root.to_word(), &proofs).unwrap();
+        (proofs, updates)
+    }
+
+    fn test_circuit(rws: &[Rw]) -> MptCircuit<Fr> {
+        let randomness = Fr::random(ChaCha20Rng::seed_from_u64(2));
+        let (proofs, updates) = test_updates(rws);
+        MptCircuit::new(&proofs, updates, randomness, 1 << 10).unwrap()
+    }
+
+    #[test]
+    fn mpt_circuit_account_updates() {
+        let circuit = test_circuit(&test_rws()[..4]);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_storage_update() {
+        let circuit = test_circuit(&test_rws()[4..]);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_updates() {
+        let circuit = test_circuit(&test_rws());
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_integer_encodings() {
+        let addresses = addresses();
+        let (alice, bob) = (addresses[0], addresses[1]);
+        let storage_key = storage_keys()[0];
+        // A long integer of 1 byte, an empty integer and a long storage value of
+        // 1 byte.
+        let rws = vec![
+            account_rw(
+                alice,
+                AccountFieldTag::Nonce,
+                account(alice).nonce,
+                U256::from(0x80),
+            ),
+            account_rw(
+                bob,
+                AccountFieldTag::Balance,
+                account(bob).balance,
+                U256::zero(),
+            ),
+            storage_rw(alice, storage_key, storage_key * 1000, U256::from(0x80)),
+        ];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_wrong_new_root() {
+        let mut circuit = test_circuit(&test_rws()[..2]);
+        let new_roots = circuit
+            .updates
+            .iter()
+            .map(|update| update.new_root() + 1)
+            .collect::<Vec<_>>();
+        circuit
+            .updates
+            .set_roots(circuit.updates.old_root(), &new_roots);
+        assert!(run(&circuit).is_err());
+    }
+
+    #[test]
+    fn mpt_circuit_wrong_balance() {
+        let mut circuit = test_circuit(&test_rws()[1..2]);
+        circuit.witness[0].accounts[1].balance += U256::one();
+        assert!(run(&circuit).is_err());
+    }
+
+    #[test]
+    fn mpt_circuit_root_mismatch() {
+        let (proofs, mut updates) = test_updates(&test_rws()[..2]);
+        let new_roots = updates
+            .iter()
+            .map(|update| update.new_root() + 1)
+            .collect::<Vec<_>>();
+        updates.set_roots(updates.old_root(), &new_roots);
+        let result = MptCircuit::<Fr>::new(&proofs, updates, Fr::one(), 1 << 10);
+        assert_eq!(result.err(), Some(TrieError::RootMismatch));
+    }
+
+    #[test]
+    fn mpt_circuit_missing_proof() {
+        let (proofs, updates) = test_updates(&test_rws());
+        let result = MptCircuit::<Fr>::new(&proofs[1..], updates, Fr::one(), 1 << 10);
+        assert!(matches!(result.err(), Some(TrieError::MissingNode(_))));
+    }
+
+    #[test]
+    fn mpt_circuit_account_insertions() {
+        // Erin's leaf splits the one of bob, frank's leaf fills an empty slot of the
+        // root.
+        let rws = vec![
+            account_rw(
+                erin(),
+                AccountFieldTag::Balance,
+                U256::zero(),
+                U256::from(1000),
+            ),
+            account_rw(frank(), AccountFieldTag::Nonce, U256::zero(), U256::one()),
+        ];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_account_deletion() {
+        // The branch of alice and dave is merged into the leaf of alice.
+        let rws = vec![account_rw(
+            dave(),
+            AccountFieldTag::Balance,
+            U256::exp10(18),
+            U256::zero(),
+        )];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_missing_accounts() {
+        let rws = vec![
+            account_rw(erin(), AccountFieldTag::Balance, U256::zero(), U256::zero()),
+            account_rw(frank(), AccountFieldTag::Nonce, U256::zero(), U256::zero()),
+        ];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_storage_insertion_and_deletion() {
+        let alice = addresses()[0];
+        let deleted_key = storage_keys()[1];
+        let rws = vec![
+            storage_rw(alice, slot_near(), U256::zero(), U256::from(7)),
+            storage_rw(alice, deleted_key, deleted_key * 1000, U256::zero()),
+        ];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_missing_slots() {
+        let alice = addresses()[0];
+        let rws = vec![
+            storage_rw(alice, slot_near(), U256::zero(), U256::zero()),
+            storage_rw(alice, missing_slot(), U256::zero(), U256::zero()),
+            storage_rw(erin(), U256::one(), U256::zero(), U256::zero()),
+        ];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_extension_update() {
+        let [e1, ..] = extension_keys();
+        let rws = vec![storage_rw(addresses()[1], e1, U256::one(), U256::from(5))];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_extension_split() {
+        // The extension node at the root is split after its first nibble, the
+        // branch of bob's slots being the sibling of the leaf of the key.
+        let [_, _, e3, _] = extension_keys();
+        let rws = vec![storage_rw(addresses()[1], e3, U256::zero(), U256::from(3))];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_extension_split_at_root() {
+        // The extension node at the root drifts to the new branch at the root.
+        let [_, _, _, e4] = extension_keys();
+        let rws = vec![storage_rw(addresses()[1], e4, U256::zero(), U256::from(4))];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_extension_merge() {
+        // The extension node and the branch are merged into the leaf of the other
+        // slot.
+        let [e1, ..] = extension_keys();
+        let rws = vec![storage_rw(addresses()[1], e1, U256::one(), U256::zero())];
+        let circuit = test_circuit(&rws);
+        assert_eq!(run(&circuit), Ok(()));
+    }
+
+    #[test]
+    fn mpt_circuit_wrong_drifted_node() {
+        let rws = vec![account_rw(
+            erin(),
+            AccountFieldTag::Balance,
+            U256::zero(),
+            U256::from(1000),
+        )];
+        let mut circuit = test_circuit(&rws);
+        let drifted = circuit.witness[0]
+            .account_path
+            .iter_mut()
+            .find(|node| node.role == NodeRole::Drifted)
+            .unwrap();
+        for node in &mut drifted.nodes {
+            if let TrieNode::Leaf { value, .. } = node {
+                *value.last_mut().unwrap() ^= 1;
+            }
+        }
+        assert!(run(&circuit).is_err());
+    }
+}
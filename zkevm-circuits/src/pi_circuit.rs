@@ -0,0 +1,838 @@
+//! The PublicInputs circuit implementation.
+//!
+//! The circuit lays out the raw public inputs (`rpi`) of a block, one field
+//! element per row, in the following sections:
+//! - The values of the [`BlockTable`], starting with its all-zero row and
+//!   followed by the history hashes, padded to [`NUM_HISTORY_HASHES`].
+//! - The extra values that aren't part of any table: the block hash, the state
+//!   root, the previous state root and the receipts root.
+//! - The `tx_id`, `index` and `value` columns of the [`TxTable`], each as a
+//!   section of its own, following the layout of the Tx Circuit.
+//!
+//! Words are encoded with the RLC of their little-endian bytes, as in the
+//! tables.  The circuit assigns the [`BlockTable`] next to its section of the
+//! raw public inputs, and requires every row of the [`TxTable`] to be found in
+//! the raw public inputs.  The instance column contains:
+//! - `rand_rpi`: the keccak hash of the raw public inputs, each one encoded as
+//!   32 big-endian bytes, reduced to a field element.
+//! - `rpi_rlc`: the RLC of the raw public inputs using `rand_rpi` as the
+//!   randomness.
+//! - The chain id, block hash, state root, previous state root and receipts
+//!   root, which are copied from the raw public inputs.
+//!
+//! NOTE: The receipts root isn't constrained yet against the TxReceipt and
+//! TxLog rows of the Rw Table, which requires a circuit encoding the receipts
+//! and the MPT Circuit to prove the receipts trie.
+//!
+//! A verifier that knows the block data can compute the raw public inputs and
+//! so `rand_rpi`, and check the proof against the instance.  Since `rand_rpi`
+//! commits to the raw public inputs, the circuit only needs to verify their
+//! RLC.
+
+use crate::{
+    evm_circuit::witness::BlockContext,
+    table::{BlockContextFieldTag, BlockTable, DynamicTableColumns, TxFieldTag, TxTable},
+    tx_circuit::{tx_field_values, tx_hash_rlc, tx_sign_hash_rlc, TX_LEN},
+    util::{random_linear_combine_word as rlc, Expr},
+};
+use eth_types::{geth_types::Transaction, Field, ToLittleEndian, ToScalar, Word};
+use halo2_proofs::{
+    circuit::{AssignedCell, Layouter, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance},
+    poly::Rotation,
+};
+use itertools::Itertools;
+use log::error;
+use sha3::{Digest, Keccak256};
+use std::{iter, marker::PhantomData};
+
+/// Number of history hashes in the Block Table
+pub const NUM_HISTORY_HASHES: usize = 256;
+/// Number of fields of the block in the Block Table, excluding the history
+/// hashes
+const BLOCK_FIELDS_LEN: usize = 7;
+/// Number of rows of the Block Table, excluding the all-zero row
+const BLOCK_LEN: usize = BLOCK_FIELDS_LEN + NUM_HISTORY_HASHES;
+/// Number of extra values: block hash, state root, previous state root and
+/// receipts root
+const EXTRA_LEN: usize = 4;
+
+/// Offset of the block number in the raw public inputs
+const NUMBER_OFFSET: usize = 3;
+/// Offset of the chain id in the raw public inputs
+const CHAIN_ID_OFFSET: usize = 7;
+/// Offset of the extra values in the raw public inputs
+const EXTRA_OFFSET: usize = BLOCK_LEN + 1;
+/// Offset of the Tx Table sections in the raw public inputs
+const TX_OFFSET: usize = EXTRA_OFFSET + EXTRA_LEN;
+
+/// Number of rows of the Tx Table assigned by the Tx Circuit
+const fn tx_table_len(max_txs: usize, max_calldata: usize) -> usize {
+    1 + TX_LEN * max_txs + max_calldata
+}
+
+/// Public data of a block, from which the raw public inputs are derived
+#[derive(Clone, Debug, Default)]
+pub struct PublicData {
+    /// Block context, with the values of the Block Table
+    pub block: BlockContext,
+    /// Hash of the block
+    pub block_hash: Word,
+    /// State root after applying the block
+    pub state_root: Word,
+    /// State root before applying the block
+    pub prev_state_root: Word,
+    /// Root of the receipts trie of the block
+    pub receipts_root: Word,
+    /// Transactions of the block
+    pub txs: Vec<Transaction>,
+}
+
+impl PublicData {
+    /// Return the rows of the Block Table, including its all-zero row, with the
+    /// history hashes padded with zeros to [`NUM_HISTORY_HASHES`].
+    fn block_table_rows<F: Field>(&self, randomness: F) -> Result<Vec<[F; 3]>, Error> {
+        let num_history_hashes = self.block.history_hashes.len();
+        if num_history_hashes > NUM_HISTORY_HASHES {
+            error!(
+                "history hashes ({}) exceed the Block Table capacity ({})",
+                num_history_hashes, NUM_HISTORY_HASHES
+            );
+            return Err(Error::Synthesis);
+        }
+        let number: F = self.block.number.to_scalar().ok_or(Error::Synthesis)?;
+        Ok(iter::once([F::zero(); 3])
+            .chain(self.block.table_assignments(randomness))
+            .chain((num_history_hashes..NUM_HISTORY_HASHES).map(|idx| {
+                [
+                    F::from(BlockContextFieldTag::BlockHash as u64),
+                    number - F::from(idx as u64 + 1),
+                    F::zero(),
+                ]
+            }))
+            .collect())
+    }
+
+    /// Return the rows of the Tx Table following the layout of the Tx Circuit:
+    /// the all-zero row, the fields of `MAX_TXS` transactions (padded with
+    /// default transactions) and `MAX_CALLDATA` call data bytes.
+    pub fn tx_table_rows<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>(
+        &self,
+        randomness: F,
+    ) -> Result<Vec<[F; 4]>, Error> {
+        if self.txs.len() > MAX_TXS {
+            error!("txs ({}) exceed MAX_TXS ({})", self.txs.len(), MAX_TXS);
+            return Err(Error::Synthesis);
+        }
+        let calldata_len = self
+            .txs
+            .iter()
+            .map(|tx| tx.call_data.0.len())
+            .sum::<usize>();
+        if calldata_len > MAX_CALLDATA {
+            error!(
+                "call data bytes ({}) exceed MAX_CALLDATA ({})",
+                calldata_len, MAX_CALLDATA
+            );
+            return Err(Error::Synthesis);
+        }
+
+        let chain_id = self.block.chain_id.as_u64();
+        let tx_default = Transaction::default();
+        let mut rows = vec![[F::zero(); 4]];
+        for i in 0..MAX_TXS {
+            let (tx, sign_hash_rlc, hash_rlc) = match self.txs.get(i) {
+                Some(tx) => (
+                    tx,
+                    tx_sign_hash_rlc(tx, chain_id, randomness)?,
+                    tx_hash_rlc(tx, chain_id, randomness)?,
+                ),
+                None => (&tx_default, F::zero(), F::zero()),
+            };
+            let tx_id = F::from(i as u64 + 1);
+            rows.extend(
+                tx_field_values(tx, sign_hash_rlc, hash_rlc, randomness)
+                    .map(|(tag, value)| [tx_id, F::from(tag as u64), F::zero(), value]),
+            );
+        }
+        for (i, tx) in self.txs.iter().enumerate() {
+            for (index, byte) in tx.call_data.0.iter().enumerate() {
+                rows.push([
+                    F::from(i as u64 + 1),
+                    F::from(TxFieldTag::CallData as u64),
+                    F::from(index as u64),
+                    F::from(*byte as u64),
+                ]);
+            }
+        }
+        rows.extend(
+            iter::repeat([
+                F::zero(),
+                F::from(TxFieldTag::CallData as u64),
+                F::zero(),
+                F::zero(),
+            ])
+            .take(MAX_CALLDATA - calldata_len),
+        );
+        Ok(rows)
+    }
+
+    /// Return the extra values: block hash, state root, previous state root
+    /// and receipts root
+    fn extra_values<F: Field>(&self, randomness: F) -> [F; EXTRA_LEN] {
+        [
+            self.block_hash,
+            self.state_root,
+            self.prev_state_root,
+            self.receipts_root,
+        ]
+        .map(|word| rlc(word.to_le_bytes(), randomness))
+    }
+
+    /// Return the raw public inputs
+    pub fn raw_public_inputs<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>(
+        &self,
+        randomness: F,
+    ) -> Result<Vec<F>, Error> {
+        Ok(raw_public_inputs(
+            &self.block_table_rows(randomness)?,
+            self.extra_values(randomness),
+            &self.tx_table_rows::<F, MAX_TXS, MAX_CALLDATA>(randomness)?,
+        ))
+    }
+
+    /// Return the values of the instance column: `rand_rpi`, `rpi_rlc`, chain
+    /// id, block hash, state root, previous state root and receipts root.
+    pub fn public_inputs<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>(
+        &self,
+        randomness: F,
+    ) -> Result<Vec<F>, Error> {
+        let rpi = self.raw_public_inputs::<F, MAX_TXS, MAX_CALLDATA>(randomness)?;
+        let rand_rpi = rand_rpi(&rpi);
+        Ok(vec![
+            rand_rpi,
+            rpi_rlc_accs(&rpi, rand_rpi)[0],
+            rpi[CHAIN_ID_OFFSET],
+            rpi[EXTRA_OFFSET],
+            rpi[EXTRA_OFFSET + 1],
+            rpi[EXTRA_OFFSET + 2],
+            rpi[EXTRA_OFFSET + 3],
+        ])
+    }
+}
+
+/// Lay out the raw public inputs from the Block Table rows, the extra values
+/// and the Tx Table rows.
+fn raw_public_inputs<F: Field>(
+    block_table_rows: &[[F; 3]],
+    extra_values: [F; EXTRA_LEN],
+    tx_table_rows: &[[F; 4]],
+) -> Vec<F> {
+    iter::empty()
+        .chain(block_table_rows.iter().map(|row| row[2]))
+        .chain(extra_values)
+        .chain(tx_table_rows.iter().map(|row| row[0]))
+        .chain(tx_table_rows.iter().map(|row| row[2]))
+        .chain(tx_table_rows.iter().map(|row| row[3]))
+        .collect()
+}
+
+/// Return the keccak hash of the raw public inputs, each one encoded as 32
+/// big-endian bytes, reduced to a field element.
+fn rand_rpi<F: Field>(rpi: &[F]) -> F {
+    let mut hasher = Keccak256::new();
+    for value in rpi {
+        hasher.update(value.to_repr().iter().rev().copied().collect::<Vec<u8>>());
+    }
+    let mut bytes = [0u8; 64];
+    bytes[..32].copy_from_slice(&hasher.finalize());
+    bytes[..32].reverse();
+    F::from_bytes_wide(&bytes)
+}
+
+/// Return the RLC accumulators of the raw public inputs, where the first one
+/// is `rpi_rlc`.
+fn rpi_rlc_accs<F: Field>(rpi: &[F], rand_rpi: F) -> Vec<F> {
+    let mut accs = rpi
+        .iter()
+        .rev()
+        .scan(F::zero(), |acc, value| {
+            *acc = *acc * rand_rpi + *value;
+            Some(*acc)
+        })
+        .collect::<Vec<_>>();
+    accs.reverse();
+    accs
+}
+
+/// Config for PiCircuit
+#[derive(Clone, Debug)]
+pub struct PiCircuitConfig<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> {
+    q_not_end: Column<Fixed>,
+    q_end: Column<Fixed>,
+    q_block_table: Column<Fixed>,
+    q_block_zero: Column<Fixed>,
+    q_block_number: Column<Fixed>,
+    q_block_field: Column<Fixed>,
+    q_block_hash_first: Column<Fixed>,
+    q_block_hash: Column<Fixed>,
+    block_tag: Column<Fixed>,
+    q_tx_table: Column<Fixed>,
+    tx_tag: Column<Fixed>,
+    raw_public_inputs: Column<Advice>,
+    rpi_rlc_acc: Column<Advice>,
+    rand_rpi: Column<Advice>,
+    pi: Column<Instance>,
+    block_table: BlockTable,
+    tx_table: TxTable,
+    _marker: PhantomData<F>,
+}
+
+impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>
+    PiCircuitConfig<F, MAX_TXS, MAX_CALLDATA>
+{
+    /// Return a new PiCircuitConfig
+    pub fn new(meta: &mut ConstraintSystem<F>, block_table: BlockTable, tx_table: TxTable) -> Self {
+        let q_not_end = meta.fixed_column();
+        let q_end = meta.fixed_column();
+        let q_block_table = meta.fixed_column();
+        let q_block_zero = meta.fixed_column();
+        let q_block_number = meta.fixed_column();
+        let q_block_field = meta.fixed_column();
+        let q_block_hash_first = meta.fixed_column();
+        let q_block_hash = meta.fixed_column();
+        let block_tag = meta.fixed_column();
+        let q_tx_table = meta.fixed_column();
+        let tx_tag = meta.fixed_column();
+        let raw_public_inputs = meta.advice_column();
+        let rpi_rlc_acc = meta.advice_column();
+        let rand_rpi = meta.advice_column();
+        let pi = meta.instance_column();
+
+        meta.enable_equality(raw_public_inputs);
+        meta.enable_equality(rpi_rlc_acc);
+        meta.enable_equality(rand_rpi);
+        meta.enable_equality(pi);
+
+        // rpi_rlc_acc[i] = rpi_rlc_acc[i + 1] * rand_rpi + rpi[i]
+        meta.create_gate("rpi_rlc_acc", |meta| {
+            let q_not_end = meta.query_fixed(q_not_end, Rotation::cur());
+            let q_end = meta.query_fixed(q_end, Rotation::cur());
+            let rpi = meta.query_advice(raw_public_inputs, Rotation::cur());
+            let rpi_rlc_acc_cur = meta.query_advice(rpi_rlc_acc, Rotation::cur());
+            let rpi_rlc_acc_next = meta.query_advice(rpi_rlc_acc, Rotation::next());
+            let rand_rpi_cur = meta.query_advice(rand_rpi, Rotation::cur());
+            let rand_rpi_next = meta.query_advice(rand_rpi, Rotation::next());
+
+            vec![
+                q_not_end.clone()
+                    * (rpi_rlc_acc_next * rand_rpi_cur.clone() + rpi.clone()
+                        - rpi_rlc_acc_cur.clone()),
+                q_not_end * (rand_rpi_next - rand_rpi_cur),
+                q_end * (rpi - rpi_rlc_acc_cur),
+            ]
+        });
+
+        // The Block Table values are the first section of the raw public
+        // inputs and its tags are fixed.  The block fields are indexed by the
+        // block number, and the history hashes by decreasing block number.
+        meta.create_gate("block table", |meta| {
+            let q_block_table = meta.query_fixed(q_block_table, Rotation::cur());
+            let q_block_zero = meta.query_fixed(q_block_zero, Rotation::cur());
+            let q_block_number = meta.query_fixed(q_block_number, Rotation::cur());
+            let q_block_field = meta.query_fixed(q_block_field, Rotation::cur());
+            let q_block_hash_first = meta.query_fixed(q_block_hash_first, Rotation::cur());
+            let q_block_hash = meta.query_fixed(q_block_hash, Rotation::cur());
+            let block_tag = meta.query_fixed(block_tag, Rotation::cur());
+            let tag = meta.query_advice(block_table.tag, Rotation::cur());
+            let index = meta.query_advice(block_table.index, Rotation::cur());
+            let index_prev = meta.query_advice(block_table.index, Rotation::prev());
+            let value = meta.query_advice(block_table.value, Rotation::cur());
+            let rpi = meta.query_advice(raw_public_inputs, Rotation::cur());
+            let number_first =
+                meta.query_advice(raw_public_inputs, Rotation(NUMBER_OFFSET as i32 - 1));
+            let number = meta.query_advice(
+                raw_public_inputs,
+                Rotation(NUMBER_OFFSET as i32 - CHAIN_ID_OFFSET as i32 - 1),
+            );
+
+            vec![
+                q_block_table.clone() * (tag - block_tag),
+                q_block_table * (value - rpi),
+                q_block_zero * index.clone(),
+                q_block_number * (index.clone() - number_first),
+                q_block_field * (index.clone() - index_prev.clone()),
+                q_block_hash_first * (index.clone() - (number - 1.expr())),
+                q_block_hash * (index - (index_prev - 1.expr())),
+            ]
+        });
+
+        // Every row of the Tx Table is a row of the Tx Table sections of the raw
+        // public inputs.  Rows out of these sections match the all-zero row.
+        let tx_table_len = tx_table_len(MAX_TXS, MAX_CALLDATA) as i32;
+        meta.lookup_any("tx table in raw public inputs", |meta| {
+            let q_tx_table = meta.query_fixed(q_tx_table, Rotation::cur());
+            let tx_id = meta.query_advice(raw_public_inputs, Rotation(-2 * tx_table_len));
+            let tag = meta.query_fixed(tx_tag, Rotation::cur());
+            let index = meta.query_advice(raw_public_inputs, Rotation(-tx_table_len));
+            let value = meta.query_advice(raw_public_inputs, Rotation::cur());
+
+            tx_table
+                .columns()
+                .into_iter()
+                .zip([tx_id, tag, index, value])
+                .map(|(column, expr)| {
+                    (
+                        meta.query_advice(column, Rotation::cur()),
+                        q_tx_table.clone() * expr,
+                    )
+                })
+                .collect()
+        });
+
+        Self {
+            q_not_end,
+            q_end,
+            q_block_table,
+            q_block_zero,
+            q_block_number,
+            q_block_field,
+            q_block_hash_first,
+            q_block_hash,
+            block_tag,
+            q_tx_table,
+            tx_tag,
+            raw_public_inputs,
+            rpi_rlc_acc,
+            rand_rpi,
+            pi,
+            block_table,
+            tx_table,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Assign the raw public inputs of `public_data` together with the
+    /// [`BlockTable`], and constrain the instance column.  The [`TxTable`] is
+    /// assigned by the Tx Circuit.
+    /// Return the cells of the previous state root and the state root, for the
+    /// MPT Circuit to start and end at them.
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        public_data: &PublicData,
+        randomness: F,
+    ) -> Result<[AssignedCell<F, F>; 2], Error> {
+        let block_table_rows = public_data.block_table_rows(randomness)?;
+        let tx_table_rows = public_data.tx_table_rows::<F, MAX_TXS, MAX_CALLDATA>(randomness)?;
+        let rpi = raw_public_inputs(
+            &block_table_rows,
+            public_data.extra_values(randomness),
+            &tx_table_rows,
+        );
+        let rand_rpi = rand_rpi(&rpi);
+        let rpi_rlc_accs = rpi_rlc_accs(&rpi, rand_rpi);
+
+        let pi_cells = layouter.assign_region(
+            || "raw public inputs",
+            |mut region| {
+                let mut rpi_cells: Vec<AssignedCell<F, F>> = Vec::with_capacity(rpi.len());
+                let mut rand_rpi_cell = None;
+                let mut rpi_rlc_cell = None;
+                for (offset, (value, rpi_rlc_acc)) in
+                    rpi.iter().zip_eq(rpi_rlc_accs.iter()).enumerate()
+                {
+                    if offset + 1 < rpi.len() {
+                        region.assign_fixed(
+                            || "q_not_end",
+                            self.q_not_end,
+                            offset,
+                            || Ok(F::one()),
+                        )?;
+                    } else {
+                        region.assign_fixed(|| "q_end", self.q_end, offset, || Ok(F::one()))?;
+                    }
+                    rpi_cells.push(region.assign_advice(
+                        || "raw_public_inputs",
+                        self.raw_public_inputs,
+                        offset,
+                        || Ok(*value),
+                    )?);
+                    let rpi_rlc_acc_cell = region.assign_advice(
+                        || "rpi_rlc_acc",
+                        self.rpi_rlc_acc,
+                        offset,
+                        || Ok(*rpi_rlc_acc),
+                    )?;
+                    let rand_rpi_cell_cur = region.assign_advice(
+                        || "rand_rpi",
+                        self.rand_rpi,
+                        offset,
+                        || Ok(rand_rpi),
+                    )?;
+                    if offset == 0 {
+                        rand_rpi_cell = Some(rand_rpi_cell_cur);
+                        rpi_rlc_cell = Some(rpi_rlc_acc_cell);
+                    }
+                }
+
+                // Block Table
+                for (offset, row) in block_table_rows.iter().enumerate() {
+                    region.assign_fixed(
+                        || "q_block_table",
+                        self.q_block_table,
+                        offset,
+                        || Ok(F::one()),
+                    )?;
+                    if offset == 0 {
+                        region.assign_fixed(
+                            || "q_block_zero",
+                            self.q_block_zero,
+                            offset,
+                            || Ok(F::one()),
+                        )?;
+                    } else if offset == 1 {
+                        region.assign_fixed(
+                            || "q_block_number",
+                            self.q_block_number,
+                            offset,
+                            || Ok(F::one()),
+                        )?;
+                    } else if offset <= CHAIN_ID_OFFSET {
+                        region.assign_fixed(
+                            || "q_block_field",
+                            self.q_block_field,
+                            offset,
+                            || Ok(F::one()),
+                        )?;
+                    } else if offset == CHAIN_ID_OFFSET + 1 {
+                        region.assign_fixed(
+                            || "q_block_hash_first",
+                            self.q_block_hash_first,
+                            offset,
+                            || Ok(F::one()),
+                        )?;
+                    } else {
+                        region.assign_fixed(
+                            || "q_block_hash",
+                            self.q_block_hash,
+                            offset,
+                            || Ok(F::one()),
+                        )?;
+                    }
+                    region.assign_fixed(|| "block_tag", self.block_tag, offset, || Ok(row[0]))?;
+                    for (column, value) in self.block_table.columns().iter().zip_eq(row) {
+                        region.assign_advice(
+                            || format!("block table row {}", offset),
+                            *column,
+                            offset,
+                            || Ok(*value),
+                        )?;
+                    }
+                }
+
+                // Tx Table tags, next to the values section
+                let value_offset = TX_OFFSET + 2 * tx_table_len(MAX_TXS, MAX_CALLDATA);
+                for (i, row) in tx_table_rows.iter().enumerate() {
+                    let offset = value_offset + i;
+                    region.assign_fixed(
+                        || "q_tx_table",
+                        self.q_tx_table,
+                        offset,
+                        || Ok(F::one()),
+                    )?;
+                    region.assign_fixed(|| "tx_tag", self.tx_tag, offset, || Ok(row[1]))?;
+                }
+
+                Ok([
+                    rand_rpi_cell.ok_or(Error::Synthesis)?,
+                    rpi_rlc_cell.ok_or(Error::Synthesis)?,
+                    rpi_cells[CHAIN_ID_OFFSET].clone(),
+                    rpi_cells[EXTRA_OFFSET].clone(),
+                    rpi_cells[EXTRA_OFFSET + 1].clone(),
+                    rpi_cells[EXTRA_OFFSET + 2].clone(),
+                    rpi_cells[EXTRA_OFFSET + 3].clone(),
+                ])
+            },
+        )?;
+
+        for (row, cell) in pi_cells.iter().enumerate() {
+            layouter.constrain_instance(cell.cell(), self.pi, row)?;
+        }
+        let [_, _, _, _, state_root, prev_state_root, _] = pi_cells;
+        Ok([prev_state_root, state_root])
+    }
+}
+
+/// PublicInputs Circuit for verifying the Block Table and Tx Table against the
+/// public inputs
+#[derive(Clone, Default)]
+pub struct PiCircuit<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> {
+    /// Randomness for RLC encoding
+    pub randomness: F,
+    /// Public data of the block
+    pub public_data: PublicData,
+}
+
+impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>
+    PiCircuit<F, MAX_TXS, MAX_CALLDATA>
+{
+    /// Return a new PiCircuit
+    pub fn new(randomness: F, public_data: PublicData) -> Self {
+        Self {
+            randomness,
+            public_data,
+        }
+    }
+
+    /// Return the values of the instance column
+    pub fn instance(&self) -> Result<Vec<Vec<F>>, Error> {
+        let public_inputs = self
+            .public_data
+            .public_inputs::<F, MAX_TXS, MAX_CALLDATA>(self.randomness)?;
+        Ok(vec![public_inputs])
+    }
+}
+
+impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> Circuit<F>
+    for PiCircuit<F, MAX_TXS, MAX_CALLDATA>
+{
+    type Config = PiCircuitConfig<F, MAX_TXS, MAX_CALLDATA>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let block_table = BlockTable::construct(meta);
+        let tx_table = TxTable::construct(meta);
+        PiCircuitConfig::new(meta, block_table, tx_table)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.assign(&mut layouter, &self.public_data, self.randomness)?;
+        // Without the Tx Circuit, assign the Tx Table as it would.
+        let tx_table_rows = self
+            .public_data
+            .tx_table_rows::<F, MAX_TXS, MAX_CALLDATA>(self.randomness)?;
+        layouter.assign_region(
+            || "tx table",
+            |mut region| {
+                for (offset, row) in tx_table_rows.iter().enumerate() {
+                    for (column, value) in config.tx_table.columns().iter().zip_eq(row) {
+                        region.assign_advice(
+                            || format!("tx table row {}", offset),
+                            *column,
+                            offset,
+                            || Ok(*value),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod pi_circuit_test {
+    use super::*;
+    use eth_types::{address, Address, Bytes};
+    use ethers_core::{types::TransactionRequest, utils::keccak256};
+    use ethers_signers::{LocalWallet, Signer};
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        pairing::bn256::Fr,
+    };
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    const MAX_TXS: usize = 2;
+    const MAX_CALLDATA: usize = 16;
+    const CHAIN_ID: u64 = 1337;
+
+    fn run<C: Circuit<Fr>>(circuit: &C, instance: Vec<Vec<Fr>>) -> Result<(), Vec<VerifyFailure>> {
+        let k = 9;
+        let prover = match MockProver::<Fr>::run(k, circuit, instance) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:#?}", e),
+        };
+        prover.verify()
+    }
+
+    fn sign_tx(wallet: &LocalWallet, to: Address, nonce: u64, data: &[u8]) -> Transaction {
+        let req = TransactionRequest::new()
+            .from(wallet.address())
+            .to(to)
+            .nonce(nonce)
+            .value(1000)
+            .data(data.to_vec())
+            .gas(500_000)
+            .gas_price(1234);
+        let sighash = keccak256(req.rlp(CHAIN_ID).as_ref()).into();
+        let sig = wallet.sign_hash(sighash, true);
+        Transaction {
+            from: wallet.address(),
+            to: Some(to),
+            nonce: nonce.into(),
+            gas_limit: Word::from(500_000u64),
+            gas_price: Word::from(1234u64),
+            value: Word::from(1000u64),
+            call_data: Bytes::from(data.to_vec()),
+            v: sig.v,
+            r: sig.r,
+            s: sig.s,
+            ..Transaction::default()
+        }
+    }
+
+    fn public_data() -> PublicData {
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let wallet = LocalWallet::new(&mut rng).with_chain_id(CHAIN_ID);
+        let to = address!("0x000000000000000000000000000000000000cafe");
+        PublicData {
+            block: BlockContext {
+                coinbase: address!("0x00000000000000000000000000000000c014ba5e"),
+                gas_limit: 0x2386f26fc10000,
+                number: Word::from(0xcafeu64),
+                timestamp: Word::from(0x1234u64),
+                difficulty: Word::from(0x20000u64),
+                base_fee: Word::from(0x3b9aca00u64),
+                history_hashes: (1..=3u64).map(|i| Word::from(0xbeef00 + i)).collect(),
+                chain_id: CHAIN_ID.into(),
+            },
+            block_hash: Word::from(0xb10cu64),
+            state_root: Word::from(0x5707u64),
+            prev_state_root: Word::from(0x5706u64),
+            receipts_root: Word::from(0x7ec0u64),
+            txs: vec![
+                sign_tx(&wallet, to, 0, b"hello"),
+                sign_tx(&wallet, to, 1, &[0, 1, 2]),
+            ],
+        }
+    }
+
+    fn circuit(public_data: PublicData) -> PiCircuit<Fr, MAX_TXS, MAX_CALLDATA> {
+        PiCircuit::new(Fr::from(0x100), public_data)
+    }
+
+    // PiCircuit assigning a Tx Table that differs from the public data.
+    #[derive(Default)]
+    struct TamperedTxTableCircuit {
+        pi_circuit: PiCircuit<Fr, MAX_TXS, MAX_CALLDATA>,
+        tx_table_rows: Vec<[Fr; 4]>,
+    }
+
+    impl Circuit<Fr> for TamperedTxTableCircuit {
+        type Config = PiCircuitConfig<Fr, MAX_TXS, MAX_CALLDATA>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            PiCircuit::<Fr, MAX_TXS, MAX_CALLDATA>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config.assign(
+                &mut layouter,
+                &self.pi_circuit.public_data,
+                self.pi_circuit.randomness,
+            )?;
+            layouter.assign_region(
+                || "tx table",
+                |mut region| {
+                    for (offset, row) in self.tx_table_rows.iter().enumerate() {
+                        for (column, value) in config.tx_table.columns().iter().zip_eq(row) {
+                            region.assign_advice(|| "", *column, offset, || Ok(*value))?;
+                        }
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn pi_circuit_empty() {
+        let circuit = circuit(PublicData::default());
+        assert_eq!(run(&circuit, circuit.instance().unwrap()), Ok(()));
+    }
+
+    #[test]
+    fn pi_circuit_valid() {
+        let circuit = circuit(public_data());
+        assert_eq!(run(&circuit, circuit.instance().unwrap()), Ok(()));
+    }
+
+    #[test]
+    fn pi_circuit_too_many_txs() {
+        let mut public_data = public_data();
+        public_data.txs.push(public_data.txs[0].clone());
+        assert!(matches!(
+            public_data.raw_public_inputs::<Fr, MAX_TXS, MAX_CALLDATA>(Fr::from(0x100)),
+            Err(Error::Synthesis)
+        ));
+    }
+
+    #[test]
+    fn pi_circuit_invalid_instance() {
+        let circuit = circuit(public_data());
+        for row in 0..7 {
+            let mut instance = circuit.instance().unwrap();
+            instance[0][row] += Fr::one();
+            assert!(run(&circuit, instance).is_err());
+        }
+    }
+
+    #[test]
+    fn pi_circuit_invalid_public_data() {
+        // The instance is computed from the expected public data, while the
+        // circuit is assigned from a block with a different gas limit.
+        let instance = circuit(public_data()).instance().unwrap();
+        let mut public_data = public_data();
+        public_data.block.gas_limit += 1;
+        assert!(run(&circuit(public_data), instance).is_err());
+    }
+
+    #[test]
+    fn pi_circuit_invalid_tx_table() {
+        let pi_circuit = circuit(public_data());
+        let tx_table_rows = pi_circuit
+            .public_data
+            .tx_table_rows::<Fr, MAX_TXS, MAX_CALLDATA>(pi_circuit.randomness)
+            .unwrap();
+        let instance = pi_circuit.instance().unwrap();
+
+        let mut circuit = TamperedTxTableCircuit {
+            pi_circuit,
+            tx_table_rows,
+        };
+        assert_eq!(run(&circuit, instance.clone()), Ok(()));
+
+        // Change the Gas of the first tx
+        circuit.tx_table_rows[2][3] += Fr::one();
+        assert!(run(&circuit, instance.clone()).is_err());
+
+        // Add a row for a tx that isn't in the public data
+        circuit.tx_table_rows[2][3] -= Fr::one();
+        circuit.tx_table_rows.push([
+            Fr::from(MAX_TXS as u64 + 1),
+            Fr::from(TxFieldTag::Gas as u64),
+            Fr::zero(),
+            Fr::from(21_000u64),
+        ]);
+        assert!(run(&circuit, instance).is_err());
+    }
+}
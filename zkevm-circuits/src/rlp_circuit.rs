@@ -0,0 +1,2147 @@
+//! The RLP circuit implementation.
+//!
+//! The circuit proves the RLP encodings of the transactions of a block, one
+//! byte per row, from the fields of the transactions in the [`TxTable`].  Each
+//! transaction has two encodings, laid out one after the other:
+//! - [`RlpDataType::TxSign`]: the encoding signed by the sender, whose hash is
+//!   the `TxSignHash` of the transaction.
+//! - [`RlpDataType::TxHash`]: the encoding of the signed transaction, whose
+//!   hash is the `TxHash` of the transaction.
+//!
+//! Legacy (EIP-155), EIP-2930 and EIP-1559 transactions are supported, as long
+//! as their access list is empty.  An encoding is split in items (see
+//! [`RlpTxTag`]): the list header, the fields of the transaction and, for
+//! typed transactions, the leading transaction type byte.  The sequence of
+//! items of each kind of encoding is fixed, and checked with a lookup to a
+//! fixed table.  The value of each item is looked up in the [`TxTable`], the
+//! chain id in the [`BlockTable`], and the RLC of the bytes of each encoding in
+//! the [`KeccakTable`] together with its hash, which is also looked up in the
+//! [`TxTable`].
+//!
+//! NOTE: The circuit doesn't check that every transaction of the [`TxTable`]
+//! has its encodings laid out, only that the encodings that are laid out
+//! belong to the transactions `1..=n` in order.
+
+use crate::{
+    evm_circuit::{
+        util::{and, constraint_builder::BaseConstraintBuilder, not, select},
+        witness::BlockContext,
+    },
+    impl_expr,
+    table::{
+        BlockContextFieldTag, BlockTable, DynamicTableColumns, KeccakTable, TxFieldTag, TxTable,
+    },
+    tx_circuit::{tx_field_values, tx_hash_rlc, tx_sign_hash_rlc},
+    util::{power_of_randomness_from_instance, Expr},
+};
+use eth_types::{geth_types::Transaction, Field, ToBigEndian, Word};
+use halo2_proofs::{
+    circuit::{Layouter, Region, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, VirtualCells},
+    poly::Rotation,
+};
+use log::error;
+use std::iter;
+
+/// Type of a transaction, which is its EIP-2718 type except for legacy
+/// transactions signed without a chain id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxType {
+    /// Legacy transaction, signed following EIP-155
+    Legacy = 0,
+    /// EIP-2930 transaction
+    Eip2930,
+    /// EIP-1559 transaction
+    Eip1559,
+    /// Legacy transaction signed before EIP-155, with `v = 27` or `v = 28`
+    PreEip155,
+}
+
+impl TxType {
+    const ALL: [Self; 4] = [Self::Legacy, Self::Eip2930, Self::Eip1559, Self::PreEip155];
+
+    /// Return the type of a transaction
+    pub(crate) fn from_tx(tx: &Transaction) -> Result<Self, Error> {
+        match tx.transaction_type {
+            0 if tx.v == 27 || tx.v == 28 => Ok(Self::PreEip155),
+            0 => Ok(Self::Legacy),
+            1 => Ok(Self::Eip2930),
+            2 => Ok(Self::Eip1559),
+            tx_type => {
+                error!("unsupported transaction type {}", tx_type);
+                Err(Error::Synthesis)
+            }
+        }
+    }
+}
+
+/// Encoding of a transaction proved by the RLP Circuit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RlpDataType {
+    /// Encoding of the transaction without the signature, used for signing
+    TxSign = 0,
+    /// Encoding of the signed transaction, used for the transaction hash
+    TxHash,
+}
+
+impl RlpDataType {
+    const ALL: [Self; 2] = [Self::TxSign, Self::TxHash];
+}
+
+impl_expr!(RlpDataType);
+
+/// Tag of an item of the RLP encoding of a transaction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RlpTxTag {
+    /// Unused tag
+    Null = 0,
+    /// Transaction type byte of typed transactions
+    TxType,
+    /// Header of the list of fields
+    Prefix,
+    /// Chain ID
+    ChainId,
+    /// Nonce
+    Nonce,
+    /// Gas price
+    GasPrice,
+    /// Max priority fee per gas
+    GasTipCap,
+    /// Max fee per gas
+    GasFeeCap,
+    /// Gas
+    Gas,
+    /// Callee address
+    To,
+    /// Value
+    Value,
+    /// Call data
+    Data,
+    /// Access list, which must be empty
+    AccessList,
+    /// V value of the signature
+    SigV,
+    /// R value of the signature
+    SigR,
+    /// S value of the signature
+    SigS,
+}
+
+impl RlpTxTag {
+    /// Return the tag of the Tx Table row holding the value of the item
+    fn tx_field_tag(self) -> Option<TxFieldTag> {
+        match self {
+            Self::Nonce => Some(TxFieldTag::Nonce),
+            Self::GasPrice => Some(TxFieldTag::GasPrice),
+            Self::GasTipCap => Some(TxFieldTag::GasTipCap),
+            Self::GasFeeCap => Some(TxFieldTag::GasFeeCap),
+            Self::Gas => Some(TxFieldTag::Gas),
+            Self::To => Some(TxFieldTag::CalleeAddress),
+            Self::Value => Some(TxFieldTag::Value),
+            Self::Data => Some(TxFieldTag::CallDataLength),
+            Self::SigV => Some(TxFieldTag::SigV),
+            Self::SigR => Some(TxFieldTag::SigR),
+            Self::SigS => Some(TxFieldTag::SigS),
+            Self::Null | Self::TxType | Self::Prefix | Self::ChainId | Self::AccessList => None,
+        }
+    }
+}
+
+/// Kind of an item, which defines how it's encoded and how its value is
+/// computed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RlpItemKind {
+    /// Fixed single byte
+    Raw(u8),
+    /// List header, whose value is the length of the list payload
+    Header,
+    /// Integer of up to 8 bytes, whose value is the integer
+    Int,
+    /// Integer of up to 32 bytes, whose value is the RLC of its little-endian
+    /// bytes
+    Word,
+    /// Address or empty string, whose value is the address or zero
+    Address,
+    /// Byte string, whose value is its length
+    Data,
+}
+
+/// Return the sequence of items of an encoding, with their kind.
+fn rlp_tags(tx_type: TxType, data_type: RlpDataType) -> Vec<(RlpTxTag, RlpItemKind)> {
+    let mut tags = match tx_type {
+        TxType::Legacy | TxType::PreEip155 => vec![
+            (RlpTxTag::Prefix, RlpItemKind::Header),
+            (RlpTxTag::Nonce, RlpItemKind::Word),
+            (RlpTxTag::GasPrice, RlpItemKind::Word),
+        ],
+        TxType::Eip2930 => vec![
+            (RlpTxTag::TxType, RlpItemKind::Raw(TxType::Eip2930 as u8)),
+            (RlpTxTag::Prefix, RlpItemKind::Header),
+            (RlpTxTag::ChainId, RlpItemKind::Word),
+            (RlpTxTag::Nonce, RlpItemKind::Word),
+            (RlpTxTag::GasPrice, RlpItemKind::Word),
+        ],
+        TxType::Eip1559 => vec![
+            (RlpTxTag::TxType, RlpItemKind::Raw(TxType::Eip1559 as u8)),
+            (RlpTxTag::Prefix, RlpItemKind::Header),
+            (RlpTxTag::ChainId, RlpItemKind::Word),
+            (RlpTxTag::Nonce, RlpItemKind::Word),
+            (RlpTxTag::GasTipCap, RlpItemKind::Word),
+            (RlpTxTag::GasFeeCap, RlpItemKind::Word),
+        ],
+    };
+    tags.extend([
+        (RlpTxTag::Gas, RlpItemKind::Int),
+        (RlpTxTag::To, RlpItemKind::Address),
+        (RlpTxTag::Value, RlpItemKind::Word),
+        (RlpTxTag::Data, RlpItemKind::Data),
+    ]);
+    if matches!(tx_type, TxType::Eip2930 | TxType::Eip1559) {
+        tags.push((RlpTxTag::AccessList, RlpItemKind::Raw(0xc0)));
+    }
+    match (tx_type, data_type) {
+        // EIP-155: [..., chain_id, 0, 0]
+        (TxType::Legacy, RlpDataType::TxSign) => tags.extend([
+            (RlpTxTag::ChainId, RlpItemKind::Word),
+            (RlpTxTag::SigR, RlpItemKind::Raw(0x80)),
+            (RlpTxTag::SigS, RlpItemKind::Raw(0x80)),
+        ]),
+        // Typed and pre-EIP-155 txs don't append anything to the fields
+        (_, RlpDataType::TxSign) => (),
+        (_, RlpDataType::TxHash) => tags.extend([
+            (RlpTxTag::SigV, RlpItemKind::Int),
+            (RlpTxTag::SigR, RlpItemKind::Word),
+            (RlpTxTag::SigS, RlpItemKind::Word),
+        ]),
+    }
+    tags
+}
+
+/// Return the big-endian bytes of a word without leading zeros
+fn word_bytes(word: Word) -> Vec<u8> {
+    let len = (word.bits() + 7) / 8;
+    word.to_be_bytes()[32 - len..].to_vec()
+}
+
+/// Return the payload of a string item of a transaction
+fn item_payload(tx: &Transaction, chain_id: u64, tag: RlpTxTag) -> Vec<u8> {
+    match tag {
+        RlpTxTag::ChainId => word_bytes(Word::from(chain_id)),
+        RlpTxTag::Nonce => word_bytes(tx.nonce),
+        RlpTxTag::GasPrice => word_bytes(tx.gas_price),
+        RlpTxTag::GasTipCap => word_bytes(tx.gas_tip_cap),
+        RlpTxTag::GasFeeCap => word_bytes(tx.gas_fee_cap),
+        RlpTxTag::Gas => word_bytes(tx.gas_limit),
+        RlpTxTag::To => tx.to.map(|to| to.as_bytes().to_vec()).unwrap_or_default(),
+        RlpTxTag::Value => word_bytes(tx.value),
+        RlpTxTag::Data => tx.call_data.to_vec(),
+        RlpTxTag::SigV => word_bytes(Word::from(tx.v)),
+        RlpTxTag::SigR => word_bytes(tx.r),
+        RlpTxTag::SigS => word_bytes(tx.s),
+        RlpTxTag::Null | RlpTxTag::TxType | RlpTxTag::Prefix | RlpTxTag::AccessList => vec![],
+    }
+}
+
+/// Return the prefix of a string (`offset = 0x80`) or list (`offset = 0xc0`)
+/// with a payload of `len` bytes.
+fn rlp_prefix(offset: u8, len: usize) -> Vec<u8> {
+    if len <= 55 {
+        vec![offset + len as u8]
+    } else {
+        let len_be: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        iter::once(offset + 55 + len_be.len() as u8)
+            .chain(len_be)
+            .collect()
+    }
+}
+
+/// Item of the encoding of a transaction, as laid out in the circuit
+#[derive(Clone, Debug)]
+struct RlpItem {
+    tag: RlpTxTag,
+    kind: RlpItemKind,
+    /// Encoded bytes of the item
+    bytes: Vec<u8>,
+    /// Length of the payload, which for the header is the length of the list
+    /// payload
+    payload_len: usize,
+    /// Whether the item is a single byte without prefix
+    is_single: bool,
+    /// Number of bytes of the payload length, when it's longer than 55 bytes
+    len_len: usize,
+}
+
+impl RlpItem {
+    fn raw(tag: RlpTxTag, byte: u8) -> Self {
+        Self {
+            tag,
+            kind: RlpItemKind::Raw(byte),
+            bytes: vec![byte],
+            payload_len: 1,
+            is_single: true,
+            len_len: 0,
+        }
+    }
+
+    fn header(payload_len: usize) -> Self {
+        let bytes = rlp_prefix(0xc0, payload_len);
+        Self {
+            tag: RlpTxTag::Prefix,
+            kind: RlpItemKind::Header,
+            len_len: bytes.len() - 1,
+            bytes,
+            payload_len,
+            is_single: false,
+        }
+    }
+
+    fn string(tag: RlpTxTag, kind: RlpItemKind, payload: Vec<u8>) -> Self {
+        if payload.len() == 1 && payload[0] < 0x80 {
+            return Self {
+                tag,
+                kind,
+                bytes: payload,
+                payload_len: 1,
+                is_single: true,
+                len_len: 0,
+            };
+        }
+        let prefix = rlp_prefix(0x80, payload.len());
+        Self {
+            tag,
+            kind,
+            len_len: prefix.len() - 1,
+            payload_len: payload.len(),
+            bytes: prefix.into_iter().chain(payload).collect(),
+            is_single: false,
+        }
+    }
+
+    fn is_long(&self) -> bool {
+        self.len_len > 0
+    }
+}
+
+/// Return the items of an encoding of a transaction
+fn rlp_items(
+    tx: &Transaction,
+    chain_id: u64,
+    data_type: RlpDataType,
+) -> Result<Vec<RlpItem>, Error> {
+    if tx
+        .access_list
+        .as_ref()
+        .map_or(false, |list| !list.0.is_empty())
+    {
+        error!("transactions with a non-empty access list are not supported");
+        return Err(Error::Synthesis);
+    }
+    let tx_type = TxType::from_tx(tx)?;
+    let mut items: Vec<RlpItem> = rlp_tags(tx_type, data_type)
+        .into_iter()
+        .map(|(tag, kind)| match kind {
+            RlpItemKind::Raw(byte) => RlpItem::raw(tag, byte),
+            // The list header is set once the payload is known
+            RlpItemKind::Header => RlpItem::header(0),
+            _ => RlpItem::string(tag, kind, item_payload(tx, chain_id, tag)),
+        })
+        .collect();
+    let header = items
+        .iter()
+        .position(|item| item.kind == RlpItemKind::Header)
+        .expect("encodings have a header");
+    let list_len = items[header + 1..]
+        .iter()
+        .map(|item| item.bytes.len())
+        .sum();
+    items[header] = RlpItem::header(list_len);
+    Ok(items)
+}
+
+/// Return an RLP encoding of a transaction
+pub fn tx_rlp(tx: &Transaction, chain_id: u64, data_type: RlpDataType) -> Result<Vec<u8>, Error> {
+    Ok(rlp_items(tx, chain_id, data_type)?
+        .into_iter()
+        .flat_map(|item| item.bytes)
+        .collect())
+}
+
+/// Return all the keccak inputs that the RLP Circuit requires, which are the
+/// encodings of the transactions.
+pub fn keccak_inputs(txs: &[Transaction], chain_id: u64) -> Result<Vec<Vec<u8>>, Error> {
+    let mut inputs = Vec::new();
+    for tx in txs {
+        for data_type in RlpDataType::ALL {
+            inputs.push(tx_rlp(tx, chain_id, data_type)?);
+        }
+    }
+    Ok(inputs)
+}
+
+/// Fixed table with the sequence of items of each kind of encoding
+#[derive(Clone, Debug)]
+struct RlpTagTable {
+    enabled: Column<Fixed>,
+    tx_type: Column<Fixed>,
+    data_type: Column<Fixed>,
+    tag: Column<Fixed>,
+    /// Tag of the next item, or `Null` for the last item
+    tag_next: Column<Fixed>,
+    is_first: Column<Fixed>,
+    tx_tag: Column<Fixed>,
+    is_tx_field: Column<Fixed>,
+    is_chain_id: Column<Fixed>,
+    is_header: Column<Fixed>,
+    is_raw: Column<Fixed>,
+    raw_byte: Column<Fixed>,
+    is_word: Column<Fixed>,
+    is_address: Column<Fixed>,
+    is_data: Column<Fixed>,
+}
+
+impl RlpTagTable {
+    fn construct<F: Field>(meta: &mut ConstraintSystem<F>) -> Self {
+        Self {
+            enabled: meta.fixed_column(),
+            tx_type: meta.fixed_column(),
+            data_type: meta.fixed_column(),
+            tag: meta.fixed_column(),
+            tag_next: meta.fixed_column(),
+            is_first: meta.fixed_column(),
+            tx_tag: meta.fixed_column(),
+            is_tx_field: meta.fixed_column(),
+            is_chain_id: meta.fixed_column(),
+            is_header: meta.fixed_column(),
+            is_raw: meta.fixed_column(),
+            raw_byte: meta.fixed_column(),
+            is_word: meta.fixed_column(),
+            is_address: meta.fixed_column(),
+            is_data: meta.fixed_column(),
+        }
+    }
+
+    fn columns(&self) -> [Column<Fixed>; 15] {
+        [
+            self.enabled,
+            self.tx_type,
+            self.data_type,
+            self.tag,
+            self.tag_next,
+            self.is_first,
+            self.tx_tag,
+            self.is_tx_field,
+            self.is_chain_id,
+            self.is_header,
+            self.is_raw,
+            self.raw_byte,
+            self.is_word,
+            self.is_address,
+            self.is_data,
+        ]
+    }
+
+    /// Return the rows of the table, in the order of [`Self::columns`],
+    /// excluding the all-zero row.
+    fn rows() -> Vec<[u64; 15]> {
+        let mut rows = Vec::new();
+        for tx_type in TxType::ALL {
+            for data_type in RlpDataType::ALL {
+                let tags = rlp_tags(tx_type, data_type);
+                for (idx, (tag, kind)) in tags.iter().enumerate() {
+                    let tag_next = tags.get(idx + 1).map_or(RlpTxTag::Null, |(tag, _)| *tag);
+                    let tx_tag = match kind {
+                        RlpItemKind::Raw(_) => None,
+                        _ => tag.tx_field_tag(),
+                    };
+                    let raw_byte = match kind {
+                        RlpItemKind::Raw(byte) => *byte,
+                        _ => 0,
+                    };
+                    rows.push([
+                        1,
+                        tx_type as u64,
+                        data_type as u64,
+                        *tag as u64,
+                        tag_next as u64,
+                        (idx == 0) as u64,
+                        tx_tag.map_or(0, |tx_tag| tx_tag as u64),
+                        tx_tag.is_some() as u64,
+                        (*tag == RlpTxTag::ChainId) as u64,
+                        (*kind == RlpItemKind::Header) as u64,
+                        matches!(kind, RlpItemKind::Raw(_)) as u64,
+                        raw_byte as u64,
+                        (*kind == RlpItemKind::Word) as u64,
+                        (*kind == RlpItemKind::Address) as u64,
+                        (*kind == RlpItemKind::Data) as u64,
+                    ]);
+                }
+            }
+        }
+        rows
+    }
+}
+
+/// Config for the RLP Circuit
+#[derive(Clone, Debug)]
+pub struct RlpCircuitConfig<F> {
+    randomness: Expression<F>,
+    q_enable: Column<Fixed>,
+    q_first: Column<Fixed>,
+    q_last: Column<Fixed>,
+    /// Fixed table with the values `0..256`
+    u8_table: Column<Fixed>,
+    tag_table: RlpTagTable,
+    // Columns constant within an encoding
+    tx_id: Column<Advice>,
+    data_type: Column<Advice>,
+    tx_type: Column<Advice>,
+    block_number: Column<Advice>,
+    length: Column<Advice>,
+    hash: Column<Advice>,
+    // Columns of the bytes of an encoding
+    padding: Column<Advice>,
+    is_start: Column<Advice>,
+    is_last: Column<Advice>,
+    index: Column<Advice>,
+    bytes_rlc: Column<Advice>,
+    // Columns constant within an item
+    tag: Column<Advice>,
+    tx_tag: Column<Advice>,
+    is_tx_field: Column<Advice>,
+    is_chain_id: Column<Advice>,
+    is_header: Column<Advice>,
+    is_raw: Column<Advice>,
+    is_word: Column<Advice>,
+    is_address: Column<Advice>,
+    is_data: Column<Advice>,
+    value: Column<Advice>,
+    is_single: Column<Advice>,
+    is_long: Column<Advice>,
+    len_len: Column<Advice>,
+    payload_len: Column<Advice>,
+    // Columns of the bytes of an item
+    byte: Column<Advice>,
+    is_item_start: Column<Advice>,
+    is_item_end: Column<Advice>,
+    is_prefix: Column<Advice>,
+    is_len: Column<Advice>,
+    is_payload: Column<Advice>,
+    is_first_payload: Column<Advice>,
+    item_index: Column<Advice>,
+    len_acc: Column<Advice>,
+    value_acc: Column<Advice>,
+    tx_table: TxTable,
+    block_table: BlockTable,
+    keccak_table: KeccakTable,
+}
+
+impl<F: Field> RlpCircuitConfig<F> {
+    /// Configure the RLP Circuit
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        randomness: Expression<F>,
+        tx_table: TxTable,
+        block_table: BlockTable,
+        keccak_table: KeccakTable,
+    ) -> Self {
+        let q_enable = meta.fixed_column();
+        let q_first = meta.fixed_column();
+        let q_last = meta.fixed_column();
+        let u8_table = meta.fixed_column();
+        let tag_table = RlpTagTable::construct(meta);
+        let [tx_id, data_type, tx_type, block_number, length, hash] =
+            [(); 6].map(|_| meta.advice_column());
+        let [padding, is_start, is_last, index, bytes_rlc] = [(); 5].map(|_| meta.advice_column());
+        let [tag, tx_tag, is_tx_field, is_chain_id, is_header, is_raw, is_word, is_address, is_data] =
+            [(); 9].map(|_| meta.advice_column());
+        let [value, is_single, is_long, len_len, payload_len] =
+            [(); 5].map(|_| meta.advice_column());
+        let [byte, is_item_start, is_item_end, is_prefix, is_len] =
+            [(); 5].map(|_| meta.advice_column());
+        let [is_payload, is_first_payload, item_index, len_acc, value_acc] =
+            [(); 5].map(|_| meta.advice_column());
+
+        // Integers are the items that are neither headers, raw bytes, words,
+        // addresses nor byte strings.
+        let is_int = |meta: &mut VirtualCells<F>| {
+            1.expr()
+                - meta.query_advice(is_header, Rotation::cur())
+                - meta.query_advice(is_raw, Rotation::cur())
+                - meta.query_advice(is_word, Rotation::cur())
+                - meta.query_advice(is_address, Rotation::cur())
+                - meta.query_advice(is_data, Rotation::cur())
+        };
+        // Length of the prefix and the bytes of the payload length of an item
+        let header_len = |meta: &mut VirtualCells<F>| {
+            not::expr(meta.query_advice(is_single, Rotation::cur()))
+                * (1.expr() + meta.query_advice(len_len, Rotation::cur()))
+        };
+
+        meta.create_gate("rlp row", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let q_first = meta.query_fixed(q_first, Rotation::cur());
+            let is_padding = meta.query_advice(padding, Rotation::cur());
+
+            for (name, column) in [
+                ("padding is boolean", padding),
+                ("is_start is boolean", is_start),
+                ("is_last is boolean", is_last),
+                ("is_item_start is boolean", is_item_start),
+                ("is_item_end is boolean", is_item_end),
+                ("is_prefix is boolean", is_prefix),
+                ("is_len is boolean", is_len),
+                ("is_payload is boolean", is_payload),
+                ("is_first_payload is boolean", is_first_payload),
+                ("is_single is boolean", is_single),
+                ("is_long is boolean", is_long),
+            ] {
+                cb.require_boolean(name, meta.query_advice(column, Rotation::cur()));
+            }
+            cb.require_equal(
+                "a row is a prefix, length or payload byte unless it's padding",
+                meta.query_advice(is_prefix, Rotation::cur())
+                    + meta.query_advice(is_len, Rotation::cur())
+                    + meta.query_advice(is_payload, Rotation::cur()),
+                not::expr(is_padding.clone()),
+            );
+            cb.require_zero(
+                "padding rows don't start nor end encodings or items",
+                is_padding.clone()
+                    * (meta.query_advice(is_start, Rotation::cur())
+                        + meta.query_advice(is_last, Rotation::cur())
+                        + meta.query_advice(is_item_start, Rotation::cur())
+                        + meta.query_advice(is_item_end, Rotation::cur())),
+            );
+            cb.require_equal(
+                "is_start := q_first || is_last_prev, unless padding",
+                meta.query_advice(is_start, Rotation::cur()),
+                not::expr(is_padding.clone())
+                    * select::expr(
+                        q_first.clone(),
+                        1.expr(),
+                        meta.query_advice(is_last, Rotation::prev()),
+                    ),
+            );
+            cb.require_equal(
+                "is_item_start := q_first || is_item_end_prev, unless padding",
+                meta.query_advice(is_item_start, Rotation::cur()),
+                not::expr(is_padding.clone())
+                    * select::expr(
+                        q_first,
+                        1.expr(),
+                        meta.query_advice(is_item_end, Rotation::prev()),
+                    ),
+            );
+            cb.require_zero(
+                "the last byte of an encoding ends an item",
+                meta.query_advice(is_last, Rotation::cur())
+                    * not::expr(meta.query_advice(is_item_end, Rotation::cur())),
+            );
+            cb.require_equal(
+                "is_first_payload := is_payload && (is_item_start || !is_payload_prev)",
+                meta.query_advice(is_first_payload, Rotation::cur()),
+                meta.query_advice(is_payload, Rotation::cur())
+                    * not::expr(
+                        not::expr(meta.query_advice(is_item_start, Rotation::cur()))
+                            * meta.query_advice(is_payload, Rotation::prev()),
+                    ),
+            );
+            cb.condition(meta.query_fixed(q_last, Rotation::cur()), |cb| {
+                cb.require_equal(
+                    "the last row is padding or the end of an encoding",
+                    is_padding + meta.query_advice(is_last, Rotation::cur()),
+                    1.expr(),
+                );
+            });
+
+            // Conditions: Always
+            cb.gate(meta.query_fixed(q_enable, Rotation::cur()))
+        });
+
+        meta.create_gate("rlp padding", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let is_padding = meta.query_advice(padding, Rotation::cur());
+            let is_padding_prev = meta.query_advice(padding, Rotation::prev());
+            cb.require_boolean(
+                "padding can only go 0 -> 1 once",
+                is_padding.clone() - is_padding_prev.clone(),
+            );
+            cb.require_zero(
+                "padding starts after the last byte of a TxHash encoding",
+                is_padding
+                    * not::expr(is_padding_prev)
+                    * not::expr(
+                        meta.query_advice(is_last, Rotation::prev())
+                            * meta.query_advice(data_type, Rotation::prev()),
+                    ),
+            );
+
+            // Conditions:
+            // - Not on the first row
+            cb.gate(and::expr(vec![
+                meta.query_fixed(q_enable, Rotation::cur()),
+                not::expr(meta.query_fixed(q_first, Rotation::cur())),
+            ]))
+        });
+
+        meta.create_gate("rlp encoding", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let q_first = meta.query_fixed(q_first, Rotation::cur());
+            let is_start = meta.query_advice(is_start, Rotation::cur());
+            let byte = meta.query_advice(byte, Rotation::cur());
+            let data_type_prev = meta.query_advice(data_type, Rotation::prev());
+
+            cb.condition(is_start.clone(), |cb| {
+                cb.require_zero(
+                    "index starts at 0",
+                    meta.query_advice(index, Rotation::cur()),
+                );
+                cb.require_equal(
+                    "bytes_rlc starts with the first byte",
+                    meta.query_advice(bytes_rlc, Rotation::cur()),
+                    byte.clone(),
+                );
+                cb.require_equal(
+                    "tx_id := 1 on the first row, then tx_id_prev + data_type_prev",
+                    meta.query_advice(tx_id, Rotation::cur()),
+                    select::expr(
+                        q_first.clone(),
+                        1.expr(),
+                        meta.query_advice(tx_id, Rotation::prev()) + data_type_prev.clone(),
+                    ),
+                );
+                cb.require_equal(
+                    "data_type := TxSign on the first row, then alternates",
+                    meta.query_advice(data_type, Rotation::cur()),
+                    select::expr(
+                        q_first,
+                        RlpDataType::TxSign.expr(),
+                        not::expr(data_type_prev),
+                    ),
+                );
+            });
+            cb.condition(not::expr(is_start), |cb| {
+                for (name, column) in [
+                    ("tx_id needs to remain the same", tx_id),
+                    ("data_type needs to remain the same", data_type),
+                    ("tx_type needs to remain the same", tx_type),
+                    ("block_number needs to remain the same", block_number),
+                    ("length needs to remain the same", length),
+                    ("hash needs to remain the same", hash),
+                ] {
+                    cb.require_equal(
+                        name,
+                        meta.query_advice(column, Rotation::cur()),
+                        meta.query_advice(column, Rotation::prev()),
+                    );
+                }
+                cb.require_equal(
+                    "index := index_prev + 1",
+                    meta.query_advice(index, Rotation::cur()),
+                    meta.query_advice(index, Rotation::prev()) + 1.expr(),
+                );
+                cb.require_equal(
+                    "bytes_rlc := bytes_rlc_prev * randomness + byte",
+                    meta.query_advice(bytes_rlc, Rotation::cur()),
+                    meta.query_advice(bytes_rlc, Rotation::prev()) * randomness.clone() + byte,
+                );
+            });
+
+            // Conditions:
+            // - Not padding
+            cb.gate(and::expr(vec![
+                meta.query_fixed(q_enable, Rotation::cur()),
+                not::expr(meta.query_advice(padding, Rotation::cur())),
+            ]))
+        });
+
+        meta.create_gate("rlp encoding end", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            cb.require_equal(
+                "index + 1 needs to equal length",
+                meta.query_advice(index, Rotation::cur()) + 1.expr(),
+                meta.query_advice(length, Rotation::cur()),
+            );
+
+            // Conditions:
+            // - On the last byte of an encoding
+            cb.gate(and::expr(vec![
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_last, Rotation::cur()),
+            ]))
+        });
+
+        meta.create_gate("rlp item", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let is_prefix_prev = meta.query_advice(is_prefix, Rotation::prev());
+            let is_payload_prev = meta.query_advice(is_payload, Rotation::prev());
+            let is_len_next = meta.query_advice(is_len, Rotation::next());
+            let item_columns = [
+                ("tag needs to remain the same", tag),
+                ("tx_tag needs to remain the same", tx_tag),
+                ("is_tx_field needs to remain the same", is_tx_field),
+                ("is_chain_id needs to remain the same", is_chain_id),
+                ("is_header needs to remain the same", is_header),
+                ("is_raw needs to remain the same", is_raw),
+                ("is_word needs to remain the same", is_word),
+                ("is_address needs to remain the same", is_address),
+                ("is_data needs to remain the same", is_data),
+                ("value needs to remain the same", value),
+                ("is_single needs to remain the same", is_single),
+                ("is_long needs to remain the same", is_long),
+                ("len_len needs to remain the same", len_len),
+                ("payload_len needs to remain the same", payload_len),
+            ];
+            let is_item_start = meta.query_advice(is_item_start, Rotation::cur());
+            let is_single = meta.query_advice(is_single, Rotation::cur());
+            let is_long = meta.query_advice(is_long, Rotation::cur());
+            let is_prefix = meta.query_advice(is_prefix, Rotation::cur());
+            let is_len = meta.query_advice(is_len, Rotation::cur());
+            let is_payload = meta.query_advice(is_payload, Rotation::cur());
+            let byte = meta.query_advice(byte, Rotation::cur());
+
+            cb.condition(is_item_start.clone(), |cb| {
+                cb.require_zero(
+                    "item_index starts at 0",
+                    meta.query_advice(item_index, Rotation::cur()),
+                );
+                cb.require_equal(
+                    "items start with a prefix unless they are a single byte",
+                    is_prefix.clone(),
+                    not::expr(is_single.clone()),
+                );
+            });
+            cb.condition(not::expr(is_item_start.clone()), |cb| {
+                for (name, column) in item_columns {
+                    cb.require_equal(
+                        name,
+                        meta.query_advice(column, Rotation::cur()),
+                        meta.query_advice(column, Rotation::prev()),
+                    );
+                }
+                cb.require_equal(
+                    "item_index := item_index_prev + 1",
+                    meta.query_advice(item_index, Rotation::cur()),
+                    meta.query_advice(item_index, Rotation::prev()) + 1.expr(),
+                );
+                cb.require_zero("only the first byte is a prefix", is_prefix.clone());
+                cb.require_zero(
+                    "the prefix of a long item is followed by the length bytes",
+                    is_prefix_prev * (is_len.clone() - is_long.clone()),
+                );
+                cb.require_zero(
+                    "the length bytes are before the payload",
+                    is_payload_prev * is_len.clone(),
+                );
+            });
+            cb.condition(is_single.clone(), |cb| {
+                cb.require_equal("a single item is a payload byte", is_payload.clone(), 1.expr());
+                cb.require_equal(
+                    "a single item is a single byte",
+                    meta.query_advice(is_item_end, Rotation::cur()),
+                    1.expr(),
+                );
+                cb.require_equal(
+                    "the payload of a single item is a single byte",
+                    meta.query_advice(payload_len, Rotation::cur()),
+                    1.expr(),
+                );
+            });
+            cb.condition(is_prefix.clone(), |cb| {
+                cb.require_equal(
+                    "prefix := 0x80 + 0x40 * is_header + (is_long ? 55 + len_len : payload_len)",
+                    byte.clone(),
+                    0x80.expr()
+                        + 0x40.expr() * meta.query_advice(is_header, Rotation::cur())
+                        + select::expr(
+                            is_long.clone(),
+                            55.expr() + meta.query_advice(len_len, Rotation::cur()),
+                            meta.query_advice(payload_len, Rotation::cur()),
+                        ),
+                );
+            });
+            cb.require_zero(
+                "raw items are a single byte",
+                meta.query_advice(is_raw, Rotation::cur()) * not::expr(is_single),
+            );
+            cb.require_zero(
+                "only long items have length bytes",
+                is_len.clone() * not::expr(is_long.clone()),
+            );
+            cb.require_zero(
+                "short items have no length bytes",
+                not::expr(is_long) * meta.query_advice(len_len, Rotation::cur()),
+            );
+            cb.require_zero(
+                "headers have no payload bytes",
+                meta.query_advice(is_header, Rotation::cur()) * is_payload.clone(),
+            );
+            cb.require_zero(
+                "the last length byte is at item_index == len_len",
+                is_len.clone()
+                    * not::expr(is_len_next)
+                    * (meta.query_advice(item_index, Rotation::cur())
+                        - meta.query_advice(len_len, Rotation::cur())),
+            );
+            cb.require_equal(
+                "len_acc := is_len ? len_acc_prev * 256 + byte : len_acc_prev, reset on item start",
+                meta.query_advice(len_acc, Rotation::cur()),
+                select::expr(
+                    is_len,
+                    meta.query_advice(len_acc, Rotation::prev()) * 256.expr() + byte.clone(),
+                    not::expr(is_item_start) * meta.query_advice(len_acc, Rotation::prev()),
+                ),
+            );
+            // Words are accumulated as the RLC of their little-endian bytes,
+            // and the other items as big-endian integers.
+            cb.require_equal(
+                "value_acc := is_payload ? value_acc_prev * mult + byte : 0, reset on first payload byte",
+                meta.query_advice(value_acc, Rotation::cur()),
+                is_payload.clone()
+                    * (not::expr(meta.query_advice(is_first_payload, Rotation::cur()))
+                        * meta.query_advice(value_acc, Rotation::prev())
+                        * select::expr(
+                            meta.query_advice(is_word, Rotation::cur()),
+                            randomness.clone(),
+                            256.expr(),
+                        )
+                        + byte),
+            );
+
+            // Conditions:
+            // - Not padding
+            cb.gate(and::expr(vec![
+                meta.query_fixed(q_enable, Rotation::cur()),
+                not::expr(meta.query_advice(padding, Rotation::cur())),
+            ]))
+        });
+
+        meta.create_gate("rlp item end", |meta| {
+            let mut cb = BaseConstraintBuilder::default();
+            let is_header = meta.query_advice(is_header, Rotation::cur());
+            let is_data = meta.query_advice(is_data, Rotation::cur());
+            let value = meta.query_advice(value, Rotation::cur());
+            let payload_len = meta.query_advice(payload_len, Rotation::cur());
+
+            cb.require_equal(
+                "item_index + 1 == header_len + payload_len (excluded for headers)",
+                meta.query_advice(item_index, Rotation::cur()) + 1.expr(),
+                header_len(meta) + not::expr(is_header.clone()) * payload_len.clone(),
+            );
+            cb.require_zero(
+                "the length bytes of a long item are the payload length",
+                meta.query_advice(is_long, Rotation::cur())
+                    * (meta.query_advice(len_acc, Rotation::cur()) - payload_len.clone()),
+            );
+            cb.require_zero(
+                "the value of headers and byte strings is the payload length",
+                (is_header.clone() + is_data.clone()) * (value.clone() - payload_len.clone()),
+            );
+            cb.require_zero(
+                "the value of integers, words and addresses is the accumulated payload",
+                (1.expr()
+                    - is_header.clone()
+                    - is_data
+                    - meta.query_advice(is_raw, Rotation::cur()))
+                    * (value.clone() - meta.query_advice(value_acc, Rotation::cur())),
+            );
+            cb.require_zero(
+                "the list payload is the rest of the encoding",
+                is_header
+                    * (value
+                        - (meta.query_advice(length, Rotation::cur())
+                            - meta.query_advice(index, Rotation::cur())
+                            - 1.expr())),
+            );
+            cb.require_zero(
+                "addresses are 20 bytes or empty",
+                meta.query_advice(is_address, Rotation::cur())
+                    * payload_len.clone()
+                    * (payload_len - 20.expr()),
+            );
+
+            // Conditions:
+            // - On the last byte of an item
+            cb.gate(and::expr(vec![
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_item_end, Rotation::cur()),
+            ]))
+        });
+
+        // Range checks of the bytes and lengths, which also make the encoding
+        // canonical.
+        range_check(meta, q_enable, u8_table, "rlp byte range", |meta| {
+            (1.expr(), meta.query_advice(byte, Rotation::cur()))
+        });
+        range_check(meta, q_enable, u8_table, "rlp single byte < 0x80", |meta| {
+            (
+                meta.query_advice(is_single, Rotation::cur())
+                    * not::expr(meta.query_advice(is_raw, Rotation::cur())),
+                meta.query_advice(byte, Rotation::cur()) + 128.expr(),
+            )
+        });
+        range_check(
+            meta,
+            q_enable,
+            u8_table,
+            "rlp prefixed single byte >= 0x80",
+            |meta| {
+                (
+                    meta.query_advice(is_first_payload, Rotation::cur())
+                        * meta.query_advice(is_item_end, Rotation::cur())
+                        * not::expr(meta.query_advice(is_single, Rotation::cur())),
+                    meta.query_advice(byte, Rotation::cur()) - 128.expr(),
+                )
+            },
+        );
+        range_check(
+            meta,
+            q_enable,
+            u8_table,
+            "rlp integer without leading zeros",
+            |meta| {
+                (
+                    meta.query_advice(is_first_payload, Rotation::cur())
+                        * (is_int(meta) + meta.query_advice(is_word, Rotation::cur())),
+                    meta.query_advice(byte, Rotation::cur()) - 1.expr(),
+                )
+            },
+        );
+        range_check(meta, q_enable, u8_table, "rlp word length <= 32", |meta| {
+            (
+                meta.query_advice(is_item_end, Rotation::cur())
+                    * meta.query_advice(is_word, Rotation::cur()),
+                32.expr() - meta.query_advice(payload_len, Rotation::cur()),
+            )
+        });
+        range_check(
+            meta,
+            q_enable,
+            u8_table,
+            "rlp integer length <= 8",
+            |meta| {
+                (
+                    meta.query_advice(is_item_end, Rotation::cur()) * is_int(meta),
+                    8.expr() - meta.query_advice(payload_len, Rotation::cur()),
+                )
+            },
+        );
+        range_check(
+            meta,
+            q_enable,
+            u8_table,
+            "rlp short item length <= 55",
+            |meta| {
+                (
+                    meta.query_advice(is_item_end, Rotation::cur())
+                        * not::expr(meta.query_advice(is_long, Rotation::cur()))
+                        * not::expr(meta.query_advice(is_single, Rotation::cur())),
+                    55.expr() - meta.query_advice(payload_len, Rotation::cur()),
+                )
+            },
+        );
+        range_check(
+            meta,
+            q_enable,
+            u8_table,
+            "rlp length of length <= 8",
+            |meta| {
+                (
+                    meta.query_advice(is_prefix, Rotation::cur())
+                        * meta.query_advice(is_long, Rotation::cur()),
+                    8.expr() - meta.query_advice(len_len, Rotation::cur()),
+                )
+            },
+        );
+        range_check(
+            meta,
+            q_enable,
+            u8_table,
+            "rlp length without leading zeros",
+            |meta| {
+                (
+                    meta.query_advice(is_len, Rotation::cur())
+                        * meta.query_advice(is_prefix, Rotation::prev()),
+                    meta.query_advice(byte, Rotation::cur()) - 1.expr(),
+                )
+            },
+        );
+        range_check(
+            meta,
+            q_enable,
+            u8_table,
+            "rlp single length byte >= 56",
+            |meta| {
+                (
+                    meta.query_advice(is_len, Rotation::cur())
+                        * meta.query_advice(is_prefix, Rotation::prev())
+                        * not::expr(meta.query_advice(is_len, Rotation::next())),
+                    meta.query_advice(byte, Rotation::cur()) - 56.expr(),
+                )
+            },
+        );
+
+        meta.lookup_any("rlp item tag", |meta| {
+            // Conditions:
+            // - On the last byte of an item
+            let enable = and::expr(vec![
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_item_end, Rotation::cur()),
+            ]);
+            let is_raw = meta.query_advice(is_raw, Rotation::cur());
+            let tag_next = not::expr(meta.query_advice(is_last, Rotation::cur()))
+                * meta.query_advice(tag, Rotation::next());
+            let raw_byte = is_raw.clone() * meta.query_advice(byte, Rotation::cur());
+            let t = &tag_table;
+            vec![
+                (1.expr(), t.enabled),
+                (meta.query_advice(tx_type, Rotation::cur()), t.tx_type),
+                (meta.query_advice(data_type, Rotation::cur()), t.data_type),
+                (meta.query_advice(tag, Rotation::cur()), t.tag),
+                (tag_next, t.tag_next),
+                (meta.query_advice(tx_tag, Rotation::cur()), t.tx_tag),
+                (
+                    meta.query_advice(is_tx_field, Rotation::cur()),
+                    t.is_tx_field,
+                ),
+                (
+                    meta.query_advice(is_chain_id, Rotation::cur()),
+                    t.is_chain_id,
+                ),
+                (meta.query_advice(is_header, Rotation::cur()), t.is_header),
+                (is_raw, t.is_raw),
+                (raw_byte, t.raw_byte),
+                (meta.query_advice(is_word, Rotation::cur()), t.is_word),
+                (meta.query_advice(is_address, Rotation::cur()), t.is_address),
+                (meta.query_advice(is_data, Rotation::cur()), t.is_data),
+            ]
+            .into_iter()
+            .map(|(input, column)| {
+                (
+                    enable.clone() * input,
+                    meta.query_fixed(column, Rotation::cur()),
+                )
+            })
+            .collect()
+        });
+
+        meta.lookup_any("rlp first item tag", |meta| {
+            // Conditions:
+            // - On the first byte of an encoding
+            let enable = and::expr(vec![
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_start, Rotation::cur()),
+            ]);
+            let t = &tag_table;
+            vec![
+                (1.expr(), t.enabled),
+                (meta.query_advice(tx_type, Rotation::cur()), t.tx_type),
+                (meta.query_advice(data_type, Rotation::cur()), t.data_type),
+                (meta.query_advice(tag, Rotation::cur()), t.tag),
+                (1.expr(), t.is_first),
+            ]
+            .into_iter()
+            .map(|(input, column)| {
+                (
+                    enable.clone() * input,
+                    meta.query_fixed(column, Rotation::cur()),
+                )
+            })
+            .collect()
+        });
+
+        // Tx Table lookups of the (tag, index, value) of a field of the tx
+        tx_lookup(meta, q_enable, tx_id, &tx_table, "rlp tx type", |meta| {
+            (
+                meta.query_advice(is_start, Rotation::cur()),
+                [
+                    TxFieldTag::TxType.expr(),
+                    0.expr(),
+                    meta.query_advice(tx_type, Rotation::cur()),
+                ],
+            )
+        });
+        tx_lookup(
+            meta,
+            q_enable,
+            tx_id,
+            &tx_table,
+            "rlp tx block number",
+            |meta| {
+                (
+                    meta.query_advice(is_start, Rotation::cur()),
+                    [
+                        TxFieldTag::BlockNumber.expr(),
+                        0.expr(),
+                        meta.query_advice(block_number, Rotation::cur()),
+                    ],
+                )
+            },
+        );
+        tx_lookup(meta, q_enable, tx_id, &tx_table, "rlp tx field", |meta| {
+            (
+                meta.query_advice(is_item_end, Rotation::cur())
+                    * meta.query_advice(is_tx_field, Rotation::cur()),
+                [
+                    meta.query_advice(tx_tag, Rotation::cur()),
+                    0.expr(),
+                    meta.query_advice(value, Rotation::cur()),
+                ],
+            )
+        });
+        tx_lookup(
+            meta,
+            q_enable,
+            tx_id,
+            &tx_table,
+            "rlp tx is create",
+            |meta| {
+                // is_create := (20 - payload_len) / 20
+                let inv_20 = Expression::Constant(F::from(20).invert().unwrap());
+                (
+                    meta.query_advice(is_item_end, Rotation::cur())
+                        * meta.query_advice(is_address, Rotation::cur()),
+                    [
+                        TxFieldTag::IsCreate.expr(),
+                        0.expr(),
+                        (20.expr() - meta.query_advice(payload_len, Rotation::cur())) * inv_20,
+                    ],
+                )
+            },
+        );
+        tx_lookup(
+            meta,
+            q_enable,
+            tx_id,
+            &tx_table,
+            "rlp tx call data",
+            |meta| {
+                (
+                    meta.query_advice(is_payload, Rotation::cur())
+                        * meta.query_advice(is_data, Rotation::cur()),
+                    [
+                        TxFieldTag::CallData.expr(),
+                        meta.query_advice(item_index, Rotation::cur()) - header_len(meta),
+                        meta.query_advice(byte, Rotation::cur()),
+                    ],
+                )
+            },
+        );
+        // NOTE: The TxSignHash in the Tx Table is the hash reduced modulo the order
+        // of the secp256k1 scalar field, so this lookup fails for the negligible
+        // fraction of hashes which are greater than the order.
+        tx_lookup(meta, q_enable, tx_id, &tx_table, "rlp tx hash", |meta| {
+            (
+                meta.query_advice(is_last, Rotation::cur()),
+                [
+                    select::expr(
+                        meta.query_advice(data_type, Rotation::cur()),
+                        TxFieldTag::TxHash.expr(),
+                        TxFieldTag::TxSignHash.expr(),
+                    ),
+                    0.expr(),
+                    meta.query_advice(hash, Rotation::cur()),
+                ],
+            )
+        });
+
+        meta.lookup_any("rlp block chain id", |meta| {
+            // Conditions:
+            // - On the last byte of the chain id item
+            let enable = and::expr(vec![
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_item_end, Rotation::cur()),
+                meta.query_advice(is_chain_id, Rotation::cur()),
+            ]);
+            vec![
+                (BlockContextFieldTag::ChainId.expr(), block_table.tag),
+                (
+                    meta.query_advice(block_number, Rotation::cur()),
+                    block_table.index,
+                ),
+                (meta.query_advice(value, Rotation::cur()), block_table.value),
+            ]
+            .into_iter()
+            .map(|(input, column)| {
+                (
+                    enable.clone() * input,
+                    meta.query_advice(column, Rotation::cur()),
+                )
+            })
+            .collect()
+        });
+
+        meta.lookup_any("rlp keccak", |meta| {
+            // Conditions:
+            // - On the last byte of an encoding
+            let enable = and::expr(vec![
+                meta.query_fixed(q_enable, Rotation::cur()),
+                meta.query_advice(is_last, Rotation::cur()),
+            ]);
+            vec![
+                (1.expr(), keccak_table.is_enabled),
+                (
+                    meta.query_advice(bytes_rlc, Rotation::cur()),
+                    keccak_table.input_rlc,
+                ),
+                (
+                    meta.query_advice(length, Rotation::cur()),
+                    keccak_table.input_len,
+                ),
+                (
+                    meta.query_advice(hash, Rotation::cur()),
+                    keccak_table.output_rlc,
+                ),
+            ]
+            .into_iter()
+            .map(|(input, column)| {
+                (
+                    enable.clone() * input,
+                    meta.query_advice(column, Rotation::cur()),
+                )
+            })
+            .collect()
+        });
+
+        Self {
+            randomness,
+            q_enable,
+            q_first,
+            q_last,
+            u8_table,
+            tag_table,
+            tx_id,
+            data_type,
+            tx_type,
+            block_number,
+            length,
+            hash,
+            padding,
+            is_start,
+            is_last,
+            index,
+            bytes_rlc,
+            tag,
+            tx_tag,
+            is_tx_field,
+            is_chain_id,
+            is_header,
+            is_raw,
+            is_word,
+            is_address,
+            is_data,
+            value,
+            is_single,
+            is_long,
+            len_len,
+            payload_len,
+            byte,
+            is_item_start,
+            is_item_end,
+            is_prefix,
+            is_len,
+            is_payload,
+            is_first_payload,
+            item_index,
+            len_acc,
+            value_acc,
+            tx_table,
+            block_table,
+            keccak_table,
+        }
+    }
+
+    /// Load the fixed tables
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_region(
+            || "u8 table",
+            |mut region| {
+                for byte in 0..256 {
+                    region.assign_fixed(
+                        || format!("u8 table row {}", byte),
+                        self.u8_table,
+                        byte,
+                        || Ok(F::from(byte as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+
+        layouter.assign_region(
+            || "rlp tag table",
+            |mut region| {
+                let rows = iter::once([0; 15]).chain(RlpTagTable::rows());
+                for (offset, row) in rows.enumerate() {
+                    for (column, value) in self.tag_table.columns().iter().zip(row) {
+                        region.assign_fixed(
+                            || format!("rlp tag table row {}", offset),
+                            *column,
+                            offset,
+                            || Ok(F::from(value)),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Assign the encodings of `txs` using `size` rows, the last ones being
+    /// padding.
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        size: usize,
+        txs: &[Transaction],
+        chain_id: u64,
+        randomness: F,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rlp circuit",
+            |mut region| {
+                let mut offset = 0;
+                for (idx, tx) in txs.iter().enumerate() {
+                    for data_type in RlpDataType::ALL {
+                        let tx_type = TxType::from_tx(tx)?;
+                        let items = rlp_items(tx, chain_id, data_type)?;
+                        let bytes: Vec<u8> =
+                            items.iter().flat_map(|item| item.bytes.clone()).collect();
+                        if offset + bytes.len() > size {
+                            error!("tx encodings exceed the RLP Circuit size ({})", size);
+                            return Err(Error::Synthesis);
+                        }
+                        let [_, _, _, hash] = KeccakTable::assignments(&bytes, randomness)[0];
+                        let encoding = [
+                            ("tx_id", self.tx_id, F::from(idx as u64 + 1)),
+                            ("data_type", self.data_type, F::from(data_type as u64)),
+                            ("tx_type", self.tx_type, F::from(tx_type as u64)),
+                            ("block_number", self.block_number, F::from(tx.block_number)),
+                            ("length", self.length, F::from(bytes.len() as u64)),
+                            ("hash", self.hash, hash),
+                        ];
+                        let mut index = 0;
+                        let mut bytes_rlc = F::zero();
+                        for (item_idx, item) in items.iter().enumerate() {
+                            let is_last_item = item_idx == items.len() - 1;
+                            self.assign_item(
+                                &mut region,
+                                offset,
+                                size,
+                                &encoding,
+                                item,
+                                is_last_item,
+                                &mut index,
+                                &mut bytes_rlc,
+                                randomness,
+                            )?;
+                            offset += item.bytes.len();
+                        }
+                    }
+                }
+
+                for offset in offset..size {
+                    self.assign_fixed_row(&mut region, offset, size)?;
+                    for (name, column) in self.advice_columns() {
+                        let value = if column == self.padding {
+                            F::one()
+                        } else {
+                            F::zero()
+                        };
+                        region.assign_advice(
+                            || format!("assign {} {}", name, offset),
+                            column,
+                            offset,
+                            || Ok(value),
+                        )?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn assign_item(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        size: usize,
+        encoding: &[(&'static str, Column<Advice>, F)],
+        item: &RlpItem,
+        is_last_item: bool,
+        index: &mut usize,
+        bytes_rlc: &mut F,
+        randomness: F,
+    ) -> Result<(), Error> {
+        let tx_tag = match item.kind {
+            RlpItemKind::Raw(_) => None,
+            _ => item.tag.tx_field_tag(),
+        };
+        let header_len = if item.is_single { 0 } else { 1 + item.len_len };
+        let mult = match item.kind {
+            RlpItemKind::Word => randomness,
+            _ => F::from(256),
+        };
+        // The accumulated payload is the value of integers, words and addresses
+        let payload_acc = item.bytes[header_len..]
+            .iter()
+            .fold(F::zero(), |acc, byte| acc * mult + F::from(*byte as u64));
+        let value = match item.kind {
+            RlpItemKind::Header | RlpItemKind::Data => F::from(item.payload_len as u64),
+            RlpItemKind::Raw(_) => F::zero(),
+            _ => payload_acc,
+        };
+        let item_values = [
+            ("tag", self.tag, F::from(item.tag as u64)),
+            (
+                "tx_tag",
+                self.tx_tag,
+                F::from(tx_tag.map_or(0, |tag| tag as u64)),
+            ),
+            (
+                "is_tx_field",
+                self.is_tx_field,
+                F::from(tx_tag.is_some() as u64),
+            ),
+            (
+                "is_chain_id",
+                self.is_chain_id,
+                F::from((item.tag == RlpTxTag::ChainId) as u64),
+            ),
+            (
+                "is_header",
+                self.is_header,
+                F::from((item.kind == RlpItemKind::Header) as u64),
+            ),
+            (
+                "is_raw",
+                self.is_raw,
+                F::from(matches!(item.kind, RlpItemKind::Raw(_)) as u64),
+            ),
+            (
+                "is_word",
+                self.is_word,
+                F::from((item.kind == RlpItemKind::Word) as u64),
+            ),
+            (
+                "is_address",
+                self.is_address,
+                F::from((item.kind == RlpItemKind::Address) as u64),
+            ),
+            (
+                "is_data",
+                self.is_data,
+                F::from((item.kind == RlpItemKind::Data) as u64),
+            ),
+            ("value", self.value, value),
+            ("is_single", self.is_single, F::from(item.is_single as u64)),
+            ("is_long", self.is_long, F::from(item.is_long() as u64)),
+            ("len_len", self.len_len, F::from(item.len_len as u64)),
+            (
+                "payload_len",
+                self.payload_len,
+                F::from(item.payload_len as u64),
+            ),
+        ];
+
+        let mut len_acc = F::zero();
+        let mut value_acc = F::zero();
+        for (item_index, byte) in item.bytes.iter().enumerate() {
+            let offset = offset + item_index;
+            let byte_value = F::from(*byte as u64);
+            let is_prefix = !item.is_single && item_index == 0;
+            let is_len = !item.is_single && item_index >= 1 && item_index <= item.len_len;
+            let is_payload = !is_prefix && !is_len;
+            if is_len {
+                len_acc = len_acc * F::from(256) + byte_value;
+            }
+            value_acc = if item_index == header_len {
+                byte_value
+            } else if is_payload {
+                value_acc * mult + byte_value
+            } else {
+                F::zero()
+            };
+            *bytes_rlc = if *index == 0 {
+                byte_value
+            } else {
+                *bytes_rlc * randomness + byte_value
+            };
+            let is_item_end = item_index == item.bytes.len() - 1;
+            let row_values = [
+                ("padding", self.padding, F::zero()),
+                ("is_start", self.is_start, F::from((*index == 0) as u64)),
+                (
+                    "is_last",
+                    self.is_last,
+                    F::from((is_last_item && is_item_end) as u64),
+                ),
+                ("index", self.index, F::from(*index as u64)),
+                ("bytes_rlc", self.bytes_rlc, *bytes_rlc),
+                ("byte", self.byte, byte_value),
+                (
+                    "is_item_start",
+                    self.is_item_start,
+                    F::from((item_index == 0) as u64),
+                ),
+                ("is_item_end", self.is_item_end, F::from(is_item_end as u64)),
+                ("is_prefix", self.is_prefix, F::from(is_prefix as u64)),
+                ("is_len", self.is_len, F::from(is_len as u64)),
+                ("is_payload", self.is_payload, F::from(is_payload as u64)),
+                (
+                    "is_first_payload",
+                    self.is_first_payload,
+                    F::from((item_index == header_len) as u64),
+                ),
+                ("item_index", self.item_index, F::from(item_index as u64)),
+                ("len_acc", self.len_acc, len_acc),
+                ("value_acc", self.value_acc, value_acc),
+            ];
+
+            self.assign_fixed_row(region, offset, size)?;
+            for (name, column, value) in encoding.iter().chain(&item_values).chain(&row_values) {
+                region.assign_advice(
+                    || format!("assign {} {}", name, offset),
+                    *column,
+                    offset,
+                    || Ok(*value),
+                )?;
+            }
+            *index += 1;
+        }
+        Ok(())
+    }
+
+    /// Assign the fixed columns of a row of a circuit of `size` rows
+    fn assign_fixed_row(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: usize,
+        size: usize,
+    ) -> Result<(), Error> {
+        for (name, column, value) in [
+            ("q_enable", self.q_enable, true),
+            ("q_first", self.q_first, offset == 0),
+            ("q_last", self.q_last, offset + 1 == size),
+        ] {
+            region.assign_fixed(
+                || format!("assign {} {}", name, offset),
+                column,
+                offset,
+                || Ok(F::from(value as u64)),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn advice_columns(&self) -> [(&'static str, Column<Advice>); 35] {
+        [
+            ("tx_id", self.tx_id),
+            ("data_type", self.data_type),
+            ("tx_type", self.tx_type),
+            ("block_number", self.block_number),
+            ("length", self.length),
+            ("hash", self.hash),
+            ("padding", self.padding),
+            ("is_start", self.is_start),
+            ("is_last", self.is_last),
+            ("index", self.index),
+            ("bytes_rlc", self.bytes_rlc),
+            ("tag", self.tag),
+            ("tx_tag", self.tx_tag),
+            ("is_tx_field", self.is_tx_field),
+            ("is_chain_id", self.is_chain_id),
+            ("is_header", self.is_header),
+            ("is_raw", self.is_raw),
+            ("is_word", self.is_word),
+            ("is_address", self.is_address),
+            ("is_data", self.is_data),
+            ("value", self.value),
+            ("is_single", self.is_single),
+            ("is_long", self.is_long),
+            ("len_len", self.len_len),
+            ("payload_len", self.payload_len),
+            ("byte", self.byte),
+            ("is_item_start", self.is_item_start),
+            ("is_item_end", self.is_item_end),
+            ("is_prefix", self.is_prefix),
+            ("is_len", self.is_len),
+            ("is_payload", self.is_payload),
+            ("is_first_payload", self.is_first_payload),
+            ("item_index", self.item_index),
+            ("len_acc", self.len_acc),
+            ("value_acc", self.value_acc),
+        ]
+    }
+}
+
+/// Range check a value to a byte, when the condition holds
+fn range_check<F: Field>(
+    meta: &mut ConstraintSystem<F>,
+    q_enable: Column<Fixed>,
+    u8_table: Column<Fixed>,
+    name: &'static str,
+    condition_value: impl FnOnce(&mut VirtualCells<F>) -> (Expression<F>, Expression<F>),
+) {
+    meta.lookup_any(name, |meta| {
+        let (condition, value) = condition_value(meta);
+        vec![(
+            meta.query_fixed(q_enable, Rotation::cur()) * condition * value,
+            meta.query_fixed(u8_table, Rotation::cur()),
+        )]
+    });
+}
+
+/// Lookup the (tag, index, value) of a field of the tx `tx_id` in the Tx
+/// Table, when the condition holds
+fn tx_lookup<F: Field>(
+    meta: &mut ConstraintSystem<F>,
+    q_enable: Column<Fixed>,
+    tx_id: Column<Advice>,
+    tx_table: &TxTable,
+    name: &'static str,
+    condition_field: impl FnOnce(&mut VirtualCells<F>) -> (Expression<F>, [Expression<F>; 3]),
+) {
+    meta.lookup_any(name, |meta| {
+        let (condition, [tag, index, value]) = condition_field(meta);
+        let enable = meta.query_fixed(q_enable, Rotation::cur()) * condition;
+        vec![
+            (meta.query_advice(tx_id, Rotation::cur()), tx_table.tx_id),
+            (tag, tx_table.tag),
+            (index, tx_table.index),
+            (value, tx_table.value),
+        ]
+        .into_iter()
+        .map(|(input, column)| {
+            (
+                enable.clone() * input,
+                meta.query_advice(column, Rotation::cur()),
+            )
+        })
+        .collect()
+    });
+}
+
+/// RLP Circuit, proving the encodings of `txs` with `size` rows
+#[derive(Clone, Default, Debug)]
+pub struct RlpCircuit<F: Field> {
+    /// Randomness for RLC encoding
+    pub randomness: F,
+    /// List of Transactions
+    pub txs: Vec<Transaction>,
+    /// Chain ID
+    pub chain_id: u64,
+    /// Number of rows of the circuit
+    pub size: usize,
+}
+
+impl<F: Field> RlpCircuit<F> {
+    /// Return a new RlpCircuit
+    pub fn new(randomness: F, chain_id: u64, txs: Vec<Transaction>, size: usize) -> Self {
+        Self {
+            randomness,
+            txs,
+            chain_id,
+            size,
+        }
+    }
+
+    /// Return the minimum number of rows required to lay out the encodings of
+    /// `txs`.
+    pub fn min_num_rows(txs: &[Transaction], chain_id: u64) -> Result<usize, Error> {
+        Ok(keccak_inputs(txs, chain_id)?
+            .iter()
+            .map(|bytes| bytes.len())
+            .sum())
+    }
+
+    /// Return the values of the instance column, which holds the randomness
+    pub fn instance(&self) -> Vec<Vec<F>> {
+        vec![vec![self.randomness; self.size]]
+    }
+}
+
+/// Assign the Tx, Block and Keccak tables with the values looked up by the
+/// encodings of `txs`.
+fn load_tables<F: Field>(
+    config: &RlpCircuitConfig<F>,
+    layouter: &mut impl Layouter<F>,
+    txs: &[Transaction],
+    chain_id: u64,
+    randomness: F,
+) -> Result<(), Error> {
+    let mut tx_table_rows = vec![[F::zero(); 4]];
+    for (i, tx) in txs.iter().enumerate() {
+        let tx_id = F::from(i as u64 + 1);
+        let sign_hash_rlc = tx_sign_hash_rlc(tx, chain_id, randomness)?;
+        let hash_rlc = tx_hash_rlc(tx, chain_id, randomness)?;
+        tx_table_rows.extend(
+            tx_field_values(tx, sign_hash_rlc, hash_rlc, randomness)
+                .map(|(tag, value)| [tx_id, F::from(tag as u64), F::zero(), value]),
+        );
+        tx_table_rows.extend(tx.call_data.0.iter().enumerate().map(|(index, byte)| {
+            [
+                tx_id,
+                F::from(TxFieldTag::CallData as u64),
+                F::from(index as u64),
+                F::from(*byte as u64),
+            ]
+        }));
+    }
+    layouter.assign_region(
+        || "tx table",
+        |mut region| {
+            for (offset, row) in tx_table_rows.iter().enumerate() {
+                for (column, value) in config.tx_table.columns().iter().zip(row) {
+                    region.assign_advice(
+                        || format!("tx table row {}", offset),
+                        *column,
+                        offset,
+                        || Ok(*value),
+                    )?;
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    let mut block_numbers: Vec<u64> = txs.iter().map(|tx| tx.block_number).collect();
+    block_numbers.dedup();
+    let blocks: Vec<BlockContext> = block_numbers
+        .into_iter()
+        .map(|number| BlockContext {
+            number: number.into(),
+            chain_id: chain_id.into(),
+            ..BlockContext::default()
+        })
+        .collect();
+    config.block_table.load(layouter, &blocks, randomness)?;
+
+    config.keccak_table.load(
+        layouter,
+        keccak_inputs(txs, chain_id)?.iter().map(|b| b.as_slice()),
+        randomness,
+    )
+}
+
+impl<F: Field> Circuit<F> for RlpCircuit<F> {
+    type Config = RlpCircuitConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let tx_table = TxTable::construct(meta);
+        let block_table = BlockTable::construct(meta);
+        let keccak_table = KeccakTable::construct(meta);
+        let randomness = power_of_randomness_from_instance::<_, 1>(meta)[0].clone();
+        RlpCircuitConfig::configure(meta, randomness, tx_table, block_table, keccak_table)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.load(&mut layouter)?;
+        load_tables(
+            &config,
+            &mut layouter,
+            &self.txs,
+            self.chain_id,
+            self.randomness,
+        )?;
+        config.assign(
+            &mut layouter,
+            self.size,
+            &self.txs,
+            self.chain_id,
+            self.randomness,
+        )
+    }
+}
+
+#[cfg(test)]
+mod rlp_circuit_tests {
+    use super::*;
+    use eth_types::{address, AccessList, Address, Bytes};
+    use ethers_core::{types::transaction::eip2930::AccessListItem, utils::keccak256};
+    use ethers_signers::{LocalWallet, Signer};
+    use halo2_proofs::{
+        dev::{MockProver, VerifyFailure},
+        pairing::bn256::Fr,
+    };
+    use pretty_assertions::assert_eq;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use rlp::RlpStream;
+
+    const CHAIN_ID: u64 = 1337;
+
+    fn run<C: Circuit<Fr>>(circuit: &C, instance: Vec<Vec<Fr>>) -> Result<(), Vec<VerifyFailure>> {
+        let k = 11;
+        let prover = match MockProver::<Fr>::run(k, circuit, instance) {
+            Ok(prover) => prover,
+            Err(e) => panic!("{:#?}", e),
+        };
+        prover.verify()
+    }
+
+    fn sign_tx(wallet: &LocalWallet, tx: Transaction) -> Transaction {
+        let sign_hash = keccak256(tx_rlp(&tx, CHAIN_ID, RlpDataType::TxSign).unwrap());
+        let tx_type = TxType::from_tx(&tx).unwrap();
+        let sig = wallet.sign_hash(sign_hash.into(), tx_type == TxType::Legacy);
+        Transaction {
+            from: wallet.address(),
+            // Typed transactions sign with the y parity as v
+            v: match tx_type {
+                TxType::Legacy | TxType::PreEip155 => sig.v,
+                TxType::Eip2930 | TxType::Eip1559 => sig.v - 27,
+            },
+            r: sig.r,
+            s: sig.s,
+            ..tx
+        }
+    }
+
+    fn txs() -> Vec<Transaction> {
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let wallet = LocalWallet::new(&mut rng).with_chain_id(CHAIN_ID);
+        let tx = Transaction {
+            to: Some(address!("0x000000000000000000000000000000000000cafe")),
+            gas_limit: Word::from(500_000u64),
+            gas_price: Word::from(1234u64),
+            value: Word::from(1000u64),
+            ..Transaction::default()
+        };
+        vec![
+            // Legacy transaction with a short call data
+            Transaction {
+                nonce: Word::from(0u64),
+                call_data: Bytes::from(b"hello".to_vec()),
+                ..tx.clone()
+            },
+            // Legacy contract creation with a long call data
+            Transaction {
+                nonce: Word::from(1u64),
+                to: None,
+                call_data: Bytes::from(vec![0xfe; 60]),
+                ..tx.clone()
+            },
+            // EIP-2930 transaction with a single byte call data
+            Transaction {
+                transaction_type: TxType::Eip2930 as u64,
+                nonce: Word::from(2u64),
+                call_data: Bytes::from(vec![0x7f]),
+                ..tx.clone()
+            },
+            // EIP-1559 transaction without call data
+            Transaction {
+                transaction_type: TxType::Eip1559 as u64,
+                nonce: Word::from(0x80u64),
+                gas_price: Word::zero(),
+                gas_tip_cap: Word::from(2u64),
+                gas_fee_cap: Word::from(1_000_000_000u64),
+                ..tx.clone()
+            },
+            // Legacy transaction signed without a chain id, whose v is set to
+            // 27 to select the pre-EIP-155 encoding
+            Transaction {
+                nonce: Word::from(3u64),
+                v: 27,
+                ..tx
+            },
+        ]
+        .into_iter()
+        .map(|tx| sign_tx(&wallet, tx))
+        .collect()
+    }
+
+    fn circuit(txs: Vec<Transaction>) -> RlpCircuit<Fr> {
+        let size = RlpCircuit::<Fr>::min_num_rows(&txs, CHAIN_ID).unwrap() + 8;
+        RlpCircuit::new(Fr::from(0x100), CHAIN_ID, txs, size)
+    }
+
+    // RlpCircuit assigning the tables from a different list of transactions.
+    #[derive(Default)]
+    struct TamperedTablesCircuit {
+        rlp_circuit: RlpCircuit<Fr>,
+        table_txs: Vec<Transaction>,
+    }
+
+    impl Circuit<Fr> for TamperedTablesCircuit {
+        type Config = RlpCircuitConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            RlpCircuit::<Fr>::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let circuit = &self.rlp_circuit;
+            config.load(&mut layouter)?;
+            load_tables(
+                &config,
+                &mut layouter,
+                &self.table_txs,
+                circuit.chain_id,
+                circuit.randomness,
+            )?;
+            config.assign(
+                &mut layouter,
+                circuit.size,
+                &circuit.txs,
+                circuit.chain_id,
+                circuit.randomness,
+            )
+        }
+    }
+
+    #[test]
+    fn tx_rlp_legacy() {
+        let tx = &txs()[0];
+        let mut stream = RlpStream::new_list(9);
+        stream
+            .append(&tx.nonce)
+            .append(&tx.gas_price)
+            .append(&tx.gas_limit)
+            .append(&tx.to.unwrap())
+            .append(&tx.value)
+            .append(&tx.call_data.0)
+            .append(&CHAIN_ID)
+            .append(&0u32)
+            .append(&0u32);
+        assert_eq!(
+            tx_rlp(tx, CHAIN_ID, RlpDataType::TxSign).unwrap(),
+            stream.out().to_vec()
+        );
+
+        let mut stream = RlpStream::new_list(9);
+        stream
+            .append(&tx.nonce)
+            .append(&tx.gas_price)
+            .append(&tx.gas_limit)
+            .append(&tx.to.unwrap())
+            .append(&tx.value)
+            .append(&tx.call_data.0)
+            .append(&tx.v)
+            .append(&tx.r)
+            .append(&tx.s);
+        assert_eq!(
+            tx_rlp(tx, CHAIN_ID, RlpDataType::TxHash).unwrap(),
+            stream.out().to_vec()
+        );
+    }
+
+    #[test]
+    fn tx_rlp_pre_eip155() {
+        let tx = &txs()[4];
+        let mut stream = RlpStream::new_list(6);
+        stream
+            .append(&tx.nonce)
+            .append(&tx.gas_price)
+            .append(&tx.gas_limit)
+            .append(&tx.to.unwrap())
+            .append(&tx.value)
+            .append(&tx.call_data.0);
+        assert_eq!(
+            tx_rlp(tx, CHAIN_ID, RlpDataType::TxSign).unwrap(),
+            stream.out().to_vec()
+        );
+    }
+
+    #[test]
+    fn tx_rlp_eip1559() {
+        let tx = &txs()[3];
+        let mut stream = RlpStream::new_list(9);
+        stream
+            .append(&CHAIN_ID)
+            .append(&tx.nonce)
+            .append(&tx.gas_tip_cap)
+            .append(&tx.gas_fee_cap)
+            .append(&tx.gas_limit)
+            .append(&tx.to.unwrap())
+            .append(&tx.value)
+            .append(&tx.call_data.0)
+            .begin_list(0);
+        let payload = stream.out().to_vec();
+        assert_eq!(
+            tx_rlp(tx, CHAIN_ID, RlpDataType::TxSign).unwrap(),
+            [vec![TxType::Eip1559 as u8], payload].concat()
+        );
+    }
+
+    #[test]
+    fn tx_rlp_access_list_unsupported() {
+        let tx = Transaction {
+            access_list: Some(AccessList(vec![AccessListItem {
+                address: Address::zero(),
+                storage_keys: vec![],
+            }])),
+            ..txs()[2].clone()
+        };
+        assert!(matches!(
+            tx_rlp(&tx, CHAIN_ID, RlpDataType::TxSign),
+            Err(Error::Synthesis)
+        ));
+    }
+
+    #[test]
+    fn rlp_circuit_empty() {
+        let circuit = circuit(vec![]);
+        assert_eq!(run(&circuit, circuit.instance()), Ok(()));
+    }
+
+    #[test]
+    fn rlp_circuit_valid() {
+        let circuit = circuit(txs());
+        assert_eq!(run(&circuit, circuit.instance()), Ok(()));
+    }
+
+    #[test]
+    fn rlp_circuit_exact_size() {
+        let txs = txs();
+        let size = RlpCircuit::<Fr>::min_num_rows(&txs, CHAIN_ID).unwrap();
+        let circuit = RlpCircuit::new(Fr::from(0x100), CHAIN_ID, txs, size);
+        assert_eq!(run(&circuit, circuit.instance()), Ok(()));
+    }
+
+    #[test]
+    fn rlp_circuit_tampered_tx() {
+        let txs = txs();
+        let mut table_txs = txs.clone();
+        table_txs[0].value = Word::from(1001u64);
+        let circuit = TamperedTablesCircuit {
+            rlp_circuit: circuit(txs),
+            table_txs,
+        };
+        assert!(run(&circuit, circuit.rlp_circuit.instance()).is_err());
+    }
+
+    #[test]
+    fn rlp_circuit_size_exceeded() {
+        let txs = txs();
+        let size = RlpCircuit::<Fr>::min_num_rows(&txs, CHAIN_ID).unwrap() - 1;
+        let circuit = RlpCircuit::new(Fr::from(0x100), CHAIN_ID, txs, size);
+        let k = 11;
+        assert!(matches!(
+            MockProver::<Fr>::run(k, &circuit, circuit.instance()),
+            Err(Error::Synthesis)
+        ));
+    }
+}
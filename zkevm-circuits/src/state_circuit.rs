@@ -10,21 +10,18 @@ mod test;
 use crate::{
     evm_circuit::{
         param::N_BYTES_WORD,
-        witness::{Rw, RwMap},
+        util::RandomLinearCombination,
+        witness::{Block, MptUpdates, Rw, RwMap},
     },
-    table::RwTableTag,
+    table::{LookupTable, MptTable, RwTable, RwTableTag, TxReceiptFieldTag},
+    util::power_of_randomness_from_instance,
 };
 use constraint_builder::{ConstraintBuilder, Queries};
-use eth_types::{Address, Field};
-use gadgets::{
-    binary_number::{BinaryNumberChip, BinaryNumberConfig},
-    util::Expr,
-};
+use eth_types::{Address, Field, ToLittleEndian};
+use gadgets::binary_number::{BinaryNumberChip, BinaryNumberConfig};
 use halo2_proofs::{
-    circuit::{Layouter, SimpleFloorPlanner},
-    plonk::{
-        Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Instance, VirtualCells,
-    },
+    circuit::{Layouter, Region, SimpleFloorPlanner},
+    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, VirtualCells},
     poly::Rotation,
 };
 use lexicographic_ordering::Config as LexicographicOrderingConfig;
@@ -38,21 +35,31 @@ use std::iter::once;
 const N_LIMBS_RW_COUNTER: usize = 2;
 const N_LIMBS_ACCOUNT_ADDRESS: usize = 10;
 const N_LIMBS_ID: usize = 2;
+const N_LIMBS_GAS_USED: usize = 4;
 
 /// Config for StateCircuit
-#[derive(Clone, Copy)]
-pub struct StateConfig {
+#[derive(Clone)]
+pub struct StateConfig<F: Field> {
     selector: Column<Fixed>, // Figure out why you get errors when this is Selector.
     // https://github.com/privacy-scaling-explorations/zkevm-circuits/issues/407
     sort_keys: SortKeysConfig,
-    is_write: Column<Advice>,
-    value: Column<Advice>,
+    // The rw_counter, is_write, tag, keys, value and value_prev of every row are
+    // assigned in the RwTable, so that it can be shared with the EVM Circuit.
+    rw_table: RwTable,
     initial_value: Column<Advice>, /* Assigned value at the start of the block. For Rw::Account
                                     * and Rw::AccountStorage rows this is the committed value in
                                     * the MPT, for others, it is 0. */
+    is_last_access: Column<Advice>, /* 1 if the next row is in a different access group, 0
+                                     * otherwise. */
+    state_root: Column<Advice>, // RLC of the state root after the MPT updates up to this row.
+    cumulative_gas_used: Column<Advice>, /* Value of the last TxReceipt CumulativeGasUsed row up
+                                 * to this row, 0 outside of the TxReceipt rows. */
+    gas_used_limbs: [Column<Advice>; N_LIMBS_GAS_USED], /* u16 limbs of the increase of
+                                                         * cumulative_gas_used in this row. */
     lexicographic_ordering: LexicographicOrderingConfig,
     lookups: LookupsConfig,
-    power_of_randomness: [Column<Instance>; N_BYTES_WORD - 1],
+    mpt_table: MptTable,
+    power_of_randomness: [Expression<F>; N_BYTES_WORD - 1],
 }
 
 /// Keys for sorting the rows of the state circuit
@@ -68,73 +75,40 @@ pub struct SortKeysConfig {
 
 type Lookup<F> = (&'static str, Expression<F>, Expression<F>);
 
-/// State Circuit for proving RwTable is valid
-#[derive(Default)]
-pub struct StateCircuit<F: Field> {
-    pub(crate) randomness: F,
-    pub(crate) rows: Vec<Rw>,
-    pub(crate) n_rows: usize,
-    #[cfg(test)]
-    overrides: HashMap<(test::AdviceColumn, isize), F>,
-}
-
-impl<F: Field> StateCircuit<F> {
-    /// make a new state circuit from an RwMap
-    pub fn new(randomness: F, rw_map: RwMap, n_rows: usize) -> Self {
-        let mut rows: Vec<_> = rw_map.0.into_values().flatten().collect();
-        rows.sort_by_key(|row| {
-            (
-                row.tag() as u64,
-                row.id().unwrap_or_default(),
-                row.address().unwrap_or_default(),
-                row.field_tag().unwrap_or_default(),
-                row.storage_key().unwrap_or_default(),
-                row.rw_counter(),
-            )
-        });
-        Self {
-            randomness,
-            rows,
-            n_rows,
-            #[cfg(test)]
-            overrides: HashMap::new(),
-        }
-    }
-
-    /// powers of randomness for instance columns
-    pub fn instance(&self) -> Vec<Vec<F>> {
-        (1..32)
-            .map(|exp| vec![self.randomness.pow(&[exp, 0, 0, 0]); self.n_rows])
-            .collect()
-    }
-}
-
-impl<F: Field> Circuit<F> for StateCircuit<F> {
-    type Config = StateConfig;
-    type FloorPlanner = SimpleFloorPlanner;
-
-    fn without_witnesses(&self) -> Self {
-        Self::default()
-    }
-
-    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+impl<F: Field> StateConfig<F> {
+    /// Configure the State Circuit on top of an `RwTable` and an `MptTable`
+    /// constructed by the caller, so that they can be shared with other
+    /// circuits.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        power_of_randomness: [Expression<F>; N_BYTES_WORD - 1],
+        rw_table: &RwTable,
+        mpt_table: MptTable,
+    ) -> Self {
         let selector = meta.fixed_column();
         let lookups = LookupsChip::configure(meta);
-        let power_of_randomness = [0; N_BYTES_WORD - 1].map(|_| meta.instance_column());
 
-        let [is_write, field_tag, value, initial_value] = [0; 4].map(|_| meta.advice_column());
+        let [initial_value, is_last_access, state_root, cumulative_gas_used] =
+            [0; 4].map(|_| meta.advice_column());
+        let gas_used_limbs = [0; N_LIMBS_GAS_USED].map(|_| meta.advice_column());
 
         let tag = BinaryNumberChip::configure(meta, selector);
 
-        let id = MpiChip::configure(meta, selector, lookups.u16);
-        let address = MpiChip::configure(meta, selector, lookups.u16);
-        let storage_key = RlcChip::configure(meta, selector, lookups.u8, power_of_randomness);
-        let rw_counter = MpiChip::configure(meta, selector, lookups.u16);
+        let id = MpiChip::configure(meta, selector, rw_table.key1, lookups.u16);
+        let address = MpiChip::configure(meta, selector, rw_table.key2, lookups.u16);
+        let storage_key = RlcChip::configure(
+            meta,
+            selector,
+            rw_table.key4,
+            lookups.u8,
+            power_of_randomness.clone(),
+        );
+        let rw_counter = MpiChip::configure(meta, selector, rw_table.rw_counter, lookups.u16);
 
         let sort_keys = SortKeysConfig {
             tag,
             id,
-            field_tag,
+            field_tag: rw_table.key3,
             address,
             storage_key,
             rw_counter,
@@ -144,17 +118,21 @@ impl<F: Field> Circuit<F> for StateCircuit<F> {
             meta,
             sort_keys,
             lookups.u16,
-            power_of_randomness,
+            power_of_randomness.clone(),
         );
 
-        let config = Self::Config {
+        let config = Self {
             selector,
             sort_keys,
-            is_write,
-            value,
+            rw_table: *rw_table,
             initial_value,
+            is_last_access,
+            state_root,
+            cumulative_gas_used,
+            gas_used_limbs,
             lexicographic_ordering,
             lookups,
+            mpt_table,
             power_of_randomness,
         };
 
@@ -165,114 +143,252 @@ impl<F: Field> Circuit<F> for StateCircuit<F> {
             constraint_builder.gate(queries.selector)
         });
         for (name, expressions) in constraint_builder.lookups() {
-            meta.lookup_any(name, |_| vec![expressions]);
+            meta.lookup_any(name, |_| expressions);
         }
 
         config
     }
 
+    /// Load the fixed tables used for the range checks of the State Circuit.
+    /// The `MptTable` is loaded by its owner.
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        LookupsChip::construct(self.lookups).load(layouter)
+    }
+
+    /// Assign the rows, sorted as in [`RwMap::table_assignments`] and padded
+    /// at the front with `Rw::Start` rows up to `n_rows`, to the `RwTable` and
+    /// the State Circuit columns.  `updates` must be the updates loaded in the
+    /// `MptTable`.
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        rows: &[Rw],
+        updates: &MptUpdates,
+        n_rows: usize,
+        randomness: F,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rw table",
+            |mut region| self.assign_with_region(&mut region, rows, updates, n_rows, randomness),
+        )
+    }
+
+    fn assign_with_region(
+        &self,
+        region: &mut Region<'_, F>,
+        rows: &[Rw],
+        updates: &MptUpdates,
+        n_rows: usize,
+        randomness: F,
+    ) -> Result<(), Error> {
+        let tag_chip = BinaryNumberChip::construct(self.sort_keys.tag);
+
+        let padding_length = n_rows - rows.len();
+        let padding = (1..=padding_length).map(|rw_counter| Rw::Start { rw_counter });
+
+        let rows = padding.chain(rows.iter().cloned());
+        let prev_rows = once(None).chain(rows.clone().map(Some));
+        let next_rows = rows.clone().skip(1).map(Some).chain(once(None));
+
+        let mut initial_value = F::zero();
+        let mut cumulative_gas_used = 0;
+        let mut state_root = updates.old_root();
+        let mut updates = updates.iter();
+
+        for (offset, ((row, prev_row), next_row)) in rows.zip(prev_rows).zip(next_rows).enumerate()
+        {
+            region.assign_fixed(|| "selector", self.selector, offset, || Ok(F::one()))?;
+            self.rw_table
+                .assign(region, offset, &row.table_assignment(randomness))?;
+            self.sort_keys
+                .rw_counter
+                .assign(region, offset, row.rw_counter() as u32)?;
+            tag_chip.assign(region, offset, &row.tag())?;
+            if let Some(id) = row.id() {
+                self.sort_keys.id.assign(region, offset, id as u32)?;
+            }
+            if let Some(address) = row.address() {
+                self.sort_keys.address.assign(region, offset, address)?;
+            }
+            if let Some(storage_key) = row.storage_key() {
+                self.sort_keys
+                    .storage_key
+                    .assign(region, offset, randomness, storage_key)?;
+            }
+
+            let is_first_access = match prev_row {
+                Some(prev_row) => self
+                    .lexicographic_ordering
+                    .assign(region, offset, &row, &prev_row)?,
+                None => true,
+            };
+
+            // For Rw::Account and Rw::AccountStorage rows, the initial value is
+            // checked against the MPT update of the access group.
+            if is_first_access {
+                initial_value = row.value_prev_assignment(randomness).unwrap_or_default();
+            }
+            region.assign_advice(
+                || "initial_value",
+                self.initial_value,
+                offset,
+                || Ok(initial_value),
+            )?;
+
+            let gas_used = match row {
+                Rw::TxReceipt {
+                    field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+                    value,
+                    ..
+                } => {
+                    let gas_used = value.saturating_sub(cumulative_gas_used);
+                    cumulative_gas_used = value;
+                    gas_used
+                }
+                Rw::TxReceipt { .. } => 0,
+                _ => {
+                    cumulative_gas_used = 0;
+                    0
+                }
+            };
+            region.assign_advice(
+                || "cumulative_gas_used",
+                self.cumulative_gas_used,
+                offset,
+                || Ok(F::from(cumulative_gas_used)),
+            )?;
+            for (i, &limb) in self.gas_used_limbs.iter().enumerate() {
+                region.assign_advice(
+                    || format!("gas_used_limbs[{}]", i),
+                    limb,
+                    offset,
+                    || Ok(F::from((gas_used >> (16 * i)) & 0xffff)),
+                )?;
+            }
+
+            let is_last_access =
+                next_row.map_or(true, |next_row| !row.is_same_access_group(&next_row));
+            region.assign_advice(
+                || "is_last_access",
+                self.is_last_access,
+                offset,
+                || Ok(if is_last_access { F::one() } else { F::zero() }),
+            )?;
+
+            if is_last_access
+                && matches!(row.tag(), RwTableTag::Account | RwTableTag::AccountStorage)
+            {
+                state_root = match updates.next() {
+                    Some(update) => update.new_root(),
+                    None => {
+                        log::error!("missing mpt update for access group of {:?}", row);
+                        return Err(Error::Synthesis);
+                    }
+                };
+            }
+            region.assign_advice(
+                || "state_root",
+                self.state_root,
+                offset,
+                || {
+                    Ok(RandomLinearCombination::random_linear_combine(
+                        state_root.to_le_bytes(),
+                        randomness,
+                    ))
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// State Circuit for proving RwTable is valid
+#[derive(Default)]
+pub struct StateCircuit<F: Field> {
+    pub(crate) randomness: F,
+    pub(crate) rows: Vec<Rw>,
+    pub(crate) updates: MptUpdates,
+    pub(crate) n_rows: usize,
+    #[cfg(test)]
+    overrides: HashMap<(test::AdviceColumn, isize), F>,
+}
+
+impl<F: Field> StateCircuit<F> {
+    /// make a new state circuit from an RwMap and the MPT updates of its
+    /// account and storage rws
+    pub fn new(randomness: F, rw_map: RwMap, updates: MptUpdates, n_rows: usize) -> Self {
+        let rows = rw_map.table_assignments();
+        Self {
+            randomness,
+            rows,
+            updates,
+            n_rows,
+            #[cfg(test)]
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Return the minimum number of rows of a circuit proving the rws of
+    /// `block`, which are preceded by at least one `Rw::Start` row
+    pub fn min_num_rows_block(block: &Block<F>) -> usize {
+        block.rws.0.values().map(|rws| rws.len()).sum::<usize>() + 1
+    }
+
+    /// powers of randomness for instance columns
+    pub fn instance(&self) -> Vec<Vec<F>> {
+        (1..32)
+            .map(|exp| vec![self.randomness.pow(&[exp, 0, 0, 0]); self.n_rows])
+            .collect()
+    }
+}
+
+impl<F: Field> Circuit<F> for StateCircuit<F> {
+    type Config = StateConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let rw_table = RwTable::construct(meta);
+        let mpt_table = MptTable::construct(meta);
+        let power_of_randomness = power_of_randomness_from_instance(meta);
+        StateConfig::configure(meta, power_of_randomness, &rw_table, mpt_table)
+    }
+
     fn synthesize(
         &self,
         config: Self::Config,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
-        LookupsChip::construct(config.lookups).load(&mut layouter)?;
-
-        let tag_chip = BinaryNumberChip::construct(config.sort_keys.tag);
+        config.load(&mut layouter)?;
+        config
+            .mpt_table
+            .load(&mut layouter, &self.updates, self.randomness)?;
 
         layouter.assign_region(
             || "rw table",
             |mut region| {
-                let padding_length = self.n_rows - self.rows.len();
-                let padding = (1..=padding_length).map(|rw_counter| Rw::Start { rw_counter });
-
-                let rows = padding.chain(self.rows.iter().cloned());
-                let prev_rows = once(None).chain(rows.clone().map(Some));
-
-                let mut initial_value = F::zero();
-
-                for (offset, (row, prev_row)) in rows.zip(prev_rows).enumerate() {
-                    region.assign_fixed(|| "selector", config.selector, offset, || Ok(F::one()))?;
-                    config.sort_keys.rw_counter.assign(
-                        &mut region,
-                        offset,
-                        row.rw_counter() as u32,
-                    )?;
-                    region.assign_advice(
-                        || "is_write",
-                        config.is_write,
-                        offset,
-                        || Ok(if row.is_write() { F::one() } else { F::zero() }),
-                    )?;
-                    tag_chip.assign(&mut region, offset, &row.tag())?;
-                    if let Some(id) = row.id() {
-                        config.sort_keys.id.assign(&mut region, offset, id as u32)?;
-                    }
-                    if let Some(address) = row.address() {
-                        config
-                            .sort_keys
-                            .address
-                            .assign(&mut region, offset, address)?;
-                    }
-                    if let Some(field_tag) = row.field_tag() {
-                        region.assign_advice(
-                            || "field_tag",
-                            config.sort_keys.field_tag,
-                            offset,
-                            || Ok(F::from(field_tag as u64)),
-                        )?;
-                    }
-                    if let Some(storage_key) = row.storage_key() {
-                        config.sort_keys.storage_key.assign(
-                            &mut region,
-                            offset,
-                            self.randomness,
-                            storage_key,
-                        )?;
-                    }
-                    region.assign_advice(
-                        || "value",
-                        config.value,
-                        offset,
-                        || Ok(row.value_assignment(self.randomness)),
-                    )?;
-
-                    if let Some(prev_row) = prev_row {
-                        let is_first_access = config.lexicographic_ordering.assign(
-                            &mut region,
-                            offset,
-                            &row,
-                            &prev_row,
-                        )?;
-
-                        // TODO: Get initial_values from MPT updates instead.
-                        if is_first_access {
-                            // TODO: Set initial values for Rw::CallContext to be 0 instead of
-                            // special casing it.
-                            initial_value = if matches!(row.tag(), RwTableTag::CallContext) {
-                                row.value_assignment(self.randomness)
-                            } else {
-                                row.value_prev_assignment(self.randomness)
-                                    .unwrap_or_default()
-                            };
-                        }
-                    }
-
-                    region.assign_advice(
-                        || "initial_value",
-                        config.initial_value,
-                        offset,
-                        || Ok(initial_value),
-                    )?;
-                }
+                config.assign_with_region(
+                    &mut region,
+                    &self.rows,
+                    &self.updates,
+                    self.n_rows,
+                    self.randomness,
+                )?;
 
                 #[cfg(test)]
-                for ((column, row_offset), &f) in &self.overrides {
-                    let advice_column = column.value(&config);
-                    let offset =
-                        usize::try_from(isize::try_from(padding_length).unwrap() + *row_offset)
-                            .unwrap();
-                    region.assign_advice(|| "override", advice_column, offset, || Ok(f))?;
+                {
+                    let padding_length = self.n_rows - self.rows.len();
+                    for ((column, row_offset), &f) in &self.overrides {
+                        let advice_column = column.value(&config);
+                        let offset =
+                            usize::try_from(isize::try_from(padding_length).unwrap() + *row_offset)
+                                .unwrap();
+                        region.assign_advice(|| "override", advice_column, offset, || Ok(f))?;
+                    }
                 }
 
                 Ok(())
@@ -281,7 +397,7 @@ impl<F: Field> Circuit<F> for StateCircuit<F> {
     }
 }
 
-fn queries<F: Field>(meta: &mut VirtualCells<'_, F>, c: &StateConfig) -> Queries<F> {
+fn queries<F: Field>(meta: &mut VirtualCells<'_, F>, c: &StateConfig<F>) -> Queries<F> {
     let first_different_limb = c.lexicographic_ordering.first_different_limb;
     let final_bits_sum = meta.query_advice(first_different_limb.bits[3], Rotation::cur())
         + meta.query_advice(first_different_limb.bits[4], Rotation::cur());
@@ -291,8 +407,8 @@ fn queries<F: Field>(meta: &mut VirtualCells<'_, F>, c: &StateConfig) -> Queries
         lexicographic_ordering_selector: meta
             .query_fixed(c.lexicographic_ordering.selector, Rotation::cur()),
         rw_counter: MpiQueries::new(meta, c.sort_keys.rw_counter),
-        is_write: meta.query_advice(c.is_write, Rotation::cur()),
-        tag: c.sort_keys.tag.value(Rotation::cur())(meta),
+        is_write: meta.query_advice(c.rw_table.is_write, Rotation::cur()),
+        tag: meta.query_advice(c.rw_table.tag, Rotation::cur()),
         tag_bits: c
             .sort_keys
             .tag
@@ -311,14 +427,25 @@ fn queries<F: Field>(meta: &mut VirtualCells<'_, F>, c: &StateConfig) -> Queries
         address: MpiQueries::new(meta, c.sort_keys.address),
         field_tag: meta.query_advice(c.sort_keys.field_tag, Rotation::cur()),
         storage_key: RlcQueries::new(meta, c.sort_keys.storage_key),
-        value: meta.query_advice(c.value, Rotation::cur()),
-        value_prev: meta.query_advice(c.value, Rotation::prev()),
+        value: meta.query_advice(c.rw_table.value, Rotation::cur()),
+        value_prev: meta.query_advice(c.rw_table.value, Rotation::prev()),
+        value_prev_column: meta.query_advice(c.rw_table.value_prev, Rotation::cur()),
+        aux1: meta.query_advice(c.rw_table.aux1, Rotation::cur()),
+        aux2: meta.query_advice(c.rw_table.aux2, Rotation::cur()),
         initial_value: meta.query_advice(c.initial_value, Rotation::cur()),
         initial_value_prev: meta.query_advice(c.initial_value, Rotation::prev()),
+        is_last_access: meta.query_advice(c.is_last_access, Rotation::cur()),
+        is_last_access_prev: meta.query_advice(c.is_last_access, Rotation::prev()),
+        state_root: meta.query_advice(c.state_root, Rotation::cur()),
+        state_root_prev: meta.query_advice(c.state_root, Rotation::prev()),
+        cumulative_gas_used: meta.query_advice(c.cumulative_gas_used, Rotation::cur()),
+        cumulative_gas_used_prev: meta.query_advice(c.cumulative_gas_used, Rotation::prev()),
+        gas_used_limbs: c
+            .gas_used_limbs
+            .map(|limb| meta.query_advice(limb, Rotation::cur())),
         lookups: LookupsQueries::new(meta, c.lookups),
-        power_of_randomness: c
-            .power_of_randomness
-            .map(|c| meta.query_instance(c, Rotation::cur())),
+        mpt_table: c.mpt_table.table_exprs(meta),
+        power_of_randomness: c.power_of_randomness.clone(),
         // this isn't binary! only 0 if most significant 4 bits are all 1.
         first_access: 4.expr()
             - meta.query_advice(first_different_limb.bits[0], Rotation::cur())
@@ -331,5 +458,11 @@ fn queries<F: Field>(meta: &mut VirtualCells<'_, F>, c: &StateConfig) -> Queries
             * meta.query_advice(first_different_limb.bits[1], Rotation::cur())
             * meta.query_advice(first_different_limb.bits[2], Rotation::cur())
             * meta.query_advice(first_different_limb.bits[3], Rotation::cur()),
+        // 1 if the next row is enabled and in the same access group as this one, 0 otherwise
+        not_last_access: meta.query_fixed(c.selector, Rotation::next())
+            * meta.query_advice(first_different_limb.bits[0], Rotation::next())
+            * meta.query_advice(first_different_limb.bits[1], Rotation::next())
+            * meta.query_advice(first_different_limb.bits[2], Rotation::next())
+            * meta.query_advice(first_different_limb.bits[3], Rotation::next()),
     }
 }
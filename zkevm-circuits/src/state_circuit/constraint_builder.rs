@@ -1,12 +1,12 @@
 use super::{
     lookups::Queries as LookupsQueries, multiple_precision_integer::Queries as MpiQueries,
-    random_linear_combination::Queries as RlcQueries, N_LIMBS_ACCOUNT_ADDRESS, N_LIMBS_ID,
-    N_LIMBS_RW_COUNTER,
+    random_linear_combination::Queries as RlcQueries, N_LIMBS_ACCOUNT_ADDRESS, N_LIMBS_GAS_USED,
+    N_LIMBS_ID, N_LIMBS_RW_COUNTER,
 };
 use crate::util::Expr;
 use crate::{
     evm_circuit::{param::N_BYTES_WORD, util::not},
-    table::{AccountFieldTag, RwTableTag},
+    table::{AccountFieldTag, RwTableTag, TxReceiptFieldTag},
 };
 use eth_types::Field;
 use gadgets::binary_number::BinaryNumberConfig;
@@ -28,16 +28,28 @@ pub struct Queries<F: Field> {
     pub storage_key: RlcQueries<F, N_BYTES_WORD>,
     pub value: Expression<F>,
     pub value_prev: Expression<F>,
+    pub value_prev_column: Expression<F>,
+    pub aux1: Expression<F>,
+    pub aux2: Expression<F>,
     pub initial_value: Expression<F>,
     pub initial_value_prev: Expression<F>,
+    pub is_last_access: Expression<F>,
+    pub is_last_access_prev: Expression<F>,
+    pub state_root: Expression<F>,
+    pub state_root_prev: Expression<F>,
+    pub cumulative_gas_used: Expression<F>,
+    pub cumulative_gas_used_prev: Expression<F>,
+    pub gas_used_limbs: [Expression<F>; N_LIMBS_GAS_USED],
     pub lookups: LookupsQueries<F>,
+    pub mpt_table: Vec<Expression<F>>,
     pub power_of_randomness: [Expression<F>; N_BYTES_WORD - 1],
     pub first_access: Expression<F>,
     pub not_first_access: Expression<F>,
+    pub not_last_access: Expression<F>,
 }
 
 type Constraint<F> = (&'static str, Expression<F>);
-type Lookup<F> = (&'static str, (Expression<F>, Expression<F>));
+type Lookup<F> = (&'static str, Vec<(Expression<F>, Expression<F>)>);
 
 pub struct ConstraintBuilder<F: Field> {
     pub constraints: Vec<Constraint<F>>,
@@ -102,11 +114,23 @@ impl<F: Field> ConstraintBuilder<F> {
         self.condition(q.tag_matches(RwTableTag::TxLog), |cb| {
             cb.build_tx_log_constraints(q)
         });
+        self.condition(q.tag_matches(RwTableTag::TxReceipt), |cb| {
+            cb.build_tx_receipt_constraints(q)
+        });
     }
 
     fn build_general_constraints(&mut self, q: &Queries<F>) {
         // tag value in RwTableTag range is enforced in BinaryNumberChip
         self.require_boolean("is_write is boolean", q.is_write());
+        self.require_equal(
+            "tag column matches the tag bits",
+            q.tag(),
+            q.tag_from_bits(),
+        );
+        self.require_zero("aux1 is 0", q.aux1.clone());
+        self.condition(not::expr(q.tag_matches(RwTableTag::AccountStorage)), |cb| {
+            cb.require_zero("aux2 is 0 for all tags but AccountStorage", q.aux2.clone());
+        });
 
         // When at least one of the keys (tag, id, address, field_tag, or storage_key)
         // in the current row differs from the previous row.
@@ -128,6 +152,62 @@ impl<F: Field> ConstraintBuilder<F> {
                 q.initial_value.clone() - q.initial_value_prev(),
             );
         });
+
+        // The value_prev column of the RwTable is only used by the tags that can
+        // be reverted, where it is the value before the access.
+        self.condition(q.is_reversible(), |cb| {
+            cb.condition(q.first_access(), |cb| {
+                cb.require_equal(
+                    "value_prev column is initial_value for first access",
+                    q.value_prev_column(),
+                    q.initial_value(),
+                );
+            });
+            // 1 - is_last_access in the previous row is not_first_access, but
+            // with degree 1.
+            cb.condition(
+                q.lexicographic_ordering_selector.clone()
+                    * not::expr(q.is_last_access_prev.clone()),
+                |cb| {
+                    cb.require_equal(
+                        "value_prev column is value of the previous row for non-first access",
+                        q.value_prev_column(),
+                        q.value_prev(),
+                    );
+                },
+            );
+        });
+        self.condition(not::expr(q.is_reversible()), |cb| {
+            cb.require_zero(
+                "value_prev column is 0 for non-reversible tags",
+                q.value_prev_column(),
+            );
+        });
+
+        self.require_equal(
+            "is_last_access is 1 iff the next row is in a different access group",
+            q.is_last_access(),
+            1.expr() - q.not_last_access.clone(),
+        );
+        self.condition(q.lexicographic_ordering_selector.clone(), |cb| {
+            cb.require_zero(
+                "state root only changes in the last access of an Account or AccountStorage group",
+                (1.expr() - q.is_mpt_update()) * (q.state_root() - q.state_root_prev()),
+            );
+        });
+
+        self.condition(not::expr(q.tag_matches(RwTableTag::TxReceipt)), |cb| {
+            cb.require_zero(
+                "cumulative_gas_used is 0 for all tags but TxReceipt",
+                q.cumulative_gas_used.clone(),
+            );
+        });
+        for limb in &q.gas_used_limbs {
+            self.add_lookup(
+                "gas_used limb fits into u16",
+                vec![(limb.clone(), q.lookups.u16.clone())],
+            );
+        }
     }
 
     fn build_start_constraints(&mut self, q: &Queries<F>) {
@@ -153,7 +233,7 @@ impl<F: Field> ConstraintBuilder<F> {
         }
         self.add_lookup(
             "memory value is a byte",
-            (q.value.clone(), q.lookups.u8.clone()),
+            vec![(q.value.clone(), q.lookups.u8.clone())],
         );
         self.require_zero("initial Memory value is 0", q.initial_value());
     }
@@ -167,7 +247,7 @@ impl<F: Field> ConstraintBuilder<F> {
         );
         self.add_lookup(
             "stack address fits into 10 bits",
-            (q.address.value.clone(), q.lookups.u10.clone()),
+            vec![(q.address.value.clone(), q.lookups.u10.clone())],
         );
         self.condition(q.is_tag_and_id_unchanged.clone(), |cb| {
             cb.require_boolean(
@@ -180,12 +260,26 @@ impl<F: Field> ConstraintBuilder<F> {
     }
 
     fn build_account_storage_constraints(&mut self, q: &Queries<F>) {
-        // TODO: cold VS warm
+        // Whether a slot is cold or warm is not tracked here, but in the
+        // TxAccessListAccountStorage rows of the same tx and storage key.
         self.require_zero("field_tag is 0 for AccountStorage", q.field_tag());
+        // The tx id is part of the key, so the initial value of the access
+        // group is the value at the start of the tx.
+        self.require_equal(
+            "committed value is the initial value",
+            q.aux2.clone(),
+            q.initial_value(),
+        );
 
-        // TODO: add mpt lookup for committed value and final value in an access
-        // group.
+        // The committed value and the final value of the access group are
+        // checked against the MPT update.
+        self.condition(q.is_last_access(), |cb| {
+            cb.add_lookup("mpt update for AccountStorage", q.mpt_update_lookup())
+        });
     }
+
+    // The tx id is part of the key of the access list and refund rows, so
+    // requiring the initial value to be 0 resets them at the start of every tx.
     fn build_tx_access_list_account_constraints(&mut self, q: &Queries<F>) {
         self.require_zero("field_tag is 0 for TxAccessListAccount", q.field_tag());
         self.require_zero(
@@ -233,8 +327,11 @@ impl<F: Field> ConstraintBuilder<F> {
             set::<F, AccountFieldTag>(),
         );
 
-        // TODO: add mpt lookup for committed value and final value in an access
-        // group.
+        // The committed value and the final value of the access group are
+        // checked against the MPT update.
+        self.condition(q.is_last_access(), |cb| {
+            cb.add_lookup("mpt update for Account", q.mpt_update_lookup())
+        });
     }
 
     fn build_account_destructed_constraints(&mut self, q: &Queries<F>) {
@@ -244,7 +341,11 @@ impl<F: Field> ConstraintBuilder<F> {
             "storage_key is 0 for AccountDestructed",
             q.storage_key.encoded.clone(),
         );
-        // TODO: Missing constraints
+        self.require_boolean("AccountDestructed value is boolean", q.value());
+        self.require_zero(
+            "initial AccountDestructed value is false",
+            q.initial_value(),
+        );
     }
 
     fn build_call_context_constraints(&mut self, q: &Queries<F>) {
@@ -255,9 +356,15 @@ impl<F: Field> ConstraintBuilder<F> {
         );
         self.add_lookup(
             "field_tag in CallContextFieldTag range",
-            (q.field_tag(), q.lookups.call_context_field_tag.clone()),
+            vec![(q.field_tag(), q.lookups.call_context_field_tag.clone())],
         );
-        // TODO: explain why call context doesn't need an initial value.
+        // The fields of a call are written by BeginTx or by the step entering
+        // the call, before any of them is read.
+        self.require_zero(
+            "first access to a CallContext field is a write",
+            q.first_access() * (1.expr() - q.is_write()),
+        );
+        self.require_zero("initial CallContext value is 0", q.initial_value());
     }
 
     fn build_tx_log_constraints(&mut self, q: &Queries<F>) {
@@ -267,6 +374,13 @@ impl<F: Field> ConstraintBuilder<F> {
             1.expr(),
         );
         self.require_zero("initial TxLog value is 0", q.initial_value());
+        self.require_zero("TxLog fields are written once", q.not_first_access.clone());
+        self.condition(q.is_tag_and_id_unchanged.clone(), |cb| {
+            cb.require_boolean(
+                "log_id change is 0 or 1 within a tx",
+                q.tx_log_id() - q.tx_log_id_prev(),
+            )
+        });
 
         // Comment out the following field_tag-related constraints as it is
         // duplicated between state circuit and evm circuit. For more information, please refer to https://github.com/privacy-scaling-explorations/zkevm-specs/issues/221
@@ -339,6 +453,51 @@ impl<F: Field> ConstraintBuilder<F> {
         // });
     }
 
+    fn build_tx_receipt_constraints(&mut self, q: &Queries<F>) {
+        self.require_zero("address is 0 for TxReceipt", q.address.value.clone());
+        self.require_zero(
+            "storage_key is 0 for TxReceipt",
+            q.storage_key.encoded.clone(),
+        );
+        self.require_in_set(
+            "field_tag in TxReceiptFieldTag range",
+            q.field_tag(),
+            set::<F, TxReceiptFieldTag>(),
+        );
+        self.require_zero("initial TxReceipt value is 0", q.initial_value());
+        // EndTx of tx i reads the cumulative gas used of tx i - 1 and writes
+        // it plus the gas used by tx i. Requiring the first access to be a
+        // write means that the value read is the one written by EndTx of tx
+        // i - 1.
+        self.require_zero(
+            "first access to a TxReceipt field is a write",
+            q.first_access() * (1.expr() - q.is_write()),
+        );
+
+        // The rows are sorted by tx id, so cumulative_gas_used follows the
+        // CumulativeGasUsed rows of the txs in order, and every such row can
+        // only increase it by a 64 bit amount.
+        self.condition(q.is_cumulative_gas_used(), |cb| {
+            cb.require_equal(
+                "cumulative_gas_used is the value of a CumulativeGasUsed row",
+                q.cumulative_gas_used.clone(),
+                q.value(),
+            );
+            cb.require_equal(
+                "cumulative gas used increases by gas_used",
+                q.value() - q.cumulative_gas_used_prev.clone(),
+                from_digits(&q.gas_used_limbs, (1u64 << 16).expr()),
+            );
+        });
+        self.condition(not::expr(q.is_cumulative_gas_used()), |cb| {
+            cb.require_equal(
+                "cumulative_gas_used doesn't change in other TxReceipt rows",
+                q.cumulative_gas_used.clone(),
+                q.cumulative_gas_used_prev.clone(),
+            );
+        });
+    }
+
     fn require_zero(&mut self, name: &'static str, e: Expression<F>) {
         self.constraints.push((name, self.condition.clone() * e));
     }
@@ -360,9 +519,11 @@ impl<F: Field> ConstraintBuilder<F> {
         );
     }
 
-    fn add_lookup(&mut self, name: &'static str, lookup: (Expression<F>, Expression<F>)) {
-        let mut lookup = lookup;
-        lookup.0 = lookup.0 * self.condition.clone();
+    fn add_lookup(&mut self, name: &'static str, lookup: Vec<(Expression<F>, Expression<F>)>) {
+        let lookup = lookup
+            .into_iter()
+            .map(|(input, table)| (input * self.condition.clone(), table))
+            .collect();
         self.lookups.push((name, lookup));
     }
 
@@ -411,6 +572,10 @@ impl<F: Field> Queries<F> {
         self.value_prev.clone()
     }
 
+    fn value_prev_column(&self) -> Expression<F> {
+        self.value_prev_column.clone()
+    }
+
     fn initial_value(&self) -> Expression<F> {
         self.initial_value.clone()
     }
@@ -419,10 +584,68 @@ impl<F: Field> Queries<F> {
         self.initial_value_prev.clone()
     }
 
+    fn is_last_access(&self) -> Expression<F> {
+        self.is_last_access.clone()
+    }
+
+    fn state_root(&self) -> Expression<F> {
+        self.state_root.clone()
+    }
+
+    fn state_root_prev(&self) -> Expression<F> {
+        self.state_root_prev.clone()
+    }
+
     fn tag_matches(&self, tag: RwTableTag) -> Expression<F> {
         BinaryNumberConfig::<RwTableTag, 4>::value_equals_expr(tag, self.tag_bits.clone())
     }
 
+    fn tag_from_bits(&self) -> Expression<F> {
+        from_digits(
+            &self.tag_bits.iter().rev().cloned().collect::<Vec<_>>(),
+            2.expr(),
+        )
+    }
+
+    // 1 if the tag is one of the reversible tags, which use the value_prev
+    // column, 0 otherwise.
+    fn is_reversible(&self) -> Expression<F> {
+        RwTableTag::iter()
+            .filter(|tag| tag.is_reversible())
+            .map(|tag| self.tag_matches(tag))
+            .reduce(|acc, e| acc + e)
+            .unwrap()
+    }
+
+    // 1 if the row is the last access of an Account or AccountStorage group, 0
+    // otherwise.
+    fn is_mpt_update(&self) -> Expression<F> {
+        (self.tag_matches(RwTableTag::Account) + self.tag_matches(RwTableTag::AccountStorage))
+            * self.is_last_access()
+    }
+
+    // 1 if the field tag of a TxReceipt row is CumulativeGasUsed, 0 for the
+    // other TxReceiptFieldTag values.
+    fn is_cumulative_gas_used(&self) -> Expression<F> {
+        (self.field_tag() - TxReceiptFieldTag::PostStateOrStatus.expr())
+            * (TxReceiptFieldTag::LogLength.expr() - self.field_tag())
+    }
+
+    fn mpt_update_lookup(&self) -> Vec<(Expression<F>, Expression<F>)> {
+        vec![
+            self.address.value.clone(),
+            self.storage_key.encoded.clone(),
+            self.field_tag(),
+            self.state_root_prev(),
+            self.state_root(),
+            self.initial_value(),
+            self.value(),
+        ]
+        .into_iter()
+        .zip(self.mpt_table.clone())
+        .collect()
+    }
+
     fn first_access(&self) -> Expression<F> {
         self.first_access.clone()
     }
@@ -8,7 +8,7 @@ use eth_types::{Field, ToBigEndian};
 use gadgets::binary_number::{AsBits, BinaryNumberChip, BinaryNumberConfig};
 use halo2_proofs::{
     circuit::Region,
-    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Instance, VirtualCells},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, VirtualCells},
     poly::Rotation,
 };
 use itertools::Itertools;
@@ -111,7 +111,7 @@ impl Config {
         meta: &mut ConstraintSystem<F>,
         keys: SortKeysConfig,
         u16_range: Column<Fixed>,
-        power_of_randomness: [Column<Instance>; 31],
+        power_of_randomness: [Expression<F>; 31],
     ) -> Self {
         let selector = meta.fixed_column();
         let first_different_limb = BinaryNumberChip::configure(meta, selector);
@@ -146,13 +146,13 @@ impl Config {
                 let selector = meta.query_fixed(selector, Rotation::cur());
                 let cur = Queries::new(meta, keys, Rotation::cur());
                 let prev = Queries::new(meta, keys, Rotation::prev());
-                let powers_of_randomness =
-                    power_of_randomness.map(|i| meta.query_instance(i, Rotation::cur()));
 
                 let mut constraints = vec![];
-                for (i, rlc_expression) in
-                    LimbIndex::iter().zip(rlc_limb_differences(cur, prev, powers_of_randomness))
-                {
+                for (i, rlc_expression) in LimbIndex::iter().zip(rlc_limb_differences(
+                    cur,
+                    prev,
+                    power_of_randomness.clone(),
+                )) {
                     // E.g. if first_different_limb = 5, four limb differences before need to be 0.
                     // Using RLC, we check that (cur_1 - prev_1) + r(cur_2 - prev_2) + r^2(cur_3 -
                     // prev_3) + r^3(cur_4 - prev_4) = 0.
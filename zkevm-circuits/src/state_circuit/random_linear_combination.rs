@@ -2,7 +2,7 @@ use crate::evm_circuit::util::rlc;
 use eth_types::{Field, ToLittleEndian, U256};
 use halo2_proofs::{
     circuit::{AssignedCell, Layouter, Region},
-    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, Instance, VirtualCells},
+    plonk::{Advice, Column, ConstraintSystem, Error, Expression, Fixed, VirtualCells},
     poly::Rotation,
 };
 use std::marker::PhantomData;
@@ -73,10 +73,10 @@ impl<F: Field, const N: usize> Chip<F, N> {
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         selector: Column<Fixed>,
+        encoded: Column<Advice>,
         u8_lookup: Column<Fixed>,
-        power_of_randomness: [Column<Instance>; 31],
+        power_of_randomness: [Expression<F>; 31],
     ) -> Config<N> {
-        let encoded = meta.advice_column();
         let bytes = [0; N].map(|_| meta.advice_column());
 
         for &byte in &bytes {
@@ -91,8 +91,6 @@ impl<F: Field, const N: usize> Chip<F, N> {
             let selector = meta.query_fixed(selector, Rotation::cur());
             let encoded = meta.query_advice(encoded, Rotation::cur());
             let bytes = bytes.map(|c| meta.query_advice(c, Rotation::cur()));
-            let power_of_randomness =
-                power_of_randomness.map(|c| meta.query_instance(c, Rotation::cur()));
             vec![selector * (encoded - rlc::expr(&bytes, &power_of_randomness))]
         });
 
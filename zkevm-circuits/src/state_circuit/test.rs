@@ -1,6 +1,6 @@
 use super::{StateCircuit, StateConfig};
 use crate::{
-    evm_circuit::witness::{Rw, RwMap},
+    evm_circuit::witness::{MptUpdates, Rw, RwMap},
     table::{AccountFieldTag, CallContextFieldTag, RwTableTag, TxLogFieldTag, TxReceiptFieldTag},
 };
 use bus_mapping::operation::{
@@ -9,7 +9,7 @@ use bus_mapping::operation::{
 use eth_types::{
     address,
     evm_types::{MemoryAddress, StackAddress},
-    Address, ToAddress, Word, U256,
+    Address, Field, ToAddress, Word, U256,
 };
 use gadgets::binary_number::AsBits;
 use halo2_proofs::poly::commitment::Params;
@@ -27,6 +27,7 @@ const N_ROWS: usize = 1 << 16;
 #[derive(Hash, Eq, PartialEq)]
 pub enum AdviceColumn {
     IsWrite,
+    Tag,
     Address,
     AddressLimb0,
     AddressLimb1,
@@ -34,6 +35,8 @@ pub enum AdviceColumn {
     StorageKeyByte0,
     StorageKeyByte1,
     Value,
+    ValuePrev,
+    Aux2,
     RwCounter,
     RwCounterLimb0,
     RwCounterLimb1,
@@ -47,19 +50,26 @@ pub enum AdviceColumn {
     LimbIndexBit3,
     LimbIndexBit4, // least significant bit
     InitialValue,
+    IsLastAccess,
+    StateRoot,
+    CumulativeGasUsed,
+    GasUsedLimb0,
 }
 
 impl AdviceColumn {
-    pub fn value(&self, config: &StateConfig) -> Column<Advice> {
+    pub fn value<F: Field>(&self, config: &StateConfig<F>) -> Column<Advice> {
         match self {
-            Self::IsWrite => config.is_write,
+            Self::IsWrite => config.rw_table.is_write,
+            Self::Tag => config.rw_table.tag,
             Self::Address => config.sort_keys.address.value,
             Self::AddressLimb0 => config.sort_keys.address.limbs[0],
             Self::AddressLimb1 => config.sort_keys.address.limbs[1],
             Self::StorageKey => config.sort_keys.storage_key.encoded,
             Self::StorageKeyByte0 => config.sort_keys.storage_key.bytes[0],
             Self::StorageKeyByte1 => config.sort_keys.storage_key.bytes[1],
-            Self::Value => config.value,
+            Self::Value => config.rw_table.value,
+            Self::ValuePrev => config.rw_table.value_prev,
+            Self::Aux2 => config.rw_table.aux2,
             Self::RwCounter => config.sort_keys.rw_counter.value,
             Self::RwCounterLimb0 => config.sort_keys.rw_counter.limbs[0],
             Self::RwCounterLimb1 => config.sort_keys.rw_counter.limbs[1],
@@ -73,6 +83,10 @@ impl AdviceColumn {
             Self::LimbIndexBit3 => config.lexicographic_ordering.first_different_limb.bits[3],
             Self::LimbIndexBit4 => config.lexicographic_ordering.first_different_limb.bits[4],
             Self::InitialValue => config.initial_value,
+            Self::IsLastAccess => config.is_last_access,
+            Self::StateRoot => config.state_root,
+            Self::CumulativeGasUsed => config.cumulative_gas_used,
+            Self::GasUsedLimb0 => config.gas_used_limbs[0],
         }
     }
 }
@@ -90,7 +104,8 @@ fn test_state_circuit_ok(
     });
 
     let randomness = Fr::rand();
-    let circuit = StateCircuit::<Fr>::new(randomness, rw_map, N_ROWS);
+    let updates = MptUpdates::mock_from(&rw_map.table_assignments());
+    let circuit = StateCircuit::<Fr>::new(randomness, rw_map, updates, N_ROWS);
     let power_of_randomness = circuit.instance();
 
     let prover = MockProver::<Fr>::run(19, &circuit, power_of_randomness).unwrap();
@@ -111,7 +126,8 @@ fn verifying_key_independent_of_rw_length() {
     let degree = 17;
     let params = Params::<G1Affine>::unsafe_setup::<Bn256>(degree);
 
-    let no_rows = StateCircuit::<Fr>::new(randomness, RwMap::default(), N_ROWS);
+    let no_rows =
+        StateCircuit::<Fr>::new(randomness, RwMap::default(), MptUpdates::default(), N_ROWS);
     let one_row = StateCircuit::<Fr>::new(
         randomness,
         RwMap::from(&OperationContainer {
@@ -122,6 +138,7 @@ fn verifying_key_independent_of_rw_length() {
             )],
             ..Default::default()
         }),
+        MptUpdates::default(),
         N_ROWS,
     );
 
@@ -416,13 +433,13 @@ fn tx_log_bad() {
 
 #[test]
 fn address_limb_mismatch() {
-    let rows = vec![Rw::Account {
+    let rows = vec![Rw::TxAccessListAccount {
         rw_counter: 1,
         is_write: false,
+        tx_id: 1,
         account_address: address!("0x000000000000000000000000000000000cafe002"),
-        field_tag: AccountFieldTag::CodeHash,
-        value: U256::zero(),
-        value_prev: U256::zero(),
+        is_warm: false,
+        is_warm_prev: false,
     }];
     let overrides = HashMap::from([((AdviceColumn::Address, 0), Fr::from(10))]);
 
@@ -433,13 +450,13 @@ fn address_limb_mismatch() {
 
 #[test]
 fn address_limb_out_of_range() {
-    let rows = vec![Rw::Account {
+    let rows = vec![Rw::TxAccessListAccount {
         rw_counter: 1,
         is_write: false,
+        tx_id: 1,
         account_address: address!("0x0000000000000000000000000000000000010000"),
-        field_tag: AccountFieldTag::CodeHash,
-        value: U256::zero(),
-        value_prev: U256::zero(),
+        is_warm: false,
+        is_warm_prev: false,
     }];
     let overrides = HashMap::from([
         ((AdviceColumn::AddressLimb0, 0), Fr::from(1 << 16)),
@@ -453,15 +470,14 @@ fn address_limb_out_of_range() {
 
 #[test]
 fn storage_key_mismatch() {
-    let rows = vec![Rw::AccountStorage {
+    let rows = vec![Rw::TxAccessListAccountStorage {
         rw_counter: 1,
         is_write: false,
+        tx_id: 4,
         account_address: Address::default(),
         storage_key: U256::from(6),
-        value: U256::from(34),
-        value_prev: U256::from(34),
-        tx_id: 4,
-        committed_value: U256::from(34),
+        is_warm: false,
+        is_warm_prev: false,
     }];
     let overrides = HashMap::from([((AdviceColumn::StorageKey, 0), Fr::from(10))]);
 
@@ -472,15 +488,14 @@ fn storage_key_mismatch() {
 
 #[test]
 fn storage_key_byte_out_of_range() {
-    let rows = vec![Rw::AccountStorage {
+    let rows = vec![Rw::TxAccessListAccountStorage {
         rw_counter: 1,
         is_write: false,
+        tx_id: 4,
         account_address: Address::default(),
         storage_key: U256::from(256),
-        value: U256::from(500),
-        value_prev: U256::from(500),
-        tx_id: 4,
-        committed_value: U256::from(500),
+        is_warm: false,
+        is_warm_prev: false,
     }];
     let overrides = HashMap::from([
         ((AdviceColumn::StorageKey, 0), Fr::from(256)),
@@ -495,14 +510,23 @@ fn storage_key_byte_out_of_range() {
 
 #[test]
 fn is_write_nonbinary() {
-    let rows = vec![Rw::CallContext {
-        rw_counter: 1,
-        is_write: false,
-        call_id: 0,
-        field_tag: CallContextFieldTag::TxId,
-        value: U256::zero(),
-    }];
-    let overrides = HashMap::from([((AdviceColumn::IsWrite, 0), Fr::from(4))]);
+    let rows = vec![
+        Rw::CallContext {
+            rw_counter: 1,
+            is_write: true,
+            call_id: 0,
+            field_tag: CallContextFieldTag::TxId,
+            value: U256::zero(),
+        },
+        Rw::CallContext {
+            rw_counter: 2,
+            is_write: false,
+            call_id: 0,
+            field_tag: CallContextFieldTag::TxId,
+            value: U256::zero(),
+        },
+    ];
+    let overrides = HashMap::from([((AdviceColumn::IsWrite, 1), Fr::from(4))]);
 
     let result = verify_with_overrides(rows, overrides);
 
@@ -520,7 +544,7 @@ fn nonlexicographic_order_tag() {
     };
     let second = Rw::CallContext {
         rw_counter: 2,
-        is_write: false,
+        is_write: true,
         call_id: 1,
         field_tag: CallContextFieldTag::IsSuccess,
         value: U256::zero(),
@@ -653,14 +677,14 @@ fn nonlexicographic_order_storage_key_lower() {
 fn nonlexicographic_order_rw_counter() {
     let first = Rw::CallContext {
         rw_counter: 1,
-        is_write: false,
+        is_write: true,
         call_id: 1,
         field_tag: CallContextFieldTag::IsSuccess,
         value: U256::zero(),
     };
     let second = Rw::CallContext {
         rw_counter: 2,
-        is_write: false,
+        is_write: true,
         call_id: 1,
         field_tag: CallContextFieldTag::IsSuccess,
         value: U256::zero(),
@@ -868,6 +892,7 @@ fn invalid_tags() {
             ((AdviceColumn::TagBit1, first_row_offset), bits[1]),
             ((AdviceColumn::TagBit2, first_row_offset), bits[2]),
             ((AdviceColumn::TagBit3, first_row_offset), bits[3]),
+            ((AdviceColumn::Tag, first_row_offset), Fr::from(i as u64)),
         ]);
 
         let result = prover(vec![], overrides).verify_at_rows(0..1, 0..1);
@@ -905,7 +930,10 @@ fn bad_initial_tx_access_list_account_value() {
         is_warm_prev: false,
     }];
 
-    let overrides = HashMap::from([((AdviceColumn::InitialValue, 0), Fr::from(1))]);
+    let overrides = HashMap::from([
+        ((AdviceColumn::InitialValue, 0), Fr::from(1)),
+        ((AdviceColumn::ValuePrev, 0), Fr::from(1)),
+    ]);
 
     assert_error_matches(
         verify_with_overrides(rows, overrides),
@@ -927,6 +955,7 @@ fn bad_initial_tx_refund_value() {
         ((AdviceColumn::IsWrite, 0), Fr::from(1)),
         ((AdviceColumn::Value, 0), Fr::from(10)),
         ((AdviceColumn::InitialValue, 0), Fr::from(10)),
+        ((AdviceColumn::ValuePrev, 0), Fr::from(10)),
     ]);
 
     assert_error_matches(
@@ -956,11 +985,10 @@ fn bad_initial_tx_log_value() {
 }
 
 #[test]
-#[ignore = ""]
 fn bad_initial_tx_receipt_value() {
     let rows = vec![Rw::TxReceipt {
         rw_counter: 1,
-        is_write: false,
+        is_write: true,
         tx_id: 3421,
         field_tag: TxReceiptFieldTag::CumulativeGasUsed,
         value: 0,
@@ -969,6 +997,8 @@ fn bad_initial_tx_receipt_value() {
     let overrides = HashMap::from([
         ((AdviceColumn::Value, 0), Fr::from(1900)),
         ((AdviceColumn::InitialValue, 0), Fr::from(1900)),
+        ((AdviceColumn::CumulativeGasUsed, 0), Fr::from(1900)),
+        ((AdviceColumn::GasUsedLimb0, 0), Fr::from(1900)),
     ]);
 
     assert_error_matches(
@@ -977,11 +1007,391 @@ fn bad_initial_tx_receipt_value() {
     );
 }
 
+#[test]
+fn tx_receipt_read_before_write() {
+    let rows = vec![Rw::TxReceipt {
+        rw_counter: 1,
+        is_write: false,
+        tx_id: 1,
+        field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+        value: 0,
+    }];
+
+    assert_error_matches(verify(rows), "first access to a TxReceipt field is a write");
+}
+
+#[test]
+fn tx_receipt_ok() {
+    let rows = [(1, 21000), (2, 42000)]
+        .into_iter()
+        .flat_map(|(tx_id, cumulative_gas_used)| {
+            [
+                (TxReceiptFieldTag::PostStateOrStatus, 1),
+                (TxReceiptFieldTag::CumulativeGasUsed, cumulative_gas_used),
+                (TxReceiptFieldTag::LogLength, 0),
+            ]
+            .into_iter()
+            .map(move |(field_tag, value)| (tx_id, field_tag, value))
+        })
+        .enumerate()
+        .map(|(i, (tx_id, field_tag, value))| Rw::TxReceipt {
+            rw_counter: i + 1,
+            is_write: true,
+            tx_id,
+            field_tag,
+            value,
+        })
+        .collect();
+
+    assert_eq!(verify(rows), Ok(()));
+}
+
+#[test]
+fn tx_receipt_cumulative_gas_used_decreases() {
+    let rows = vec![
+        Rw::TxReceipt {
+            rw_counter: 1,
+            is_write: true,
+            tx_id: 1,
+            field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+            value: 42000,
+        },
+        Rw::TxReceipt {
+            rw_counter: 2,
+            is_write: true,
+            tx_id: 2,
+            field_tag: TxReceiptFieldTag::CumulativeGasUsed,
+            value: 21000,
+        },
+    ];
+
+    assert_error_matches(verify(rows), "cumulative gas used increases by gas_used");
+}
+
+#[test]
+fn tx_log_written_twice() {
+    let row = Rw::TxLog {
+        rw_counter: 1,
+        is_write: true,
+        tx_id: 1,
+        log_id: 1,
+        field_tag: TxLogFieldTag::Address,
+        index: 0,
+        value: U256::from(20),
+    };
+    let mut rewrite = row;
+    if let Rw::TxLog { rw_counter, .. } = &mut rewrite {
+        *rw_counter = 2;
+    }
+
+    assert_error_matches(verify(vec![row, rewrite]), "TxLog fields are written once");
+}
+
+#[test]
+fn tx_log_id_skipped() {
+    let rows = vec![
+        Rw::TxLog {
+            rw_counter: 1,
+            is_write: true,
+            tx_id: 1,
+            log_id: 1,
+            field_tag: TxLogFieldTag::Address,
+            index: 0,
+            value: U256::from(20),
+        },
+        Rw::TxLog {
+            rw_counter: 2,
+            is_write: true,
+            tx_id: 1,
+            log_id: 3,
+            field_tag: TxLogFieldTag::Address,
+            index: 0,
+            value: U256::from(20),
+        },
+    ];
+
+    assert_error_matches(verify(rows), "log_id change is 0 or 1 within a tx");
+}
+
+#[test]
+fn nonbinary_tx_access_list_account_value() {
+    let rows = vec![Rw::TxAccessListAccount {
+        rw_counter: 1,
+        is_write: true,
+        tx_id: 1,
+        account_address: address!("0x0000000000000000000000000000000004356002"),
+        is_warm: true,
+        is_warm_prev: false,
+    }];
+
+    let overrides = HashMap::from([((AdviceColumn::Value, 0), Fr::from(2))]);
+
+    assert_error_matches(
+        verify_with_overrides(rows, overrides),
+        "TxAccessListAccount value is boolean",
+    );
+}
+
+#[test]
+fn bad_initial_tx_access_list_account_storage_value() {
+    let rows = vec![Rw::TxAccessListAccountStorage {
+        rw_counter: 1,
+        is_write: true,
+        tx_id: 1,
+        account_address: address!("0x0000000000000000000000000000000004356002"),
+        storage_key: U256::from(7),
+        is_warm: true,
+        is_warm_prev: false,
+    }];
+
+    let overrides = HashMap::from([
+        ((AdviceColumn::InitialValue, 0), Fr::from(1)),
+        ((AdviceColumn::ValuePrev, 0), Fr::from(1)),
+    ]);
+
+    assert_error_matches(
+        verify_with_overrides(rows, overrides),
+        "initial TxAccessListAccountStorage value is false",
+    );
+}
+
+#[test]
+fn tx_refund_not_reset_between_txs() {
+    let rows = vec![
+        Rw::TxRefund {
+            rw_counter: 1,
+            is_write: true,
+            tx_id: 1,
+            value: 10,
+            value_prev: 0,
+        },
+        Rw::TxRefund {
+            rw_counter: 2,
+            is_write: false,
+            tx_id: 2,
+            value: 10,
+            value_prev: 10,
+        },
+    ];
+
+    assert_error_matches(verify(rows), "initial TxRefund value is 0");
+}
+
+#[test]
+fn bad_initial_account_destructed_value() {
+    let rows = vec![Rw::AccountDestructed {
+        rw_counter: 1,
+        is_write: true,
+        tx_id: 1,
+        account_address: address!("0x000000000000000000000000000000000cafe002"),
+        is_destructed: true,
+        is_destructed_prev: true,
+    }];
+
+    assert_error_matches(verify(rows), "initial AccountDestructed value is false");
+}
+
+#[test]
+fn bad_initial_call_context_value() {
+    let rows = vec![Rw::CallContext {
+        rw_counter: 1,
+        is_write: true,
+        call_id: 1,
+        field_tag: CallContextFieldTag::GasLeft,
+        value: U256::from(3000),
+    }];
+
+    let overrides = HashMap::from([((AdviceColumn::InitialValue, 0), Fr::from(20))]);
+
+    assert_error_matches(
+        verify_with_overrides(rows, overrides),
+        "initial CallContext value is 0",
+    );
+}
+
+#[test]
+fn call_context_read_before_write() {
+    let rows = vec![Rw::CallContext {
+        rw_counter: 1,
+        is_write: false,
+        call_id: 1,
+        field_tag: CallContextFieldTag::TxId,
+        value: U256::zero(),
+    }];
+
+    assert_error_matches(
+        verify(rows),
+        "first access to a CallContext field is a write",
+    );
+}
+
+#[test]
+fn bad_initial_account_value() {
+    let rows = vec![Rw::Account {
+        rw_counter: 1,
+        is_write: false,
+        account_address: address!("0x000000000000000000000000000000000cafe002"),
+        field_tag: AccountFieldTag::Nonce,
+        value: U256::from(5),
+        value_prev: U256::from(5),
+    }];
+    let overrides = HashMap::from([
+        ((AdviceColumn::Value, 0), Fr::from(3)),
+        ((AdviceColumn::InitialValue, 0), Fr::from(3)),
+        ((AdviceColumn::ValuePrev, 0), Fr::from(3)),
+    ]);
+
+    let result = verify_with_overrides(rows, overrides);
+
+    assert_error_matches(result, "mpt update for Account");
+}
+
+#[test]
+fn bad_final_account_storage_value() {
+    let rows = vec![
+        Rw::AccountStorage {
+            rw_counter: 1,
+            is_write: true,
+            account_address: Address::default(),
+            storage_key: U256::from(6),
+            value: U256::from(34),
+            value_prev: U256::from(12),
+            tx_id: 4,
+            committed_value: U256::from(12),
+        },
+        Rw::AccountStorage {
+            rw_counter: 2,
+            is_write: true,
+            account_address: Address::default(),
+            storage_key: U256::from(6),
+            value: U256::from(56),
+            value_prev: U256::from(34),
+            tx_id: 4,
+            committed_value: U256::from(12),
+        },
+    ];
+    let overrides = HashMap::from([((AdviceColumn::Value, 1), Fr::from(78))]);
+
+    let result = verify_with_overrides(rows, overrides);
+
+    assert_error_matches(result, "mpt update for AccountStorage");
+}
+
+#[test]
+fn value_prev_column_mismatch() {
+    let rows = vec![
+        Rw::Account {
+            rw_counter: 1,
+            is_write: true,
+            account_address: address!("0x000000000000000000000000000000000cafe002"),
+            field_tag: AccountFieldTag::Nonce,
+            value: U256::from(1),
+            value_prev: U256::from(0),
+        },
+        Rw::Account {
+            rw_counter: 2,
+            is_write: true,
+            account_address: address!("0x000000000000000000000000000000000cafe002"),
+            field_tag: AccountFieldTag::Nonce,
+            value: U256::from(2),
+            value_prev: U256::from(1),
+        },
+    ];
+    let overrides = HashMap::from([((AdviceColumn::ValuePrev, 1), Fr::from(5))]);
+
+    assert_error_matches(
+        verify_with_overrides(rows, overrides),
+        "value_prev column is value of the previous row for non-first access",
+    );
+}
+
+#[test]
+fn nonzero_value_prev_column_for_stack() {
+    let rows = vec![Rw::Stack {
+        rw_counter: 1,
+        is_write: true,
+        call_id: 1,
+        stack_pointer: 1023,
+        value: U256::from(4),
+    }];
+    let overrides = HashMap::from([((AdviceColumn::ValuePrev, 0), Fr::from(4))]);
+
+    assert_error_matches(
+        verify_with_overrides(rows, overrides),
+        "value_prev column is 0 for non-reversible tags",
+    );
+}
+
+#[test]
+fn bad_committed_value() {
+    let rows = vec![Rw::AccountStorage {
+        rw_counter: 1,
+        is_write: true,
+        account_address: Address::default(),
+        storage_key: U256::from(6),
+        value: U256::from(34),
+        value_prev: U256::from(12),
+        tx_id: 4,
+        committed_value: U256::from(12),
+    }];
+    let overrides = HashMap::from([((AdviceColumn::Aux2, 0), Fr::from(9))]);
+
+    assert_error_matches(
+        verify_with_overrides(rows, overrides),
+        "committed value is the initial value",
+    );
+}
+
+#[test]
+fn state_root_changes_outside_mpt_update() {
+    let rows = vec![Rw::TxRefund {
+        rw_counter: 1,
+        is_write: true,
+        tx_id: 1,
+        value: 10,
+        value_prev: 0,
+    }];
+    let overrides = HashMap::from([((AdviceColumn::StateRoot, 0), Fr::from(1))]);
+
+    let result = verify_with_overrides(rows, overrides);
+
+    assert_error_matches(
+        result,
+        "state root only changes in the last access of an Account or AccountStorage group",
+    );
+}
+
+#[test]
+fn skipped_mpt_update() {
+    let rows = vec![Rw::Account {
+        rw_counter: 1,
+        is_write: true,
+        account_address: address!("0x000000000000000000000000000000000cafe002"),
+        field_tag: AccountFieldTag::Balance,
+        value: U256::from(100),
+        value_prev: U256::from(0),
+    }];
+    // skip the mpt lookup and keep the state root unchanged
+    let overrides = HashMap::from([
+        ((AdviceColumn::IsLastAccess, 0), Fr::zero()),
+        ((AdviceColumn::StateRoot, 0), Fr::zero()),
+    ]);
+
+    let result = verify_with_overrides(rows, overrides);
+
+    assert_error_matches(
+        result,
+        "is_last_access is 1 iff the next row is in a different access group",
+    );
+}
+
 fn prover(rows: Vec<Rw>, overrides: HashMap<(AdviceColumn, isize), Fr>) -> MockProver<Fr> {
     let randomness = Fr::rand();
+    let updates = MptUpdates::mock_from(&rows);
     let circuit = StateCircuit::<Fr> {
         randomness,
         rows,
+        updates,
         overrides,
         n_rows: N_ROWS,
     };
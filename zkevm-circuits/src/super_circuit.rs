@@ -4,64 +4,74 @@
 //!   tables, to verify that the table layouts match.
 //! - Allow having a single circuit setup for which a proof can be generated
 //!   that would be verified under a single aggregation circuit for the first
-//!   milestone.
+//!   milestone. The aggregation circuit, see [`crate::aggregation_circuit`],
+//!   isn't used by the prover yet.
 //!
 //! The current implementation contains the following circuits:
 //!
 //! - [x] EVM Circuit
-//! - [ ] State Circuit
+//! - [x] State Circuit
 //! - [x] Tx Circuit
 //! - [x] Bytecode Circuit
-//! - [ ] Copy Circuit
-//! - [ ] Keccak Circuit
-//! - [ ] MPT Circuit
-//! - [ ] PublicInputs Circuit
+//! - [x] Copy Circuit
+//! - [x] Keccak Circuit
+//! - [x] MPT Circuit
+//! - [x] PublicInputs Circuit
+//! - [x] RLP Circuit
 //!
 //! And the following shared tables, with the circuits that use them:
 //!
 //! - [x] Copy Table
-//!   - [ ] Copy Circuit
+//!   - [x] Copy Circuit
 //!   - [x] EVM Circuit
-//! - [ ] Rw Table
-//!   - [ ] State Circuit
-//!   - [ ] EVM Circuit
-//!   - [ ] Copy Circuit
+//! - [x] Rw Table
+//!   - [x] State Circuit
+//!   - [x] EVM Circuit
+//!   - [x] Copy Circuit
 //! - [x] Tx Table
 //!   - [x] Tx Circuit
 //!   - [x] EVM Circuit
-//!   - [ ] Copy Circuit
-//!   - [ ] PublicInputs Circuit
+//!   - [x] Copy Circuit
+//!   - [x] PublicInputs Circuit
+//!   - [x] RLP Circuit
 //! - [x] Bytecode Table
 //!   - [x] Bytecode Circuit
 //!   - [x] EVM Circuit
-//!   - [ ] Copy Circuit
-//! - [ ] Block Table
-//!   - [ ] EVM Circuit
-//!   - [ ] PublicInputs Circuit
-//! - [ ] MPT Table
-//!   - [ ] MPT Circuit
-//!   - [ ] State Circuit
+//!   - [x] Copy Circuit
+//! - [x] Block Table
+//!   - [x] EVM Circuit
+//!   - [x] PublicInputs Circuit
+//!   - [x] RLP Circuit
+//! - [x] MPT Table
+//!   - [x] MPT Circuit
+//!   - [x] State Circuit
 //! - [x] Keccak Table
-//!   - [ ] Keccak Circuit
+//!   - [x] Keccak Circuit
 //!   - [ ] EVM Circuit
 //!   - [x] Bytecode Circuit
 //!   - [x] Tx Circuit
-//!   - [ ] MPT Circuit
+//!   - [x] MPT Circuit
+//!   - [x] RLP Circuit
 
-use crate::tx_circuit::{self, TxCircuit, TxCircuitConfig};
+use crate::copy_circuit::CopyCircuit;
+use crate::keccak_circuit::{KeccakCircuit, KeccakConfig};
+use crate::mpt_circuit::{MptCircuit, MptCircuitConfig};
+use crate::pi_circuit::{PiCircuit, PiCircuitConfig, PublicData};
+use crate::rlp_circuit::{self, RlpCircuit, RlpCircuitConfig};
+use crate::tx_circuit::{self, TxCircuit, TxCircuitConfig, POW_RAND_SIZE};
 
-use crate::bytecode_circuit::bytecode_unroller::{
-    unroll, Config as BytecodeConfig, UnrolledBytecode,
-};
+use crate::bytecode_circuit::{bytecode_unroller::Config as BytecodeConfig, BytecodeCircuit};
 
 use crate::evm_circuit::{table::FixedTableTag, witness::Block, EvmCircuit};
-use crate::table::{BlockTable, BytecodeTable, CopyTable, KeccakTable, RwTable, TxTable};
+use crate::state_circuit::StateConfig;
+use crate::table::{BlockTable, BytecodeTable, CopyTable, KeccakTable, MptTable, RwTable, TxTable};
 use crate::util::power_of_randomness_from_instance;
-use eth_types::Field;
+use eth_types::{geth_types::Transaction, Field};
 use halo2_proofs::{
     circuit::{Layouter, SimpleFloorPlanner},
     plonk::{Circuit, ConstraintSystem, Error},
 };
+use secp256k1::Secp256k1Affine;
 
 /// Configuration of the Super Circuit
 #[derive(Clone)]
@@ -69,25 +79,36 @@ pub struct SuperCircuitConfig<F: Field, const MAX_TXS: usize, const MAX_CALLDATA
     tx_table: TxTable,
     rw_table: RwTable,
     bytecode_table: BytecodeTable,
-    block_table: BlockTable,
-    keccak_table: KeccakTable,
-    copy_table: CopyTable,
+    mpt_table: MptTable,
     evm_circuit: EvmCircuit<F>,
+    state_circuit: StateConfig<F>,
+    copy_circuit: CopyCircuit<F>,
+    keccak_circuit: KeccakConfig<F>,
+    mpt_circuit: MptCircuitConfig<F>,
     tx_circuit: TxCircuitConfig<F>,
     bytecode_circuit: BytecodeConfig<F>,
+    pi_circuit: PiCircuitConfig<F, MAX_TXS, MAX_CALLDATA>,
+    rlp_circuit: RlpCircuitConfig<F>,
 }
 
 /// The Super Circuit contains all the zkEVM circuits
 #[derive(Default)]
 pub struct SuperCircuit<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> {
-    // EVM Circuit
+    // EVM Circuit and State Circuit, which has `block.max_rws` rows
     block: Block<F>,
     fixed_table_tags: Vec<FixedTableTag>,
     // Tx Circuit
     tx_circuit: TxCircuit<F, MAX_TXS, MAX_CALLDATA>,
-    // Bytecode Circuit
-    // bytecodes: Vec<UnrolledBytecode<F>>,
-    bytecode_size: usize,
+    /// Number of rows of the Bytecode Circuit
+    pub bytecode_size: usize,
+    /// Number of rows of the Keccak Circuit
+    pub keccak_circuit_size: usize,
+    // MPT Circuit, with the rows of the updates of `block`
+    mpt_circuit: MptCircuit<F>,
+    // PublicInputs Circuit
+    pi_circuit: PiCircuit<F, MAX_TXS, MAX_CALLDATA>,
+    /// Number of rows of the RLP Circuit
+    pub rlp_circuit_size: usize,
 }
 
 impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>
@@ -99,6 +120,141 @@ impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>
         let config = Self::configure(&mut cs);
         config.evm_circuit.get_num_rows_required(block)
     }
+
+    /// Build the Super Circuit of `block`, whose transactions are `txs` and
+    /// whose public data is `public_data`.
+    /// Return the minimum degree that fits the circuit, the circuit and its
+    /// instance columns.
+    pub fn build(
+        mut block: Block<F>,
+        txs: Vec<Transaction>,
+        aux_generator: Secp256k1Affine,
+        public_data: PublicData,
+        fixed_table_tags: Vec<FixedTableTag>,
+    ) -> Result<(u32, Self, Vec<Vec<F>>), Error> {
+        if txs.len() > MAX_TXS {
+            log::error!(
+                "super circuit supports {} txs but the block has {}",
+                MAX_TXS,
+                txs.len()
+            );
+            return Err(Error::Synthesis);
+        }
+        let log2_ceil = |n| u32::BITS - (n as u32).leading_zeros() - (n & (n - 1) == 0) as u32;
+
+        // The Tx Circuit pads the TxTable up to MAX_TXS transactions.
+        block.max_txs = MAX_TXS;
+        let num_rows_required_for_steps = Self::get_num_rows_required(&block);
+
+        let k = log2_ceil(
+            64 + fixed_table_tags
+                .iter()
+                .map(|tag| tag.build::<F>().count())
+                .sum::<usize>(),
+        );
+        // The sizes of the circuits below are rounded up to a power of two, so that
+        // blocks of similar sizes get circuits of the same shape, which share keys.
+        let bytecode_size =
+            (BytecodeCircuit::<F>::min_num_rows_block(&block) + 64).next_power_of_two();
+        let k = k.max(log2_ceil(bytecode_size));
+        let k = k.max(log2_ceil(64 + num_rows_required_for_steps));
+        let k = k.max(log2_ceil(64 + block.max_rws));
+        block.max_copy_rows =
+            (CopyCircuit::<F>::get_num_rows_required(&block) - 2).next_power_of_two();
+        let k = k.max(log2_ceil(64 + CopyCircuit::get_num_rows_required(&block)));
+        let mut mpt_circuit = MptCircuit::new(
+            &block.state_proofs,
+            block.mpt_updates.clone(),
+            block.randomness,
+            0,
+        )
+        .map_err(|err| {
+            log::error!(
+                "mpt circuit can't prove the updates of the block: {:?}",
+                err
+            );
+            Error::Synthesis
+        })?;
+        // As for the Bytecode Circuit, only the rows of the updates and some padding
+        // are enabled.
+        mpt_circuit.size = (mpt_circuit.min_num_rows() + 64).next_power_of_two();
+        let k = k.max(log2_ceil(mpt_circuit.size));
+
+        let randomness = block.randomness;
+        let chain_id = block.context.chain_id;
+        let rlp_circuit_size =
+            RlpCircuit::<F>::min_num_rows(&txs, chain_id.as_u64())?.next_power_of_two();
+        let k = k.max(log2_ceil(64 + rlp_circuit_size));
+        let tx_circuit = TxCircuit::new(aux_generator, block.randomness, chain_id.as_u64(), txs);
+        let pi_circuit = PiCircuit::new(block.randomness, public_data);
+        let mut circuit = Self {
+            block,
+            fixed_table_tags,
+            tx_circuit,
+            // Instead of using 1 << k - NUM_BLINDING_ROWS, we use a much smaller number of enabled
+            // rows for the Bytecode Circuit because otherwise it penalizes significantly the
+            // MockProver verification time.
+            bytecode_size,
+            keccak_circuit_size: 0,
+            mpt_circuit,
+            pi_circuit,
+            rlp_circuit_size,
+        };
+        circuit.keccak_circuit_size =
+            KeccakCircuit::<F>::min_num_rows(&circuit.keccak_inputs()?).next_power_of_two();
+        let k = k.max(log2_ceil(64 + circuit.keccak_circuit_size));
+        let k = k + 1;
+        log::debug!("super circuit uses k = {}", k);
+
+        let mut instance: Vec<Vec<F>> = (1..POW_RAND_SIZE + 1)
+            .map(|exp| vec![randomness.pow(&[exp as u64, 0, 0, 0]); (1 << k) - 64])
+            .collect();
+        // SignVerifyChip -> ECDSAChip -> MainGate instance column
+        instance.push(vec![]);
+        // PublicInputs Circuit instance column
+        instance.extend(circuit.pi_circuit.instance()?);
+
+        Ok((k, circuit, instance))
+    }
+
+    /// Return the inputs hashed by the Keccak Circuit, which are the inputs
+    /// looked up in the Keccak Table by the other circuits.
+    pub fn keccak_inputs(&self) -> Result<Vec<Vec<u8>>, Error> {
+        let mut keccak_inputs = keccak_inputs(&self.block, &self.tx_circuit.txs)?;
+        // Lookups from MptCircuit
+        keccak_inputs.extend(self.mpt_circuit.keccak_inputs());
+        Ok(keccak_inputs)
+    }
+
+    /// Number of rows of the MPT Circuit
+    pub fn mpt_circuit_size(&self) -> usize {
+        self.mpt_circuit.size
+    }
+
+    /// Number of rows of the Copy Circuit
+    pub fn copy_circuit_size(&self) -> usize {
+        self.block.max_copy_rows
+    }
+}
+
+/// Return the inputs hashed by the Keccak Circuit for `block`, whose
+/// transactions are `txs`, which are the inputs looked up in the Keccak Table
+/// by the Tx Circuit, the Bytecode Circuit and the RLP Circuit.
+pub fn keccak_inputs<F: Field>(
+    block: &Block<F>,
+    txs: &[Transaction],
+) -> Result<Vec<Vec<u8>>, Error> {
+    let chain_id = block.context.chain_id.as_u64();
+    let mut keccak_inputs = Vec::new();
+    // Lookups from TxCircuit
+    keccak_inputs.extend_from_slice(&tx_circuit::keccak_inputs(txs, chain_id)?);
+    // Lookups from BytecodeCircuit
+    for bytecode in block.bytecodes.values() {
+        keccak_inputs.push(bytecode.bytes.clone());
+    }
+    // Lookups from RlpCircuit
+    keccak_inputs.extend_from_slice(&rlp_circuit::keccak_inputs(txs, chain_id)?);
+    Ok(keccak_inputs)
 }
 
 impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> Circuit<F>
@@ -119,6 +275,7 @@ impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> Circuit<F>
         let keccak_table = KeccakTable::construct(meta);
         let q_copy_table = meta.fixed_column();
         let copy_table = CopyTable::construct(meta, q_copy_table);
+        let mpt_table = MptTable::construct(meta);
 
         let power_of_randomness = power_of_randomness_from_instance(meta);
         let evm_circuit = EvmCircuit::configure(
@@ -130,27 +287,69 @@ impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> Circuit<F>
             &block_table,
             &copy_table,
         );
+        let state_circuit = StateConfig::configure(
+            meta,
+            power_of_randomness[..31].to_vec().try_into().unwrap(),
+            &rw_table,
+            mpt_table,
+        );
+        let copy_circuit = CopyCircuit::configure(
+            meta,
+            &tx_table,
+            &rw_table,
+            &bytecode_table,
+            copy_table,
+            q_copy_table,
+        );
+        let keccak_circuit = KeccakConfig::configure(
+            meta,
+            power_of_randomness[..31].to_vec().try_into().unwrap(),
+            keccak_table.clone(),
+        );
+        let mpt_circuit = MptCircuitConfig::new(
+            meta,
+            power_of_randomness[..31].to_vec().try_into().unwrap(),
+            mpt_table,
+            keccak_table.clone(),
+        );
+
+        let tx_circuit = TxCircuitConfig::new(
+            meta,
+            power_of_randomness.clone(),
+            tx_table.clone(),
+            keccak_table.clone(),
+        );
+        let bytecode_circuit = BytecodeConfig::configure(
+            meta,
+            power_of_randomness[0].clone(),
+            bytecode_table.clone(),
+            keccak_table.clone(),
+        );
+        // The instance column of the PublicInputs Circuit goes after the ones of
+        // the power of randomness and the Tx Circuit.
+        let pi_circuit = PiCircuitConfig::new(meta, block_table.clone(), tx_table.clone());
+        let rlp_circuit = RlpCircuitConfig::configure(
+            meta,
+            power_of_randomness[0].clone(),
+            tx_table.clone(),
+            block_table,
+            keccak_table,
+        );
 
         Self::Config {
-            tx_table: tx_table.clone(),
+            tx_table,
             rw_table,
-            bytecode_table: bytecode_table.clone(),
-            block_table,
-            keccak_table: keccak_table.clone(),
-            copy_table,
+            bytecode_table,
+            mpt_table,
             evm_circuit,
-            tx_circuit: TxCircuitConfig::new(
-                meta,
-                power_of_randomness.clone(),
-                tx_table,
-                keccak_table.clone(),
-            ),
-            bytecode_circuit: BytecodeConfig::configure(
-                meta,
-                power_of_randomness[0].clone(),
-                bytecode_table,
-                keccak_table,
-            ),
+            state_circuit,
+            copy_circuit,
+            keccak_circuit,
+            mpt_circuit,
+            tx_circuit,
+            bytecode_circuit,
+            pi_circuit,
+            rlp_circuit,
         }
     }
 
@@ -164,49 +363,80 @@ impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize> Circuit<F>
             .evm_circuit
             .load_fixed_table(&mut layouter, self.fixed_table_tags.clone())?;
         config.evm_circuit.load_byte_table(&mut layouter)?;
-        config
-            .rw_table
-            .load(&mut layouter, &self.block.rws, self.block.randomness)?;
-        config
-            .block_table
-            .load(&mut layouter, &self.block.context, self.block.randomness)?;
-        config
-            .copy_table
-            .load(&mut layouter, &self.block, self.block.randomness)?;
         config
             .evm_circuit
             .assign_block(&mut layouter, &self.block)?;
+        // --- State Circuit ---
+        // The State Circuit assigns the RwTable, sorted in its own order.
+        let rows = self.block.rws.table_assignments();
+        let updates = &self.block.mpt_updates;
+        config.state_circuit.load(&mut layouter)?;
+        config.state_circuit.assign(
+            &mut layouter,
+            &rows,
+            updates,
+            self.block.max_rws,
+            self.block.randomness,
+        )?;
+        // --- MPT Circuit ---
+        // The MPT Circuit assigns the MptTable, which the State Circuit looks up.
+        let mpt_roots = self
+            .mpt_circuit
+            .assign(&config.mpt_circuit, &mut layouter)?;
+        // --- Copy Circuit ---
+        // The Copy Circuit assigns the CopyTable, which the EVM Circuit looks up.
+        config
+            .copy_circuit
+            .assign_block(&mut layouter, &self.block)?;
         // --- Tx Circuit ---
         self.tx_circuit.assign(&config.tx_circuit, &mut layouter)?;
         // --- Bytecode Circuit ---
-        let bytecodes: Vec<UnrolledBytecode<F>> = self
-            .block
-            .bytecodes
-            .iter()
-            .map(|(_, b)| unroll(b.bytes.clone(), self.block.randomness))
-            .collect();
+        let bytecode_circuit = BytecodeCircuit::new_from_block(&self.block, self.bytecode_size);
         config.bytecode_circuit.load(&mut layouter)?;
         config.bytecode_circuit.assign(
             &mut layouter,
-            self.bytecode_size,
-            &bytecodes,
+            bytecode_circuit.size,
+            &bytecode_circuit.bytecodes,
+            bytecode_circuit.randomness,
+        )?;
+        // --- Keccak Circuit ---
+        config.keccak_circuit.assign(
+            &mut layouter,
+            self.keccak_circuit_size,
+            &self.keccak_inputs()?,
             self.block.randomness,
         )?;
-        // --- Keccak Table ---
-        let mut keccak_inputs = Vec::new();
-        // Lookups from TxCircuit
-        keccak_inputs.extend_from_slice(&tx_circuit::keccak_inputs(
-            &self.tx_circuit.txs,
-            self.block.context.chain_id.as_u64(),
-        )?);
-        // Lookups from BytecodeCircuit
-        for bytecode in self.block.bytecodes.values() {
-            keccak_inputs.push(bytecode.bytes.clone());
-        }
-        // Load Keccak Table
-        config.keccak_table.load(
+        // --- PublicInputs Circuit ---
+        // The PublicInputs Circuit assigns the BlockTable, and checks the TxTable
+        // assigned by the Tx Circuit.
+        let state_roots = config.pi_circuit.assign(
             &mut layouter,
-            keccak_inputs.iter().map(|b| b.as_slice()),
+            &self.pi_circuit.public_data,
+            self.pi_circuit.randomness,
+        )?;
+        // The updates of the MPT Circuit go from the previous state root to the
+        // state root of the public inputs.
+        layouter.assign_region(
+            || "state roots",
+            |mut region| {
+                let [prev_state_root, state_root] = &state_roots;
+                match &mpt_roots {
+                    Some([old_root, new_root]) => {
+                        region.constrain_equal(prev_state_root.cell(), old_root.cell())?;
+                        region.constrain_equal(state_root.cell(), new_root.cell())?;
+                    }
+                    None => region.constrain_equal(prev_state_root.cell(), state_root.cell())?,
+                }
+                Ok(())
+            },
+        )?;
+        // --- RLP Circuit ---
+        config.rlp_circuit.load(&mut layouter)?;
+        config.rlp_circuit.assign(
+            &mut layouter,
+            self.rlp_circuit_size,
+            &self.tx_circuit.txs,
+            self.tx_circuit.chain_id,
             self.block.randomness,
         )?;
         Ok(())
@@ -227,14 +457,17 @@ pub mod test {
     ) {
         for tx in txs.iter_mut() {
             let wallet = wallets.get(&tx.from).unwrap();
-            let req = TransactionRequest::new()
+            let mut req = TransactionRequest::new()
                 .from(tx.from)
-                .to(tx.to.unwrap())
                 .nonce(tx.nonce)
                 .value(tx.value)
                 .data(tx.input.clone())
                 .gas(tx.gas)
                 .gas_price(tx.gas_price.unwrap());
+            // Contract creations have no callee
+            if let Some(to) = tx.to {
+                req = req.to(to);
+            }
             let tx_rlp = req.rlp(chain_id);
             let sighash = keccak256(tx_rlp.as_ref()).into();
             let sig = wallet.sign_hash(sighash, true);
@@ -249,12 +482,12 @@ pub mod test {
 mod super_circuit_tests {
     use super::test::*;
     use super::*;
-    use crate::{evm_circuit::witness::block_convert, tx_circuit::sign_verify::POW_RAND_SIZE};
+    use crate::{evm_circuit::witness::block_convert, pi_circuit::PublicData};
     use bus_mapping::mock::BlockData;
     use eth_types::{
         address, bytecode,
         geth_types::{self, GethData},
-        Word,
+        Bytecode, Bytes, Hash, ToWord, Word,
     };
     use ethers_signers::{LocalWallet, Signer};
     use group::{Curve, Group};
@@ -272,6 +505,7 @@ mod super_circuit_tests {
         block: Block<F>,
         txs: Vec<geth_types::Transaction>,
         aux_generator: Secp256k1Affine,
+        public_data: PublicData,
     }
 
     fn run_test_circuit<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>(
@@ -282,46 +516,17 @@ mod super_circuit_tests {
             block,
             txs,
             aux_generator,
+            public_data,
         } = inputs;
 
-        let log2_ceil = |n| u32::BITS - (n as u32).leading_zeros() - (n & (n - 1) == 0) as u32;
-
-        let num_rows_required_for_steps =
-            SuperCircuit::<F, MAX_TXS, MAX_CALLDATA>::get_num_rows_required(&block);
-
-        let k = log2_ceil(
-            64 + fixed_table_tags
-                .iter()
-                .map(|tag| tag.build::<F>().count())
-                .sum::<usize>(),
-        );
-        let bytecodes_len = block
-            .bytecodes
-            .iter()
-            .map(|(_, bytecode)| bytecode.bytes.len())
-            .sum::<usize>();
-        let k = k.max(log2_ceil(64 + bytecodes_len));
-        let k = k.max(log2_ceil(64 + num_rows_required_for_steps));
-        let k = k + 1;
-        log::debug!("evm circuit uses k = {}", k);
-
-        let mut instance: Vec<Vec<F>> = (1..POW_RAND_SIZE + 1)
-            .map(|exp| vec![block.randomness.pow(&[exp as u64, 0, 0, 0]); (1 << k) - 64])
-            .collect();
-        // SignVerifyChip -> ECDSAChip -> MainGate instance column
-        instance.push(vec![]);
-
-        let chain_id = block.context.chain_id;
-        let tx_circuit = TxCircuit::new(aux_generator, block.randomness, chain_id.as_u64(), txs);
-        let circuit = SuperCircuit::<F, MAX_TXS, MAX_CALLDATA> {
+        let (k, circuit, instance) = SuperCircuit::<F, MAX_TXS, MAX_CALLDATA>::build(
             block,
+            txs,
+            aux_generator,
+            public_data,
             fixed_table_tags,
-            tx_circuit,
-            // Instead of using 1 << k - NUM_BLINDING_ROWS, we use a much smaller number of enabled
-            // rows for the Bytecode Circuit because otherwise it penalizes significantly the
-            // MockProver verification time.
-            bytecode_size: bytecodes_len + 64,
-        };
+        )
+        .unwrap();
         let prover = MockProver::<F>::run(k, &circuit, instance).unwrap();
         prover.verify()
     }
@@ -335,22 +540,11 @@ mod super_circuit_tests {
         run_test_circuit::<F, MAX_TXS, MAX_CALLDATA>(inputs, FixedTableTag::iter().collect())
     }
 
-    // High memory usage test.  Run in serial with:
-    // `cargo test [...] skip_ -- --ignored --test-threads 1`
-    // NOTE: This test is not run as part of CI because it requires more memory than
-    // is available in github workers and so it gets killed before completion.
-    #[ignore]
-    #[test]
-    fn skip_test_super_circuit() {
+    fn build_inputs(bytecode: Bytecode, calldata: Bytes) -> Inputs<Fr> {
         let mut rng = ChaCha20Rng::seed_from_u64(2);
 
         let chain_id = (*MOCK_CHAIN_ID).as_u64();
 
-        let bytecode = bytecode! {
-            GAS
-            STOP
-        };
-
         let wallet_a = LocalWallet::new(&mut rng).with_chain_id(chain_id);
 
         let addr_a = wallet_a.address();
@@ -372,15 +566,16 @@ mod super_circuit_tests {
                 txs[0]
                     .from(accs[1].address)
                     .to(accs[0].address)
+                    .input(calldata)
                     .gas(Word::from(1_000_000u64));
             },
-            |block, _tx| block.number(0xcafeu64),
+            |block, _tx| block.number(0xcafeu64).hash(Hash::from_low_u64_be(0xb10c)),
         )
         .unwrap()
         .into();
 
         sign_txs(&mut block.eth_block.transactions, chain_id, &wallets);
-        let txs = block
+        let txs: Vec<geth_types::Transaction> = block
             .eth_block
             .transactions
             .iter()
@@ -393,17 +588,33 @@ mod super_circuit_tests {
         builder
             .handle_block(&block.eth_block, &block.geth_traces)
             .expect("could not handle block tx");
-        let mut block = block_convert(&builder.block, &builder.code_db);
+        let eth_block = block.eth_block;
+        let mut block = block_convert(&builder.block, &builder.code_db).unwrap();
         block.randomness = Fr::random(&mut rng);
 
         let aux_generator =
             <Secp256k1Affine as CurveAffine>::CurveExt::random(&mut rng).to_affine();
-        let inputs = Inputs {
+        let public_data = PublicData {
+            block: block.context.clone(),
+            block_hash: eth_block.hash.unwrap_or_default().to_word(),
+            // The mock block doesn't come with the state roots before and after it,
+            // which are the ones of the tries of its accounts.
+            state_root: block.mpt_updates.new_root(),
+            prev_state_root: builder.block.prev_state_root,
+            // The mock block doesn't come with the receipts root, which is
+            // computed from the execution instead.
+            receipts_root: builder.block.receipts_root().to_word(),
+            txs: txs.clone(),
+        };
+        Inputs {
             block,
             txs,
             aux_generator,
-        };
+            public_data,
+        }
+    }
 
+    fn test_super_circuit(inputs: Inputs<Fr>) {
         let res = run_test_circuit_complete_fixed_table(inputs);
         if let Err(err) = res {
             eprintln!("Verification failures:");
@@ -411,4 +622,113 @@ mod super_circuit_tests {
             panic!("Failed verification");
         }
     }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test [...] skip_ -- --ignored --test-threads 1`
+    // NOTE: This test is not run as part of CI because it requires more memory than
+    // is available in github workers and so it gets killed before completion.
+    #[ignore]
+    #[test]
+    fn skip_test_super_circuit() {
+        let bytecode = bytecode! {
+            GAS
+            STOP
+        };
+        test_super_circuit(build_inputs(bytecode, Bytes::default()));
+    }
+
+    // High memory usage test.  See `skip_test_super_circuit`.
+    #[ignore]
+    #[test]
+    fn skip_test_super_circuit_copy() {
+        // Copies the calldata and the first 32 bytes of code to memory, then logs
+        // both words, so that the Copy Circuit sees TxCalldata, Bytecode, Memory
+        // and TxLog steps.
+        let bytecode = bytecode! {
+            PUSH1(0x20) // size
+            PUSH1(0x00) // offset
+            PUSH1(0x00) // dest_offset
+            CALLDATACOPY
+            PUSH1(0x20) // size
+            PUSH1(0x00) // offset
+            PUSH1(0x20) // dest_offset
+            CODECOPY
+            PUSH32(Word::from(0xcafe)) // topic
+            PUSH1(0x40) // size
+            PUSH1(0x00) // offset
+            LOG1
+            STOP
+        };
+        let calldata = Bytes::from((1..=32).collect::<Vec<u8>>());
+        test_super_circuit(build_inputs(bytecode, calldata));
+    }
+
+    fn block_ctx_bytecode() -> Bytecode {
+        bytecode! {
+            COINBASE
+            POP
+            TIMESTAMP
+            POP
+            NUMBER
+            POP
+            DIFFICULTY
+            POP
+            GASLIMIT
+            POP
+            CHAINID
+            POP
+            BASEFEE
+            STOP
+        }
+    }
+
+    // High memory usage test.  See `skip_test_super_circuit`.
+    #[ignore]
+    #[test]
+    fn skip_test_super_circuit_block_ctx() {
+        test_super_circuit(build_inputs(block_ctx_bytecode(), Bytes::default()));
+    }
+
+    // High memory usage test.  See `skip_test_super_circuit`.
+    #[ignore]
+    #[test]
+    fn skip_test_super_circuit_invalid_block_ctx() {
+        // The Block Table is assigned from the public data, so the EVM Circuit
+        // lookups of the block context opcodes fail when it doesn't match the
+        // block the txs were executed in.  The number and chain id are looked
+        // up by the block number of the txs, so they are tampered as well.
+        let tamperings: [fn(&mut PublicData); 7] = [
+            |public_data| {
+                public_data.block.coinbase = address!("0x00000000000000000000000000000000c014ba5e")
+            },
+            |public_data| public_data.block.timestamp += Word::one(),
+            |public_data| public_data.block.number += Word::one(),
+            |public_data| public_data.block.difficulty += Word::one(),
+            |public_data| public_data.block.gas_limit += 1,
+            |public_data| public_data.block.chain_id += Word::one(),
+            |public_data| public_data.block.base_fee += Word::one(),
+        ];
+        for tamper in tamperings {
+            let mut inputs = build_inputs(block_ctx_bytecode(), Bytes::default());
+            tamper(&mut inputs.public_data);
+            assert!(run_test_circuit_complete_fixed_table(inputs).is_err());
+        }
+    }
+
+    // High memory usage test.  See `skip_test_super_circuit`.
+    #[ignore]
+    #[test]
+    fn skip_test_super_circuit_invalid_state_roots() {
+        // The MPT Circuit goes from the previous state root to the state root of
+        // the public data.
+        let tamperings: [fn(&mut PublicData); 2] = [
+            |public_data| public_data.prev_state_root += Word::one(),
+            |public_data| public_data.state_root += Word::one(),
+        ];
+        for tamper in tamperings {
+            let mut inputs = build_inputs(block_ctx_bytecode(), Bytes::default());
+            tamper(&mut inputs.public_data);
+            assert!(run_test_circuit_complete_fixed_table(inputs).is_err());
+        }
+    }
 }
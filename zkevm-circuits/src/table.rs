@@ -4,7 +4,7 @@ use crate::copy_circuit::number_or_hash_to_field;
 use crate::evm_circuit::witness::RwRow;
 use crate::evm_circuit::{
     util::{rlc, RandomLinearCombination},
-    witness::{Block, BlockContext, Bytecode, RwMap, Transaction},
+    witness::{Block, BlockContext, Bytecode, MptUpdates, Rw, RwMap, Transaction},
 };
 use crate::impl_expr;
 use bus_mapping::circuit_input_builder::{CopyDataType, CopyEvent};
@@ -78,6 +78,22 @@ pub enum TxFieldTag {
     /// TxSignHash: Hash of the transaction without the signature, used for
     /// signing.
     TxSignHash,
+    /// TxHash: Hash of the signed transaction
+    TxHash,
+    /// BlockNumber: Number of the block that includes the transaction
+    BlockNumber,
+    /// TxType: [`TxType`](crate::rlp_circuit::TxType) of the transaction
+    TxType,
+    /// GasTipCap: Max priority fee per gas (EIP-1559)
+    GasTipCap,
+    /// GasFeeCap: Max fee per gas (EIP-1559)
+    GasFeeCap,
+    /// SigV: V value of the signature
+    SigV,
+    /// SigR: R value of the signature
+    SigR,
+    /// SigS: S value of the signature
+    SigS,
     /// CallData
     CallData,
 }
@@ -110,14 +126,28 @@ impl TxTable {
         }
     }
 
-    /// Assign the `TxTable` from a list of block `Transaction`s, followig the
-    /// same layout that the Tx Circuit uses.
+    /// Assign the `TxTable` from a list of block `Transaction`s, padded with
+    /// default transactions up to `max_txs`, followig the same layout that the
+    /// Tx Circuit uses.
     pub fn load<F: Field>(
         &self,
         layouter: &mut impl Layouter<F>,
         txs: &[Transaction],
+        max_txs: usize,
         randomness: F,
     ) -> Result<(), Error> {
+        assert!(
+            txs.len() <= max_txs,
+            "txs ({}) exceed max_txs ({})",
+            txs.len(),
+            max_txs
+        );
+        let padding_txs: Vec<_> = (txs.len()..max_txs)
+            .map(|i| Transaction {
+                id: i + 1,
+                ..Default::default()
+            })
+            .collect();
         layouter.assign_region(
             || "tx table",
             |mut region| {
@@ -133,7 +163,7 @@ impl TxTable {
                 offset += 1;
 
                 let tx_table_columns = self.columns();
-                for tx in txs.iter() {
+                for tx in txs.iter().chain(padding_txs.iter()) {
                     for row in tx.table_assignments(randomness) {
                         for (column, value) in tx_table_columns.iter().zip_eq(row) {
                             region.assign_advice(
@@ -390,12 +420,13 @@ impl RwTable {
         Ok(())
     }
 
-    /// Assign the `RwTable` from a `RwMap`, followig the same
-    /// table layout that the State Circuit uses.
+    /// Assign the `RwTable` from a `RwMap`, padded at the front with
+    /// `Rw::Start` rows up to `n_rows` as the State Circuit does.
     pub fn load<F: Field>(
         &self,
         layouter: &mut impl Layouter<F>,
         rws: &RwMap,
+        n_rows: usize,
         randomness: F,
     ) -> Result<(), Error> {
         layouter.assign_region(
@@ -411,6 +442,13 @@ impl RwTable {
                     .flat_map(|rws| rws.iter())
                     .collect::<Vec<_>>();
 
+                let padding_length = n_rows - rows.len();
+                for rw_counter in 1..=padding_length {
+                    let row = Rw::Start { rw_counter };
+                    self.assign(&mut region, offset, &row.table_assignment(randomness))?;
+                    offset += 1;
+                }
+
                 rows.sort_by_key(|a| a.rw_counter());
                 let mut expected_rw_counter = 1;
                 for rw in rows {
@@ -426,6 +464,94 @@ impl RwTable {
     }
 }
 
+/// The MptTable shared between MPT Circuit and State Circuit, which contains
+/// the updates of account fields and storage slots and the state root
+/// transitions they cause.
+#[derive(Clone, Copy, Debug)]
+pub struct MptTable {
+    /// Account address
+    pub address: Column<Advice>,
+    /// Storage key (RLC), 0 for account fields
+    pub storage_key: Column<Advice>,
+    /// Field tag (AccountFieldTag), 0 for storage slots
+    pub field_tag: Column<Advice>,
+    /// State root (RLC) before the update
+    pub old_root: Column<Advice>,
+    /// State root (RLC) after the update
+    pub new_root: Column<Advice>,
+    /// Value before the update
+    pub old_value: Column<Advice>,
+    /// Value after the update
+    pub new_value: Column<Advice>,
+}
+
+impl DynamicTableColumns for MptTable {
+    fn columns(&self) -> Vec<Column<Advice>> {
+        vec![
+            self.address,
+            self.storage_key,
+            self.field_tag,
+            self.old_root,
+            self.new_root,
+            self.old_value,
+            self.new_value,
+        ]
+    }
+}
+
+impl MptTable {
+    /// Construct a new MptTable
+    pub fn construct<F: Field>(meta: &mut ConstraintSystem<F>) -> Self {
+        Self {
+            address: meta.advice_column(),
+            storage_key: meta.advice_column(),
+            field_tag: meta.advice_column(),
+            old_root: meta.advice_column(),
+            new_root: meta.advice_column(),
+            old_value: meta.advice_column(),
+            new_value: meta.advice_column(),
+        }
+    }
+
+    /// Assign the `MptTable` from the `MptUpdates` of a block.
+    pub fn load<F: Field>(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        updates: &MptUpdates,
+        randomness: F,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "mpt table",
+            |mut region| {
+                let mut offset = 0;
+                for column in self.columns() {
+                    region.assign_advice(
+                        || "mpt table all-zero row",
+                        column,
+                        offset,
+                        || Ok(F::zero()),
+                    )?;
+                }
+                offset += 1;
+
+                let mpt_table_columns = self.columns();
+                for row in updates.table_assignments(randomness) {
+                    for (column, value) in mpt_table_columns.iter().zip_eq(row) {
+                        region.assign_advice(
+                            || format!("mpt table row {}", offset),
+                            *column,
+                            offset,
+                            || Ok(value),
+                        )?;
+                    }
+                    offset += 1;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
 /// Tag to identify the field in a Bytecode Table row
 #[derive(Clone, Copy, Debug)]
 pub enum BytecodeFieldTag {
@@ -548,7 +674,8 @@ impl_expr!(BlockContextFieldTag);
 pub struct BlockTable {
     /// Tag
     pub tag: Column<Advice>,
-    /// Index
+    /// Index: the block number for the header fields, or the number of the
+    /// hashed block for `BlockHash`
     pub index: Column<Advice>,
     /// Value
     pub value: Column<Advice>,
@@ -564,11 +691,11 @@ impl BlockTable {
         }
     }
 
-    /// Assign the `BlockTable` from a `BlockContext`.
-    pub fn load<F: Field>(
+    /// Assign the `BlockTable` from a list of `BlockContext`s.
+    pub fn load<'a, F: Field>(
         &self,
         layouter: &mut impl Layouter<F>,
-        block: &BlockContext,
+        blocks: impl IntoIterator<Item = &'a BlockContext> + Clone,
         randomness: F,
     ) -> Result<(), Error> {
         layouter.assign_region(
@@ -586,7 +713,11 @@ impl BlockTable {
                 offset += 1;
 
                 let block_table_columns = self.columns();
-                for row in block.table_assignments(randomness) {
+                for row in blocks
+                    .clone()
+                    .into_iter()
+                    .flat_map(|block| block.table_assignments(randomness))
+                {
                     for (column, value) in block_table_columns.iter().zip_eq(row) {
                         region.assign_advice(
                             || format!("block table row {}", offset),
@@ -653,8 +784,9 @@ impl KeccakTable {
     // `RLC(reversed(input))` for convenience of the circuits that do the lookups.
     // This allows calculating the `input_rlc` after all the inputs bytes have been
     // layed out via the pattern `acc[i] = acc[i-1] * r + value[i]`.
-    /// Assign the `KeccakTable` from a list hashing inputs, followig the same
-    /// table layout that the Keccak Circuit uses.
+    /// Assign the `KeccakTable` from a list hashing inputs, with one row per
+    /// input.  The Keccak Circuit assigns the same rows, spread over its
+    /// layout.
     pub fn load<'a, F: Field>(
         &self,
         layouter: &mut impl Layouter<F>,
@@ -732,8 +864,8 @@ pub struct CopyTable {
     /// Decrementing counter denoting reverse read-write counter.
     pub rwc_inc_left: Column<Advice>,
     /// Binary chip to constrain the copy table conditionally depending on the
-    /// current row's tag, whether it is Bytecode, Memory, TxCalldata or
-    /// TxLog.
+    /// current row's tag, whether it is Padding, Bytecode, Memory, TxCalldata
+    /// or TxLog.
     pub tag: BinaryNumberConfig<CopyDataType, 3>,
 }
 
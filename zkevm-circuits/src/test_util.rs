@@ -71,7 +71,8 @@ pub fn run_test_circuits<const NACC: usize, const NTX: usize>(
         .unwrap();
 
     // build a witness block from trace result
-    let block = crate::evm_circuit::witness::block_convert(&builder.block, &builder.code_db);
+    let block =
+        crate::evm_circuit::witness::block_convert(&builder.block, &builder.code_db).unwrap();
 
     // finish required tests according to config using this witness block
     test_circuits_using_witness_block(block, config.unwrap_or_default())
@@ -91,7 +92,8 @@ pub fn test_circuits_using_witness_block(
     // state circuit and evm circuit must be same
     if config.enable_state_circuit_test {
         const N_ROWS: usize = 1 << 16;
-        let state_circuit = StateCircuit::<Fr>::new(block.randomness, block.rws, N_ROWS);
+        let state_circuit =
+            StateCircuit::<Fr>::new(block.randomness, block.rws, block.mpt_updates, N_ROWS);
         let power_of_randomness = state_circuit.instance();
         let prover = MockProver::<Fr>::run(18, &state_circuit, power_of_randomness).unwrap();
         prover.verify_at_rows(
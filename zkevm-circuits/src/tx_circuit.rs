@@ -6,6 +6,7 @@
 
 pub mod sign_verify;
 
+use crate::rlp_circuit::{tx_rlp, RlpDataType, TxType};
 use crate::table::{KeccakTable, TxFieldTag, TxTable};
 use crate::util::{power_of_randomness_from_instance, random_linear_combine_word as rlc};
 use eth_types::{
@@ -24,7 +25,6 @@ use log::error;
 use num::Integer;
 use num_bigint::BigUint;
 // use rand_core::RngCore;
-use rlp::RlpStream;
 use secp256k1::Secp256k1Affine;
 use sha3::{Digest, Keccak256};
 use sign_verify::{pk_bytes_swap_endianness, SignData, SignVerifyChip, SignVerifyConfig};
@@ -42,6 +42,9 @@ lazy_static! {
         0xfffffffe, 0xffffffff,
         0xffffffff, 0xffffffff,
     ]);
+    // Half of the curve scalar, which bounds the `s` value of the signatures
+    // accepted since EIP-2
+    static ref SECP256K1_Q_HALF: BigUint = &*SECP256K1_Q >> 1usize;
 }
 
 fn recover_pk(v: u8, r: &Word, s: &Word, msg_hash: &[u8; 32]) -> Result<Secp256k1Affine, Error> {
@@ -96,11 +99,101 @@ pub fn keccak_inputs(txs: &[Transaction], chain_id: u64) -> Result<Vec<Vec<u8>>,
     // Keccak inputs from SignVerify Chip
     let sign_verify_inputs = sign_verify::keccak_inputs(&sign_datas);
     inputs.extend_from_slice(&sign_verify_inputs);
-    // NOTE: We don't verify the Tx Hash in the circuit yet, so we don't have more
-    // hash inputs.
+    // NOTE: The hashes of the tx encodings are looked up by the RLP Circuit, see
+    // `rlp_circuit::keccak_inputs`.
     Ok(inputs)
 }
 
+/// Number of fields of a transaction in the Tx Table, excluding the call data.
+pub const TX_LEN: usize = 18;
+
+/// Return the values of the Tx Table rows of a transaction, excluding the call
+/// data, in the order in which the Tx Circuit assigns them.
+pub(crate) fn tx_field_values<F: Field>(
+    tx: &Transaction,
+    sign_hash_rlc: F,
+    tx_hash_rlc: F,
+    randomness: F,
+) -> [(TxFieldTag, F); TX_LEN] {
+    [
+        (TxFieldTag::Nonce, rlc(tx.nonce.to_le_bytes(), randomness)),
+        (TxFieldTag::Gas, F::from(tx.gas_limit.as_u64())),
+        (
+            TxFieldTag::GasPrice,
+            rlc(tx.gas_price.to_le_bytes(), randomness),
+        ),
+        (
+            TxFieldTag::CallerAddress,
+            tx.from.to_scalar().expect("tx.from too big"),
+        ),
+        (
+            TxFieldTag::CalleeAddress,
+            tx.to
+                .unwrap_or_else(Address::zero)
+                .to_scalar()
+                .expect("tx.to too big"),
+        ),
+        (TxFieldTag::IsCreate, F::from(tx.to.is_none() as u64)),
+        (TxFieldTag::Value, rlc(tx.value.to_le_bytes(), randomness)),
+        (
+            TxFieldTag::CallDataLength,
+            F::from(tx.call_data.0.len() as u64),
+        ),
+        (
+            TxFieldTag::CallDataGasCost,
+            F::from(
+                tx.call_data
+                    .0
+                    .iter()
+                    .fold(0, |acc, byte| acc + if *byte == 0 { 4 } else { 16 }),
+            ),
+        ),
+        (TxFieldTag::TxSignHash, sign_hash_rlc),
+        (TxFieldTag::TxHash, tx_hash_rlc),
+        (TxFieldTag::BlockNumber, F::from(tx.block_number)),
+        (
+            TxFieldTag::TxType,
+            F::from(TxType::from_tx(tx).expect("unsupported tx type") as u64),
+        ),
+        (
+            TxFieldTag::GasTipCap,
+            rlc(tx.gas_tip_cap.to_le_bytes(), randomness),
+        ),
+        (
+            TxFieldTag::GasFeeCap,
+            rlc(tx.gas_fee_cap.to_le_bytes(), randomness),
+        ),
+        (TxFieldTag::SigV, F::from(tx.v)),
+        (TxFieldTag::SigR, rlc(tx.r.to_le_bytes(), randomness)),
+        (TxFieldTag::SigS, rlc(tx.s.to_le_bytes(), randomness)),
+    ]
+}
+
+/// Return the RLC of the message hash signed by a transaction, which is the
+/// value of its `TxSignHash` row in the Tx Table.
+pub(crate) fn tx_sign_hash_rlc<F: Field>(
+    tx: &Transaction,
+    chain_id: u64,
+    randomness: F,
+) -> Result<F, Error> {
+    let sign_data = tx_to_sign_data(tx, chain_id)?;
+    Ok(rlc(sign_data.msg_hash.to_repr(), randomness))
+}
+
+/// Return the RLC of the hash of a signed transaction, which is the value of
+/// its `TxHash` row in the Tx Table.
+pub(crate) fn tx_hash_rlc<F: Field>(
+    tx: &Transaction,
+    chain_id: u64,
+    randomness: F,
+) -> Result<F, Error> {
+    let tx_hash = Keccak256::digest(&tx_rlp(tx, chain_id, RlpDataType::TxHash)?);
+    Ok(rlc(
+        Word::from_big_endian(tx_hash.as_slice()).to_le_bytes(),
+        randomness,
+    ))
+}
+
 fn tx_to_sign_data(tx: &Transaction, chain_id: u64) -> Result<SignData, Error> {
     let sig_r_le = tx.r.to_le_bytes();
     let sig_s_le = tx.s.to_le_bytes();
@@ -114,25 +207,35 @@ fn tx_to_sign_data(tx: &Transaction, chain_id: u64) -> Result<SignData, Error> {
             error!("Invalid 's' signature value");
             e
         })?;
-    // msg = rlp([nonce, gasPrice, gas, to, value, data, sig_v, r, s])
-    let mut stream = RlpStream::new_list(9);
-    stream
-        .append(&tx.nonce)
-        .append(&tx.gas_price)
-        .append(&tx.gas_limit)
-        .append(&tx.to.unwrap_or_else(Address::zero))
-        .append(&tx.value)
-        .append(&tx.call_data.0)
-        .append(&chain_id)
-        .append(&0u32)
-        .append(&0u32);
-    let msg = stream.out();
+    // EIP-2: signatures with s > q/2 are invalid
+    if BigUint::from_bytes_le(&sig_s_le) > *SECP256K1_Q_HALF {
+        error!("Invalid 's' signature value greater than q/2");
+        return Err(Error::Synthesis);
+    }
+    // msg = rlp([nonce, gasPrice, gas, to, value, data, chain_id, 0, 0]) for legacy
+    // txs, rlp([nonce, gasPrice, gas, to, value, data]) for pre-EIP-155 legacy txs
+    // and tx_type || rlp([chain_id, nonce, ...]) for typed txs
+    let msg = tx_rlp(tx, chain_id, RlpDataType::TxSign)?;
     let msg_hash: [u8; 32] = Keccak256::digest(&msg)
         .as_slice()
         .to_vec()
         .try_into()
         .expect("hash length isn't 32 bytes");
-    let v = (tx.v - 35 - chain_id * 2) as u8;
+    // v = 35 + chain_id * 2 + recovery_id for legacy txs (EIP-155), 27 +
+    // recovery_id for pre-EIP-155 legacy txs, and the recovery id for typed txs
+    let v = match TxType::from_tx(tx)? {
+        TxType::Legacy => tx.v.checked_sub(35 + chain_id * 2),
+        TxType::PreEip155 => Some(tx.v - 27),
+        TxType::Eip2930 | TxType::Eip1559 => Some(tx.v),
+    }
+    .filter(|v| *v <= 1)
+    .ok_or_else(|| {
+        error!(
+            "Invalid 'v' signature value {} for chain id {}",
+            tx.v, chain_id
+        );
+        Error::Synthesis
+    })? as u8;
     let pk = recover_pk(v, &tx.r, &tx.s, &msg_hash)?;
     // msg_hash = msg_hash % q
     let msg_hash = BigUint::from_bytes_be(msg_hash.as_slice());
@@ -282,50 +385,17 @@ impl<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>
                     let address_cell = assigned_sig_verif.address.cell();
                     let msg_hash_rlc_cell = assigned_sig_verif.msg_hash_rlc.cell();
                     let msg_hash_rlc_value = assigned_sig_verif.msg_hash_rlc.value();
-                    for (tag, value) in &[
-                        (
-                            TxFieldTag::Nonce,
-                            rlc(tx.nonce.to_le_bytes(), self.randomness),
-                        ),
-                        (TxFieldTag::Gas, F::from(tx.gas_limit.as_u64())),
-                        (
-                            TxFieldTag::GasPrice,
-                            rlc(tx.gas_price.to_le_bytes(), self.randomness),
-                        ),
-                        (
-                            TxFieldTag::CallerAddress,
-                            tx.from.to_scalar().expect("tx.from too big"),
-                        ),
-                        (
-                            TxFieldTag::CalleeAddress,
-                            tx.to
-                                .unwrap_or_else(Address::zero)
-                                .to_scalar()
-                                .expect("tx.to too big"),
-                        ),
-                        (TxFieldTag::IsCreate, F::from(tx.to.is_none() as u64)),
-                        (
-                            TxFieldTag::Value,
-                            rlc(tx.value.to_le_bytes(), self.randomness),
-                        ),
-                        (
-                            TxFieldTag::CallDataLength,
-                            F::from(tx.call_data.0.len() as u64),
-                        ),
-                        (
-                            TxFieldTag::CallDataGasCost,
-                            F::from(
-                                tx.call_data
-                                    .0
-                                    .iter()
-                                    .fold(0, |acc, byte| acc + if *byte == 0 { 4 } else { 16 }),
-                            ),
-                        ),
-                        (
-                            TxFieldTag::TxSignHash,
-                            *msg_hash_rlc_value.unwrap_or(&F::zero()),
-                        ),
-                    ] {
+                    let tx_hash_rlc = if i < self.txs.len() {
+                        tx_hash_rlc(tx, self.chain_id, self.randomness)?
+                    } else {
+                        F::zero()
+                    };
+                    for (tag, value) in &tx_field_values(
+                        tx,
+                        *msg_hash_rlc_value.unwrap_or(&F::zero()),
+                        tx_hash_rlc,
+                        self.randomness,
+                    ) {
                         let assigned_cell =
                             config.assign_row(&mut region, offset, i + 1, *tag, 0, *value)?;
                         offset += 1;
@@ -436,6 +506,7 @@ mod tx_circuit_tests {
     use pretty_assertions::assert_eq;
     use rand::{CryptoRng, Rng, SeedableRng};
     use rand_chacha::ChaCha20Rng;
+    use sign_verify::pk_bytes_le;
 
     fn run<F: Field, const MAX_TXS: usize, const MAX_CALLDATA: usize>(
         k: u32,
@@ -506,6 +577,110 @@ mod tx_circuit_tests {
         }
     }
 
+    // Sign `tx` following EIP-155, unless its v is set to 27 or 28 to sign it
+    // without a chain id.
+    fn sign_tx(wallet: &LocalWallet, tx: Transaction, chain_id: u64) -> Transaction {
+        let sighash = keccak256(tx_rlp(&tx, chain_id, RlpDataType::TxSign).unwrap());
+        let eip155 = TxType::from_tx(&tx).unwrap() == TxType::Legacy;
+        let sig = wallet.sign_hash(sighash.into(), eip155);
+        Transaction {
+            from: wallet.address(),
+            v: sig.v,
+            r: sig.r,
+            s: sig.s,
+            ..tx
+        }
+    }
+
+    // Return a contract creation tx and a pre-EIP-155 tx
+    fn create_and_pre_eip155_txs(chain_id: u64) -> (LocalWallet, Vec<Transaction>) {
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let wallet = LocalWallet::new(&mut rng).with_chain_id(chain_id);
+        let tx = Transaction {
+            to: Some(address!("0x701653d7ae8ddaa5c8cee1ee056849f271827926")),
+            nonce: word!("0x3"),
+            gas_limit: word!("0x7a120"),
+            value: word!("0x3e8"),
+            gas_price: word!("0x4d2"),
+            call_data: Bytes::from(b"hello"),
+            ..Transaction::default()
+        };
+        let txs = vec![
+            Transaction {
+                to: None,
+                ..tx.clone()
+            },
+            Transaction {
+                nonce: word!("0x4"),
+                v: 27,
+                ..tx
+            },
+        ];
+        let txs = txs
+            .into_iter()
+            .map(|tx| sign_tx(&wallet, tx, chain_id))
+            .collect();
+        (wallet, txs)
+    }
+
+    // Address of the public key recovered from the signature of `tx`
+    fn recover_address(tx: &Transaction, chain_id: u64) -> Result<Address, Error> {
+        let sign_data = tx_to_sign_data(tx, chain_id)?;
+        let pk_be = pk_bytes_swap_endianness(&pk_bytes_le(&sign_data.pk));
+        Ok(Address::from_slice(&keccak256(pk_be)[12..]))
+    }
+
+    #[test]
+    fn tx_sign_data_create() {
+        let chain_id: u64 = 1337;
+        let (wallet, txs) = create_and_pre_eip155_txs(chain_id);
+        let tx = &txs[0];
+        assert_eq!(tx.to, None);
+        assert_eq!(recover_address(tx, chain_id).unwrap(), wallet.address());
+    }
+
+    #[test]
+    fn tx_sign_data_pre_eip155() {
+        let chain_id: u64 = 1337;
+        let (wallet, txs) = create_and_pre_eip155_txs(chain_id);
+        let tx = &txs[1];
+        assert!(tx.v == 27 || tx.v == 28);
+        assert_eq!(recover_address(tx, chain_id).unwrap(), wallet.address());
+        // The signature doesn't depend on the chain id
+        assert_eq!(recover_address(tx, 1).unwrap(), wallet.address());
+    }
+
+    #[test]
+    fn tx_sign_data_invalid_v() {
+        let chain_id: u64 = 1337;
+        let tx = rand_tx(ChaCha20Rng::seed_from_u64(2), chain_id);
+        assert!(recover_address(&tx, chain_id).is_ok());
+        // The v of a legacy tx must encode the chain id (EIP-155)
+        assert!(matches!(
+            recover_address(&tx, chain_id + 1),
+            Err(Error::Synthesis)
+        ));
+    }
+
+    #[test]
+    fn tx_sign_data_high_s() {
+        let chain_id: u64 = 1337;
+        let tx = rand_tx(ChaCha20Rng::seed_from_u64(2), chain_id);
+        // (r, q - s) with the opposite recovery id is also a valid signature of
+        // the tx, but it's rejected since EIP-2.
+        let q = Word::from_little_endian(&SECP256K1_Q.to_bytes_le());
+        let recovery_id = tx.v - 35 - chain_id * 2;
+        let tx = Transaction {
+            v: 35 + chain_id * 2 + (1 - recovery_id),
+            s: q - tx.s,
+            ..tx
+        };
+        assert!(matches!(
+            tx_to_sign_data(&tx, chain_id),
+            Err(Error::Synthesis)
+        ));
+    }
+
     // High memory usage test.  Run in serial with:
     // `cargo test [...] serial_ -- --ignored --test-threads 1`
     #[ignore]
@@ -538,6 +713,7 @@ mod tx_circuit_tests {
         // Transaction generated with `rand_tx` using `rng =
         // ChaCha20Rng::seed_from_u64(42)`
         let tx = Transaction {
+            transaction_type: 0,
             from: address!("0x5f9b7e36af4ff81688f712fb738bbbc1b7348aae"),
             to: Some(address!("0x701653d7ae8ddaa5c8cee1ee056849f271827926")),
             nonce: word!("0x3"),
@@ -551,6 +727,7 @@ mod tx_circuit_tests {
             v: 2710,
             r: word!("0xaf180d27f90b2b20808bc7670ce0aca862bc2b5fa39c195ab7b1a96225ee14d7"),
             s: word!("0x61159fa4664b698ea7d518526c96cd94cf4d8adf418000754be106a3a133f866"),
+            block_number: 0,
         };
 
         let k = 19;
@@ -570,6 +747,7 @@ mod tx_circuit_tests {
 
         let chain_id: u64 = 1337;
         let tx = Transaction {
+            transaction_type: 0,
             // This address doesn't correspond to the account that signed this tx.
             from: address!("0x1230000000000000000000000000000000000456"),
             to: Some(address!("0x701653d7ae8ddaa5c8cee1ee056849f271827926")),
@@ -584,9 +762,25 @@ mod tx_circuit_tests {
             v: 2710,
             r: word!("0xaf180d27f90b2b20808bc7670ce0aca862bc2b5fa39c195ab7b1a96225ee14d7"),
             s: word!("0x61159fa4664b698ea7d518526c96cd94cf4d8adf418000754be106a3a133f866"),
+            block_number: 0,
         };
 
         let k = 19;
         assert!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, vec![tx], chain_id).is_err(),);
     }
+
+    // High memory usage test.  Run in serial with:
+    // `cargo test [...] serial_ -- --ignored --test-threads 1`
+    #[ignore]
+    #[test]
+    fn serial_test_tx_circuit_create_and_pre_eip155() {
+        const MAX_TXS: usize = 2;
+        const MAX_CALLDATA: usize = 32;
+
+        let chain_id: u64 = 1337;
+        let (_, txs) = create_and_pre_eip155_txs(chain_id);
+
+        let k = 19;
+        assert_eq!(run::<Fr, MAX_TXS, MAX_CALLDATA>(k, txs, chain_id), Ok(()));
+    }
 }